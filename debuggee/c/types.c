@@ -0,0 +1,50 @@
+#include <stddef.h>
+#include <string.h>
+
+struct Point
+{
+    int x;
+    int y;
+};
+
+enum Color { RED, GREEN = 5, BLUE };
+
+union Number
+{
+    int i;
+    float f;
+};
+
+struct Node
+{
+    int value;
+    struct Node *next;
+};
+
+struct Packet
+{
+    unsigned short len;
+    unsigned char flags : 3;
+    unsigned char kind : 5;
+    char payload[16];
+};
+
+typedef struct Point Vec2;
+
+void c_vars(void)
+{
+    struct Point point = {1, 2};
+    Vec2 vec2 = {3, 4};
+    enum Color color = GREEN;
+    union Number number;
+    struct Node tail = {2, NULL};
+    struct Node head = {1, &tail};
+    struct Packet packet = {5, 1, 2, "hello"};
+    int matrix[2][3] = {{1, 2, 3}, {4, 5, 6}};
+    const char *cstr = "The quick brown fox";
+    char buffer[8];
+    number.f = 1.5f;
+    strcpy(buffer, "ABC");
+    int zzz = point.x + vec2.y + color + head.next->value + packet.len + matrix[1][2]; // #BP5
+    (void)zzz;
+}
@@ -30,6 +30,7 @@
 
 extern "C" void vars();
 extern "C" void vars_update();
+extern "C" void c_vars();
 extern "C" void sharedlib_entry();
 extern "C" void disassembly1();
 extern "C" void remote_path1();
@@ -262,6 +263,10 @@ int main(int argc, char *argv[])
     {
         vars_update();
     }
+    else if (testcase == "c_vars")
+    {
+        c_vars();
+    }
     else if (testcase == "header")
     {
         header_fn1(1);
@@ -336,6 +341,13 @@ int main(int argc, char *argv[])
     {
         signals();
     }
+    else if (testcase == "ignore_sigterm")
+    {
+#if !defined(_WIN32)
+        signal(SIGTERM, SIG_IGN);
+#endif
+        inf_loop();
+    }
     else
     {
         printf("Unknown testcase.\n");
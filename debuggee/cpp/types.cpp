@@ -24,6 +24,16 @@ public:
     ~DerivedClass() override {}
 };
 
+// Diamond with a virtual base: one VirtualBase subobject, reached through vbase offsets.
+struct VirtualBase
+{
+    int vb = 10;
+    virtual ~VirtualBase() {}
+};
+struct VirtualLeft : virtual VirtualBase { int l = 11; };
+struct VirtualRight : virtual VirtualBase { int r = 12; };
+struct Diamond : VirtualLeft, VirtualRight { int d = 13; };
+
 
 int global = 1234;
 
@@ -95,6 +105,11 @@ void vars()
         std::map<int, float> ord_map = {{1, 2.34f}, {2, 3.56f}};
         std::unordered_map<int, float> unord_map = {{1, 2.34f}, {2, 3.56f}};
         auto shared_ptr = std::make_shared<std::map<int, float>>(ord_map);
+        std::unique_ptr<Struct> unique_ptr(new Struct{i, 'u', 1.0f});
+        std::unique_ptr<Struct> null_unique_ptr;
+        std::weak_ptr<std::map<int, float>> weak_ptr = shared_ptr;
+        Diamond diamond;
+        VirtualBase* virtual_base_ptr = &diamond;
 
         Struct array_struct[5];
         for (int j = 0; j < 5; ++j)
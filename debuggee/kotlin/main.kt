@@ -0,0 +1,194 @@
+// Kotlin/Native debuggee. Shares the Rust debuggee's testcase interface
+// (`stdio`, `panic`, `spawn`, `sleep`, `inf_loop`, anything else runs the
+// variable testcases, no argument exits with -1), so expected DAP message
+// sequences are reusable.
+@file:OptIn(kotlinx.cinterop.ExperimentalForeignApi::class)
+
+package kdap.debuggee
+
+import kotlinx.cinterop.convert
+import platform.posix.*
+import kotlin.system.exitProcess
+
+fun main(args: Array<String>) {
+    when (args.firstOrNull()) {
+        "stdio" -> {
+            println("stdout")
+            fprintf(stderr, "stderr\n")
+        }
+        "panic" -> throw IllegalStateException("Oops!!!")
+        "spawn" -> {
+            val pid = fork()
+            if (pid == 0) {
+                execl("/proc/self/exe", "kotlin-debuggee", "sleep", null)
+                _exit(127)
+            }
+            println("pid = $pid")
+            waitpid(pid, null, 0)
+        }
+        "sleep" -> sleep(10.convert())
+        "inf_loop" -> {
+            var i = 0L
+            while (true) {
+                print("\r$i ")
+                fflush(stdout)
+                sleep(1.convert())
+                i += 1
+            }
+        }
+        null -> {
+            println("No testcase was specified.")
+            exitProcess(-1)
+        }
+        else -> {
+            primitives()
+            nullables()
+            classes()
+            arrays()
+            strings()
+            collections()
+            misc()
+            stepIn()
+        }
+    }
+}
+
+fun primitives() {
+    val char: Char = 'A'
+    val bool: Boolean = true
+    val byte: Byte = -8
+    val ubyte: UByte = 8u
+    val short: Short = -16
+    val ushort: UShort = 16u
+    val int: Int = -32
+    val uint: UInt = 32u
+    val long: Long = -64
+    val ulong: ULong = 64u
+    val float: Float = 3.1415926535f
+    val double: Double = 3.1415926535 * 2.0
+    val unit = Unit
+
+    println("---") // #BP_primitives
+    println("---")
+    println("---")
+}
+
+fun nullables() {
+    val str1: String? = "string"
+    val str2: String? = null
+    val int1: Int? = 5
+    val int2: Int? = null
+    val point1: Point? = Point(1, 2)
+    val point2: Point? = null
+
+    println("---") // #BP_nullables
+    println("---")
+    println("---")
+}
+
+data class Point(val x: Int, val y: Int)
+
+open class Shape(val name: String) {
+    open fun area(): Double = 0.0
+}
+
+class Circle(val radius: Double) : Shape("circle") {
+    override fun area(): Double = 3.14159 * radius * radius
+}
+
+enum class Color { RED, GREEN, BLUE }
+
+sealed class Result {
+    data class Ok(val value: String) : Result()
+    data class Err(val message: String) : Result()
+    object Empty : Result()
+}
+
+fun classes() {
+    val point = Point(1, 2)
+    val circle = Circle(1.5)
+    val shape: Shape = circle
+    val color = Color.GREEN
+    val ok: Result = Result.Ok("ok")
+    val err: Result = Result.Err("err")
+    val empty: Result = Result.Empty
+    val pair = Pair(1, "a")
+    val triple = Triple(1, "a", 42.0)
+
+    println("${point.x} ${shape.area()}")
+
+    println("---") // #BP_classes
+    println("---")
+    println("---")
+}
+
+fun arrays() {
+    val intArray = intArrayOf(1, 2, 3, 4, 5)
+    val doubleArray = doubleArrayOf(1.5, 2.5)
+    val stringArray = arrayOf("111", "2222", "3333")
+    val pointArray = arrayOf(Point(1, 2), Point(2, 3), Point(3, 4))
+    val emptyArray = emptyArray<Int>()
+    val largeArray = IntArray(20000) { it }
+
+    println("---") // #BP_arrays
+    println("---")
+    println("---")
+}
+
+fun strings() {
+    val emptyString = ""
+    val string = "A String"
+    val wstr1 = "Превед йожэг!"
+    val wstr2 = "Ḥ̪͔̦̺E͍̹̯̭͜ C̨͙̹̖̙O̡͍̪͖ͅM̢̗͙̫̬E̜͍̟̟̮S̢̢̪̘̦!"
+    val builder = StringBuilder("String").append("Builder")
+    val template = "$string and ${builder.length}"
+
+    println("---") // #BP_strings
+    println("---")
+    println("---")
+}
+
+fun collections() {
+    val emptyList = emptyList<Int>()
+    val list = listOf(1, 2, 3, 4, 5, 6, 7, 8, 9, 10)
+    val mutableList = mutableListOf("111", "2222", "3333", "4444", "5555")
+    mutableList.removeAt(0)
+    val subList = list.subList(2, 5)
+    val largeList = (0 until 20000).toMutableList()
+    val map = mutableMapOf("Einar" to 25, "Olaf" to 24, "Harald" to 12, "Conan" to 29)
+    val set = map.keys.toSet()
+
+    println("---") // #BP_collections
+    println("---")
+    println("---")
+}
+
+val GLOBAL: Int = 1234
+
+fun misc() {
+    val int = 32
+    val float = 42.0f
+    val closure = { x: Int -> (x + int) * float }
+    val lazyValue by lazy { "lazy" }
+
+    println(closure(1))
+
+    println("---") // #BP_misc
+    println("---")
+    println("---")
+}
+
+fun stepIn() {
+    val even = (0 until 10).filter { it % 2 == 0 }
+    println("---")
+
+    fun add3(a: Int, b: Int, c: Int): Int = a + b + c
+    fun add2(a: Int, b: Int): Int = a + b
+    fun get1(): Int = 1
+    fun get2(): Int = 2
+
+    add3(get1(),
+         add2(get2(),
+              get1()), get1())
+    println("---")
+}
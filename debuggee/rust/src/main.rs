@@ -19,6 +19,11 @@ fn main() {
             println!("pid = {}", child.id());
             child.wait().unwrap();
         }
+        Some("echo") => {
+            for line in std::io::stdin().lines() {
+                println!("echo: {}", line.unwrap());
+            }
+        }
         Some("sleep") => {
             std::thread::sleep(std::time::Duration::from_secs(10));
         }
@@ -40,6 +45,7 @@ fn main() {
             strings();
             maps();
             misc();
+            calls();
             step_in();
         }
         None => {
@@ -160,6 +160,8 @@ pub fn strings() {
     let str_slice = "String slice";
     let wstr1 = "Превед йожэг!";
     let wstr2 = String::from("Ḥ̪͔̦̺E͍̹̯̭͜ C̨͙̹̖̙O̡͍̪͖ͅM̢̗͙̫̬E̜͍̟̟̮S̢̢̪̘̦!");
+    let long_string = "x".repeat(10_000);
+    let bytes = b"hello".to_vec();
 
     let cstring = std::ffi::CString::new("C String").unwrap();
     let cstr = &cstring[..];
@@ -220,6 +222,9 @@ pub fn boxes() {
     let ref_cell3 = cell::RefCell::new(12);
     let ref_cell3_borrow = ref_cell3.borrow_mut();
 
+    let dyn_box: Box<dyn std::fmt::Debug> = Box::new(vec![1, 2, 3]);
+    let dyn_ref: &dyn std::fmt::Debug = &reg_struct.d;
+
     println!("---"); // #BP_boxes
     println!("---");
     println!("---");
@@ -269,6 +274,34 @@ pub fn misc() {
     println!("---");
 }
 
+pub fn calls() {
+    let a = 2;
+    let b = 3;
+    let sum = call_sum(a, b);
+    if sum < 0 {
+        call_spin();
+        call_abort();
+    }
+    println!("---"); // #BP_calls
+}
+
+#[inline(never)]
+pub fn call_sum(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+#[inline(never)]
+pub fn call_spin() -> i32 {
+    loop {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
+
+#[inline(never)]
+pub fn call_abort() -> i32 {
+    std::process::abort()
+}
+
 pub fn step_in() {
     let even = (0..10).filter(|x| x % 2 == 0).collect::<Vec<_>>();
     println!("---");
@@ -1,6 +1,7 @@
 package com.github.jomof
 
 import com.github.jomof.dap.*
+import com.github.jomof.dap.messages.ConfigurationSchema
 import java.io.File
 
 /**
@@ -8,8 +9,23 @@ import java.io.File
  * of `lldb-dap`, proxying DAP messages bidirectionally.
  *
  * Supports stdio (default), TCP listen (--port N), and TCP connect (--connect N),
- * matching CodeLLDB's command line. `--lldb-dap <path>` is required to specify
- * the `lldb-dap` executable.
+ * matching CodeLLDB's command line, and a Unix domain socket (--socket PATH).
+ * `--lldb-dap <path>` is required to specify the `lldb-dap` executable.
+ *
+ * With `--server`, a listener serves session after session; each gets its
+ * own `lldb-dap` process and interceptor state, so one long-lived JVM
+ * serves an editor that reconnects.
+ *
+ * `kdap debug <program> [args…]` debugs the program at a command-line
+ * prompt instead of serving a client (see [CliDebugger]).
+ *
+ * `kdap --generate-schema` prints the launch and attach configuration
+ * schema as a VS Code `configurationAttributes` block to stdout, for IDE
+ * extensions to contribute (see [ConfigurationSchema]).
+ *
+ * With `--replay <capture>`, no client is served: the capture is replayed
+ * against lldb-dap, what differs is printed to stderr, and the exit code
+ * is 0 only if nothing did (see [DapReplay]).
  *
  * When `--port 0` is used, the OS assigns an available port. The actual port
  * is printed to stderr as `Listening on port <N>` so callers can discover it
@@ -24,16 +40,33 @@ fun main(args: Array<String>) = mainImpl(args)
 fun mainImpl(args: Array<String>) {
     val config = Cli.parse(args)
     if (config == null) {
-        System.err.println("Usage: [--port N] | [--connect N] [--lldb-dap PATH] [--sb-log PATH]")
+        System.err.println("Usage: [--port N] | [--connect N] | [--socket PATH] [--server] [--lldb-dap PATH]")
+        System.err.println("       [--sb-log PATH] [--log PATH] [--record PATH] | [--replay PATH]")
+        System.err.println("       [options] debug PROGRAM [ARGS...] | --generate-schema")
         System.err.println("  --port N         Listen on port N (use 0 for OS-assigned)")
         System.err.println("  --connect N      Connect to 127.0.0.1:N")
+        System.err.println("  --socket PATH    Listen on a Unix domain socket at PATH")
+        System.err.println("  --server         With --port or --socket, serve one session after another")
         System.err.println("  --lldb-dap PATH  Path to lldb-dap executable (required)")
         System.err.println("  --sb-log PATH    Write SB API call trace to file")
+        System.err.println("  --log PATH       Write a JSON-lines protocol log to file (or set KDAP_LOG)")
+        System.err.println("  --log-categories LIST")
+        System.err.println("                   Log only these of client,backend,sb (or set KDAP_LOG_CATEGORIES)")
+        System.err.println("  --record PATH    Capture the client's DAP traffic to file")
+        System.err.println("  --replay PATH    Replay a capture against lldb-dap and report differences")
+        System.err.println("  debug PROGRAM    Debug PROGRAM at a command-line prompt")
+        System.err.println("  --generate-schema")
+        System.err.println("                   Print the launch and attach configuration schema")
         System.err.println("  (no args)        Use stdio")
         System.exit(1)
         return
     }
 
+    if (config.generateSchema) {
+        println(ConfigurationSchema.configurationAttributes().toString(2))
+        return
+    }
+
     // When using TCP listen, report the actual bound port to stderr.
     // This is essential for --port 0 (OS-assigned) but also useful for
     // confirming the port with an explicit value.
@@ -41,6 +74,9 @@ fun mainImpl(args: Array<String>) {
         is Transport.TcpListen -> t.copy(onBound = { port ->
             System.err.println("Listening on port $port")
         })
+        is Transport.UnixListen -> t.copy(onBound = { path ->
+            System.err.println("Listening on $path")
+        })
         else -> t
     }
 
@@ -51,17 +87,78 @@ fun mainImpl(args: Array<String>) {
             return
         }
 
-    val sbWatcher = config.sbLogPath?.let { FileSBWatcher(File(it)) }
+    val protocolLog = config.logPath?.let { ProtocolLog(File(it), config.logCategories) }
+    val sbWatcher = SBWatcher.of(listOfNotNull(config.sbLogPath?.let { FileSBWatcher(File(it)) }, protocolLog))
 
-    val lldbDap = LldbDapProcess.start(lldbDapPath)
+    if (config.replayPath != null) {
+        val lldbDap = LldbDapProcess.start(lldbDapPath)
+        val mismatches = try {
+            DapReplay.run(
+                File(config.replayPath),
+                lldbDap.inputStream,
+                lldbDap.outputStream,
+                KdapInterceptor(KdapInterceptor.defaultHandlers(sbWatcher), sbWatcher),
+                listOfNotNull(protocolLog),
+            )
+        } finally {
+            lldbDap.close()
+            protocolLog?.close()
+        }
+        mismatches.forEach { System.err.println(it) }
+        System.err.println(if (mismatches.isEmpty()) "Replay matches" else "Replay differs: ${mismatches.size}")
+        if (mismatches.isNotEmpty()) System.exit(1)
+        return
+    }
+
+    if (config.debugCommand != null) {
+        val capture = config.recordPath?.let { DapCaptureWriter(File(it)) }
+        val lldbDap = LldbDapProcess.start(lldbDapPath)
+        val exitCode = try {
+            CliDebugger.run(
+                config.debugCommand.first(),
+                config.debugCommand.drop(1),
+                lldbDap.inputStream,
+                lldbDap.outputStream,
+                KdapInterceptor(KdapInterceptor.defaultHandlers(sbWatcher), sbWatcher),
+                listOfNotNull(protocolLog, capture),
+                System.`in`.bufferedReader(),
+                System.out,
+                interactive = System.console() != null,
+                breakpointsFile = config.breakpointsPath?.let { File(it) },
+            )
+        } finally {
+            lldbDap.close()
+            capture?.close()
+            protocolLog?.close()
+        }
+        System.exit(exitCode)
+        return
+    }
+
+    // One lldb-dap, interceptor, and capture per session; in server mode, the
+    // transport runs this once per connection.
+    var sessions = 0
     try {
-        DapServer.runDecorator(
-            transport,
-            lldbDap.inputStream,
-            lldbDap.outputStream,
-            KdapInterceptor(KdapInterceptor.defaultHandlers(sbWatcher), sbWatcher),
-        )
+        transport.run { clientInput, clientOutput ->
+            sessions++
+            val capture = config.recordPath?.let { DapCaptureWriter(DapCaptureWriter.sessionFile(File(it), sessions)) }
+            val lldbDap = LldbDapProcess.start(lldbDapPath)
+            try {
+                DapServer.runDecorator(
+                    clientInput,
+                    clientOutput,
+                    lldbDap.inputStream,
+                    lldbDap.outputStream,
+                    KdapInterceptor(KdapInterceptor.defaultHandlers(sbWatcher), sbWatcher),
+                    listOfNotNull(protocolLog, capture),
+                    stopBackend = { lldbDap.close() },
+                )
+            } finally {
+                lldbDap.close()
+                capture?.close()
+            }
+        }
     } finally {
-        lldbDap.close()
+        protocolLog?.close()
     }
 }
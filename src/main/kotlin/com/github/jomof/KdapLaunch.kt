@@ -24,8 +24,11 @@ import java.util.concurrent.TimeUnit
  *
  * 1. Connects to KDAP's TCP listener at HOST:PORT
  * 2. Sends a JSON object: `{"tty": "/dev/ttys003"}` or `{"tty": null}`
- * 3. Reads a response: `{"success": true}` or `{"success": false, "message": "..."}`
- * 4. Exits with code 0 on success, 1 on failure
+ * 3. Reads a response line: `{"success": true}` or `{"success": false, "message": "..."}`
+ * 4. On success, waits until KDAP closes the connection (at the end of the
+ *    debug session), so the terminal's shell does not compete with the
+ *    debuggee for input
+ * 5. Exits with code 0 on success, 1 on failure
  *
  * ## Role 2 rejection
  *
@@ -94,9 +97,8 @@ fun kdapLaunchMain(args: Array<String>) {
             socket.shutdownOutput()
 
             // Read response
-            val responseText = socket.getInputStream()
-                .bufferedReader(StandardCharsets.UTF_8)
-                .readText()
+            val reader = socket.getInputStream().bufferedReader(StandardCharsets.UTF_8)
+            val responseText = reader.readLine().orEmpty()
             if (responseText.isNotBlank()) {
                 val response = JSONObject(responseText)
                 if (!response.optBoolean("success", false)) {
@@ -106,6 +108,11 @@ fun kdapLaunchMain(args: Array<String>) {
                     return
                 }
             }
+
+            // Hold the terminal until the debug session ends
+            while (reader.read() != -1) {
+                // Nothing else is sent on this connection
+            }
         }
     } catch (e: Exception) {
         System.err.println("kdap-launch: connection failed: ${e.message}")
@@ -6,14 +6,45 @@ package com.github.jomof.dap
  * - no args: stdio
  * - --port N: listen on port N, accept one connection
  * - --connect N: connect to localhost:N
+ * - --socket PATH: listen on a Unix domain socket at PATH
+ * - --server: with --port or --socket, serve session after session (see
+ *   [Transport])
  * - --lldb-dap PATH: explicit path to the lldb-dap executable
  * - --sb-log PATH: write SB API call trace to file
+ * - --log PATH (or `KDAP_LOG`): write the structured [ProtocolLog] to file
+ * - --log-categories LIST (or `KDAP_LOG_CATEGORIES`): comma-separated
+ *   [ProtocolLog.Category] IDs to log; all if absent
+ * - --record PATH: capture the client's traffic to file ([DapCaptureWriter])
+ * - --replay PATH: replay a capture against lldb-dap and report what
+ *   differs ([DapReplay]), instead of serving a client
+ * - debug PROGRAM [ARGS…]: debug PROGRAM at a command-line prompt
+ *   ([CliDebugger]) instead of serving a client; everything after
+ *   PROGRAM is its arguments
+ * - --breakpoints PATH: with debug, set the breakpoints of a breakpoint
+ *   file (see [com.github.jomof.dap.debugsession.handleBreakpointFile])
+ *   before the first prompt
+ * - --generate-schema: print the launch and attach configuration schema
+ *   ([com.github.jomof.dap.messages.ConfigurationSchema]) as a VS Code
+ *   `configurationAttributes` block instead of serving a client
+ *
+ * Flags take precedence over the environment variables.
  */
 object Cli {
     private const val PORT = "--port"
     private const val CONNECT = "--connect"
+    private const val SOCKET = "--socket"
+    private const val SERVER = "--server"
     private const val LLDB_DAP = "--lldb-dap"
     private const val SB_LOG = "--sb-log"
+    private const val LOG = "--log"
+    private const val LOG_CATEGORIES = "--log-categories"
+    private const val RECORD = "--record"
+    private const val REPLAY = "--replay"
+    private const val BREAKPOINTS = "--breakpoints"
+    private const val GENERATE_SCHEMA = "--generate-schema"
+    private const val DEBUG = "debug"
+    private const val LOG_ENV = "KDAP_LOG"
+    private const val LOG_CATEGORIES_ENV = "KDAP_LOG_CATEGORIES"
     private const val DEFAULT_HOST = "127.0.0.1"
 
     data class Config(
@@ -22,16 +53,43 @@ object Cli {
         val lldbDapPath: String? = null,
         /** Path to write SB API call trace, or null to disable. */
         val sbLogPath: String? = null,
+        /** Path to write the structured protocol log, or null to disable. */
+        val logPath: String? = null,
+        /** Categories the protocol log records. */
+        val logCategories: Set<ProtocolLog.Category> = ProtocolLog.Category.entries.toSet(),
+        /** Path to write a DAP capture to, or null to disable. */
+        val recordPath: String? = null,
+        /** Path of a DAP capture to replay instead of serving a client, or null. */
+        val replayPath: String? = null,
+        /** Program and arguments to debug at a command-line prompt instead of serving a client, or null. */
+        val debugCommand: List<String>? = null,
+        /** Breakpoint file to set the breakpoints of before the [debugCommand] prompt, or null. */
+        val breakpointsPath: String? = null,
+        /** Print the configuration schema instead of serving a client. */
+        val generateSchema: Boolean = false,
     )
 
     /**
-     * Parses [args] and returns [Config], or null if args are invalid (e.g. both --port and --connect).
+     * Parses [args], with `KDAP_LOG` and `KDAP_LOG_CATEGORIES` read from
+     * [environment], and returns [Config], or null if args are invalid
+     * (e.g. both --port and --connect, --server without a listener, an
+     * unknown log category, both --record and --replay, --breakpoints
+     * without debug, or --generate-schema with debug or --replay).
      */
-    fun parse(args: Array<String>): Config? {
+    fun parse(args: Array<String>, environment: Map<String, String> = System.getenv()): Config? {
         var port: Int? = null
         var connect: Int? = null
+        var socket: String? = null
+        var server = false
         var lldbDapPath: String? = null
         var sbLogPath: String? = null
+        var recordPath: String? = null
+        var replayPath: String? = null
+        var debugCommand: List<String>? = null
+        var breakpointsPath: String? = null
+        var generateSchema = false
+        var logPath: String? = environment[LOG_ENV]?.ifEmpty { null }
+        var logCategories: String? = environment[LOG_CATEGORIES_ENV]?.ifEmpty { null }
         var i = 0
         while (i < args.size) {
             when (args[i]) {
@@ -47,6 +105,15 @@ object Cli {
                     if (connect == null || connect !in 1..65535) return null
                     i += 2
                 }
+                SOCKET -> {
+                    if (i + 1 >= args.size) return null
+                    socket = args[i + 1]
+                    i += 2
+                }
+                SERVER -> {
+                    server = true
+                    i++
+                }
                 LLDB_DAP -> {
                     if (i + 1 >= args.size) return null
                     lldbDapPath = args[i + 1]
@@ -57,14 +124,59 @@ object Cli {
                     sbLogPath = args[i + 1]
                     i += 2
                 }
+                LOG -> {
+                    if (i + 1 >= args.size) return null
+                    logPath = args[i + 1]
+                    i += 2
+                }
+                LOG_CATEGORIES -> {
+                    if (i + 1 >= args.size) return null
+                    logCategories = args[i + 1]
+                    i += 2
+                }
+                RECORD -> {
+                    if (i + 1 >= args.size) return null
+                    recordPath = args[i + 1]
+                    i += 2
+                }
+                REPLAY -> {
+                    if (i + 1 >= args.size) return null
+                    replayPath = args[i + 1]
+                    i += 2
+                }
+                BREAKPOINTS -> {
+                    if (i + 1 >= args.size) return null
+                    breakpointsPath = args[i + 1]
+                    i += 2
+                }
+                GENERATE_SCHEMA -> {
+                    generateSchema = true
+                    i++
+                }
+                DEBUG -> {
+                    if (i + 1 >= args.size) return null
+                    debugCommand = args.drop(i + 1)
+                    break
+                }
                 else -> i++
             }
         }
-        return when {
-            port != null && connect != null -> null
-            connect != null -> Config(Transport.TcpConnect(DEFAULT_HOST, connect), lldbDapPath, sbLogPath)
-            port != null -> Config(Transport.TcpListen(port), lldbDapPath, sbLogPath)
-            else -> Config(Transport.Stdio, lldbDapPath, sbLogPath)
+        val categories = logCategories?.let { list ->
+            list.split(',').filter { it.isNotBlank() }.map { ProtocolLog.Category.fromId(it) ?: return null }.toSet()
+        } ?: ProtocolLog.Category.entries.toSet()
+        if (recordPath != null && replayPath != null) return null
+        if (debugCommand != null && replayPath != null) return null
+        if (breakpointsPath != null && debugCommand == null) return null
+        if (generateSchema && (debugCommand != null || replayPath != null)) return null
+        val transport = when {
+            listOfNotNull(port, connect, socket).size > 1 -> return null
+            connect != null -> if (server) return null else Transport.TcpConnect(DEFAULT_HOST, connect)
+            port != null -> Transport.TcpListen(port, server = server)
+            socket != null -> Transport.UnixListen(socket, server = server)
+            server -> return null
+            else -> Transport.Stdio
         }
+        return Config(transport, lldbDapPath, sbLogPath, logPath, categories, recordPath, replayPath, debugCommand,
+            breakpointsPath, generateSchema)
     }
 }
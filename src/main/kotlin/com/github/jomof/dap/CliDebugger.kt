@@ -0,0 +1,606 @@
+package com.github.jomof.dap
+
+import com.github.jomof.dap.debugsession.parseBreakpointFile
+import org.json.JSONArray
+import org.json.JSONObject
+import java.io.BufferedReader
+import java.io.File
+import java.io.InputStream
+import java.io.OutputStream
+import java.io.PrintStream
+import java.util.concurrent.CompletableFuture
+import java.util.concurrent.CountDownLatch
+import java.util.concurrent.LinkedBlockingQueue
+import java.util.concurrent.TimeUnit
+
+/**
+ * Command-line debugger (`kdap debug <program> [args…]`, see [Cli]): a
+ * GDB-like prompt in front of the same session an IDE would get, so the
+ * debugger core can be tried, scripted, and triaged without one.
+ *
+ * The debugger is a DAP client ([DapClient]) of an in-process session
+ * ([DapServer.runInProcess]) with KDAP's interceptor and lldb-dap behind
+ * it: every command is a DAP request, and what the user sees is built
+ * from the responses and events, so a bug seen here is one an IDE sees.
+ *
+ * ## Commands
+ *
+ * | Command                           | Does                                     |
+ * |-----------------------------------|------------------------------------------|
+ * | `break`, `b` `<file>:<line>`      | breakpoint at a line                     |
+ * | `break`, `b` `<line>`             | breakpoint in the current frame's file   |
+ * | `break`, `b` `<function>`         | breakpoint on a function                 |
+ * | `delete`, `d` `[<n>]`             | removes breakpoint `n`, or all           |
+ * | `info breakpoints`, `info b`      | lists breakpoints                        |
+ * | `save breakpoints <file>`         | writes the breakpoints to a file         |
+ * | `run`, `r`                        | starts the program                       |
+ * | `continue`, `c`                   | resumes the current thread               |
+ * | `next`, `n` / `step`, `s`         | steps over / into                        |
+ * | `finish`                          | steps out                                |
+ * | `backtrace`, `bt`                 | the current thread's stack               |
+ * | `frame`, `f` `<n>`                | selects frame `n`                        |
+ * | `threads`                         | lists threads                            |
+ * | `print`, `p` `<expr>`             | evaluates an expression (watch context)  |
+ * | `locals`                          | the current frame's local variables      |
+ * | `lldb <command>`                  | runs an LLDB command (debug console)     |
+ * | `help`, `h` / `quit`, `q`         |                                          |
+ *
+ * `kdap debug --breakpoints <file>` sets the breakpoints of a breakpoint
+ * file, as `save breakpoints` or an IDE's `kdap/breakpoints` export
+ * writes it (see BreakpointFile.kt), before the first prompt.
+ *
+ * Breakpoints can be set before `run`, which sends `configurationDone`;
+ * execution commands wait for the program to stop or end before the next
+ * prompt, so a script piped to stdin runs deterministically. Lines that
+ * are blank or start with `#` are skipped. Without a terminal, each
+ * command is echoed after the prompt, so the output reads as a
+ * transcript.
+ */
+class CliDebugger(
+    adapterInput: InputStream,
+    adapterOutput: OutputStream,
+    private val out: PrintStream,
+    private val timeoutMs: Long = DapClient.DEFAULT_TIMEOUT_MS,
+) : AutoCloseable {
+
+    /**
+     * A breakpoint as the user set it, with what the adapter last said of
+     * it. [options] are the other fields it is sent with (a breakpoint
+     * file's conditions and log messages).
+     */
+    private data class Breakpoint(
+        val location: BreakLocation,
+        val options: JSONObject = JSONObject(),
+        var verified: Boolean = false,
+        var line: Int? = null,
+    )
+
+    private val initialized = CountDownLatch(1)
+
+    /** `stopped` and `terminated` events (or [ENDED]) that execution commands wait for. */
+    private val stops = LinkedBlockingQueue<JSONObject>()
+    private val breakpoints = mutableListOf<Breakpoint?>()
+    private val sentFiles = mutableSetOf<String>()
+    private var launch: CompletableFuture<JSONObject>? = null
+    private var started = false
+    private var ended = false
+    @Volatile private var threadId: Int? = null
+    @Volatile private var frameIndex = 0
+    @Volatile private var exitCode: Int? = null
+
+    // Last, as its reader thread starts at once and reports to the state above.
+    private val client = DapClient(adapterInput, adapterOutput, ::onEvent) { stops.put(ENDED) }
+
+    /**
+     * Launches [program] with [args], then reads commands from [commands]
+     * until `quit` or end of input, with the breakpoints of
+     * [breakpointsFile] set first. Returns the program's exit code (0 if
+     * it did not exit), or 1 if it could not be debugged.
+     */
+    fun run(
+        program: String,
+        args: List<String>,
+        commands: BufferedReader,
+        interactive: Boolean,
+        breakpointsFile: File? = null,
+    ): Int {
+        val initialize = client.request("initialize", JSONObject()
+            .put("clientID", "kdap-cli")
+            .put("clientName", "KDAP CLI")
+            .put("adapterID", "kdap")
+            .put("linesStartAt1", true)
+            .put("columnsStartAt1", true)
+            .put("pathFormat", "path"), timeoutMs)
+        if (!initialize.optBoolean("success")) return fail("initialize", initialize)
+        launch = client.send("launch", JSONObject()
+            .put("program", File(program).absolutePath)
+            .put("args", JSONArray(args))
+            .put("cwd", File("").absolutePath))
+        while (!initialized.await(100, TimeUnit.MILLISECONDS)) {
+            val response = launch!!.getNow(null) ?: continue
+            if (!response.optBoolean("success")) return fail("launch", response)
+        }
+        out.println("Launched $program; set breakpoints, then 'run'.")
+        breakpointsFile?.let { file ->
+            try {
+                loadBreakpoints(file)
+            } catch (e: Exception) {
+                out.println("error: ${e.message ?: e.javaClass.simpleName}")
+            }
+        }
+        while (true) {
+            if (!interactive) out.print(PROMPT)
+            val line = (if (interactive) System.console()?.readLine(PROMPT) else commands.readLine()) ?: break
+            if (!interactive) out.println(line)
+            val command = parseDebugCommand(line) ?: continue
+            if (command == DebugCommand.Quit) break
+            execute(command)
+        }
+        quit()
+        return exitCode ?: 0
+    }
+
+    /** Runs one [command], printing what it shows. */
+    internal fun execute(command: DebugCommand) {
+        try {
+            when (command) {
+                is DebugCommand.Break -> setBreakpoint(command.location)
+                is DebugCommand.Delete -> deleteBreakpoints(command.number)
+                DebugCommand.ListBreakpoints -> listBreakpoints()
+                is DebugCommand.SaveBreakpoints -> saveBreakpoints(command.path)
+                DebugCommand.Run -> start()
+                DebugCommand.Continue -> resume("continue")
+                DebugCommand.Next -> resume("next")
+                DebugCommand.Step -> resume("stepIn")
+                DebugCommand.Finish -> resume("stepOut")
+                DebugCommand.Backtrace -> backtrace()
+                is DebugCommand.Frame -> selectFrame(command.index)
+                DebugCommand.Threads -> threads()
+                is DebugCommand.Print -> evaluate(command.expression, "watch")
+                DebugCommand.Locals -> locals()
+                is DebugCommand.Lldb -> evaluate("`${command.command}", "repl")
+                DebugCommand.Help -> out.print(HELP)
+                DebugCommand.Quit -> Unit
+                is DebugCommand.Invalid -> out.println(command.message)
+            }
+        } catch (e: CommandError) {
+            out.println(e.message)
+        } catch (e: Exception) {
+            out.println("error: ${e.message ?: e.javaClass.simpleName}")
+        }
+    }
+
+    // ── Breakpoints ──────────────────────────────────────────────────────
+
+    private fun setBreakpoint(location: BreakLocation) {
+        val resolved = if (location is BreakLocation.Line && location.file == null) {
+            val path = currentFrame()?.optJSONObject("source")?.optString("path")?.ifEmpty { null }
+                ?: throw CommandError("No current source file; use <file>:<line>.")
+            location.copy(file = path)
+        } else {
+            location
+        }
+        breakpoints += Breakpoint(resolved)
+        syncBreakpoints()
+        showBreakpoint(breakpoints.size)
+    }
+
+    private fun deleteBreakpoints(number: Int?) {
+        if (number == null) {
+            breakpoints.indices.forEach { breakpoints[it] = null }
+        } else {
+            if (breakpoints.getOrNull(number - 1) == null) throw CommandError("No breakpoint $number.")
+            breakpoints[number - 1] = null
+        }
+        syncBreakpoints()
+    }
+
+    private fun listBreakpoints() {
+        if (breakpoints.none { it != null }) {
+            out.println("No breakpoints.")
+            return
+        }
+        for (number in 1..breakpoints.size) if (breakpoints[number - 1] != null) showBreakpoint(number)
+    }
+
+    /** Adds the breakpoints of the breakpoint [file] to the user's and sets them. */
+    private fun loadBreakpoints(file: File) {
+        val loaded = parseBreakpointFile(file.readText())
+        for (source in loaded.sources) {
+            val path = source.source.path ?: source.source.name ?: continue
+            for (bp in source.breakpoints) {
+                breakpoints += Breakpoint(BreakLocation.Line(path, bp.line), bp.toJsonObject().apply { remove("line") })
+            }
+        }
+        for (fbp in loaded.functions) {
+            breakpoints += Breakpoint(BreakLocation.Function(fbp.name), fbp.toJsonObject().apply { remove("name") })
+        }
+        syncBreakpoints()
+        val count = loaded.sources.sumOf { it.breakpoints.size } + loaded.functions.size
+        out.println("Loaded $count breakpoint(s) from ${file.path}.")
+    }
+
+    private fun saveBreakpoints(path: String) {
+        val response = client.request("kdap/breakpoints", JSONObject()
+            .put("action", "export")
+            .put("path", File(path).absolutePath), timeoutMs)
+        if (!response.optBoolean("success")) throw CommandError(errorText("save breakpoints", response))
+    }
+
+    private fun showBreakpoint(number: Int) {
+        val bp = breakpoints[number - 1] ?: return
+        out.println(formatBreakpoint(number, bp.location, bp.verified, bp.line))
+    }
+
+    /** Sends every file's and the function breakpoints, and records what the adapter said of each. */
+    private fun syncBreakpoints() {
+        val live = breakpoints.filterNotNull()
+        val byFile = live.filter { it.location is BreakLocation.Line }.groupBy { sourcePath((it.location as BreakLocation.Line).file!!) }
+        for (file in sentFiles + byFile.keys) {
+            val bps = byFile[file].orEmpty()
+            val response = client.request("setBreakpoints", JSONObject()
+                .put("source", JSONObject().put("path", file))
+                .put("breakpoints", JSONArray(bps.map { JSONObject(it.options.toMap()).put("line", (it.location as BreakLocation.Line).line) })),
+                timeoutMs)
+            record(bps, response)
+        }
+        sentFiles.clear()
+        sentFiles += byFile.keys
+        val functions = live.filter { it.location is BreakLocation.Function }
+        val response = client.request("setFunctionBreakpoints", JSONObject()
+            .put("breakpoints", JSONArray(functions.map { JSONObject(it.options.toMap()).put("name", (it.location as BreakLocation.Function).name) })),
+            timeoutMs)
+        record(functions, response)
+    }
+
+    private fun record(bps: List<Breakpoint>, response: JSONObject) {
+        val results = response.optJSONObject("body")?.optJSONArray("breakpoints") ?: JSONArray()
+        bps.forEachIndexed { i, bp ->
+            val result = results.optJSONObject(i)
+            bp.verified = result?.optBoolean("verified") ?: false
+            bp.line = result?.optInt("line", 0)?.takeIf { it > 0 }
+        }
+    }
+
+    /** [file] as the adapter should see it: absolute if it exists here, else as given, to match by name. */
+    private fun sourcePath(file: String): String = File(file).let { if (it.exists()) it.absolutePath else file }
+
+    // ── Execution ────────────────────────────────────────────────────────
+
+    private fun start() {
+        if (started) throw CommandError("The program is already running.")
+        started = true
+        stops.clear()
+        val done = client.request("configurationDone", JSONObject(), timeoutMs)
+        if (!done.optBoolean("success")) throw CommandError(errorText("configurationDone", done))
+        val response = launch!!.get(timeoutMs, TimeUnit.MILLISECONDS)
+        if (!response.optBoolean("success")) throw CommandError(errorText("launch", response))
+        waitForStop()
+    }
+
+    private fun resume(command: String) {
+        val thread = stoppedThread()
+        stops.clear()
+        val response = client.request(command, JSONObject().put("threadId", thread), timeoutMs)
+        if (!response.optBoolean("success")) throw CommandError(errorText(command, response))
+        waitForStop()
+    }
+
+    /** Waits for the program to stop or end and shows where it stopped. */
+    private fun waitForStop() {
+        val event = stops.take()
+        if (event.optString("event") != "stopped") {
+            ended = true
+            threadId = null
+            return
+        }
+        val body = event.optJSONObject("body") ?: JSONObject()
+        val reason = body.optString("description").ifEmpty { body.optString("reason") }
+        out.println("Thread ${threadId ?: "?"} stopped: $reason")
+        body.optString("text").ifEmpty { null }?.let { out.println(it) }
+        currentFrame()?.let { showFrame(0, it) }
+    }
+
+    private fun stoppedThread(): Int {
+        if (!started) throw CommandError("The program is not running; use 'run'.")
+        if (ended) throw CommandError("The program is not being run.")
+        return threadId ?: throw CommandError("No thread is stopped.")
+    }
+
+    // ── Inspection ───────────────────────────────────────────────────────
+
+    private fun backtrace() {
+        val thread = stoppedThread()
+        val response = client.request("stackTrace", JSONObject().put("threadId", thread), timeoutMs)
+        if (!response.optBoolean("success")) throw CommandError(errorText("stackTrace", response))
+        val frames = response.optJSONObject("body")?.optJSONArray("stackFrames") ?: JSONArray()
+        for (i in 0 until frames.length()) {
+            val marker = if (i == frameIndex) "* " else "  "
+            out.println(marker + formatFrame(i, frames.getJSONObject(i)))
+        }
+    }
+
+    private fun selectFrame(index: Int) {
+        stoppedThread()
+        val previous = frameIndex
+        frameIndex = index
+        val frame = currentFrame()
+        if (frame == null) {
+            frameIndex = previous
+            throw CommandError("No frame $index.")
+        }
+        showFrame(index, frame)
+    }
+
+    private fun threads() {
+        stoppedThread()
+        val response = client.request("threads", JSONObject(), timeoutMs)
+        val threads = response.optJSONObject("body")?.optJSONArray("threads") ?: JSONArray()
+        for (i in 0 until threads.length()) {
+            val thread = threads.getJSONObject(i)
+            val marker = if (thread.optInt("id") == threadId) "* " else "  "
+            out.println("${marker}Thread ${thread.optInt("id")}: ${thread.optString("name")}")
+        }
+    }
+
+    private fun evaluate(expression: String, context: String) {
+        val frameId = if (started && !ended && threadId != null) currentFrame()?.optInt("id") else null
+        val arguments = JSONObject().put("expression", expression).put("context", context)
+        frameId?.let { arguments.put("frameId", it) }
+        val response = client.request("evaluate", arguments, timeoutMs)
+        if (!response.optBoolean("success")) throw CommandError(errorText("evaluate", response))
+        val result = response.optJSONObject("body")?.optString("result").orEmpty()
+        if (result.isEmpty()) return
+        out.println(if (context == "watch") "$expression = $result" else result.trimEnd())
+    }
+
+    private fun locals() {
+        val frame = currentFrame() ?: throw CommandError("No frame is selected.")
+        val scopes = client.request("scopes", JSONObject().put("frameId", frame.optInt("id")), timeoutMs)
+            .optJSONObject("body")?.optJSONArray("scopes") ?: JSONArray()
+        val locals = (0 until scopes.length()).map { scopes.getJSONObject(it) }
+            .firstOrNull { it.optString("presentationHint") == "locals" || it.optString("name") == "Locals" }
+            ?: throw CommandError("No local variables.")
+        val variables = client.request("variables",
+            JSONObject().put("variablesReference", locals.optInt("variablesReference")), timeoutMs)
+            .optJSONObject("body")?.optJSONArray("variables") ?: JSONArray()
+        if (variables.length() == 0) out.println("No local variables.")
+        for (i in 0 until variables.length()) out.println(formatVariable(variables.getJSONObject(i)))
+    }
+
+    /** The selected frame of the stopped thread, or null if there is none. */
+    private fun currentFrame(): JSONObject? {
+        val thread = threadId ?: return null
+        if (ended) return null
+        val response = client.request("stackTrace",
+            JSONObject().put("threadId", thread).put("startFrame", frameIndex).put("levels", 1), timeoutMs)
+        return response.optJSONObject("body")?.optJSONArray("stackFrames")?.optJSONObject(0)
+    }
+
+    /** Shows [frame], and its line of source if the file is here. */
+    private fun showFrame(index: Int, frame: JSONObject) {
+        out.println(formatFrame(index, frame))
+        val path = frame.optJSONObject("source")?.optString("path")?.ifEmpty { null } ?: return
+        val line = frame.optInt("line")
+        val text = File(path).takeIf { it.isFile }?.useLines { lines -> lines.drop(line - 1).firstOrNull() } ?: return
+        out.println("$line\t$text")
+    }
+
+    // ── Events and shutdown ──────────────────────────────────────────────
+
+    private fun onEvent(event: JSONObject) {
+        val body = event.optJSONObject("body") ?: JSONObject()
+        when (event.optString("event")) {
+            "initialized" -> initialized.countDown()
+            "output" -> if (body.optString("category") != "telemetry") {
+                out.print(body.optString("output"))
+                out.flush()
+            }
+            "stopped" -> {
+                threadId = body.optInt("threadId")
+                frameIndex = 0
+                stops.put(event)
+            }
+            "exited" -> {
+                exitCode = body.optInt("exitCode")
+                out.println("Process exited with code $exitCode.")
+            }
+            "terminated" -> stops.put(event)
+        }
+    }
+
+    private fun quit() {
+        if (started && !ended && !client.isClosed) {
+            try {
+                client.request("disconnect", JSONObject().put("terminateDebuggee", true), timeoutMs)
+            } catch (_: Exception) {
+            }
+        }
+    }
+
+    private fun fail(command: String, response: JSONObject): Int {
+        out.println(errorText(command, response))
+        return 1
+    }
+
+    private fun errorText(command: String, response: JSONObject): String {
+        val message = response.optJSONObject("body")?.optJSONObject("error")?.optString("format")?.ifEmpty { null }
+            ?: response.optString("message").ifEmpty { "failed" }
+        return "$command: $message"
+    }
+
+    override fun close() = client.close()
+
+    /** A command that cannot run now; its message is shown to the user. */
+    private class CommandError(message: String) : Exception(message)
+
+    companion object {
+        const val PROMPT = "(kdap) "
+
+        /** Put on [stops] when the adapter closes its stream. */
+        private val ENDED = JSONObject().put("event", "ended")
+
+        private val HELP = """
+            |break, b <file>:<line> | <line> | <function>   set a breakpoint
+            |delete, d [<n>]                                 delete breakpoint n, or all
+            |info breakpoints, info b                        list breakpoints
+            |save breakpoints <file>                         write the breakpoints to a file
+            |run, r                                          start the program
+            |continue, c                                     resume
+            |next, n / step, s / finish                      step over / into / out
+            |backtrace, bt                                   show the stack
+            |frame, f <n>                                    select frame n
+            |threads                                         list threads
+            |print, p <expr>                                 evaluate an expression
+            |locals                                          show local variables
+            |lldb <command>                                  run an LLDB command
+            |quit, q                                         end the session
+            |""".trimMargin()
+
+        /**
+         * Debugs [program] with [args] in a session in front of
+         * [backendInput] and [backendOutput] (an `lldb-dap` process),
+         * reading commands from [commands], with the breakpoints of
+         * [breakpointsFile] set first; returns the exit code for
+         * `kdap debug`.
+         */
+        fun run(
+            program: String,
+            args: List<String>,
+            backendInput: InputStream,
+            backendOutput: OutputStream,
+            interceptor: DapSession.Interceptor,
+            wireObservers: List<WireObserver>,
+            commands: BufferedReader,
+            out: PrintStream,
+            interactive: Boolean,
+            breakpointsFile: File? = null,
+        ): Int {
+            val session = DapServer.runInProcess(backendInput, backendOutput, interceptor, wireObservers)
+            return CliDebugger(session.fromSession, session.toSession, out).use { debugger ->
+                debugger.run(program, args, commands, interactive, breakpointsFile)
+            }
+        }
+    }
+}
+
+/** Where a [CliDebugger] breakpoint goes. */
+sealed class BreakLocation {
+    /** [line] of [file], or of the current frame's file if null. */
+    data class Line(val file: String?, val line: Int) : BreakLocation()
+
+    /** The function [name]. */
+    data class Function(val name: String) : BreakLocation()
+}
+
+/** A [CliDebugger] command (see its KDoc). */
+sealed class DebugCommand {
+    data class Break(val location: BreakLocation) : DebugCommand()
+    data class Delete(val number: Int?) : DebugCommand()
+    data object ListBreakpoints : DebugCommand()
+    data class SaveBreakpoints(val path: String) : DebugCommand()
+    data object Run : DebugCommand()
+    data object Continue : DebugCommand()
+    data object Next : DebugCommand()
+    data object Step : DebugCommand()
+    data object Finish : DebugCommand()
+    data object Backtrace : DebugCommand()
+    data class Frame(val index: Int) : DebugCommand()
+    data object Threads : DebugCommand()
+    data class Print(val expression: String) : DebugCommand()
+    data object Locals : DebugCommand()
+    data class Lldb(val command: String) : DebugCommand()
+    data object Help : DebugCommand()
+    data object Quit : DebugCommand()
+
+    /** Input that is not a command; [message] says why. */
+    data class Invalid(val message: String) : DebugCommand()
+}
+
+/**
+ * Parses one line of [CliDebugger] input, or returns null for a line
+ * that is blank or a `#` comment.
+ */
+fun parseDebugCommand(line: String): DebugCommand? {
+    val text = line.trim()
+    if (text.isEmpty() || text.startsWith("#")) return null
+    val name = text.substringBefore(' ')
+    val rest = text.substringAfter(' ', "").trim()
+    fun needs(what: String, build: (String) -> DebugCommand) =
+        if (rest.isEmpty()) DebugCommand.Invalid("'$name' needs $what.") else build(rest)
+    return when (name) {
+        "break", "b" -> needs("a location") { DebugCommand.Break(parseBreakLocation(it)) }
+        "delete", "d" -> if (rest.isEmpty()) {
+            DebugCommand.Delete(null)
+        } else {
+            rest.toIntOrNull()?.let { DebugCommand.Delete(it) } ?: DebugCommand.Invalid("'$name' takes a breakpoint number.")
+        }
+        "info" -> if (rest == "breakpoints" || rest == "b") {
+            DebugCommand.ListBreakpoints
+        } else {
+            DebugCommand.Invalid("Try 'info breakpoints'.")
+        }
+        "save" -> if (rest.substringBefore(' ') == "breakpoints") {
+            rest.substringAfter(' ', "").trim().ifEmpty { null }?.let { DebugCommand.SaveBreakpoints(it) }
+                ?: DebugCommand.Invalid("'save breakpoints' needs a file.")
+        } else {
+            DebugCommand.Invalid("Try 'save breakpoints <file>'.")
+        }
+        "run", "r" -> DebugCommand.Run
+        "continue", "c" -> DebugCommand.Continue
+        "next", "n" -> DebugCommand.Next
+        "step", "s" -> DebugCommand.Step
+        "finish" -> DebugCommand.Finish
+        "backtrace", "bt" -> DebugCommand.Backtrace
+        "frame", "f" -> rest.toIntOrNull()?.takeIf { it >= 0 }?.let { DebugCommand.Frame(it) }
+            ?: DebugCommand.Invalid("'$name' takes a frame number.")
+        "threads" -> DebugCommand.Threads
+        "print", "p" -> needs("an expression") { DebugCommand.Print(it) }
+        "locals" -> DebugCommand.Locals
+        "lldb" -> needs("a command") { DebugCommand.Lldb(it) }
+        "help", "h" -> DebugCommand.Help
+        "quit", "q" -> DebugCommand.Quit
+        else -> DebugCommand.Invalid("Unknown command '$name'; try 'help'.")
+    }
+}
+
+/**
+ * Parses a `break` location: `<file>:<line>`, a line number alone, or a
+ * function name (`main`, `my_crate::run`).
+ */
+fun parseBreakLocation(text: String): BreakLocation {
+    text.toIntOrNull()?.let { return BreakLocation.Line(null, it) }
+    val colon = text.lastIndexOf(':')
+    if (colon > 0) {
+        text.substring(colon + 1).toIntOrNull()?.let { return BreakLocation.Line(text.substring(0, colon), it) }
+    }
+    return BreakLocation.Function(text)
+}
+
+/** One line of `info breakpoints`: `Breakpoint 2: src/main.rs:12` or `… (pending)`. */
+internal fun formatBreakpoint(number: Int, location: BreakLocation, verified: Boolean, line: Int?): String {
+    val where = when (location) {
+        is BreakLocation.Line -> "${location.file}:${line ?: location.line}"
+        is BreakLocation.Function -> location.name
+    }
+    return "Breakpoint $number: $where" + if (verified) "" else " (pending)"
+}
+
+/** One line of a backtrace: `#1  main at /src/main.rs:12:5`, or the frame's address without a source. */
+internal fun formatFrame(index: Int, frame: JSONObject): String {
+    val name = frame.optString("name").ifEmpty { "??" }
+    val path = frame.optJSONObject("source")?.let { it.optString("path").ifEmpty { it.optString("name") } }.orEmpty()
+    val where = when {
+        path.isNotEmpty() && frame.optInt("line") > 0 ->
+            " at $path:${frame.optInt("line")}" + (frame.optInt("column").takeIf { it > 0 }?.let { ":$it" } ?: "")
+        frame.has("instructionPointerReference") -> " at ${frame.optString("instructionPointerReference")}"
+        else -> ""
+    }
+    return "#$index  $name$where"
+}
+
+/** One line of `locals`: `name: type = value`, without the type if it has none. */
+internal fun formatVariable(variable: JSONObject): String {
+    val type = variable.optString("type").ifEmpty { null }?.let { ": $it" }.orEmpty()
+    return "${variable.optString("name")}$type = ${variable.optString("value")}"
+}
@@ -0,0 +1,121 @@
+package com.github.jomof.dap
+
+import org.json.JSONObject
+import java.io.File
+import java.io.Writer
+
+/**
+ * DAP traffic capture (`.dapcap`): the messages between the client and
+ * KDAP, with when each crossed the wire, for [DapReplay] to re-drive the
+ * adapter with.
+ *
+ * Recorded with `--record <path>` (see [Cli]). The file holds one JSON
+ * object per line ([CapturedMessage]):
+ *
+ * ```
+ * {"timeMs":0,"from":"client","message":{"seq":1,"type":"request","command":"initialize",...}}
+ * {"timeMs":4,"from":"adapter","message":{"seq":1,"type":"response","request_seq":1,...}}
+ * ```
+ *
+ * `timeMs` is the time since the first message. Only the client side of
+ * KDAP is captured: what lldb-dap was sent is KDAP's doing, and is what a
+ * replay checks. Unlike [ProtocolLog], nothing is redacted, so a replay
+ * launches the debuggee with the environment it had; captures are not
+ * meant to leave the machine they were made on.
+ *
+ * In server mode each session gets a capture of its own ([sessionFile]).
+ *
+ * Thread-safe: messages are written under a lock, each flushed at once.
+ */
+class DapCaptureWriter(file: File, private val clock: () -> Long = System::nanoTime) : WireObserver {
+    private val lock = Any()
+    private val writer: Writer
+    private var start: Long? = null
+
+    init {
+        file.absoluteFile.parentFile?.mkdirs()
+        writer = file.bufferedWriter()
+    }
+
+    override fun message(direction: WireDirection, json: String) {
+        if (!direction.isClient) return
+        val message = try {
+            JSONObject(json)
+        } catch (_: Exception) {
+            return
+        }
+        synchronized(lock) {
+            val now = clock()
+            val first = start ?: now.also { start = it }
+            val captured = CapturedMessage((now - first) / 1_000_000, direction == WireDirection.FromClient, message)
+            writer.write(captured.toJson().toString())
+            writer.write("\n")
+            writer.flush()
+        }
+    }
+
+    /** Closes the capture file. */
+    fun close() {
+        synchronized(lock) { writer.close() }
+    }
+
+    companion object {
+        /**
+         * The capture of the [session]th session (from 1) for `--record`
+         * [file]: [file] itself for the first, and `name.N.ext` after.
+         */
+        fun sessionFile(file: File, session: Int): File {
+            if (session <= 1) return file
+            val name = if (file.extension.isEmpty()) {
+                "${file.name}.$session"
+            } else {
+                "${file.nameWithoutExtension}.$session.${file.extension}"
+            }
+            return file.resolveSibling(name)
+        }
+    }
+}
+
+/**
+ * One message of a capture: [message] as it crossed the wire,
+ * [fromClient] or from the adapter, [timeMs] after the first message.
+ */
+data class CapturedMessage(val timeMs: Long, val fromClient: Boolean, val message: JSONObject) {
+    val type: String get() = message.optString("type")
+
+    fun toJson(): JSONObject = JSONObject()
+        .put("timeMs", timeMs)
+        .put("from", if (fromClient) FROM_CLIENT else FROM_ADAPTER)
+        .put("message", message)
+
+    companion object {
+        private const val FROM_CLIENT = "client"
+        private const val FROM_ADAPTER = "adapter"
+
+        /** Parses one capture line. Throws [IllegalArgumentException] if it is not one. */
+        fun fromJson(line: String): CapturedMessage {
+            val obj = try {
+                JSONObject(line)
+            } catch (e: Exception) {
+                throw IllegalArgumentException("not a capture record: ${e.message}")
+            }
+            val fromClient = when (val from = obj.optString("from")) {
+                FROM_CLIENT -> true
+                FROM_ADAPTER -> false
+                else -> throw IllegalArgumentException("unknown capture source '$from'")
+            }
+            val message = obj.optJSONObject("message") ?: throw IllegalArgumentException("capture record has no message")
+            return CapturedMessage(obj.optLong("timeMs", 0), fromClient, message)
+        }
+
+        /** Reads the capture [file], skipping blank lines. */
+        fun readCapture(file: File): List<CapturedMessage> =
+            file.readLines().filter { it.isNotBlank() }.mapIndexed { index, line ->
+                try {
+                    fromJson(line)
+                } catch (e: IllegalArgumentException) {
+                    throw IllegalArgumentException("${file.path}:${index + 1}: ${e.message}")
+                }
+            }
+    }
+}
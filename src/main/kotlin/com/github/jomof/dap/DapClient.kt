@@ -0,0 +1,113 @@
+package com.github.jomof.dap
+
+import org.json.JSONObject
+import java.io.IOException
+import java.io.InputStream
+import java.io.OutputStream
+import java.util.concurrent.CompletableFuture
+import java.util.concurrent.ConcurrentHashMap
+import java.util.concurrent.TimeUnit
+import java.util.concurrent.atomic.AtomicInteger
+import kotlin.concurrent.thread
+
+/**
+ * Minimal in-process DAP client: sends requests to an adapter over
+ * [output] and reads its responses and events from [input]. Used by
+ * [CliDebugger] to drive the same session a client would.
+ *
+ * A thread reads the adapter's messages: responses complete the future
+ * [send] returned, and events go to [onEvent], on that thread. Reverse
+ * requests (`runInTerminal`, `startDebugging`) are refused. When the
+ * adapter closes its stream, requests still waiting fail with
+ * [IOException], [onClose] is called, and [onEvent] is not called again.
+ */
+class DapClient(
+    private val input: InputStream,
+    private val output: OutputStream,
+    private val onEvent: (JSONObject) -> Unit = {},
+    private val onClose: () -> Unit = {},
+) : AutoCloseable {
+    private val nextSeq = AtomicInteger(1)
+    private val pending = ConcurrentHashMap<Int, CompletableFuture<JSONObject>>()
+    private val writeLock = Any()
+    @Volatile private var closed = false
+
+    private val reader = thread(name = "kdap-client-reader", isDaemon = true) {
+        try {
+            while (true) {
+                val json = DapFraming.readMessage(input) ?: break
+                val message = try {
+                    JSONObject(json)
+                } catch (_: Exception) {
+                    continue
+                }
+                when (message.optString("type")) {
+                    "response" -> pending.remove(message.optInt("request_seq"))?.complete(message)
+                    "event" -> onEvent(message)
+                    "request" -> write(JSONObject()
+                        .put("type", "response")
+                        .put("request_seq", message.optInt("seq"))
+                        .put("command", message.optString("command"))
+                        .put("success", false)
+                        .put("message", "not supported by this client"))
+                }
+            }
+        } catch (_: IOException) {
+        }
+        closed = true
+        pending.values.forEach { it.completeExceptionally(IOException("adapter closed the connection")) }
+        pending.clear()
+        onClose()
+    }
+
+    /** Whether the adapter has closed its stream. */
+    val isClosed: Boolean get() = closed
+
+    /**
+     * Sends the request [command] with [arguments] and returns its
+     * response, successful or not, when it comes.
+     */
+    fun send(command: String, arguments: JSONObject = JSONObject()): CompletableFuture<JSONObject> {
+        val seq = nextSeq.getAndIncrement()
+        val future = CompletableFuture<JSONObject>()
+        if (closed) return future.also { it.completeExceptionally(IOException("adapter closed the connection")) }
+        pending[seq] = future
+        if (closed) pending.remove(seq)?.completeExceptionally(IOException("adapter closed the connection"))
+        try {
+            write(JSONObject().put("seq", seq).put("type", "request").put("command", command).put("arguments", arguments))
+        } catch (e: IOException) {
+            pending.remove(seq)
+            future.completeExceptionally(e)
+        }
+        return future
+    }
+
+    /**
+     * Sends the request [command] with [arguments] and waits up to
+     * [timeoutMs] for its response, successful or not.
+     */
+    fun request(command: String, arguments: JSONObject = JSONObject(), timeoutMs: Long = DEFAULT_TIMEOUT_MS): JSONObject =
+        send(command, arguments).get(timeoutMs, TimeUnit.MILLISECONDS)
+
+    private fun write(message: JSONObject) {
+        synchronized(writeLock) {
+            DapFraming.writeMessage(output, message.toString())
+        }
+    }
+
+    /** Closes the stream to the adapter, which ends its session, and waits briefly for it to close its own. */
+    override fun close() {
+        try {
+            output.close()
+        } catch (_: IOException) {
+        }
+        reader.join(CLOSE_TIMEOUT_MS)
+    }
+
+    companion object {
+        /** How long [request] waits for a response unless told otherwise. */
+        const val DEFAULT_TIMEOUT_MS = 30_000L
+
+        private const val CLOSE_TIMEOUT_MS = 2_000L
+    }
+}
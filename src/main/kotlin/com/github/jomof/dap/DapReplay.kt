@@ -0,0 +1,295 @@
+package com.github.jomof.dap
+
+import org.json.JSONArray
+import org.json.JSONObject
+import java.io.File
+import java.io.IOException
+import java.io.InputStream
+import java.io.OutputStream
+import java.util.concurrent.TimeUnit
+import java.util.concurrent.locks.ReentrantLock
+import kotlin.concurrent.thread
+import kotlin.concurrent.withLock
+
+/**
+ * Replay of a DAP capture ([DapCaptureWriter]): sends an adapter the
+ * client's messages from the capture and checks that it answers as it
+ * did when the capture was made. For regression testing, from the command
+ * line (`kdap --replay <path>`, see [Cli]) or from a test
+ * ([assertMatches]).
+ *
+ * ## Pacing
+ *
+ * The client's messages are sent in their recorded order, each once the
+ * adapter has sent what it had sent before it in the capture: the
+ * responses to the earlier requests and as many of each event and
+ * reverse request. So `threads` is sent after the `stopped` event it
+ * followed, however long the debuggee takes to get there. Recorded times
+ * are not waited out. `output` events are not waited for, since their
+ * text and count vary from run to run.
+ *
+ * ## IDs
+ *
+ * Thread, frame, variable, source, module, and breakpoint IDs differ from
+ * run to run ([ID_KEYS]). While replaying, each ID the adapter sends is
+ * matched with the one in the same place of the recorded message, and the
+ * client's later messages are sent with the IDs of the replay, as is the
+ * `request_seq` of the client's response to a reverse request. When
+ * comparing ([mismatches]), IDs, `seq`s, and [IGNORED_KEYS] are left
+ * out, and responses are paired by the request they answer and events
+ * and reverse requests by their name and place among those of the name.
+ *
+ * Reverse requests are answered as recorded, so a capture that ran the
+ * debuggee in the client's terminal (`runInTerminal`) replays with the
+ * terminal's old process ID and cannot be replayed faithfully.
+ */
+object DapReplay {
+    /** How long the replay waits for the adapter to catch up to the capture. */
+    const val DEFAULT_TIMEOUT_MS = 30_000L
+
+    /** Keys whose values are left out when comparing, alongside the IDs. */
+    val IGNORED_KEYS = setOf("seq", "request_seq", "systemProcessId")
+
+    /** Events that are neither waited for nor compared. */
+    val UNCOMPARED_EVENTS = setOf("output")
+
+    /** Keys whose numbers are IDs, by the kind of ID; `id` is classed by where it is (see [idKind]). */
+    val ID_KEYS = mapOf(
+        "threadId" to "thread",
+        "frameId" to "frame",
+        "variablesReference" to "variables",
+        "sourceReference" to "source",
+        "breakpointId" to "breakpoint",
+        "hitBreakpointIds" to "breakpoint",
+    )
+
+    private val ID_PARENTS = mapOf(
+        "threads" to "thread",
+        "thread" to "thread",
+        "stackFrames" to "frame",
+        "frame" to "frame",
+        "breakpoints" to "breakpoint",
+        "breakpoint" to "breakpoint",
+        "modules" to "module",
+        "module" to "module",
+    )
+
+    /** Placeholder IDs are compared as. */
+    private const val ID = "<id>"
+
+    /**
+     * Replays the capture [file] against [backendInput] and
+     * [backendOutput] (an `lldb-dap` process), through a session run with
+     * [interceptor], and returns the [mismatches].
+     */
+    fun run(
+        file: File,
+        backendInput: InputStream,
+        backendOutput: OutputStream,
+        interceptor: DapSession.Interceptor,
+        wireObservers: List<WireObserver> = emptyList(),
+        timeoutMs: Long = DEFAULT_TIMEOUT_MS,
+    ): List<String> {
+        val recorded = CapturedMessage.readCapture(file)
+        val session = DapServer.runInProcess(backendInput, backendOutput, interceptor, wireObservers)
+        val replayed = replay(recorded, session.toSession, session.fromSession, timeoutMs)
+        session.join(timeoutMs)
+        return mismatches(recorded, replayed)
+    }
+
+    /**
+     * Sends the client's messages of [recorded] to [toAdapter], paced and
+     * with IDs mapped as the object KDoc says, and returns what came back
+     * from [fromAdapter]. Closes [toAdapter] when done, so the adapter's
+     * session ends. Stops early if the adapter does not catch up within
+     * [timeoutMs]; [mismatches] then reports what it did not send.
+     */
+    fun replay(
+        recorded: List<CapturedMessage>,
+        toAdapter: OutputStream,
+        fromAdapter: InputStream,
+        timeoutMs: Long = DEFAULT_TIMEOUT_MS,
+    ): List<CapturedMessage> {
+        val lock = ReentrantLock()
+        val changed = lock.newCondition()
+        val received = mutableListOf<CapturedMessage>()
+        val start = System.nanoTime()
+        var ended = false
+        val reader = thread(name = "kdap-replay-reader", isDaemon = true) {
+            try {
+                while (true) {
+                    val json = DapFraming.readMessage(fromAdapter) ?: break
+                    val message = try {
+                        JSONObject(json)
+                    } catch (_: Exception) {
+                        continue
+                    }
+                    lock.withLock {
+                        received += CapturedMessage((System.nanoTime() - start) / 1_000_000, false, message)
+                        changed.signalAll()
+                    }
+                }
+            } catch (_: IOException) {
+            }
+            lock.withLock {
+                ended = true
+                changed.signalAll()
+            }
+        }
+
+        val ids = mutableMapOf<Pair<String, Long>, Any>()
+        val reverseSeqs = mutableMapOf<Int, Int>()
+        // Waits until the adapter has sent its messages in `expected`, and learns their IDs;
+        // false if it does not within the timeout, or ends first.
+        fun awaitAdapter(expected: List<JSONObject>): Boolean {
+            val deadline = System.nanoTime() + timeoutMs * 1_000_000
+            lock.withLock {
+                while (true) {
+                    val actual = received.map { it.message }
+                    val pairs = pair(expected, actual)
+                    if (pairs.all { it.second != null }) {
+                        for ((r, a) in pairs) {
+                            learnIds(r, a!!, null, null, ids)
+                            if (r.optString("type") == "request") reverseSeqs[r.optInt("seq")] = a.optInt("seq")
+                        }
+                        return true
+                    }
+                    val left = deadline - System.nanoTime()
+                    if (ended || left <= 0) return false
+                    changed.await(left, TimeUnit.NANOSECONDS)
+                }
+            }
+        }
+
+        try {
+            var caughtUp = true
+            for ((index, captured) in recorded.withIndex()) {
+                if (!captured.fromClient) continue
+                val before = recorded.subList(0, index).filter { !it.fromClient }.map { it.message }
+                caughtUp = awaitAdapter(before)
+                if (!caughtUp) break
+                val message = JSONObject(captured.message.toString())
+                message.optJSONObject("arguments")?.let { mapIds(it, null, null, ids) }
+                if (message.optString("type") == "response") {
+                    reverseSeqs[message.optInt("request_seq")]?.let { message.put("request_seq", it) }
+                }
+                DapFraming.writeMessage(toAdapter, message.toString())
+            }
+            if (caughtUp) awaitAdapter(recorded.filter { !it.fromClient }.map { it.message })
+        } catch (_: IOException) {
+        } finally {
+            try {
+                toAdapter.close()
+            } catch (_: IOException) {
+            }
+        }
+        reader.join(timeoutMs)
+        return lock.withLock { received.toList() }
+    }
+
+    /**
+     * What differs between the adapter's messages in [recorded] and in
+     * [replayed], modulo IDs (see the object KDoc); empty if they match.
+     * Client messages in either list are ignored.
+     */
+    fun mismatches(recorded: List<CapturedMessage>, replayed: List<CapturedMessage>): List<String> {
+        val expected = recorded.filter { !it.fromClient }.map { it.message }
+        val actual = replayed.filter { !it.fromClient }.map { it.message }
+        val result = mutableListOf<String>()
+        val paired = mutableSetOf<JSONObject>()
+        for ((r, a) in pair(expected, actual)) {
+            if (a == null) {
+                result += "missing ${describe(r)}"
+                continue
+            }
+            paired += a
+            val wanted = normalize(r, null, null)
+            val got = normalize(a, null, null)
+            if (!(wanted as JSONObject).similar(got)) result += "${describe(r)}: expected $wanted, got $got"
+        }
+        for (a in actual) {
+            if (a in paired || isUncompared(a) || a.optString("type") == "response") continue
+            result += "unexpected ${describe(a)}"
+        }
+        return result
+    }
+
+    /** Throws [AssertionError] listing the [mismatches] between [recorded] and [replayed], if any. */
+    fun assertMatches(recorded: List<CapturedMessage>, replayed: List<CapturedMessage>) {
+        val mismatches = mismatches(recorded, replayed)
+        if (mismatches.isNotEmpty()) {
+            throw AssertionError("Replay differs from capture:\n" + mismatches.joinToString("\n") { "  $it" })
+        }
+    }
+
+    /**
+     * Each of the adapter's messages in [expected], except the uncompared
+     * events, with its counterpart in [actual], or null: the response to
+     * the same request, or the event or reverse request of the same name
+     * in the same place among those of the name.
+     */
+    private fun pair(expected: List<JSONObject>, actual: List<JSONObject>): List<Pair<JSONObject, JSONObject?>> {
+        val responses = actual.filter { it.optString("type") == "response" }.associateBy { it.optInt("request_seq") }
+        val byName = actual.filter { it.optString("type") != "response" }.groupBy { nameOf(it) }
+        val seen = mutableMapOf<String, Int>()
+        return expected.filter { !isUncompared(it) }.map { r ->
+            if (r.optString("type") == "response") {
+                r to responses[r.optInt("request_seq")]
+            } else {
+                val name = nameOf(r)
+                val n = seen.merge(name, 1, Int::plus)!! - 1
+                r to byName[name]?.getOrNull(n)
+            }
+        }
+    }
+
+    private fun nameOf(message: JSONObject) = message.optString("type") + ":" +
+        (message.optString("event").ifEmpty { message.optString("command") })
+
+    private fun isUncompared(message: JSONObject) =
+        message.optString("type") == "event" && message.optString("event") in UNCOMPARED_EVENTS
+
+    private fun describe(message: JSONObject): String = when (message.optString("type")) {
+        "response" -> "response to '${message.optString("command")}' (request ${message.optInt("request_seq")})"
+        "event" -> "event '${message.optString("event")}'"
+        else -> "reverse request '${message.optString("command")}'"
+    }
+
+    /** The kind of ID the number under [key] is, in an object under [parent], or null if it is not an ID. */
+    internal fun idKind(key: String?, parent: String?): String? =
+        if (key == "id") ID_PARENTS[parent] else ID_KEYS[key]
+
+    /** Records, in [ids], the replay's ID for each recorded ID [recorded] has where [actual] has one. */
+    private fun learnIds(recorded: Any?, actual: Any?, key: String?, parent: String?, ids: MutableMap<Pair<String, Long>, Any>) {
+        when {
+            recorded is JSONObject && actual is JSONObject ->
+                for (k in recorded.keySet()) if (actual.has(k)) learnIds(recorded.get(k), actual.get(k), k, key, ids)
+            recorded is JSONArray && actual is JSONArray ->
+                for (i in 0 until minOf(recorded.length(), actual.length())) {
+                    learnIds(recorded.get(i), actual.get(i), key, parent, ids)
+                }
+            recorded is Number && actual != null -> idKind(key, parent)?.let { ids[it to recorded.toLong()] = actual }
+        }
+    }
+
+    /** Replaces each recorded ID in [value] with the replay's, where [ids] has it. */
+    private fun mapIds(value: Any?, key: String?, parent: String?, ids: Map<Pair<String, Long>, Any>): Any? = when (value) {
+        is JSONObject -> value.apply { for (k in keySet().toList()) put(k, mapIds(get(k), k, key, ids)) }
+        is JSONArray -> value.apply { for (i in 0 until length()) put(i, mapIds(get(i), key, parent, ids)) }
+        is Number -> idKind(key, parent)?.let { ids[it to value.toLong()] } ?: value
+        else -> value
+    }
+
+    /**
+     * [value] with [IGNORED_KEYS] removed and IDs replaced by a placeholder.
+     * A `variablesReference` of 0, no children, is kept.
+     */
+    private fun normalize(value: Any?, key: String?, parent: String?): Any? = when (value) {
+        is JSONObject -> JSONObject().also { copy ->
+            for (k in value.keySet()) if (k !in IGNORED_KEYS) copy.put(k, normalize(value.get(k), k, key))
+        }
+        is JSONArray -> JSONArray().also { copy -> for (item in value) copy.put(normalize(item, key, parent)) }
+        0, JSONObject.NULL -> value
+        else -> if (idKind(key, parent) != null) ID else value
+    }
+}
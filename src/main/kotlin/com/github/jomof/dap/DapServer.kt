@@ -3,6 +3,9 @@ package com.github.jomof.dap
 import kotlinx.coroutines.runBlocking
 import java.io.InputStream
 import java.io.OutputStream
+import java.io.PipedInputStream
+import java.io.PipedOutputStream
+import kotlin.concurrent.thread
 
 /**
  * DAP server: runs as a decorator in front of `lldb-dap`, forwarding messages
@@ -28,15 +31,17 @@ object DapServer {
      * @param backendInput   stream to read DAP messages from the backend
      * @param backendOutput  stream to write DAP messages to the backend
      * @param interceptor    per-request handler
+     * @param wireObservers  see every message read and written
      */
     fun runDecorator(
         transport: Transport,
         backendInput: InputStream,
         backendOutput: OutputStream,
         interceptor: DapSession.Interceptor = DapSession.Interceptor.PASS_THROUGH,
+        wireObservers: List<WireObserver> = emptyList(),
     ) {
         transport.run { clientInput, clientOutput ->
-            runDecorator(clientInput, clientOutput, backendInput, backendOutput, interceptor)
+            runDecorator(clientInput, clientOutput, backendInput, backendOutput, interceptor, wireObservers)
         }
     }
 
@@ -48,6 +53,8 @@ object DapServer {
      * @param backendInput   stream to read DAP messages from the backend
      * @param backendOutput  stream to write DAP messages to the backend
      * @param interceptor    per-request handler
+     * @param wireObservers  see every message read and written
+     * @param stopBackend    stops the backend when the session force-detaches from it ([Watchdog])
      */
     fun runDecorator(
         clientInput: InputStream,
@@ -55,6 +62,8 @@ object DapServer {
         backendInput: InputStream,
         backendOutput: OutputStream,
         interceptor: DapSession.Interceptor = DapSession.Interceptor.PASS_THROUGH,
+        wireObservers: List<WireObserver> = emptyList(),
+        stopBackend: () -> Unit = {},
     ) {
         val session = DapSession(
             clientInput = clientInput,
@@ -62,9 +71,49 @@ object DapServer {
             backendInput = backendInput,
             backendOutput = backendOutput,
             interceptor = interceptor,
+            wireObservers = wireObservers,
+            stopBackend = stopBackend,
         )
         runBlocking {
             session.run()
         }
     }
+
+    /**
+     * A decorator session run on a thread of its own, for a client in the
+     * same process ([DapReplay], [CliDebugger]): the client writes DAP
+     * messages to [toSession] and reads them from [fromSession]. Closing
+     * [toSession] ends the session.
+     */
+    class InProcessSession internal constructor(
+        val toSession: OutputStream,
+        val fromSession: InputStream,
+        private val thread: Thread,
+    ) {
+        /** Waits up to [timeoutMs] for the session to end. */
+        fun join(timeoutMs: Long) = thread.join(timeoutMs)
+    }
+
+    /**
+     * Starts a decorator session in front of [backendInput] and
+     * [backendOutput] for a client in this process, and returns its
+     * client-side streams.
+     */
+    fun runInProcess(
+        backendInput: InputStream,
+        backendOutput: OutputStream,
+        interceptor: DapSession.Interceptor = DapSession.Interceptor.PASS_THROUGH,
+        wireObservers: List<WireObserver> = emptyList(),
+    ): InProcessSession {
+        val toSession = PipedOutputStream()
+        val sessionInput = PipedInputStream(toSession, IN_PROCESS_PIPE_SIZE)
+        val fromSession = PipedInputStream(IN_PROCESS_PIPE_SIZE)
+        val sessionOutput = PipedOutputStream(fromSession)
+        val sessionThread = thread(name = "kdap-in-process-session", isDaemon = true) {
+            runDecorator(sessionInput, sessionOutput, backendInput, backendOutput, interceptor, wireObservers)
+        }
+        return InProcessSession(toSession, fromSession, sessionThread)
+    }
+
+    private const val IN_PROCESS_PIPE_SIZE = 1 shl 16
 }
@@ -1,9 +1,11 @@
 package com.github.jomof.dap
 
+import com.github.jomof.dap.messages.CancelRequest
 import com.github.jomof.dap.messages.DapMessage
 import com.github.jomof.dap.messages.DapRequest
 import com.github.jomof.dap.messages.DapResponse
 import com.github.jomof.dap.messages.OutputEvent
+import com.github.jomof.dap.messages.TerminatedEvent
 import kotlinx.coroutines.*
 import kotlinx.coroutines.channels.Channel
 import kotlinx.coroutines.selects.select
@@ -42,6 +44,50 @@ import java.util.logging.Logger
  * [DapMessage.toJson] is only used for interceptor-created or modified
  * messages.
  *
+ * ## Cancellation
+ *
+ * A [RequestAction.HandleAsync] marked `cancellable` runs as a job keyed
+ * by its request seq. A client `cancel` naming that request (or its
+ * progress, see [requestProgressId]) cancels the job and is answered
+ * here: the cancelled request gets `success: false` with message
+ * `cancelled`, as DAP prescribes, and the backend never sees either
+ * request. Every other `cancel` goes to the interceptor as usual. A
+ * cancelled handler can no longer send messages through its
+ * [AsyncRequestContext].
+ *
+ * ## Concurrency and timeouts
+ *
+ * Requests are pipelined: the client reader never waits for a response.
+ * [RequestAction.HandleAsync] blocks run on [Dispatchers.IO], so a slow
+ * handler (a deep `stackTrace` of one thread, a large `variables` of
+ * another) neither blocks the others nor the readers. `pause`,
+ * `disconnect`, `terminate`, and `cancel` requests ([URGENT_COMMANDS]) go
+ * to the backend ahead of any requests still queued for it, whether
+ * forwarded or sent by a handler.
+ *
+ * A request whose command has a timeout in [requestTimeouts] (by default
+ * [DEFAULT_REQUEST_TIMEOUTS]: `threads`, `stackTrace`, `scopes`,
+ * `variables`, and other reads of a stopped process) that is not answered
+ * in time gets a [DapResponse.timedOut] error response:
+ *
+ * - A forwarded request is cancelled in the backend with a `cancel`
+ *   request (lldb-dap interrupts the work on it, such as symbolication),
+ *   and its late response, if any, is dropped.
+ * - A cancellable [RequestAction.HandleAsync] block is cancelled, as for
+ *   a client `cancel`. Other blocks, which may have lasting side effects,
+ *   are never timed out.
+ *
+ * ## Stalls
+ *
+ * The [watchdog] sees every message. A client request unanswered for
+ * its stall timeout, forwarded or handled by a [RequestAction.HandleAsync]
+ * block, is reported to the client with a console `output` event. While
+ * one is, a client `disconnect` or `terminate`, and a `kdap/forceDetach`
+ * at any time, force-detach: the session answers it, fails every
+ * request not answered yet (cancelling the blocks handling them) and
+ * every request still waiting for the backend, sends `terminated`, calls
+ * [stopBackend], and ends (see [Watchdog]).
+ *
  * ## Shutdown
  *
  * The session terminates when either reader detects EOF (stream closed).
@@ -51,16 +97,30 @@ import java.util.logging.Logger
  * ensuring prompt shutdown even with streams that ignore thread interruption
  * (e.g., `Process.getInputStream()`).
  *
+ * ## Logging
+ *
+ * Each of the [wireObservers] sees every message as it is read from or
+ * written to either side, so it sees the wire as each peer saw it.
+ *
  * ## Thread safety
  *
- * All blocking I/O runs on [Dispatchers.IO]. Communication between
- * coroutines uses [Channel], so no shared mutable state exists.
+ * All blocking I/O runs on [Dispatchers.IO]. Communication between the
+ * readers and writers uses [Channel]. The [interceptor] is called from
+ * both readers, and its [RequestAction.HandleAsync] blocks run in
+ * parallel, so the state they share must be thread-safe. KDAP's
+ * handlers guard the debug session state and Python globals they
+ * read, modify, and write back with `DebugSession.stateLock`, which
+ * lists what it covers.
  *
  * @param clientInput  stream to read DAP messages from the client
  * @param clientOutput stream to write DAP messages to the client
  * @param backendInput  stream to read DAP messages from the backend
  * @param backendOutput stream to write DAP messages to the backend
  * @param interceptor   decides per-request whether to handle locally or forward
+ * @param wireObservers see every message read and written
+ * @param requestTimeouts timeout in milliseconds, by command, of requests that have one
+ * @param watchdog      detects client requests that stall
+ * @param stopBackend   stops the backend when the session force-detaches from it
  */
 class DapSession(
     private val clientInput: InputStream,
@@ -68,7 +128,14 @@ class DapSession(
     private val backendInput: InputStream,
     private val backendOutput: OutputStream,
     private val interceptor: Interceptor = Interceptor.PASS_THROUGH,
+    private val wireObservers: List<WireObserver> = emptyList(),
+    private val requestTimeouts: Map<String, Long> = DEFAULT_REQUEST_TIMEOUTS,
+    private val watchdog: Watchdog = Watchdog(),
+    private val stopBackend: () -> Unit = {},
 ) {
+    /** The [wireObservers] and the [watchdog]. */
+    private val observers: List<WireObserver> = wireObservers + watchdog
+
     /**
      * The result of an [Interceptor] deciding what to do with a client request.
      */
@@ -90,8 +157,13 @@ class DapSession(
          * requests sent by this handler). The block receives the original
          * raw JSON and an [AsyncRequestContext] for sending reverse requests
          * to the client, awaiting responses, and forwarding to the backend.
+         *
+         * A [cancellable] handler is cancelled by a client `cancel` request
+         * for it (see the class KDoc). Only mark handlers with no lasting
+         * side effects: the backend may still be working on their behalf.
          */
         data class HandleAsync(
+            val cancellable: Boolean = false,
             val block: suspend (rawJson: String, ctx: AsyncRequestContext) -> Unit,
         ) : RequestAction()
     }
@@ -145,7 +217,8 @@ class DapSession(
          * `sys.stdout`, which LLDB routes both into the evaluate response's
          * `result` field **and** as separate DAP `output` events. The result
          * field is how we read return values; the output events are unwanted
-         * noise that would pollute the client's event stream.
+         * noise that would pollute the client's event stream. Output
+         * carrying a KDAP marker ([kdapMarker]) is not suppressed.
          */
         suspend fun sendSilentRequestToBackendAndAwait(json: String): DapResponse
 
@@ -249,6 +322,10 @@ class DapSession(
         val toClient = Channel<String>(CHANNEL_CAPACITY)
         val toBackend = Channel<String>(CHANNEL_CAPACITY)
 
+        // Requests that must not wait behind others queued for the backend
+        // (URGENT_COMMANDS); the backend writer takes these first.
+        val toBackendUrgent = Channel<String>(CHANNEL_CAPACITY)
+
         // Seq counter for reverse requests sent to the client.
         val reverseSeq = AtomicInteger(1_000_000)
 
@@ -272,8 +349,9 @@ class DapSession(
 
         // Counter for pending "silent" evaluate requests. When > 0, console
         // output events from the backend are suppressed (not forwarded to the
-        // client). This prevents Python SB API calls from polluting the DAP
-        // event stream with auto-display output.
+        // client), except those carrying a KDAP marker. This prevents Python
+        // SB API calls from polluting the DAP event stream with auto-display
+        // output.
         val pendingSilentRequests = AtomicInteger(0)
 
         // Set of seq numbers belonging to silent evaluate requests. Used by
@@ -296,8 +374,109 @@ class DapSession(
         // (they are consumed before the gate check).
         val eventGate = AtomicReference<ConcurrentLinkedQueue<String>?>(null)
 
+        // Running cancellable HandleAsync jobs, keyed by the seq of the
+        // client request they handle. Entries remove themselves on
+        // completion, so a `cancel` that finds one is answered here.
+        val cancellableRequests = ConcurrentHashMap<Int, CancellableRequest>()
+
+        // Running HandleAsync jobs, keyed by the seq of the client request
+        // they handle, so a force-detach can cancel those it answers.
+        val asyncHandlers = ConcurrentHashMap<Int, Job>()
+
+        // Timers of forwarded client requests that have a timeout, keyed by
+        // request seq. Whoever removes an entry first — the backend reader
+        // on the response, or the timer when it fires — answers the client.
+        val requestTimers = ConcurrentHashMap<Int, Job>()
+
+        // Seqs of forwarded requests answered with a timeout error. Their
+        // late responses from the backend are dropped.
+        val timedOutRequests = ConcurrentHashMap.newKeySet<Int>()
+
+        // Parent of the timers, cancelled when the session ends so that
+        // pending timers don't hold it open.
+        val timers = SupervisorJob(coroutineContext[Job])
+
+        // Starts the timer of a forwarded client request, if its command
+        // has a timeout. See the class KDoc.
+        fun startRequestTimer(request: DapRequest) {
+            val timeout = requestTimeouts[request.command] ?: return
+            val requestSeq = request.seq
+            val timer = launch(timers, start = CoroutineStart.LAZY) {
+                delay(timeout)
+                withContext(NonCancellable) {
+                    // Marked before the timer is removed, so that a response
+                    // arriving in between is dropped rather than forwarded.
+                    timedOutRequests.add(requestSeq)
+                    if (requestTimers.remove(requestSeq) == null) {
+                        timedOutRequests.remove(requestSeq)
+                        return@withContext
+                    }
+                    log.warning { "'${request.command}' (seq $requestSeq) timed out after $timeout ms" }
+                    toClient.send(DapResponse.timedOut(requestSeq, request.command, timeout).toJson())
+                    val cancelSeq = backendSeq.getAndIncrement()
+                    // The backend's answer to the cancel is consumed here.
+                    pendingBackendResponses[cancelSeq] = CompletableDeferred()
+                    val cancel = JSONObject()
+                        .put("seq", cancelSeq)
+                        .put("type", "request")
+                        .put("command", "cancel")
+                        .put("arguments", JSONObject().put("requestId", requestSeq))
+                    toBackendUrgent.send(cancel.toString())
+                }
+            }
+            requestTimers[requestSeq] = timer
+            timer.start()
+        }
+
+        // Sends a request to the backend, urgent ones first.
+        suspend fun sendToBackend(json: String, command: String) {
+            if (command in URGENT_COMMANDS) toBackendUrgent.send(json) else toBackend.send(json)
+        }
+
+        // Sends the client request, as json, to the backend and starts its timer.
+        suspend fun forward(request: DapRequest, json: String) {
+            startRequestTimer(request)
+            sendToBackend(json, request.command)
+        }
+
+        // Reports stalled client requests to the client. See [Watchdog].
+        launch(timers) {
+            while (true) {
+                delay(Watchdog.POLL_MS)
+                watchdog.check()?.let { diagnostic ->
+                    log.warning { diagnostic }
+                    toClient.send(OutputEvent.console("$diagnostic\n").toJson())
+                }
+            }
+        }
+
+        // Completed when the session force-detached from the backend.
+        val detached = CompletableDeferred<Unit>()
+
+        // Answers the recovery request, fails the client requests not
+        // answered yet and the requests waiting for the backend, and ends
+        // the session without it. See [Watchdog].
+        suspend fun forceDetach(request: DapRequest) {
+            log.warning { "'${request.command}' (seq ${request.seq}): force-detaching from lldb-dap" }
+            for (unanswered in watchdog.drain().filter { it.seq != request.seq }) {
+                asyncHandlers.remove(unanswered.seq)?.cancel()
+                requestTimers.remove(unanswered.seq)?.cancel()
+                if (!timedOutRequests.remove(unanswered.seq)) {
+                    toClient.send(DapResponse.error(unanswered.seq, unanswered.command, "lldb-dap stalled").toJson())
+                }
+            }
+            for (seq in pendingBackendResponses.keys.toList()) {
+                pendingBackendResponses.remove(seq)?.complete(DapResponse.error(seq, "", "lldb-dap stalled"))
+            }
+            toClient.send(DapResponse(seq = 0, requestSeq = request.seq, command = request.command, success = true).toJson())
+            toClient.send(TerminatedEvent(seq = 0).toJson())
+            detached.complete(Unit)
+            runCatching { stopBackend() }.onFailure { log.warning { "stopBackend failed: ${it.message}" } }
+        }
+
         val asyncCtx = object : AsyncRequestContext {
             override suspend fun sendReverseRequest(json: String): Int {
+                currentCoroutineContext().ensureActive()
                 val seq = reverseSeq.getAndIncrement()
                 // Inject our assigned seq into the JSON
                 val obj = JSONObject(json)
@@ -315,24 +494,28 @@ class DapSession(
             }
 
             override suspend fun forwardToBackend(json: String) {
-                toBackend.send(json)
+                currentCoroutineContext().ensureActive()
+                sendToBackend(json, JSONObject(json).optString("command"))
             }
 
             override suspend fun sendEventToClient(json: String) {
+                currentCoroutineContext().ensureActive()
                 toClient.send(json)
             }
 
             override suspend fun sendRequestToBackendAndAwait(json: String): DapResponse {
+                currentCoroutineContext().ensureActive()
                 val seq = backendSeq.getAndIncrement()
                 val obj = JSONObject(json)
                 obj.put("seq", seq)
                 val deferred = CompletableDeferred<DapResponse>()
                 pendingBackendResponses[seq] = deferred
-                toBackend.send(obj.toString())
+                sendToBackend(obj.toString(), obj.optString("command"))
                 return deferred.await()
             }
 
             override suspend fun sendSilentRequestToBackendAndAwait(json: String): DapResponse {
+                currentCoroutineContext().ensureActive()
                 val seq = backendSeq.getAndIncrement()
                 val obj = JSONObject(json)
                 obj.put("seq", seq)
@@ -386,10 +569,10 @@ class DapSession(
             }
         }
 
-        val clientWriterJob = launchWriter("clientWriter", toClient, clientOutput)
-        val backendWriterJob = launchWriter("backendWriter", toBackend, backendOutput)
+        val clientWriterJob = launchWriter("clientWriter", toClient, clientOutput, WireDirection.ToClient)
+        val backendWriterJob = launchWriter("backendWriter", toBackend, backendOutput, WireDirection.ToBackend, toBackendUrgent)
 
-        val backendReaderJob = launchReader("backendReader", backendInput) { rawJson ->
+        val backendReaderJob = launchReader("backendReader", backendInput, WireDirection.FromBackend) { rawJson ->
             val message = DapMessage.parse(rawJson)
 
             // Route responses to pending sendRequestToBackendAndAwait callers.
@@ -406,6 +589,14 @@ class DapSession(
                     deferred.complete(message)
                     return@launchReader
                 }
+                // A client request's response: stop its timer, or drop the
+                // response if the client already got a timeout error.
+                val timer = requestTimers.remove(message.requestSeq)
+                timer?.cancel()
+                if (timer == null && timedOutRequests.remove(message.requestSeq)) {
+                    log.fine { "backendReader: dropped late response to '${message.command}' (seq ${message.requestSeq})" }
+                    return@launchReader
+                }
             }
 
             // Suppress console output events BEFORE the interceptor chain.
@@ -414,9 +605,14 @@ class DapSession(
             // 2. OutputCoalescingHandler may buffer the reclassified event
             //    and flush it on a later message when suppression is inactive
             // By suppressing early, the chain never sees auto-display noise.
+            // Output carrying a KDAP marker (see OutputMarkers.kt) is never
+            // noise: it is debuggee output or a record KDAP's Python wrote,
+            // possibly while another handler's SB call was in flight, so it
+            // is always dispatched.
             if (pendingSilentRequests.get() > 0
                 && message is OutputEvent
                 && message.category == "console"
+                && KDAP_MARKER_PREFIX !in message.output
             ) {
                 log.fine { "backendReader: suppressed console output during silent evaluate: ${message.output.take(80)}" }
                 // Still apply deferred decrements
@@ -446,7 +642,7 @@ class DapSession(
             if (n > 0) pendingSilentRequests.addAndGet(-n)
         }
 
-        val clientReaderJob = launchReader("clientReader", clientInput) { rawJson ->
+        val clientReaderJob = launchReader("clientReader", clientInput, WireDirection.FromClient) { rawJson ->
             val message = DapMessage.parse(rawJson)
             when (message) {
                 is DapResponse -> {
@@ -463,19 +659,56 @@ class DapSession(
                 is DapRequest -> {
                     // Check if an async handler is waiting for this command.
                     val interceptionDeferred = pendingClientInterceptions.remove(message.command)
-                    if (interceptionDeferred != null) {
+                    val cancelled = (message as? CancelRequest)?.let { cancel ->
+                        val requestSeq = cancel.requestId
+                            ?: cancel.progressId?.let { progressRequestSeq(it) }
+                        requestSeq?.let { cancellableRequests.remove(it) }
+                    }
+                    if (message.command in Watchdog.RECOVERY_COMMANDS &&
+                        (message.command == Watchdog.FORCE_DETACH_COMMAND || watchdog.isStalled)
+                    ) {
+                        forceDetach(message)
+                    } else if (interceptionDeferred != null) {
                         interceptionDeferred.complete(rawJson)
+                    } else if (cancelled != null) {
+                        // Cancel a KDAP handler: the backend never saw its request.
+                        cancelled.job.cancel()
+                        toClient.send(DapResponse.error(cancelled.requestSeq, cancelled.command, "cancelled").toJson())
+                        toClient.send(
+                            DapResponse(seq = 0, requestSeq = message.seq, command = "cancel", success = true).toJson()
+                        )
                     } else {
                         when (val action = interceptor.onRequest(message)) {
-                            is RequestAction.Forward -> toBackend.send(rawJson)
+                            is RequestAction.Forward -> forward(message, rawJson)
                             is RequestAction.Respond -> toClient.send(action.response.toJson())
-                            is RequestAction.ForwardModified -> toBackend.send(action.modifiedRequest.toJson())
+                            is RequestAction.ForwardModified ->
+                                forward(action.modifiedRequest, action.modifiedRequest.toJson())
                             is RequestAction.HandleAsync -> {
                                 // Launch async handler as a child coroutine so the
-                                // client reader continues processing messages.
-                                launch {
-                                    action.block(rawJson, asyncCtx)
+                                // client reader continues processing messages, on
+                                // the IO pool so that handlers run in parallel.
+                                val requestSeq = message.seq
+                                val timeout = requestTimeouts[message.command]?.takeIf { action.cancellable }
+                                val job = launch(Dispatchers.IO + CoroutineName(message.command), CoroutineStart.LAZY) {
+                                    if (timeout == null) {
+                                        action.block(rawJson, asyncCtx)
+                                    } else if (withTimeoutOrNull(timeout) { action.block(rawJson, asyncCtx) } == null &&
+                                        cancellableRequests.remove(requestSeq) != null
+                                    ) {
+                                        log.warning { "'${message.command}' (seq $requestSeq) timed out after $timeout ms" }
+                                        // In case the handler forwarded the request itself.
+                                        timedOutRequests.add(requestSeq)
+                                        toClient.send(DapResponse.timedOut(requestSeq, message.command, timeout).toJson())
+                                    }
+                                }
+                                asyncHandlers[requestSeq] = job
+                                job.invokeOnCompletion { asyncHandlers.remove(requestSeq, job) }
+                                if (action.cancellable) {
+                                    cancellableRequests[requestSeq] =
+                                        CancellableRequest(requestSeq, message.command, job)
+                                    job.invokeOnCompletion { cancellableRequests.remove(requestSeq) }
                                 }
+                                job.start()
                             }
                         }
                     }
@@ -492,11 +725,14 @@ class DapSession(
         select {
             clientReaderJob.onJoin {}
             backendReaderJob.onJoin {}
+            detached.onAwait {}
         }
+        timers.cancel()
 
         // Close channels so writers drain remaining messages.
         toClient.close()
         toBackend.close()
+        toBackendUrgent.close()
 
         // Wait for writers to finish draining their channels.
         clientWriterJob.join()
@@ -517,9 +753,9 @@ class DapSession(
     }
 
     /**
-     * Launches a coroutine that reads DAP messages from [input] and calls
-     * [onMessage] for each one. Returns when the stream reaches EOF or an
-     * I/O error occurs.
+     * Launches a coroutine that reads DAP messages from [input], logs each
+     * as coming [direction], and calls [onMessage] for it. Returns when
+     * the stream reaches EOF or an I/O error occurs.
      *
      * Uses [runInterruptible] so that coroutine cancellation interrupts the
      * blocking [InputStream.read] call, allowing prompt shutdown when the
@@ -528,11 +764,13 @@ class DapSession(
     private fun CoroutineScope.launchReader(
         name: String,
         input: InputStream,
+        direction: WireDirection,
         onMessage: suspend (String) -> Unit,
     ): Job = launch(Dispatchers.IO + CoroutineName(name)) {
         try {
             while (isActive) {
                 val message = runInterruptible { DapFraming.readMessage(input) } ?: break
+                observers.forEach { it.message(direction, message) }
                 onMessage(message)
             }
         } catch (e: IOException) {
@@ -546,17 +784,32 @@ class DapSession(
 
     /**
      * Launches a coroutine that drains [channel] and writes each message
-     * to [output] using DAP framing. Exits when the channel is closed
-     * and all buffered messages have been written.
+     * to [output] using DAP framing, logging it as going [direction].
+     * Messages in [urgent], if given, are written ahead of those waiting in
+     * [channel]. Exits when the channels are closed and all buffered
+     * messages have been written.
      */
     private fun CoroutineScope.launchWriter(
         name: String,
         channel: Channel<String>,
         output: OutputStream,
+        direction: WireDirection,
+        urgent: Channel<String>? = null,
     ): Job = launch(Dispatchers.IO + CoroutineName(name)) {
+        // The next message, urgent first; `null` once both channels are closed and empty.
+        suspend fun next(): String? {
+            if (urgent == null) return channel.receiveCatching().getOrNull()
+            urgent.tryReceive().getOrNull()?.let { return it }
+            return select {
+                urgent.onReceiveCatching { it.getOrNull() ?: channel.receiveCatching().getOrNull() }
+                channel.onReceiveCatching { it.getOrNull() ?: urgent.receiveCatching().getOrNull() }
+            }
+        }
         try {
-            for (message in channel) {
+            while (true) {
+                val message = next() ?: break
                 DapFraming.writeMessage(output, message)
+                observers.forEach { it.message(direction, message) }
             }
         } catch (e: IOException) {
             log.fine { "$name: write failed (${e.message})" }
@@ -566,6 +819,9 @@ class DapSession(
         log.fine { "$name: exiting" }
     }
 
+    /** A running cancellable [RequestAction.HandleAsync] job. */
+    private class CancellableRequest(val requestSeq: Int, val command: String, val job: Job)
+
     /** Closes [stream] ignoring any IOException (stream may already be closed). */
     private fun closeQuietly(stream: OutputStream, name: String) {
         try {
@@ -593,6 +849,40 @@ class DapSession(
          */
         private const val CHANNEL_CAPACITY = 64
 
+        private const val REQUEST_PROGRESS_PREFIX = "kdap/request/"
+
+        /** Default timeout of the requests in [DEFAULT_REQUEST_TIMEOUTS]. */
+        const val DEFAULT_REQUEST_TIMEOUT_MS = 30_000L
+
+        /**
+         * Requests that read a stopped process, which may wait for
+         * symbolication or a large value but never need to run it, with
+         * their timeouts.
+         */
+        val DEFAULT_REQUEST_TIMEOUTS: Map<String, Long> = listOf(
+            "threads", "stackTrace", "scopes", "variables", "source", "loadedSources", "modules",
+            "disassemble", "readMemory", "exceptionInfo", "completions", "stepInTargets", "gotoTargets",
+        ).associateWith { DEFAULT_REQUEST_TIMEOUT_MS }
+
+        /**
+         * Forwarded requests that go to the backend ahead of others queued
+         * for it, so that slow requests can't hold them up.
+         */
+        val URGENT_COMMANDS = setOf("pause", "disconnect", "terminate", "cancel")
+
+        /**
+         * The `progressId` for progress reported on behalf of the client
+         * request [requestSeq]. Cancelling that progress cancels the
+         * request, if its handler is cancellable.
+         */
+        fun requestProgressId(requestSeq: Int): String = "$REQUEST_PROGRESS_PREFIX$requestSeq"
+
+        /** The request seq in a [requestProgressId], or `null` for other progress. */
+        internal fun progressRequestSeq(progressId: String): Int? =
+            progressId.removePrefix(REQUEST_PROGRESS_PREFIX)
+                .takeIf { progressId.startsWith(REQUEST_PROGRESS_PREFIX) }
+                ?.toIntOrNull()
+
         /** Drains all messages from [queue] into [channel]. */
         private suspend fun drainGateQueue(
             queue: ConcurrentLinkedQueue<String>,
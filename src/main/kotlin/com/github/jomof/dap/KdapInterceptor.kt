@@ -29,6 +29,27 @@ import com.github.jomof.dap.messages.DapRequest
  * - [AttachHandler] — handles attach request via [DebugSession]
  * - [DisconnectHandler] — handles disconnect request via [DebugSession]
  * - [TerminateHandler] — handles terminate request via [DebugSession]
+ * - [StdinHandler] — writes `kdap/stdin` text to the debuggee's stdin
+ * - [SignalsHandler] — changes signal dispositions for `kdap/signals`
+ * - [MemorySearchHandler] — searches the debuggee's memory for `kdap/searchMemory`
+ * - [MemoryMapHandler] — lists the debuggee's memory regions for `kdap/memoryMap`
+ * - [HeapHandler] — answers `kdap/heap` from tracked allocations
+ * - [HotReplaceHandler] — replaces functions from a rebuilt object for `kdap/hotReplace`
+ * - [BreakpointFileHandler] — saves and loads breakpoint files for `kdap/breakpoints`
+ * - [LoadSymbolsHandler] — adds a symbol file to a module for `kdap/loadSymbols`
+ * - [ModulesHandler] — `module` events, `modules`, and `loadedSources`
+ * - [ThreadsHandler] — names threads in `threads`; `thread` events
+ * - [BreakpointsHandler] — applies KDAP breakpoint behavior to source, function, and instruction breakpoints
+ * - [DataBreakpointsHandler] — falls back to software watchpoints for setDataBreakpoints
+ * - [ExceptionBreakpointsHandler] — adds Rust panic and signal exception filters
+ * - [SteppingHandler] — steps by instruction in frames without source; single-thread continue
+ * - [AsyncTasksHandler] — lists tokio tasks as pseudo-threads with await stacks
+ * - [StackTraceHandler] — marks inlined frames in stack traces
+ * - [SourceHandler] — serves sources not on disk for `source`
+ * - [VariablesHandler] — adds memory references to pointer-like variables;
+ *   writes values for `setVariable` and `setExpression`
+ * - [EvaluateHandler] — simple expressions, assignments, and console meta-commands
+ * - [ScriptingHandler] — runs the `kdap.init` script's stop hooks
  * - [EvaluateContextRewriter] — rewrites CodeLLDB `_command` to `repl`
  * - [OutputCategoryNormalizer] — reclassifies debuggee console output as stdout
  * - [ExitStatusHandler] — reformats process exit output to match CodeLLDB
@@ -71,6 +92,26 @@ class KdapInterceptor(
                 AttachHandler(session),            // handles attach request
                 DisconnectHandler(session),        // handles disconnect request
                 TerminateHandler(session),         // handles terminate request
+                StdinHandler(session),             // kdap/stdin → debuggee stdin
+                SignalsHandler(session),           // kdap/signals → signal dispositions
+                MemorySearchHandler(session),      // kdap/searchMemory → matching addresses
+                MemoryMapHandler(session),         // kdap/memoryMap → memory regions
+                HeapHandler(session),              // kdap/heap → tracked allocations
+                HotReplaceHandler(session),        // kdap/hotReplace → patched functions
+                BreakpointFileHandler(session),    // kdap/breakpoints → breakpoint files
+                LoadSymbolsHandler(session),       // kdap/loadSymbols → module symbol files
+                ModulesHandler(session),           // module events, modules, loadedSources
+                ThreadsHandler(session),           // thread names and events
+                BreakpointsHandler(session),       // KDAP conditions/hit counts/logpoints
+                DataBreakpointsHandler(session),   // software watchpoint fallback
+                ExceptionBreakpointsHandler(session), // rust_panic and signal filters
+                SteppingHandler(session),          // instruction stepping, single-thread continue
+                AsyncTasksHandler(session),        // tokio tasks as pseudo-threads
+                StackTraceHandler(session),        // inlined frames in stackTrace
+                SourceHandler(session),            // fetched sources for source
+                VariablesHandler(session),         // memory references, setVariable/setExpression
+                EvaluateHandler(session),          // simple expressions and REPL input
+                ScriptingHandler(session),         // kdap.init stop hooks
                 EvaluateContextRewriter(),         // rewrites CodeLLDB _command → repl
                 OutputCategoryNormalizer(session),  // reclassifies debuggee console → stdout
                 ExitStatusHandler(),               // reformats exit output to match CodeLLDB
@@ -0,0 +1,19 @@
+package com.github.jomof.dap
+
+/**
+ * Markers on records KDAP writes to LLDB's output stream from Python
+ * (logpoint messages, debuggee output copied from a pty, listener
+ * records). lldb-dap relays them as `console` output events, and KDAP's
+ * handlers tell them apart from other console output by their marker.
+ *
+ * A marker is `kdap-<name>` set off by ASCII record separators
+ * (`\u001E`), which real console output does not contain, so a marker
+ * can't collide with it. [DapSession] forwards marked output even while
+ * it suppresses the console output of silent SB API calls.
+ */
+
+/** What every [kdapMarker] starts with. */
+internal const val KDAP_MARKER_PREFIX = "\u001Ekdap-"
+
+/** The marker named [name] (see the file header). */
+internal fun kdapMarker(name: String): String = "$KDAP_MARKER_PREFIX$name\u001E"
@@ -0,0 +1,184 @@
+package com.github.jomof.dap
+
+import org.json.JSONObject
+import java.io.File
+import java.io.Writer
+import java.time.Instant
+import java.util.concurrent.ConcurrentHashMap
+
+/**
+ * Structured adapter log: every DAP message KDAP reads or writes, and
+ * every SB API call, as one JSON object per line.
+ *
+ * Enabled with `--log <path>` or the `KDAP_LOG` environment variable
+ * (see [Cli]). Each record has the wall-clock `time` (ISO-8601, in
+ * milliseconds) and a `category`:
+ *
+ * | Category  | Records                                                  |
+ * |-----------|----------------------------------------------------------|
+ * | `client`  | messages between the client and KDAP                     |
+ * | `backend` | messages between KDAP and lldb-dap, including its events |
+ * | `sb`      | SB API calls made through [SBWatcher]                    |
+ *
+ * Message records carry the `direction` ([WireDirection]), the message's
+ * `type`, `command` or `event`, `seq`, and the whole `message`. A
+ * response also has the `durationMs` since the request it answers went
+ * the other way, so slow requests stand out. `--log-categories` (or
+ * `KDAP_LOG_CATEGORIES`) keeps only the categories it lists. The values
+ * of the `env` of `launch`, `attach`, and `restart` requests are
+ * replaced with [REDACTED], since they often hold credentials.
+ *
+ * ## Rotation
+ *
+ * When a record would take the file past [maxBytes], the file is renamed
+ * to `<path>.1` (shifting older files up to `<path>.<keep>`, and dropping
+ * the oldest) and a new one is started. A non-empty file left by an
+ * earlier session is rotated the same way on start, so each session
+ * begins a file of its own.
+ *
+ * Thread-safe: records are written under a lock, each flushed at once so
+ * the log is complete when KDAP dies.
+ *
+ * @param file the log file
+ * @param categories the categories to record
+ * @param maxBytes size at which the file is rotated
+ * @param keep how many rotated files are kept
+ * @param clock the time records are stamped with
+ */
+class ProtocolLog(
+    private val file: File,
+    private val categories: Set<Category> = Category.entries.toSet(),
+    private val maxBytes: Long = DEFAULT_MAX_BYTES,
+    private val keep: Int = DEFAULT_KEEP,
+    private val clock: () -> Instant = Instant::now,
+) : SBWatcher, WireObserver {
+
+    /** What a record is about (see the class KDoc). */
+    enum class Category {
+        Client, Backend, Sb;
+
+        val id: String get() = name.lowercase()
+
+        companion object {
+            fun fromId(id: String): Category? = entries.find { it.id == id.trim().lowercase() }
+        }
+    }
+
+    private val lock = Any()
+    private var writer: Writer
+    private var size = 0L
+
+    /** When each request in flight was seen, by its direction and seq. */
+    private val requestTimes = ConcurrentHashMap<Pair<WireDirection, Int>, Long>()
+
+    init {
+        file.absoluteFile.parentFile?.mkdirs()
+        if (file.length() > 0) rotate()
+        writer = file.bufferedWriter()
+    }
+
+    /** Records the DAP message [json] sent in [direction]. */
+    override fun message(direction: WireDirection, json: String) {
+        val category = if (direction.isClient) Category.Client else Category.Backend
+        if (category !in categories) return
+        val now = System.nanoTime()
+        val record = JSONObject()
+            .put("direction", direction.label)
+        try {
+            val message = JSONObject(json)
+            val type = message.optString("type")
+            record.put("type", type)
+            message.opt("command")?.let { record.put("command", it) }
+            message.opt("event")?.let { record.put("event", it) }
+            message.opt("seq")?.let { record.put("seq", it) }
+            when (type) {
+                "request" -> requestTimes[direction to message.optInt("seq")] = now
+                "response" -> {
+                    record.put("request_seq", message.opt("request_seq"))
+                    record.put("success", message.optBoolean("success"))
+                    requestTimes.remove(direction.reverse to message.optInt("request_seq"))?.let { start ->
+                        record.put("durationMs", (now - start) / 1_000_000.0)
+                    }
+                }
+            }
+            if (type == "request" && message.optString("command") in REDACTED_COMMANDS) redactEnv(message)
+            record.put("message", message)
+        } catch (_: Exception) {
+            record.put("raw", json)
+        }
+        write(category, record)
+    }
+
+    override fun onCall(interfaceName: String, methodName: String, args: String, result: String) {
+        if (Category.Sb !in categories) return
+        write(Category.Sb, JSONObject()
+            .put("interface", interfaceName)
+            .put("method", methodName)
+            .put("args", args)
+            .put("result", result))
+    }
+
+    /** Wire traffic is recorded through [message], as [DapSession] reports it. */
+    override fun onMessage(direction: String, message: String) = Unit
+
+    /** Closes the log file. */
+    fun close() {
+        synchronized(lock) { writer.close() }
+    }
+
+    private fun write(category: Category, record: JSONObject) {
+        val line = record.put("time", clock().toString()).put("category", category.id).toString()
+        val bytes = line.toByteArray(Charsets.UTF_8).size + 1L
+        synchronized(lock) {
+            if (size > 0 && size + bytes > maxBytes) {
+                writer.close()
+                rotate()
+                writer = file.bufferedWriter()
+                size = 0
+            }
+            writer.write(line)
+            writer.write("\n")
+            writer.flush()
+            size += bytes
+        }
+    }
+
+    /** Replaces the values of every `env` object in [obj], at any depth, with [REDACTED]. */
+    private fun redactEnv(obj: JSONObject) {
+        for (key in obj.keySet()) {
+            val value = obj.get(key)
+            if (value !is JSONObject) continue
+            if (key == "env") value.keySet().forEach { value.put(it, REDACTED) } else redactEnv(value)
+        }
+    }
+
+    /** Shifts `<path>.N` to `<path>.N+1`, dropping the oldest, and moves the file to `<path>.1`. */
+    private fun rotate() {
+        if (keep <= 0) {
+            file.delete()
+            return
+        }
+        rotated(keep).delete()
+        for (n in keep - 1 downTo 1) {
+            val older = rotated(n)
+            if (older.exists()) older.renameTo(rotated(n + 1))
+        }
+        file.renameTo(rotated(1))
+    }
+
+    private fun rotated(n: Int) = File(file.path + ".$n")
+
+    companion object {
+        /** Size at which the log is rotated unless told otherwise: 10 MiB. */
+        const val DEFAULT_MAX_BYTES = 10L * 1024 * 1024
+
+        /** Rotated files kept unless told otherwise. */
+        const val DEFAULT_KEEP = 5
+
+        /** What logged environment variable values are replaced with. */
+        const val REDACTED = "<redacted>"
+
+        /** Requests whose `env` is redacted. */
+        private val REDACTED_COMMANDS = setOf("launch", "attach", "restart")
+    }
+}
@@ -16,6 +16,8 @@ package com.github.jomof.dap
  *
  * Register via `--sb-log <file>` CLI flag, which installs a
  * [FileSBWatcher] that writes each call as a single line to the file.
+ * `--log <file>` records the calls in the [ProtocolLog] as well; [of]
+ * combines the two.
  *
  * @see com.github.jomof.dap.sb.watched
  * @see FileSBWatcher
@@ -39,4 +41,19 @@ interface SBWatcher {
      * @param message   the raw DAP message JSON or summary
      */
     fun onMessage(direction: String, message: String)
+
+    companion object {
+        /** A watcher reporting to each of [watchers], or null if there are none. */
+        fun of(watchers: List<SBWatcher>): SBWatcher? = when (watchers.size) {
+            0 -> null
+            1 -> watchers.single()
+            else -> object : SBWatcher {
+                override fun onCall(interfaceName: String, methodName: String, args: String, result: String) =
+                    watchers.forEach { it.onCall(interfaceName, methodName, args, result) }
+
+                override fun onMessage(direction: String, message: String) =
+                    watchers.forEach { it.onMessage(direction, message) }
+            }
+        }
+    }
 }
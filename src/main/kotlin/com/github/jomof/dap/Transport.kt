@@ -6,15 +6,29 @@ import java.net.InetAddress
 import java.net.InetSocketAddress
 import java.net.ServerSocket
 import java.net.Socket
+import java.net.StandardProtocolFamily
+import java.net.UnixDomainSocketAddress
+import java.nio.channels.Channels
+import java.nio.channels.ServerSocketChannel
+import java.nio.file.Files
+import java.nio.file.Path
+import java.util.logging.Logger
 
 /**
  * Transport for DAP: provides an [InputStream] and [OutputStream] over which
  * the server runs. Aligns with CodeLLDB's modes: stdio, listen (--port), connect (--connect).
+ *
+ * KDAP adds a Unix domain socket listener (--socket), and server mode
+ * (--server): a listener that, instead of exiting after its one
+ * connection, accepts the next when a session ends, so an editor that
+ * reconnects finds KDAP already running. Sessions are served one at a
+ * time; a connection made during a session waits in the listen backlog.
  */
 sealed class Transport {
 
     /**
      * Runs [block] with the transport's input and output streams. Resources are closed after [block].
+     * A [server] listener runs [block] once per connection, until it is interrupted.
      */
     abstract fun run(block: (InputStream, OutputStream) -> Unit)
 
@@ -33,22 +47,61 @@ sealed class Transport {
      * (or test harnesses) can discover it without a TOCTOU race.
      *
      * `SO_REUSEADDR` is set to handle TIME_WAIT overlap from previous runs.
+     *
+     * With [server], accepts connection after connection (see [Transport]).
      */
     data class TcpListen(
         val port: Int,
         /** Called with the actual bound port after the server socket is listening. */
         val onBound: (Int) -> Unit = {},
+        /** Whether to serve session after session instead of one. */
+        val server: Boolean = false,
+    ) : Transport() {
+        override fun run(block: (InputStream, OutputStream) -> Unit) {
+            val serverSocket = ServerSocket()
+            serverSocket.reuseAddress = true
+            serverSocket.bind(InetSocketAddress(InetAddress.getLoopbackAddress(), port), if (server) BACKLOG else 1)
+            onBound(serverSocket.localPort)
+            serverSocket.use {
+                do {
+                    val socket = serverSocket.accept()
+                    serveSession(server) {
+                        socket.use { block(socket.getInputStream(), socket.getOutputStream()) }
+                    }
+                } while (server && !Thread.currentThread().isInterrupted)
+            }
+        }
+    }
+
+    /**
+     * DAP over a Unix domain socket: listen at [path], accept one
+     * connection (or, with [server], connection after connection), then
+     * use that socket. A stale socket file at [path] is replaced, and the
+     * file is removed when the listener closes.
+     */
+    data class UnixListen(
+        val path: String,
+        /** Called with the socket path after the server socket is listening. */
+        val onBound: (String) -> Unit = {},
+        /** Whether to serve session after session instead of one. */
+        val server: Boolean = false,
     ) : Transport() {
         override fun run(block: (InputStream, OutputStream) -> Unit) {
-            val server = ServerSocket()
-            server.reuseAddress = true
-            server.bind(InetSocketAddress(InetAddress.getLoopbackAddress(), port), 1)
-            onBound(server.localPort)
-            server.use {
-                val socket = server.accept()
-                socket.use {
-                    block(socket.getInputStream(), socket.getOutputStream())
+            val socketPath = Path.of(path)
+            Files.deleteIfExists(socketPath)
+            try {
+                ServerSocketChannel.open(StandardProtocolFamily.UNIX).use { channel ->
+                    channel.bind(UnixDomainSocketAddress.of(socketPath), if (server) BACKLOG else 1)
+                    onBound(path)
+                    do {
+                        val socket = channel.accept()
+                        serveSession(server) {
+                            socket.use { block(Channels.newInputStream(socket), Channels.newOutputStream(socket)) }
+                        }
+                    } while (server && !Thread.currentThread().isInterrupted)
                 }
+            } finally {
+                Files.deleteIfExists(socketPath)
             }
         }
     }
@@ -62,4 +115,23 @@ sealed class Transport {
         }
     }
 
+    private companion object {
+        /** Connections a server-mode listener queues while it serves a session. */
+        const val BACKLOG = 16
+
+        val log: Logger = Logger.getLogger(Transport::class.java.name)
+
+        /**
+         * Runs one [session]. In [server] mode a session that fails is
+         * logged rather than thrown, so it does not end the server.
+         */
+        inline fun serveSession(server: Boolean, session: () -> Unit) {
+            if (!server) return session()
+            try {
+                session()
+            } catch (e: Exception) {
+                log.warning { "Transport: session failed: ${e.message}" }
+            }
+        }
+    }
 }
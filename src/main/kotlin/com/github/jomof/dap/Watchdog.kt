@@ -0,0 +1,176 @@
+package com.github.jomof.dap
+
+import org.json.JSONObject
+import java.io.File
+import java.util.concurrent.ConcurrentHashMap
+
+/**
+ * Hang detection for requests to the backend. KDAP extension; CodeLLDB
+ * and lldb-dap wait for LLDB as long as it takes.
+ *
+ * LLDB runs the debuggee through ptrace and `waitpid`. When the debuggee
+ * can't be stopped or reaped (a zombie whose parent doesn't reap it, a
+ * process in uninterruptible sleep, a child that died under a `vfork`),
+ * lldb-dap blocks in LLDB and answers nothing, and every request behind
+ * the stuck one, `disconnect` included, waits with it.
+ *
+ * As a [WireObserver] of the session, the watchdog times each client
+ * request from its arrival until its response goes to the client,
+ * whether KDAP forwards it to the backend or handles it with SB API
+ * calls of its own, and records the debuggee's pid from the `process`
+ * event sent to the client. The requests KDAP sends the backend are
+ * timed through the client request they serve; one that serves none (a
+ * listener's, the symbol index warm-up's) is not watched, so it never
+ * makes a `disconnect` force-detach and leave the debuggee running.
+ * [DapSession] polls [check] every [POLL_MS]: once a request has gone
+ * unanswered for [stallTimeoutMs], the client is told in the console
+ * which request it is, for how long, and what state the debuggee is in
+ * ([describeProcess], read from `/proc` on Linux), once per stalled
+ * request. Requests that may rightly take unbounded time
+ * ([EXEMPT_COMMANDS]: `attach` waiting for a process, `launch`) are not
+ * watched.
+ *
+ * While a request is stalled, `disconnect`, `terminate`, and the custom
+ * `kdap/forceDetach` request (which works at any time) force-detach: the
+ * session answers them itself, fails the requests not answered yet
+ * (cancelling KDAP's handlers of them), sends `terminated`, and ends
+ * without waiting for lldb-dap, which is then stopped. Ending lldb-dap
+ * drops its ptrace attachment, so the debuggee is released rather than
+ * killed.
+ */
+class Watchdog(
+    /** How long a request may go unanswered before it is reported as stalled; 0 disables the watchdog. */
+    private val stallTimeoutMs: Long = DEFAULT_STALL_TIMEOUT_MS,
+    /** Reads the state of a process by pid (see [processState]); replaced in tests. */
+    private val stateOf: (Long) -> ProcessState? = { processState(it) },
+    /** The current time in milliseconds; replaced in tests. */
+    private val clock: () -> Long = System::currentTimeMillis,
+) : WireObserver {
+
+    /** A client request not answered yet. */
+    data class Pending(val seq: Int, val command: String, val sentAt: Long)
+
+    /** A process state from `/proc/<pid>/stat`, and the pids of its children. */
+    data class ProcessState(val state: Char, val children: Map<Long, Char> = emptyMap())
+
+    private val pending = ConcurrentHashMap<Int, Pending>()
+    private val reported = ConcurrentHashMap.newKeySet<Int>()
+
+    /** The debuggee's pid, from the last `process` event sent to the client. */
+    @Volatile
+    var debuggeePid: Long? = null
+        private set
+
+    override fun message(direction: WireDirection, json: String) {
+        if (stallTimeoutMs <= 0) return
+        val obj = try { JSONObject(json) } catch (_: Exception) { return }
+        when (direction) {
+            WireDirection.FromClient -> if (obj.optString("type") == "request") {
+                val seq = obj.optInt("seq")
+                val command = obj.optString("command")
+                if (command !in EXEMPT_COMMANDS) pending[seq] = Pending(seq, command, clock())
+            }
+            WireDirection.ToBackend, WireDirection.FromBackend -> Unit
+            WireDirection.ToClient -> when {
+                obj.optString("type") == "response" -> {
+                    pending.remove(obj.optInt("request_seq"))
+                    reported.remove(obj.optInt("request_seq"))
+                }
+                obj.optString("type") == "event" && obj.optString("event") == "process" ->
+                    obj.optJSONObject("body")?.takeIf { it.has("systemProcessId") }?.let { debuggeePid = it.optLong("systemProcessId") }
+            }
+        }
+    }
+
+    /** The requests unanswered for longer than the stall timeout, oldest first. */
+    fun stalled(): List<Pending> {
+        if (stallTimeoutMs <= 0) return emptyList()
+        val now = clock()
+        return pending.values.filter { now - it.sentAt >= stallTimeoutMs }.sortedBy { it.sentAt }
+    }
+
+    /** Whether a request is stalled. */
+    val isStalled: Boolean get() = stalled().isNotEmpty()
+
+    /**
+     * The diagnostic for the oldest stalled request not reported yet, or
+     * `null` if there is none.
+     */
+    fun check(): String? {
+        val stall = stalled().firstOrNull { it.seq !in reported } ?: return null
+        reported.add(stall.seq)
+        val seconds = (clock() - stall.sentAt) / 1000
+        val debuggee = debuggeePid?.let { pid -> describeProcess(pid, stateOf(pid)) } ?: "The debuggee's state is unknown."
+        return "'${stall.command}' has had no response for $seconds s; lldb-dap may be stuck in LLDB waiting for the debuggee. " +
+            "$debuggee Disconnect, or send kdap/forceDetach, to end lldb-dap and detach from the debuggee."
+    }
+
+    /** Forgets the requests not answered yet and returns them, for failing them on a force-detach. */
+    fun drain(): List<Pending> {
+        val all = pending.values.sortedBy { it.sentAt }
+        pending.clear()
+        reported.clear()
+        return all
+    }
+
+    companion object {
+        /** Default time a request may go unanswered. */
+        const val DEFAULT_STALL_TIMEOUT_MS = 60_000L
+
+        /** How often the session checks for stalls. */
+        const val POLL_MS = 1_000L
+
+        /** Requests that may rightly wait without bound, not watched. */
+        val EXEMPT_COMMANDS = setOf("attach", "launch", "restart")
+
+        /** Requests that force-detach while a request is stalled; `kdap/forceDetach` does at any time. */
+        val RECOVERY_COMMANDS = setOf("disconnect", "terminate", FORCE_DETACH_COMMAND)
+
+        /** The custom request that force-detaches. */
+        const val FORCE_DETACH_COMMAND = "kdap/forceDetach"
+
+        /**
+         * The state of process [pid] from `/proc/<pid>/stat`, with its
+         * children's; `null` if it doesn't exist or `/proc` can't be read.
+         */
+        fun processState(pid: Long): ProcessState? {
+            val state = statState(File("/proc/$pid/stat")) ?: return null
+            val children = File("/proc/$pid/task").listFiles().orEmpty().flatMap { task ->
+                File(task, "children").takeIf { it.isFile }?.readText()?.trim()?.split(' ')?.mapNotNull { it.toLongOrNull() }.orEmpty()
+            }
+            return ProcessState(state, children.associateWith { child -> statState(File("/proc/$child/stat")) ?: 'X' })
+        }
+
+        /** The state field of a `/proc/<pid>/stat` file: the first character after the command's closing `)`. */
+        internal fun statState(file: File): Char? = try {
+            parseStatState(file.readText())
+        } catch (_: Exception) {
+            null
+        }
+
+        /** The state field of the text of a `/proc/<pid>/stat` file. */
+        internal fun parseStatState(stat: String): Char? =
+            stat.substring(stat.lastIndexOf(')') + 1).trim().firstOrNull()
+
+        /** A sentence describing debuggee process [pid] in [state], or gone if [state] is `null`. */
+        fun describeProcess(pid: Long, state: ProcessState?): String {
+            if (state == null) return "The debuggee (process $pid) no longer exists."
+            val self = when (state.state) {
+                'Z' -> "is a zombie: it has exited, but has not been reaped"
+                'D' -> "is in uninterruptible sleep, blocked in the kernel"
+                'T', 't' -> "is stopped"
+                'X' -> "is dead"
+                'R' -> "is running"
+                'S' -> "is sleeping"
+                else -> "is in state '${state.state}'"
+            }
+            val zombies = state.children.filterValues { it == 'Z' }.keys.sorted()
+            val children = when (zombies.size) {
+                0 -> ""
+                1 -> "; its child process ${zombies.single()} is a zombie"
+                else -> "; its child processes ${zombies.joinToString(", ")} are zombies"
+            }
+            return "The debuggee (process $pid) $self$children."
+        }
+    }
+}
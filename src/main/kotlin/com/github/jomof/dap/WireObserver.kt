@@ -0,0 +1,32 @@
+package com.github.jomof.dap
+
+/** Which way a DAP message went through KDAP. */
+enum class WireDirection(val label: String) {
+    FromClient("client→kdap"),
+    ToClient("kdap→client"),
+    FromBackend("lldb-dap→kdap"),
+    ToBackend("kdap→lldb-dap");
+
+    /** Whether the message went between the client and KDAP. */
+    val isClient: Boolean get() = this == FromClient || this == ToClient
+
+    /** The direction a response to a request sent this way comes back. */
+    val reverse: WireDirection get() = when (this) {
+        FromClient -> ToClient
+        ToClient -> FromClient
+        FromBackend -> ToBackend
+        ToBackend -> FromBackend
+    }
+}
+
+/**
+ * Observer for the DAP messages [DapSession] reads and writes, each
+ * reported once, as raw JSON, when it crosses the wire. Used by
+ * [ProtocolLog] and [DapCaptureWriter].
+ *
+ * Implementations must be thread-safe: the session's readers and writers
+ * report from their own coroutines.
+ */
+fun interface WireObserver {
+    fun message(direction: WireDirection, json: String)
+}
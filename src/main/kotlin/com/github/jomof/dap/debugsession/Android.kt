@@ -0,0 +1,251 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.messages.AndroidAttachOptions
+import com.github.jomof.dap.messages.CommonLaunchFields
+import com.github.jomof.dap.sb.SBDebugger
+import com.github.jomof.dap.sb.SBError
+import com.github.jomof.dap.sb.SBProcess
+import com.github.jomof.dap.sb.SBTarget
+import kotlinx.coroutines.Dispatchers
+import kotlinx.coroutines.delay
+import kotlinx.coroutines.runInterruptible
+import java.io.File
+import java.net.InetAddress
+import java.net.ServerSocket
+import java.util.concurrent.TimeUnit
+import java.util.logging.Logger
+
+/**
+ * Android attach: an `attach` request with an `"android"` object
+ * attaches to a running app by package name (KDAP extension). KDAP
+ * drives `adb` (on the device given by `android.device`, or `adb`'s
+ * default) the way Android Studio does:
+ *
+ * | Step      | `adb` command                                                              |
+ * |-----------|----------------------------------------------------------------------------|
+ * | ABI       | `shell getprop ro.product.cpu.abi`                                         |
+ * | process   | `shell pidof <package>`                                                    |
+ * | server    | `push <server> /data/local/tmp/kdap-<name>`, then `run-as <package> cp` it into the app's data directory |
+ * | start     | `shell run-as <package> ./kdap-lldb-server gdbserver unix-abstract-accept://<socket> --attach <pid>` |
+ * | forward   | `forward tcp:<local port> localabstract:<socket>`                          |
+ *
+ * and then connects LLDB to the forwarded port, as a `gdbRemote` attach
+ * does. `run-as` runs the server as the app's user, so the app must be
+ * debuggable. The server is the NDK's `lldb-server` for the device's ABI
+ * unless `android.server` names another; a `gdbserver` is started with
+ * `--attach :<port> <pid>` on `android.port` ([DEFAULT_ANDROID_GDBSERVER_PORT]
+ * by default) and forwarded from `tcp:<port>`, which needs the app's
+ * `INTERNET` permission. At disconnect KDAP stops the server and removes
+ * the forward ([releaseAndroid]).
+ *
+ * ## Tools
+ *
+ * `adb` is `android.adb`, `platform-tools/adb` under `ANDROID_HOME` or
+ * `ANDROID_SDK_ROOT`, or `adb` on `PATH` ([adbExecutable]). The NDK is
+ * `android.ndk`, `ANDROID_NDK_HOME`, `ANDROID_NDK_ROOT`, or the newest
+ * `ndk/<version>` of the SDK ([findNdk]).
+ *
+ * ## Symbols
+ *
+ * The device's system libraries are read from the process (LLDB loads a
+ * module from memory when it has no file for it). `target.exec-search-paths`
+ * and `target.debug-file-search-paths` get `solibSearchPath`, then
+ * `android.symbols` (the app's unstripped libraries, e.g.
+ * `app/build/intermediates/merged_native_libs/debug/out/lib/arm64-v8a`),
+ * then the NDK sysroot's libraries for the ABI, which hold
+ * `libc++_shared.so` ([ndkSymbolPaths]).
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.Android")
+
+/** Device directory `adb push` can write to and `run-as` can read from. */
+internal const val ANDROID_STAGING_DIR = "/data/local/tmp"
+
+/** Device port `gdbserver` listens on when `android.port` is not set. */
+internal const val DEFAULT_ANDROID_GDBSERVER_PORT = 5039
+
+/** Number of times KDAP tries to connect to the device's server before giving up. */
+private const val ANDROID_CONNECT_ATTEMPTS = 50
+
+/** Delay between attempts to connect to the device's server. */
+private const val ANDROID_CONNECT_DELAY_MS = 200L
+
+/** An Android ABI's target triple (NDK sysroot directory) and `lldb-server` architecture directory. */
+internal data class AndroidAbi(val triple: String, val lldbServerArch: String)
+
+/** The [AndroidAbi] of [abi], as `ro.product.cpu.abi` names it. */
+internal fun androidAbi(abi: String): AndroidAbi = when (abi) {
+    "arm64-v8a" -> AndroidAbi("aarch64-linux-android", "aarch64")
+    "armeabi-v7a", "armeabi" -> AndroidAbi("arm-linux-androideabi", "arm")
+    "x86" -> AndroidAbi("i686-linux-android", "i386")
+    "x86_64" -> AndroidAbi("x86_64-linux-android", "x86_64")
+    "riscv64" -> AndroidAbi("riscv64-linux-android", "riscv64")
+    else -> throw SBError("Unsupported Android ABI \"$abi\".")
+}
+
+/** A server started on the device: the `adb` command line, the forwarded host port, and the `adb shell` running it. */
+class AndroidConnection(val adb: List<String>, val localPort: Int, val server: Process)
+
+/** The `adb` to run: [configured], the SDK's, or `adb` on `PATH` (see the file header). */
+internal fun adbExecutable(configured: String?, env: Map<String, String>): String {
+    if (configured != null) return configured
+    val name = if (isWindowsHost()) "adb.exe" else "adb"
+    return listOf("ANDROID_HOME", "ANDROID_SDK_ROOT")
+        .mapNotNull { env[it] }
+        .map { File(it, "platform-tools/$name") }
+        .firstOrNull { it.isFile }
+        ?.path ?: name
+}
+
+/** The NDK directory: [configured], or found from [env] (see the file header); `null` if there is none. */
+internal fun findNdk(configured: String?, env: Map<String, String>): File? {
+    if (configured != null) return File(configured)
+    listOf("ANDROID_NDK_HOME", "ANDROID_NDK_ROOT").mapNotNull { env[it] }.map(::File)
+        .firstOrNull { it.isDirectory }?.let { return it }
+    return listOf("ANDROID_HOME", "ANDROID_SDK_ROOT").mapNotNull { env[it] }
+        .flatMap { File(it, "ndk").listFiles()?.filter { dir -> dir.isDirectory }.orEmpty() }
+        .maxWithOrNull { a, b -> compareVersions(a.name, b.name) }
+}
+
+/** The NDK's `toolchains/llvm/prebuilt/<host>` directory. */
+private fun ndkPrebuilt(ndk: File): File? =
+    File(ndk, "toolchains/llvm/prebuilt").listFiles()?.filter { it.isDirectory }?.minByOrNull { it.name }
+
+/**
+ * The NDK's `lldb-server` for [abi], from the newest clang resource
+ * directory (`lib/clang/<version>`, `lib64/clang/<version>` before r23).
+ */
+internal fun ndkLldbServer(ndk: File, abi: AndroidAbi): File? {
+    val prebuilt = ndkPrebuilt(ndk) ?: return null
+    return listOf("lib", "lib64")
+        .flatMap { File(prebuilt, "$it/clang").listFiles()?.toList().orEmpty() }
+        .sortedWith { a, b -> compareVersions(b.name, a.name) }
+        .map { File(it, "lib/linux/${abi.lldbServerArch}/lldb-server") }
+        .firstOrNull { it.isFile }
+}
+
+/** The NDK sysroot's library directory for [abi], if it exists. */
+internal fun ndkSymbolPaths(ndk: File, abi: AndroidAbi): List<String> {
+    val prebuilt = ndkPrebuilt(ndk) ?: return emptyList()
+    return listOf(File(prebuilt, "sysroot/usr/lib/${abi.triple}")).filter { it.isDirectory }.map { it.path }
+}
+
+/** Compares dotted version strings numerically, e.g. `9.0` < `26.1.10909125`. */
+private fun compareVersions(a: String, b: String): Int {
+    val x = a.split('.').map { it.toIntOrNull() ?: -1 }
+    val y = b.split('.').map { it.toIntOrNull() ?: -1 }
+    for (i in 0 until maxOf(x.size, y.size)) {
+        val c = x.getOrElse(i) { 0 }.compareTo(y.getOrElse(i) { 0 })
+        if (c != 0) return c
+    }
+    return 0
+}
+
+/** Whether [server] (a file name) is a `gdbserver` rather than an `lldb-server`. */
+internal fun isGdbserver(server: String): Boolean = "gdbserver" in server.substringAfterLast('/')
+
+/** The first process id in `pidof` [output], or `null` if the process isn't running. */
+internal fun parsePidof(output: String): Long? =
+    output.trim().split(Regex("\\s+")).firstOrNull()?.toLongOrNull()
+
+/**
+ * The `adb shell` arguments that run the pushed server [name] as
+ * [packageName] and attach it to [pid]: an `lldb-server` listens on the
+ * abstract [socket], a `gdbserver` on [port].
+ */
+internal fun androidServerCommand(
+    packageName: String,
+    name: String,
+    pid: Long,
+    socket: String,
+    port: Int,
+): List<String> =
+    if (isGdbserver(name)) {
+        listOf("run-as", packageName, "./$name", "--attach", ":$port", pid.toString())
+    } else {
+        listOf("run-as", packageName, "./$name", "gdbserver", "unix-abstract-accept://$socket", "--attach", pid.toString())
+    }
+
+/** Runs `adb` ([adb] and [args]) to completion and returns its output. */
+private suspend fun runAdb(adb: List<String>, vararg args: String): String = runInterruptible(Dispatchers.IO) {
+    val process = ProcessBuilder(adb + args).redirectErrorStream(true).start()
+    process.outputStream.close()
+    val output = process.inputStream.readBytes().toString(Charsets.UTF_8)
+    val exitCode = process.waitFor()
+    if (exitCode != 0) throw SBError("adb ${args.joinToString(" ")} failed: ${output.trim()}")
+    output
+}
+
+/**
+ * Attaches [target] to the app [options] names (see the file header).
+ *
+ * @return the app's process, stopped
+ */
+internal suspend fun DebugSession.attachAndroid(
+    options: AndroidAttachOptions,
+    common: CommonLaunchFields,
+    debugger: SBDebugger,
+    target: SBTarget,
+    ctx: AsyncRequestContext,
+): SBProcess {
+    val env = System.getenv()
+    val adb = listOf(adbExecutable(options.adb, env)) + (options.device?.let { listOf("-s", it) } ?: emptyList())
+    val packageName = options.packageName
+    val abi = androidAbi(runAdb(adb, "shell", "getprop", "ro.product.cpu.abi").trim())
+    val pid = parsePidof(runCatching { runAdb(adb, "shell", "pidof", packageName) }.getOrDefault(""))
+        ?: throw SBError("$packageName is not running on the device.")
+    val ndk = findNdk(options.ndk, env)
+    val server = options.server?.let(::File) ?: ndk?.let { ndkLldbServer(it, abi) }
+        ?: throw SBError("No lldb-server for ${abi.lldbServerArch} was found; set \"android.server\" or \"android.ndk\".")
+    if (!server.isFile) throw SBError("Android debug server ${server.path} does not exist.")
+
+    // Symbol search paths, ahead of the connection so modules resolve as they load
+    val paths = common.solibSearchPath.orEmpty() + options.symbols.orEmpty() + ndk?.let { ndkSymbolPaths(it, abi) }.orEmpty()
+    initSolibSearchPath(paths, debugger)
+
+    val name = "kdap-${server.name}"
+    consoleMessage("Installing ${server.name} for $packageName", ctx)
+    runAdb(adb, "push", server.path, "$ANDROID_STAGING_DIR/$name")
+    runAdb(adb, "shell", "run-as", packageName, "cp", "$ANDROID_STAGING_DIR/$name", "./$name")
+    runAdb(adb, "shell", "run-as", packageName, "chmod", "700", "./$name")
+
+    val localPort = ServerSocket(0, 1, InetAddress.getLoopbackAddress()).use { it.localPort }
+    val socket = "kdap-$packageName-$localPort"
+    val port = options.port ?: DEFAULT_ANDROID_GDBSERVER_PORT
+    val process = ProcessBuilder(adb + "shell" + androidServerCommand(packageName, name, pid, socket, port))
+        .redirectErrorStream(true)
+        .redirectOutput(ProcessBuilder.Redirect.DISCARD)
+        .start()
+    androidConnection = AndroidConnection(adb, localPort, process)
+    runAdb(adb, "forward", "tcp:$localPort", if (isGdbserver(name)) "tcp:$port" else "localabstract:$socket")
+    consoleMessage("Connecting to $packageName (pid $pid)", ctx)
+
+    var lastError: Exception? = null
+    repeat(ANDROID_CONNECT_ATTEMPTS) {
+        if (!process.isAlive) throw SBError("${server.name} exited on the device with code ${process.exitValue()}.")
+        try {
+            return target.connectRemote("connect://127.0.0.1:$localPort")
+        } catch (e: Exception) {
+            lastError = e
+            delay(ANDROID_CONNECT_DELAY_MS)
+        }
+    }
+    throw SBError("Could not connect to ${server.name} on the device: ${lastError?.message}")
+}
+
+/** Stops the device's server, if any, and removes its port forward. */
+internal fun DebugSession.releaseAndroid() {
+    val connection = androidConnection ?: return
+    androidConnection = null
+    connection.server.destroy()
+    try {
+        ProcessBuilder(connection.adb + listOf("forward", "--remove", "tcp:${connection.localPort}"))
+            .redirectErrorStream(true)
+            .redirectOutput(ProcessBuilder.Redirect.DISCARD)
+            .start()
+            .waitFor(5, TimeUnit.SECONDS)
+    } catch (e: Exception) {
+        log.fine { "Android: removing the port forward: ${e.message}" }
+    }
+}
@@ -0,0 +1,315 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.messages.StackTraceRequest
+import com.github.jomof.dap.sb.SBDebugger
+import com.github.jomof.dap.sb.pyStr
+import kotlinx.coroutines.sync.withLock
+import org.json.JSONArray
+import java.util.logging.Logger
+
+/**
+ * Tokio task inspection (`"tokioTasks": true` on launch or attach). This
+ * is a KDAP extension; CodeLLDB has no counterpart.
+ *
+ * While the process is stopped, each `threads` response lists the live
+ * tasks of every tokio runtime after the OS threads, as pseudo-threads
+ * numbered from [TASK_THREAD_BASE] plus the tokio task ID. A task's
+ * `stackTrace` is its logical await stack: the spawned future, then
+ * each `async fn` or `async` block it is suspended in (the `__awaitee`
+ * of the state machine's current `SuspendN` variant), down to the leaf
+ * future (a timer, a channel receive, ...). The frames have no source
+ * and no scopes; they can't be stepped or resumed on their own.
+ *
+ * The runtime is found without tokio's cooperation: KDAP searches the
+ * locals of `tokio::runtime::` frames on every thread for the runtime's
+ * `OwnedTasks` list and walks its `LinkedList`s (sharded since tokio
+ * 1.37) from task `Header` to task `Header` through the `owned` list
+ * pointers (in the `Trailer` since tokio 1.26, in the `Header` before).
+ * Layout differences between tokio versions are read from DWARF rather
+ * than hard-coded: `Header::vtable` gives the trailer offset, and the
+ * task's `Cell<T, S>` type is the one for the runtime's scheduler `S`
+ * with that trailer offset whose `Stage` decodes. Two future types with
+ * the same size and scheduler can't be told apart this way, so a task
+ * may be shown with the wrong future type. Tasks are read only when the
+ * client asks for threads; a runtime that no stopped thread is running
+ * (say, one parked in `block_on` elsewhere) is not found.
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.AsyncTasks")
+
+/** Pseudo-thread IDs at and above this are tokio tasks, not OS threads. */
+internal const val TASK_THREAD_BASE = 1 shl 30
+
+/** First frame ID used for await stacks; below it, frame IDs are lldb-dap's. */
+internal const val FIRST_TASK_FRAME_ID = 1 shl 30
+
+/** A live tokio task: its ID, `Stage` variant, and await stack (outermost first). */
+data class AsyncTask(val id: Long, val stage: String, val levels: List<AwaitLevel>)
+
+/** One future in an await stack, with the state machine variant it is in, if any. */
+data class AwaitLevel(val typeName: String, val state: String?)
+
+/** Parses the task list printed by `_kdap_tokio_tasks`. */
+internal fun parseAsyncTasks(json: String): List<AsyncTask> {
+    val array = JSONArray(json)
+    return (0 until array.length()).map { i ->
+        val task = array.getJSONObject(i)
+        val levels = task.optJSONArray("levels") ?: JSONArray()
+        AsyncTask(
+            id = task.getLong("id"),
+            stage = task.optString("stage", ""),
+            levels = (0 until levels.length()).map { j ->
+                val level = levels.getJSONObject(j)
+                AwaitLevel(level.getString("type"), level.optString("state", null))
+            },
+        )
+    }
+}
+
+/** The pseudo-thread ID for tokio task [taskId], or `null` if it doesn't fit. */
+internal fun taskThreadId(taskId: Long): Int? =
+    if (taskId in 0 until (Int.MAX_VALUE - TASK_THREAD_BASE).toLong()) TASK_THREAD_BASE + taskId.toInt() else null
+
+/**
+ * The name of an async state machine's body: `{async_fn_env#N}` is the
+ * environment of `{async_fn#N}`, the name frames in the body carry.
+ */
+private fun asyncBodyName(typeName: String): String =
+    typeName.replace("{async_fn_env#", "{async_fn#").replace("{async_block_env#", "{async_block#")
+
+/** The stack frame name for [level], e.g. `app::serve::{async_fn#0} [Suspend1]`. */
+internal fun awaitFrameName(level: AwaitLevel): String {
+    val name = asyncBodyName(level.typeName)
+    return if (level.state != null) "$name [${level.state}]" else name
+}
+
+/** The pseudo-thread name for [task], e.g. `task 7: app::serve::{async_fn#0}`. */
+internal fun taskThreadName(task: AsyncTask): String = buildString {
+    append("task ${task.id}")
+    task.levels.firstOrNull()?.let { append(": ${asyncBodyName(it.typeName)}") }
+    if (task.stage.isNotEmpty() && task.stage != "Running") append(" (${task.stage.lowercase()})")
+}
+
+/** Whether [threadId] is a tokio task pseudo-thread. */
+fun isTaskThread(threadId: Int): Boolean = threadId >= TASK_THREAD_BASE
+
+/** Whether [frameId] is a frame of a tokio task's await stack. */
+fun isTaskFrame(frameId: Int): Boolean = frameId >= FIRST_TASK_FRAME_ID
+
+/** Defines the [PY_TOKIO_TASKS] helpers. Call after [initRustFormatters]. */
+internal suspend fun initTokioTasks(debugger: SBDebugger) {
+    debugger.commandInterpreter().handleCommand("script exec(${pyStr(PY_TOKIO_TASKS)})")
+}
+
+/**
+ * The `threads` entries for the tokio tasks, one pseudo-thread per task
+ * (see the file header), listed after the OS threads by
+ * [handleThreads]. If the tasks can't be read, there are none.
+ */
+internal suspend fun DebugSession.taskThreads(debugger: SBDebugger): List<Map<String, Any>> {
+    val tasks = stateLock.withLock {
+        val tasks = try {
+            val output = debugger.commandInterpreter()
+                .handleCommand("script print(json.dumps(_kdap_tokio_tasks()))").trim()
+            parseAsyncTasks(output)
+        } catch (e: Exception) {
+            log.warning { "AsyncTasks: reading tokio tasks failed: ${e.message}" }
+            emptyList()
+        }
+        tasks.mapNotNull { task -> taskThreadId(task.id)?.let { it to task } }.toMap().also { asyncTasks = it }
+    }
+    return tasks.map { (id, task) -> mapOf("id" to id, "name" to taskThreadName(task)) }
+}
+
+/** Answers `stackTrace` for a tokio task with its await stack. */
+fun DebugSession.taskStackTrace(request: StackTraceRequest): DapResponse {
+    val task = asyncTasks[request.threadId]
+        ?: return DapResponse.error(request.seq, "stackTrace", "Task ${request.threadId - TASK_THREAD_BASE} is gone.")
+    val start = request.startFrame ?: 0
+    val levels = request.levels?.takeIf { it > 0 } ?: Int.MAX_VALUE
+    val frames = task.levels.drop(start).take(levels).map { level ->
+        mapOf(
+            "id" to nextTaskFrameId.getAndIncrement(),
+            "name" to awaitFrameName(level),
+            "line" to 0,
+            "column" to 0,
+            "presentationHint" to "label",
+        )
+    }
+    return DapResponse(
+        seq = 0, requestSeq = request.seq, command = "stackTrace", success = true,
+        body = mapOf("stackFrames" to frames, "totalFrames" to task.levels.size),
+    )
+}
+
+/**
+ * Python side of [taskThreads]. `_kdap_tokio_tasks` returns a list of
+ * `{"id", "stage", "levels": [{"type", "state"}]}`. Values are searched
+ * raw (no synthetic children), following pointers and expanding boxed
+ * slices, within `_KDAP_TOKIO_SEARCH_LIMIT` values per search. The
+ * `Cell` types are collected from the modules' type lists once per
+ * session. Uses the [initRustFormatters] helpers to decode enums.
+ */
+private val PY_TOKIO_TASKS = """
+    import collections, json
+
+    _KDAP_TOKIO_OWNED = 'tokio::runtime::task::list::OwnedTasks<'
+    _KDAP_TOKIO_LIST = 'tokio::util::linked_list::LinkedList<'
+    _KDAP_TOKIO_CELL = 'tokio::runtime::task::core::Cell<'
+    _KDAP_TOKIO_SEARCH_LIMIT = 20000
+    _KDAP_TOKIO_MAX_TASKS = 100000
+    _KDAP_MAX_AWAIT_DEPTH = 32
+    _kdap_tokio_cells = None
+
+    def _kdap_tokio_children(v):
+        v = v.GetNonSyntheticValue()
+        if v.GetType().IsPointerType():
+            return [v.Dereference()] if v.GetValueAsUnsigned() else []
+        data = v.GetChildMemberWithName('data_ptr')
+        length = v.GetChildMemberWithName('length')
+        if data.IsValid() and length.IsValid():
+            element = data.GetType().GetPointeeType()
+            base = data.GetValueAsUnsigned()
+            size = element.GetByteSize()
+            return [v.CreateValueFromAddress('[%d]' % i, base + i * size, element)
+                    for i in range(min(length.GetValueAsUnsigned(), 4096))]
+        return [v.GetChildAtIndex(i) for i in range(v.GetNumChildren())]
+
+    def _kdap_tokio_search(roots, prefix, depth):
+        found, seen = [], set()
+        queue = collections.deque((root, 0) for root in roots)
+        visited = 0
+        while queue and visited < _KDAP_TOKIO_SEARCH_LIMIT:
+            v, d = queue.popleft()
+            visited += 1
+            if not v.IsValid():
+                continue
+            v = v.GetNonSyntheticValue()
+            name = v.GetType().GetName() or ''
+            key = (v.GetLoadAddress(), name)
+            if key in seen:
+                continue
+            seen.add(key)
+            if name.startswith(prefix):
+                found.append(v)
+            elif d < depth:
+                queue.extend((child, d + 1) for child in _kdap_tokio_children(v))
+        return found
+
+    def _kdap_tokio_member(v, name, depth):
+        if not v.IsValid() or depth < 0 or v.GetType().IsPointerType():
+            return None
+        child = v.GetChildMemberWithName(name)
+        if child.IsValid():
+            return child
+        for i in range(v.GetNumChildren()):
+            found = _kdap_tokio_member(v.GetChildAtIndex(i), name, depth - 1)
+            if found is not None:
+                return found
+        return None
+
+    def _kdap_tokio_field(v, name):
+        child = v.GetChildMemberWithName(name)
+        while (child.GetType().GetName() or '').startswith('core::cell::UnsafeCell<'):
+            child = child.GetChildMemberWithName('value')
+        return child
+
+    def _kdap_tokio_address(v):
+        pointer = _kdap_first_pointer(v) if v is not None else None
+        return pointer.GetValueAsUnsigned() if pointer is not None else 0
+
+    def _kdap_tokio_offset(sbtype, name):
+        for i in range(sbtype.GetNumberOfFields()):
+            field = sbtype.GetFieldAtIndex(i)
+            if field.GetName() == name:
+                return field.GetOffsetInBytes()
+        return None
+
+    def _kdap_tokio_cell_types(target):
+        global _kdap_tokio_cells
+        if _kdap_tokio_cells is None:
+            _kdap_tokio_cells = []
+            for module in target.module_iter():
+                types = module.GetTypes(lldb.eTypeClassStruct)
+                for i in range(types.GetSize()):
+                    sbtype = types.GetTypeAtIndex(i)
+                    if (sbtype.GetName() or '').startswith(_KDAP_TOKIO_CELL):
+                        _kdap_tokio_cells.append(sbtype)
+        return _kdap_tokio_cells
+
+    def _kdap_tokio_owned(process):
+        roots = []
+        for thread in process:
+            for frame in thread:
+                if not (frame.GetFunctionName() or '').startswith('tokio::runtime::'):
+                    continue
+                variables = frame.GetVariables(True, True, False, True)
+                roots.extend(variables.GetValueAtIndex(i) for i in range(variables.GetSize()))
+        return _kdap_tokio_search(roots, _KDAP_TOKIO_OWNED, 14)
+
+    def _kdap_await_stack(future):
+        levels = []
+        v = future
+        while v is not None and v.IsValid() and len(levels) < _KDAP_MAX_AWAIT_DEPTH:
+            v = v.GetNonSyntheticValue()
+            name = v.GetType().GetName() or ''
+            if name.startswith(('core::pin::Pin<', 'alloc::boxed::Box<')):
+                v = _kdap_pointee(v)
+                continue
+            if '{async_fn_env#' not in name and '{async_block_env#' not in name:
+                levels.append({'type': name, 'state': None})
+                break
+            variant = _kdap_enum_variant(v)
+            state = _kdap_variant_name(variant) if variant is not None else None
+            levels.append({'type': name, 'state': state})
+            v = variant.GetChildMemberWithName('__awaitee') if variant is not None else None
+        return levels
+
+    def _kdap_tokio_task(target, address, trailer, scheduler):
+        for cell_type in _kdap_tokio_cell_types(target):
+            if not cell_type.GetName().endswith(', ' + scheduler + '>'):
+                continue
+            if _kdap_tokio_offset(cell_type, 'trailer') not in (None, trailer):
+                continue
+            cell = target.CreateValueFromAddress('task', lldb.SBAddress(address, target), cell_type)
+            core = cell.GetChildMemberWithName('core')
+            active = _kdap_enum_active(_kdap_tokio_field(_kdap_tokio_field(core, 'stage'), 'stage'))
+            if active is None:
+                continue
+            stage, fields = active
+            task_id = _kdap_scalar(core.GetChildMemberWithName('task_id')).GetValueAsUnsigned()
+            levels = _kdap_await_stack(fields[0]) if stage == 'Running' and fields else []
+            return {'id': task_id, 'stage': stage, 'levels': levels}
+        return None
+
+    def _kdap_tokio_next(target, header, trailer_address):
+        trailer_type = target.FindFirstType('tokio::runtime::task::core::Trailer')
+        if trailer_type.IsValid():
+            trailer = target.CreateValueFromAddress('trailer', lldb.SBAddress(trailer_address, target), trailer_type)
+            owner = trailer.GetChildMemberWithName('owned')
+        else:
+            owner = header.GetChildMemberWithName('owned')
+        return _kdap_tokio_address(_kdap_tokio_member(owner, 'next', 4))
+
+    def _kdap_tokio_tasks():
+        target = lldb.debugger.GetSelectedTarget()
+        header_type = target.FindFirstType('tokio::runtime::task::core::Header')
+        if not header_type.IsValid():
+            return []
+        tasks, seen = [], set()
+        for owned in _kdap_tokio_owned(target.GetProcess()):
+            scheduler = (owned.GetType().GetName() or '')[len(_KDAP_TOKIO_OWNED):-1]
+            for task_list in _kdap_tokio_search([owned], _KDAP_TOKIO_LIST, 8):
+                address = _kdap_tokio_address(task_list.GetChildMemberWithName('head'))
+                while address and address not in seen and len(seen) < _KDAP_TOKIO_MAX_TASKS:
+                    seen.add(address)
+                    header = target.CreateValueFromAddress('header', lldb.SBAddress(address, target), header_type)
+                    vtable = header.GetChildMemberWithName('vtable').Dereference()
+                    trailer = _kdap_scalar(vtable.GetChildMemberWithName('trailer_offset')).GetValueAsUnsigned()
+                    task = _kdap_tokio_task(target, address, trailer, scheduler)
+                    if task is not None:
+                        tasks.append(task)
+                    address = _kdap_tokio_next(target, header, address + trailer)
+        return tasks
+""".trimIndent()
@@ -0,0 +1,243 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.messages.BreakpointEvent
+import com.github.jomof.dap.messages.BreakpointsRequest
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.DapRequest
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.messages.FunctionBreakpoint
+import com.github.jomof.dap.messages.SetBreakpointsRequest
+import com.github.jomof.dap.messages.SetFunctionBreakpointsRequest
+import com.github.jomof.dap.messages.Source
+import com.github.jomof.dap.messages.SourceBreakpoint
+import com.github.jomof.dap.sb.SBError
+import kotlinx.coroutines.Dispatchers
+import kotlinx.coroutines.runInterruptible
+import org.json.JSONArray
+import org.json.JSONObject
+import java.io.File
+import java.util.logging.Logger
+
+/**
+ * Breakpoint files: a session's breakpoints saved to and loaded from
+ * JSON, so IDE sessions, `kdap debug` runs, and CI jobs can share one
+ * set. KDAP extension; CodeLLDB leaves breakpoints to the IDE, which
+ * keeps them in its own workspace storage.
+ *
+ * KDAP remembers the breakpoints the client last set
+ * ([rememberBreakpoints]): each source's `setBreakpoints` and the
+ * `setFunctionBreakpoints` entries. The custom `kdap/breakpoints`
+ * request ([handleBreakpointFile]) takes an `action` and a `path`:
+ *
+ * - `export` writes them to the file with their conditions, hit
+ *   conditions, log messages, and hardware hints ([formatBreakpointFile]);
+ * - `import` reads the file and adds its breakpoints to the session's.
+ *   Each source's are merged with the ones already set in it, an
+ *   imported breakpoint replacing one at the same line and column
+ *   ([mergeSourceBreakpoints]), and set through KDAP's own breakpoint
+ *   handling (see Breakpoints.kt), so Python conditions and logpoints
+ *   behave as if the client had set them. The client learns of each
+ *   breakpoint with a `breakpoint` event: `new`, or `changed` for one it
+ *   already had. Importing needs a target, so it comes after `launch`
+ *   or `attach`.
+ *
+ * A file reads:
+ *
+ * ```json
+ * {"version": 1,
+ *  "sourceBreakpoints": [{"source": {"path": "/src/main.rs"},
+ *                         "breakpoints": [{"line": 12, "condition": "/py n > 3"},
+ *                                         {"line": 20, "logMessage": "n = {n}"}]}],
+ *  "functionBreakpoints": [{"name": "rust_debuggee::step_*"}]}
+ * ```
+ *
+ * Instruction and data breakpoints are not saved, as their addresses do
+ * not outlast the process, nor are exception filters, which the IDE
+ * sends at every start. `kdap debug --breakpoints <file>` loads a file
+ * before the first prompt (see CliDebugger.kt).
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.BreakpointFile")
+
+/** Format version written by `export`; `import` reads this version and older. */
+internal const val BREAKPOINT_FILE_VERSION = 1
+
+/** The breakpoints of a breakpoint file: [sources] by source, and [functions]. */
+internal data class BreakpointFile(
+    val sources: List<SetBreakpointsRequest> = emptyList(),
+    val functions: List<FunctionBreakpoint> = emptyList(),
+)
+
+/**
+ * Records the breakpoints of the client's [request] if it sets source
+ * or function breakpoints. A source set to no breakpoints is forgotten.
+ */
+fun DebugSession.rememberBreakpoints(request: DapRequest) {
+    when (request) {
+        is SetBreakpointsRequest -> if (request.breakpoints.isEmpty()) {
+            clientSourceBreakpoints.remove(request.source.key)
+        } else {
+            clientSourceBreakpoints[request.source.key] = request.copy(seq = 0)
+        }
+        is SetFunctionBreakpointsRequest -> clientFunctionBreakpoints = request.breakpoints
+        else -> Unit
+    }
+}
+
+/** [file] as the JSON of a breakpoint file, sources in path order. */
+internal fun formatBreakpointFile(file: BreakpointFile): String = JSONObject().apply {
+    put("version", BREAKPOINT_FILE_VERSION)
+    put("sourceBreakpoints", JSONArray(file.sources.sortedBy { it.source.key }.map { request ->
+        JSONObject()
+            .put("source", request.source.toJsonObject())
+            .put("breakpoints", JSONArray(request.breakpoints.map { it.toJsonObject() }))
+    }))
+    put("functionBreakpoints", JSONArray(file.functions.map { it.toJsonObject() }))
+}.toString(2)
+
+/**
+ * Parses the JSON [text] of a breakpoint file. Throws [SBError] if it is
+ * not one or has a newer version than [BREAKPOINT_FILE_VERSION].
+ */
+internal fun parseBreakpointFile(text: String): BreakpointFile {
+    val obj = try {
+        JSONObject(text)
+    } catch (e: Exception) {
+        throw SBError("Not a breakpoint file: ${e.message}")
+    }
+    val version = obj.optInt("version", 0)
+    if (version !in 1..BREAKPOINT_FILE_VERSION) throw SBError("Unsupported breakpoint file version $version.")
+    fun JSONArray?.objects(): List<JSONObject> =
+        if (this == null) emptyList() else (0 until length()).mapNotNull { optJSONObject(it) }
+    val sources = obj.optJSONArray("sourceBreakpoints").objects().mapNotNull { entry ->
+        val source = Source.fromJson(entry.optJSONObject("source"))
+        if (source.path == null && source.name == null) return@mapNotNull null
+        SetBreakpointsRequest(seq = 0, source = source,
+            breakpoints = entry.optJSONArray("breakpoints").objects().map(SourceBreakpoint::fromJson).filter { it.line > 0 })
+    }
+    return BreakpointFile(
+        sources = sources.filter { it.breakpoints.isNotEmpty() },
+        functions = obj.optJSONArray("functionBreakpoints").objects().map(FunctionBreakpoint::fromJson)
+            .filter { it.name.isNotEmpty() },
+    )
+}
+
+/**
+ * A source's [current] breakpoints with [imported] added; an imported
+ * breakpoint replaces a current one at the same line and column. The
+ * imported ones come last, in their order.
+ */
+internal fun mergeSourceBreakpoints(current: List<SourceBreakpoint>, imported: List<SourceBreakpoint>): List<SourceBreakpoint> {
+    val sites = imported.map { it.line to it.column }.toSet()
+    return current.filter { (it.line to it.column) !in sites } + imported
+}
+
+/** The [current] function breakpoints with [imported] added, replacing those with the same name. */
+internal fun mergeFunctionBreakpoints(current: List<FunctionBreakpoint>, imported: List<FunctionBreakpoint>): List<FunctionBreakpoint> {
+    val names = imported.map { it.name }.toSet()
+    return current.filter { it.name !in names } + imported
+}
+
+/** Handles `kdap/breakpoints` (see the file header). */
+suspend fun DebugSession.handleBreakpointFile(rawJson: String, ctx: AsyncRequestContext) {
+    val requestSeq = JSONObject(rawJson).optInt("seq", 0)
+    try {
+        val request = DapMessage.parse(rawJson) as BreakpointsRequest
+        if (request.path.isEmpty()) throw SBError("\"path\" is required.")
+        val file = File(request.path)
+        val body = when (request.action) {
+            "export" -> exportBreakpoints(file, ctx)
+            "import" -> importBreakpoints(file, ctx)
+            else -> throw SBError("Unknown action \"${request.action}\"; expected \"export\" or \"import\".")
+        }
+        sendSuccessResponse(ctx, requestSeq, "kdap/breakpoints", body)
+    } catch (e: Exception) {
+        log.warning { "BreakpointFile: kdap/breakpoints failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "kdap/breakpoints", e.message ?: "kdap/breakpoints failed")
+    }
+}
+
+private suspend fun DebugSession.exportBreakpoints(file: File, ctx: AsyncRequestContext): Map<String, Any?> {
+    val breakpoints = BreakpointFile(clientSourceBreakpoints.values.toList(), clientFunctionBreakpoints)
+    runInterruptible(Dispatchers.IO) { file.writeText(formatBreakpointFile(breakpoints) + "\n") }
+    val count = breakpoints.sources.sumOf { it.breakpoints.size } + breakpoints.functions.size
+    consoleMessage("Saved $count breakpoint(s) to ${file.path}", ctx)
+    return mapOf("breakpoints" to count)
+}
+
+/**
+ * Sets the breakpoints of [file] along with the session's, and returns
+ * the results of the imported ones in the file's order.
+ */
+private suspend fun DebugSession.importBreakpoints(file: File, ctx: AsyncRequestContext): Map<String, Any?> {
+    if (!file.isFile) throw SBError("Breakpoint file ${file.path} does not exist.")
+    val imported = parseBreakpointFile(runInterruptible(Dispatchers.IO) { file.readText() })
+    val results = mutableListOf<Any?>()
+    for (source in imported.sources) {
+        val current = clientSourceBreakpoints[source.source.key]?.breakpoints.orEmpty()
+        val merged = source.copy(breakpoints = mergeSourceBreakpoints(current, source.breakpoints))
+        val response = setAsClient(merged, ctx) { json, c -> handleSetBreakpoints(json, c) }
+        rememberBreakpoints(merged)
+        val known = current.map { it.line to it.column }.toSet()
+        val sourceResults = breakpointResults(response).takeLast(source.breakpoints.size)
+        sourceResults.forEachIndexed { i, result ->
+            val bp = source.breakpoints[i]
+            val reason = if ((bp.line to bp.column) in known) "changed" else "new"
+            notifyBreakpoint(reason, result, source.source, ctx)
+        }
+        results += sourceResults
+    }
+    if (imported.functions.isNotEmpty()) {
+        val current = clientFunctionBreakpoints
+        val merged = SetFunctionBreakpointsRequest(seq = 0, breakpoints = mergeFunctionBreakpoints(current, imported.functions))
+        val response = setAsClient(merged, ctx) { json, c -> handleSetFunctionBreakpoints(json, c) }
+        rememberBreakpoints(merged)
+        val known = current.map { it.name }.toSet()
+        val functionResults = breakpointResults(response).takeLast(imported.functions.size)
+        functionResults.forEachIndexed { i, result ->
+            notifyBreakpoint(if (imported.functions[i].name in known) "changed" else "new", result, null, ctx)
+        }
+        results += functionResults
+    }
+    consoleMessage("Loaded ${results.size} breakpoint(s) from ${file.path}", ctx)
+    return mapOf("breakpoints" to results)
+}
+
+/**
+ * Runs [handle], one of KDAP's breakpoint request handlers, on
+ * [request] as if the client had sent it, and returns its response
+ * instead of sending it to the client. Throws [SBError] if it failed.
+ */
+private suspend fun setAsClient(
+    request: DapRequest,
+    ctx: AsyncRequestContext,
+    handle: suspend (String, AsyncRequestContext) -> Unit,
+): DapResponse {
+    var response: DapResponse? = null
+    val capturing = object : AsyncRequestContext by ctx {
+        override suspend fun sendEventToClient(json: String) {
+            val message = DapMessage.parse(json)
+            if (message is DapResponse && message.command == request.command && message.requestSeq == request.seq) {
+                response = message
+            } else {
+                ctx.sendEventToClient(json)
+            }
+        }
+    }
+    handle(request.toJson(), capturing)
+    val result = response ?: throw SBError("${request.command} did not respond")
+    if (!result.success) throw SBError(result.message ?: "${request.command} failed")
+    return result
+}
+
+/** The `breakpoints` of a set-breakpoints [response]. */
+private fun breakpointResults(response: DapResponse): List<Map<*, *>> =
+    (response.body["breakpoints"] as? List<*>).orEmpty().map { it as? Map<*, *> ?: emptyMap<String, Any?>() }
+
+/** Tells the client of an imported breakpoint, with its [source] if lldb-dap's [result] has none. */
+private suspend fun notifyBreakpoint(reason: String, result: Map<*, *>, source: Source?, ctx: AsyncRequestContext) {
+    val breakpoint = result.entries.associate { (key, value) -> key.toString() to value }.toMutableMap()
+    if (source != null && breakpoint["source"] == null) breakpoint["source"] = DapMessage.jsonObjectToMap(source.toJsonObject())
+    ctx.sendEventToClient(BreakpointEvent(seq = 0, reason = reason, breakpoint = breakpoint).toJson())
+}
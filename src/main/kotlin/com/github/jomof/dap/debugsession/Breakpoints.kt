@@ -0,0 +1,1929 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.kdapMarker
+import com.github.jomof.dap.messages.*
+import com.github.jomof.dap.sb.*
+import kotlinx.coroutines.sync.withLock
+import org.json.JSONArray
+import org.json.JSONObject
+import java.io.File
+import java.security.MessageDigest
+import java.time.Instant
+import java.util.logging.Logger
+
+/**
+ * Source breakpoint logic as extension functions on [DebugSession],
+ * mirroring CodeLLDB's `debug_session/breakpoints.rs`.
+ *
+ * lldb-dap still creates, resolves, and reports every breakpoint. KDAP
+ * rewrites each `setBreakpoints` request so that lldb-dap only sees what
+ * it can express natively, then attaches a Python callback
+ * ([SBBreakpoint.setScriptCallbackBody]) to every breakpoint that needs
+ * KDAP behavior. The callback runs inside LLDB at hit time and returns
+ * `False` to auto-continue, so suppressed hits never surface to the
+ * client as `stopped` events.
+ *
+ * ## Conditions
+ *
+ * | Condition      | Evaluated by                                         |
+ * |----------------|------------------------------------------------------|
+ * | `/py <expr>`   | KDAP callback; Python, compiled once per breakpoint |
+ * | `/se <expr>`   | KDAP callback; simple expression (`Expressions.kt`) |
+ * | `/nat <expr>`  | lldb-dap (prefix stripped); LLDB native expression  |
+ * | `<expr>`       | lldb-dap; LLDB native expression                     |
+ *
+ * Python conditions see `frame`, `lldb`, and the frame's variables by
+ * name (scalars as Python numbers, everything else as `SBValue`).
+ *
+ * ## Hit conditions
+ *
+ * `hitCondition` is always handled by KDAP (lldb-dap only understands a
+ * plain ignore count). See [HitCondition] for the syntax. Hit counts are
+ * kept per source line (and column, for column breakpoints), so they
+ * survive `setBreakpoints` re-sends (which happen on every edit of the
+ * file's breakpoints). Only hits whose condition passed are counted.
+ *
+ * ## Columns
+ *
+ * A `column` on a source breakpoint reaches lldb-dap unchanged, which
+ * binds it to the line-table entry at or after that column, so several
+ * breakpoints on one line (e.g., inside a closure and on the call that
+ * takes it) are distinct. lldb-dap reports the line and column each
+ * breakpoint actually bound to in the response and in later `changed`
+ * events. KDAP keeps their callback state apart ([sourceBreakpointSite]).
+ *
+ * ## Logpoints
+ *
+ * `logMessage` is always handled by KDAP. When the breakpoint is hit
+ * (and its conditions pass), the callback interpolates each `{expr}`
+ * placeholder by evaluating `expr` as a native expression in the hit
+ * frame, prints the message, and returns `False` so the debuggee keeps
+ * running. `{{` and `}}` produce literal braces. See [parseLogMessage].
+ *
+ * Printed messages are prefixed with [LOGPOINT_MARKER] so
+ * [OutputCategoryNormalizer][com.github.jomof.dap.interception.OutputCategoryNormalizer]
+ * can strip it and keep them in the `console` category.
+ *
+ * ## Instruction breakpoints
+ *
+ * Breakpoints set in the disassembly view (`setInstructionBreakpoints`)
+ * are created by lldb-dap at the instruction's address and take the same
+ * conditions and hit conditions as source breakpoints, with callback
+ * state kept per address. KDAP advertises
+ * `supportsInstructionBreakpoints`.
+ *
+ * ## Function breakpoints
+ *
+ * Plain function names go to lldb-dap, which creates them with
+ * `SBTarget::BreakpointCreateByName`. LLDB matches mangled names
+ * (`_ZN…E`, `_R…`) as well as demangled ones, and qualified Rust paths
+ * such as `rust_debuggee::step_in`. KDAP creates the breakpoints for
+ * names lldb-dap cannot express (see [functionNameRegex]):
+ *
+ * | Name                      | Breaks on                                |
+ * |---------------------------|------------------------------------------|
+ * | `mod::step_in*`           | functions matching the wildcard pattern |
+ * | `/re <regex>`             | functions matching the regex             |
+ *
+ * `*` matches any run of characters and `?` matches one. Wildcard
+ * patterns match the whole name, optionally followed by the
+ * `::h<hash>` suffix legacy Rust symbols carry. Pattern breakpoints take
+ * a native `condition` and a numeric `hitCondition` (an ignore count),
+ * as lldb-dap's do.
+ *
+ * Every function breakpoint resolves lazily: LLDB adds locations as
+ * shared libraries load. KDAP labels its breakpoints with lldb-dap's
+ * [DAP_BREAKPOINT_LABEL], so lldb-dap reports their new locations to the
+ * client with `breakpoint` `changed` events as it does for its own.
+ *
+ * ## Hardware breakpoints
+ *
+ * A source breakpoint with `"hardware": true` is served by a hardware
+ * breakpoint at the same line and column. lldb-dap still creates,
+ * verifies, and reports the requested breakpoint, but KDAP disables it
+ * and creates the hardware one alongside, unreported, with the same
+ * condition, ignore count, and KDAP callback. The next `setBreakpoints`
+ * for the source replaces them.
+ *
+ * Code on read-only or W^X pages (e.g., JIT output) cannot take the
+ * trap instruction of a software breakpoint; LLDB then leaves the
+ * location without a breakpoint site. Once a process exists, after every
+ * launch, attach, and `setBreakpoints`, KDAP gives each such location of
+ * lldb-dap's breakpoints a hardware breakpoint instead
+ * ([applyHardwareFallback]) and says so on the console. Hardware
+ * breakpoints are few (four on x86-64), so the fallback can run out.
+ *
+ * A hit of a hardware breakpoint is reported with the ID of the
+ * breakpoint it stands in for ([rewriteHardwareBreakpointStop]).
+ *
+ * ## Changed sources
+ *
+ * A breakpoint set in a source edited since the program was built binds
+ * to the line numbers of the old text, so it stops on the wrong line or
+ * not at all. KDAP checks each `setBreakpoints` source
+ * ([sourceChangedSinceBuild]) and reports its breakpoints unverified,
+ * with [SOURCE_CHANGED_MESSAGE] and a console message saying to
+ * rebuild, when:
+ *
+ * - the client sets `sourceModified`,
+ * - an `MD5`, `SHA1`, or `SHA256` checksum of the source does not match
+ *   the file on disk (the client's copy has unsaved edits), or
+ * - the file, or a `timestamp` checksum, is newer than the modules whose
+ *   line tables have it (the program, if none do yet).
+ *
+ * The breakpoints are still set, and their `changed` events keep the
+ * mark ([markChangedSourceBreakpoint]) until the next `setBreakpoints`
+ * for the source finds it unchanged.
+ *
+ * ## Breakpoint locations
+ *
+ * KDAP answers `breakpointLocations` for source files from LLDB's line
+ * tables ([handleBreakpointLocations]), so the client only offers lines
+ * and columns where a breakpoint will bind.
+ *
+ * ## Data breakpoints
+ *
+ * lldb-dap creates hardware watchpoints for `setDataBreakpoints`. When
+ * the hardware debug registers are exhausted, a write watchpoint it
+ * could not create falls back to a KDAP software watchpoint: `continue`
+ * then single-steps the selected thread under a scripted thread plan
+ * that compares the watched bytes after every instruction and stops
+ * (reported as a `data breakpoint` stop) when they change. This is
+ * orders of magnitude slower than running freely, only sees writes made
+ * by the stepping thread, and cannot detect reads.
+ *
+ * ## Exception filters
+ *
+ * KDAP adds [KDAP_EXCEPTION_FILTERS] to lldb-dap's C++/Objective-C
+ * filters and strips them from `setExceptionBreakpoints` before
+ * forwarding it:
+ *
+ * - `rust_panic` sets a breakpoint on `rust_panic` (or
+ *   `__rust_start_panic`). The panic hook has already written the panic
+ *   message to stderr by the time it is hit, so KDAP remembers the last
+ *   `panicked at` output and reports the stop as an `exception` carrying
+ *   that message. `exceptionInfo` reads the panic from the stopped
+ *   thread instead ([handleExceptionInfo]): the payload string, the
+ *   location the hook was given, and the backtrace, formatted as
+ *   `RUST_BACKTRACE` does, in `details`.
+ * - `crash_abort`, `crash_stack_protector`, `crash_ubsan`, and
+ *   `crash_alloc` set a breakpoint on the runtime functions a memory
+ *   safety crash goes through before it takes the process down: `abort`,
+ *   `__stack_chk_fail`, the `__ubsan_handle_*` reporters, and the Rust
+ *   allocation failure hooks ([CRASH_FILTERS]). Stopping at their entry,
+ *   before any signal is raised, keeps the faulting caller's frame and
+ *   registers intact; the stop is reported as an `exception`
+ *   ([rewriteCrashStop]), and the crash functions are runtime internals
+ *   (StackFrames.kt), so the client selects the caller.
+ * - `signal_<NAME>` and `signal_all` make LLDB stop on those signals.
+ *   Disabling a filter restores LLDB's previous setting. Signal handling
+ *   belongs to the process, so filters set before launch are applied
+ *   once it exists ([applySignalFilters]).
+ *
+ * ## CodeLLDB method mapping
+ *
+ * | CodeLLDB (breakpoints.rs)  | KDAP (this file)                        |
+ * |----------------------------|-----------------------------------------|
+ * | `handle_set_breakpoints`   | [DebugSession.handleSetBreakpoints]     |
+ * | `init_bp_actions`          | [DebugSession.initBreakpointActions]    |
+ * | `parse_condition`          | [planSourceBreakpoint]                  |
+ * | `parse_hit_condition`      | [HitCondition.parse]                    |
+ * | `parse_log_message`        | [parseLogMessage]                       |
+ * | `handle_set_instruction_breakpoints` | [DebugSession.handleSetInstructionBreakpoints] |
+ * | `handle_set_function_breakpoints` | [DebugSession.handleSetFunctionBreakpoints] |
+ * | `handle_set_data_breakpoints` | [DebugSession.handleSetDataBreakpoints] |
+ * | `handle_set_exception_breakpoints` | [DebugSession.handleSetExceptionBreakpoints] |
+ * | `handle_exception_info`    | [DebugSession.handleExceptionInfo]      |
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.Breakpoints")
+
+private const val PY_CONDITION_PREFIX = "/py "
+private const val SIMPLE_CONDITION_PREFIX = "/se "
+private const val NATIVE_CONDITION_PREFIX = "/nat "
+
+/** Prefix on logpoint output printed by KDAP callbacks (see [kdapMarker]). */
+internal val LOGPOINT_MARKER = kdapMarker("log")
+
+private const val REGEX_FUNCTION_PREFIX = "/re "
+
+/** [DebugSession.scriptedBreakpoints] key of the instruction breakpoints. */
+private const val INSTRUCTION_BREAKPOINTS_KEY = "<instructions>"
+
+/**
+ * Name lldb-dap gives the breakpoints it creates. lldb-dap only sends
+ * `breakpoint` events for breakpoints carrying it.
+ */
+internal const val DAP_BREAKPOINT_LABEL = "dap"
+
+/** Suffix legacy-mangled Rust symbols carry after the demangled path. */
+private const val RUST_HASH_SUFFIX = "(::h[0-9a-f]{16})?"
+
+/** Python class name of the software watchpoint thread plan. */
+private const val SOFTWARE_WATCH_PLAN = "_KdapSoftwareWatch"
+
+private const val SOFTWARE_WATCHPOINT_MESSAGE =
+    "Hardware watchpoints exhausted; using a software watchpoint (slow)"
+
+/**
+ * How one [SourceBreakpoint] is split between lldb-dap and the KDAP
+ * hit-time callback.
+ */
+internal data class BreakpointPlan(
+    /** Condition forwarded to lldb-dap, or `null` for none. */
+    val nativeCondition: String? = null,
+    /** Python condition evaluated by the KDAP callback, or `null` for none. */
+    val pyCondition: String? = null,
+    /** Simple-expression condition evaluated by the KDAP callback, or `null` for none. */
+    val simpleCondition: String? = null,
+    /** Why a `/se` condition failed to parse (and is ignored), or `null`. */
+    val invalidCondition: String? = null,
+    /** Hit condition evaluated by the KDAP callback, or `null` for none. */
+    val hitCondition: HitCondition? = null,
+    /** The original `hitCondition` text if it failed to parse (and is ignored). */
+    val invalidHitCondition: String? = null,
+    /** Parsed `logMessage`, or `null` if this is not a logpoint. */
+    val logMessage: List<LogSegment>? = null,
+) {
+    /** Whether this breakpoint needs a KDAP callback. */
+    val isScripted: Boolean
+        get() = pyCondition != null || simpleCondition != null || hitCondition != null || logMessage != null
+}
+
+/** One piece of a parsed logpoint `logMessage`. */
+internal sealed class LogSegment {
+    /** Literal text, printed as-is. */
+    data class Text(val text: String) : LogSegment()
+    /** A `{expr}` placeholder, evaluated in the hit frame. */
+    data class Expr(val expr: String) : LogSegment()
+}
+
+/**
+ * A parsed `hitCondition`, mirroring CodeLLDB's `HitCondition`.
+ *
+ * | Syntax        | Stops when the hit count `n` satisfies |
+ * |---------------|----------------------------------------|
+ * | `N` or `>=N`  | `n >= N`                               |
+ * | `>N`          | `n > N`                                |
+ * | `<N` / `<=N`  | `n < N` / `n <= N`                     |
+ * | `=N` or `==N` | `n == N` (only the Nth hit)            |
+ * | `%N`          | `n % N == 0` (every Nth hit)           |
+ */
+internal data class HitCondition(val op: Op, val value: Long) {
+    enum class Op(val symbol: String) { LT("<"), LE("<="), EQ("=="), GE(">="), GT(">"), MOD("%") }
+
+    /** Renders this condition as a Python boolean expression over [count]. */
+    fun toPython(count: String): String = when (op) {
+        Op.MOD -> "$count % $value == 0"
+        else -> "$count ${op.symbol} $value"
+    }
+
+    companion object {
+        /** Parses a hit condition, returning `null` if [text] is not valid. */
+        fun parse(text: String): HitCondition? {
+            val expr = text.trim()
+            // Longest operators first so "<=" isn't read as "<".
+            val (op, rest) = when {
+                expr.startsWith("<=") -> Op.LE to expr.substring(2)
+                expr.startsWith(">=") -> Op.GE to expr.substring(2)
+                expr.startsWith("==") -> Op.EQ to expr.substring(2)
+                expr.startsWith("<") -> Op.LT to expr.substring(1)
+                expr.startsWith(">") -> Op.GT to expr.substring(1)
+                expr.startsWith("=") -> Op.EQ to expr.substring(1)
+                expr.startsWith("%") -> Op.MOD to expr.substring(1)
+                else -> Op.GE to expr
+            }
+            val value = rest.trim().toLongOrNull() ?: return null
+            if (value < 0 || (op == Op.MOD && value == 0L)) return null
+            return HitCondition(op, value)
+        }
+    }
+}
+
+// ── parse_condition (breakpoints.rs) ─────────────────────────────
+
+/** Decides which parts of [bp] lldb-dap handles and which KDAP handles. */
+internal fun planSourceBreakpoint(bp: SourceBreakpoint): BreakpointPlan =
+    planBreakpoint(bp.condition, bp.hitCondition, bp.logMessage)
+
+/** Like [planSourceBreakpoint], for a disassembly view breakpoint. */
+internal fun planInstructionBreakpoint(bp: InstructionBreakpoint): BreakpointPlan =
+    planBreakpoint(bp.condition, bp.hitCondition, logMessage = null)
+
+private fun planBreakpoint(conditionText: String?, hitConditionText: String?, logMessage: String?): BreakpointPlan {
+    val condition = conditionText?.trim()?.ifEmpty { null }
+    val base = when {
+        condition == null -> BreakpointPlan()
+        condition.startsWith(PY_CONDITION_PREFIX) ->
+            BreakpointPlan(pyCondition = condition.removePrefix(PY_CONDITION_PREFIX).trim())
+        condition.startsWith(SIMPLE_CONDITION_PREFIX) -> {
+            val text = condition.removePrefix(SIMPLE_CONDITION_PREFIX).trim()
+            try {
+                parseSimpleExpression(text)
+                BreakpointPlan(simpleCondition = text)
+            } catch (e: ExpressionError) {
+                BreakpointPlan(invalidCondition = "\"$text\": ${e.message}")
+            }
+        }
+        condition.startsWith(NATIVE_CONDITION_PREFIX) ->
+            BreakpointPlan(nativeCondition = condition.removePrefix(NATIVE_CONDITION_PREFIX).trim())
+        else -> BreakpointPlan(nativeCondition = condition)
+    }
+    val plan = logMessage?.let { base.copy(logMessage = parseLogMessage(it)) } ?: base
+    val hitText = hitConditionText?.trim()?.ifEmpty { null } ?: return plan
+    val hitCondition = HitCondition.parse(hitText)
+        ?: return plan.copy(invalidHitCondition = hitText)
+    return plan.copy(hitCondition = hitCondition)
+}
+
+// ── parse_log_message (breakpoints.rs) ───────────────────────────
+
+/**
+ * Splits a logpoint template into literal text and `{expr}` placeholders.
+ * `{{` and `}}` are literal braces; an unterminated `{` is kept as text.
+ */
+internal fun parseLogMessage(template: String): List<LogSegment> {
+    val segments = mutableListOf<LogSegment>()
+    val text = StringBuilder()
+    fun flushText() {
+        if (text.isNotEmpty()) segments.add(LogSegment.Text(text.toString()))
+        text.clear()
+    }
+    var i = 0
+    while (i < template.length) {
+        val c = template[i]
+        when {
+            c == '{' && template.startsWith("{{", i) -> { text.append('{'); i += 2 }
+            c == '}' && template.startsWith("}}", i) -> { text.append('}'); i += 2 }
+            c == '{' -> {
+                val end = template.indexOf('}', i + 1)
+                if (end < 0) {
+                    text.append(template, i, template.length)
+                    i = template.length
+                } else {
+                    flushText()
+                    segments.add(LogSegment.Expr(template.substring(i + 1, end).trim()))
+                    i = end + 1
+                }
+            }
+            else -> { text.append(c); i++ }
+        }
+    }
+    flushText()
+    return segments
+}
+
+/**
+ * Whether KDAP must rewrite [request] rather than forward it: some
+ * breakpoint needs KDAP behavior, a condition needs rewriting, the
+ * source still has KDAP callbacks or hardware breakpoints from an
+ * earlier request that may need removing, or there is a process whose
+ * breakpoints may need the hardware fallback.
+ */
+fun DebugSession.ownsBreakpoints(request: SetBreakpointsRequest): Boolean =
+    processRunning ||
+        request.source.key in scriptedBreakpoints ||
+        request.source.key in hintedHardwareBreakpoints ||
+        request.source.key in changedSourceBreakpoints ||
+        mayHaveChangedSource(request) ||
+        request.breakpoints.any { bp ->
+            val plan = planSourceBreakpoint(bp)
+            plan.isScripted || plan.nativeCondition != bp.condition ||
+                bp.hitCondition != null || bp.logMessage != null || bp.hardware == true
+        }
+
+// ── handle_set_breakpoints (breakpoints.rs) ──────────────────────
+
+/**
+ * Mirrors CodeLLDB's `handle_set_breakpoints`.
+ *
+ * Forwards a rewritten request to lldb-dap, installs KDAP callbacks on
+ * the resulting breakpoints, and relays lldb-dap's response to the
+ * client under the original request's `seq`.
+ */
+suspend fun DebugSession.handleSetBreakpoints(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val request = DapMessage.parse(rawJson) as SetBreakpointsRequest
+    val plans = request.breakpoints.map { planSourceBreakpoint(it) }
+
+    try {
+        val argsObj = obj.optJSONObject("arguments") ?: JSONObject()
+        rewriteBreakpointArguments(argsObj.optJSONArray("breakpoints"), plans)
+        val backendRequest = JSONObject().apply {
+            put("type", "request")
+            put("command", "setBreakpoints")
+            put("arguments", argsObj)
+        }
+        val response = ctx.sendRequestToBackendAndAwait(backendRequest.toString())
+
+        val sourceKey = request.source.key
+        val sites = request.breakpoints.map { sourceBreakpointSite(sourceKey, it) }
+        if (response.success) {
+            val ids = breakpointIds(response)
+            initBreakpointActions(sourceKey, plans, ids, sites, ctx)
+            logErrors { setHintedHardwareBreakpoints(sourceKey, request, ids, ctx) }
+            if (processRunning) logErrors { applyHardwareFallback(ctx) }
+        }
+        reportInvalidConditions(plans, sites, ctx)
+        val marked = if (response.success) markChangedSource(request, breakpointIds(response), response, ctx) else response
+
+        ctx.sendEventToClient(marked.copy(seq = 0, requestSeq = requestSeq).toJson())
+
+    } catch (e: Exception) {
+        log.warning { "Breakpoints: setBreakpoints failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "setBreakpoints", e.message ?: "setBreakpoints failed")
+    }
+}
+
+/**
+ * Where one requested breakpoint sits: the [key] its per-site callback
+ * state is kept under, and a [label] for console messages (e.g.,
+ * `"on line 10"`).
+ */
+internal data class BreakpointSite(val key: String, val label: String)
+
+/** The [BreakpointSite] of [bp] in the source with key [sourceKey]. */
+internal fun sourceBreakpointSite(sourceKey: String, bp: SourceBreakpoint): BreakpointSite =
+    if (bp.column == null) {
+        BreakpointSite("$sourceKey:${bp.line}", "on line ${bp.line}")
+    } else {
+        BreakpointSite("$sourceKey:${bp.line}:${bp.column}", "on line ${bp.line}, column ${bp.column}")
+    }
+
+/**
+ * Rewrites the raw `breakpoints` arguments in place so that lldb-dap
+ * only sees the native condition of each plan. Fields KDAP doesn't
+ * model (e.g., source checksums) still reach lldb-dap.
+ */
+private fun rewriteBreakpointArguments(bpArray: JSONArray?, plans: List<BreakpointPlan>) {
+    if (bpArray == null) return
+    for (i in 0 until minOf(bpArray.length(), plans.size)) {
+        val bpObj = bpArray.getJSONObject(i)
+        val plan = plans[i]
+        if (plan.nativeCondition != null) {
+            bpObj.put("condition", plan.nativeCondition)
+        } else {
+            bpObj.remove("condition")
+        }
+        bpObj.remove("hitCondition")
+        bpObj.remove("logMessage")
+        bpObj.remove("hardware")
+    }
+}
+
+/** The breakpoint IDs in lldb-dap's [response], in request order. */
+private fun breakpointIds(response: DapResponse): List<Int?> =
+    (response.body["breakpoints"] as? List<*>).orEmpty().map { bp ->
+        ((bp as? Map<*, *>)?.get("id") as? Number)?.toInt()
+    }
+
+/** Reports conditions KDAP could not parse and is ignoring. */
+private suspend fun reportInvalidConditions(
+    plans: List<BreakpointPlan>,
+    sites: List<BreakpointSite>,
+    ctx: AsyncRequestContext,
+) {
+    for ((index, plan) in plans.withIndex()) {
+        val label = sites[index].label
+        if (plan.invalidCondition != null) {
+            consoleMessage("Invalid condition $label: ${plan.invalidCondition} (ignored)", ctx)
+        }
+        if (plan.invalidHitCondition != null) {
+            consoleMessage("Invalid hit condition $label: \"${plan.invalidHitCondition}\" (ignored)", ctx)
+        }
+    }
+}
+
+// ── Changed sources ──────────────────────────────────────────────
+
+/** Message of the breakpoints in a source changed since the program was built. */
+internal const val SOURCE_CHANGED_MESSAGE =
+    "Source changed since the program was built — rebuild, or this breakpoint may stop on the wrong line."
+
+/** DAP checksum algorithms and their [MessageDigest] names. */
+private val CHECKSUM_DIGESTS = mapOf("MD5" to "MD5", "SHA1" to "SHA-1", "SHA256" to "SHA-256")
+
+/**
+ * Whether [request]'s source may have changed since the program was
+ * built, judged without LLDB: the client says so or sends checksums, or
+ * the file is newer than the program.
+ */
+private fun DebugSession.mayHaveChangedSource(request: SetBreakpointsRequest): Boolean {
+    val path = request.source.path ?: return false
+    if (request.sourceModified == true || request.source.checksums != null) return true
+    val builtAt = programBuiltAt ?: return false
+    return File(path).lastModified() > builtAt
+}
+
+/**
+ * Whether [source] changed since it was built into modules last modified
+ * at [builtAt] (ms since the epoch; `null` if unknown), given the
+ * request's [sourceModified] and the client's [checksums] (see the file
+ * header).
+ */
+internal fun sourceChangedSinceBuild(
+    source: File,
+    sourceModified: Boolean?,
+    checksums: List<Checksum>,
+    builtAt: Long?,
+): Boolean {
+    if (sourceModified == true) return true
+    if (!source.isFile) return false
+    val contents by lazy { source.readBytes() }
+    for (checksum in checksums) {
+        val digest = CHECKSUM_DIGESTS[checksum.algorithm]
+        if (digest != null) {
+            val actual = MessageDigest.getInstance(digest).digest(contents).joinToString("") { "%02x".format(it) }
+            if (!actual.equals(checksum.checksum, ignoreCase = true)) return true
+        } else if (checksum.algorithm == "timestamp" && builtAt != null) {
+            val time = checksum.checksum.toLongOrNull()
+                ?: runCatching { Instant.parse(checksum.checksum).toEpochMilli() }.getOrNull()
+            if (time != null && time > builtAt) return true
+        }
+    }
+    return builtAt != null && source.lastModified() > builtAt
+}
+
+/**
+ * Marks the breakpoints of [response] unverified, with
+ * [SOURCE_CHANGED_MESSAGE], if [request]'s source changed since the
+ * program was built, and remembers their [ids] for later `changed`
+ * events. Says so on the console when the source was not already
+ * marked.
+ */
+private suspend fun DebugSession.markChangedSource(
+    request: SetBreakpointsRequest,
+    ids: List<Int?>,
+    response: DapResponse,
+    ctx: AsyncRequestContext,
+): DapResponse {
+    val key = request.source.key
+    val wasChanged = changedSourceBreakpoints.remove(key) != null
+    val path = request.source.path ?: return response
+    if (!mayHaveChangedSource(request)) return response
+    val changed = try {
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val builtAt = sourceBuiltAt(path, debugger) ?: programBuiltAt
+        sourceChangedSinceBuild(File(path), request.sourceModified, request.source.checksums.orEmpty(), builtAt)
+    } catch (e: Exception) {
+        log.fine { "Breakpoints: checking $path for changes: ${e.message}" }
+        false
+    }
+    if (!changed) return response
+    changedSourceBreakpoints[key] = ids.filterNotNull().toSet()
+    if (!wasChanged) {
+        consoleMessage("${File(path).name} changed since the program was built; rebuild it to set breakpoints on the right lines.", ctx)
+    }
+    val breakpoints = (response.body["breakpoints"] as? List<*>).orEmpty().map { bp ->
+        (bp as? Map<*, *>)?.let(::changedSourceBreakpoint) ?: bp
+    }
+    return response.copy(body = response.body + ("breakpoints" to breakpoints))
+}
+
+/** DAP breakpoint [bp] marked as in a changed source. */
+private fun changedSourceBreakpoint(bp: Map<*, *>): Map<String, Any?> =
+    bp.entries.associate { (k, v) -> k.toString() to v } + mapOf("verified" to false, "message" to SOURCE_CHANGED_MESSAGE)
+
+/** [event] with its breakpoint still marked if it is in a changed source (see the file header). */
+fun DebugSession.markChangedSourceBreakpoint(event: BreakpointEvent): BreakpointEvent {
+    val id = (event.breakpoint["id"] as? Number)?.toInt() ?: return event
+    if (event.reason == "removed" || changedSourceBreakpoints.values.none { id in it }) return event
+    return event.copy(breakpoint = changedSourceBreakpoint(event.breakpoint))
+}
+
+/**
+ * Modification time (ms) of the newest module whose line tables have
+ * [path] (matched in full, as by [handleBreakpointLocations]), or `null`
+ * if none does or their files cannot be read.
+ */
+private suspend fun sourceBuiltAt(path: String, debugger: SBDebugger): Long? {
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_SOURCE_BUILT_AT)})")
+    return interpreter.handleCommand("script print(_kdap_source_built_at(${pyStr(path)}))").trim()
+        .toLongOrNull()?.takeIf { it > 0 }
+}
+
+/** Python side of [sourceBuiltAt]; prints 0 when no module has the file. */
+private val PY_SOURCE_BUILT_AT = """
+    import os
+
+    def _kdap_source_built_at(path):
+        target = lldb.debugger.GetSelectedTarget()
+        spec = lldb.SBFileSpec(path, False)
+        latest = 0
+        for module in target.module_iter():
+            units = (module.GetCompileUnitAtIndex(c) for c in range(module.GetNumCompileUnits()))
+            if all(cu.FindSupportFileIndex(0, spec, True) == 0xffffffff for cu in units):
+                continue
+            try:
+                latest = max(latest, int(os.path.getmtime(module.GetFileSpec().fullpath) * 1000))
+            except (OSError, TypeError):
+                pass
+        return latest
+""".trimIndent()
+
+// ── init_bp_actions (breakpoints.rs) ─────────────────────────────
+
+/**
+ * Mirrors CodeLLDB's `init_bp_actions`.
+ *
+ * Installs a callback on each breakpoint whose plan needs one and clears
+ * callbacks from breakpoints that no longer do. [setKey] identifies the
+ * breakpoint set (a source, or the instruction breakpoints); [ids] are
+ * the breakpoint IDs lldb-dap returned, in request order (`null` if it
+ * created none).
+ */
+private suspend fun DebugSession.initBreakpointActions(
+    setKey: String,
+    plans: List<BreakpointPlan>,
+    ids: List<Int?>,
+    sites: List<BreakpointSite>,
+    ctx: AsyncRequestContext,
+) {
+    val previous = scriptedBreakpoints[setKey].orEmpty()
+    val scripted = mutableSetOf<Int>()
+
+    val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+    val target = debugger.selectedTarget()
+
+    for ((index, plan) in plans.withIndex()) {
+        val id = ids.getOrNull(index) ?: continue
+        val bp = target.findBreakpointById(id)
+        if (plan.isScripted) {
+            try {
+                val body = breakpointCallbackBody(sites[index].key, plan)
+                bp.setScriptCallbackBody(body)
+                breakpointCallbackBodies[id] = body
+                scripted.add(id)
+            } catch (e: Exception) {
+                consoleMessage("Could not set breakpoint actions ${sites[index].label}: ${e.message}", ctx)
+            }
+        } else if (id in previous) {
+            breakpointCallbackBodies.remove(id)
+            logErrors { bp.clearCallback() }
+        }
+    }
+
+    if (scripted.isEmpty()) {
+        scriptedBreakpoints.remove(setKey)
+    } else {
+        scriptedBreakpoints[setKey] = scripted
+    }
+}
+
+// ── Hardware breakpoints ─────────────────────────────────────────
+
+/**
+ * Serves the breakpoints of [request] hinted `"hardware": true` with
+ * hardware breakpoints, after deleting the ones an earlier request for
+ * source [setKey] created and re-enabling what they stood in for. [ids]
+ * are lldb-dap's breakpoint IDs, in request order.
+ */
+private suspend fun DebugSession.setHintedHardwareBreakpoints(
+    setKey: String,
+    request: SetBreakpointsRequest,
+    ids: List<Int?>,
+    ctx: AsyncRequestContext,
+) {
+    val previous = hintedHardwareBreakpoints.remove(setKey).orEmpty()
+    val path = request.source.path
+    val hinted = request.breakpoints.withIndex().filter { (index, bp) ->
+        bp.hardware == true && ids.getOrNull(index) != null
+    }
+    if (previous.isEmpty() && (hinted.isEmpty() || path == null)) return
+
+    val replaced = JSONArray(previous.map { JSONArray(listOf(it, hardwareBreakpoints[it] ?: 0)) })
+    val wanted = JSONArray(if (path == null) emptyList() else hinted.map { (index, bp) ->
+        JSONArray(listOf(ids[index], path, bp.line, bp.column ?: 0))
+    })
+    previous.forEach { hardwareBreakpoints.remove(it) }
+    val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_HARDWARE_BREAKPOINTS)})")
+    val created = JSONArray(interpreter.handleCommand(
+        "script print(_kdap_hinted_hardware(${pyStr(replaced.toString())}, ${pyStr(wanted.toString())}))").trim())
+    val target = debugger.selectedTarget()
+    val shadows = mutableSetOf<Int>()
+    for (i in 0 until created.length()) {
+        val entry = created.getJSONArray(i)
+        val shadow = entry.getInt(0)
+        val original = entry.getInt(1)
+        hardwareBreakpoints[shadow] = original
+        shadows.add(shadow)
+        copyBreakpointCallback(target, shadow, original)
+    }
+    if (shadows.isNotEmpty()) hintedHardwareBreakpoints[setKey] = shadows
+}
+
+/**
+ * Gives every location of lldb-dap's breakpoints that LLDB could not
+ * write a software breakpoint for a hardware breakpoint instead, and
+ * deletes fallback breakpoints whose breakpoint is gone (see the file
+ * header). Does nothing without a process.
+ */
+internal suspend fun DebugSession.applyHardwareFallback(ctx: AsyncRequestContext) {
+    val hinted = hintedHardwareBreakpoints.values.flatten().toSet()
+    val fallbacks = hardwareBreakpoints.filterKeys { it !in hinted }
+    val existing = JSONArray(fallbacks.map { (shadow, original) -> JSONArray(listOf(shadow, original)) })
+    val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_HARDWARE_BREAKPOINTS)})")
+    val result = JSONObject(interpreter.handleCommand(
+        "script print(_kdap_hardware_fallback(${pyStr(existing.toString())}))").trim())
+    val removed = result.getJSONArray("removed")
+    for (i in 0 until removed.length()) hardwareBreakpoints.remove(removed.getInt(i))
+    val target = debugger.selectedTarget()
+    for ((shadow, original) in fallbacks) {
+        if (shadow in hardwareBreakpoints) copyBreakpointCallback(target, shadow, original)
+    }
+    val created = result.getJSONArray("created")
+    for (i in 0 until created.length()) {
+        val entry = created.getJSONObject(i)
+        val shadow = entry.getInt("shadow")
+        val original = entry.getInt("original")
+        hardwareBreakpoints[shadow] = original
+        copyBreakpointCallback(target, shadow, original)
+        val where = "breakpoint $original at ${entry.getString("address")}"
+        consoleMessage(if (entry.getBoolean("resolved")) {
+            "Could not write $where (read-only code); using a hardware breakpoint."
+        } else {
+            "Could not write $where (read-only code), and no hardware breakpoint is free."
+        }, ctx)
+    }
+}
+
+/** Gives hardware breakpoint [shadow] the KDAP callback of breakpoint [original], or none. */
+private suspend fun DebugSession.copyBreakpointCallback(target: SBTarget, shadow: Int, original: Int) {
+    val bp = target.findBreakpointById(shadow)
+    val body = breakpointCallbackBodies[original]
+    if (body != null) bp.setScriptCallbackBody(body) else bp.clearCallback()
+}
+
+/**
+ * Reports a hit of a KDAP hardware breakpoint as a hit of the breakpoint
+ * it stands in for, the only one the client knows.
+ */
+fun DebugSession.rewriteHardwareBreakpointStop(event: StoppedEvent): StoppedEvent {
+    val ids = event.hitBreakpointIds ?: return event
+    if (ids.none { it in hardwareBreakpoints }) return event
+    val description = event.description?.let { description ->
+        val match = BREAKPOINT_STOP_DESCRIPTION.matchEntire(description)
+        val original = match?.let { hardwareBreakpoints[it.groupValues[1].toInt()] }
+        if (original != null) "breakpoint $original${match.groupValues[2]}" else description
+    }
+    return event.copy(
+        description = description,
+        hitBreakpointIds = ids.map { hardwareBreakpoints[it] ?: it }.distinct(),
+    )
+}
+
+/** lldb-dap's description of a breakpoint stop: `breakpoint <id>.<location>`. */
+private val BREAKPOINT_STOP_DESCRIPTION = Regex("""breakpoint (\d+)(\..*)?""")
+
+/**
+ * Python side of [setHintedHardwareBreakpoints] and
+ * [applyHardwareFallback]. Hardware breakpoints are created with
+ * `target.require-hardware-breakpoint` set, and take the condition and
+ * ignore count of the breakpoint they stand in for. A location whose
+ * software breakpoint could not be written is enabled but unresolved
+ * (has no breakpoint site) at a loaded address.
+ */
+private val PY_HARDWARE_BREAKPOINTS = """
+    import json
+
+    def _kdap_require_hardware(create):
+        interpreter = lldb.debugger.GetCommandInterpreter()
+        result = lldb.SBCommandReturnObject()
+        interpreter.HandleCommand('settings show target.require-hardware-breakpoint', result)
+        required = '= true' in (result.GetOutput() or '')
+        interpreter.HandleCommand('settings set target.require-hardware-breakpoint true', result)
+        try:
+            return create()
+        finally:
+            if not required:
+                interpreter.HandleCommand('settings set target.require-hardware-breakpoint false', result)
+
+    def _kdap_copy_options(original, shadow):
+        shadow.SetCondition(original.GetCondition())
+        shadow.SetIgnoreCount(original.GetIgnoreCount())
+
+    def _kdap_hinted_hardware(replaced, wanted):
+        target = lldb.debugger.GetSelectedTarget()
+        for shadow, original in json.loads(replaced):
+            target.BreakpointDelete(shadow)
+            bp = target.FindBreakpointByID(original)
+            if bp.IsValid():
+                bp.SetEnabled(True)
+        created = []
+        for original, path, line, column in json.loads(wanted):
+            bp = target.FindBreakpointByID(original)
+            if not bp.IsValid():
+                continue
+            spec = lldb.SBFileSpec(path, False)
+            shadow = _kdap_require_hardware(
+                lambda: target.BreakpointCreateByLocation(spec, line, column, 0, lldb.SBFileSpecList()))
+            if not shadow.IsValid():
+                continue
+            bp.SetEnabled(False)
+            _kdap_copy_options(bp, shadow)
+            created.append([shadow.GetID(), original])
+        return json.dumps(created)
+
+    def _kdap_hardware_fallback(existing):
+        target = lldb.debugger.GetSelectedTarget()
+        removed = []
+        for shadow, original in json.loads(existing):
+            bp = target.FindBreakpointByID(original)
+            if bp.IsValid():
+                _kdap_copy_options(bp, target.FindBreakpointByID(shadow))
+            else:
+                target.BreakpointDelete(shadow)
+                removed.append(shadow)
+        created = []
+        if target.GetProcess().IsValid():
+            for i in range(target.GetNumBreakpoints()):
+                bp = target.GetBreakpointAtIndex(i)
+                if not bp.IsEnabled() or bp.IsHardware() or not bp.MatchesName('$DAP_BREAKPOINT_LABEL'):
+                    continue
+                for j in range(bp.GetNumLocations()):
+                    loc = bp.GetLocationAtIndex(j)
+                    load_address = loc.GetLoadAddress()
+                    if not loc.IsEnabled() or loc.IsResolved() or load_address == lldb.LLDB_INVALID_ADDRESS:
+                        continue
+                    address = loc.GetAddress()
+                    shadow = _kdap_require_hardware(lambda: target.BreakpointCreateBySBAddress(address))
+                    if not shadow.IsValid():
+                        continue
+                    loc.SetEnabled(False)
+                    _kdap_copy_options(bp, shadow)
+                    created.append({
+                        'shadow': shadow.GetID(),
+                        'original': bp.GetID(),
+                        'address': hex(load_address),
+                        'resolved': shadow.GetNumResolvedLocations() > 0,
+                    })
+        return json.dumps({'created': created, 'removed': removed})
+""".trimIndent()
+
+// ── handle_set_instruction_breakpoints (breakpoints.rs) ──────────
+
+/** Advertises `supportsInstructionBreakpoints` in a successful `initialize` response. */
+fun addInstructionBreakpointsCapability(response: DapResponse): DapResponse =
+    response.copy(body = response.body + ("supportsInstructionBreakpoints" to true))
+
+/**
+ * Address of [bp] (its reference plus offset) as `0x…` hex, or the
+ * reference and offset as sent if the reference is not an address.
+ */
+internal fun instructionAddress(bp: InstructionBreakpoint): String {
+    val offset = bp.offset ?: 0
+    val base = bp.instructionReference.trim().removePrefix("0x").toULongOrNull(16)
+        ?: return if (offset == 0) bp.instructionReference else "${bp.instructionReference}+$offset"
+    return "0x" + (base + offset.toLong().toULong()).toString(16)
+}
+
+/**
+ * Whether KDAP must rewrite [request] rather than forward it, by the
+ * same rules as [ownsBreakpoints].
+ */
+fun DebugSession.ownsInstructionBreakpoints(request: SetInstructionBreakpointsRequest): Boolean =
+    INSTRUCTION_BREAKPOINTS_KEY in scriptedBreakpoints ||
+        request.breakpoints.any { bp ->
+            val plan = planInstructionBreakpoint(bp)
+            plan.isScripted || plan.nativeCondition != bp.condition || bp.hitCondition != null
+        }
+
+/**
+ * Mirrors CodeLLDB's `handle_set_instruction_breakpoints`.
+ *
+ * Handles conditions the same way as [handleSetBreakpoints]. Callback
+ * state is kept per instruction address, so hit counts survive
+ * re-sends. lldb-dap creates the breakpoints by address; LLDB stops once
+ * on an instruction that also has a source breakpoint, running both
+ * breakpoints' conditions, and lldb-dap lists both IDs in the `stopped`
+ * event's `hitBreakpointIds`.
+ */
+suspend fun DebugSession.handleSetInstructionBreakpoints(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val request = DapMessage.parse(rawJson) as SetInstructionBreakpointsRequest
+    val plans = request.breakpoints.map { planInstructionBreakpoint(it) }
+
+    try {
+        val argsObj = obj.optJSONObject("arguments") ?: JSONObject()
+        rewriteBreakpointArguments(argsObj.optJSONArray("breakpoints"), plans)
+        val backendRequest = JSONObject().apply {
+            put("type", "request")
+            put("command", "setInstructionBreakpoints")
+            put("arguments", argsObj)
+        }
+        val response = ctx.sendRequestToBackendAndAwait(backendRequest.toString())
+
+        val sites = request.breakpoints.map { bp ->
+            val address = instructionAddress(bp)
+            BreakpointSite("$INSTRUCTION_BREAKPOINTS_KEY:$address", "at $address")
+        }
+        if (response.success) {
+            initBreakpointActions(INSTRUCTION_BREAKPOINTS_KEY, plans, breakpointIds(response), sites, ctx)
+        }
+        reportInvalidConditions(plans, sites, ctx)
+
+        ctx.sendEventToClient(response.copy(seq = 0, requestSeq = requestSeq).toJson())
+
+    } catch (e: Exception) {
+        log.warning { "Breakpoints: setInstructionBreakpoints failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "setInstructionBreakpoints", e.message ?: "setInstructionBreakpoints failed")
+    }
+}
+
+// ── Callback generation ──────────────────────────────────────────
+
+/**
+ * Builds the Python body installed via [SBBreakpoint.setScriptCallbackBody].
+ *
+ * [key] identifies the breakpoint's site (see [BreakpointSite]); per-site
+ * state (compiled conditions, hit counts) lives in LLDB's per-session `internal_dict` under that key,
+ * so it survives `setBreakpoints` re-sends that recreate the breakpoint.
+ * Logpoints print their message and return `False`, so they never stop.
+ */
+internal fun breakpointCallbackBody(key: String, plan: BreakpointPlan): String = buildString {
+    appendLine("_key = ${pyStr(key)}")
+    if (plan.pyCondition != null) {
+        appendLine("_cond = ${pyStr(plan.pyCondition)}")
+        appendLine(PY_CONDITION_CHECK)
+    }
+    if (plan.simpleCondition != null) {
+        // Compiled to inline Python over the helpers from initSimpleExpressions.
+        appendLine("_cond = ${pyStr(plan.simpleCondition)}")
+        appendLine("try:")
+        appendLine("    _ok = _kdap_se_truthy(${parseSimpleExpression(plan.simpleCondition).toPython("frame")})")
+        appendLine("except Exception as e:")
+        appendLine("    print('Could not evaluate breakpoint condition %r: %s' % (_cond, e))")
+        appendLine("    _ok = True")
+        appendLine("if not _ok:")
+        appendLine("    return False")
+    }
+    if (plan.hitCondition != null) {
+        appendLine("_hits = internal_dict.setdefault('_kdap_hit_counts', {})")
+        appendLine("_n = _hits.get(_key, 0) + 1")
+        appendLine("_hits[_key] = _n")
+        appendLine("if not (${plan.hitCondition.toPython("_n")}):")
+        appendLine("    return False")
+    }
+    if (plan.logMessage != null) {
+        appendLine(PY_LOG_FORMAT)
+        val parts = plan.logMessage.map { segment ->
+            when (segment) {
+                is LogSegment.Text -> pyStr(segment.text)
+                is LogSegment.Expr -> "_kdap_fmt(${pyStr(segment.expr)})"
+            }
+        }
+        appendLine("print(${pyStr(LOGPOINT_MARKER)} + ''.join([${parts.joinToString(", ")}]))")
+        append("return False")
+    } else {
+        append("return True")
+    }
+}
+
+/**
+ * Evaluates `_cond` against the hit frame, returning `False` (continue)
+ * when it is falsy. Errors are reported and the breakpoint stops, as in
+ * CodeLLDB.
+ */
+private val PY_CONDITION_CHECK = """
+    try:
+        _cache = internal_dict.setdefault('_kdap_conditions', {})
+        _entry = _cache.get(_key)
+        if _entry is None or _entry[0] != _cond:
+            _entry = (_cond, compile(_cond, '<breakpoint condition>', 'eval'))
+            _cache[_key] = _entry
+        class _FrameLocals(dict):
+            def __missing__(self, name):
+                v = frame.FindVariable(name)
+                if not v.IsValid():
+                    v = frame.FindValue(name, lldb.eValueTypeVariableGlobal)
+                if not v.IsValid():
+                    raise KeyError(name)
+                flags = v.GetType().GetCanonicalType().GetTypeFlags()
+                if flags & lldb.eTypeIsFloat:
+                    return float(v.GetValue())
+                if flags & lldb.eTypeIsInteger and flags & lldb.eTypeIsSigned:
+                    return v.GetValueAsSigned()
+                if flags & lldb.eTypeIsScalar:
+                    return v.GetValueAsUnsigned()
+                return v
+        if not eval(_entry[1], {'lldb': lldb, 'frame': frame}, _FrameLocals()):
+            return False
+    except Exception as e:
+        print('Could not evaluate breakpoint condition %r: %s' % (_cond, e))
+""".trimIndent()
+
+
+/**
+ * Defines `_kdap_fmt(expr)`, which evaluates a logpoint placeholder in
+ * the hit frame and renders it like the Variables view: the summary if
+ * there is one, otherwise the value, or `<error: ...>` on failure.
+ */
+private val PY_LOG_FORMAT = """
+    def _kdap_fmt(expr):
+        v = frame.EvaluateExpression(expr)
+        if not v.GetError().Success():
+            return '<error: %s>' % v.GetError().GetCString()
+        s = v.GetSummary()
+        if s is None:
+            s = v.GetValue()
+        return s if s is not None else ''
+""".trimIndent()
+
+// ── handle_set_function_breakpoints (breakpoints.rs) ─────────────
+
+/**
+ * Returns the regex KDAP breaks on for function breakpoint [name], or
+ * `null` if [name] is a plain function name for lldb-dap. A `/re `
+ * prefix gives the regex directly; a name containing `*` or `?` is a
+ * wildcard pattern.
+ */
+internal fun functionNameRegex(name: String): String? {
+    val trimmed = name.trim()
+    if (trimmed.startsWith(REGEX_FUNCTION_PREFIX)) {
+        return trimmed.removePrefix(REGEX_FUNCTION_PREFIX).trim().ifEmpty { null }
+    }
+    if ('*' !in trimmed && '?' !in trimmed) return null
+    val regex = StringBuilder("^")
+    for (c in trimmed) {
+        when (c) {
+            '*' -> regex.append(".*")
+            '?' -> regex.append('.')
+            in "\\.^\$|()[]{}+" -> regex.append('\\').append(c)
+            else -> regex.append(c)
+        }
+    }
+    return regex.append(RUST_HASH_SUFFIX).append('$').toString()
+}
+
+/**
+ * Whether KDAP must handle [request]: some name is a pattern, or
+ * pattern breakpoints from an earlier request need removing.
+ */
+fun DebugSession.ownsFunctionBreakpoints(request: SetFunctionBreakpointsRequest): Boolean =
+    patternFunctionBreakpoints.isNotEmpty() ||
+        request.breakpoints.any { functionNameRegex(it.name) != null }
+
+/**
+ * Mirrors CodeLLDB's `handle_set_function_breakpoints`.
+ *
+ * Forwards the plain names to lldb-dap, replaces the session's pattern
+ * breakpoints with new regex breakpoints, and responds with both sets of
+ * results in request order.
+ */
+suspend fun DebugSession.handleSetFunctionBreakpoints(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val request = DapMessage.parse(rawJson) as SetFunctionBreakpointsRequest
+    val regexes = request.breakpoints.map { functionNameRegex(it.name) }
+
+    try {
+        // Forward the plain entries as sent so fields KDAP doesn't model
+        // still reach lldb-dap. An empty list clears lldb-dap's own.
+        val argsObj = obj.optJSONObject("arguments") ?: JSONObject()
+        val bpArray = argsObj.optJSONArray("breakpoints") ?: JSONArray()
+        val plain = JSONArray()
+        for (i in 0 until bpArray.length()) {
+            if (regexes.getOrNull(i) == null) plain.put(bpArray.get(i))
+        }
+        argsObj.put("breakpoints", plain)
+        val backendRequest = JSONObject().apply {
+            put("type", "request")
+            put("command", "setFunctionBreakpoints")
+            put("arguments", argsObj)
+        }
+        val response = ctx.sendRequestToBackendAndAwait(backendRequest.toString())
+        if (!response.success) {
+            ctx.sendEventToClient(response.copy(seq = 0, requestSeq = requestSeq).toJson())
+            return
+        }
+
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val target = debugger.selectedTarget()
+        val results = stateLock.withLock {
+            for (id in patternFunctionBreakpoints) logErrors { target.breakpointDelete(id) }
+            patternFunctionBreakpoints = emptyList()
+
+            val created = mutableListOf<Int>()
+            val backendResults = (response.body["breakpoints"] as? List<*>).orEmpty().iterator()
+            request.breakpoints.mapIndexed { index, fbp ->
+                val regex = regexes[index]
+                if (regex == null) {
+                    backendResults.takeIf { it.hasNext() }?.next() ?: mapOf("verified" to false)
+                } else {
+                    try {
+                        val bp = target.breakpointCreateByRegex(regex)
+                        created.add(bp.id())
+                        initPatternBreakpoint(bp, fbp)
+                        mapOf("id" to bp.id(), "verified" to (bp.numLocations() > 0))
+                    } catch (e: Exception) {
+                        mapOf("verified" to false, "message" to (e.message ?: "Could not create breakpoint"))
+                    }
+                }
+            }.also { patternFunctionBreakpoints = created }
+        }
+
+        val body = response.body + ("breakpoints" to results)
+        ctx.sendEventToClient(response.copy(seq = 0, requestSeq = requestSeq, body = body).toJson())
+
+    } catch (e: Exception) {
+        log.warning { "Breakpoints: setFunctionBreakpoints failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "setFunctionBreakpoints", e.message ?: "setFunctionBreakpoints failed")
+    }
+}
+
+/**
+ * Applies [fbp]'s condition and hit condition to the pattern breakpoint
+ * [bp] the way lldb-dap does for its own, and labels it so lldb-dap
+ * reports its location changes.
+ */
+private suspend fun initPatternBreakpoint(bp: SBBreakpoint, fbp: FunctionBreakpoint) {
+    bp.addName(DAP_BREAKPOINT_LABEL)
+    fbp.condition?.trim()?.ifEmpty { null }?.let { bp.setCondition(it) }
+    fbp.hitCondition?.trim()?.toIntOrNull()?.takeIf { it > 0 }?.let { bp.setIgnoreCount(it - 1) }
+}
+
+// ── Breakpoint locations ─────────────────────────────────────────
+
+/**
+ * Narrows the `(line, column)` line-table entries found for [request]'s
+ * lines to its column bounds, sorted and without duplicates. Column `0`
+ * (unknown) becomes `null`, and is dropped on lines that also have
+ * known columns.
+ */
+internal fun filterBreakpointLocations(
+    entries: List<Pair<Int, Int>>,
+    request: BreakpointLocationsRequest,
+): List<Pair<Int, Int?>> {
+    val endLine = request.endLine ?: request.line
+    val inRange = entries.filter { (line, column) ->
+        line in request.line..endLine &&
+            (column == 0 || request.column == null || line != request.line || column >= request.column) &&
+            (column == 0 || request.endColumn == null || line != endLine || column <= request.endColumn)
+    }
+    val linesWithColumns = inRange.filter { it.second != 0 }.map { it.first }.toSet()
+    return inRange
+        .filter { (line, column) -> column != 0 || line !in linesWithColumns }
+        .distinct()
+        .sortedWith(compareBy({ it.first }, { it.second }))
+        .map { (line, column) -> line to column.takeIf { it != 0 } }
+}
+
+/**
+ * Handles `breakpointLocations` for a source with a path. This is a KDAP
+ * extension; CodeLLDB has no counterpart.
+ *
+ * lldb-dap only searches compile units whose main file is the source,
+ * so files that are part of a larger compile unit (every Rust module
+ * other than the crate root) report no locations. KDAP searches every
+ * compile unit that lists the file, matching it by full path or, when no
+ * compile unit lists the full path (e.g. a binary built elsewhere), by
+ * file name, and responds with the line-table entries in the requested
+ * range. The compile units found for a path are cached until the
+ * target's modules or their compile units change.
+ */
+suspend fun DebugSession.handleBreakpointLocations(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val request = DapMessage.parse(rawJson) as BreakpointLocationsRequest
+
+    try {
+        val path = request.source.path ?: throw SBError("breakpointLocations needs a source path")
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val interpreter = debugger.commandInterpreter()
+        interpreter.handleCommand("script exec(${pyStr(PY_BREAKPOINT_LOCATIONS)})")
+        val output = interpreter.handleCommand(
+            "script print(_kdap_breakpoint_locations(" +
+                "${pyStr(path)}, ${request.line}, ${request.endLine ?: request.line}))").trim()
+        val found = JSONArray(output)
+        val entries = (0 until found.length()).map { i ->
+            val entry = found.getJSONArray(i)
+            entry.getInt(0) to entry.getInt(1)
+        }
+        val locations = filterBreakpointLocations(entries, request).map { (line, column) ->
+            if (column == null) mapOf("line" to line) else mapOf("line" to line, "column" to column)
+        }
+        sendSuccessResponse(ctx, requestSeq, "breakpointLocations", mapOf("breakpoints" to locations))
+
+    } catch (e: Exception) {
+        log.warning { "Breakpoints: breakpointLocations failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "breakpointLocations", e.message ?: "breakpointLocations failed")
+    }
+}
+
+/**
+ * Python side of [handleBreakpointLocations]: the `[line, column]`
+ * line-table entries for `path` between `start_line` and `end_line`, as
+ * JSON. The compile units listing `path` are those listing its full
+ * path or, if there are none, its file name; they are cached per path
+ * in `_kdap_bp_location_cus`, keyed by each module's compile unit count.
+ */
+private val PY_BREAKPOINT_LOCATIONS = """
+    import json, os
+
+    if '_kdap_bp_location_cus' not in globals():
+        _kdap_bp_location_cus = {'modules': None, 'paths': {}}
+
+    def _kdap_find_location_cus(target, path):
+        name = os.path.basename(path)
+        for full in (True, False):
+            spec = lldb.SBFileSpec(path if full else name, False)
+            cus = []
+            for module in target.module_iter():
+                for c in range(module.GetNumCompileUnits()):
+                    cu = module.GetCompileUnitAtIndex(c)
+                    if cu.FindSupportFileIndex(0, spec, full) != 0xffffffff:
+                        cus.append(cu)
+            if cus:
+                return cus, full
+        return [], False
+
+    def _kdap_location_cus(target, path):
+        modules = tuple((m.GetFileSpec().fullpath, m.GetNumCompileUnits()) for m in target.module_iter())
+        if _kdap_bp_location_cus['modules'] != modules:
+            _kdap_bp_location_cus.update(modules=modules, paths={})
+        paths = _kdap_bp_location_cus['paths']
+        if path not in paths:
+            paths[path] = _kdap_find_location_cus(target, path)
+        return paths[path]
+
+    def _kdap_breakpoint_locations(path, start_line, end_line):
+        target = lldb.debugger.GetSelectedTarget()
+        name = os.path.basename(path)
+        cus, full = _kdap_location_cus(target, path)
+        found = set()
+        for cu in cus:
+            for i in range(cu.GetNumLineEntries()):
+                entry = cu.GetLineEntryAtIndex(i)
+                line = entry.GetLine()
+                if line < start_line or line > end_line:
+                    continue
+                file = entry.GetFileSpec()
+                if file.GetFilename() != name:
+                    continue
+                if full and os.path.normpath(file.fullpath or '') != os.path.normpath(path):
+                    continue
+                found.add((line, entry.GetColumn()))
+        return json.dumps(sorted(found))
+""".trimIndent()
+
+// ── handle_set_data_breakpoints (breakpoints.rs) ─────────────────
+
+/** A watched memory range emulated by single-stepping. */
+data class SoftwareWatchpoint(val address: Long, val size: Int)
+
+/**
+ * Parses lldb-dap's data breakpoint ID (`"<hex address>/<size>"`), or
+ * returns `null` if [dataId] is not in that form.
+ */
+internal fun parseDataId(dataId: String): SoftwareWatchpoint? {
+    val parts = dataId.split('/')
+    if (parts.size != 2) return null
+    val address = parts[0].removePrefix("0x").toLongOrNull(16) ?: return null
+    val size = parts[1].toIntOrNull()?.takeIf { it > 0 } ?: return null
+    return SoftwareWatchpoint(address, size)
+}
+
+/**
+ * Mirrors CodeLLDB's `handle_set_data_breakpoints`.
+ *
+ * Relays the request to lldb-dap, then turns each write watchpoint it
+ * could not create into a [SoftwareWatchpoint] and reports it as
+ * verified. Read watchpoints stay unverified: software watching only
+ * detects changes.
+ */
+suspend fun DebugSession.handleSetDataBreakpoints(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val request = DapMessage.parse(rawJson) as SetDataBreakpointsRequest
+
+    try {
+        val backendRequest = JSONObject().apply {
+            put("type", "request")
+            put("command", "setDataBreakpoints")
+            put("arguments", obj.optJSONObject("arguments") ?: JSONObject())
+        }
+        val response = ctx.sendRequestToBackendAndAwait(backendRequest.toString())
+
+        val software = mutableMapOf<String, SoftwareWatchpoint>()
+        val results = (response.body["breakpoints"] as? List<*>).orEmpty().mapIndexed { index, result ->
+            val fields = (result as? Map<*, *>)?.entries?.associate { (k, v) -> k.toString() to v }
+                ?: return@mapIndexed result
+            val dataBp = request.breakpoints.getOrNull(index)
+            val watch = dataBp?.takeIf { (it.accessType ?: "write") == "write" }?.let { parseDataId(it.dataId) }
+            if (fields["verified"] == true || dataBp == null || watch == null) {
+                result
+            } else {
+                software[dataBp.dataId] = watch
+                fields + mapOf("verified" to true, "message" to SOFTWARE_WATCHPOINT_MESSAGE)
+            }
+        }
+        syncSoftwareWatchpoints(software, ctx)
+        if (software.isNotEmpty()) consoleMessage(SOFTWARE_WATCHPOINT_MESSAGE, ctx)
+
+        val body = if (response.success) response.body + ("breakpoints" to results) else response.body
+        ctx.sendEventToClient(response.copy(seq = 0, requestSeq = requestSeq, body = body).toJson())
+
+    } catch (e: Exception) {
+        log.warning { "Breakpoints: setDataBreakpoints failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "setDataBreakpoints", e.message ?: "setDataBreakpoints failed")
+    }
+}
+
+/**
+ * Replaces the session's software watchpoints with [watches] and
+ * publishes them to LLDB's script interpreter, where the thread plan
+ * reads them. The plan class is (re)defined whenever any are active.
+ */
+private suspend fun DebugSession.syncSoftwareWatchpoints(
+    watches: Map<String, SoftwareWatchpoint>,
+    ctx: AsyncRequestContext,
+) {
+    if (watches.isEmpty() && softwareWatchpoints.isEmpty()) return
+    softwareWatchpoints.clear()
+    softwareWatchpoints.putAll(watches)
+
+    val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+    val interpreter = debugger.commandInterpreter()
+    if (watches.isNotEmpty()) {
+        interpreter.handleCommand("script exec(${pyStr(PY_SOFTWARE_WATCH_PLAN)})")
+    }
+    val entries = watches.entries.joinToString(", ") { (id, w) -> "${pyStr(id)}: (${w.address}, ${w.size})" }
+    interpreter.handleCommand("script _kdap_sw_watches = {$entries}")
+}
+
+/**
+ * Handles `continue` while software watchpoints are active: resumes the
+ * requested thread under the software watchpoint thread plan instead of
+ * letting lldb-dap resume the process freely. As for any other resume,
+ * it first releases the threads a single-thread `continue` suspended and
+ * an async step's breakpoints.
+ */
+suspend fun DebugSession.handleContinueWithSoftwareWatchpoints(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val threadId = obj.optJSONObject("arguments")?.optLong("threadId") ?: 0L
+    try {
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val thread = debugger.selectedTarget().process().threads().firstOrNull { it.threadId() == threadId }
+            ?: throw SBError("Invalid thread ID $threadId")
+        releaseStepState(debugger)
+        softwareWatchStepping = true
+        thread.stepUsingScriptedThreadPlan(SOFTWARE_WATCH_PLAN)
+        sendSuccessResponse(ctx, requestSeq, "continue")
+    } catch (e: Exception) {
+        softwareWatchStepping = false
+        log.warning { "Breakpoints: continue under software watchpoints failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "continue", e.message ?: "continue failed")
+    }
+}
+
+/**
+ * Reports the stop that ends a software-watched `continue` as a data
+ * breakpoint hit. lldb-dap sees the completed thread plan as a `step`.
+ */
+fun DebugSession.rewriteSoftwareWatchStop(event: StoppedEvent): StoppedEvent {
+    if (!softwareWatchStepping) return event
+    softwareWatchStepping = false
+    if (event.reason != "step") return event
+    return event.copy(reason = "data breakpoint", description = "Software watchpoint hit")
+}
+
+/**
+ * Scripted thread plan that single-steps and stops once any range in
+ * `_kdap_sw_watches` changes. It goes stale (and is discarded) when the
+ * watch list is emptied.
+ */
+private val PY_SOFTWARE_WATCH_PLAN = """
+    class $SOFTWARE_WATCH_PLAN:
+        def __init__(self, thread_plan, args, internal_dict):
+            self.plan = thread_plan
+            self.process = thread_plan.GetThread().GetProcess()
+            self.values = self.snapshot()
+        def snapshot(self):
+            values = {}
+            for key, (addr, size) in _kdap_sw_watches.items():
+                e = lldb.SBError()
+                values[key] = self.process.ReadMemory(addr, size, e)
+            return values
+        def explains_stop(self, event):
+            return self.plan.GetThread().GetStopReason() == lldb.eStopReasonTrace
+        def should_stop(self, event):
+            values = self.snapshot()
+            changed = any(k in self.values and values[k] != self.values[k] for k in values)
+            self.values = values
+            if changed:
+                self.plan.SetPlanComplete(True)
+            return changed
+        def should_step(self):
+            return True
+        def is_stale(self):
+            return not _kdap_sw_watches
+        def stop_description(self, stream):
+            stream.Print('software watchpoint')
+""".trimIndent()
+
+// ── Exception filters ────────────────────────────────────────────
+
+/** An exception filter KDAP implements itself, as advertised in `initialize`. */
+data class ExceptionFilter(
+    val filter: String,
+    val label: String,
+    val description: String,
+    val default: Boolean = false,
+) {
+    fun toMap(): Map<String, Any?> = mapOf(
+        "filter" to filter,
+        "label" to label,
+        "description" to description,
+        "default" to default,
+    )
+}
+
+internal const val RUST_PANIC_FILTER = "rust_panic"
+private const val CRASH_FILTER_PREFIX = "crash_"
+internal const val ALL_SIGNALS_FILTER = "signal_all"
+private const val SIGNAL_FILTER_PREFIX = "signal_"
+
+/**
+ * A crash-time exception filter: [filter] as advertised, the [functions]
+ * (an LLDB function name regex) it breaks on, and the [stopText] of its
+ * `exception` stops.
+ */
+data class CrashFilter(val filter: ExceptionFilter, val functions: String, val stopText: String)
+
+/** The `crash_*` filters (see the file header). */
+val CRASH_FILTERS = listOf(
+    CrashFilter(
+        ExceptionFilter("${CRASH_FILTER_PREFIX}abort", "Crash: abort", "Stop when the debuggee calls abort"),
+        functions = "^(abort|__GI_abort)$",
+        stopText = "abort() called",
+    ),
+    CrashFilter(
+        ExceptionFilter("${CRASH_FILTER_PREFIX}stack_protector", "Crash: stack smashing",
+            "Stop when the stack protector detects a corrupted stack"),
+        functions = "^__stack_chk_fail(_local)?$",
+        stopText = "Stack smashing detected",
+    ),
+    CrashFilter(
+        ExceptionFilter("${CRASH_FILTER_PREFIX}ubsan", "Crash: undefined behavior",
+            "Stop when UndefinedBehaviorSanitizer reports an error"),
+        functions = "^__ubsan_handle_",
+        stopText = "Undefined behavior detected",
+    ),
+    CrashFilter(
+        ExceptionFilter("${CRASH_FILTER_PREFIX}alloc", "Crash: allocation failure",
+            "Stop when a Rust memory allocation fails"),
+        functions = "^(__rust_alloc_error_handler|__rdl_oom|__rg_oom|" +
+            "alloc::alloc::handle_alloc_error|std::alloc::rust_oom)(::h[0-9a-f]+)?$",
+        stopText = "Memory allocation failed",
+    ),
+)
+
+/** Filters KDAP adds to lldb-dap's `exceptionBreakpointFilters`. */
+val KDAP_EXCEPTION_FILTERS = listOf(
+    ExceptionFilter(RUST_PANIC_FILTER, "Rust: on panic", "Stop when a Rust panic begins", default = true),
+) + CRASH_FILTERS.map { it.filter } + listOf(
+    ExceptionFilter("signal_SIGSEGV", "Signal: SIGSEGV", "Stop when the debuggee receives SIGSEGV"),
+    ExceptionFilter("signal_SIGABRT", "Signal: SIGABRT", "Stop when the debuggee receives SIGABRT"),
+    ExceptionFilter(ALL_SIGNALS_FILTER, "Signal: all", "Stop when the debuggee receives any signal"),
+)
+
+private val KDAP_FILTER_IDS = KDAP_EXCEPTION_FILTERS.map { it.filter }.toSet()
+
+/** Symbols tried, in order, for the Rust panic breakpoint. */
+private val RUST_PANIC_SYMBOLS = listOf("rust_panic", "__rust_start_panic")
+
+/** Appends [KDAP_EXCEPTION_FILTERS] to a successful `initialize` response. */
+fun addExceptionFilters(response: DapResponse): DapResponse {
+    val existing = (response.body["exceptionBreakpointFilters"] as? List<*>).orEmpty()
+    val filters = existing + KDAP_EXCEPTION_FILTERS.map { it.toMap() }
+    return response.copy(body = response.body + ("exceptionBreakpointFilters" to filters))
+}
+
+/** Whether [request] enables or disables any KDAP-implemented filter. */
+fun DebugSession.ownsExceptionBreakpoints(request: SetExceptionBreakpointsRequest): Boolean =
+    enabledExceptionFilters(request).isNotEmpty() || panicBreakpointId != null || crashBreakpointIds.isNotEmpty() ||
+        signalFilters.isNotEmpty()
+
+private fun enabledExceptionFilters(request: SetExceptionBreakpointsRequest): Set<String> =
+    (request.filters + request.filterOptions.map { it.filterId }).filter { it in KDAP_FILTER_IDS }.toSet()
+
+// ── handle_set_exception_breakpoints (breakpoints.rs) ────────────
+
+/**
+ * Mirrors CodeLLDB's `handle_set_exception_breakpoints`.
+ *
+ * Forwards lldb-dap's own filters, then applies the KDAP filters: the
+ * Rust panic and crash breakpoints and signal stop settings.
+ */
+suspend fun DebugSession.handleSetExceptionBreakpoints(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val request = DapMessage.parse(rawJson) as SetExceptionBreakpointsRequest
+    val enabled = enabledExceptionFilters(request)
+
+    try {
+        val argsObj = JSONObject().apply {
+            put("filters", JSONArray(request.filters.filter { it !in KDAP_FILTER_IDS }))
+            val options = request.filterOptions.filter { it.filterId !in KDAP_FILTER_IDS }
+            if (options.isNotEmpty()) put("filterOptions", JSONArray(options.map { it.toJsonObject() }))
+        }
+        val backendRequest = JSONObject().apply {
+            put("type", "request")
+            put("command", "setExceptionBreakpoints")
+            put("arguments", argsObj)
+        }
+        val response = ctx.sendRequestToBackendAndAwait(backendRequest.toString())
+
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val target = debugger.selectedTarget()
+        setPanicBreakpoint(RUST_PANIC_FILTER in enabled, target, ctx)
+        setCrashBreakpoints(enabled, target)
+        signalFilters = enabled.filter { it.startsWith(SIGNAL_FILTER_PREFIX) }.toSet()
+        if (processRunning) {
+            applySignalFilters(target.process(), ctx)
+        }
+
+        ctx.sendEventToClient(response.copy(seq = 0, requestSeq = requestSeq).toJson())
+
+    } catch (e: Exception) {
+        log.warning { "Breakpoints: setExceptionBreakpoints failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "setExceptionBreakpoints", e.message ?: "setExceptionBreakpoints failed")
+    }
+}
+
+/** Creates or deletes the Rust panic breakpoint to match [enabled]. */
+private suspend fun DebugSession.setPanicBreakpoint(enabled: Boolean, target: SBTarget, ctx: AsyncRequestContext) {
+    val existing = panicBreakpointId
+    if (!enabled) {
+        if (existing != null) logErrors { target.breakpointDelete(existing) }
+        panicBreakpointId = null
+        return
+    }
+    if (existing != null) return
+    for (symbol in RUST_PANIC_SYMBOLS) {
+        val bp = target.breakpointCreateByName(symbol)
+        if (bp.numLocations() > 0 || symbol == RUST_PANIC_SYMBOLS.last()) {
+            // Keep the last candidate even if unresolved; it may resolve
+            // once the module defining it loads.
+            panicBreakpointId = bp.id()
+            return
+        }
+        target.breakpointDelete(bp.id())
+    }
+}
+
+/** Creates or deletes the breakpoint of each of [CRASH_FILTERS] to match [enabled]. */
+private suspend fun DebugSession.setCrashBreakpoints(enabled: Set<String>, target: SBTarget) {
+    for (crash in CRASH_FILTERS) {
+        val filter = crash.filter.filter
+        val existing = crashBreakpointIds[filter]
+        if (filter !in enabled) {
+            if (existing != null) logErrors { target.breakpointDelete(existing) }
+            crashBreakpointIds.remove(filter)
+        } else if (existing == null) {
+            // Kept even if unresolved; the functions' modules may load later.
+            crashBreakpointIds[filter] = target.breakpointCreateByRegex(crash.functions).id()
+        }
+    }
+}
+
+/**
+ * Applies [DebugSession.signalFilters] to [process]: signals that are
+ * filtered stop, and signals whose filter was removed get back the stop
+ * setting they had before KDAP changed it.
+ */
+internal suspend fun DebugSession.applySignalFilters(process: SBProcess, ctx: AsyncRequestContext) {
+    if (signalFilters.isEmpty() && signalStopOverrides.isEmpty()) return
+    val signals = process.unixSignals()
+    val wanted = mutableSetOf<Int>()
+    for (filter in signalFilters) {
+        if (filter == ALL_SIGNALS_FILTER) {
+            wanted.addAll(signals.signalNumbers())
+        } else {
+            val name = filter.removePrefix(SIGNAL_FILTER_PREFIX)
+            val signo = signals.signalNumberFromName(name)
+            if (signo != null) wanted.add(signo) else consoleMessage("Unknown signal: $name", ctx)
+        }
+    }
+    for (signo in wanted - signalStopOverrides.keys) {
+        signalStopOverrides[signo] = signals.shouldStop(signo)
+        signals.setShouldStop(signo, true)
+    }
+    for (signo in signalStopOverrides.keys - wanted) {
+        val previous = signalStopOverrides.remove(signo) ?: continue
+        logErrors { signals.setShouldStop(signo, previous) }
+    }
+}
+
+// ── Panic reporting ──────────────────────────────────────────────
+
+/** Upper bound on remembered panic output, so runaway stderr can't grow it. */
+private const val MAX_PANIC_MESSAGE = 4096
+
+/**
+ * Remembers the Rust panic message from debuggee stderr. The default
+ * panic hook prints `thread '<name>' panicked at <location>:` followed
+ * by the message before `rust_panic` is called; output after that line
+ * is appended until the next stop.
+ */
+fun DebugSession.observePanicOutput(event: OutputEvent) {
+    if (panicBreakpointId == null || event.category != "stderr") return
+    val current = panicMessage
+    if (current != null) {
+        if (current.length < MAX_PANIC_MESSAGE) panicMessage = (current + event.output).take(MAX_PANIC_MESSAGE)
+        return
+    }
+    val index = event.output.indexOf("panicked at")
+    if (index < 0) return
+    val lineStart = event.output.lastIndexOf('\n', index) + 1
+    panicMessage = event.output.substring(lineStart).take(MAX_PANIC_MESSAGE)
+}
+
+/** Formats remembered panic output for display, dropping `note:` hints. */
+internal fun formatPanicMessage(output: String): String =
+    output.lines().filterNot { it.startsWith("note:") }.joinToString("\n").trim()
+
+/**
+ * Reports a hit of the Rust panic breakpoint as an `exception` stop
+ * with the panic message. Every stop clears the remembered message.
+ */
+fun DebugSession.rewritePanicStop(event: StoppedEvent): StoppedEvent {
+    val message = panicMessage
+    panicMessage = null
+    lastPanic = null
+    val id = panicBreakpointId ?: return event
+    if (!isBreakpointStop(event, id)) return event
+    val text = message?.let { formatPanicMessage(it) }?.ifEmpty { null } ?: "Rust panic"
+    lastPanic = event.threadId?.let { it to text }
+    return event.copy(reason = "exception", description = text, text = text)
+}
+
+/**
+ * Reports a hit of a crash filter's breakpoint as an `exception` stop
+ * with the filter's stop text. Every stop clears the last crash.
+ */
+fun DebugSession.rewriteCrashStop(event: StoppedEvent): StoppedEvent {
+    lastCrash = null
+    val filter = crashBreakpointIds.entries.firstOrNull { isBreakpointStop(event, it.value) }?.key ?: return event
+    val crash = CRASH_FILTERS.first { it.filter.filter == filter }
+    lastCrash = event.threadId?.let { it to crash }
+    return event.copy(reason = "exception", description = crash.stopText, text = crash.stopText)
+}
+
+/** Whether [event] is a hit of breakpoint [id]. */
+private fun isBreakpointStop(event: StoppedEvent, id: Int): Boolean =
+    event.hitBreakpointIds?.contains(id) == true || event.description?.startsWith("breakpoint $id.") == true
+
+/**
+ * Whether `exceptionInfo` for [threadId] is about the last Rust panic or
+ * crash stop (lldb-dap would only describe the breakpoint).
+ */
+fun DebugSession.ownsExceptionInfo(request: ExceptionInfoRequest): Boolean =
+    lastPanic?.first == request.threadId || lastCrash?.first == request.threadId
+
+// ── handle_exception_info (breakpoints.rs) ───────────────────────
+
+/** A frame of a panic's backtrace: its [function] and source position, if any. */
+data class BacktraceFrame(val function: String, val file: String?, val line: Int, val column: Int)
+
+/**
+ * A Rust panic read from the stopped thread: the payload [message], the
+ * [file], [line], and [column] the panic hook was given, the [thread]'s
+ * name, and the thread's [backtrace] from the top frame.
+ */
+data class PanicDetails(
+    val message: String?,
+    val file: String?,
+    val line: Int,
+    val column: Int,
+    val thread: String?,
+    val backtrace: List<BacktraceFrame>,
+)
+
+/** Parses the panic printed by `_kdap_panic_details`. */
+internal fun parsePanicDetails(json: String): PanicDetails {
+    val obj = JSONObject(json)
+    val frames = obj.optJSONArray("backtrace") ?: JSONArray()
+    return PanicDetails(
+        message = obj.optString("message", null),
+        file = obj.optString("file", null),
+        line = obj.optInt("line"),
+        column = obj.optInt("column"),
+        thread = obj.optString("thread", null),
+        backtrace = (0 until frames.length()).map { i ->
+            val frame = frames.getJSONObject(i)
+            BacktraceFrame(
+                function = frame.optString("function", "???"),
+                file = frame.optString("file", null),
+                line = frame.optInt("line"),
+                column = frame.optInt("column"),
+            )
+        },
+    )
+}
+
+/** Formats [frames] as Rust's `RUST_BACKTRACE=full` does: index, function, then `at file:line:column`. */
+internal fun formatBacktrace(frames: List<BacktraceFrame>): String = buildString {
+    val width = frames.size.toString().length.coerceAtLeast(4)
+    frames.forEachIndexed { index, frame ->
+        append(index.toString().padStart(width)).append(": ").append(frame.function).append('\n')
+        if (frame.file != null) {
+            append(" ".repeat(width + 2)).append("at ").append(frame.file)
+            if (frame.line > 0) append(':').append(frame.line)
+            if (frame.column > 0) append(':').append(frame.column)
+            append('\n')
+        }
+    }
+}.trimEnd()
+
+/**
+ * The `exceptionInfo` body for a panic: the panic hook's message, as the
+ * default hook prints it, from [details] where they could be read and
+ * from [fallback] (the stderr message of the stop) otherwise, with the
+ * payload and the backtrace in `details`.
+ */
+internal fun panicExceptionInfo(details: PanicDetails?, fallback: String): Map<String, Any?> {
+    val message = details?.message
+    val description = if (details?.file != null && message != null) {
+        val location = listOf(details.file, details.line, details.column).joinToString(":")
+        "thread '${details.thread ?: "<unnamed>"}' panicked at $location:\n$message"
+    } else {
+        fallback
+    }
+    val extra = mutableMapOf<String, Any?>("message" to (message ?: fallback))
+    if (!details?.backtrace.isNullOrEmpty()) extra["stackTrace"] = formatBacktrace(details!!.backtrace)
+    return mapOf(
+        "exceptionId" to RUST_PANIC_FILTER,
+        "description" to description,
+        "breakMode" to "always",
+        "details" to extra,
+    )
+}
+
+/** The `exceptionInfo` body for a stop of [crash]. */
+internal fun crashExceptionInfo(crash: CrashFilter): Map<String, Any?> = mapOf(
+    "exceptionId" to crash.filter.filter,
+    "description" to crash.stopText,
+    "breakMode" to "always",
+)
+
+/**
+ * Mirrors CodeLLDB's `handle_exception_info` for Rust panics. The
+ * payload and location are read from the thread's
+ * `std::panicking::rust_panic_with_hook` frame (see [PY_PANIC_DETAILS]);
+ * if they can't be, the stderr message of the stop is used. Crash stops
+ * are described by their filter.
+ */
+suspend fun DebugSession.handleExceptionInfo(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val threadId = obj.optJSONObject("arguments")?.optLong("threadId") ?: 0L
+    val crash = lastCrash
+    if (crash != null && crash.first.toLong() == threadId) {
+        sendSuccessResponse(ctx, requestSeq, "exceptionInfo", crashExceptionInfo(crash.second))
+        return
+    }
+    val details = try {
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val interpreter = debugger.commandInterpreter()
+        interpreter.handleCommand("script exec(${pyStr(PY_PANIC_DETAILS)})")
+        parsePanicDetails(interpreter.handleCommand("script print(_kdap_panic_details($threadId))").trim())
+    } catch (e: Exception) {
+        log.warning { "Breakpoints: reading panic details failed: ${e.message}" }
+        null
+    }
+    sendSuccessResponse(ctx, requestSeq, "exceptionInfo", panicExceptionInfo(details, lastPanic?.second ?: "Rust panic"))
+}
+
+/**
+ * Python side of [handleExceptionInfo]. The payload is the
+ * `&mut dyn PanicPayload` argument of `rust_panic_with_hook`; its type
+ * is read from the name of the vtable's first method (such as
+ * `<T as core::panic::PanicPayload>::take_box`), and its message from
+ * the formatted `string` of a `FormatStringPayload`, the `&str` of a
+ * `StaticStrPayload`, the `&str` or `String` of `begin_panic`'s
+ * `Payload`, or the literal pieces of a `fmt::Arguments` without
+ * arguments. Strings are read through the Rust formatters (see
+ * Formatters.kt), or from their `data_ptr` and `length`.
+ */
+private val PY_PANIC_DETAILS = """
+    import json, re
+
+    _KDAP_PANIC_MAX = 4096
+
+    def _kdap_panic_unquote(summary):
+        if not summary or len(summary) < 2 or summary[0] != '"':
+            return None
+        return summary[1:summary.rfind('"')].replace('\\"', '"').replace('\\\\', '\\')
+
+    def _kdap_panic_str(v):
+        for _ in range(8):
+            if not v.IsValid():
+                return None
+            name = v.GetType().GetName() or ''
+            if name.startswith('core::fmt::Arguments'):
+                return _kdap_panic_arguments(v)
+            if name.startswith('core::option::Option<'):
+                active = _kdap_enum_active(v.GetNonSyntheticValue())
+                if active is None or active[0] != 'Some' or not active[1]:
+                    return None
+                v = active[1][0]
+                continue
+            text = _kdap_panic_unquote(v.GetSummary())
+            if text is not None:
+                return text
+            ptr, length = v.GetChildMemberWithName('data_ptr'), v.GetChildMemberWithName('length')
+            if ptr.IsValid() and length.IsValid():
+                size = length.GetValueAsUnsigned()
+                if size == 0:
+                    return ''
+                error = lldb.SBError()
+                data = v.GetProcess().ReadMemory(ptr.GetValueAsUnsigned(), min(size, _KDAP_PANIC_MAX), error)
+                return data.decode('utf-8', 'replace').rstrip('\0') if error.Success() else None
+            if v.GetType().IsPointerType() or v.GetType().IsReferenceType():
+                v = v.Dereference()
+            elif v.GetNumChildren() > 0:
+                v = v.GetChildAtIndex(0)
+            else:
+                return None
+        return None
+
+    def _kdap_panic_arguments(v):
+        args = v.GetChildMemberWithName('args')
+        pieces = v.GetChildMemberWithName('pieces')
+        if not pieces.IsValid() or (args.IsValid() and args.GetChildMemberWithName('length').GetValueAsUnsigned() != 0):
+            return None
+        count = pieces.GetChildMemberWithName('length').GetValueAsUnsigned()
+        texts = [_kdap_panic_str(pieces.GetChildAtIndex(i)) for i in range(min(count, 16))]
+        return None if None in texts else ''.join(texts)
+
+    def _kdap_panic_payload(frame):
+        payload = frame.FindVariable('payload')
+        data, vtable = payload.GetChildMemberWithName('pointer'), payload.GetChildMemberWithName('vtable')
+        if not data.IsValid() or not vtable.IsValid():
+            return None
+        process = frame.GetThread().GetProcess()
+        target = process.GetTarget()
+        error = lldb.SBError()
+        method = process.ReadPointerFromMemory(vtable.GetValueAsUnsigned() + 3 * target.GetAddressByteSize(), error)
+        if error.Fail():
+            return None
+        address = target.ResolveLoadAddress(method)
+        function = address.GetFunction()
+        name = function.GetName() if function.IsValid() else address.GetSymbol().GetName()
+        match = re.match(r'^<(.+) as [^<>]+>::\w+(::h[0-9a-f]{16})?$', name or '')
+        payload_type = target.FindFirstType(match.group(1)) if match else lldb.SBType()
+        if not payload_type.IsValid():
+            return None
+        value = target.CreateValueFromAddress('payload', lldb.SBAddress(data.GetValueAsUnsigned(), target), payload_type)
+        for field in ('string', 'inner', '__0', '0'):
+            child = value.GetChildMemberWithName(field)
+            text = _kdap_panic_str(child) if child.IsValid() else None
+            if text is not None:
+                return text
+        return None
+
+    def _kdap_panic_location(location):
+        location = location.Dereference() if location.GetType().IsPointerType() or location.GetType().IsReferenceType() else location
+        file = None
+        for field in ('file', 'filename', 'file_bytes_with_nul'):
+            child = location.GetChildMemberWithName(field)
+            if child.IsValid():
+                file = _kdap_panic_str(child)
+                break
+        line = location.GetChildMemberWithName('line').GetValueAsUnsigned()
+        column = location.GetChildMemberWithName('col').GetValueAsUnsigned()
+        return file, line, column
+
+    def _kdap_panic_details(thread_id):
+        thread = lldb.debugger.GetSelectedTarget().GetProcess().GetThreadByID(thread_id)
+        details = {'thread': thread.GetName(), 'backtrace': []}
+        for frame in thread:
+            name = frame.GetDisplayFunctionName() or frame.GetFunctionName() or '???'
+            entry = frame.GetLineEntry()
+            record = {'function': re.sub(r'::h[0-9a-f]{16}$', '', name)}
+            if entry.IsValid() and entry.GetFileSpec().fullpath:
+                record.update(file=entry.GetFileSpec().fullpath, line=entry.GetLine(), column=entry.GetColumn())
+            details['backtrace'].append(record)
+            if 'message' in details or not name.startswith('std::panicking::rust_panic_with_hook'):
+                continue
+            try:
+                details['message'] = _kdap_panic_payload(frame)
+                file, line, column = _kdap_panic_location(frame.FindVariable('location'))
+                if file:
+                    details.update(file=file, line=line, column=column)
+            except Exception:
+                details['message'] = None
+        if details.get('message') is None:
+            details.pop('message', None)
+        return json.dumps(details)
+""".trimIndent()
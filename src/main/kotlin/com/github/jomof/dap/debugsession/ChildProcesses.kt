@@ -0,0 +1,144 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.sb.SBDebugger
+import com.github.jomof.dap.sb.SBError
+import com.github.jomof.dap.sb.SBTarget
+import com.github.jomof.dap.sb.pyStr
+import org.json.JSONObject
+
+/**
+ * Following child processes (`"followChildProcesses": true` on launch or
+ * attach). This is a KDAP extension; CodeLLDB has no counterpart.
+ *
+ * LLDB follows only the parent across a fork and detaches the child. KDAP
+ * breaks on the process-creating libc calls ([CHILD_PROCESS_FUNCTIONS]);
+ * when one returns in the parent with a new child PID, the breakpoint
+ * callback stops the child with `SIGSTOP` and asks the client, through
+ * lldb-dap's `lldb-dap start-debugging` command, to start a new `attach`
+ * session for it. The child session inherits the parent's configuration
+ * (see [childProcessConfiguration]), including `followChildProcesses`,
+ * and resumes the child once attached. All callbacks return `False`, so
+ * the parent never reports a stop for them.
+ *
+ * Breakpoints reach the child because clients send their breakpoints to
+ * every session. The child runs briefly between the fork and `SIGSTOP`,
+ * and `SIGSTOP` only reaches local processes. Windows creates processes
+ * with `CreateProcess` and has no `SIGSTOP`, so following is Unix-only.
+ */
+
+/** Functions whose return in the parent yields a new child process. */
+internal val CHILD_PROCESS_FUNCTIONS = listOf("fork", "vfork", "posix_spawn", "posix_spawnp")
+
+/** Launch/attach arguments that describe the parent process only. */
+private val PARENT_ONLY_ARGUMENTS = setOf(
+    "program", "coreDump", "args", "cwd", "env", "envFile", "stdio", "pty", "terminal", "console",
+    "stopOnEntry", "noDebug", "requireDebugInfo", "targetCreateCommands", "processCreateCommands",
+    "pid", "gdbRemote", "android", "waitFor", "__restart", "__sessionId", "__childExeced",
+)
+
+/**
+ * The `attach` configuration for a child of the session launched or
+ * attached with [parentArguments], without the child's `pid` (added when
+ * the child appears).
+ */
+internal fun childProcessConfiguration(parentArguments: JSONObject): JSONObject {
+    val config = JSONObject()
+    for (key in parentArguments.keySet()) {
+        if (key !in PARENT_ONLY_ARGUMENTS) config.put(key, parentArguments.get(key))
+    }
+    return config.put("followChildProcesses", true)
+}
+
+/**
+ * Installs the [PY_FOLLOW_CHILDREN] helpers and breaks on each of
+ * [CHILD_PROCESS_FUNCTIONS] in [target].
+ */
+internal suspend fun initChildProcessFollowing(
+    parentArguments: JSONObject,
+    debugger: SBDebugger,
+    target: SBTarget,
+) {
+    if (isWindowsHost()) throw SBError("followChildProcesses is not supported on Windows.")
+    val config = childProcessConfiguration(parentArguments).toString()
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_FOLLOW_CHILDREN)})")
+    interpreter.handleCommand("script _kdap_child_config = json.loads(${pyStr(config)})")
+    for (function in CHILD_PROCESS_FUNCTIONS) {
+        val bp = target.breakpointCreateByName(function)
+        bp.setScriptCallbackBody("return _kdap_child_entry(frame, bp_loc, internal_dict)")
+    }
+}
+
+/**
+ * Breakpoint callbacks for [initChildProcessFollowing].
+ * `_kdap_child_entry` runs on entry to a process-creating call and
+ * breaks once on its return address in the same thread;
+ * `_kdap_child_return` reads the child PID there (the return value, or
+ * `*pid` for `posix_spawn`), stops the child, and requests its session,
+ * marked `__childExeced` when the call has already exec'd the child.
+ */
+private val PY_FOLLOW_CHILDREN = """
+    import json, os, signal
+
+    _kdap_child_config = {}
+    _kdap_child_pid_pointers = {}
+    _KDAP_RETURN_REGISTERS = {
+        'x86_64': 'rax', 'i386': 'eax', 'i686': 'eax',
+        'aarch64': 'x0', 'arm64': 'x0', 'arm64e': 'x0', 'arm': 'r0',
+    }
+
+    def _kdap_child_entry(frame, bp_loc, internal_dict):
+        thread = frame.GetThread()
+        target = thread.GetProcess().GetTarget()
+        pid_pointer = None
+        function = frame.GetFunctionName() or ''
+        if function.startswith('posix_spawn'):
+            pid_pointer = frame.FindRegister('arg1').GetValueAsUnsigned()
+        bp = target.BreakpointCreateByAddress(thread.GetFrameAtIndex(1).GetPC())
+        bp.SetOneShot(True)
+        bp.SetThreadID(thread.GetThreadID())
+        bp.SetScriptCallbackFunction('_kdap_child_return')
+        # The parent of posix_spawn and vfork resumes once the child exec'd.
+        execed = function.startswith('posix_spawn') or function == 'vfork'
+        _kdap_child_pid_pointers[bp.GetID()] = (pid_pointer, execed)
+        return False
+
+    def _kdap_child_return(frame, bp_loc, internal_dict):
+        process = frame.GetThread().GetProcess()
+        target = process.GetTarget()
+        pid_pointer, execed = _kdap_child_pid_pointers.pop(bp_loc.GetBreakpoint().GetID(), (None, False))
+        register = _KDAP_RETURN_REGISTERS.get(target.GetTriple().split('-')[0])
+        if register is None:
+            return False
+        # pid_t and int are 32 bits; the upper half of the register is junk.
+        result = frame.FindRegister(register).GetValueAsUnsigned() & 0xffffffff
+        if result >= 0x80000000:
+            result -= 0x100000000
+        if pid_pointer is None:
+            pid = result
+        elif result == 0:
+            e = lldb.SBError()
+            pid = process.ReadUnsignedFromMemory(pid_pointer, 4, e)
+            if e.Fail():
+                return False
+        else:
+            return False
+        if pid > 0:
+            _kdap_follow_child(target.GetDebugger(), pid, execed)
+        return False
+
+    def _kdap_follow_child(debugger, pid, execed):
+        try:
+            # Keep the child from running ahead of its debug session.
+            os.kill(pid, signal.SIGSTOP)
+        except OSError:
+            return
+        config = dict(_kdap_child_config)
+        config['pid'] = pid
+        if execed:
+            config['__childExeced'] = True
+        config['name'] = '%s (child %d)' % (config.get('name') or 'Child process', pid)
+        # Single quotes delimit the argument for LLDB's command parser.
+        text = json.dumps(config).replace("'", '\\u0027')
+        debugger.HandleCommand("lldb-dap start-debugging attach '%s'" % text)
+""".trimIndent()
@@ -0,0 +1,257 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.sb.createDebugger
+import com.github.jomof.dap.sb.pyStr
+import com.github.jomof.dap.sb.watched
+import org.json.JSONObject
+import java.util.logging.Logger
+
+/**
+ * `evaluate` in the `clipboard` context, which clients send for "Copy
+ * Value". lldb-dap lacks it, so clients copy the displayed value, which
+ * LLDB truncates. KDAP renders the whole value instead and advertises
+ * `supportsClipboardContext`, as CodeLLDB does.
+ *
+ * The rendering is deterministic and does not depend on LLDB's summary
+ * limits:
+ *
+ * - Rust `String`/`&str`, C++ `std::string` (libstdc++ and libc++), and C
+ *   `char` pointers and arrays: the entire contents, quoted (up to 16 MiB).
+ * - Sequences: `[a, b, …]`; maps, C++ `std::map` and `std::unordered_map`
+ *   included: `{key: value, …}`.
+ * - Structs: `Name { field: value, … }`, with base classes as fields
+ *   named by their class and without vtable pointers; tuples: `(a, b)`;
+ *   Rust enums: the active variant, `Some(5)`.
+ * - References and C++ `std::unique_ptr`/`std::shared_ptr` are followed
+ *   (a null smart pointer is `nullptr`); raw pointers are shown as
+ *   addresses.
+ *
+ * At most `_adapterSettings.clipboardMaxElements` elements
+ * ([DEFAULT_CLIPBOARD_MAX_ELEMENTS] by default) are rendered in all; a
+ * container cut short ends in `...`.
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.Clipboard")
+
+/** Elements rendered for one "Copy Value" when the launch configuration sets no limit. */
+internal const val DEFAULT_CLIPBOARD_MAX_ELEMENTS = 10_000
+
+/** Advertises `supportsClipboardContext` in a successful `initialize` response. */
+fun addClipboardCapability(response: DapResponse): DapResponse =
+    response.copy(body = response.body + ("supportsClipboardContext" to true))
+
+/**
+ * Handles `evaluate` in the `clipboard` context: evaluates the
+ * expression as a watch would, ignoring any format suffix, and returns
+ * the full rendering described in the file header.
+ */
+suspend fun DebugSession.handleClipboardEvaluate(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val args = obj.optJSONObject("arguments") ?: JSONObject()
+
+    try {
+        val (text, _) = splitFormatSuffix(args.optString("expression"))
+        val fn = when (val prepared = prepareExpression(text)) {
+            is PreparedExpression.Simple -> "lambda frame: ${prepared.expr.toPython("frame")}"
+            is PreparedExpression.Native -> "lambda frame: _kdap_se_native(frame, ${pyStr(prepared.text)})"
+        }
+        val frameId = if (args.has("frameId")) args.optInt("frameId") else null
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val interpreter = debugger.commandInterpreter()
+        interpreter.handleCommand("script exec(${pyStr(PY_CLIPBOARD)})")
+        val result = JSONObject(interpreter.handleCommand(
+            "script print(_kdap_clipboard($fn, ${framePython(frameId)}, $clipboardMaxElements))").trim())
+        if (result.has("error")) {
+            sendErrorResponse(ctx, requestSeq, "evaluate", result.getString("error"))
+            return
+        }
+        sendSuccessResponse(ctx, requestSeq, "evaluate", mapOf(
+            "result" to result.getString("result"),
+            "variablesReference" to 0,
+        ))
+    } catch (e: Exception) {
+        log.warning { "Clipboard: evaluate failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "evaluate", e.message ?: "evaluate failed")
+    }
+}
+
+/**
+ * Python side of [handleClipboardEvaluate]. Uses the simple expression
+ * runtime to evaluate and the Rust formatters' layout helpers to find
+ * string contents and enum variants. C++ strings and smart pointers are
+ * read from the libstdc++ or libc++ layout.
+ */
+private val PY_CLIPBOARD = """
+    import json, re
+
+    _KDAP_CLIP_MAX_STRING = 1 << 24
+    _KDAP_CLIP_MAX_DEPTH = 16
+    _KDAP_CLIP_CHARS = ('char', 'signed char', 'unsigned char')
+    _KDAP_CLIP_CPP_STRING = re.compile(r'^std::(__cxx11::|__1::)?basic_string<char,')
+    _KDAP_CLIP_CPP_MAP = re.compile(r'^std::(__1::)?(unordered_)?(multi)?map<')
+    _KDAP_CLIP_CPP_SMART_POINTER = re.compile(r'^std::(__1::)?(unique_ptr|shared_ptr)<')
+    _KDAP_CLIP_CPP_POINTER_FIELDS = ('_M_ptr', '_M_t', '_M_head_impl', '__ptr_', '__value_')
+
+    def _kdap_clip_quote(text):
+        return '"%s"' % text.replace('\\', '\\\\').replace('"', '\\"')
+
+    def _kdap_clip_is_char(t):
+        return t.GetUnqualifiedType().GetCanonicalType().GetName() in _KDAP_CLIP_CHARS
+
+    def _kdap_clip_read(v, address, length):
+        if length == 0:
+            return '""'
+        e = lldb.SBError()
+        data = v.GetProcess().ReadMemory(address, min(length, _KDAP_CLIP_MAX_STRING), e)
+        if not e.Success():
+            return None
+        return _kdap_clip_quote(data.decode('utf-8', 'replace'))
+
+    def _kdap_clip_cpp_string(v, raw):
+        data = raw.GetValueForExpressionPath('._M_dataplus._M_p')
+        if data.IsValid():
+            return _kdap_clip_read(v, data.GetValueAsUnsigned(),
+                                   raw.GetChildMemberWithName('_M_string_length').GetValueAsUnsigned())
+        rep = raw.GetChildMemberWithName('__rep_')
+        if not rep.IsValid():
+            rep = raw.GetValueForExpressionPath('.__r_.__value_')
+        short = rep.GetChildMemberWithName('__s')
+        is_long = short.GetChildMemberWithName('__is_long_')
+        if not is_long.IsValid():
+            return v.GetSummary()
+        if is_long.GetValueAsUnsigned():
+            heap = rep.GetChildMemberWithName('__l')
+            return _kdap_clip_read(v, heap.GetChildMemberWithName('__data_').GetValueAsUnsigned(),
+                                   heap.GetChildMemberWithName('__size_').GetValueAsUnsigned())
+        return _kdap_clip_read(v, short.GetChildMemberWithName('__data_').GetLoadAddress(),
+                               short.GetChildMemberWithName('__size_').GetValueAsUnsigned())
+
+    def _kdap_clip_cpp_pointer(v):
+        v = v.GetNonSyntheticValue()
+        for _ in range(_KDAP_CLIP_MAX_DEPTH):
+            if not v.IsValid():
+                return None
+            if v.GetType().IsPointerType():
+                return v
+            for field in _KDAP_CLIP_CPP_POINTER_FIELDS:
+                child = v.GetChildMemberWithName(field)
+                if child.IsValid():
+                    v = child
+                    break
+            else:
+                return None
+        return None
+
+    def _kdap_clip_string(v):
+        t = v.GetType()
+        name = v.GetTypeName() or ''
+        raw = v.GetNonSyntheticValue()
+        if re.match(r'^(alloc::string::)?String${'$'}', name):
+            vec = raw.GetChildMemberWithName('vec')
+            ptr = _kdap_first_pointer(vec)
+            address = ptr.GetValueAsUnsigned() if ptr is not None else 0
+            return _kdap_clip_read(v, address, vec.GetChildMemberWithName('len').GetValueAsUnsigned())
+        if re.match(r'^&(mut )?str${'$'}', name):
+            return _kdap_clip_read(v, raw.GetChildMemberWithName('data_ptr').GetValueAsUnsigned(),
+                                   raw.GetChildMemberWithName('length').GetValueAsUnsigned())
+        if _KDAP_CLIP_CPP_STRING.match(t.GetCanonicalType().GetName() or ''):
+            return _kdap_clip_cpp_string(v, raw)
+        if t.IsPointerType() and _kdap_clip_is_char(t.GetPointeeType()) and v.GetValueAsUnsigned() != 0:
+            e = lldb.SBError()
+            s = v.GetProcess().ReadCStringFromMemory(v.GetValueAsUnsigned(), _KDAP_CLIP_MAX_STRING, e)
+            if not e.Success():
+                return None
+            return _kdap_clip_quote(s.decode('utf-8', 'replace') if isinstance(s, bytes) else s)
+        if t.IsArrayType() and _kdap_clip_is_char(t.GetArrayElementType()):
+            e = lldb.SBError()
+            data = v.GetData().ReadRawData(e, 0, v.GetByteSize())
+            if not e.Success():
+                return None
+            return _kdap_clip_quote(data.split(b'\0', 1)[0].decode('utf-8', 'replace'))
+        return None
+
+    def _kdap_clip_items(children, budget, depth):
+        items = []
+        for child in children:
+            if (child.GetName() or '').startswith('_vptr'):
+                continue
+            if budget[0] <= 0:
+                items.append((None, '...'))
+                break
+            budget[0] -= 1
+            items.append((child.GetName() or '', _kdap_clip_render(child, budget, depth + 1)))
+        return items
+
+    def _kdap_clip_join(items, named):
+        return ', '.join(text if name is None or not named else '%s: %s' % (name, text) for name, text in items)
+
+    def _kdap_clip_render(v, budget, depth):
+        if v.GetError().Fail():
+            raise ValueError(v.GetError().GetCString())
+        text = _kdap_clip_string(v)
+        if text is not None:
+            return text
+        t = v.GetType()
+        name = v.GetTypeName() or ''
+        if depth >= _KDAP_CLIP_MAX_DEPTH:
+            return _kdap_display(v)
+        if t.IsReferenceType() or (t.IsPointerType() and name.startswith('&')):
+            return _kdap_clip_render(v.Dereference(), budget, depth + 1)
+        if t.IsPointerType():
+            return _kdap_display(v)
+        canonical = t.GetCanonicalType().GetName() or ''
+        if _KDAP_CLIP_CPP_SMART_POINTER.match(canonical):
+            ptr = _kdap_clip_cpp_pointer(v)
+            if ptr is not None:
+                if ptr.GetValueAsUnsigned() == 0:
+                    return 'nullptr'
+                return _kdap_clip_render(ptr.Dereference(), budget, depth + 1)
+        if _KDAP_CLIP_CPP_MAP.match(canonical):
+            items = []
+            for i in range(v.GetNumChildren()):
+                if budget[0] <= 0:
+                    items.append('...')
+                    break
+                budget[0] -= 1
+                pair = v.GetChildAtIndex(i)
+                key, value = pair.GetChildMemberWithName('first'), pair.GetChildMemberWithName('second')
+                if key.IsValid() and value.IsValid():
+                    items.append('%s: %s' % (_kdap_clip_render(key, budget, depth + 1),
+                                             _kdap_clip_render(value, budget, depth + 1)))
+                else:
+                    items.append(_kdap_clip_render(pair, budget, depth + 1))
+            return '{%s}' % ', '.join(items)
+        if _kdap_is_rust_enum(t.GetCanonicalType(), None):
+            active = _kdap_enum_active(v.GetNonSyntheticValue())
+            if active is not None:
+                variant, fields = active
+                if not fields:
+                    return variant
+                return '%s(%s)' % (variant, _kdap_clip_join(_kdap_clip_items(fields, budget, depth), False))
+        count = v.GetNumChildren()
+        if count == 0:
+            return _kdap_display(v)
+        children = (v.GetChildAtIndex(i) for i in range(count))
+        items = _kdap_clip_items(children, budget, depth)
+        names = [n for n, _ in items if n is not None]
+        if all(n == '[%d]' % i for i, n in enumerate(names)):
+            return '[%s]' % _kdap_clip_join(items, False)
+        if all(n.startswith('[') and n.endswith(']') for n in names):
+            return '{%s}' % _kdap_clip_join([(n[1:-1] if n else n, s) for n, s in items], True)
+        if all(n.lstrip('_').isdigit() for n in names):
+            prefix = '' if name.startswith('(') else v.GetDisplayTypeName()
+            return '%s(%s)' % (prefix, _kdap_clip_join(items, False))
+        return '%s { %s }' % (v.GetDisplayTypeName(), _kdap_clip_join(items, True))
+
+    def _kdap_clipboard(fn, frame, limit):
+        try:
+            x = fn(frame)
+            if not isinstance(x, lldb.SBValue):
+                return json.dumps({'result': _kdap_se_result(x)['result']})
+            return json.dumps({'result': _kdap_clip_render(x, [limit], 0)})
+        except Exception as e:
+            return json.dumps({'error': str(e)})
+""".trimIndent()
@@ -0,0 +1,175 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.sb.createDebugger
+import com.github.jomof.dap.sb.pyStr
+import com.github.jomof.dap.sb.watched
+import org.json.JSONArray
+import org.json.JSONObject
+import java.util.logging.Logger
+
+/**
+ * Debug console completions for the input KDAP owns (see
+ * [classifyReplInput]):
+ *
+ * | Input                   | Completes                                   |
+ * |-------------------------|---------------------------------------------|
+ * | `` `<command> ``        | the LLDB command line, as LLDB's own completer does |
+ * | `?<name>`               | variables in the frame's scope              |
+ * | `?<expr>.<name>`        | fields of the simple expression `<expr>`, through pointers and references |
+ *
+ * Other input is an LLDB command, which lldb-dap completes itself.
+ * Completion items replace the word at the cursor (`start`/`length`,
+ * 1-based like the request's `column`).
+ *
+ * CodeLLDB's `handle_completions` completes only LLDB commands in the
+ * console; variable and field names are a KDAP extension.
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.Completions")
+
+/** Maximum number of completion items returned. */
+private const val MAX_COMPLETIONS = 200
+
+/** What a `completions` request completes (see [completionContext]). */
+internal sealed class CompletionContext {
+    /** Position (0-based, in the request's text) of the word being completed. */
+    abstract val start: Int
+
+    /** The word between [start] and the cursor. */
+    abstract val word: String
+
+    /** An LLDB command [line] after a backtick, completed at its end. */
+    data class Command(val line: String, override val start: Int, override val word: String) : CompletionContext()
+
+    /**
+     * A name in a `?` expression: a field of [base], or a variable in
+     * scope when [base] is `null`.
+     */
+    data class Name(val base: SimpleExpr?, override val start: Int, override val word: String) : CompletionContext()
+}
+
+/**
+ * Decides what to complete in debug console [text] with the cursor at
+ * 1-based [column], or `null` if lldb-dap should complete it.
+ */
+internal fun completionContext(text: String, column: Int): CompletionContext? {
+    val before = text.take((column - 1).coerceIn(0, text.length))
+    val lead = before.length - before.trimStart().length
+    val input = before.substring(lead)
+    val wordStart = before.indexOfLast { !it.isLetterOrDigit() && it != '_' } + 1
+    val word = before.substring(wordStart)
+    return when {
+        input.startsWith("`") -> {
+            val line = input.drop(1)
+            val start = before.indexOfLast { it.isWhitespace() || it == '`' } + 1
+            CompletionContext.Command(line, start, before.substring(start))
+        }
+        input.startsWith("?") -> when (before.getOrNull(wordStart - 1)) {
+            '.' -> baseExpression(before.substring(lead + 1, wordStart - 1))?.let {
+                CompletionContext.Name(it, wordStart, word)
+            }
+            ':' -> null
+            else -> CompletionContext.Name(null, wordStart, word)
+        }
+        else -> null
+    }
+}
+
+/**
+ * Parses the postfix expression at the end of [text], the part before a
+ * `.` being completed, or returns `null` if there is none.
+ */
+private fun baseExpression(text: String): SimpleExpr? {
+    var depth = 0
+    var start = text.length
+    while (start > 0) {
+        val c = text[start - 1]
+        when {
+            c == ']' -> depth++
+            c == '[' -> if (depth == 0) break else depth--
+            depth > 0 || c.isLetterOrDigit() || c == '_' || c == '.' || c == ':' -> {}
+            else -> break
+        }
+        start--
+    }
+    val base = text.substring(start)
+    if (base.isEmpty()) return null
+    return try {
+        parseSimpleExpression(base)
+    } catch (e: ExpressionError) {
+        null
+    }
+}
+
+/**
+ * Handles `completions` for the input [completionContext] accepts: LLDB
+ * command lines after a backtick, and names in `?` expressions.
+ */
+suspend fun DebugSession.handleCompletions(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val args = obj.optJSONObject("arguments") ?: JSONObject()
+    val text = args.optString("text")
+
+    try {
+        val context = completionContext(text, args.optInt("column", text.length + 1))
+            ?: throw ExpressionError("Nothing to complete")
+        val frameId = if (args.has("frameId")) args.optInt("frameId") else null
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val interpreter = debugger.commandInterpreter()
+        interpreter.handleCommand("script exec(${pyStr(PY_COMPLETIONS)})")
+        val (output, type) = when (context) {
+            is CompletionContext.Command -> interpreter.handleCommand(
+                "script print(_kdap_complete_command(${pyStr(context.line)}, $MAX_COMPLETIONS))") to null
+            is CompletionContext.Name -> {
+                val base = context.base?.let { "lambda frame: ${it.toPython("frame")}" } ?: "None"
+                interpreter.handleCommand(
+                    "script print(_kdap_complete_names($base, ${framePython(frameId)}, " +
+                        "${pyStr(context.word)}, $MAX_COMPLETIONS))",
+                ) to if (context.base == null) "variable" else "field"
+            }
+        }
+        val matches = JSONArray(output.trim())
+        val targets = (0 until matches.length()).map { i ->
+            buildMap {
+                put("label", matches.getString(i))
+                put("start", context.start + 1)
+                put("length", context.word.length)
+                type?.let { put("type", it) }
+            }
+        }
+        sendSuccessResponse(ctx, requestSeq, "completions", mapOf("targets" to targets))
+    } catch (e: Exception) {
+        log.warning { "Completions: completions failed: ${e.message}" }
+        sendSuccessResponse(ctx, requestSeq, "completions", mapOf("targets" to emptyList<Any>()))
+    }
+}
+
+/**
+ * Python side of [handleCompletions]. Both functions return a JSON list
+ * of completed words. Names are completed with the simple expression
+ * helpers, so fields are found the way `?<expr>.<field>` reads them.
+ */
+private val PY_COMPLETIONS = """
+    import json
+
+    def _kdap_complete_command(line, limit):
+        matches = lldb.SBStringList()
+        lldb.debugger.GetCommandInterpreter().HandleCompletion(line, len(line), 0, limit, matches)
+        return json.dumps([matches.GetStringAtIndex(i).rstrip() for i in range(1, matches.GetSize())])
+
+    def _kdap_complete_names(base, frame, prefix, limit):
+        names = []
+        try:
+            if base is None:
+                names = [v.GetName() for v in frame.GetVariables(True, True, True, True)]
+            else:
+                t = _kdap_se_autoderef(_kdap_se_place(base(frame))).GetType()
+                for i in range(t.GetNumberOfFields()):
+                    name = t.GetFieldAtIndex(i).GetName() or ''
+                    names.append(name[2:] if name.startswith('__') and name[2:].isdigit() else name)
+        except Exception:
+            pass
+        return json.dumps(sorted({n for n in names if n and n.startswith(prefix)})[:limit])
+""".trimIndent()
@@ -0,0 +1,559 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.messages.CommonLaunchFields
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.LoadSymbolsRequest
+import com.github.jomof.dap.sb.SBDebugger
+import com.github.jomof.dap.sb.SBError
+import com.github.jomof.dap.sb.createDebugger
+import com.github.jomof.dap.sb.pyStr
+import com.github.jomof.dap.sb.watched
+import org.json.JSONArray
+import org.json.JSONObject
+import java.io.File
+import java.nio.file.Path
+import java.nio.file.Paths
+import java.util.logging.Logger
+
+/**
+ * Separate debug info: symbols stored outside the binary, found by
+ * `.gnu_debuglink`, in build-ID trees, or on a debuginfod server. KDAP
+ * extension; CodeLLDB relies on LLDB's own lookup.
+ *
+ * A Python worker ([initSeparateDebugInfo]) looks at each ELF module the
+ * target loads that has no debug info and tries, in order:
+ *
+ * 1. `.gnu_debuglink`: the file named by the section, next to the
+ *    module, in its `.debug` directory, or under each debug file
+ *    directory followed by the module's directory, as GDB does. The
+ *    file's CRC-32 must match the one in the section.
+ * 2. Build ID: `<directory>/.build-id/xx/yyyy….debug` in each debug file
+ *    directory (`debugFileDirectories`, default `/usr/lib/debug`).
+ * 3. debuginfod: `GET <url>/buildid/<build-id>/debuginfo` on each server
+ *    in `debuginfodUrls` (default `DEBUGINFOD_URLS`). Downloads are
+ *    cached in `<cache>/<build-id>/debuginfo`, the layout of elfutils'
+ *    client, under `DEBUGINFOD_CACHE_PATH` or
+ *    `$XDG_CACHE_HOME/debuginfod_client` ([debuginfodCacheDirectory]).
+ *    A build ID no server has is not asked for again for a day.
+ *
+ * The file found is added with `target symbols add`; LLDB then resolves
+ * pending breakpoints in the module, and the module's `changed` event
+ * (see Modules.kt) reports its symbol file. Each file is announced on the
+ * console. The worker runs on its own thread, so a slow download does not
+ * hold up the session; breakpoints in the module bind when it finishes.
+ *
+ * ## DWARF 5 and split DWARF
+ *
+ * LLDB reads DWARF 5 itself, including the forms that index into
+ * `.debug_str_offsets` and `.debug_addr` (`DW_FORM_strx*`,
+ * `DW_FORM_addrx*`) and `.debug_rnglists`/`.debug_loclists`. With split
+ * DWARF (`-gsplit-dwarf`, Cargo's `-Csplit-debuginfo=unpacked`) the
+ * binary holds only skeleton units, and LLDB reads each unit's `.dwo`
+ * from its recorded build directory, or from the `<program>.dwp` package
+ * that `llvm-dwp` or Cargo's `packed` mode writes next to the program.
+ * KDAP adds the debug file directories to
+ * `target.debug-file-search-paths` ([initSplitDwarfSearchPaths]), where
+ * LLDB also looks for `.dwo` files by name and for `.dwp` packages, so
+ * binaries built elsewhere (CI, Bazel sandboxes) find them. The worker
+ * then checks each loaded ELF module with debug info; a module with
+ * units whose `.dwo` is still missing, which would have no variables or
+ * lines there, is reported on the console with the missing files.
+ *
+ * ## Compressed debug sections
+ *
+ * Debug sections may be compressed, as `.zdebug_*` sections (GNU's older
+ * format, zlib) or as `.debug_*` sections flagged `SHF_COMPRESSED` with a
+ * zlib or zstd header (`-gz`, `--compress-debug-sections`; common in
+ * distribution packages and Bazel builds). LLDB decompresses each section
+ * the first time it reads it, provided it was built with that codec;
+ * builds without zstd see no debug info at all. When a module still has
+ * no compile units after the lookups above, and its debug file (the one
+ * found, else the module itself) has compressed debug sections, the
+ * worker writes a copy with those sections decompressed and adds it with
+ * `target symbols add`. Copies are cached under
+ * `<kdap cache>/debug-sections` ([kdapCacheDirectory]), keyed by the
+ * file's path, size, and modification time, so a file is decompressed
+ * once. zlib comes with Python; zstd uses Python's `compression.zstd`,
+ * the `zstandard` package, or the `zstd` program, whichever is present.
+ *
+ * ## Symbol files on demand
+ *
+ * For a module none of the lookups above could help (a vendor library
+ * shipped with sideband symbols, a symbol file without a matching build
+ * ID), the custom `kdap/loadSymbols` request ([handleLoadSymbols]) names
+ * the module, by path, file name, or build ID, and the `path` of its
+ * symbol file, which KDAP adds with `target symbols add -s`. LLDB then
+ * resolves the breakpoints in the module again; the ones that gain
+ * locations are sent as `breakpoint` `changed` events (see
+ * PendingBreakpoints.kt) and listed in the response, with the module's
+ * symbol file and compile unit count. The module's `changed` event (see
+ * Modules.kt) reports the new symbol file.
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.DebugInfo")
+
+/** Debug file directory searched when the launch configuration names none. */
+internal const val DEFAULT_DEBUG_FILE_DIRECTORY = "/usr/lib/debug"
+
+/** How long a build ID no debuginfod server had is not asked for again. */
+private const val DEBUGINFOD_MISS_SECONDS = 24 * 60 * 60
+
+/** Timeout of each debuginfod connection and read. */
+private const val DEBUGINFOD_TIMEOUT_SECONDS = 30
+
+/**
+ * The debuginfod servers to use: [configured] if given, else the
+ * whitespace-separated URLs in [environment] (`DEBUGINFOD_URLS`).
+ */
+internal fun debuginfodUrls(configured: List<String>?, environment: String?): List<String> =
+    configured ?: environment.orEmpty().split(Regex("\\s+")).filter { it.isNotEmpty() }
+
+/**
+ * The debuginfod cache directory: `DEBUGINFOD_CACHE_PATH`, else
+ * `debuginfod_client` under `XDG_CACHE_HOME` or `~/.cache`.
+ */
+internal fun debuginfodCacheDirectory(environment: Map<String, String>, home: String): Path =
+    environment["DEBUGINFOD_CACHE_PATH"]?.takeIf { it.isNotEmpty() }?.let { Paths.get(it) }
+        ?: Paths.get(environment["XDG_CACHE_HOME"]?.takeIf { it.isNotEmpty() } ?: "$home/.cache", "debuginfod_client")
+
+/**
+ * KDAP's cache directory: `kdap` under `XDG_CACHE_HOME` or `~/.cache`.
+ */
+internal fun kdapCacheDirectory(environment: Map<String, String>, home: String): Path =
+    Paths.get(environment["XDG_CACHE_HOME"]?.takeIf { it.isNotEmpty() } ?: "$home/.cache", "kdap")
+
+/**
+ * Appends the launch configuration's debug file directories to
+ * `target.debug-file-search-paths` (see the file header). Leaves the
+ * setting alone if the configuration names none.
+ */
+internal suspend fun initSplitDwarfSearchPaths(common: CommonLaunchFields, debugger: SBDebugger) {
+    val directories = common.debugFileDirectories.orEmpty()
+    if (directories.isEmpty()) return
+    debugger.commandInterpreter()
+        .handleCommand("settings append target.debug-file-search-paths ${lldbPathList(directories)}")
+}
+
+/** Starts the separate debug info worker on the selected target. */
+internal suspend fun initSeparateDebugInfo(common: CommonLaunchFields, debugger: SBDebugger) {
+    initSplitDwarfSearchPaths(common, debugger)
+    if (isWindowsHost()) return
+    val config = JSONObject()
+        .put("directories", JSONArray(common.debugFileDirectories ?: listOf(DEFAULT_DEBUG_FILE_DIRECTORY)))
+        .put("urls", JSONArray(debuginfodUrls(common.debuginfodUrls, System.getenv("DEBUGINFOD_URLS"))))
+        .put("cache", debuginfodCacheDirectory(System.getenv(), System.getProperty("user.home")).toString())
+        .put("decompressedCache",
+            kdapCacheDirectory(System.getenv(), System.getProperty("user.home")).resolve("debug-sections").toString())
+        .put("missSeconds", DEBUGINFOD_MISS_SECONDS)
+        .put("timeout", DEBUGINFOD_TIMEOUT_SECONDS)
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_DEBUG_INFO)})")
+    interpreter.handleCommand("script _kdap_debug_info_start(${pyStr(config.toString())})")
+}
+
+/**
+ * Python side of [initSeparateDebugInfo]. A listener thread queues the
+ * modules that load; the worker thread looks up the debug info of those
+ * without compile units, and checks the split DWARF units of the others,
+ * one module at a time. LLDB reads a module's `.dwo` files to list them
+ * for `image dump separate-debug-info`. A decompressed copy keeps the
+ * file's layout: the decompressed sections are appended, and their
+ * section headers (and, for `.zdebug_*`, names, through an appended
+ * section name table) point at the new data.
+ */
+private val PY_DEBUG_INFO = """
+    import binascii, hashlib, json, os, queue, shutil, struct, subprocess, tempfile, threading, time, urllib.request, zlib
+
+    if '_kdap_debug_info' not in globals():
+        _kdap_debug_info = {'listener': None}
+
+    def _kdap_debug_info_say(text):
+        output = _kdap_debug_info['output']
+        output.Write((text + '\n').encode('utf-8'))
+        output.Flush()
+
+    def _kdap_crc32(path):
+        crc = 0
+        with open(path, 'rb') as f:
+            for chunk in iter(lambda: f.read(1 << 20), b''):
+                crc = binascii.crc32(chunk, crc)
+        return crc & 0xffffffff
+
+    def _kdap_debuglink(module):
+        section = module.FindSection('.gnu_debuglink')
+        if not section.IsValid():
+            return None
+        error = lldb.SBError()
+        data = section.GetSectionData()
+        raw = data.ReadRawData(error, 0, data.GetByteSize())
+        if error.Fail() or not raw:
+            return None
+        end = raw.find(b'\0')
+        offset = (end + 4) & ~3
+        if end <= 0 or len(raw) < offset + 4:
+            return None
+        order = '>I' if module.GetByteOrder() == lldb.eByteOrderBig else '<I'
+        return raw[:end].decode('utf-8', 'replace'), struct.unpack(order, raw[offset:offset + 4])[0]
+
+    def _kdap_debuglink_file(module, directories):
+        link = _kdap_debuglink(module)
+        if link is None:
+            return None
+        name, crc = link
+        module_dir = os.path.dirname(module.GetFileSpec().fullpath)
+        candidates = [os.path.join(module_dir, name), os.path.join(module_dir, '.debug', name)]
+        candidates += [os.path.join(d, module_dir.lstrip('/'), name) for d in directories]
+        for path in candidates:
+            try:
+                if os.path.isfile(path) and _kdap_crc32(path) == crc:
+                    return path
+            except OSError:
+                pass
+        return None
+
+    def _kdap_build_id(module):
+        return module.GetUUIDString().replace('-', '').lower()
+
+    def _kdap_build_id_file(build_id, directories):
+        for d in directories:
+            path = os.path.join(d, '.build-id', build_id[:2], build_id[2:] + '.debug')
+            if os.path.isfile(path):
+                return path
+        return None
+
+    def _kdap_debuginfod_file(module, build_id, config):
+        directory = os.path.join(config['cache'], build_id)
+        path = os.path.join(directory, 'debuginfo')
+        miss = os.path.join(directory, 'kdap-miss')
+        if os.path.isfile(path):
+            return path
+        try:
+            if time.time() - os.path.getmtime(miss) < config['missSeconds']:
+                return None
+        except OSError:
+            pass
+        for url in config['urls']:
+            request = urllib.request.Request(url.rstrip('/') + '/buildid/' + build_id + '/debuginfo',
+                                             headers={'User-Agent': 'kdap'})
+            temporary = None
+            try:
+                with urllib.request.urlopen(request, timeout=config['timeout']) as response:
+                    _kdap_debug_info_say('Downloading debug info for %s from %s' % (
+                        module.GetFileSpec().GetFilename(), url))
+                    os.makedirs(directory, exist_ok=True)
+                    fd, temporary = tempfile.mkstemp(dir=directory)
+                    with os.fdopen(fd, 'wb') as f:
+                        for chunk in iter(lambda: response.read(1 << 20), b''):
+                            f.write(chunk)
+                    os.replace(temporary, path)
+                    return path
+            except Exception:
+                if temporary is not None and os.path.exists(temporary):
+                    os.unlink(temporary)
+        if config['urls']:
+            try:
+                os.makedirs(directory, exist_ok=True)
+                open(miss, 'w').close()
+            except OSError:
+                pass
+        return None
+
+    def _kdap_is_elf(path):
+        try:
+            with open(path, 'rb') as f:
+                return f.read(4) == b'\x7fELF'
+        except OSError:
+            return False
+
+    def _kdap_debug_info_find(module, config):
+        if module.GetNumCompileUnits() > 0 or not _kdap_is_elf(module.GetFileSpec().fullpath):
+            return None
+        directories = config['directories']
+        path = _kdap_debuglink_file(module, directories)
+        build_id = _kdap_build_id(module)
+        if path is None and build_id:
+            path = _kdap_build_id_file(build_id, directories)
+            if path is None:
+                path = _kdap_debuginfod_file(module, build_id, config)
+        return path
+
+    _KDAP_SHF_COMPRESSED = 0x800
+    _KDAP_COMPRESS_ZLIB = 1
+    _KDAP_COMPRESS_ZSTD = 2
+
+    def _kdap_symbols_add(debugger, module, path, announcement):
+        result = lldb.SBCommandReturnObject()
+        debugger.GetCommandInterpreter().HandleCommand('target symbols add ' + _kdap_command_path(path), result)
+        if result.Succeeded():
+            _kdap_debug_info_say(announcement % (module.GetFileSpec().GetFilename(), path))
+
+    def _kdap_elf_layout(f):
+        ident = f.read(16)
+        if len(ident) < 16 or ident[:4] != b'\x7fELF':
+            return None
+        is64 = ident[4] == 2
+        end = '>' if ident[5] == 2 else '<'
+        f.seek(0x28 if is64 else 0x20)
+        shoff = struct.unpack(end + ('Q' if is64 else 'I'), f.read(8 if is64 else 4))[0]
+        f.seek(0x3a if is64 else 0x2e)
+        shentsize, shnum, shstrndx = struct.unpack(end + 'HHH', f.read(6))
+        if shoff == 0 or shnum == 0 or shstrndx >= shnum:
+            return None
+        header = end + ('IIQQQQIIQQ' if is64 else 'IIIIIIIIII')
+        sections = []
+        for i in range(shnum):
+            f.seek(shoff + i * shentsize)
+            sections.append(list(struct.unpack(header, f.read(struct.calcsize(header)))))
+        f.seek(sections[shstrndx][4])
+        names = f.read(sections[shstrndx][5])
+        return {'is64': is64, 'end': end, 'shoff': shoff, 'shentsize': shentsize, 'header': header,
+                'sections': sections, 'shstrndx': shstrndx, 'names': names}
+
+    def _kdap_section_name(names, offset):
+        end = names.find(b'\0', offset)
+        return names[offset:end if end >= 0 else len(names)].decode('utf-8', 'replace')
+
+    def _kdap_zstd_decompress(data, size):
+        try:
+            from compression import zstd
+            return zstd.decompress(data)
+        except ImportError:
+            pass
+        try:
+            import zstandard
+            return zstandard.ZstdDecompressor().decompress(data, max_output_size=size)
+        except ImportError:
+            pass
+        return subprocess.run(['zstd', '-d', '-c'], input=data, stdout=subprocess.PIPE,
+                              stderr=subprocess.DEVNULL, check=True).stdout
+
+    def _kdap_section_contents(f, layout, section, name):
+        f.seek(section[4])
+        raw = f.read(section[5])
+        if name.startswith('.zdebug_'):
+            if raw[:4] != b'ZLIB':
+                raise ValueError(name)
+            return zlib.decompress(raw[12:]), section[8]
+        if layout['is64']:
+            kind, _, size, align = struct.unpack(layout['end'] + 'IIQQ', raw[:24])
+            data = raw[24:]
+        else:
+            kind, size, align = struct.unpack(layout['end'] + 'III', raw[:12])
+            data = raw[12:]
+        if kind == _KDAP_COMPRESS_ZLIB:
+            return zlib.decompress(data), align
+        if kind == _KDAP_COMPRESS_ZSTD:
+            return _kdap_zstd_decompress(data, size), align
+        raise ValueError(name)
+
+    def _kdap_decompress_into(f, layout, compressed, out):
+        f.seek(0)
+        shutil.copyfileobj(f, out)
+        sections = layout['sections']
+        names = bytearray(layout['names'])
+        for i, name in compressed:
+            section = sections[i]
+            data, align = _kdap_section_contents(f, layout, section, name)
+            position = out.seek(0, os.SEEK_END)
+            offset = (position + max(align, 1) - 1) // max(align, 1) * max(align, 1)
+            out.write(b'\0' * (offset - position) + data)
+            section[2] &= ~_KDAP_SHF_COMPRESSED
+            section[4], section[5], section[8] = offset, len(data), max(align, 1)
+            if name.startswith('.zdebug_'):
+                section[0] = len(names)
+                names += ('.' + name[2:]).encode('utf-8') + b'\0'
+        if len(names) != len(layout['names']):
+            table = sections[layout['shstrndx']]
+            table[4], table[5] = out.seek(0, os.SEEK_END), len(names)
+            out.write(bytes(names))
+        for i, section in enumerate(sections):
+            out.seek(layout['shoff'] + i * layout['shentsize'])
+            out.write(struct.pack(layout['header'], *section))
+
+    def _kdap_decompressed_file(path, config):
+        try:
+            info = os.stat(path)
+        except OSError:
+            return None
+        key = hashlib.sha1(('%s\0%d\0%d' % (path, info.st_size, info.st_mtime_ns)).encode('utf-8')).hexdigest()
+        directory = os.path.join(config['decompressedCache'], key)
+        copy = os.path.join(directory, os.path.basename(path) + '.debug')
+        if os.path.isfile(copy):
+            return copy
+        with open(path, 'rb') as f:
+            layout = _kdap_elf_layout(f)
+            if layout is None:
+                return None
+            compressed = []
+            for i, section in enumerate(layout['sections']):
+                name = _kdap_section_name(layout['names'], section[0])
+                if name.startswith('.zdebug_') or (name.startswith('.debug_') and section[2] & _KDAP_SHF_COMPRESSED):
+                    compressed.append((i, name))
+            if not compressed:
+                return None
+            os.makedirs(directory, exist_ok=True)
+            fd, temporary = tempfile.mkstemp(dir=directory)
+            try:
+                with os.fdopen(fd, 'w+b') as out:
+                    _kdap_decompress_into(f, layout, compressed, out)
+                os.replace(temporary, copy)
+            finally:
+                if os.path.exists(temporary):
+                    os.unlink(temporary)
+        return copy
+
+    def _kdap_command_path(path):
+        return '"%s"' % path.replace('\\', '\\\\').replace('"', '\\"')
+
+    def _kdap_split_dwarf_check(debugger, module):
+        path = module.GetFileSpec().fullpath
+        if module.GetNumCompileUnits() == 0 or not _kdap_is_elf(path):
+            return
+        result = lldb.SBCommandReturnObject()
+        debugger.GetCommandInterpreter().HandleCommand(
+            'image dump separate-debug-info --json ' + _kdap_command_path(path), result)
+        if not result.Succeeded():
+            return
+        try:
+            infos = json.loads(result.GetOutput())
+        except ValueError:
+            return
+        missing = []
+        for info in infos:
+            if info.get('type') != 'dwo':
+                continue
+            for unit in info.get('separate-debug-info-files', []):
+                if not unit.get('loaded', False):
+                    missing.append(unit.get('dwo_name') or '?')
+        if not missing:
+            return
+        name = module.GetFileSpec().GetFilename()
+        shown = ', '.join(missing[:3]) + (', ...' if len(missing) > 3 else '')
+        _kdap_debug_info_say(
+            '%s: %d split DWARF unit(s) have no .dwo file (%s), so they have no variables or source lines. '
+            'Restore the .dwo files to their build directory, or package them as %s.dwp (llvm-dwp, or '
+            'Cargo -Csplit-debuginfo=packed) next to the program or in a debugFileDirectories entry.'
+            % (name, len(missing), shown, name))
+
+    def _kdap_debug_info_work(debugger, config, work):
+        seen = set()
+        while True:
+            module = work.get()
+            if module is None:
+                return
+            key = module.GetFileSpec().fullpath
+            if key in seen:
+                continue
+            seen.add(key)
+            try:
+                path = _kdap_debug_info_find(module, config)
+                if path is not None:
+                    _kdap_symbols_add(debugger, module, path, 'Loaded debug info for %s from %s')
+                if module.GetNumCompileUnits() == 0 and _kdap_is_elf(key):
+                    source = path if path is not None else key
+                    copy = _kdap_decompressed_file(source, config)
+                    if copy is not None:
+                        _kdap_symbols_add(debugger, module, copy, 'Loaded decompressed debug info for %s from %s')
+                _kdap_split_dwarf_check(debugger, module)
+            except Exception:
+                pass
+
+    def _kdap_debug_info_listen(listener, work):
+        event = lldb.SBEvent()
+        while _kdap_debug_info['listener'] is listener:
+            if not listener.WaitForEvent(1, event):
+                continue
+            for i in range(lldb.SBTarget.GetNumModulesFromEvent(event)):
+                work.put(lldb.SBTarget.GetModuleAtIndexFromEvent(i, event))
+        work.put(None)
+
+    def _kdap_debug_info_start(config_json):
+        config = json.loads(config_json)
+        debugger = lldb.debugger
+        target = debugger.GetSelectedTarget()
+        listener = lldb.SBListener('kdap.debug-info')
+        target.GetBroadcaster().AddListener(listener, lldb.SBTarget.eBroadcastBitModulesLoaded)
+        _kdap_debug_info.update(listener=listener, output=debugger.GetOutputFile())
+        work = queue.Queue()
+        for module in target.module_iter():
+            work.put(module)
+        threading.Thread(target=_kdap_debug_info_work, args=(debugger, config, work), daemon=True).start()
+        threading.Thread(target=_kdap_debug_info_listen, args=(listener, work), daemon=True).start()
+""".trimIndent()
+
+/** Handles `kdap/loadSymbols` (see the file header). */
+suspend fun DebugSession.handleLoadSymbols(rawJson: String, ctx: AsyncRequestContext) {
+    val requestSeq = JSONObject(rawJson).optInt("seq", 0)
+    try {
+        val request = DapMessage.parse(rawJson) as LoadSymbolsRequest
+        if (request.module.isEmpty()) throw SBError("\"module\" is required.")
+        if (!File(request.path).isFile) throw SBError("\"path\" ${request.path} does not exist.")
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val interpreter = debugger.commandInterpreter()
+        interpreter.handleCommand("script exec(${pyStr(PY_LOAD_SYMBOLS)})")
+        val result = JSONObject(interpreter.handleCommand(
+            "script print(_kdap_load_symbols(${pyStr(request.module)}, ${pyStr(request.path)}))").trim())
+        if (result.has("error")) throw SBError(result.getString("error"))
+        val bound = result.getJSONArray("breakpoints")
+        consoleMessage("Loaded symbols for ${result.getString("module")} from ${request.path}" +
+            if (bound.length() == 0) "" else "; ${bound.length()} breakpoint(s) bound", ctx)
+        sendSuccessResponse(ctx, requestSeq, "kdap/loadSymbols", DapMessage.jsonObjectToMap(result))
+    } catch (e: Exception) {
+        log.warning { "DebugInfo: kdap/loadSymbols failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "kdap/loadSymbols", e.message ?: "Loading symbols failed")
+    }
+}
+
+/**
+ * Python side of [handleLoadSymbols]. `_kdap_load_symbols` returns the
+ * module's file name, its symbol file and compile unit count, and the
+ * IDs of the client's breakpoints that gained locations in it, or an
+ * `error`.
+ */
+private val PY_LOAD_SYMBOLS = """
+    import json
+
+    def _kdap_load_symbols_quote(path):
+        return '"%s"' % path.replace('\\', '\\\\').replace('"', '\\"')
+
+    def _kdap_load_symbols_module(target, query):
+        for module in target.module_iter():
+            spec = module.GetFileSpec()
+            build_id = (module.GetUUIDString() or '').replace('-', '').lower()
+            if query in (spec.fullpath, spec.GetFilename()) or (build_id and query.lower() == build_id):
+                return module
+        return None
+
+    def _kdap_load_symbols_bound(target, module):
+        bound = set()
+        for i in range(target.GetNumBreakpoints()):
+            bp = target.GetBreakpointAtIndex(i)
+            if not bp.MatchesName('$DAP_BREAKPOINT_LABEL'):
+                continue
+            for j in range(bp.GetNumLocations()):
+                if bp.GetLocationAtIndex(j).GetAddress().GetModule() == module:
+                    bound.add(bp.GetID())
+                    break
+        return bound
+
+    def _kdap_load_symbols(query, path):
+        target = lldb.debugger.GetSelectedTarget()
+        module = _kdap_load_symbols_module(target, query)
+        if module is None:
+            return json.dumps({'error': "No loaded module matches '%s'." % query})
+        before = _kdap_load_symbols_bound(target, module)
+        result = lldb.SBCommandReturnObject()
+        command = 'target symbols add -s %s %s' % (
+            _kdap_load_symbols_quote(module.GetFileSpec().fullpath), _kdap_load_symbols_quote(path))
+        lldb.debugger.GetCommandInterpreter().HandleCommand(command, result)
+        if not result.Succeeded():
+            return json.dumps({'error': (result.GetError() or 'target symbols add failed').strip()})
+        bound = _kdap_load_symbols_bound(target, module) - before
+        return json.dumps({
+            'module': module.GetFileSpec().GetFilename(),
+            'symbolFilePath': module.GetSymbolFileSpec().fullpath,
+            'compileUnits': module.GetNumCompileUnits(),
+            'breakpoints': sorted(bound),
+        })
+""".trimIndent()
@@ -2,15 +2,32 @@ package com.github.jomof.dap.debugsession
 
 import com.github.jomof.dap.SBWatcher
 import com.github.jomof.dap.messages.Either
+import com.github.jomof.dap.messages.FunctionBreakpoint
+import com.github.jomof.dap.messages.InternalFrames
+import com.github.jomof.dap.messages.SetBreakpointsRequest
+import com.github.jomof.dap.messages.ShowDisassembly
+import com.github.jomof.dap.messages.SignalDisposition
+import com.github.jomof.dap.messages.StaticsScope
+import com.github.jomof.dap.messages.WatchRefresh
 import com.github.jomof.dap.sb.SBTarget
+import kotlinx.coroutines.CoroutineName
+import kotlinx.coroutines.CoroutineScope
+import kotlinx.coroutines.Dispatchers
+import kotlinx.coroutines.SupervisorJob
+import kotlinx.coroutines.sync.Mutex
+import org.json.JSONObject
+import java.io.Closeable
+import java.nio.file.Path
+import java.util.concurrent.ConcurrentHashMap
+import java.util.concurrent.atomic.AtomicInteger
 
 /**
  * Mutable session state for a single debug session, mirroring the fields
  * that CodeLLDB's `DebugSession` struct accumulates across
  * initialize / launch / attach / disconnect / terminate.
  *
- * The launch-related extension functions in [Launch.kt] read and write
- * these fields. Each [com.github.jomof.dap.interception] handler
+ * The launch-related extension functions in [Launch.kt] (and their
+ * siblings, e.g. [Breakpoints.kt]) read and write these fields. Each [com.github.jomof.dap.interception] handler
  * shares the same [DebugSession] instance so state flows naturally
  * across request boundaries.
  *
@@ -18,6 +35,8 @@ import com.github.jomof.dap.sb.SBTarget
  *
  * Fields are `@Volatile` for safe publication between the client-reader
  * coroutine (which calls [onInitialize]) and async handler coroutines.
+ * Async handlers run in parallel (see DapSession.kt), so state they
+ * read, modify, and write back is guarded by [stateLock].
  */
 class DebugSession {
 
@@ -25,6 +44,53 @@ class DebugSession {
     @Volatile
     var clientSupportsRunInTerminal: Boolean = false
 
+    /** Whether the client advertised `supportsProgressReporting`. */
+    @Volatile
+    var clientSupportsProgressReporting: Boolean = false
+
+    /**
+     * Connection to the `kdap-launch` helper running in the debuggee's
+     * terminal. The helper blocks until it closes, which keeps the
+     * terminal's shell from reading the debuggee's input.
+     */
+    @Volatile
+    var terminalConnection: Closeable? = null
+
+    /**
+     * Arguments of the `launch` request that started the debuggee, for
+     * `restart` (see [handleRestart]). `null` for attach and core dump
+     * sessions.
+     */
+    @Volatile
+    var launchArguments: JSONObject? = null
+
+    /** TTY of the client terminal the debuggee was launched in, if any. */
+    @Volatile
+    var launchTty: String? = null
+
+    /**
+     * Whether the debuggee can run backwards: a `"record": true` launch
+     * or `"reverseDebugging": true` (see [handleReverseContinue]).
+     */
+    @Volatile
+    var reverseExecution: Boolean = false
+
+    /** The `rr replay` gdbserver of a `"record": true` launch. */
+    @Volatile
+    var replayServer: Process? = null
+
+    /** Directory holding the rr trace of a `"record": true` launch. */
+    @Volatile
+    var traceDirectory: Path? = null
+
+    /** The device server and port forward of an `"android"` attach. */
+    @Volatile
+    var androidConnection: AndroidConnection? = null
+
+    /** Whether the debuggee's stdio is a KDAP pty (see [openStdioPty]). */
+    @Volatile
+    var stdioPty: Boolean = false
+
     /**
      * Set to `true` once the debuggee process is running (after
      * `target.launch()` or `target.attach()`). Used by
@@ -50,6 +116,348 @@ class DebugSession {
     @Volatile
     var gracefulShutdown: Either<String, List<String>>? = null
 
+    /**
+     * IDs of breakpoints carrying a KDAP hit-time callback, keyed by
+     * [com.github.jomof.dap.messages.Source.key]. Used to clear callbacks
+     * when a later `setBreakpoints` no longer needs them.
+     */
+    val scriptedBreakpoints: MutableMap<String, Set<Int>> = ConcurrentHashMap()
+
+    /** Callback bodies KDAP installed, by breakpoint ID, for copying to hardware breakpoints. */
+    val breakpointCallbackBodies: MutableMap<Int, String> = ConcurrentHashMap()
+
+    /**
+     * Hardware breakpoints KDAP created in place of lldb-dap's software
+     * ones, mapped to the ID of the breakpoint they stand in for (see
+     * Breakpoints.kt).
+     */
+    val hardwareBreakpoints: MutableMap<Int, Int> = ConcurrentHashMap()
+
+    /**
+     * IDs of the [hardwareBreakpoints] created for `"hardware": true`
+     * hints, keyed by [com.github.jomof.dap.messages.Source.key]. Replaced
+     * by every `setBreakpoints` for the source.
+     */
+    val hintedHardwareBreakpoints: MutableMap<String, Set<Int>> = ConcurrentHashMap()
+
+    /**
+     * IDs of the breakpoints in sources changed since the program was
+     * built, keyed by [com.github.jomof.dap.messages.Source.key].
+     * Replaced by every `setBreakpoints` for the source (see
+     * Breakpoints.kt).
+     */
+    val changedSourceBreakpoints: MutableMap<String, Set<Int>> = ConcurrentHashMap()
+
+    /** Modification time (ms) of the launched or attached `program`, if known. */
+    @Volatile
+    var programBuiltAt: Long? = null
+
+    /**
+     * IDs of the breakpoints KDAP created for wildcard and regex function
+     * breakpoints. Replaced by every `setFunctionBreakpoints`.
+     */
+    @Volatile
+    var patternFunctionBreakpoints: List<Int> = emptyList()
+
+    /**
+     * The last `setBreakpoints` of each source that has breakpoints,
+     * keyed by [com.github.jomof.dap.messages.Source.key], for
+     * `kdap/breakpoints` (see BreakpointFile.kt).
+     */
+    val clientSourceBreakpoints: MutableMap<String, SetBreakpointsRequest> = ConcurrentHashMap()
+
+    /** The breakpoints of the last `setFunctionBreakpoints`, for `kdap/breakpoints`. */
+    @Volatile
+    var clientFunctionBreakpoints: List<FunctionBreakpoint> = emptyList()
+
+    /**
+     * Data breakpoints emulated in software because lldb-dap could not
+     * create hardware watchpoints for them, keyed by DAP `dataId`.
+     */
+    val softwareWatchpoints: MutableMap<String, SoftwareWatchpoint> = ConcurrentHashMap()
+
+    /** Whether a `continue` is running under the software watchpoint thread plan. */
+    @Volatile
+    var softwareWatchStepping: Boolean = false
+
+    /** ID of the breakpoint behind the `rust_panic` exception filter, if enabled. */
+    @Volatile
+    var panicBreakpointId: Int? = null
+
+    /** IDs of the breakpoints behind the enabled `crash_*` exception filters, by filter ID. */
+    val crashBreakpointIds: MutableMap<String, Int> = ConcurrentHashMap()
+
+    /** ID of the one-shot breakpoint of `"stopOnEntry": "main"` until it is hit (see EntryStops.kt). */
+    @Volatile
+    var entryBreakpointId: Int? = null
+
+    /** Panic output seen on debuggee stderr since the last stop. */
+    @Volatile
+    var panicMessage: String? = null
+
+    /** Thread ID and message of the last stop reported as a Rust panic. */
+    @Volatile
+    var lastPanic: Pair<Int, String>? = null
+
+    /** Thread ID and filter of the last stop reported as a crash. */
+    @Volatile
+    var lastCrash: Pair<Int, CrashFilter>? = null
+
+    /** Enabled `signal_*` exception filter IDs. */
+    @Volatile
+    var signalFilters: Set<String> = emptySet()
+
+    /**
+     * Signals KDAP made stop for a signal filter, mapped to the stop
+     * setting they had before, so it can be restored.
+     */
+    val signalStopOverrides: MutableMap<Int, Boolean> = ConcurrentHashMap()
+
+    /**
+     * Signal dispositions by signal name, from `signals` and
+     * `kdap/signals` (see Signals.kt).
+     */
+    val signalDispositions: MutableMap<String, SignalDisposition> = ConcurrentHashMap()
+
+    /** Thread IDs of in-flight top-of-stack `stackTrace` requests, keyed by request seq. */
+    val pendingStackTraces: MutableMap<Int, Int> = ConcurrentHashMap()
+
+    /** How stack traces show runtime internals (`_adapterSettings.internalFrames`; see StackFrames.kt). */
+    @Volatile
+    var internalFrames: InternalFrames = InternalFrames.Subtle
+
+    /**
+     * Per thread, since the last stop, the lldb-dap frame index at which
+     * each client frame index continues, for paging stack traces with
+     * collapsed frames (see StackFrames.kt).
+     */
+    val collapsedFramePages: MutableMap<Int, MutableMap<Int, Int>> = ConcurrentHashMap()
+
+    /** Threads whose top frame has no source line since the last stop. */
+    val threadsWithoutSource: MutableSet<Int> = ConcurrentHashMap.newKeySet()
+
+    /**
+     * Threads KDAP suspended for a single-thread `continue`. They stay
+     * suspended across the stop and are released on the next resume.
+     */
+    val suspendedThreads: MutableSet<Long> = ConcurrentHashMap.newKeySet()
+
+    /** Thread resumed by the running single-thread `continue`, if any. */
+    @Volatile
+    var singleThreadContinue: Int? = null
+
+    /** Threads whose top frame is a Rust async body since the last stop. */
+    val threadsInAsyncBody: MutableSet<Int> = ConcurrentHashMap.newKeySet()
+
+    /**
+     * Held by async handlers while they read, modify, and write back
+     * state another handler may change at the same time:
+     *
+     * - the step state cleared by [releaseStepState] and set by the
+     *   handlers that resume with temporary breakpoints: [asyncStep],
+     *   [stepBack], [skippedStepIn], and [suspendedThreads], with the
+     *   Python globals behind them (`_kdap_async_step`, …);
+     * - [patternFunctionBreakpoints], replaced by `setFunctionBreakpoints`;
+     * - the Python globals of simple-expression evaluation
+     *   (`_kdap_se_calls`, see Variables.kt) and of the tokio task list
+     *   (`_kdap_tokio_cells`, with [asyncTasks]; see [taskThreads]).
+     *
+     * It is never held while waiting for the client (`launch` waiting
+     * for `configurationDone`), so such a handler can't block the others.
+     */
+    val stateLock = Mutex()
+
+    /**
+     * The async-aware `next` whose breakpoints are still set, if any
+     * (see [handleAsyncNext]). Cleared on the next resume.
+     */
+    @Volatile
+    var asyncStep: AsyncStep? = null
+
+    /**
+     * The `stepBack` whose breakpoints are still set, if any (see
+     * [handleStepBack]). Cleared on the next resume.
+     */
+    @Volatile
+    var stepBack: StepBack? = null
+
+    /** Whether heap allocations are tracked (`"trackAllocations": true`; see HeapTracking.kt). */
+    @Volatile
+    var trackAllocations: Boolean = false
+
+    /** The session's `kdap.init` script, if one loaded (see Scripting.kt). */
+    @Volatile
+    var script: KdapScript? = null
+
+    /** Where the script's stop hooks run, one at a time (see [onScriptStop]). */
+    val scriptScope = CoroutineScope(SupervisorJob() + Dispatchers.IO.limitedParallelism(1) + CoroutineName("kdap-script"))
+
+    /** Whether `stepIn` steps over external code (`"justMyCode": true`; see [handleJustMyCodeStepIn]). */
+    @Volatile
+    var justMyCode: Boolean = false
+
+    /**
+     * The `kdap/stepIntoSkipped` whose breakpoint is still set, if any
+     * (see [handleStepIntoSkipped]). Cleared on the next resume.
+     */
+    @Volatile
+    var skippedStepIn: SkippedStepIn? = null
+
+    /**
+     * The `pause` still waiting for the process to stop, if any (see
+     * [handlePause]). Cleared by the next stop or resume.
+     */
+    @Volatile
+    var pendingPause: PendingPause? = null
+
+    /** Whether the last stop ended a `pause` (see [rewritePauseStop]). */
+    @Volatile
+    var pausedStop: Boolean = false
+
+    /** Thread the last `stopped` event was reported on, if any. */
+    @Volatile
+    var lastStoppedThreadId: Int? = null
+
+    /** Thread IDs of the last `threads` response from lldb-dap. */
+    val knownThreads: MutableSet<Int> = ConcurrentHashMap.newKeySet()
+
+    /**
+     * Calls listed by the `stepInTargets` responses since the last stop,
+     * by target ID (see [handleStepInTargets]).
+     */
+    val stepInTargets: MutableMap<Int, StepInTarget> = ConcurrentHashMap()
+
+    /** Next [stepInTargets] ID to hand out. */
+    val nextStepInTargetId = AtomicInteger(1)
+
+    /** `goto` targets listed since the last stop, by target ID (see [handleGotoTargets]). */
+    val gotoTargets: MutableMap<Int, GotoTarget> = ConcurrentHashMap()
+
+    /** Next [gotoTargets] ID to hand out. */
+    val nextGotoTargetId = AtomicInteger(1)
+
+    /** How frames are shown as disassembly (`_adapterSettings.showDisassembly`; see Disassembly.kt). */
+    @Volatile
+    var showDisassembly: ShowDisassembly = ShowDisassembly.Auto
+
+    /** Disassembled code stack traces referred to, by `sourceReference` (see [handleDisassemblySource]). */
+    val disassemblySources: MutableMap<Int, DisassembledRange> = ConcurrentHashMap()
+
+    /** Next [disassemblySources] index, above [DISASSEMBLY_SOURCE_REFERENCE_BASE]. */
+    val nextDisassemblySource = AtomicInteger(0)
+
+    /** Sources not on disk that stack traces referred to, by `sourceReference` (see [handleSource]). */
+    val fetchedSources: MutableMap<Int, FetchedSource> = ConcurrentHashMap()
+
+    /** The `sourceReference` of each of [fetchedSources]. */
+    val fetchedSourceReferences: ConcurrentHashMap<FetchedSource, Int> = ConcurrentHashMap()
+
+    /** Next [fetchedSources] index, above [FETCHED_SOURCE_REFERENCE_BASE]. */
+    val nextFetchedSource = AtomicInteger(0)
+
+    /** Source server URL templates (see [sourceServerUrls]). */
+    @Volatile
+    var sourceServers: List<String> = emptyList()
+
+    /** The rust-src `library` directories of the rustup toolchains, found on first use. */
+    val rustSources: Map<String, Path> by lazy {
+        rustSourceDirectories(System.getenv(), System.getProperty("user.home"))
+    }
+
+    /** Whether `threads` lists tokio tasks (`"tokioTasks": true`; see [handleThreads]). */
+    @Volatile
+    var tokioTasks: Boolean = false
+
+    /** Tokio tasks from the last `threads` response, by pseudo-thread ID. */
+    @Volatile
+    var asyncTasks: Map<Int, AsyncTask> = emptyMap()
+
+    /** Next frame ID for a tokio task's await stack. */
+    val nextTaskFrameId = AtomicInteger(FIRST_TASK_FRAME_ID)
+
+    /**
+     * Number of indexed children of each lldb-dap `variablesReference`
+     * seen in a relayed `variables` response, for chunking. Valid until
+     * the next stop, like the references themselves.
+     */
+    val indexedVariableCounts: MutableMap<Int, Int> = ConcurrentHashMap()
+
+    /** Where each lldb-dap `variablesReference` seen since the last stop sits (see [guardExpansion]). */
+    val valueAncestries: MutableMap<Int, ValueAncestry> = ConcurrentHashMap()
+
+    /** Chunk nodes KDAP created in `variables` responses since the last stop or resume. */
+    val variableChunks = HandleTable<VariableChunk>(FIRST_CHUNK_REFERENCE, FIRST_LOCAL_SCOPE_REFERENCE)
+
+    /** Pointers viewed as arrays in `evaluate` responses since the last stop or resume. */
+    internal val arrayViews = HandleTable<ArrayView>(FIRST_ARRAY_REFERENCE, FIRST_CHUNK_REFERENCE)
+
+    /** Frame IDs of in-flight `scopes` requests, keyed by request seq. */
+    val pendingScopes: MutableMap<Int, Int> = ConcurrentHashMap()
+
+    /**
+     * Locals `variablesReference`s of top frames seen since the last stop,
+     * mapped to their thread's index ID, for return values (see
+     * [returnValueVariable]).
+     */
+    val returnValueScopes: MutableMap<Int, Int> = ConcurrentHashMap()
+
+    /** Arguments and Captures scopes seen since the last stop or resume, by reference (see [handleScopes]). */
+    val localScopes = HandleTable<LocalScope>(FIRST_LOCAL_SCOPE_REFERENCE, FIRST_STATICS_REFERENCE)
+
+    /** Variables of each lldb-dap Locals scope split since the last stop or resume. */
+    val localsPartitions: MutableMap<Int, List<LocalVariable>> = ConcurrentHashMap()
+
+    /** Which statics the Statics scope lists (`_adapterSettings.staticsScope`; see [sendStatics]). */
+    @Volatile
+    var staticsScope: StaticsScope = StaticsScope.Unit
+
+    /** lldb-dap frame IDs of the Statics scopes seen since the last stop or resume, by reference. */
+    val staticsScopes = HandleTable<Int>(FIRST_STATICS_REFERENCE, FIRST_REGISTERS_REFERENCE)
+
+    /** Nodes of the Registers scopes seen since the last stop or resume, by reference. */
+    val registerNodes = HandleTable<RegisterNode>(FIRST_REGISTERS_REFERENCE, FIRST_STATIC_VALUE_REFERENCE)
+
+    /** When watches are evaluated again (`_adapterSettings.watchRefresh`; see [handleWatch]). */
+    @Volatile
+    var watchRefresh: WatchRefresh = WatchRefresh.Stop
+
+    /** Elements rendered for one "Copy Value" (`_adapterSettings.clipboardMaxElements`; see [handleClipboardEvaluate]). */
+    @Volatile
+    var clipboardMaxElements: Int = DEFAULT_CLIPBOARD_MAX_ELEMENTS
+
+    /** Seconds a function call in an expression may run (`_adapterSettings.evaluationTimeout`; see FunctionCalls.kt). */
+    @Volatile
+    var evaluationTimeout: Float = DEFAULT_EVALUATION_TIMEOUT
+
+    /** Child levels compared for changed containers (`_adapterSettings.changedValuesDepth`; see ValueHistory.kt). */
+    @Volatile
+    var changedValuesDepth: Int = DEFAULT_CHANGED_VALUES_DEPTH
+
+    /** Levels a variable may be expanded below its scope (`_adapterSettings.maxExpansionDepth`; see RecursiveValues.kt). */
+    @Volatile
+    var maxExpansionDepth: Int = DEFAULT_MAX_EXPANSION_DEPTH
+
+    /** Values shown at this stop and earlier ones, for marking changed values (see ValueHistory.kt). */
+    val valueHistory = ValueHistory()
+
+    /** The frame of each scope and variable reference seen since the last stop or resume (see [trackValues]). */
+    val valueFrames: MutableMap<Int, ValueFrame> = ConcurrentHashMap()
+
+    /** Number of stops so far, which dates [watchResults] and [watchTargets]. */
+    val watchStop = AtomicInteger(0)
+
+    /** The last `evaluate` response of each watch. */
+    val watchResults: MutableMap<WatchKey, WatchResult> = ConcurrentHashMap()
+
+    /** The last stop at which each watch was asked for, which decides the watches kept at a stop (see [onWatchStop]). */
+    val watchRequests: MutableMap<WatchKey, Int> = ConcurrentHashMap()
+
+    /** The watches that had children, by reference; not reset, so each keeps its reference while it is asked for. */
+    val watchReferences = HandleTable<WatchKey>(FIRST_WATCH_REFERENCE, FIRST_ARRAY_REFERENCE)
+
+    /** The watches behind [watchReferences], by reference. */
+    val watchTargets: MutableMap<Int, WatchTarget> = ConcurrentHashMap()
+
     /**
      * Optional observer for SB API calls. When set, [com.github.jomof.dap.sb.createDebugger]
      * wraps the returned [com.github.jomof.dap.sb.SBDebugger] with a decorator that
@@ -0,0 +1,279 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.messages.ShowDisassembly
+import com.github.jomof.dap.sb.pyStr
+import org.json.JSONArray
+import org.json.JSONObject
+import java.util.logging.Logger
+
+/**
+ * Disassembly sources: frames without line information shown as a
+ * document of their function's instructions, as CodeLLDB does.
+ *
+ * A frame in code without line tables (a stripped libc, hand-written
+ * assembly, a JIT's output) has no source in lldb-dap's `stackTrace`,
+ * only its `instructionPointerReference`. A client without a
+ * disassembly view then has nothing to show, and one step later the
+ * user is back in source code, not knowing where the thread went. KDAP
+ * gives such frames a source of its own ([assignDisassemblySources]):
+ * named `@<function>`, with `origin` `"disassembly"` and a
+ * `sourceReference` whose `source` text is the function's instructions,
+ * one per line ([formatDisassembly]):
+ *
+ * ```
+ * ; __memmove_avx_unaligned_erms
+ * ; libc.so.6
+ * 0x7ffff7e2b6c0: f3 0f 1e fa              endbr64
+ * 0x7ffff7e2b6c4: 48 89 f8                 mov      rax, rdi
+ * ```
+ *
+ * and the frame's `line` is the line of the instruction at its PC. A
+ * function is disassembled once, from its symbol's range, and every
+ * frame in it shares the document, so each instruction step moves the
+ * line within it. Code without a symbol, or with one larger than
+ * [DISASSEMBLY_MAX_BYTES], is shown from the PC on,
+ * [DISASSEMBLY_WINDOW] instructions at a time.
+ *
+ * Line steps in such a frame are instruction steps (see Stepping.kt),
+ * so `next` and `stepIn` keep stepping through it, and `stepOut`
+ * returns to the caller. `_adapterSettings.showDisassembly` picks the
+ * frames ([needsDisassembly]):
+ *
+ * | Setting  | Frames with a disassembly source          |
+ * |----------|-------------------------------------------|
+ * | `auto`   | those without a source line (the default) |
+ * | `always` | every frame with an address               |
+ * | `never`  | none; frames are as lldb-dap reports them |
+ *
+ * Breakpoints for these documents are set by address in the client's
+ * disassembly view (`setInstructionBreakpoints`; see Breakpoints.kt).
+ *
+ * ## CodeLLDB method mapping
+ *
+ * | CodeLLDB (disassembly.rs)                | KDAP (this file)             |
+ * |------------------------------------------|------------------------------|
+ * | `AddressSpace::from_address`             | [assignDisassemblySources]   |
+ * | `DisassembledRange::get_source_text`     | [formatDisassembly]          |
+ * | `DisassembledRange::line_num_by_address` | [disassemblyLine]            |
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.Disassembly")
+
+/** First `sourceReference` KDAP gives a disassembly source, below the fetched sources of Sources.kt. */
+internal const val DISASSEMBLY_SOURCE_REFERENCE_BASE = 1 shl 29
+
+/** Largest function disassembled whole; beyond it, [DISASSEMBLY_WINDOW] instructions from the PC. */
+internal const val DISASSEMBLY_MAX_BYTES = 64 * 1024
+
+/** Instructions disassembled from the PC in code without a (small enough) symbol. */
+internal const val DISASSEMBLY_WINDOW = 64
+
+/** MIME type of the `source` text of a disassembly source, as CodeLLDB sends it. */
+internal const val DISASSEMBLY_MIME_TYPE = "text/x-lldb.disassembly"
+
+/** One instruction of a [DisassembledRange]; [bytes] as hex pairs. */
+internal data class DisassembledInstruction(
+    val address: Long,
+    val bytes: String,
+    val mnemonic: String,
+    val operands: String,
+    val comment: String = "",
+)
+
+/**
+ * The instructions from [start] to [end] (exclusive), the range of
+ * function [name] in [module], or a window of it.
+ */
+internal data class DisassembledRange(
+    val name: String,
+    val module: String?,
+    val start: Long,
+    val end: Long,
+    val instructions: List<DisassembledInstruction>,
+) {
+    operator fun contains(pc: Long): Boolean = pc in start until end
+}
+
+/** Whether [frame] gets a disassembly source under [mode] (see the file header). */
+internal fun needsDisassembly(frame: Map<*, *>, mode: ShowDisassembly): Boolean {
+    if (frame["instructionPointerReference"] == null) return false
+    return when (mode) {
+        ShowDisassembly.Always -> true
+        ShowDisassembly.Never -> false
+        ShowDisassembly.Auto -> {
+            val path = (frame["source"] as? Map<*, *>)?.get("path")
+            path == null || ((frame["line"] as? Number)?.toInt() ?: 0) <= 0
+        }
+    }
+}
+
+/** The lines [formatDisassembly] puts before the instructions of [range]. */
+private fun disassemblyHeader(range: DisassembledRange): List<String> = listOfNotNull("; ${range.name}", range.module?.let { "; $it" })
+
+/** The `source` text of [range] (see the file header). */
+internal fun formatDisassembly(range: DisassembledRange): String = buildString {
+    for (line in disassemblyHeader(range)) append(line).append('\n')
+    for (instruction in range.instructions) {
+        append("0x%x: %-24s %-8s %s".format(instruction.address, instruction.bytes, instruction.mnemonic, instruction.operands).trimEnd())
+        if (instruction.comment.isNotEmpty()) append("  ; ").append(instruction.comment)
+        append('\n')
+    }
+}
+
+/** The 1-based line of [formatDisassembly]'s text with the instruction at (or last before) [pc]. */
+internal fun disassemblyLine(range: DisassembledRange, pc: Long): Int {
+    val index = range.instructions.indexOfLast { it.address <= pc }.coerceAtLeast(0)
+    return disassemblyHeader(range).size + index + 1
+}
+
+/** [frame] at [pc] shown in [range], the disassembly source with [reference]. */
+internal fun disassemblyFrame(frame: Map<*, *>, reference: Int, range: DisassembledRange, pc: Long): Map<*, *> {
+    val hint = (frame["source"] as? Map<*, *>)?.get("presentationHint")
+    val source = mapOf("name" to "@${range.name}", "sourceReference" to reference, "origin" to "disassembly") +
+        (if (hint != null) mapOf("presentationHint" to hint) else emptyMap())
+    return frame + mapOf("source" to source, "line" to disassemblyLine(range, pc), "column" to 0) - listOf("endLine", "endColumn")
+}
+
+/** A [DisassembledRange] from `_kdap_disassemble` output. */
+internal fun parseDisassembledRange(obj: JSONObject): DisassembledRange {
+    val instructions = obj.getJSONArray("instructions")
+    return DisassembledRange(
+        name = obj.getString("name"),
+        module = obj.optString("module", null),
+        start = obj.getLong("start"),
+        end = obj.getLong("end"),
+        instructions = (0 until instructions.length()).map { i ->
+            val instruction = instructions.getJSONObject(i)
+            DisassembledInstruction(
+                address = instruction.getLong("address"),
+                bytes = instruction.optString("bytes", ""),
+                mnemonic = instruction.optString("mnemonic", ""),
+                operands = instruction.optString("operands", ""),
+                comment = instruction.optString("comment", ""),
+            )
+        },
+    )
+}
+
+/** A frame's PC, from its `instructionPointerReference`. */
+private fun framePc(frame: Map<*, *>): Long? =
+    (frame["instructionPointerReference"] as? String)?.removePrefix("0x")?.toULongOrNull(16)?.toLong()
+
+/**
+ * Gives the [frames] of a `stackTrace` response that [needsDisassembly]
+ * a disassembly source (see the file header), disassembling the code
+ * of PCs no earlier range covers. [topFrameOf] is the thread whose top
+ * frame is the first of [frames], if it is; a top frame shown as
+ * disassembly is stepped by instruction. If the code can't be
+ * disassembled, frames are left as they are.
+ */
+internal suspend fun DebugSession.assignDisassemblySources(
+    frames: List<Any?>,
+    topFrameOf: Int?,
+    ctx: AsyncRequestContext,
+): List<Any?> {
+    val pcs = frames.mapNotNull { frame ->
+        (frame as? Map<*, *>)?.takeIf { needsDisassembly(it, showDisassembly) }?.let(::framePc)
+    }
+    val missing = pcs.filter { pc -> disassemblySources.values.none { pc in it } }.distinct()
+    if (missing.isNotEmpty()) {
+        try {
+            val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+            val interpreter = debugger.commandInterpreter()
+            interpreter.handleCommand("script exec(${pyStr(PY_DISASSEMBLY)})")
+            val output = interpreter.handleCommand("script print(_kdap_disassemble(" +
+                "${pyStr(JSONArray(missing).toString())}, $DISASSEMBLY_MAX_BYTES, $DISASSEMBLY_WINDOW))").trim()
+            val ranges = JSONArray(output)
+            for (i in 0 until ranges.length()) {
+                val range = parseDisassembledRange(ranges.getJSONObject(i))
+                if (range.instructions.isEmpty()) continue
+                disassemblySources[DISASSEMBLY_SOURCE_REFERENCE_BASE + nextDisassemblySource.getAndIncrement()] = range
+            }
+        } catch (e: Exception) {
+            log.warning { "Disassembly: disassembling frames failed: ${e.message}" }
+        }
+    }
+    return frames.mapIndexed { index, frame ->
+        val map = frame as? Map<*, *> ?: return@mapIndexed frame
+        if (!needsDisassembly(map, showDisassembly)) return@mapIndexed frame
+        val pc = framePc(map) ?: return@mapIndexed frame
+        val (reference, range) = disassemblySources.entries.firstOrNull { pc in it.value } ?: return@mapIndexed frame
+        if (index == 0 && topFrameOf != null) threadsWithoutSource.add(topFrameOf)
+        disassemblyFrame(map, reference, range, pc)
+    }
+}
+
+/** Whether [sourceReference] is one of KDAP's disassembly sources. */
+fun DebugSession.isDisassemblySource(sourceReference: Int): Boolean = sourceReference in disassemblySources
+
+/** Handles `source` for a disassembly source: its instructions (see the file header). */
+suspend fun DebugSession.handleDisassemblySource(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val args = obj.optJSONObject("arguments")
+    val reference = args?.optJSONObject("source")?.optInt("sourceReference")?.takeIf { it > 0 }
+        ?: args?.optInt("sourceReference") ?: 0
+    val range = disassemblySources[reference]
+    if (range == null) {
+        sendErrorResponse(ctx, requestSeq, "source", "Unknown source reference $reference")
+        return
+    }
+    sendSuccessResponse(ctx, requestSeq, "source", mapOf("content" to formatDisassembly(range), "mimeType" to DISASSEMBLY_MIME_TYPE))
+}
+
+/**
+ * Python side of [assignDisassemblySources]. `_kdap_disassemble`
+ * returns one range for each PC not in an earlier one: the instructions
+ * of its symbol, or `window` instructions from the PC without a symbol
+ * or with one over `max_bytes`. Addresses are load addresses.
+ */
+private val PY_DISASSEMBLY = """
+    import json
+
+    def _kdap_instruction(target, instruction):
+        error = lldb.SBError()
+        data = instruction.GetData(target)
+        raw = data.ReadRawData(error, 0, data.GetByteSize()) if data.GetByteSize() else b''
+        return {
+            'address': instruction.GetAddress().GetLoadAddress(target),
+            'bytes': ' '.join('%02x' % b for b in bytearray(raw or b'')),
+            'mnemonic': instruction.GetMnemonic(target) or '',
+            'operands': instruction.GetOperands(target) or '',
+            'comment': instruction.GetComment(target) or '',
+        }
+
+    def _kdap_disassemble(pcs_json, max_bytes, window):
+        target = lldb.debugger.GetSelectedTarget()
+        ranges = []
+        for pc in json.loads(pcs_json):
+            if any(r['start'] <= pc < r['end'] for r in ranges):
+                continue
+            address = target.ResolveLoadAddress(pc)
+            symbol = address.GetSymbol()
+            start = end = lldb.LLDB_INVALID_ADDRESS
+            if symbol.IsValid():
+                start = symbol.GetStartAddress().GetLoadAddress(target)
+                end = symbol.GetEndAddress().GetLoadAddress(target)
+            whole = start != lldb.LLDB_INVALID_ADDRESS and end != lldb.LLDB_INVALID_ADDRESS and start <= pc < end \
+                and end - start <= max_bytes
+            if whole:
+                instructions = symbol.GetInstructions(target)
+                name = symbol.GetName()
+            else:
+                instructions = target.ReadInstructions(address, window)
+                name = ('%s+0x%x' % (symbol.GetName(), pc - start)) if symbol.IsValid() and start <= pc else '0x%x' % pc
+            listed = [_kdap_instruction(target, instructions.GetInstructionAtIndex(i)) for i in range(instructions.GetSize())]
+            if not listed:
+                continue
+            last = instructions.GetInstructionAtIndex(instructions.GetSize() - 1)
+            module = address.GetModule()
+            ranges.append({
+                'name': name,
+                'module': module.GetFileSpec().GetFilename() if module.IsValid() else None,
+                'start': start if whole else listed[0]['address'],
+                'end': end if whole else listed[-1]['address'] + last.GetByteSize(),
+                'instructions': listed,
+            })
+        return json.dumps(ranges)
+""".trimIndent()
@@ -0,0 +1,103 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.StoppedEvent
+import com.github.jomof.dap.sb.SBDebugger
+import com.github.jomof.dap.sb.pyStr
+
+/**
+ * Initial stops: where a launch first stops, and stops at exec. KDAP
+ * extension of CodeLLDB's `stopOnEntry`, which stops at the program's
+ * first instruction only.
+ *
+ * - `"stopOnEntry": "main"` stops at `main` before it runs: a Rust
+ *   program's own `main` (the crate's, not the C `main` of std's
+ *   runtime), else C's `main`, through a one-shot breakpoint
+ *   ([setMainBreakpoint]) whose stop is reported with reason `entry`
+ *   ([rewriteEntryStop]). A program without a `main` symbol stops at its
+ *   first instruction, as with `true`.
+ * - `"stopOnExec"` sets LLDB's `target.process.stop-on-exec`: with
+ *   `true`, a process that execs a new program stops at the new program's
+ *   start (lldb-dap reports it with reason `entry`); with `false` it runs
+ *   on. A followed child that was created by `posix_spawn` or `vfork` has
+ *   exec'd by the time its session attaches, so that session reports the
+ *   attach stop instead of resuming (see ChildProcesses.kt).
+ */
+
+/** Applies `stopOnExec` to [debugger], if the configuration sets it. */
+internal suspend fun initStopOnExec(stopOnExec: Boolean?, debugger: SBDebugger) {
+    if (stopOnExec == null) return
+    debugger.setVariable("target.process.stop-on-exec", stopOnExec.toString())
+}
+
+/**
+ * Sets the one-shot breakpoint at `main` for `"stopOnEntry": "main"`,
+ * replacing any earlier one. Returns `false` if the program has no
+ * `main`, so the launch stops at the entry point instead.
+ */
+internal suspend fun DebugSession.setMainBreakpoint(debugger: SBDebugger): Boolean {
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_MAIN_BREAKPOINT)})")
+    val previous = entryBreakpointId ?: 0
+    val id = interpreter.handleCommand("script print(_kdap_main_breakpoint($previous))").trim().toIntOrNull()
+    entryBreakpointId = id?.takeIf { it > 0 }
+    return entryBreakpointId != null
+}
+
+/**
+ * Reports a hit of the `main` breakpoint as an `entry` stop. Other stops
+ * are left alone.
+ */
+fun DebugSession.rewriteEntryStop(event: StoppedEvent): StoppedEvent {
+    val id = entryBreakpointId ?: return event
+    val isEntry = event.hitBreakpointIds?.contains(id) == true ||
+        event.description?.startsWith("breakpoint $id.") == true
+    if (!isEntry) return event
+    entryBreakpointId = null
+    return event.copy(reason = "entry", description = null, hitBreakpointIds = null)
+}
+
+/**
+ * Python side of [setMainBreakpoint]. `_kdap_main_breakpoint` deletes
+ * the breakpoint with ID `previous`, then breaks once after the prologue
+ * of the main module's Rust `crate::main`, else at C's `main`, and
+ * returns the new breakpoint's ID, or 0 if there is no `main`.
+ */
+private val PY_MAIN_BREAKPOINT = """
+    import re
+
+    _KDAP_RUST_MAIN = re.compile(r'^[A-Za-z_]\w*::main(::h[0-9a-f]{16})?${'$'}')
+
+    def _kdap_main_address(target):
+        module = target.GetModuleAtIndex(0)
+        found = target.FindGlobalFunctions(_KDAP_RUST_MAIN.pattern, 0, lldb.eMatchTypeRegex)
+        for i in range(found.GetSize()):
+            sc = found.GetContextAtIndex(i)
+            function = sc.GetFunction()
+            if (function.IsValid() and sc.GetModule() == module
+                    and sc.GetCompileUnit().GetLanguage() == lldb.eLanguageTypeRust
+                    and _KDAP_RUST_MAIN.match(function.GetName() or '')):
+                address = function.GetStartAddress()
+                address.OffsetAddress(function.GetPrologueByteSize())
+                return address
+        for sc in module.FindFunctions('main'):
+            function = sc.GetFunction()
+            if function.IsValid():
+                address = function.GetStartAddress()
+                address.OffsetAddress(function.GetPrologueByteSize())
+                return address
+        symbol = module.FindSymbol('main')
+        if symbol.IsValid():
+            return symbol.GetStartAddress()
+        return None
+
+    def _kdap_main_breakpoint(previous):
+        target = lldb.debugger.GetSelectedTarget()
+        if previous:
+            target.BreakpointDelete(previous)
+        address = _kdap_main_address(target)
+        if address is None or not address.IsValid():
+            return 0
+        bp = target.BreakpointCreateBySBAddress(address)
+        bp.SetOneShot(True)
+        return bp.GetID()
+""".trimIndent()
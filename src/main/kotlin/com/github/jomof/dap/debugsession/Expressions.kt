@@ -0,0 +1,831 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.sb.SBDebugger
+import com.github.jomof.dap.sb.pyStr
+
+/**
+ * Expression preparation and KDAP's Rust-like "simple" expressions,
+ * mirroring CodeLLDB's `expressions.rs`.
+ *
+ * ## Prefixes
+ *
+ * | Expression     | Evaluated as                                       |
+ * |----------------|----------------------------------------------------|
+ * | `/se <expr>`   | simple expression; an error if it doesn't parse    |
+ * | `/nat <expr>`  | LLDB native expression, by lldb-dap                |
+ * | `<expr>`       | simple expression if it parses, else native        |
+ *
+ * Breakpoint conditions only use simple expressions when prefixed with
+ * `/se` (unprefixed conditions stay native; see `Breakpoints.kt`).
+ *
+ * ## Format suffixes
+ *
+ * A watch, hover, or `?` expression may end in a format suffix (see
+ * [splitFormatSuffix]) that sets how its value is shown:
+ *
+ * | Suffix | Format                                                   |
+ * |--------|----------------------------------------------------------|
+ * | `,x`   | hexadecimal; an aggregate's children too, through lldb-dap's `format.hex` |
+ * | `,b`   | binary                                                   |
+ * | `,d`   | decimal, overriding the request's `format.hex`           |
+ * | `,c`   | character                                                |
+ * | `,s`   | UTF-8 text, for byte buffers (`&[u8]`, `Vec<u8>`) and C strings |
+ *
+ * Without a suffix, `format.hex` in the request applies.
+ *
+ * Before the format suffix, `,[<len>]` or `@<len>` views a raw pointer as
+ * an array of `<len>` elements, where `<len>` is a simple expression
+ * (`buf@len`, `data,[16],x`; see [splitArraySuffix]). A base that is not
+ * a pointer is viewed from its address, as GDB's `*p@n` is.
+ *
+ * ## Debug console
+ *
+ * `repl` input is an LLDB command unless it is one of (see
+ * [classifyReplInput]):
+ *
+ * | Input            | Handled as                                       |
+ * |------------------|--------------------------------------------------|
+ * | `` `<command> `` | LLDB command run by KDAP; output streamed as `output` events |
+ * | `` `mappings ``  | the memory map (MemoryMap.kt), printed as a table |
+ * | `?<expr>`        | expression, with the prefixes and suffixes above |
+ * | `<place> = <expr>` | simple-expression assignment                   |
+ *
+ * Anything else, including native expressions that call debuggee
+ * functions, goes to lldb-dap.
+ *
+ * ## Syntax
+ *
+ * ```text
+ * expr    := or
+ * or      := and ('||' and)*
+ * and     := cmp ('&&' cmp)*
+ * cmp     := sum (('==' | '!=' | '<' | '<=' | '>' | '>=') sum)?
+ * sum     := term (('+' | '-') term)*
+ * term    := unary (('*' | '/' | '%') unary)*
+ * unary   := ('-' | '!' | '*') unary | postfix
+ * postfix := primary ('.' (ident | int) | '.' ident args | '[' expr ']')*
+ * primary := int | float | 'true' | 'false' | path | path args | '(' expr ')'
+ * path    := ident ('::' ident)*
+ * args    := '(' (expr (',' expr)*)? ')'
+ * ```
+ *
+ * Field access auto-dereferences pointers and references, `.0` reads a
+ * tuple field, `*` also looks through `Box`/`Rc`/`Arc`, and `/` and `%`
+ * truncate toward zero on integers as in Rust. Simple expressions are
+ * compiled to Python ([toPython]) that runs against LLDB's SB API via the
+ * helpers in [PY_SIMPLE_EXPRESSIONS]; they only resume the debuggee to
+ * call a function (`f(x)`, `v.len()`), in watch and debug console
+ * expressions (see FunctionCalls.kt).
+ *
+ * Assignments (debug console, `setVariable`, `setExpression`; see
+ * [prepareAssignment]) encode the value with the target's size,
+ * signedness, and the process byte order before writing it. Assigning a
+ * variant name to a Rust enum writes its discriminant; an enumerator name
+ * sets a C-like enum.
+ *
+ * ## CodeLLDB mapping
+ *
+ * | CodeLLDB (expressions.rs) | KDAP (this file)             |
+ * |---------------------------|------------------------------|
+ * | `prepare`                 | [prepareExpression]          |
+ * | `PreparedExpression`      | [PreparedExpression]         |
+ * | `preprocess_simple_expr`  | [parseSimpleExpression]      |
+ * | `get_expression_format`   | [splitFormatSuffix]          |
+ * | —                         | [splitArraySuffix]           |
+ * | —                         | [prepareAssignment]          |
+ */
+
+private const val SIMPLE_PREFIX = "/se "
+private const val NATIVE_PREFIX = "/nat "
+private const val META_COMMAND_PREFIX = "`"
+private const val REPL_EXPRESSION_PREFIX = "?"
+
+/** A trailing `,x`-style format suffix (see [splitFormatSuffix]). */
+private val FORMAT_SUFFIX = Regex("""^(.*\S)\s*,([xbdcs])$""", RegexOption.DOT_MATCHES_ALL)
+
+/** A trailing `,[<len>]` array suffix (see [splitArraySuffix]). */
+private val ARRAY_SUFFIX = Regex("""^(.*\S)\s*,\s*\[([^\[\]]+)]$""", RegexOption.DOT_MATCHES_ALL)
+
+/** A syntax error in a simple expression. */
+class ExpressionError(message: String) : Exception(message)
+
+/**
+ * How a value is shown, set by a format suffix or `format.hex`; mirrors
+ * CodeLLDB's `FormatSpec`. [lldbFormat] is the Python name of the
+ * `lldb.Format` value.
+ */
+internal enum class ValueFormat(val suffix: Char, val lldbFormat: String) {
+    Hex('x', "lldb.eFormatHex"),
+    Binary('b', "lldb.eFormatBinary"),
+    Decimal('d', "lldb.eFormatDecimal"),
+    Char('c', "lldb.eFormatChar"),
+    Text('s', "lldb.eFormatCString");
+
+    /** Whether lldb-dap can show values this way, through `format.hex`. */
+    val lldbDapCanShow: Boolean get() = this == Hex || this == Decimal
+}
+
+/** How an expression is evaluated, mirroring CodeLLDB's `PreparedExpression`. */
+internal sealed class PreparedExpression {
+    /** An LLDB native expression, passed to lldb-dap as-is. */
+    data class Native(val text: String) : PreparedExpression()
+    /** A parsed simple expression. */
+    data class Simple(val expr: SimpleExpr) : PreparedExpression()
+}
+
+// ── prepare (expressions.rs) ─────────────────────────────────────
+
+/**
+ * Mirrors CodeLLDB's `prepare`: strips the prefix of [text] and decides
+ * how it is evaluated. Throws [ExpressionError] if a `/se` expression
+ * doesn't parse.
+ */
+internal fun prepareExpression(text: String): PreparedExpression {
+    val trimmed = text.trim()
+    return when {
+        trimmed.startsWith(SIMPLE_PREFIX) ->
+            PreparedExpression.Simple(parseSimpleExpression(trimmed.removePrefix(SIMPLE_PREFIX)))
+        trimmed.startsWith(NATIVE_PREFIX) ->
+            PreparedExpression.Native(trimmed.removePrefix(NATIVE_PREFIX).trim())
+        else -> try {
+            PreparedExpression.Simple(parseSimpleExpression(trimmed))
+        } catch (e: ExpressionError) {
+            PreparedExpression.Native(text)
+        }
+    }
+}
+
+/**
+ * Mirrors CodeLLDB's `get_expression_format`: splits a trailing format
+ * suffix (`,x`, `,b`, `,d`, `,c`, or `,s`) off [text]. Returns the expression
+ * and its format, or [text] and `null` if there is no suffix.
+ */
+internal fun splitFormatSuffix(text: String): Pair<String, ValueFormat?> {
+    val match = FORMAT_SUFFIX.find(text.trim()) ?: return text to null
+    val format = ValueFormat.entries.first { it.suffix == match.groupValues[2][0] }
+    return match.groupValues[1] to format
+}
+
+/**
+ * Splits a trailing array suffix (`,[<len>]` or `@<len>`) off [text].
+ * Returns the base expression and the parsed length, or [text] and
+ * `null` if there is no suffix or its length is not a simple expression.
+ */
+internal fun splitArraySuffix(text: String): Pair<String, SimpleExpr?> {
+    val trimmed = text.trim()
+    val match = ARRAY_SUFFIX.find(trimmed)
+    val (base, length) = when {
+        match != null -> match.groupValues[1] to match.groupValues[2]
+        trimmed.lastIndexOf('@') > 0 -> trimmed.substringBeforeLast('@').trim() to trimmed.substringAfterLast('@')
+        else -> return text to null
+    }
+    return try {
+        base to parseSimpleExpression(length)
+    } catch (e: ExpressionError) {
+        text to null
+    }
+}
+
+/**
+ * The expression lldb-dap evaluates for [prepared], prepared from
+ * [text] outside the debug console: a native expression without its
+ * prefix, or a bare variable name, which lldb-dap looks up the same way.
+ * `null` if KDAP evaluates it as a simple expression.
+ */
+internal fun lldbDapExpression(text: String, prepared: PreparedExpression): String? = when (prepared) {
+    is PreparedExpression.Native -> prepared.text
+    is PreparedExpression.Simple ->
+        text.takeIf { prepared.expr == SimpleExpr.Variable(text.trim()) && "::" !in text }
+}
+
+/**
+ * Prepares the write of [value] to the place [target] for `setVariable`
+ * and `setExpression`. Both sides follow the `/se`/`/nat` prefix rules of
+ * [prepareExpression]; unless both are simple expressions the assignment
+ * is a native `target = value` expression. Throws [ExpressionError] if a
+ * `/se` side doesn't parse or a simple [target] isn't a place.
+ */
+internal fun prepareAssignment(target: String, value: String): PreparedExpression {
+    val place = prepareExpression(target)
+    val newValue = prepareExpression(value)
+    if (place is PreparedExpression.Simple && newValue is PreparedExpression.Simple) {
+        if (!place.expr.isPlace()) throw ExpressionError("'${target.trim()}' is not assignable")
+        return PreparedExpression.Simple(SimpleExpr.Assign(place.expr, newValue.expr))
+    }
+    return PreparedExpression.Native("${place.nativeText(target)} = ${newValue.nativeText(value)}")
+}
+
+/** Whether this expression denotes a value in the debuggee that can be written. */
+private fun SimpleExpr.isPlace(): Boolean =
+    this is SimpleExpr.Variable || this is SimpleExpr.Field || this is SimpleExpr.Index || this is SimpleExpr.Deref
+
+/** The text lldb-dap evaluates for this side of an assignment. */
+private fun PreparedExpression.nativeText(original: String): String = when (this) {
+    is PreparedExpression.Native -> text.trim()
+    is PreparedExpression.Simple -> original.trim().removePrefix(SIMPLE_PREFIX).trim()
+}
+
+// ── Syntax tree ──────────────────────────────────────────────────
+
+/** A parsed simple expression. */
+internal sealed class SimpleExpr {
+    data class IntLiteral(val value: Long) : SimpleExpr()
+    data class FloatLiteral(val value: Double) : SimpleExpr()
+    data class BoolLiteral(val value: Boolean) : SimpleExpr()
+    /** A variable, or a `::`-separated path to a static. */
+    data class Variable(val path: String) : SimpleExpr()
+    /** `base.name`; [name] is a field name or a tuple index. */
+    data class Field(val base: SimpleExpr, val name: String) : SimpleExpr()
+    data class Index(val base: SimpleExpr, val index: SimpleExpr) : SimpleExpr()
+    data class Deref(val operand: SimpleExpr) : SimpleExpr()
+    /** `-x` or `!x`. */
+    data class Unary(val op: String, val operand: SimpleExpr) : SimpleExpr()
+    /** An arithmetic, comparison, or logical operator. */
+    data class Binary(val op: String, val left: SimpleExpr, val right: SimpleExpr) : SimpleExpr()
+    /** `target = value`; only accepted at the top level of debug console input. */
+    data class Assign(val target: SimpleExpr, val value: SimpleExpr) : SimpleExpr()
+    /** `path(args)`: a call of the debuggee function [path]. */
+    data class Call(val path: String, val args: List<SimpleExpr>) : SimpleExpr()
+    /** `receiver.name(args)`: a call of the method [name] of [receiver]'s type, with [receiver] as `self`. */
+    data class MethodCall(val receiver: SimpleExpr, val name: String, val args: List<SimpleExpr>) : SimpleExpr()
+}
+
+/** How a line of debug console (`repl`) input is handled. */
+internal sealed class ReplInput {
+    /** A backtick-prefixed LLDB command, run by KDAP. */
+    data class MetaCommand(val command: String) : ReplInput()
+    /** An expression or assignment evaluated by KDAP, shown in [format] if given. */
+    data class Simple(val expr: SimpleExpr, val format: ValueFormat? = null) : ReplInput()
+    /** A `?`-prefixed native expression, evaluated by lldb-dap unless [format] needs KDAP. */
+    data class NativeExpression(val text: String, val format: ValueFormat? = null) : ReplInput()
+    /** A `?`-prefixed `/se` expression that doesn't parse. */
+    data class Invalid(val message: String) : ReplInput()
+    /** An LLDB command or anything else lldb-dap handles itself. */
+    object Passthrough : ReplInput()
+}
+
+// ── Debug console input ──────────────────────────────────────────
+
+/** Decides how debug console input [text] is handled. */
+internal fun classifyReplInput(text: String): ReplInput {
+    val trimmed = text.trim()
+    if (trimmed.startsWith(META_COMMAND_PREFIX)) {
+        val command = trimmed.removePrefix(META_COMMAND_PREFIX).trim()
+        return if (command.isEmpty()) ReplInput.Passthrough else ReplInput.MetaCommand(command)
+    }
+    if (trimmed.startsWith(REPL_EXPRESSION_PREFIX)) {
+        val (expression, format) = splitFormatSuffix(trimmed.removePrefix(REPL_EXPRESSION_PREFIX))
+        return try {
+            when (val prepared = prepareExpression(expression)) {
+                is PreparedExpression.Simple -> ReplInput.Simple(prepared.expr, format)
+                is PreparedExpression.Native -> ReplInput.NativeExpression(prepared.text.trim(), format)
+            }
+        } catch (e: ExpressionError) {
+            ReplInput.Invalid(e.message ?: "Invalid expression")
+        }
+    }
+    // Only assignments are taken from unprefixed input: `p -x` and the
+    // like are LLDB commands that also parse as expressions.
+    val statement = try {
+        SimpleExprParser(tokenize(trimmed)).parseStatement()
+    } catch (e: ExpressionError) {
+        return ReplInput.Passthrough
+    }
+    return if (statement is SimpleExpr.Assign) ReplInput.Simple(statement) else ReplInput.Passthrough
+}
+
+// ── preprocess_simple_expr (expressions.rs) ──────────────────────
+
+/** Parses a simple expression, throwing [ExpressionError] on invalid syntax. */
+internal fun parseSimpleExpression(text: String): SimpleExpr = SimpleExprParser(tokenize(text)).parseAll()
+
+private sealed class Token {
+    data class Ident(val name: String) : Token()
+    data class Integer(val value: Long) : Token()
+    data class Real(val value: Double) : Token()
+    data class Punct(val symbol: String) : Token()
+}
+
+/** Operators, longest first so `<=` isn't read as `<`. */
+private val PUNCTUATION = listOf(
+    "::", "==", "!=", "<=", ">=", "&&", "||",
+    "<", ">", "=", "+", "-", "*", "/", "%", "!", "(", ")", "[", "]", ".", ",",
+)
+
+private val NUMBER_SUFFIX = Regex("""^(i|u)(8|16|32|64|128|size)|^f(32|64)""")
+
+private fun tokenize(text: String): List<Token> {
+    val tokens = mutableListOf<Token>()
+    var i = 0
+    while (i < text.length) {
+        val c = text[i]
+        when {
+            c.isWhitespace() -> i++
+            c.isLetter() || c == '_' -> {
+                val start = i
+                while (i < text.length && (text[i].isLetterOrDigit() || text[i] == '_')) i++
+                tokens.add(Token.Ident(text.substring(start, i)))
+            }
+            c.isDigit() -> {
+                // After '.', digits are a tuple index: `t.0.1` is not `t.(0.1)`.
+                val afterDot = tokens.lastOrNull() == Token.Punct(".")
+                i = lexNumber(text, i, allowFloat = !afterDot, tokens)
+            }
+            else -> {
+                val symbol = PUNCTUATION.firstOrNull { text.startsWith(it, i) }
+                    ?: throw ExpressionError("Unexpected character '$c'")
+                tokens.add(Token.Punct(symbol))
+                i += symbol.length
+            }
+        }
+    }
+    return tokens
+}
+
+private fun lexNumber(text: String, start: Int, allowFloat: Boolean, tokens: MutableList<Token>): Int {
+    var i = start
+    val hex = text.startsWith("0x", i) || text.startsWith("0X", i)
+    if (hex) i += 2
+    val digitsStart = i
+    while (i < text.length && (text[i].isDigit() || hex && text[i].isHex() || text[i] == '_')) i++
+    var isFloat = false
+    if (!hex && allowFloat && i + 1 < text.length && text[i] == '.' && text[i + 1].isDigit()) {
+        isFloat = true
+        i++
+        while (i < text.length && (text[i].isDigit() || text[i] == '_')) i++
+    }
+    val digits = text.substring(digitsStart, i).replace("_", "")
+    val suffix = NUMBER_SUFFIX.find(text.substring(i))?.value.orEmpty()
+    i += suffix.length
+    if (i < text.length && (text[i].isLetterOrDigit() || text[i] == '_')) {
+        throw ExpressionError("Invalid number '${text.substring(start, i + 1)}'")
+    }
+    tokens.add(when {
+        isFloat || suffix.startsWith("f") -> Token.Real(digits.toDouble())
+        else -> Token.Integer(digits.toLongOrNull(if (hex) 16 else 10)
+            ?: throw ExpressionError("Integer literal out of range: ${text.substring(start, i)}"))
+    })
+    return i
+}
+
+private fun Char.isHex(): Boolean = isDigit() || lowercaseChar() in 'a'..'f'
+
+private val COMPARISONS = setOf("==", "!=", "<", "<=", ">", ">=")
+
+private class SimpleExprParser(private val tokens: List<Token>) {
+    private var pos = 0
+
+    fun parseAll(): SimpleExpr {
+        if (tokens.isEmpty()) throw ExpressionError("Empty expression")
+        val expr = parseOr()
+        if (pos < tokens.size) throw ExpressionError("Unexpected ${describe(tokens[pos])}")
+        return expr
+    }
+
+    /** Parses an expression or a top-level `target = value` assignment. */
+    fun parseStatement(): SimpleExpr {
+        if (tokens.isEmpty()) throw ExpressionError("Empty expression")
+        val target = parseOr()
+        val expr = if (accept("=")) SimpleExpr.Assign(target, parseOr()) else target
+        if (pos < tokens.size) throw ExpressionError("Unexpected ${describe(tokens[pos])}")
+        return expr
+    }
+
+    private fun peekPunct(): String? = (tokens.getOrNull(pos) as? Token.Punct)?.symbol
+
+    private fun accept(symbol: String): Boolean {
+        if (peekPunct() != symbol) return false
+        pos++
+        return true
+    }
+
+    private fun expect(symbol: String) {
+        if (!accept(symbol)) {
+            throw ExpressionError("Expected '$symbol'" + (tokens.getOrNull(pos)?.let { ", found ${describe(it)}" } ?: ""))
+        }
+    }
+
+    private fun parseBinary(ops: Set<String>, next: () -> SimpleExpr): SimpleExpr {
+        var left = next()
+        while (true) {
+            val op = peekPunct()?.takeIf { it in ops } ?: return left
+            pos++
+            left = SimpleExpr.Binary(op, left, next())
+        }
+    }
+
+    private fun parseOr(): SimpleExpr = parseBinary(setOf("||")) { parseAnd() }
+
+    private fun parseAnd(): SimpleExpr = parseBinary(setOf("&&")) { parseComparison() }
+
+    private fun parseComparison(): SimpleExpr {
+        val left = parseSum()
+        val op = peekPunct()?.takeIf { it in COMPARISONS } ?: return left
+        pos++
+        val right = parseSum()
+        if (peekPunct() in COMPARISONS) throw ExpressionError("Comparison operators cannot be chained")
+        return SimpleExpr.Binary(op, left, right)
+    }
+
+    private fun parseSum(): SimpleExpr = parseBinary(setOf("+", "-")) { parseTerm() }
+
+    private fun parseTerm(): SimpleExpr = parseBinary(setOf("*", "/", "%")) { parseUnary() }
+
+    private fun parseUnary(): SimpleExpr = when {
+        accept("-") -> SimpleExpr.Unary("-", parseUnary())
+        accept("!") -> SimpleExpr.Unary("!", parseUnary())
+        accept("*") -> SimpleExpr.Deref(parseUnary())
+        else -> parsePostfix()
+    }
+
+    private fun parsePostfix(): SimpleExpr {
+        var expr = parsePrimary()
+        while (true) {
+            expr = when {
+                accept(".") -> when (val token = tokens.getOrNull(pos++)) {
+                    is Token.Ident -> SimpleExpr.Field(expr, token.name)
+                    is Token.Integer -> SimpleExpr.Field(expr, token.value.toString())
+                    else -> throw ExpressionError("Expected a field name after '.'")
+                }
+                accept("[") -> SimpleExpr.Index(expr, parseOr()).also { expect("]") }
+                accept("(") -> when (expr) {
+                    is SimpleExpr.Variable -> SimpleExpr.Call(expr.path, parseArguments())
+                    is SimpleExpr.Field -> SimpleExpr.MethodCall(expr.base, expr.name, parseArguments())
+                    else -> throw ExpressionError("Only functions and methods can be called")
+                }
+                else -> return expr
+            }
+        }
+    }
+
+    /** Parses call arguments after the opening `(`, through the closing `)`. */
+    private fun parseArguments(): List<SimpleExpr> {
+        val args = mutableListOf<SimpleExpr>()
+        if (accept(")")) return args
+        do {
+            args.add(parseOr())
+        } while (accept(","))
+        expect(")")
+        return args
+    }
+
+    private fun parsePrimary(): SimpleExpr = when (val token = tokens.getOrNull(pos++)) {
+        is Token.Integer -> SimpleExpr.IntLiteral(token.value)
+        is Token.Real -> SimpleExpr.FloatLiteral(token.value)
+        is Token.Ident -> when (token.name) {
+            "true" -> SimpleExpr.BoolLiteral(true)
+            "false" -> SimpleExpr.BoolLiteral(false)
+            else -> {
+                val path = mutableListOf(token.name)
+                while (accept("::")) {
+                    val segment = tokens.getOrNull(pos++) as? Token.Ident
+                        ?: throw ExpressionError("Expected a name after '::'")
+                    path.add(segment.name)
+                }
+                SimpleExpr.Variable(path.joinToString("::"))
+            }
+        }
+        is Token.Punct -> if (token.symbol == "(") {
+            parseOr().also { expect(")") }
+        } else {
+            throw ExpressionError("Unexpected '${token.symbol}'")
+        }
+        null -> throw ExpressionError("Unexpected end of expression")
+    }
+
+    private fun describe(token: Token): String = when (token) {
+        is Token.Ident -> "'${token.name}'"
+        is Token.Integer -> "'${token.value}'"
+        is Token.Real -> "'${token.value}'"
+        is Token.Punct -> "'${token.symbol}'"
+    }
+}
+
+// ── Python code generation ───────────────────────────────────────
+
+/**
+ * Compiles this expression to a Python expression over the `SBFrame`
+ * named [frame], using the helpers defined by [initSimpleExpressions].
+ * The result is an `SBValue` for places and a Python scalar otherwise.
+ */
+internal fun SimpleExpr.toPython(frame: String): String = when (this) {
+    is SimpleExpr.IntLiteral -> value.toString()
+    is SimpleExpr.FloatLiteral -> value.toString()
+    is SimpleExpr.BoolLiteral -> if (value) "True" else "False"
+    is SimpleExpr.Variable -> "_kdap_se_var($frame, ${pyStr(path)})"
+    is SimpleExpr.Field -> "_kdap_se_field(${base.toPython(frame)}, ${pyStr(name)})"
+    is SimpleExpr.Index -> "_kdap_se_index(${base.toPython(frame)}, ${index.toPython(frame)})"
+    is SimpleExpr.Deref -> "_kdap_se_deref(${operand.toPython(frame)})"
+    is SimpleExpr.Unary -> "_kdap_se_unary(${pyStr(op)}, ${operand.toPython(frame)})"
+    is SimpleExpr.Binary -> when (op) {
+        "&&" -> "(_kdap_se_truthy(${left.toPython(frame)}) and _kdap_se_truthy(${right.toPython(frame)}))"
+        "||" -> "(_kdap_se_truthy(${left.toPython(frame)}) or _kdap_se_truthy(${right.toPython(frame)}))"
+        else -> "_kdap_se_binary(${pyStr(op)}, ${left.toPython(frame)}, ${right.toPython(frame)})"
+    }
+    is SimpleExpr.Assign -> {
+        // A bare name may be an enumerator or variant of the target's type,
+        // so the value is only evaluated if it isn't one.
+        val name = (value as? SimpleExpr.Variable)?.path?.substringAfterLast("::")
+        "_kdap_se_assign(${target.toPython(frame)}, lambda: ${value.toPython(frame)}" +
+            (name?.let { ", ${pyStr(it)})" } ?: ")")
+    }
+    is SimpleExpr.Call -> "_kdap_se_call($frame, None, ${pyStr(path)}, [${args.joinToString(", ") { it.toPython(frame) }}])"
+    is SimpleExpr.MethodCall ->
+        "_kdap_se_call($frame, ${receiver.toPython(frame)}, ${pyStr(name)}, [${args.joinToString(", ") { it.toPython(frame) }}])"
+}
+
+/**
+ * Defines the [PY_SIMPLE_EXPRESSIONS] and [PY_FUNCTION_CALLS] helpers in
+ * LLDB's script interpreter. Call after [initRustFormatters], whose enum
+ * helpers they use.
+ */
+internal suspend fun initSimpleExpressions(debugger: SBDebugger) {
+    debugger.commandInterpreter().handleCommand("script exec(${pyStr(PY_SIMPLE_EXPRESSIONS)})")
+    debugger.commandInterpreter().handleCommand("script exec(${pyStr(PY_FUNCTION_CALLS)})")
+}
+
+/**
+ * Runtime for compiled simple expressions. `_kdap_se_run(fn, frame, fmt, calls)`
+ * evaluates `fn(frame)` and returns a JSON object with `result` (shown in
+ * the `lldb.Format` `fmt`, if given), `type`, and `path` (the LLDB
+ * expression path of a place that has children), or `error`. Function
+ * calls may run for `calls` seconds each; without it they fail.
+ * Assignments to enums (`_kdap_se_assign`, `_kdap_se_set_variant`) use
+ * the [initRustFormatters] helpers to find variants: `_kdap_is_rust_enum`,
+ * `_kdap_variant_name`, and the `_VARIANT` and `_DISCR` field prefixes.
+ */
+private val PY_SIMPLE_EXPRESSIONS = """
+    import json, math, operator, struct
+
+    _KDAP_SE_OPS = {
+        '+': operator.add, '-': operator.sub, '*': operator.mul,
+        '==': operator.eq, '!=': operator.ne, '<': operator.lt,
+        '<=': operator.le, '>': operator.gt, '>=': operator.ge,
+    }
+
+    def _kdap_se_var(frame, name):
+        v = frame.FindVariable(name)
+        if not v.IsValid():
+            v = frame.FindValue(name, lldb.eValueTypeVariableGlobal)
+        if not v.IsValid():
+            v = frame.FindValue(name, lldb.eValueTypeVariableStatic)
+        if not v.IsValid():
+            v = frame.GetThread().GetProcess().GetTarget().FindFirstGlobalVariable(name)
+        if not v.IsValid():
+            raise NameError("cannot find value '%s'" % name)
+        return v
+
+    def _kdap_se_place(x):
+        if not isinstance(x, lldb.SBValue):
+            raise TypeError('%s is not a place' % _kdap_se_result(x)['result'])
+        return x
+
+    def _kdap_se_autoderef(v):
+        for _ in range(8):
+            t = v.GetType()
+            if not (t.IsPointerType() or t.IsReferenceType()):
+                break
+            v = v.Dereference()
+        return v
+
+    def _kdap_se_field(x, name):
+        v = _kdap_se_autoderef(_kdap_se_place(x))
+        child = v.GetChildMemberWithName(name)
+        if not child.IsValid() and name.isdigit():
+            child = v.GetChildMemberWithName('__' + name)
+        if not child.IsValid():
+            raise AttributeError("no field '%s' on type '%s'" % (name, v.GetTypeName()))
+        return child
+
+    def _kdap_se_index(x, index):
+        v = _kdap_se_place(x)
+        i = _kdap_se_scalar(index)
+        if isinstance(i, bool) or not isinstance(i, int):
+            raise TypeError('index must be an integer')
+        if v.GetType().IsReferenceType():
+            v = v.Dereference()
+        if v.GetType().IsPointerType():
+            if not v.GetType().GetPointeeType().IsArrayType():
+                return v.GetChildAtIndex(i, lldb.eNoDynamicValues, True)
+            v = v.Dereference()
+        length = v.GetNumChildren()
+        if i < 0 or i >= length:
+            raise IndexError('index out of bounds: the len is %d but the index is %d' % (length, i))
+        return v.GetChildAtIndex(i)
+
+    def _kdap_se_deref(x):
+        v = _kdap_se_place(x)
+        t = v.GetType()
+        if t.IsPointerType() or t.IsReferenceType():
+            return v.Dereference()
+        # Box, Rc, and Arc: follow the first raw pointer in the layout.
+        raw = v.GetNonSyntheticValue()
+        for _ in range(8):
+            if raw.GetType().IsPointerType():
+                target = raw.Dereference()
+                inner = target.GetTypeName() or ''
+                if 'RcBox<' in inner or 'RcInner<' in inner:
+                    return target.GetChildMemberWithName('value')
+                if 'ArcInner<' in inner:
+                    return target.GetChildMemberWithName('data')
+                return target
+            if raw.GetNumChildren() == 0:
+                break
+            raw = raw.GetChildAtIndex(0)
+        raise TypeError("type '%s' cannot be dereferenced" % v.GetTypeName())
+
+    def _kdap_se_scalar(x):
+        if not isinstance(x, lldb.SBValue):
+            return x
+        if x.GetError().Fail():
+            raise ValueError(x.GetError().GetCString())
+        t = x.GetType().GetCanonicalType()
+        flags = t.GetTypeFlags()
+        if t.GetName() == 'bool':
+            return x.GetValueAsUnsigned() != 0
+        if flags & lldb.eTypeIsFloat:
+            return float(x.GetValue())
+        if flags & lldb.eTypeIsInteger and flags & lldb.eTypeIsSigned:
+            return x.GetValueAsSigned()
+        if flags & (lldb.eTypeIsScalar | lldb.eTypeIsPointer | lldb.eTypeIsEnumeration):
+            return x.GetValueAsUnsigned()
+        raise TypeError("type '%s' is not a number" % x.GetTypeName())
+
+    def _kdap_se_truthy(x):
+        return bool(_kdap_se_scalar(x))
+
+    def _kdap_se_unary(op, x):
+        v = _kdap_se_scalar(x)
+        if op == '-':
+            return -v
+        return (not v) if isinstance(v, bool) else ~v
+
+    def _kdap_se_div(a, b):
+        if isinstance(a, int) and isinstance(b, int):
+            if b == 0:
+                raise ZeroDivisionError('attempt to divide by zero')
+            q = abs(a) // abs(b)
+            return q if (a < 0) == (b < 0) else -q
+        return a / b
+
+    def _kdap_se_binary(op, x, y):
+        a, b = _kdap_se_scalar(x), _kdap_se_scalar(y)
+        if op == '/':
+            return _kdap_se_div(a, b)
+        if op == '%':
+            if isinstance(a, int) and isinstance(b, int):
+                return a - b * _kdap_se_div(a, b)
+            return math.fmod(a, b)
+        return _KDAP_SE_OPS[op](a, b)
+
+    def _kdap_se_store(target, v, wrap=False):
+        # Encodes v with the target's size, signedness, and the process byte
+        # order, and writes it where reads of the target find it.
+        t = target.GetType().GetCanonicalType()
+        flags = t.GetTypeFlags()
+        size = target.GetByteSize()
+        process = target.GetProcess()
+        order = lldb.eByteOrderBig if process.GetByteOrder() == lldb.eByteOrderBig else lldb.eByteOrderLittle
+        endian = 'big' if order == lldb.eByteOrderBig else 'little'
+        if flags & lldb.eTypeIsFloat:
+            fmt = {4: 'f', 8: 'd'}.get(size)
+            if fmt is None or isinstance(v, bool):
+                raise TypeError("cannot assign %s to '%s'" % (_kdap_se_result(v)['result'], target.GetTypeName()))
+            raw = struct.pack(('>' if endian == 'big' else '<') + fmt, float(v))
+        elif flags & (lldb.eTypeIsScalar | lldb.eTypeIsPointer | lldb.eTypeIsEnumeration) and 0 < size <= 16:
+            if isinstance(v, float):
+                raise TypeError("cannot assign a float to '%s'" % target.GetTypeName())
+            if t.GetName() == 'bool' and not isinstance(v, bool):
+                raise TypeError("cannot assign %s to 'bool'" % v)
+            bits = size * 8
+            v = int(v)
+            signed = False
+            if wrap:
+                v &= (1 << bits) - 1
+            else:
+                signed = bool(flags & lldb.eTypeIsSigned)
+                low = -(1 << (bits - 1)) if signed else 0
+                high = (1 << (bits - 1 if signed else bits)) - 1
+                if not low <= v <= high:
+                    raise OverflowError("%d does not fit in '%s'" % (v, target.GetTypeName()))
+            raw = v.to_bytes(size, endian, signed=signed)
+        else:
+            raise TypeError("cannot assign to '%s' of type '%s'" % (target.GetName(), target.GetTypeName()))
+        data = lldb.SBData()
+        e = lldb.SBError()
+        data.SetData(e, raw, order, process.GetAddressByteSize())
+        if e.Success():
+            target.SetData(data, e)
+        if e.Fail():
+            raise ValueError(e.GetCString() or "cannot assign to '%s'" % target.GetName())
+
+    def _kdap_se_set_variant(target, name):
+        # Rust enums (see the formatters): each `$variant$N` member holds the
+        # discriminant field, which all variants share; N is its value.
+        v = target.GetNonSyntheticValue()
+        for i in range(v.GetNumChildren()):
+            variant = v.GetChildAtIndex(i)
+            tag = variant.GetName() or ''
+            if not tag.startswith(_VARIANT) or _kdap_variant_name(variant.GetChildMemberWithName('value')) != name:
+                continue
+            discr = variant.GetChildMemberWithName(_DISCR)
+            if not discr.IsValid() or tag == _VARIANT:
+                raise ValueError("variant '%s' of '%s' has no discriminant value" % (name, target.GetTypeName()))
+            _kdap_se_store(discr, int(tag[len(_VARIANT):]), wrap=True)
+            return
+        raise ValueError("no variant '%s' in '%s'" % (name, target.GetTypeName()))
+
+    def _kdap_se_assign(x, value, name=None):
+        target = _kdap_se_place(x)
+        t = target.GetType().GetCanonicalType()
+        rust_enum = _kdap_is_rust_enum(t, None)
+        if name is not None and rust_enum:
+            _kdap_se_set_variant(target, name)
+            return target
+        if name is not None and t.GetTypeFlags() & lldb.eTypeIsEnumeration:
+            members = t.GetEnumMembers()
+            for i in range(members.GetSize()):
+                if members.GetTypeEnumMemberAtIndex(i).GetName() == name:
+                    _kdap_se_store(target, members.GetTypeEnumMemberAtIndex(i).GetValueAsUnsigned(), wrap=True)
+                    return target
+        if rust_enum:
+            raise TypeError("expected a variant name of '%s'" % target.GetTypeName())
+        _kdap_se_store(target, _kdap_se_scalar(value()))
+        return target
+
+    def _kdap_se_format_int(v, fmt):
+        # Scalars computed in Python are i64, as their reported type says.
+        if fmt == lldb.eFormatHex:
+            return '0x%x' % (v & 0xffffffffffffffff)
+        if fmt == lldb.eFormatBinary:
+            return '0b' + format(v & 0xffffffffffffffff, 'b')
+        if fmt == lldb.eFormatChar and 0 <= v < 0x110000:
+            return repr(chr(v))
+        return str(v)
+
+    def _kdap_se_result(x, fmt=None):
+        if isinstance(x, lldb.SBValue):
+            if x.GetError().Fail():
+                raise ValueError(x.GetError().GetCString())
+            path = None
+            if x.MightHaveChildren():
+                s = lldb.SBStream()
+                if x.GetExpressionPath(s):
+                    path = s.GetData()
+            value = None
+            if fmt is not None:
+                x.SetFormat(fmt)
+                value = x.GetValue()
+            return {'result': value or x.GetSummary() or x.GetValue() or '{...}', 'type': x.GetTypeName(), 'path': path}
+        if x is None:
+            return {'result': '()', 'type': '()'}
+        if isinstance(x, bool):
+            return {'result': 'true' if x else 'false', 'type': 'bool'}
+        if isinstance(x, float):
+            return {'result': repr(x), 'type': 'f64'}
+        return {'result': _kdap_se_format_int(x, fmt), 'type': 'i64'}
+
+    def _kdap_se_array_base(v):
+        # The pointer an array view indexes, and whether it is the value
+        # itself (rather than the address of a value that is not a pointer).
+        if v.GetType().IsReferenceType():
+            v = v.Dereference()
+        if v.GetType().IsPointerType():
+            return v, True
+        if v.GetType().IsArrayType():
+            v = v.GetChildAtIndex(0)
+        return v.AddressOf(), False
+
+    def _kdap_se_array_run(fn, length_fn, frame, start, count, fmt=None):
+        # Elements start.. of fn(frame) viewed as an array of length_fn(frame).
+        try:
+            ptr, indexable = _kdap_se_array_base(_kdap_se_place(fn(frame)))
+            length = _kdap_se_scalar(length_fn(frame))
+            if isinstance(length, bool) or not isinstance(length, int) or length < 0:
+                raise TypeError('array length must be a non-negative integer')
+            element_type = ptr.GetType().GetPointeeType()
+            if not element_type.IsValid() or element_type.GetByteSize() == 0:
+                raise TypeError("cannot view '%s' as an array" % ptr.GetTypeName())
+            elements = [_kdap_se_result(ptr.GetChildAtIndex(i, lldb.eNoDynamicValues, True), fmt)
+                        for i in range(start, min(length, start + count))]
+            return json.dumps({'length': length, 'type': '[%s; %d]' % (element_type.GetName(), length),
+                               'address': ptr.GetValueAsUnsigned(), 'elements': elements,
+                               'indexable': indexable})
+        except Exception as e:
+            return json.dumps({'error': str(e)})
+
+    def _kdap_se_native(frame, text):
+        # A native expression shown in a format lldb-dap has no option for.
+        options = lldb.SBExpressionOptions()
+        options.SetFetchDynamicValue(lldb.eDynamicDontRunTarget)
+        return frame.EvaluateExpression(text, options)
+
+    def _kdap_se_run(fn, frame, fmt=None, calls=None):
+        _kdap_se_calls.timeout = calls
+        try:
+            return json.dumps(_kdap_se_result(fn(frame), fmt))
+        except Exception as e:
+            return json.dumps({'error': str(e)})
+        finally:
+            _kdap_se_calls.timeout = None
+""".trimIndent()
@@ -0,0 +1,650 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.sb.SBDebugger
+import com.github.jomof.dap.sb.pyStr
+
+/**
+ * Rust data formatters, mirroring CodeLLDB's `formatters/rust.py`.
+ *
+ * lldb-dap renders Rust values with LLDB's C++ type system, so a `String`
+ * or `HashMap` shows up as its raw struct internals. KDAP installs Python
+ * summary and synthetic-children providers into LLDB's script interpreter
+ * and registers them by DWARF type name in the [RUST_CATEGORY] category:
+ *
+ * | Type                         | Summary                  | Children          |
+ * |------------------------------|--------------------------|-------------------|
+ * | `String`, `&str`             | `"text"`                 | `[raw bytes]`     |
+ * | `Vec<T>`, `&[T]`             | `(3) vec![1, 2, 3]`      | `[0]`, `[1]`, …   |
+ * | `HashMap<K, V>`, `BTreeMap`  | `(2) {"a": 1, "b": 2}`   | `["a"]`, `["b"]`  |
+ * | `Option<T>`, `Result<T, E>`  | `Some(5)`, `Err("bad")`  | variant fields    |
+ * | any other enum               | `Circle(1.5)`            | variant fields    |
+ * | `Box<T>`                     | pointee summary          | pointee children  |
+ * | `&dyn Trait`, `Box<dyn …>`   | concrete value summary   | concrete children |
+ * | `Rc<T>`, `Arc<T>`            | `(strong=1, weak=0) …`   | value children    |
+ * | `RefCell<T>`, `Mutex<T>`     | value summary + state    | value children    |
+ *
+ * Enums are read through LLDB's encoding of DWARF variant parts: one
+ * `$variant$N` member per variant, where the active one is the member
+ * whose `$discr$` equals `N`, or the member without `$discr$`. For
+ * niche-optimized enums `$discr$` is the niche field (a pointer, a
+ * `NonZero` integer, a spare `bool` or `char` value, …), so it is
+ * compared with `N` truncated to the field's width. An `Option` LLDB
+ * shows as its bare payload is decoded by the zero niche.
+ *
+ * Summaries nest their fields' summaries at most `_MAX_SUMMARY_DEPTH`
+ * levels deep, then show `...`, so a value cycling through an `Rc`
+ * summarizes in bounded time (see `RecursiveValues.kt`).
+ *
+ * A string's summary shows at most `_adapterSettings.stringPreviewLength`
+ * bytes ([DEFAULT_STRING_PREVIEW_LENGTH] by default), decoded as UTF-8
+ * with replacement characters; a longer one ends in `...` and its true
+ * length, `"abc"... (10000 bytes)`. Its `[raw bytes]` child is a `u8`
+ * array over all of it, which clients page, and its `memoryReference`
+ * (see `Variables.kt`) opens the whole buffer. Shown in hexadecimal
+ * (`,x`, or `format.hex`), a string's summary is a hex dump,
+ * `68 65 6c 6c 6f`; so is a byte buffer's (`&[u8]`, `Vec<u8>`), and
+ * with `,s` a byte buffer is decoded as text, `(5) b"hello"`.
+ *
+ * A trait object is a data pointer and a vtable pointer. The concrete
+ * type is recovered from the vtable's symbol (`<T as Trait>::{vtable}`)
+ * or, when that is anonymous, from its first entry, the drop glue
+ * `drop_in_place<T>` (null for types without drop glue, which then stay
+ * two raw pointers); the data pointer is read as a `T`.
+ *
+ * The category is registered before `initCommands`, so user commands can
+ * disable or override it.
+ *
+ * ## CodeLLDB mapping
+ *
+ * | CodeLLDB (formatters/rust.py) | KDAP (this file)          |
+ * |-------------------------------|---------------------------|
+ * | provider classes / functions  | [PY_RUST_FORMATTERS]      |
+ * | `__lldb_init_module`          | [initRustFormatters]      |
+ */
+
+/** LLDB type category holding KDAP's Rust formatters. */
+internal const val RUST_CATEGORY = "kdap-rust"
+
+/** Bytes of a string shown in its summary when the launch configuration sets no length. */
+internal const val DEFAULT_STRING_PREVIEW_LENGTH = 4096
+
+/**
+ * A Rust formatter registration: the types it applies to, the Python
+ * [summary] function, and the optional [synthetic] children class.
+ * [pattern] is a type-name regex, or the name of a Python recognizer
+ * function (called with each `SBType`) when [recognizer] is set.
+ */
+internal data class RustFormatter(
+    val pattern: String,
+    val summary: String,
+    val synthetic: String? = null,
+    val recognizer: Boolean = false,
+)
+
+/** Formatters registered by [initRustFormatters], in registration order. */
+internal val RUST_FORMATTERS = listOf(
+    RustFormatter("^alloc::string::String$", "_kdap_string_summary", "_KdapStringProvider"),
+    RustFormatter("^&(mut )?str$", "_kdap_str_summary", "_KdapStrProvider"),
+    RustFormatter("^alloc::vec::Vec<.+>$", "_kdap_vec_summary", "_KdapVecProvider"),
+    RustFormatter("^&(mut )?\\[.+\\]$", "_kdap_slice_summary", "_KdapSliceProvider"),
+    RustFormatter("^&(mut )?dyn .+$", "_kdap_dyn_summary", "_KdapDynProvider"),
+    RustFormatter("^std::collections::hash::map::HashMap<.+>$", "_kdap_hashmap_summary", "_KdapHashMapProvider"),
+    RustFormatter("^alloc::collections::btree::map::BTreeMap<.+>$", "_kdap_btreemap_summary", "_KdapBTreeMapProvider"),
+    RustFormatter("^core::option::Option<.+>$", "_kdap_enum_summary", "_KdapEnumProvider"),
+    RustFormatter("^core::result::Result<.+>$", "_kdap_enum_summary", "_KdapEnumProvider"),
+    RustFormatter("^alloc::boxed::Box<.+>$", "_kdap_box_summary", "_KdapBoxProvider"),
+    RustFormatter("^alloc::rc::Rc<.+>$", "_kdap_rc_summary", "_KdapRcProvider"),
+    RustFormatter("^alloc::sync::Arc<.+>$", "_kdap_rc_summary", "_KdapRcProvider"),
+    RustFormatter("^core::cell::RefCell<.+>$", "_kdap_refcell_summary", "_KdapRefCellProvider"),
+    RustFormatter("^std::sync::(.+::)?Mutex<.+>$", "_kdap_mutex_summary", "_KdapMutexProvider"),
+    RustFormatter("_kdap_is_rust_enum", "_kdap_enum_summary", "_KdapEnumProvider", recognizer = true),
+)
+
+/**
+ * LLDB commands that register [RUST_FORMATTERS] in [RUST_CATEGORY] and
+ * enable it. Regexes are single-quoted so LLDB passes them through
+ * without escape processing.
+ */
+internal fun rustFormatterCommands(): List<String> = buildList {
+    for (f in RUST_FORMATTERS) {
+        val match = if (f.recognizer) "--recognizer-function ${f.pattern}" else "-x '${f.pattern}'"
+        add("type summary add -w $RUST_CATEGORY -F ${f.summary} $match")
+        if (f.synthetic != null) {
+            add("type synthetic add -w $RUST_CATEGORY -l ${f.synthetic} $match")
+        }
+    }
+    add("type category enable $RUST_CATEGORY")
+}
+
+// ── __lldb_init_module (formatters/rust.py) ──────────────────────
+
+/**
+ * Defines the Python providers in LLDB's script interpreter and
+ * registers them for Rust types. String summaries show at most
+ * [stringPreviewLength] bytes.
+ */
+internal suspend fun initRustFormatters(debugger: SBDebugger, stringPreviewLength: Int = DEFAULT_STRING_PREVIEW_LENGTH) {
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_RUST_FORMATTERS)})")
+    interpreter.handleCommand("script _kdap_string_preview[0] = $stringPreviewLength")
+    for (command in rustFormatterCommands()) {
+        interpreter.handleCommand(command)
+    }
+}
+
+/**
+ * Summary functions and synthetic providers for [RUST_FORMATTERS].
+ * Summary functions read the raw layout through `GetNonSyntheticValue`;
+ * providers are handed the raw value directly.
+ */
+private val PY_RUST_FORMATTERS = """
+    _DOLLAR = '$'
+    _DISCR = _DOLLAR + 'discr' + _DOLLAR
+    _VARIANT = _DOLLAR + 'variant' + _DOLLAR
+    _POINTER_FIELDS = ('pointer', 'ptr', 'data_ptr', 'inner', 'buf', 'vec')
+    _MAX_SUMMARY_ITEMS = 8
+    _MAX_SUMMARY_DEPTH = 8
+    _summary_depth = [0]
+    _kdap_string_preview = [4096]
+    _RAW_BYTES = '[raw bytes]'
+
+    def _kdap_display(v):
+        # Summaries contain their fields' summaries; bound the nesting so
+        # a cyclic value doesn't recurse without end.
+        if _summary_depth[0] >= _MAX_SUMMARY_DEPTH:
+            return '...'
+        _summary_depth[0] += 1
+        try:
+            return v.GetSummary() or v.GetValue() or '{...}'
+        finally:
+            _summary_depth[0] -= 1
+
+    def _kdap_first_pointer(v):
+        for _ in range(8):
+            if not v.IsValid():
+                return None
+            if v.GetType().IsPointerType():
+                return v
+            for name in _POINTER_FIELDS:
+                child = v.GetChildMemberWithName(name)
+                if child.IsValid():
+                    v = child
+                    break
+            else:
+                if v.GetNumChildren() == 0:
+                    return None
+                v = v.GetChildAtIndex(0)
+        return None
+
+    def _kdap_pointee(v):
+        ptr = _kdap_first_pointer(v)
+        return ptr.Dereference() if ptr is not None else None
+
+    def _kdap_scalar(v):
+        while v.IsValid() and v.GetNumChildren() > 0:
+            v = v.GetChildAtIndex(0)
+        return v
+
+    def _kdap_uninit(v):
+        return v.GetChildMemberWithName('value').GetChildMemberWithName('value')
+
+    def _kdap_hex_format(v):
+        return v.GetFormat() in (lldb.eFormatHex, lldb.eFormatBytes)
+
+    def _kdap_read_preview(v, address, length):
+        # The first bytes of a buffer, up to the preview length.
+        if length == 0:
+            return b''
+        e = lldb.SBError()
+        data = v.GetProcess().ReadMemory(address, min(length, _kdap_string_preview[0]), e)
+        if not e.Success():
+            raise ValueError(e.GetCString())
+        return data
+
+    def _kdap_render_bytes(data, as_hex, prefix=''):
+        if as_hex:
+            return ' '.join('%02x' % b for b in bytearray(data))
+        text = data.decode('utf-8', 'replace').replace('\\', '\\\\').replace('"', '\\"')
+        return '%s"%s"' % (prefix, text)
+
+    def _kdap_read_string(valobj, address, length):
+        try:
+            data = _kdap_read_preview(valobj, address, length)
+        except ValueError as e:
+            return '<error: %s>' % e
+        text = _kdap_render_bytes(data, _kdap_hex_format(valobj))
+        return text + '... (%d bytes)' % length if length > len(data) else text
+
+    def _kdap_string_layout(v):
+        vec = v.GetChildMemberWithName('vec')
+        ptr = _kdap_first_pointer(vec)
+        return ptr.GetValueAsUnsigned() if ptr is not None else 0, vec.GetChildMemberWithName('len').GetValueAsUnsigned()
+
+    def _kdap_slice_layout(v):
+        return v.GetChildMemberWithName('data_ptr').GetValueAsUnsigned(), v.GetChildMemberWithName('length').GetValueAsUnsigned()
+
+    def _kdap_string_summary(valobj, internal_dict):
+        address, length = _kdap_string_layout(valobj.GetNonSyntheticValue())
+        return _kdap_read_string(valobj, address, length)
+
+    def _kdap_str_summary(valobj, internal_dict):
+        address, length = _kdap_slice_layout(valobj.GetNonSyntheticValue())
+        return _kdap_read_string(valobj, address, length)
+
+    def _kdap_is_byte_buffer(name):
+        return name in ('&[u8]', '&mut [u8]') or name.startswith('alloc::vec::Vec<u8,')
+
+    def _kdap_buffer_summary(valobj, address, length, prefix):
+        # A byte buffer shown in hexadecimal or as text; other sequences
+        # list their elements.
+        fmt = valobj.GetFormat()
+        if fmt not in (lldb.eFormatHex, lldb.eFormatBytes, lldb.eFormatCString) or not _kdap_is_byte_buffer(valobj.GetTypeName() or ''):
+            return _kdap_sequence_summary(valobj, length, prefix)
+        try:
+            data = _kdap_read_preview(valobj, address, length)
+        except ValueError as e:
+            return '<error: %s>' % e
+        text = _kdap_render_bytes(data, fmt != lldb.eFormatCString, 'b')
+        return '(%d) %s%s' % (length, text, '...' if length > len(data) else '')
+
+    def _kdap_sequence_summary(valobj, length, prefix):
+        count = min(length, _MAX_SUMMARY_ITEMS)
+        items = [_kdap_display(valobj.GetChildAtIndex(i)) for i in range(count)]
+        if length > count:
+            items.append('...')
+        return '(%d) %s[%s]' % (length, prefix, ', '.join(items))
+
+    def _kdap_vec_summary(valobj, internal_dict):
+        v = valobj.GetNonSyntheticValue()
+        ptr = _kdap_first_pointer(v.GetChildMemberWithName('buf'))
+        address = ptr.GetValueAsUnsigned() if ptr is not None else 0
+        return _kdap_buffer_summary(valobj, address, v.GetChildMemberWithName('len').GetValueAsUnsigned(), 'vec!')
+
+    def _kdap_slice_summary(valobj, internal_dict):
+        address, length = _kdap_slice_layout(valobj.GetNonSyntheticValue())
+        return _kdap_buffer_summary(valobj, address, length, '&')
+
+    def _kdap_map_summary(valobj, length):
+        count = min(length, _MAX_SUMMARY_ITEMS)
+        items = []
+        for i in range(count):
+            child = valobj.GetChildAtIndex(i)
+            items.append('%s: %s' % (child.GetName()[1:-1], _kdap_display(child)))
+        if length > count:
+            items.append('...')
+        return '(%d) {%s}' % (length, ', '.join(items))
+
+    def _kdap_hashmap_summary(valobj, internal_dict):
+        table = valobj.GetNonSyntheticValue().GetChildMemberWithName('base').GetChildMemberWithName('table')
+        length = table.GetChildMemberWithName('table').GetChildMemberWithName('items').GetValueAsUnsigned()
+        return _kdap_map_summary(valobj, length)
+
+    def _kdap_btreemap_summary(valobj, internal_dict):
+        length = valobj.GetNonSyntheticValue().GetChildMemberWithName('length').GetValueAsUnsigned()
+        return _kdap_map_summary(valobj, length)
+
+    def _kdap_is_rust_enum(sbtype, internal_dict):
+        return any((sbtype.GetFieldAtIndex(i).GetName() or '').startswith(_VARIANT)
+                   for i in range(sbtype.GetNumberOfFields()))
+
+    def _kdap_discr_matches(name, discr):
+        try:
+            expected = int(name[len(_VARIANT):])
+        except ValueError:
+            return False
+        mask = (1 << (discr.GetByteSize() * 8)) - 1
+        return (expected & mask) == (discr.GetValueAsUnsigned() & mask)
+
+    def _kdap_enum_variant(v):
+        default = None
+        for i in range(v.GetNumChildren()):
+            variant = v.GetChildAtIndex(i)
+            name = variant.GetName() or ''
+            if not name.startswith(_VARIANT):
+                continue
+            discr = variant.GetChildMemberWithName(_DISCR)
+            if not discr.IsValid() or name == _VARIANT:
+                default = variant.GetChildMemberWithName('value')
+            elif _kdap_discr_matches(name, discr):
+                return variant.GetChildMemberWithName('value')
+        return default
+
+    def _kdap_niche_option(v):
+        # Without variant-part support LLDB shows a niche-optimized Option
+        # as its bare payload (a reference, NonNull, NonZero, ...); zero is
+        # the None niche.
+        if not (v.GetType().GetName() or '').startswith('core::option::Option<'):
+            return None
+        payload_type = v.GetType().GetTemplateArgumentType(0)
+        if not payload_type.IsValid() or payload_type.GetByteSize() != v.GetByteSize():
+            return None
+        if _kdap_scalar(v).GetValueAsUnsigned() == 0:
+            return 'None', []
+        return 'Some', [v.CreateValueFromAddress('__0', v.GetLoadAddress(), payload_type)]
+
+    def _kdap_enum_active(v):
+        variant = _kdap_enum_variant(v)
+        if variant is None:
+            return _kdap_niche_option(v)
+        fields = [variant.GetChildAtIndex(i) for i in range(variant.GetNumChildren())]
+        return _kdap_variant_name(variant), fields
+
+    def _kdap_variant_name(value):
+        name = value.GetType().GetName() or ''
+        depth, start = 0, 0
+        for i, ch in enumerate(name):
+            if ch == '<':
+                depth += 1
+            elif ch == '>':
+                depth -= 1
+            elif ch == ':' and depth == 0:
+                start = i + 1
+        return name[start:].split('<', 1)[0]
+
+    def _kdap_enum_summary(valobj, internal_dict):
+        active = _kdap_enum_active(valobj.GetNonSyntheticValue())
+        if active is None:
+            return None
+        name, fields = active
+        return '%s(%s)' % (name, ', '.join(_kdap_display(f) for f in fields)) if fields else name
+
+    def _kdap_strip_hash(name):
+        head, sep, tail = name.rpartition('::h')
+        if sep and len(tail) == 16 and all(c in '0123456789abcdef' for c in tail):
+            return head
+        return name
+
+    def _kdap_vtable_self_type(name):
+        depth = 0
+        for i, ch in enumerate(name):
+            if ch == '<':
+                depth += 1
+            elif ch == '>':
+                depth -= 1
+            elif depth == 1 and name.startswith(' as ', i):
+                return name[1:i]
+        return None
+
+    def _kdap_symbol_name(target, address):
+        return _kdap_strip_hash(target.ResolveLoadAddress(address).GetSymbol().GetName() or '')
+
+    def _kdap_dyn_type_name(v, vtable):
+        target = v.GetTarget()
+        name = _kdap_symbol_name(target, vtable)
+        if name.startswith('<') and '::{vtable' in name:
+            return _kdap_vtable_self_type(name)
+        e = lldb.SBError()
+        drop = v.GetProcess().ReadPointerFromMemory(vtable, e)
+        if not e.Success() or drop == 0:
+            return None
+        name = _kdap_symbol_name(target, drop)
+        prefix = 'core::ptr::drop_in_place<'
+        if name.startswith(prefix) and name.endswith('>'):
+            return name[len(prefix):-1]
+        return None
+
+    def _kdap_is_dyn(v):
+        return v.GetChildMemberWithName('pointer').IsValid() and v.GetChildMemberWithName('vtable').IsValid()
+
+    def _kdap_dyn_value(v):
+        if not _kdap_is_dyn(v):
+            return None
+        name = _kdap_dyn_type_name(v, v.GetChildMemberWithName('vtable').GetValueAsUnsigned())
+        concrete = v.GetTarget().FindFirstType(name) if name else None
+        if concrete is None or not concrete.IsValid():
+            return None
+        address = v.GetChildMemberWithName('pointer').GetValueAsUnsigned()
+        return v.CreateValueFromAddress('data', address, concrete)
+
+    def _kdap_dyn_summary(valobj, internal_dict):
+        value = _kdap_dyn_value(valobj.GetNonSyntheticValue())
+        if value is None:
+            return None
+        return value.GetSummary() or value.GetValue() or value.GetType().GetName()
+
+    def _kdap_box_summary(valobj, internal_dict):
+        v = valobj.GetNonSyntheticValue()
+        if _kdap_is_dyn(v):
+            return _kdap_dyn_summary(valobj, internal_dict)
+        pointee = _kdap_pointee(v)
+        return _kdap_display(pointee) if pointee is not None else None
+
+    def _kdap_rc_inner(v):
+        inner = _kdap_pointee(v)
+        if inner is None:
+            return None, None
+        value = inner.GetChildMemberWithName('value')
+        return inner, value if value.IsValid() else inner.GetChildMemberWithName('data')
+
+    def _kdap_rc_summary(valobj, internal_dict):
+        inner, value = _kdap_rc_inner(valobj.GetNonSyntheticValue())
+        if inner is None:
+            return None
+        strong = _kdap_scalar(inner.GetChildMemberWithName('strong')).GetValueAsUnsigned()
+        weak = _kdap_scalar(inner.GetChildMemberWithName('weak')).GetValueAsUnsigned()
+        return '(strong=%d, weak=%d) %s' % (strong, max(weak - 1, 0), _kdap_display(value))
+
+    def _kdap_refcell_summary(valobj, internal_dict):
+        v = valobj.GetNonSyntheticValue()
+        borrow = _kdap_scalar(v.GetChildMemberWithName('borrow')).GetValueAsSigned()
+        value = _kdap_display(v.GetChildMemberWithName('value').GetChildMemberWithName('value'))
+        if borrow < 0:
+            return '%s (borrowed mutably)' % value
+        if borrow > 0:
+            return '%s (borrowed %d)' % (value, borrow)
+        return value
+
+    def _kdap_mutex_summary(valobj, internal_dict):
+        v = valobj.GetNonSyntheticValue()
+        value = _kdap_display(v.GetChildMemberWithName('data').GetChildMemberWithName('value'))
+        if _kdap_scalar(v.GetChildMemberWithName('poison')).GetValueAsUnsigned():
+            return '%s (poisoned)' % value
+        return value
+
+    class _KdapStringProvider:
+        # One child, a u8 array over the whole string.
+        def __init__(self, valobj, internal_dict):
+            self.valobj = valobj
+            self.update()
+        def layout(self, v):
+            return _kdap_string_layout(v)
+        def update(self):
+            self.raw = None
+            address, length = self.layout(self.valobj)
+            if address != 0 and length > 0:
+                target = self.valobj.GetTarget()
+                u8 = target.FindFirstType('u8')
+                if not u8.IsValid():
+                    u8 = target.GetBasicType(lldb.eBasicTypeUnsignedChar)
+                self.raw = self.valobj.CreateValueFromAddress(_RAW_BYTES, address, u8.GetArrayType(length))
+            return False
+        def num_children(self):
+            return 1 if self.raw is not None else 0
+        def get_child_at_index(self, index):
+            return self.raw if index == 0 else None
+        def get_child_index(self, name):
+            return 0 if name == _RAW_BYTES else -1
+        def has_children(self):
+            return self.raw is not None
+
+    class _KdapStrProvider(_KdapStringProvider):
+        def layout(self, v):
+            return _kdap_slice_layout(v)
+
+    class _KdapSequenceProvider:
+        def __init__(self, valobj, internal_dict):
+            self.valobj = valobj
+            self.update()
+        def update(self):
+            self.address, self.length, self.element_type = self.layout(self.valobj)
+            self.element_size = self.element_type.GetByteSize() if self.element_type.IsValid() else 0
+            if self.address == 0 or self.element_size == 0:
+                self.length = 0
+            return False
+        def num_children(self):
+            return self.length
+        def get_child_at_index(self, index):
+            if index < 0 or index >= self.length:
+                return None
+            address = self.address + index * self.element_size
+            return self.valobj.CreateValueFromAddress('[%d]' % index, address, self.element_type)
+        def get_child_index(self, name):
+            try:
+                return int(name.lstrip('[').rstrip(']'))
+            except ValueError:
+                return -1
+        def has_children(self):
+            return True
+
+    class _KdapVecProvider(_KdapSequenceProvider):
+        def layout(self, v):
+            ptr = _kdap_first_pointer(v.GetChildMemberWithName('buf'))
+            element_type = v.GetType().GetTemplateArgumentType(0)
+            if not element_type.IsValid() and ptr is not None:
+                element_type = ptr.GetType().GetPointeeType()
+            address = ptr.GetValueAsUnsigned() if ptr is not None else 0
+            return address, v.GetChildMemberWithName('len').GetValueAsUnsigned(), element_type
+
+    class _KdapSliceProvider(_KdapSequenceProvider):
+        def layout(self, v):
+            ptr = v.GetChildMemberWithName('data_ptr')
+            length = v.GetChildMemberWithName('length').GetValueAsUnsigned()
+            return ptr.GetValueAsUnsigned(), length, ptr.GetType().GetPointeeType()
+
+    class _KdapMapProvider:
+        def __init__(self, valobj, internal_dict):
+            self.valobj = valobj
+            self.update()
+        def update(self):
+            self.entries = []
+            self.collect(self.valobj)
+            return False
+        def num_children(self):
+            return len(self.entries)
+        def get_child_at_index(self, index):
+            if index < 0 or index >= len(self.entries):
+                return None
+            key, value = self.entries[index]
+            name = '[%s]' % _kdap_display(key)
+            return self.valobj.CreateValueFromAddress(name, value.GetLoadAddress(), value.GetType())
+        def get_child_index(self, name):
+            for i, (key, _) in enumerate(self.entries):
+                if name == '[%s]' % _kdap_display(key):
+                    return i
+            return -1
+        def has_children(self):
+            return True
+
+    class _KdapHashMapProvider(_KdapMapProvider):
+        def collect(self, v):
+            table = v.GetChildMemberWithName('base').GetChildMemberWithName('table')
+            inner = table.GetChildMemberWithName('table')
+            pair_type = table.GetType().GetTemplateArgumentType(0)
+            ctrl = _kdap_first_pointer(inner.GetChildMemberWithName('ctrl'))
+            items = inner.GetChildMemberWithName('items').GetValueAsUnsigned()
+            if ctrl is None or not pair_type.IsValid() or items == 0:
+                return
+            buckets = inner.GetChildMemberWithName('bucket_mask').GetValueAsUnsigned() + 1
+            base = ctrl.GetValueAsUnsigned()
+            e = lldb.SBError()
+            control = v.GetProcess().ReadMemory(base, buckets, e)
+            if not e.Success():
+                return
+            # hashbrown stores buckets downwards from the control bytes;
+            # a clear top bit marks an occupied bucket.
+            for i in range(buckets):
+                if control[i] & 0x80 == 0:
+                    address = base - (i + 1) * pair_type.GetByteSize()
+                    pair = v.CreateValueFromAddress('pair', address, pair_type)
+                    self.entries.append((pair.GetChildAtIndex(0), pair.GetChildAtIndex(1)))
+                    if len(self.entries) == items:
+                        return
+
+    class _KdapBTreeMapProvider(_KdapMapProvider):
+        def collect(self, v):
+            length = v.GetChildMemberWithName('length').GetValueAsUnsigned()
+            root = _kdap_enum_active(v.GetChildMemberWithName('root'))
+            if length == 0 or root is None or not root[1]:
+                return
+            root = root[1][0]
+            node = _kdap_first_pointer(root.GetChildMemberWithName('node'))
+            if node is not None:
+                self.walk(node, root.GetChildMemberWithName('height').GetValueAsUnsigned(), length)
+        def walk(self, node, height, limit):
+            leaf = node.Dereference()
+            keys = leaf.GetChildMemberWithName('keys')
+            vals = leaf.GetChildMemberWithName('vals')
+            count = leaf.GetChildMemberWithName('len').GetValueAsUnsigned()
+            internal = None
+            if height > 0:
+                name = node.GetType().GetPointeeType().GetName().replace('LeafNode', 'InternalNode', 1)
+                internal_type = node.GetTarget().FindFirstType(name)
+                internal = node.Cast(internal_type.GetPointerType()).Dereference()
+            for i in range(count + 1):
+                if len(self.entries) >= limit:
+                    return
+                if internal is not None:
+                    edge = _kdap_first_pointer(_kdap_uninit(internal.GetChildMemberWithName('edges').GetChildAtIndex(i)))
+                    if edge is not None:
+                        self.walk(edge, height - 1, limit)
+                if i < count:
+                    self.entries.append((_kdap_uninit(keys.GetChildAtIndex(i)), _kdap_uninit(vals.GetChildAtIndex(i))))
+
+    class _KdapDelegateProvider:
+        def __init__(self, valobj, internal_dict):
+            self.valobj = valobj
+            self.update()
+        def update(self):
+            target = self.target(self.valobj)
+            self.value = target if target is not None and target.IsValid() else self.valobj
+            return False
+        def num_children(self):
+            return self.value.GetNumChildren()
+        def get_child_at_index(self, index):
+            return self.value.GetChildAtIndex(index)
+        def get_child_index(self, name):
+            return self.value.GetIndexOfChildWithName(name)
+        def has_children(self):
+            return self.value.MightHaveChildren()
+
+    class _KdapEnumProvider:
+        def __init__(self, valobj, internal_dict):
+            self.valobj = valobj
+            self.update()
+        def update(self):
+            active = _kdap_enum_active(self.valobj)
+            self.fields = active[1] if active is not None else None
+            return False
+        def num_children(self):
+            return len(self.fields) if self.fields is not None else self.valobj.GetNumChildren()
+        def get_child_at_index(self, index):
+            if self.fields is None:
+                return self.valobj.GetChildAtIndex(index)
+            return self.fields[index] if 0 <= index < len(self.fields) else None
+        def get_child_index(self, name):
+            if self.fields is None:
+                return self.valobj.GetIndexOfChildWithName(name)
+            return next((i for i, f in enumerate(self.fields) if f.GetName() == name), -1)
+        def has_children(self):
+            return self.num_children() > 0
+
+    class _KdapBoxProvider(_KdapDelegateProvider):
+        def target(self, v):
+            return _kdap_dyn_value(v) if _kdap_is_dyn(v) else _kdap_pointee(v)
+
+    class _KdapDynProvider(_KdapDelegateProvider):
+        def target(self, v):
+            return _kdap_dyn_value(v)
+
+    class _KdapRcProvider(_KdapDelegateProvider):
+        def target(self, v):
+            return _kdap_rc_inner(v)[1]
+
+    class _KdapRefCellProvider(_KdapDelegateProvider):
+        def target(self, v):
+            return v.GetChildMemberWithName('value').GetChildMemberWithName('value')
+
+    class _KdapMutexProvider(_KdapDelegateProvider):
+        def target(self, v):
+            return v.GetChildMemberWithName('data').GetChildMemberWithName('value')
+""".trimIndent()
@@ -0,0 +1,317 @@
+package com.github.jomof.dap.debugsession
+
+/**
+ * Function calls in simple expressions: `f(a, b)` and `v.len()` call
+ * functions of the debuggee from watch and debug console expressions.
+ * KDAP extension; CodeLLDB's simple expressions never run the debuggee,
+ * and LLDB's own expression evaluator can't compile Rust.
+ *
+ * The function is looked up by name among the target's functions and
+ * symbols ([PY_FUNCTION_CALLS]), so it must have been compiled in: a
+ * generic that is only inlined has no symbol to call. Names compare
+ * without generic arguments or the Rust hash, `v.len()` calls
+ * `<type of v>::len` with `v` as `self`, and where monomorphizations
+ * share a name the one whose `self` has `v`'s type is chosen.
+ *
+ * The call is made by hand, on the thread the expression's frame is on,
+ * with the System V x86-64 or AAPCS64 calling convention:
+ *
+ * 1. the thread's registers are saved;
+ * 2. a scratch stack frame is set up below the stack pointer (and the
+ *    x86-64 red zone), the integer and pointer arguments go in the
+ *    argument registers, and the return address is the program's entry
+ *    point, pushed or put in the link register;
+ * 3. a breakpoint goes on the entry point for this thread, and the
+ *    thread alone runs from the function's address, with LLDB in
+ *    synchronous mode so lldb-dap sees no stop or resume;
+ * 4. the result is read from the return register once the thread stops
+ *    there with the scratch frame popped, and the saved registers are
+ *    written back.
+ *
+ * If the callee does not return within `_adapterSettings.evaluationTimeout`
+ * seconds (5 by default), the process is interrupted; if it stops
+ * anywhere else first (a signal, a trap, a breakpoint), the call is
+ * abandoned. Either way the registers are restored and the expression
+ * fails with the reason. If the interrupt does not stop the process, the
+ * registers can't be restored: the expression fails saying the thread's
+ * state is corrupt. Other threads stay suspended during the call,
+ * so a callee that waits on a lock they hold times out.
+ *
+ * Arguments may be integers, `bool`s, and pointers; a place passed
+ * where the function takes a pointer or reference is passed by address.
+ * The result may be an integer, `bool`, pointer, or C-like enum, or
+ * nothing (`()`). Hovers, completions, breakpoint conditions, and Copy
+ * Value never call functions.
+ */
+
+/** Seconds a function call may run when the launch configuration sets no `evaluationTimeout`. */
+internal const val DEFAULT_EVALUATION_TIMEOUT = 5.0f
+
+/** `evaluate` contexts whose simple expressions may call functions. */
+internal val FUNCTION_CALL_CONTEXTS = setOf("watch", "repl")
+
+/**
+ * Python side of function calls, defined with the simple-expression
+ * runtime. `_kdap_se_call(frame, receiver, name, args)` calls the
+ * function [name] (a method of `receiver`'s type if `receiver` is not
+ * `None`) and returns the result as an `SBValue` or Python scalar. It
+ * raises unless `_kdap_se_run` allowed calls on this Python thread
+ * (`_kdap_se_calls` is thread-local, so a breakpoint condition on LLDB's
+ * own thread never may).
+ */
+internal val PY_FUNCTION_CALLS = """
+    import re, threading
+
+    if '_kdap_se_calls' not in globals():
+        _kdap_se_calls = threading.local()
+
+    # Argument registers, return register, stack pointer, program counter,
+    # link register (None: the return address is pushed), and red zone.
+    _KDAP_CALL_ABIS = {
+        'x86_64': (('rdi', 'rsi', 'rdx', 'rcx', 'r8', 'r9'), 'rax', 'rsp', 'rip', None, 128),
+        'aarch64': (('x0', 'x1', 'x2', 'x3', 'x4', 'x5', 'x6', 'x7'), 'x0', 'sp', 'pc', 'lr', 0),
+        'arm64': (('x0', 'x1', 'x2', 'x3', 'x4', 'x5', 'x6', 'x7'), 'x0', 'sp', 'pc', 'lr', 0),
+        'arm64e': (('x0', 'x1', 'x2', 'x3', 'x4', 'x5', 'x6', 'x7'), 'x0', 'sp', 'pc', 'lr', 0),
+    }
+
+    _KDAP_CALL_HASH = re.compile(r'::h[0-9a-f]{16}${'$'}')
+    _KDAP_CALL_ESCAPES = (('${'$'}LT${'$'}', '<'), ('${'$'}GT${'$'}', '>'), ('${'$'}C${'$'}', ','),
+                          ('${'$'}u20${'$'}', ' '), ('${'$'}RF${'$'}', '&'), ('${'$'}BP${'$'}', '*'), ('..', '::'))
+
+    def _kdap_call_base_name(name):
+        # `<alloc::vec::Vec<T, A>>::len::h<hash>` is `alloc::vec::Vec::len`.
+        for escape, text in _KDAP_CALL_ESCAPES:
+            name = name.replace(escape, text)
+        name = _KDAP_CALL_HASH.sub('', name.split('(')[0].strip())
+        if name.startswith('<'):
+            depth = 0
+            for i, c in enumerate(name):
+                depth += (c == '<') - (c == '>')
+                if depth == 0:
+                    name = name[1:i].split(' as ')[0] + name[i + 1:]
+                    break
+        base, depth = [], 0
+        for c in name:
+            if c == '<':
+                depth += 1
+            elif c == '>':
+                depth = max(0, depth - 1)
+            elif depth == 0:
+                base.append(c)
+        return ''.join(base).strip()
+
+    def _kdap_call_type_name(t):
+        if t.IsPointerType() or t.IsReferenceType():
+            t = t.GetPointeeType()
+        return t.GetUnqualifiedType().GetName() or ''
+
+    def _kdap_call_self_type(function):
+        if function is None:
+            return None
+        types = function.GetType().GetFunctionArgumentTypes()
+        return _kdap_call_type_name(types.GetTypeAtIndex(0)) if types.GetSize() > 0 else None
+
+    def _kdap_call_find(target, path, receiver_type=None):
+        candidates, seen = [], set()
+        for sc in target.FindFunctions(path.split('::')[-1]):
+            function = sc.GetFunction() if sc.GetFunction().IsValid() else None
+            symbol = sc.GetSymbol()
+            name = function.GetName() if function else (symbol.GetName() if symbol.IsValid() else None)
+            start = function.GetStartAddress() if function else symbol.GetStartAddress()
+            address = start.GetLoadAddress(target)
+            base = _kdap_call_base_name(name or '')
+            if address == lldb.LLDB_INVALID_ADDRESS or address in seen or not (base == path or base.endswith('::' + path)):
+                continue
+            seen.add(address)
+            candidates.append((name, address, function))
+        if receiver_type is not None and len(candidates) > 1:
+            candidates = [c for c in candidates if _kdap_call_self_type(c[2]) == receiver_type] or candidates
+        if not candidates:
+            raise NameError("cannot find function '%s'; it may have been inlined or not compiled in" % path)
+        if len(candidates) > 1:
+            raise NameError("'%s' is ambiguous: %s" % (path, ', '.join(sorted(c[0] for c in candidates))))
+        return candidates[0]
+
+    def _kdap_call_arg(x, param):
+        if isinstance(x, lldb.SBValue):
+            t = x.GetType()
+            by_address = param is not None and (param.IsPointerType() or param.IsReferenceType())
+            if by_address and not (t.IsPointerType() or t.IsReferenceType()):
+                address = x.GetLoadAddress()
+                if address == lldb.LLDB_INVALID_ADDRESS:
+                    raise ValueError("'%s' is not in memory, so it can't be passed by reference" % x.GetName())
+                return address
+        v = _kdap_se_scalar(x)
+        if isinstance(v, float):
+            raise TypeError('only integer, bool, and pointer arguments can be passed')
+        return int(v) & 0xffffffffffffffff
+
+    def _kdap_call_register(frame, name):
+        reg = frame.FindRegister(name)
+        if not reg.IsValid():
+            raise RuntimeError("register '%s' is not available" % name)
+        return reg
+
+    def _kdap_call_write(frame, name, value):
+        e = lldb.SBError()
+        _kdap_call_register(frame, name).SetValueFromCString('0x%x' % value, e)
+        if e.Fail():
+            raise RuntimeError("cannot write register '%s': %s" % (name, e.GetCString()))
+
+    def _kdap_call_save(frame):
+        saved = []
+        for regset in frame.GetRegisters():
+            for reg in regset:
+                e = lldb.SBError()
+                data = reg.GetData()
+                raw = data.ReadRawData(e, 0, data.GetByteSize()) if data.GetByteSize() > 0 else None
+                if raw and e.Success():
+                    saved.append((reg.GetName(), raw))
+        return saved
+
+    def _kdap_call_restore(thread, saved, pc_name):
+        process = thread.GetProcess()
+        order = process.GetByteOrder()
+        endian = 'big' if order == lldb.eByteOrderBig else 'little'
+        frame = thread.GetFrameAtIndex(0)
+        pc = None
+        for name, raw in saved:
+            reg = frame.FindRegister(name)
+            if not reg.IsValid():
+                continue
+            if name == pc_name:
+                pc = int.from_bytes(raw, endian)
+                continue
+            e = lldb.SBError()
+            current = reg.GetData()
+            if bytes(current.ReadRawData(e, 0, current.GetByteSize()) or b'') == bytes(raw):
+                continue
+            data = lldb.SBData()
+            data.SetData(e, raw, order, process.GetAddressByteSize())
+            if e.Success():
+                reg.SetData(data, e)
+        if pc is None:
+            return
+        # `register write` also drops the thread's cached frames, which were
+        # unwound from the call's registers.
+        selected_thread = process.GetSelectedThread().GetThreadID()
+        selected_frame = thread.GetSelectedFrame().GetFrameID()
+        process.SetSelectedThread(thread)
+        thread.SetSelectedFrame(0)
+        result = lldb.SBCommandReturnObject()
+        lldb.debugger.GetCommandInterpreter().HandleCommand('register write %s 0x%x' % (pc_name, pc), result)
+        thread.SetSelectedFrame(selected_frame)
+        process.SetSelectedThreadByID(selected_thread)
+
+    def _kdap_call_run(thread, address, args, name, timeout):
+        process = thread.GetProcess()
+        target = process.GetTarget()
+        abi = _KDAP_CALL_ABIS.get(target.GetTriple().split('-')[0])
+        if abi is None:
+            raise TypeError('function calls are not supported on %s' % target.GetTriple())
+        arg_regs, ret_reg, sp_reg, pc_reg, lr_reg, red_zone = abi
+        if len(args) > len(arg_regs):
+            raise TypeError("'%s' takes more arguments than go in registers" % name)
+        module = target.FindModule(target.GetExecutable())
+        entry = module.GetObjectFileEntryPointAddress() if module.IsValid() else lldb.SBAddress()
+        ret = entry.GetLoadAddress(target) if entry.IsValid() else lldb.LLDB_INVALID_ADDRESS
+        if ret == lldb.LLDB_INVALID_ADDRESS:
+            raise RuntimeError("the program's entry point is unknown, so '%s' has nowhere to return" % name)
+        top = thread.GetFrameAtIndex(0)
+        saved = _kdap_call_save(top)
+        size = process.GetAddressByteSize()
+        sp = (_kdap_call_register(top, sp_reg).GetValueAsUnsigned() - red_zone - 256) & ~0xf
+        returned_sp = sp
+        if lr_reg is None:
+            sp -= size
+            e = lldb.SBError()
+            endian = 'big' if process.GetByteOrder() == lldb.eByteOrderBig else 'little'
+            process.WriteMemory(sp, ret.to_bytes(size, endian), e)
+            if e.Fail():
+                raise RuntimeError('cannot write the return address: %s' % e.GetCString())
+        bp = target.BreakpointCreateByAddress(ret)
+        bp.SetThreadID(thread.GetThreadID())
+        others = [t for t in process if t.GetThreadID() != thread.GetThreadID() and not t.IsSuspended()]
+        fired = []
+        def interrupt():
+            fired.append(True)
+            process.SendAsyncInterrupt()
+        timer = threading.Timer(timeout, interrupt)
+        debugger = target.GetDebugger()
+        was_async = debugger.GetAsync()
+        try:
+            for reg, value in zip(arg_regs, args):
+                _kdap_call_write(top, reg, value)
+            if lr_reg is not None:
+                _kdap_call_write(top, lr_reg, ret)
+            _kdap_call_write(top, sp_reg, sp)
+            _kdap_call_write(top, pc_reg, address)
+            for t in others:
+                t.Suspend()
+            debugger.SetAsync(False)
+            timer.start()
+            process.Continue()
+            timer.cancel()
+            state = process.GetState()
+            if state != lldb.eStateStopped:
+                raise RuntimeError("the process %s during the call to '%s'" % (
+                    'exited' if state == lldb.eStateExited else 'did not stop', name))
+            stopped = thread.GetFrameAtIndex(0)
+            if stopped.GetPC() == ret and _kdap_call_register(stopped, sp_reg).GetValueAsUnsigned() == returned_sp:
+                return _kdap_call_register(stopped, ret_reg).GetValueAsUnsigned()
+            if fired:
+                raise TimeoutError("'%s' did not return within %g s; the call was abandoned" % (name, timeout))
+            reason = thread.GetStopDescription(256) or 'an unexpected stop'
+            raise RuntimeError("'%s' stopped before returning (%s); the call was abandoned" % (name, reason))
+        finally:
+            timer.cancel()
+            debugger.SetAsync(was_async)
+            for t in others:
+                t.Resume()
+            target.BreakpointDelete(bp.GetID())
+            state = process.GetState()
+            if state == lldb.eStateStopped:
+                _kdap_call_restore(thread, saved, pc_reg)
+            elif state != lldb.eStateExited:
+                raise RuntimeError("the process did not stop after the call to '%s', so thread %d's registers "
+                                   "could not be restored; its state is corrupt" % (name, thread.GetIndexID()))
+
+    def _kdap_call_result(target, function, raw):
+        # Without debug info the result is taken as an i64.
+        if function is None:
+            return raw - (1 << 64) if raw >= (1 << 63) else raw
+        t = function.GetType().GetFunctionReturnType()
+        if not t.IsValid() or t.GetName() in ('void', '()'):
+            return None
+        flags = t.GetCanonicalType().GetTypeFlags()
+        size = t.GetByteSize()
+        if flags & lldb.eTypeIsFloat or not flags & (lldb.eTypeIsScalar | lldb.eTypeIsPointer | lldb.eTypeIsEnumeration) or size > 8:
+            raise TypeError("cannot read the result of type '%s'; only integers, bools, and pointers are returned" % t.GetName())
+        process = target.GetProcess()
+        order = lldb.eByteOrderBig if process.GetByteOrder() == lldb.eByteOrderBig else lldb.eByteOrderLittle
+        raw &= (1 << (size * 8)) - 1
+        e = lldb.SBError()
+        data = lldb.SBData()
+        data.SetData(e, raw.to_bytes(size, 'big' if order == lldb.eByteOrderBig else 'little'), order, process.GetAddressByteSize())
+        return target.CreateValueFromData('result', data, t)
+
+    def _kdap_se_call(frame, receiver, name, args):
+        timeout = getattr(_kdap_se_calls, 'timeout', None)
+        if timeout is None:
+            raise TypeError('functions are only called from watch and debug console expressions')
+        target = frame.GetThread().GetProcess().GetTarget()
+        if receiver is not None:
+            receiver = _kdap_se_place(receiver)
+            receiver_type = _kdap_call_type_name(receiver.GetType())
+            path = _kdap_call_base_name(receiver_type) + '::' + name
+            args = [receiver] + list(args)
+        else:
+            receiver_type, path = None, name
+        full_name, address, function = _kdap_call_find(target, path, receiver_type)
+        params = function.GetType().GetFunctionArgumentTypes() if function else None
+        if params is not None and params.GetSize() != len(args):
+            raise TypeError("'%s' takes %d argument(s) but %d were given" % (path, params.GetSize(), len(args)))
+        values = [_kdap_call_arg(a, params.GetTypeAtIndex(i) if params is not None else None) for i, a in enumerate(args)]
+        raw = _kdap_call_run(frame.GetThread(), address, values, path, timeout)
+        return _kdap_call_result(target, function, raw)
+""".trimIndent()
@@ -0,0 +1,221 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.HeapRequest
+import com.github.jomof.dap.sb.SBDebugger
+import com.github.jomof.dap.sb.SBError
+import com.github.jomof.dap.sb.SBTarget
+import com.github.jomof.dap.sb.pyStr
+import org.json.JSONObject
+import java.util.logging.Logger
+
+/**
+ * Heap allocation tracking (`"trackAllocations": true` on launch or
+ * attach). KDAP extension; CodeLLDB has no counterpart.
+ *
+ * KDAP breaks on the C allocator's functions and on the Rust global
+ * allocator's shims ([HEAP_FUNCTIONS]), so allocations made through a
+ * Rust `#[global_allocator]` that does not use `malloc`, such as
+ * jemalloc, are tracked as well. On entry to an allocating call the
+ * breakpoint callback records the requested size and the caller's
+ * backtrace (up to [HEAP_BACKTRACE_DEPTH] return addresses) for the
+ * thread, and breaks on the call's return address (one breakpoint per
+ * call site, kept); on return it reads the new block's address from the
+ * return register and keeps it as a live allocation. `free`,
+ * `__rust_dealloc`, and `realloc` forget the block they are given. A
+ * block seen by both `__rust_alloc` and the `malloc` under it keeps the
+ * outer call's backtrace. All callbacks return `False`, so the debuggee
+ * never reports a stop for them.
+ *
+ * The custom `kdap/heap` request ([handleHeap]) answers "what allocated
+ * this address?": with an `address` (a number, or a `"0x..."` string
+ * such as a `memoryReference`) it returns the live allocation that
+ * contains it, with its size, thread, allocating function, and
+ * symbolicated backtrace; without one, the [DEFAULT_HEAP_RESULTS] (or
+ * `limit`) most recent allocations. Every response has the total `count`
+ * and `bytes` of live allocations.
+ *
+ * Each allocation stops the debuggee twice in LLDB, so a program runs
+ * many times slower with tracking on; it is meant for a session spent
+ * finding a buffer's owner, not for everyday debugging.
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.HeapTracking")
+
+/** Allocator functions [initHeapTracking] breaks on. */
+internal val HEAP_FUNCTIONS = listOf(
+    "malloc", "calloc", "realloc", "free", "aligned_alloc", "memalign",
+    "__rust_alloc", "__rust_alloc_zeroed", "__rust_realloc", "__rust_dealloc",
+)
+
+/** Return addresses recorded for each allocation. */
+internal const val HEAP_BACKTRACE_DEPTH = 16
+
+/** Allocations `kdap/heap` returns when no `address` or `limit` is given. */
+internal const val DEFAULT_HEAP_RESULTS = 100
+
+/**
+ * Installs the [PY_HEAP_TRACKING] callbacks and breaks on each of
+ * [HEAP_FUNCTIONS] in [target].
+ */
+internal suspend fun DebugSession.initHeapTracking(debugger: SBDebugger, target: SBTarget) {
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_HEAP_TRACKING)})")
+    for (function in HEAP_FUNCTIONS) {
+        val bp = target.breakpointCreateByName(function)
+        bp.setScriptCallbackBody("return _kdap_heap_entry(frame, ${pyStr(function)})")
+    }
+    trackAllocations = true
+}
+
+/** Forgets the allocations of the previous process, for a restart. */
+internal suspend fun resetHeapTracking(debugger: SBDebugger) {
+    debugger.commandInterpreter().handleCommand("script _kdap_heap_reset()")
+}
+
+/**
+ * The address a `kdap/heap` request asks about: [text] in hex with a
+ * `0x` prefix, or in decimal. Throws [SBError] if it is neither.
+ */
+internal fun parseHeapAddress(text: String): Long {
+    val trimmed = text.trim()
+    val value = if (trimmed.startsWith("0x") || trimmed.startsWith("0X")) {
+        trimmed.substring(2).toULongOrNull(16)
+    } else {
+        trimmed.toULongOrNull()
+    }
+    return value?.toLong() ?: throw SBError("\"address\" must be a number or a \"0x\" hex address: $text")
+}
+
+/** Handles `kdap/heap` (see the file header). */
+suspend fun DebugSession.handleHeap(rawJson: String, ctx: AsyncRequestContext) {
+    val requestSeq = JSONObject(rawJson).optInt("seq", 0)
+    try {
+        if (!trackAllocations) {
+            throw SBError("Allocations are not tracked; set \"trackAllocations\": true in the launch configuration.")
+        }
+        val request = DapMessage.parse(rawJson) as HeapRequest
+        val address = request.address?.let { parseHeapAddress(it) }
+        val limit = (request.limit ?: DEFAULT_HEAP_RESULTS).coerceAtLeast(1)
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val output = debugger.commandInterpreter().handleCommand(
+            "script print(_kdap_heap_query(${address?.toULong() ?: "None"}, $limit))").trim()
+        sendSuccessResponse(ctx, requestSeq, "kdap/heap", DapMessage.jsonObjectToMap(JSONObject(output)))
+    } catch (e: Exception) {
+        log.warning { "HeapTracking: kdap/heap failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "kdap/heap", e.message ?: "Reading allocations failed")
+    }
+}
+
+/**
+ * Breakpoint callbacks for [initHeapTracking] and the query behind
+ * [handleHeap]. `_kdap_heap_entry` runs on entry to an allocator
+ * function, named by its breakpoint (glibc's `malloc` is
+ * `__libc_malloc` in its debug info); a call that returns a block is pushed on its thread's
+ * pending calls, and `_kdap_heap_return` pops it at the return address.
+ * Live allocations are kept by address, in allocation order, until
+ * `_kdap_heap_reset` forgets them when the process is restarted.
+ */
+private val PY_HEAP_TRACKING = """
+    import json
+
+    _kdap_heap = {'live': {}, 'pending': {}, 'returns': {}, 'seq': 0}
+    _KDAP_RETURN_REGISTERS = {
+        'x86_64': 'rax', 'i386': 'eax', 'i686': 'eax',
+        'aarch64': 'x0', 'arm64': 'x0', 'arm64e': 'x0', 'arm': 'r0',
+    }
+
+    def _kdap_heap_reset():
+        _kdap_heap['live'].clear()
+        _kdap_heap['pending'].clear()
+
+    def _kdap_heap_arg(frame, n):
+        return frame.FindRegister('arg%d' % n).GetValueAsUnsigned()
+
+    def _kdap_heap_entry(frame, name):
+        thread = frame.GetThread()
+        live = _kdap_heap['live']
+        if name in ('free', '__rust_dealloc'):
+            live.pop(_kdap_heap_arg(frame, 1), None)
+            return False
+        if name in ('malloc', '__rust_alloc', '__rust_alloc_zeroed'):
+            size = _kdap_heap_arg(frame, 1)
+        elif name == 'calloc':
+            size = _kdap_heap_arg(frame, 1) * _kdap_heap_arg(frame, 2)
+        elif name in ('aligned_alloc', 'memalign'):
+            size = _kdap_heap_arg(frame, 2)
+        elif name == 'realloc':
+            live.pop(_kdap_heap_arg(frame, 1), None)
+            size = _kdap_heap_arg(frame, 2)
+        elif name == '__rust_realloc':
+            live.pop(_kdap_heap_arg(frame, 1), None)
+            size = _kdap_heap_arg(frame, 4)
+        else:
+            return False
+        caller = thread.GetFrameAtIndex(1)
+        if not caller.IsValid():
+            return False
+        return_pc = caller.GetPC()
+        backtrace = []
+        for i in range(1, min(thread.GetNumFrames(), $HEAP_BACKTRACE_DEPTH + 1)):
+            backtrace.append(thread.GetFrameAtIndex(i).GetPC())
+        _kdap_heap['pending'].setdefault(thread.GetThreadID(), []).append((return_pc, name, size, backtrace))
+        if return_pc not in _kdap_heap['returns']:
+            bp = thread.GetProcess().GetTarget().BreakpointCreateByAddress(return_pc)
+            bp.SetScriptCallbackFunction('_kdap_heap_return')
+            _kdap_heap['returns'][return_pc] = bp.GetID()
+        return False
+
+    def _kdap_heap_return(frame, bp_loc, internal_dict):
+        thread = frame.GetThread()
+        pending = _kdap_heap['pending'].get(thread.GetThreadID())
+        pc = frame.GetPC()
+        if not pending or pending[-1][0] != pc:
+            return False
+        _, name, size, backtrace = pending.pop()
+        target = thread.GetProcess().GetTarget()
+        register = _KDAP_RETURN_REGISTERS.get(target.GetTriple().split('-')[0])
+        if register is None:
+            return False
+        address = frame.FindRegister(register).GetValueAsUnsigned()
+        if address == 0:
+            return False
+        _kdap_heap['seq'] += 1
+        live = _kdap_heap['live']
+        live.pop(address, None)
+        live[address] = {'size': size, 'thread': thread.GetThreadID(), 'function': name,
+                         'backtrace': backtrace, 'seq': _kdap_heap['seq']}
+        return False
+
+    def _kdap_heap_frame(target, pc):
+        # Return addresses follow the call; look up the call instruction.
+        address = target.ResolveLoadAddress(pc - 1)
+        context = address.GetSymbolContext(lldb.eSymbolContextEverything)
+        function = context.GetFunction()
+        name = function.GetDisplayName() if function.IsValid() else context.GetSymbol().GetName()
+        record = {'address': '0x%x' % pc, 'function': name or '???'}
+        entry = context.GetLineEntry()
+        if entry.IsValid() and entry.GetFileSpec().fullpath:
+            record.update(file=entry.GetFileSpec().fullpath, line=entry.GetLine(), column=entry.GetColumn())
+        return record
+
+    def _kdap_heap_record(target, address, allocation):
+        return {'address': '0x%x' % address, 'memoryReference': '0x%x' % address,
+                'size': allocation['size'], 'thread': allocation['thread'], 'function': allocation['function'],
+                'backtrace': [_kdap_heap_frame(target, pc) for pc in allocation['backtrace']]}
+
+    def _kdap_heap_query(address, limit):
+        target = lldb.debugger.GetSelectedTarget()
+        live = _kdap_heap['live']
+        if address is None:
+            chosen = list(live.items())[-limit:][::-1]
+        else:
+            chosen = [(start, allocation) for start, allocation in live.items()
+                      if start <= address < start + max(allocation['size'], 1)]
+        return json.dumps({
+            'count': len(live),
+            'bytes': sum(allocation['size'] for allocation in live.values()),
+            'allocations': [_kdap_heap_record(target, start, allocation) for start, allocation in chosen],
+        })
+""".trimIndent()
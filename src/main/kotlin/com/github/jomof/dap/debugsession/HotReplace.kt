@@ -0,0 +1,256 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.HotReplaceRequest
+import com.github.jomof.dap.sb.SBDebugger
+import com.github.jomof.dap.sb.SBError
+import com.github.jomof.dap.sb.pyStr
+import kotlinx.coroutines.Dispatchers
+import kotlinx.coroutines.runInterruptible
+import org.json.JSONArray
+import org.json.JSONObject
+import java.io.File
+import java.nio.file.Files
+import java.nio.file.StandardCopyOption
+import java.util.logging.Logger
+
+/**
+ * Hot replace: experimental edit and continue for whole functions. KDAP
+ * extension; neither CodeLLDB nor lldb-dap can change a running
+ * program's code.
+ *
+ * The custom `kdap/hotReplace` request ([handleHotReplace]) takes a
+ * rebuilt object file and, while the debuggee is stopped:
+ *
+ * 1. Links it into a shared library (`$CC -shared`, `cc` by default;
+ *    [linkCommand]), or copies a shared library, to a new temporary
+ *    file, since `dlopen` returns the same handle for a path it has
+ *    already loaded.
+ * 2. Loads the library into the debuggee (`SBProcess::LoadImage`). LLDB
+ *    reads its debug info and binds source breakpoints in it, so they
+ *    move to the new code.
+ * 3. Overwrites the entry of each old function the library also defines
+ *    (exported code symbols with the same mangled name, or those named
+ *    in `functions`) with an absolute jump to the new one
+ *    ([jumpPatch]): `movabs rax, <new>; jmp rax` on x86-64,
+ *    `ldr x16, #8; br x16; .quad <new>` on AArch64.
+ * 4. Disables the breakpoint locations left in the old functions.
+ *
+ * and responds with what it did:
+ *
+ * ```json
+ * {"replaced": [{"name": "shade", "address": "0x401136", "replacement": "0x7ffff7fb9109"}],
+ *  "skipped": [{"name": "tiny", "reason": "is 4 bytes, too small for a 12-byte jump"}],
+ *  "disabledBreakpointLocations": 1}
+ * ```
+ *
+ * It is meant for the edit-continue loop on leaf functions. Frames
+ * already in an old function finish in the old code; calls inlined into
+ * other functions are not replaced; the new code has its own copies of
+ * the object's static data; and it can only call functions and use
+ * globals the debuggee exports (an executable needs `-rdynamic`).
+ * Objects must be position-independent (`-fPIC`).
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.HotReplace")
+
+/** A function of the debuggee ([address], [size] bytes) and its [replacement] in the loaded library. */
+internal data class HotFunction(val name: String, val address: Long, val size: Long, val replacement: Long)
+
+/** The plan for a `kdap/hotReplace`: the jumps to write and the functions skipped, with why. */
+internal data class HotReplacePlan(val patches: List<HotPatch>, val skipped: Map<String, String>)
+
+/** A jump ([bytes]) to write at the entry of the function at [address], [size] bytes long. */
+internal class HotPatch(val name: String, val address: Long, val size: Long, val replacement: Long, val bytes: ByteArray)
+
+/**
+ * The instructions that jump from anywhere to [target] on the
+ * architecture of [triple], or `null` if KDAP cannot patch it.
+ */
+internal fun jumpPatch(triple: String, target: Long): ByteArray? {
+    val address = ByteArray(8) { (target ushr (8 * it)).toByte() }
+    return when (normalizedArchitecture(triple)) {
+        // movabs rax, imm64; jmp rax
+        "x86_64" -> byteArrayOf(0x48, 0xb8.toByte()) + address + byteArrayOf(0xff.toByte(), 0xe0.toByte())
+        // ldr x16, #8; br x16; .quad imm64
+        "aarch64" -> byteArrayOf(0x50, 0x00, 0x00, 0x58, 0x00, 0x02, 0x1f, 0xd6.toByte()) + address
+        else -> null
+    }
+}
+
+/** Which of [functions] to patch on [triple], and why the others are skipped. */
+internal fun planHotReplace(functions: List<HotFunction>, triple: String): HotReplacePlan {
+    val patches = mutableListOf<HotPatch>()
+    val skipped = linkedMapOf<String, String>()
+    for (function in functions) {
+        val bytes = jumpPatch(triple, function.replacement)
+        when {
+            bytes == null -> skipped[function.name] = "cannot be patched on ${normalizedArchitecture(triple).ifEmpty { triple }}"
+            function.size < bytes.size ->
+                skipped[function.name] = "is ${function.size} bytes, too small for a ${bytes.size}-byte jump"
+            else -> patches += HotPatch(function.name, function.address, function.size, function.replacement, bytes)
+        }
+    }
+    return HotReplacePlan(patches, skipped)
+}
+
+/** Whether [path] is an object file to link, rather than a shared library to load as is. */
+internal fun isObjectFile(path: String): Boolean = path.endsWith(".o") || path.endsWith(".obj")
+
+/** The command that links [objectFile] into the shared library [output] with [cc]. */
+internal fun linkCommand(cc: String, objectFile: String, output: String): List<String> =
+    listOf(cc, "-shared", "-o", output, objectFile)
+
+/** Links or copies [objectFile] to a new temporary shared library (see the file header). */
+private suspend fun hotReplaceLibrary(objectFile: File): File = runInterruptible(Dispatchers.IO) {
+    val library = Files.createTempFile("kdap-hot-", ".so").toFile().apply { deleteOnExit() }
+    if (!isObjectFile(objectFile.path)) {
+        Files.copy(objectFile.toPath(), library.toPath(), StandardCopyOption.REPLACE_EXISTING)
+        return@runInterruptible library
+    }
+    val cc = System.getenv("CC")?.ifEmpty { null } ?: "cc"
+    val process = ProcessBuilder(linkCommand(cc, objectFile.path, library.path)).redirectErrorStream(true).start()
+    process.outputStream.close()
+    val output = process.inputStream.readBytes().toString(Charsets.UTF_8)
+    if (process.waitFor() != 0) throw SBError("Linking ${objectFile.name} failed: ${output.trim()}")
+    library
+}
+
+/** Handles `kdap/hotReplace` (see the file header). */
+suspend fun DebugSession.handleHotReplace(rawJson: String, ctx: AsyncRequestContext) {
+    val requestSeq = JSONObject(rawJson).optInt("seq", 0)
+    try {
+        val request = DapMessage.parse(rawJson) as HotReplaceRequest
+        val objectFile = File(request.objectFile)
+        if (!objectFile.isFile) throw SBError("\"objectFile\" ${request.objectFile} does not exist.")
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val library = hotReplaceLibrary(objectFile)
+        val (triple, functions) = loadHotReplacement(library, request.functions, debugger)
+        val plan = planHotReplace(functions, triple)
+        val skipped = LinkedHashMap(plan.skipped)
+        for (name in request.functions.orEmpty()) {
+            if (functions.none { it.name == name } && name !in skipped) {
+                skipped[name] = "is not defined by both ${objectFile.name} and the debuggee"
+            }
+        }
+        val result = writeHotPatches(plan.patches, debugger)
+        val failed = result.optJSONObject("failed") ?: JSONObject()
+        for (name in failed.keySet()) skipped[name] = "could not be written: ${failed.getString(name)}"
+        val replaced = plan.patches.filter { !failed.has(it.name) }
+        consoleMessage("Replaced ${replaced.size} function(s) from ${objectFile.name}" +
+            if (skipped.isEmpty()) "" else "; skipped ${skipped.keys.joinToString(", ")}", ctx)
+        sendSuccessResponse(ctx, requestSeq, "kdap/hotReplace", mapOf(
+            "replaced" to replaced.map {
+                mapOf("name" to it.name, "address" to "0x%x".format(it.address), "replacement" to "0x%x".format(it.replacement))
+            },
+            "skipped" to skipped.map { (name, reason) -> mapOf("name" to name, "reason" to reason) },
+            "disabledBreakpointLocations" to result.optInt("disabled"),
+        ))
+    } catch (e: Exception) {
+        log.warning { "HotReplace: kdap/hotReplace failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "kdap/hotReplace", e.message ?: "Replacing functions failed")
+    }
+}
+
+/**
+ * Loads [library] into the debuggee and returns the target triple and
+ * the debuggee's functions it replaces, limited to [names] if given.
+ */
+private suspend fun loadHotReplacement(
+    library: File,
+    names: List<String>?,
+    debugger: SBDebugger,
+): Pair<String, List<HotFunction>> {
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_HOT_REPLACE)})")
+    val namesJson = names?.let { JSONArray(it).toString() } ?: "null"
+    val result = JSONObject(interpreter.handleCommand(
+        "script print(_kdap_hot_load(${pyStr(library.path)}, ${pyStr(namesJson)}))").trim())
+    result.optString("error", null)?.let { throw SBError(it) }
+    val functions = result.getJSONArray("functions")
+    return result.getString("triple") to (0 until functions.length()).map { i ->
+        val f = functions.getJSONObject(i)
+        HotFunction(f.getString("name"), f.getLong("address"), f.getLong("size"), f.getLong("replacement"))
+    }
+}
+
+/** Writes [patches] and disables the breakpoint locations in the patched functions. */
+private suspend fun writeHotPatches(patches: List<HotPatch>, debugger: SBDebugger): JSONObject {
+    val json = JSONArray(patches.map { patch ->
+        JSONObject().put("name", patch.name).put("address", patch.address).put("size", patch.size)
+            .put("bytes", patch.bytes.joinToString("") { "%02x".format(it) })
+    }).toString()
+    return JSONObject(debugger.commandInterpreter().handleCommand(
+        "script print(_kdap_hot_patch(${pyStr(json)}))").trim())
+}
+
+/**
+ * Python side of [handleHotReplace]: `_kdap_hot_load` loads the library
+ * and pairs its exported functions with the debuggee's;
+ * `_kdap_hot_patch` writes the jumps and disables the breakpoint
+ * locations they cover.
+ */
+private val PY_HOT_REPLACE = """
+    import json
+
+    def _kdap_hot_load(path, names):
+        names = json.loads(names)
+        target = lldb.debugger.GetSelectedTarget()
+        process = target.GetProcess()
+        if not process.IsValid() or process.GetState() != lldb.eStateStopped:
+            return json.dumps({'error': 'The debuggee must be stopped to replace functions.'})
+        error = lldb.SBError()
+        token = process.LoadImage(lldb.SBFileSpec(path, False), error)
+        if error.Fail() or token == lldb.LLDB_INVALID_IMAGE_TOKEN:
+            return json.dumps({'error': 'Could not load the rebuilt code: %s' % error.GetCString()})
+        new = target.FindModule(lldb.SBFileSpec(path, False))
+        if not new.IsValid():
+            return json.dumps({'error': 'LLDB has no module for the rebuilt code.'})
+        wanted = set(names) if names is not None else None
+        functions = []
+        for i in range(new.GetNumSymbols()):
+            symbol = new.GetSymbolAtIndex(i)
+            if symbol.GetType() != lldb.eSymbolTypeCode or not symbol.IsExternal():
+                continue
+            mangled = symbol.GetMangledName() or symbol.GetName()
+            name = symbol.GetName()
+            if wanted is not None and mangled not in wanted and name not in wanted:
+                continue
+            for module in target.module_iter():
+                if module.GetFileSpec().fullpath == new.GetFileSpec().fullpath:
+                    continue
+                old = module.FindSymbol(mangled, lldb.eSymbolTypeCode)
+                if not old.IsValid():
+                    continue
+                start = old.GetStartAddress()
+                functions.append({
+                    'name': name,
+                    'address': start.GetLoadAddress(target),
+                    'size': old.GetEndAddress().GetFileAddress() - start.GetFileAddress(),
+                    'replacement': symbol.GetStartAddress().GetLoadAddress(target),
+                })
+                break
+        return json.dumps({'triple': target.GetTriple() or '', 'functions': functions})
+
+    def _kdap_hot_patch(patches):
+        target = lldb.debugger.GetSelectedTarget()
+        process = target.GetProcess()
+        failed = {}
+        ranges = []
+        for patch in json.loads(patches):
+            error = lldb.SBError()
+            process.WriteMemory(patch['address'], bytes.fromhex(patch['bytes']), error)
+            if error.Fail():
+                failed[patch['name']] = error.GetCString() or 'write failed'
+            else:
+                ranges.append((patch['address'], patch['address'] + patch['size']))
+        disabled = 0
+        for bp in target.breakpoint_iter():
+            for loc in bp:
+                address = loc.GetLoadAddress()
+                if loc.IsEnabled() and any(lo <= address < hi for lo, hi in ranges):
+                    loc.SetEnabled(False)
+                    disabled += 1
+        return json.dumps({'failed': failed, 'disabled': disabled})
+""".trimIndent()
@@ -0,0 +1,325 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.sb.SBDebugger
+import com.github.jomof.dap.sb.pyStr
+
+/**
+ * Kotlin/Native support: symbol demangling and value formatters.
+ *
+ * ## Symbols
+ *
+ * Kotlin/Native names functions by their Kotlin signature, which LLDB
+ * reports verbatim as the frame's function name:
+ *
+ * | Symbol                                                       | Shown as                       |
+ * |--------------------------------------------------------------|--------------------------------|
+ * | `kfun:#main(kotlin.Array<kotlin.String>){}`                  | `main(Array<String>)`          |
+ * | `kfun:kdap.debuggee.Circle#area(){}kotlin.Double`            | `kdap.debuggee.Circle.area()`  |
+ * | `kfun:kdap.debuggee#add2(kotlin.Int;kotlin.Int){}kotlin.Int` | `kdap.debuggee.add2(Int, Int)` |
+ * | `kfun:kdap.debuggee#foo@kotlin.String(){}`                   | `kdap.debuggee.foo@String()`   |
+ *
+ * [demangleKotlinName] drops the prefix, the type-parameter bounds
+ * (`{…}`), and the return type, joins the owner and function name with
+ * `.`, and shortens `kotlin.`-package types in the parameter list.
+ * `stackTrace` frames are demangled before their runtime internals are
+ * picked out (StackFrames.kt), so `kotlin.` standard library functions,
+ * bridges (`$<bridge-…>`), and the runtime's start-up and exception
+ * code (`Konan_`, `Kotlin_`, `kotlin::`) are internal frames.
+ *
+ * ## Values
+ *
+ * Every Kotlin reference is an `ObjHeader *` in DWARF, so LLDB shows an
+ * address. KDAP installs a summary and a synthetic provider for it in
+ * the [KOTLIN_CATEGORY] category, which read the object through the
+ * runtime's debugger API (`Konan_DebugIsInstance`,
+ * `Konan_DebugGetFieldCount`, `Konan_DebugObjectToUtf8Array`, …) as
+ * Kotlin's own LLDB script does:
+ *
+ * | Value                        | Summary               | Children              |
+ * |------------------------------|-----------------------|-----------------------|
+ * | `null` (any nullable type)   | `null`                | none                  |
+ * | `String`                     | `"text"`              | none                  |
+ * | arrays (`IntArray`, `Array`) | `(3) [1, 2, 3]`       | `[0]`, `[1]`, …       |
+ * | `ArrayList` (`listOf`, …)    | `(3) [1, 2, 3]`       | `[0]`, `[1]`, …       |
+ * | boxed primitive (`Int?`)     | the value, `5`        | none                  |
+ * | other objects                | —                     | the object's fields   |
+ *
+ * The runtime functions are called as expressions in the value's frame,
+ * so a program without Kotlin/Native debug info is never affected: its
+ * values are not `ObjHeader *`.
+ */
+
+/** LLDB type category holding KDAP's Kotlin/Native formatters. */
+internal const val KOTLIN_CATEGORY = "kdap-kotlin"
+
+/** Prefix of Kotlin/Native function symbols. */
+internal const val KOTLIN_FUNCTION_PREFIX = "kfun:"
+
+/** A fully qualified type from the `kotlin` package, e.g. `kotlin.Int` but not `kotlin.collections.List`. */
+private val KOTLIN_BUILTIN_TYPE = Regex("""(?<![\w.])kotlin\.(?=[A-Z])""")
+
+/**
+ * [name] demangled if it is a Kotlin/Native function symbol (see the
+ * file header), otherwise [name] unchanged.
+ */
+internal fun demangleKotlinName(name: String): String {
+    if (!name.startsWith(KOTLIN_FUNCTION_PREFIX)) return name
+    val symbol = name.substring(KOTLIN_FUNCTION_PREFIX.length)
+    val open = symbol.indexOf('(')
+    val close = if (open < 0) -1 else closingParen(symbol, open)
+    if (close < 0) return name
+    val head = symbol.substring(0, open)
+    val hash = head.lastIndexOf('#')
+    val owner = if (hash < 0) "" else head.substring(0, hash)
+    val function = head.substring(hash + 1)
+    val params = splitTopLevel(symbol.substring(open + 1, close), ';')
+        .joinToString(", ") { KOTLIN_BUILTIN_TYPE.replace(it, "") }
+    val receiver = function.substringAfter('@', "").let { if (it.isEmpty()) "" else "@" + KOTLIN_BUILTIN_TYPE.replace(it, "") }
+    val qualified = if (owner.isEmpty()) function.substringBefore('@') else "$owner.${function.substringBefore('@')}"
+    return "$qualified$receiver($params)"
+}
+
+/** `stackTrace` [frames] with their Kotlin/Native function names demangled. */
+internal fun demangleKotlinFrames(frames: List<*>): List<Any?> = frames.map { frame ->
+    val map = frame as? Map<*, *> ?: return@map frame
+    val name = map["name"] as? String ?: return@map frame
+    if (!name.startsWith(KOTLIN_FUNCTION_PREFIX)) frame else map + ("name" to demangleKotlinName(name))
+}
+
+/** Index of the `)` closing the `(` at [open] in [text], or -1. */
+private fun closingParen(text: String, open: Int): Int {
+    var depth = 0
+    for (i in open until text.length) {
+        when (text[i]) {
+            '(' -> depth++
+            ')' -> if (--depth == 0) return i
+        }
+    }
+    return -1
+}
+
+/** [text] split at each [separator] outside `<…>` and `(…)`; empty for empty [text]. */
+private fun splitTopLevel(text: String, separator: Char): List<String> {
+    if (text.isEmpty()) return emptyList()
+    val parts = mutableListOf<String>()
+    var depth = 0
+    var start = 0
+    for ((i, ch) in text.withIndex()) {
+        when (ch) {
+            '<', '(' -> depth++
+            '>', ')' -> depth--
+            separator -> if (depth == 0) {
+                parts += text.substring(start, i)
+                start = i + 1
+            }
+        }
+    }
+    parts += text.substring(start)
+    return parts
+}
+
+/**
+ * LLDB commands that register the Kotlin/Native summary and provider in
+ * [KOTLIN_CATEGORY], by a recognizer that matches `ObjHeader *`, and
+ * enable it.
+ */
+internal fun kotlinFormatterCommands(): List<String> = listOf(
+    "type summary add -w $KOTLIN_CATEGORY -F _kdap_kt_summary --recognizer-function _kdap_kt_is_object",
+    "type synthetic add -w $KOTLIN_CATEGORY -l _KdapKotlinProvider --recognizer-function _kdap_kt_is_object",
+    "type category enable $KOTLIN_CATEGORY",
+)
+
+/**
+ * Defines the Python summary and provider in LLDB's script interpreter
+ * and registers them for Kotlin/Native objects.
+ */
+internal suspend fun initKotlinFormatters(debugger: SBDebugger) {
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_KOTLIN_FORMATTERS)})")
+    for (command in kotlinFormatterCommands()) {
+        interpreter.handleCommand(command)
+    }
+}
+
+/**
+ * Summary and synthetic provider for Kotlin/Native objects. Field types
+ * are the runtime's `Konan_RuntimeType` codes; type infos are found by
+ * their `ktypeglobal:` symbols.
+ */
+private val PY_KOTLIN_FORMATTERS = """
+    _KT_STRING = 'ktypeglobal:kotlin.String#internal'
+    _KT_ARRAY_LIST = 'ktypeglobal:kotlin.collections.ArrayList#internal'
+    _KT_MAX_SUMMARY_ITEMS = 8
+    _KT_BASIC_TYPES = {
+        2: lldb.eBasicTypeSignedChar, 3: lldb.eBasicTypeShort, 4: lldb.eBasicTypeInt,
+        5: lldb.eBasicTypeLongLong, 6: lldb.eBasicTypeFloat, 7: lldb.eBasicTypeDouble,
+        9: lldb.eBasicTypeBool,
+    }
+    _KT_OBJECT, _KT_NATIVE_PTR = 1, 8
+
+    def _kdap_kt_is_object(sbtype, internal_dict):
+        if not sbtype.IsPointerType():
+            return False
+        return (sbtype.GetPointeeType().GetUnqualifiedType().GetName() or '') in ('ObjHeader', 'struct ObjHeader')
+
+    def _kdap_kt_eval(valobj, expr):
+        options = lldb.SBExpressionOptions()
+        options.SetIgnoreBreakpoints(True)
+        options.SetTryAllThreads(False)
+        options.SetTimeoutInMicroSeconds(500000)
+        frame = valobj.GetFrame()
+        if frame.IsValid():
+            return frame.EvaluateExpression(expr, options)
+        return valobj.GetTarget().EvaluateExpression(expr, options)
+
+    def _kdap_kt_int(valobj, expr):
+        result = _kdap_kt_eval(valobj, expr)
+        return result.GetValueAsSigned() if result.GetError().Success() else 0
+
+    def _kdap_kt_symbol(target, name):
+        for context in target.FindSymbols(name):
+            return context.GetSymbol().GetStartAddress().GetLoadAddress(target)
+        return 0
+
+    def _kdap_kt_instance(valobj, type_symbol):
+        type_info = _kdap_kt_symbol(valobj.GetTarget(), type_symbol)
+        if type_info in (0, lldb.LLDB_INVALID_ADDRESS):
+            return False
+        return _kdap_kt_int(valobj, '(int)Konan_DebugIsInstance((void *)%#x, (void *)%#x)'
+                            % (valobj.GetValueAsUnsigned(), type_info)) != 0
+
+    def _kdap_kt_kind(valobj):
+        address = valobj.GetValueAsUnsigned()
+        if address == 0:
+            return 'null'
+        if _kdap_kt_instance(valobj, _KT_STRING):
+            return 'string'
+        if _kdap_kt_int(valobj, '(int)Konan_DebugIsArray((void *)%#x)' % address):
+            return 'array'
+        if _kdap_kt_instance(valobj, _KT_ARRAY_LIST):
+            return 'list'
+        return 'object'
+
+    def _kdap_kt_string(valobj):
+        address = valobj.GetValueAsUnsigned()
+        _kdap_kt_eval(valobj, '(int)Konan_DebugObjectToUtf8Array((void *)%#x, (char *)Konan_DebugBuffer(), '
+                              '(int)Konan_DebugBufferSize())' % address)
+        buffer = _kdap_kt_eval(valobj, '(char *)Konan_DebugBuffer()').GetValueAsUnsigned()
+        size = _kdap_kt_int(valobj, '(int)Konan_DebugBufferSize()')
+        if buffer == 0 or size <= 0:
+            return None
+        e = lldb.SBError()
+        text = valobj.GetProcess().ReadCStringFromMemory(buffer, size, e)
+        if not e.Success():
+            return None
+        if isinstance(text, bytes):
+            text = text.decode('utf-8', 'replace')
+        return '"%s"' % text.replace('\\', '\\\\').replace('"', '\\"')
+
+    def _kdap_kt_fields(valobj):
+        address = valobj.GetValueAsUnsigned()
+        count = _kdap_kt_int(valobj, '(int)Konan_DebugGetFieldCount((void *)%#x)' % address)
+        fields = []
+        for i in range(count):
+            name = _kdap_kt_eval(valobj, '(const char *)Konan_DebugGetFieldName((void *)%#x, %d)' % (address, i))
+            fields.append(((name.GetSummary() or '').strip('"') or '[%d]' % i, i))
+        return fields
+
+    def _kdap_kt_field(valobj, name, index):
+        address = valobj.GetValueAsUnsigned()
+        code = _kdap_kt_int(valobj, '(int)Konan_DebugGetFieldType((void *)%#x, %d)' % (address, index))
+        field = _kdap_kt_eval(valobj, '(void *)Konan_DebugGetFieldAddress((void *)%#x, %d)' % (address, index))
+        field_address = field.GetValueAsUnsigned()
+        target = valobj.GetTarget()
+        if code == _KT_OBJECT:
+            sbtype = valobj.GetType()
+        elif code == _KT_NATIVE_PTR:
+            sbtype = target.GetBasicType(lldb.eBasicTypeVoid).GetPointerType()
+        elif code in _KT_BASIC_TYPES:
+            sbtype = target.GetBasicType(_KT_BASIC_TYPES[code])
+        else:
+            return None
+        return valobj.CreateValueFromAddress(name, field_address, sbtype)
+
+    def _kdap_kt_field_named(valobj, name):
+        for field_name, index in _kdap_kt_fields(valobj):
+            if field_name == name:
+                return _kdap_kt_field(valobj, name, index)
+        return None
+
+    def _kdap_kt_list_layout(valobj):
+        backing = _kdap_kt_field_named(valobj, 'backing')
+        if backing is None or _kdap_kt_kind(backing) != 'array':
+            backing = _kdap_kt_field_named(valobj, 'array')
+        offset = _kdap_kt_field_named(valobj, 'offset')
+        length = _kdap_kt_field_named(valobj, 'length')
+        if backing is None or length is None:
+            return None, 0, 0
+        return backing, offset.GetValueAsSigned() if offset is not None else 0, length.GetValueAsSigned()
+
+    def _kdap_kt_display(v):
+        return v.GetSummary() or v.GetValue() or '{...}'
+
+    def _kdap_kt_summary(valobj, internal_dict):
+        kind = _kdap_kt_kind(valobj.GetNonSyntheticValue())
+        if kind == 'null':
+            return 'null'
+        if kind == 'string':
+            return _kdap_kt_string(valobj.GetNonSyntheticValue())
+        if kind in ('array', 'list'):
+            length = valobj.GetNumChildren()
+            count = min(length, _KT_MAX_SUMMARY_ITEMS)
+            items = [_kdap_kt_display(valobj.GetChildAtIndex(i)) for i in range(count)]
+            if length > count:
+                items.append('...')
+            return '(%d) [%s]' % (length, ', '.join(items))
+        fields = _kdap_kt_fields(valobj.GetNonSyntheticValue())
+        if len(fields) == 1 and fields[0][0] == 'value':
+            value = _kdap_kt_field(valobj.GetNonSyntheticValue(), 'value', fields[0][1])
+            if value is not None and not value.GetType().IsPointerType():
+                return _kdap_kt_display(value)
+        return None
+
+    class _KdapKotlinProvider:
+        def __init__(self, valobj, internal_dict):
+            self.valobj = valobj
+            self.update()
+        def update(self):
+            self.kind = _kdap_kt_kind(self.valobj)
+            self.fields = None
+            self.cache = {}
+            self.array, self.offset, self.length = self.valobj, 0, 0
+            if self.kind == 'array':
+                self.length = _kdap_kt_int(self.valobj, '(int)Konan_DebugGetFieldCount((void *)%#x)'
+                                           % self.valobj.GetValueAsUnsigned())
+            elif self.kind == 'list':
+                self.array, self.offset, self.length = _kdap_kt_list_layout(self.valobj)
+            elif self.kind == 'object':
+                self.fields = _kdap_kt_fields(self.valobj)
+                if len(self.fields) == 1 and self.fields[0][0] == 'value':
+                    self.fields = []
+            return False
+        def num_children(self):
+            if self.fields is not None:
+                return len(self.fields)
+            return self.length
+        def has_children(self):
+            return self.num_children() > 0
+        def get_child_index(self, name):
+            if self.fields is not None:
+                return next((i for i, (n, _) in enumerate(self.fields) if n == name), -1)
+            try:
+                return int(name.lstrip('[').rstrip(']'))
+            except ValueError:
+                return -1
+        def get_child_at_index(self, index):
+            if index < 0 or index >= self.num_children():
+                return None
+            if index not in self.cache:
+                if self.fields is not None:
+                    name, field = self.fields[index]
+                    self.cache[index] = _kdap_kt_field(self.valobj, name, field)
+                else:
+                    self.cache[index] = _kdap_kt_field(self.array, '[%d]' % index, self.offset + index)
+            return self.cache[index]
+""".trimIndent()
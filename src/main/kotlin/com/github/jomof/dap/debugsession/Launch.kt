@@ -31,14 +31,50 @@ import java.util.logging.Logger
  * | `handle_attach`        | [DebugSession.handleAttach]             |
  * | `complete_attach`      | [DebugSession.completeAttach]           |
  * | `handle_disconnect`    | [DebugSession.handleDisconnect]         |
+ * | `notify_process_stopped` | [notifyProcessStopped]                |
  * | `handle_terminate`     | [DebugSession.handleTerminate]          |
+ * | `handle_restart`       | [DebugSession.handleRestart]            |
  * | `exec_commands`        | [DebugSession.execCommands]             |
  * | `print_console_mode`   | [DebugSession.printConsoleMode]         |
+ *
+ * `"coreDump"` launches finish in [DebugSession.completeCoreDumpLaunch]
+ * instead of `complete_launch`; CodeLLDB loads cores only through
+ * `processCreateCommands`. `"record": true` launches finish in
+ * [DebugSession.completeRecordedLaunch] (see ReverseDebugging.kt). A
+ * `"program"` is checked before the process is created (see
+ * ProgramChecks.kt), and once it exists a position-independent program
+ * LLDB has at the wrong addresses is moved to where it was loaded (see
+ * LoadBias.kt).
+ *
+ * ## Ending the session
+ *
+ * `terminate` asks the debuggee to exit: with the `gracefulShutdown`
+ * signal or commands, else with `SIGTERM` (killed outright where the
+ * platform has no signals). KDAP answers right away and gives the
+ * debuggee [TERMINATE_GRACE_MS] to exit; one that is still alive then
+ * (it ignores the signal, or is stuck) is killed ([escalateTerminate]).
+ *
+ * `disconnect` kills the debuggee when `terminateDebuggee` is true, or
+ * by default for launches; otherwise it detaches, leaving the process
+ * stopped with `suspendDebuggee`. Before a detach, threads KDAP suspended
+ * are resumed and KDAP's step breakpoints removed, so the process runs on
+ * as if never debugged. A kill that lldb-dap doesn't finish within
+ * [KILL_TIMEOUT_MS] falls back to killing a local debuggee by its pid, so
+ * `disconnect` always gets its response and `terminated` event.
  */
 
 private val log = Logger.getLogger(DebugSession::class.java.name)
 private const val TCP_ACCEPT_TIMEOUT_MS = 30_000
 
+/** Time the debuggee has to exit after `terminate` asked it to, before it is killed. */
+internal const val TERMINATE_GRACE_MS = 5_000L
+
+/** Time lldb-dap has to kill the debuggee before KDAP kills it by pid. */
+internal const val KILL_TIMEOUT_MS = 3_000L
+
+/** Interval at which [escalateTerminate] checks whether the debuggee exited. */
+private const val EXIT_POLL_MS = 100L
+
 /** Console mode announcement matching CodeLLDB's `print_console_mode`. */
 const val CONSOLE_MODE_MESSAGE =
     "Console is in 'commands' mode, prefix expressions with '?'."
@@ -51,6 +87,7 @@ const val CONSOLE_MODE_MESSAGE =
  */
 fun DebugSession.onInitialize(request: InitializeRequest) {
     clientSupportsRunInTerminal = request.supportsRunInTerminalRequest
+    clientSupportsProgressReporting = request.supportsProgressReporting
 }
 
 // ── handle_launch (launch.rs:20) ─────────────────────────────────
@@ -66,9 +103,14 @@ suspend fun DebugSession.handleLaunch(rawJson: String, ctx: AsyncRequestContext)
     val obj = JSONObject(rawJson)
     val requestSeq = obj.optInt("seq", 0)
     val argsObj = obj.optJSONObject("arguments") ?: JSONObject()
-    val args = LaunchRequestArguments.fromJson(argsObj)
 
     try {
+        checkProcessConfiguration(argsObj)
+        // Configuration schema (KDAP extension; see ConfigurationSchema.kt)
+        reportConfigurationProblems("launch", argsObj, ctx)
+        // WebAssembly modules run under wasmtime (KDAP extension; see Wasm.kt)
+        val launchArgs = LaunchRequestArguments.fromJson(argsObj)
+        val args = wasmLaunchArguments(launchArgs)
         val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
 
         // common_init_session (launch.rs:21)
@@ -79,6 +121,9 @@ suspend fun DebugSession.handleLaunch(rawJson: String, ctx: AsyncRequestContext)
         if (noDebug) {
             logErrors { debugger.setVariable("target.preload-symbols", "false") }
             logErrors { debugger.setVariable("plugin.jit-loader.gdb.enable", "off") }
+        } else if (args !== launchArgs) {
+            // wasmtime registers the modules it compiles through the GDB JIT interface
+            logErrors { debugger.setVariable("plugin.jit-loader.gdb.enable", "on") }
         }
 
         // Create target (launch.rs:31-47)
@@ -87,12 +132,58 @@ suspend fun DebugSession.handleLaunch(rawJson: String, ctx: AsyncRequestContext)
             execCommands("targetCreateCommands", args.targetCreateCommands, debugger, ctx)
             val selected = debugger.selectedTarget()
             target = if (selected.isValid()) selected else debugger.createTarget()
+        } else if (args.program != null) {
+            // Program checks (KDAP extension; see ProgramChecks.kt)
+            checkProgramFile(args.program)
+            target = try {
+                createTargetFromProgram(args.program, debugger, ctx)
+            } catch (e: SBError) {
+                throw ProgramError(ProgramProblem.NotLoadable,
+                    "LLDB cannot load \"${args.program}\" as a program: ${e.message}")
+            }
+            checkProgramTarget(args, debugger)
+            // For breakpoints in sources changed since (see Breakpoints.kt)
+            programBuiltAt = java.io.File(launchArgs.program ?: args.program).lastModified().takeIf { it > 0 }
+        } else if (args.coreDump != null) {
+            // LLDB finds the executable from the core's file mappings
+            target = debugger.createTarget()
         } else {
-            val program = args.program
-                ?: throw SBError("The \"program\" attribute is required for launch.")
-            target = createTargetFromProgram(program, debugger, ctx)
+            throw SBError("The \"program\" attribute is required for launch.")
         }
 
+        // Follow child processes (KDAP extension; see ChildProcesses.kt)
+        if (args.common.followChildProcesses == true) {
+            logErrors { initChildProcessFollowing(argsObj, debugger, target) }
+        }
+
+        // List tokio tasks as threads (KDAP extension; see AsyncTasks.kt)
+        if (args.common.tokioTasks == true) {
+            logErrors { initTokioTasks(debugger) }
+            tokioTasks = true
+        }
+
+        // Track heap allocations (KDAP extension; see HeapTracking.kt)
+        if (args.common.trackAllocations == true) {
+            logErrors { initHeapTracking(debugger, target) }
+        }
+
+        // kdap.init script (KDAP extension; see Scripting.kt)
+        logErrors { loadInitScript(args.common.initScript, ctx) }
+
+        // Return values after steps (see ReturnValues.kt)
+        logErrors { initReturnValues(debugger) }
+
+        // Module events (see Modules.kt)
+        logErrors { initModuleEvents(debugger) }
+
+        // Breakpoints binding as libraries load (see PendingBreakpoints.kt)
+        logErrors { initBreakpointEvents(debugger) }
+
+        // Separate debug info (KDAP extension; see DebugInfo.kt)
+        logErrors { initSeparateDebugInfo(args.common, debugger) }
+
+        // Symbol index warm-up (KDAP extension; see SymbolIndex.kt)
+        logErrors { initSymbolIndexWarmUp(args.common.symbolIndexCache, debugger) }
 
         // Pre-register the configurationDone intercept BEFORE sending
         // initialized. The initialized event triggers the client to send
@@ -108,8 +199,22 @@ suspend fun DebugSession.handleLaunch(rawJson: String, ctx: AsyncRequestContext)
         val configDoneRawJson = ctx.awaitIntercept(configDoneToken)
         val configDoneSeq = JSONObject(configDoneRawJson).optInt("seq", 0)
 
-        // create_terminal (launch.rs:56)
-        val ttyPath = createTerminal(args, ctx)
+        // Post-mortem launch (KDAP extension): no terminal, no process to run
+        if (args.coreDump != null) {
+            completeCoreDumpLaunch(args, args.coreDump, debugger, target, requestSeq, configDoneSeq, ctx)
+            return
+        }
+
+        // Recorded launch (KDAP extension): run under rr, then debug the replay
+        if (args.record == true) {
+            completeRecordedLaunch(args, debugger, target, requestSeq, configDoneSeq, ctx)
+            launchArguments = argsObj
+            return
+        }
+
+        // create_terminal (launch.rs:56); a KDAP pty takes the terminal's place
+        val ttyPath = if (args.pty == true) openStdioPty(debugger) else createTerminal(args, ctx)
+        launchTty = ttyPath.takeUnless { args.pty == true }
 
         // Activate the event gate so that backend events (e.g., process
         // exit on fast-exiting programs) are buffered until we've sent
@@ -118,6 +223,7 @@ suspend fun DebugSession.handleLaunch(rawJson: String, ctx: AsyncRequestContext)
         try {
             // complete_launch (launch.rs:57)
             completeLaunch(args, debugger, target, ttyPath, ctx)
+            launchArguments = argsObj
 
             // Send responses and continued event in CodeLLDB order:
             // 1. launch response
@@ -133,7 +239,12 @@ suspend fun DebugSession.handleLaunch(rawJson: String, ctx: AsyncRequestContext)
 
     } catch (e: Exception) {
         log.warning { "Launch: launch failed: ${e.message}" }
-        sendErrorResponse(ctx, requestSeq, "launch", e.message ?: "Launch failed")
+        releaseTerminal()
+        if (e is ProgramError) {
+            ctx.sendEventToClient(e.toResponse(requestSeq).toJson())
+        } else {
+            sendErrorResponse(ctx, requestSeq, "launch", e.message ?: "Launch failed")
+        }
     }
 }
 
@@ -145,6 +256,8 @@ suspend fun DebugSession.handleLaunch(rawJson: String, ctx: AsyncRequestContext)
  *
  * Sets up environment, arguments, stdio, runs preRunCommands,
  * announces the launch, launches the process, and runs postRunCommands.
+ * With [relaunch], the target's current process is killed as the new
+ * one is launched (see [handleRestart]).
  */
 private suspend fun DebugSession.completeLaunch(
     args: LaunchRequestArguments,
@@ -152,15 +265,14 @@ private suspend fun DebugSession.completeLaunch(
     target: SBTarget,
     ttyPath: String?,
     ctx: AsyncRequestContext,
+    relaunch: Boolean = false,
 ) {
     val launchInfo = target.launchInfo()
 
-    // Environment setup (launch.rs:65-95)
+    // Environment setup (launch.rs:65-95); envFile and ${env:VAR} are KDAP's
     val env = createEnvironment()
-    if (args.env != null) {
-        for ((k, v) in args.env) {
-            env.set(k, v, overwrite = true)
-        }
+    for ((k, v) in launchEnvironment(args)) {
+        env.set(k, v, overwrite = true)
     }
     launchInfo.setEnvironment(env, append = false)
 
@@ -169,13 +281,15 @@ private suspend fun DebugSession.completeLaunch(
         launchInfo.setArguments(args.args, append = false)
     }
 
-    // Working directory (launch.rs:100-102)
+    // Working directory (launch.rs:100-102), checked when it is on this host
     if (args.cwd != null) {
+        if (target.platform().name() == HOST_PLATFORM) checkWorkingDirectory(args.cwd)
         launchInfo.setWorkingDirectory(args.cwd)
     }
 
-    // Stop on entry (launch.rs:103-105)
-    if (args.stopOnEntry == true) {
+    // Stop on entry (launch.rs:103-105); "main" is KDAP's (see EntryStops.kt)
+    val stopAtMain = args.stopOnEntry == EntryStop.Main && setMainBreakpoint(debugger)
+    if (args.stopOnEntry != null && !stopAtMain) {
         val flags = launchInfo.launchFlags().toMutableSet()
         flags.add(LaunchFlag.StopAtEntry)
         launchInfo.setLaunchFlags(flags)
@@ -211,6 +325,8 @@ private suspend fun DebugSession.completeLaunch(
     if (args.processCreateCommands != null) {
         execCommands("processCreateCommands", args.processCreateCommands, debugger, ctx)
         process = target.process()
+    } else if (relaunch) {
+        process = target.relaunch(launchInfo)
     } else {
         process = target.launch(launchInfo)
     }
@@ -227,6 +343,21 @@ private suspend fun DebugSession.completeLaunch(
     }
     consoleMessage("Launched process $pid from '$programPath' (state=$launchState)", ctx)
 
+    if (launchState.isAlive()) initProcessExtensions(debugger, ctx)
+
+    // Copy debuggee output from the KDAP pty (see Stdio.kt).
+    if (args.pty == true) {
+        startStdioPty(debugger)
+    }
+
+    // Signal dispositions and exception filters need a process (see
+    // Signals.kt and applySignalFilters).
+    logErrors { applySignalDispositions(process, ctx) }
+    logErrors { applySignalFilters(process, ctx) }
+
+    // Breakpoint sites are written now; some may need hardware (see Breakpoints.kt).
+    logErrors { applyHardwareFallback(ctx) }
+
     // Note: the continued event is sent by handleLaunch AFTER the
     // launch and configurationDone responses, matching CodeLLDB's ordering.
 
@@ -237,6 +368,250 @@ private suspend fun DebugSession.completeLaunch(
     commonPostRun(args.common)
 }
 
+// ── Core dump launch (KDAP) ──────────────────────────────────────
+
+/**
+ * Finishes a `"coreDump"` launch: runs preRunCommands, loads [corePath]
+ * as the target's process, responds to `launch` and `configurationDone`,
+ * and reports the dumped process as stopped so the client shows its
+ * threads, stacks, and variables.
+ *
+ * The process never runs. LLDB rejects `continue` and stepping on a
+ * core, and writes (`setVariable`, `writeMemory`) fail because the
+ * core's memory is read-only.
+ */
+private suspend fun DebugSession.completeCoreDumpLaunch(
+    args: LaunchRequestArguments,
+    corePath: String,
+    debugger: SBDebugger,
+    target: SBTarget,
+    requestSeq: Int,
+    configDoneSeq: Int,
+    ctx: AsyncRequestContext,
+) {
+    if (args.common.preRunCommands != null) {
+        execCommands("preRunCommands", args.common.preRunCommands, debugger, ctx)
+    }
+
+    ctx.activateEventGate()
+    try {
+        consoleMessage("Loading core dump: $corePath", ctx)
+        val process = target.loadCore(corePath)
+        consoleMessage("Loaded core dump of process ${process.processId()}", ctx)
+
+        // Disconnecting destroys the post-mortem process.
+        terminateOnDisconnect = true
+        commonPostRun(args.common)
+
+        sendSuccessResponse(ctx, requestSeq, "launch")
+        sendSuccessResponse(ctx, configDoneSeq, "configurationDone")
+        notifyProcessStopped(process, ctx)
+    } finally {
+        ctx.releaseEventGate()
+    }
+}
+
+// ── Recorded launch (KDAP) ───────────────────────────────────────
+
+/**
+ * Finishes a `"record": true` launch: runs preRunCommands, records the
+ * program under rr until it exits, connects to `rr replay` of the
+ * recording, and responds to `launch` and `configurationDone`. The
+ * replay then stops on entry or runs to the first breakpoint.
+ */
+private suspend fun DebugSession.completeRecordedLaunch(
+    args: LaunchRequestArguments,
+    debugger: SBDebugger,
+    target: SBTarget,
+    requestSeq: Int,
+    configDoneSeq: Int,
+    ctx: AsyncRequestContext,
+) {
+    if (args.common.preRunCommands != null) {
+        execCommands("preRunCommands", args.common.preRunCommands, debugger, ctx)
+    }
+
+    val executable = target.executable()
+    val program = if (executable.isValid()) executable.path() else args.program
+        ?: throw SBError("The \"program\" attribute is required for launch.")
+    val trace = recordDebuggee(program, args, ctx)
+
+    ctx.activateEventGate()
+    try {
+        processRunning = true
+        val process = startReplay(trace, target, ctx)
+        reverseExecution = true
+        logErrors { applySignalDispositions(process, ctx) }
+        logErrors { applySignalFilters(process, ctx) }
+
+        // Disconnecting kills the replay, like a launched process.
+        terminateOnDisconnect = true
+        commonPostRun(args.common)
+
+        sendSuccessResponse(ctx, requestSeq, "launch")
+        sendSuccessResponse(ctx, configDoneSeq, "configurationDone")
+        if (args.stopOnEntry != null) {
+            notifyProcessStopped(process, ctx)
+        } else {
+            process.resume()
+            ctx.sendEventToClient(ContinuedEvent(seq = 0, allThreadsContinued = true).toJson())
+        }
+    } finally {
+        ctx.releaseEventGate()
+    }
+}
+
+// ── Process configuration (KDAP) ─────────────────────────────────
+
+/** Name of LLDB's platform for processes on this machine. */
+private const val HOST_PLATFORM = "host"
+
+/** A `${env:NAME}` reference in an `env` or `envFile` value. */
+private val ENV_REFERENCE = Regex("""\$\{env:([^}]*)}""")
+
+/** An `envFile` line: `NAME=VALUE`, optionally after `export`. */
+private val ENV_FILE_LINE = Regex("""^(?:export\s+)?([A-Za-z_][A-Za-z0-9_.]*)\s*=\s*(.*)$""")
+
+/**
+ * Checks the types of a launch configuration's process attributes
+ * (`args`, `cwd`, `env`, `envFile`, `signals`, and `stopOnEntry`) before anything
+ * is created, so a mistake is reported by name rather than as a JSON
+ * error or ignored. Throws [SBError] for the first one found.
+ */
+internal fun checkProcessConfiguration(obj: JSONObject) {
+    if (!obj.isNull("stopOnEntry") && obj.opt("stopOnEntry") != false &&
+        EntryStop.fromJson(obj.opt("stopOnEntry")) == null
+    ) {
+        throw SBError("\"stopOnEntry\" must be true, false, \"entry\", or \"main\".")
+    }
+    for (key in listOf("cwd", "envFile")) {
+        if (!obj.isNull(key) && obj.opt(key) !is String) {
+            throw SBError("\"$key\" must be a path string.")
+        }
+    }
+    if (!obj.isNull("args")) {
+        val args = obj.opt("args") as? JSONArray
+            ?: throw SBError("\"args\" must be an array of strings.")
+        for (i in 0 until args.length()) {
+            if (args.opt(i) !is String) {
+                throw SBError("\"args\" must be an array of strings; element $i is not a string.")
+            }
+        }
+    }
+    if (!obj.isNull("signals")) {
+        val signals = obj.opt("signals") as? JSONObject
+            ?: throw SBError("\"signals\" must be an object mapping signal names to dispositions.")
+        parseSignalDispositions(signals.toStringStringMap())
+    }
+    if (!obj.isNull("env")) {
+        val env = obj.opt("env") as? JSONObject
+            ?: throw SBError("\"env\" must be an object mapping variable names to strings.")
+        for (name in env.keySet()) {
+            if (name.isEmpty() || '=' in name) {
+                throw SBError("\"env\" has an invalid variable name \"$name\".")
+            }
+            if (env.opt(name) !is String) {
+                throw SBError("\"env\" value of \"$name\" must be a string.")
+            }
+        }
+    }
+}
+
+/**
+ * Throws [SBError] unless [cwd] is an existing directory, which the
+ * platform would otherwise report only as a failed launch.
+ */
+internal fun checkWorkingDirectory(cwd: String) {
+    val dir = java.io.File(cwd)
+    when {
+        !dir.exists() -> throw SBError("The working directory \"$cwd\" does not exist.")
+        !dir.isDirectory -> throw SBError("The working directory \"$cwd\" is not a directory.")
+    }
+}
+
+/**
+ * The variables a launch adds to the debuggee's environment: those of
+ * `envFile`, overridden by those of `env`. `${env:NAME}` in a value is
+ * replaced by `NAME` as defined by an earlier `envFile` line, else by
+ * KDAP's own [hostEnvironment], else by nothing; `env` values see the
+ * whole `envFile` but not each other, as JSON objects are unordered.
+ */
+internal fun launchEnvironment(
+    args: LaunchRequestArguments,
+    hostEnvironment: Map<String, String> = System.getenv(),
+): Map<String, String> {
+    val env = linkedMapOf<String, String>()
+    val lookup = { name: String -> env[name] ?: hostEnvironment[name] }
+    if (args.envFile != null) {
+        val text = try {
+            java.io.File(args.envFile).readText()
+        } catch (e: java.io.IOException) {
+            throw SBError("Cannot read envFile \"${args.envFile}\": ${e.message}")
+        }
+        for ((name, value) in parseEnvFile(text, args.envFile)) {
+            env[name] = expandEnvReferences(value, lookup)
+        }
+    }
+    val fileEnv = env.toMap()
+    for ((name, value) in args.env.orEmpty()) {
+        env[name] = expandEnvReferences(value) { fileEnv[it] ?: hostEnvironment[it] }
+    }
+    return env
+}
+
+/** Replaces each `${env:NAME}` in [value] with [lookup] of `NAME`, or nothing. */
+internal fun expandEnvReferences(value: String, lookup: (String) -> String?): String =
+    ENV_REFERENCE.replace(value) { lookup(it.groupValues[1]).orEmpty() }
+
+/**
+ * Parses the text of an `envFile` ([path] names it in errors) in the
+ * dotenv format: `NAME=VALUE` lines, optionally after `export`, with
+ * blank lines and `#` comments. A value may be single-quoted (taken as
+ * is), double-quoted (with `\n`, `\t`, `\"`, and `\\` escapes), or bare
+ * (trimmed, up to a ` #` comment). Throws [SBError] on a malformed line.
+ */
+internal fun parseEnvFile(text: String, path: String): Map<String, String> {
+    val env = linkedMapOf<String, String>()
+    for ((index, line) in text.lines().withIndex()) {
+        val trimmed = line.trim()
+        if (trimmed.isEmpty() || trimmed.startsWith("#")) continue
+        val match = ENV_FILE_LINE.matchEntire(trimmed)
+            ?: throw SBError("$path:${index + 1}: expected NAME=VALUE, found \"$trimmed\".")
+        val (name, raw) = match.destructured
+        env[name] = envFileValue(raw)
+            ?: throw SBError("$path:${index + 1}: unterminated quote in the value of $name.")
+    }
+    return env
+}
+
+/** The value of an `envFile` line from its text after `=`, or `null` if a quote is not closed. */
+private fun envFileValue(raw: String): String? {
+    val quote = raw.firstOrNull()?.takeIf { it == '"' || it == '\'' }
+        ?: return raw.substringBefore(" #").trim()
+    val value = StringBuilder()
+    var i = 1
+    while (i < raw.length) {
+        val c = raw[i]
+        when {
+            c == quote -> return value.toString()
+            quote == '"' && c == '\\' && i + 1 < raw.length -> {
+                i++
+                value.append(
+                    when (val escaped = raw[i]) {
+                        'n' -> '\n'
+                        't' -> '\t'
+                        'r' -> '\r'
+                        else -> escaped
+                    },
+                )
+            }
+            else -> value.append(c)
+        }
+        i++
+    }
+    return null
+}
+
 // ── handle_attach (launch.rs:198) ────────────────────────────────
 
 /**
@@ -249,14 +624,18 @@ suspend fun DebugSession.handleAttach(rawJson: String, ctx: AsyncRequestContext)
     val args = AttachRequestArguments.fromJson(argsObj)
 
     try {
+        // Configuration schema (KDAP extension; see ConfigurationSchema.kt)
+        reportConfigurationProblems("attach", argsObj, ctx)
         val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
 
         // common_init_session (launch.rs:199)
         commonInitSession(args.common, debugger, ctx)
 
         // Validate (launch.rs:201-205)
-        if (args.program == null && args.pid == null && args.targetCreateCommands == null) {
-            throw SBError("Either \"program\" or \"pid\" is required to attach.")
+        if (args.program == null && args.pid == null && args.gdbRemote == null &&
+            args.android == null && args.targetCreateCommands == null
+        ) {
+            throw SBError("Either \"program\", \"pid\", \"gdbRemote\", or \"android\" is required to attach.")
         }
 
         // Create target (launch.rs:207-231)
@@ -268,7 +647,9 @@ suspend fun DebugSession.handleAttach(rawJson: String, ctx: AsyncRequestContext)
             }
             args.program != null -> {
                 try {
-                    createTargetFromProgram(args.program, debugger, ctx)
+                    createTargetFromProgram(args.program, debugger, ctx).also {
+                        programBuiltAt = java.io.File(args.program).lastModified().takeIf { it > 0 }
+                    }
                 } catch (_: Exception) {
                     // Assume attach-by-name
                     debugger.createTarget()
@@ -277,6 +658,40 @@ suspend fun DebugSession.handleAttach(rawJson: String, ctx: AsyncRequestContext)
             else -> debugger.createTarget()
         }
 
+        // Follow child processes (KDAP extension; see ChildProcesses.kt)
+        if (args.common.followChildProcesses == true) {
+            logErrors { initChildProcessFollowing(argsObj, debugger, target) }
+        }
+
+        // List tokio tasks as threads (KDAP extension; see AsyncTasks.kt)
+        if (args.common.tokioTasks == true) {
+            logErrors { initTokioTasks(debugger) }
+            tokioTasks = true
+        }
+
+        // Track heap allocations (KDAP extension; see HeapTracking.kt)
+        if (args.common.trackAllocations == true) {
+            logErrors { initHeapTracking(debugger, target) }
+        }
+
+        // kdap.init script (KDAP extension; see Scripting.kt)
+        logErrors { loadInitScript(args.common.initScript, ctx) }
+
+        // Return values after steps (see ReturnValues.kt)
+        logErrors { initReturnValues(debugger) }
+
+        // Module events (see Modules.kt)
+        logErrors { initModuleEvents(debugger) }
+
+        // Breakpoints binding as libraries load (see PendingBreakpoints.kt)
+        logErrors { initBreakpointEvents(debugger) }
+
+        // Separate debug info (KDAP extension; see DebugInfo.kt)
+        logErrors { initSeparateDebugInfo(args.common, debugger) }
+
+        // Symbol index warm-up (KDAP extension; see SymbolIndex.kt)
+        logErrors { initSymbolIndexWarmUp(args.common.symbolIndexCache, debugger) }
+
         // Pre-register the configurationDone intercept BEFORE sending
         // initialized (same race-prevention as handleLaunch).
         val configDoneToken = ctx.registerIntercept("configurationDone")
@@ -332,14 +747,27 @@ private suspend fun DebugSession.completeAttach(
     if (args.processCreateCommands != null) {
         execCommands("processCreateCommands", args.processCreateCommands, debugger, ctx)
         process = target.process()
+        initProcessExtensions(debugger, ctx)
+    } else if (args.gdbRemote != null) {
+        // Remote attach over the GDB remote protocol (KDAP extension).
+        // LLDB's gdb-remote plugin reads the memory map, auxv, and shared
+        // library list from the server (qXfer), so symbols resolve.
+        consoleMessage("Connecting to ${args.gdbRemote}", ctx)
+        process = target.connectRemote(gdbRemoteUrl(args.gdbRemote))
+        stopOrResumeAttached(process, args, debugger, ctx)
+    } else if (args.android != null) {
+        // Android app over adb (KDAP extension; see Android.kt)
+        process = attachAndroid(args.android, args.common, debugger, target, ctx)
+        stopOrResumeAttached(process, args, debugger, ctx)
     } else {
         val attachInfo = createAttachInfo(ctx)
         when (val pid = args.pid) {
             is Either.First<*> -> attachInfo.setProcessId(pid.value as Long)
             is Either.Second<*> -> {
+                // A non-numeric string names the process to attach to.
                 val pidStr = pid.value as String
                 val pidNum = pidStr.toLongOrNull()
-                    ?: throw SBError("Process id must be a positive integer.")
+                    ?: resolveProcessPattern(pidStr, debugger)
                 attachInfo.setProcessId(pidNum)
             }
             null -> {
@@ -353,14 +781,7 @@ private suspend fun DebugSession.completeAttach(
         attachInfo.setIgnoreExisting(false)
 
         process = target.attach(attachInfo)
-
-        if (args.stopOnEntry == true) {
-            // LLDB won't generate event for the initial stop;
-            // send a stopped event ourselves
-            ctx.sendEventToClient(StoppedEvent(seq = 0).toJson())
-        } else {
-            logErrors { process.resume() }
-        }
+        stopOrResumeAttached(process, args, debugger, ctx)
     }
 
     // Announce (launch.rs:300)
@@ -374,6 +795,47 @@ private suspend fun DebugSession.completeAttach(
     commonPostRun(args.common)
 }
 
+/**
+ * The end of `complete_attach` once LLDB has stopped [process]: applies
+ * the program's load bias and signal filters, then reports the stop
+ * (`stopOnEntry`, or `stopOnExec` for a child that has exec'd) or
+ * resumes.
+ */
+private suspend fun DebugSession.stopOrResumeAttached(
+    process: SBProcess,
+    args: AttachRequestArguments,
+    debugger: SBDebugger,
+    ctx: AsyncRequestContext,
+) {
+    initProcessExtensions(debugger, ctx)
+
+    // Signal dispositions and exception filters need a process (see
+    // Signals.kt and applySignalFilters).
+    logErrors { applySignalDispositions(process, ctx) }
+    logErrors { applySignalFilters(process, ctx) }
+
+    // Breakpoint sites are written now; some may need hardware (see Breakpoints.kt).
+    logErrors { applyHardwareFallback(ctx) }
+
+    if (args.stopOnEntry == true) {
+        // LLDB won't generate event for the initial stop;
+        // send a stopped event ourselves
+        notifyProcessStopped(process, ctx)
+    } else if (args.common.stopOnExec == true && args.childExeced == true) {
+        // A followed child stopped right after its exec (see EntryStops.kt)
+        notifyProcessStopped(process, ctx, reason = "entry")
+    } else {
+        logErrors { process.resume() }
+    }
+}
+
+/**
+ * The LLDB connect URL for a `gdbRemote` address: `host:port` becomes
+ * `connect://host:port`; anything with a scheme is used as is.
+ */
+internal fun gdbRemoteUrl(address: String): String =
+    if ("://" in address) address else "connect://$address"
+
 // ── handle_disconnect (launch.rs:382) ────────────────────────────
 
 /**
@@ -384,6 +846,7 @@ suspend fun DebugSession.handleDisconnect(rawJson: String, ctx: AsyncRequestCont
     val requestSeq = obj.optInt("seq", 0)
     val argsObj = obj.optJSONObject("arguments")
     val terminateDebuggee = argsObj?.optNullableBoolean("terminateDebuggee")
+    val suspendDebuggee = argsObj?.optNullableBoolean("suspendDebuggee") ?: false
 
     try {
         val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
@@ -394,7 +857,7 @@ suspend fun DebugSession.handleDisconnect(rawJson: String, ctx: AsyncRequestCont
         }
 
         // terminate_debuggee (launch.rs:389)
-        terminateDebuggee(debugger, terminateDebuggee, ctx)
+        val detached = terminateDebuggee(debugger, terminateDebuggee, suspendDebuggee, ctx)
 
         // exitCommands (launch.rs:391-393)
         exitCommands?.let { commands ->
@@ -403,6 +866,12 @@ suspend fun DebugSession.handleDisconnect(rawJson: String, ctx: AsyncRequestCont
 
         sendSuccessResponse(ctx, requestSeq, "disconnect")
 
+        // CodeLLDB reports the detach from its process event listener,
+        // i.e. after the disconnect response.
+        if (detached) {
+            consoleMessage("Detached from debuggee.", ctx)
+        }
+
         // Send terminated event — KDAP owns the session lifecycle since it
         // didn't forward launch/attach to lldb-dap, so lldb-dap won't
         // generate this event on disconnect.
@@ -413,6 +882,10 @@ suspend fun DebugSession.handleDisconnect(rawJson: String, ctx: AsyncRequestCont
         // Still send success — disconnect should not fail visibly
         sendSuccessResponse(ctx, requestSeq, "disconnect")
         ctx.sendEventToClient(TerminatedEvent(seq = 0).toJson())
+    } finally {
+        releaseTerminal()
+        releaseRecording()
+        releaseAndroid()
     }
 }
 
@@ -421,7 +894,9 @@ suspend fun DebugSession.handleDisconnect(rawJson: String, ctx: AsyncRequestCont
 /**
  * Mirrors CodeLLDB's `handle_terminate`.
  *
- * Sends a graceful shutdown signal or commands if configured.
+ * Sends the graceful shutdown signal or commands if configured, else
+ * `SIGTERM`, and kills the debuggee if it hasn't exited after
+ * [TERMINATE_GRACE_MS] (KDAP extension; see the file header).
  */
 suspend fun DebugSession.handleTerminate(rawJson: String, ctx: AsyncRequestContext) {
     val obj = JSONObject(rawJson)
@@ -429,68 +904,267 @@ suspend fun DebugSession.handleTerminate(rawJson: String, ctx: AsyncRequestConte
 
     try {
         val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val process = debugger.selectedTarget().process()
 
         when (val shutdown = gracefulShutdown) {
             is Either.First<*> -> {
                 // Signal name (launch.rs:347-372)
-                val process = debugger.selectedTarget().process()
                 val signals = process.unixSignals()
                 if (!signals.isValid()) {
                     throw SBError("The current platform does not support sending signals.")
                 }
                 val signo = signals.signalNumberFromName(shutdown.value as String)
                     ?: throw SBError("Invalid signal name: ${shutdown.value}")
-                signals.setShouldSuppress(signo, false)
-                signals.setShouldStop(signo, false)
-                signals.setShouldNotify(signo, false)
-
-                if (!process.state().isRunning()) {
-                    logErrors { process.resume() }
-                    // Brief delay for the process to start running
-                    kotlinx.coroutines.delay(100)
-                }
-                process.signal(signo)
+                sendShutdownSignal(process, signals, signo)
             }
             is Either.Second<*> -> {
                 // Commands (launch.rs:374-376)
                 @Suppress("UNCHECKED_CAST")
                 execCommands("gracefulShutdown", shutdown.value as List<String>, debugger, ctx)
             }
-            null -> { /* No graceful shutdown configured */ }
+            null -> {
+                // SIGTERM (KDAP extension; see the file header)
+                val signals = process.unixSignals()
+                val signo = if (signals.isValid()) signals.signalNumberFromName("SIGTERM") else null
+                if (signo != null) sendShutdownSignal(process, signals, signo) else killDebuggee(debugger, process)
+            }
         }
 
         sendSuccessResponse(ctx, requestSeq, "terminate")
 
+        // Escalation (KDAP extension; see the file header)
+        logErrors { escalateTerminate(debugger, process, ctx) }
+
     } catch (e: Exception) {
         log.warning { "Launch: terminate failed: ${e.message}" }
         sendErrorResponse(ctx, requestSeq, "terminate", e.message ?: "Terminate failed")
     }
 }
 
+// ── handle_restart (launch.rs) ───────────────────────────────────
+
+/** Advertises `supportsRestartRequest` in a successful `initialize` response. */
+fun addRestartCapability(response: DapResponse): DapResponse =
+    response.copy(body = response.body + ("supportsRestartRequest" to true))
+
+/**
+ * Mirrors CodeLLDB's `handle_restart`.
+ *
+ * Kills the debuggee and launches it again on the same target, so
+ * breakpoints and the rest of the target's state carry over. The
+ * request's `arguments.arguments`, if present, replace the saved launch
+ * configuration; the target itself is not re-created. The debuggee keeps
+ * the client terminal it ran in, and a KDAP pty is replaced by a new one.
+ *
+ * The old process is killed in the same backend call that launches the
+ * new one ([SBTarget.relaunch]): lldb-dap ends the session at a process
+ * exit unless the exited process is already gone. Attach sessions,
+ * launches through `processCreateCommands`, and recorded launches cannot
+ * be restarted.
+ */
+suspend fun DebugSession.handleRestart(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+
+    try {
+        val saved = launchArguments
+            ?: throw SBError("Restarting an \"attach\" session is not supported.")
+        val argsObj = obj.optJSONObject("arguments")?.optJSONObject("arguments") ?: saved
+        checkProcessConfiguration(argsObj)
+        val args = wasmLaunchArguments(LaunchRequestArguments.fromJson(argsObj))
+        if (args.processCreateCommands != null || args.coreDump != null || args.record == true) {
+            throw SBError("This session cannot be restarted.")
+        }
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val target = debugger.selectedTarget()
+        if (!target.process().state().isAlive()) throw SBError("The debuggee is not running.")
+
+        // KDAP's state for the old process: step breakpoints, frames, tasks
+        releaseStepState(debugger)
+        forgetFrameState()
+        asyncTasks = emptyMap()
+        panicMessage = null
+        lastPanic = null
+        lastCrash = null
+        if (trackAllocations) logErrors { resetHeapTracking(debugger) }
+
+        val ttyPath = if (args.pty == true) openStdioPty(debugger) else launchTty
+        ctx.activateEventGate()
+        try {
+            completeLaunch(args, debugger, target, ttyPath, ctx, relaunch = true)
+            launchArguments = argsObj
+            sendSuccessResponse(ctx, requestSeq, "restart")
+            ctx.sendEventToClient(ContinuedEvent(seq = 0, allThreadsContinued = true).toJson())
+        } finally {
+            ctx.releaseEventGate()
+        }
+    } catch (e: Exception) {
+        log.warning { "Launch: restart failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "restart", e.message ?: "Restart failed")
+    }
+}
+
+/**
+ * Delivers signal [signo] to [process], resuming it first if stopped so
+ * that it can handle the signal. LLDB passes the signal on without
+ * stopping or reporting it.
+ */
+private suspend fun sendShutdownSignal(process: SBProcess, signals: SBUnixSignals, signo: Int) {
+    signals.setShouldSuppress(signo, false)
+    signals.setShouldStop(signo, false)
+    signals.setShouldNotify(signo, false)
+
+    if (!process.state().isRunning()) {
+        logErrors { process.resume() }
+        // Brief delay for the process to start running
+        kotlinx.coroutines.delay(100)
+    }
+    process.signal(signo)
+}
+
+/**
+ * Waits up to [TERMINATE_GRACE_MS] for [process] to exit after
+ * `terminate`, and kills it if it is still alive then.
+ */
+private suspend fun DebugSession.escalateTerminate(debugger: SBDebugger, process: SBProcess, ctx: AsyncRequestContext) {
+    val deadline = System.nanoTime() + TERMINATE_GRACE_MS * 1_000_000
+    while (process.isValid() && process.state().isAlive()) {
+        if (System.nanoTime() >= deadline) {
+            consoleMessage("The debuggee did not exit within ${TERMINATE_GRACE_MS / 1000} s; killing it.", ctx)
+            killDebuggee(debugger, process)
+            return
+        }
+        kotlinx.coroutines.delay(EXIT_POLL_MS)
+    }
+}
+
+/**
+ * Kills [process] through LLDB, or, when that takes longer than
+ * [KILL_TIMEOUT_MS] and the process runs on this host, by its pid.
+ */
+private suspend fun killDebuggee(debugger: SBDebugger, process: SBProcess) {
+    val pid = process.processId()
+    val killed = kotlinx.coroutines.withTimeoutOrNull(KILL_TIMEOUT_MS) { process.kill() } != null
+    if (killed || debugger.selectedPlatform().name() != "host") return
+    log.warning { "Launch: LLDB did not kill process $pid in time; killing it by pid" }
+    ProcessHandle.of(pid).ifPresent { it.destroyForcibly() }
+}
+
 // ── terminate_debuggee (launch.rs:398) ───────────────────────────
 
 /**
  * Mirrors CodeLLDB's `terminate_debuggee`.
+ *
+ * When detaching, [keepStopped] (the DAP `suspendDebuggee` argument)
+ * leaves the process stopped; otherwise it resumes where it was. Threads
+ * KDAP suspended are resumed first, and kills fall back to the pid (see
+ * the file header).
+ *
+ * @return `true` if the process was detached (rather than killed)
  */
 private suspend fun DebugSession.terminateDebuggee(
     debugger: SBDebugger,
     forceTerminate: Boolean?,
+    keepStopped: Boolean,
     ctx: AsyncRequestContext,
-) {
+): Boolean {
     val process = debugger.selectedTarget().process()
     if (process.isValid()) {
         val state = process.state()
         if (state.isAlive()) {
             val terminate = forceTerminate ?: terminateOnDisconnect
             if (terminate) {
-                process.kill()
+                killDebuggee(debugger, process)
             } else {
-                process.detach(keepStopped = false)
+                logErrors { releaseStepState(debugger) }
+                process.detach(keepStopped = keepStopped)
+                return true
             }
         }
     }
+    return false
 }
 
+// ── notify_process_stopped (debug_session.rs) ────────────────────
+
+/**
+ * Mirrors CodeLLDB's `notify_process_stopped` for stops that LLDB does
+ * not report itself (the initial stop after attaching).
+ *
+ * Reports the stop description on stderr, then sends a `stopped` event
+ * for the selected thread, with [reason] in place of the thread's own.
+ */
+private suspend fun notifyProcessStopped(process: SBProcess, ctx: AsyncRequestContext, reason: String? = null) {
+    val thread = process.selectedThread()
+    if (!thread.isValid()) {
+        ctx.sendEventToClient(StoppedEvent(seq = 0, reason = "unknown", allThreadsStopped = true).toJson())
+        return
+    }
+    val description = thread.stopDescription()
+    if (description.isNotEmpty()) {
+        ctx.sendEventToClient(
+            OutputEvent(seq = 0, category = "stderr", output = "Stop reason: $description\n").toJson()
+        )
+    }
+    ctx.sendEventToClient(StoppedEvent(
+        seq = 0,
+        reason = reason ?: thread.stopReason().toDapReason(),
+        description = description.ifEmpty { null },
+        threadId = thread.threadId().toInt(),
+        allThreadsStopped = true,
+    ).toJson())
+}
+
+/** Maps an LLDB [StopReason] to the DAP `stopped` event `reason`. */
+internal fun StopReason.toDapReason(): String = when (this) {
+    StopReason.Breakpoint -> "breakpoint"
+    StopReason.Trace, StopReason.PlanComplete -> "step"
+    StopReason.Watchpoint -> "data breakpoint"
+    StopReason.Signal -> "signal"
+    StopReason.Exception, StopReason.Instrumentation -> "exception"
+    StopReason.Exec -> "entry"
+    StopReason.Interrupt -> "pause"
+    else -> "unknown"
+}
+
+// ── Attach by process name ───────────────────────────────────────
+
+/**
+ * Resolves a process name pattern (the non-numeric form of `pid`) to
+ * the single running process whose name matches it.
+ *
+ * @throws SBError if no process, or more than one, matches
+ */
+private suspend fun resolveProcessPattern(pattern: String, debugger: SBDebugger): Long {
+    val regex = processNamePatternToRegex(pattern)
+    val matches = debugger.selectedPlatform().allProcesses()
+        .map { it.processId() to it.name() }
+        .filter { (_, name) -> regex.matches(name) }
+    return when (matches.size) {
+        0 -> throw SBError("No running process matches \"$pattern\".")
+        1 -> matches.single().first
+        else -> throw SBError(
+            "Multiple processes match \"$pattern\": " +
+                matches.joinToString { (pid, name) -> "$name ($pid)" } +
+                ". Specify a numeric pid instead."
+        )
+    }
+}
+
+/**
+ * Converts a process name pattern to a [Regex]. `*` matches any run of
+ * characters and `?` matches exactly one; everything else is literal.
+ */
+internal fun processNamePatternToRegex(pattern: String): Regex = Regex(buildString {
+    for (c in pattern) {
+        when (c) {
+            '*' -> append(".*")
+            '?' -> append('.')
+            else -> append(Regex.escape(c.toString()))
+        }
+    }
+})
+
 // ── common_init_session (launch.rs:531) ──────────────────────────
 
 /**
@@ -512,11 +1186,51 @@ private suspend fun DebugSession.commonInitSession(
     // Console mode announcement (launch.rs:590)
     printConsoleMode(ctx)
 
+    // Rust and Kotlin/Native formatters, ahead of initCommands so users can override them,
+    // and ahead of simple expressions, which use the Rust enum helpers
+    val stringPreviewLength = common.adapterSettings?.stringPreviewLength?.takeIf { it > 0 } ?: DEFAULT_STRING_PREVIEW_LENGTH
+    logErrors { initRustFormatters(debugger, stringPreviewLength) }
+    logErrors { initKotlinFormatters(debugger) }
+    logErrors { initSimpleExpressions(debugger) }
+
+    // Natvis visualizers, also ahead of initCommands (KDAP extension; see Natvis.kt)
+    if (common.visualizers != null) {
+        logErrors { initNatvis(common.visualizers, debugger, ctx) }
+    }
+
+    // Source fetch for sources not on disk (KDAP extension; see Sources.kt)
+    sourceServers = sourceServerUrls(
+        common.sourceServers, debuginfodUrls(common.debuginfodUrls, System.getenv("DEBUGINFOD_URLS")))
+
+    // Just my code stepping (KDAP extension; see Stepping.kt)
+    if (common.justMyCode == true) {
+        logErrors { initJustMyCode(common.externalCodePaths, debugger) }
+    }
+
+    // Symbol index cache, ahead of initCommands so users can move it
+    // (KDAP extension; see SymbolIndex.kt)
+    logErrors { initSymbolIndexCache(common.symbolIndexCache, debugger) }
+
     // initCommands (launch.rs:592-594)
     if (common.initCommands != null) {
         execCommands("initCommands", common.initCommands, debugger, ctx)
     }
 
+    // Stops at exec (KDAP extension; see EntryStops.kt)
+    logErrors { initStopOnExec(common.stopOnExec, debugger) }
+
+    // Signal dispositions, applied once there is a process (see Signals.kt)
+    common.signals?.let { signalDispositions.putAll(it) }
+
+    // Shared library lookup (KDAP extension). After initCommands, since
+    // `platform select` there replaces the platform the sysroot belongs to.
+    if (common.sysroot != null) {
+        debugger.setCurrentPlatformSdkRoot(common.sysroot)
+    }
+    if (common.solibSearchPath != null) {
+        initSolibSearchPath(common.solibSearchPath, debugger)
+    }
+
     // adapter_settings.console_mode (launch.rs:600-608)
     val settings = common.adapterSettings
     if (settings != null) {
@@ -524,12 +1238,25 @@ private suspend fun DebugSession.commonInitSession(
             // Without a terminal, confirmations will just hang the session
             logErrors { debugger.setVariable("auto-confirm", "true") }
         }
+        applyAdapterSettings(settings)
     }
 
     // Store graceful_shutdown for later use
     gracefulShutdown = common.gracefulShutdown
 }
 
+/** Copies the KDAP adapter settings in [settings] to the session; those not given keep their defaults. */
+internal fun DebugSession.applyAdapterSettings(settings: AdapterSettings) {
+    settings.watchRefresh?.let { watchRefresh = it }
+    settings.clipboardMaxElements?.takeIf { it > 0 }?.let { clipboardMaxElements = it }
+    settings.staticsScope?.let { staticsScope = it }
+    settings.internalFrames?.let { internalFrames = it }
+    settings.showDisassembly?.let { showDisassembly = it }
+    settings.evaluationTimeout?.takeIf { it > 0 }?.let { evaluationTimeout = it }
+    settings.changedValuesDepth?.takeIf { it >= 0 }?.let { changedValuesDepth = it }
+    settings.maxExpansionDepth?.takeIf { it >= 0 }?.let { maxExpansionDepth = it }
+}
+
 // ── common_post_run (launch.rs:613) ──────────────────────────────
 
 /**
@@ -538,6 +1265,18 @@ private suspend fun DebugSession.commonInitSession(
 private fun DebugSession.commonPostRun(common: CommonLaunchFields) {
     preTerminateCommands = common.preTerminateCommands
     exitCommands = common.exitCommands
+    if (common.reverseDebugging == true) reverseExecution = true
+}
+
+/**
+ * KDAP extensions that need the process, run by every launch and attach
+ * once LLDB has the process stopped and before it is resumed: moves a
+ * PIE program LLDB has at the wrong addresses to its load bias (see
+ * LoadBias.kt) and starts thread events (see Threads.kt).
+ */
+private suspend fun DebugSession.initProcessExtensions(debugger: SBDebugger, ctx: AsyncRequestContext) {
+    logErrors { applyLoadBias(debugger, ctx) }
+    logErrors { initThreadEvents(debugger) }
 }
 
 // ── create_terminal (launch.rs:442) ──────────────────────────────
@@ -581,8 +1320,12 @@ private suspend fun DebugSession.createTerminal(
 /**
  * Orchestrates the `runInTerminal` reverse request flow, including
  * TCP handshake with `kdap-launch` to obtain a TTY path.
+ *
+ * When a TTY is reported, the connection stays open as
+ * [DebugSession.terminalConnection] so that `kdap-launch` keeps the
+ * terminal (and its stdin) for the debuggee until [releaseTerminal].
  */
-private suspend fun runTerminalHandshake(
+private suspend fun DebugSession.runTerminalHandshake(
     terminalKind: String,
     title: String,
     ctx: AsyncRequestContext,
@@ -625,20 +1368,23 @@ private suspend fun runTerminalHandshake(
 
         return serverSocket.use { ss ->
             val socket = ss.accept()
-            socket.use { s ->
-                val input = s.getInputStream()
+            try {
+                val input = socket.getInputStream()
                     .bufferedReader(StandardCharsets.UTF_8)
                     .readText()
                 val ttyJson = JSONObject(input)
                 val tty = if (ttyJson.isNull("tty")) null else ttyJson.optString("tty", null)
 
                 val successResponse = JSONObject().apply { put("success", true) }
-                s.getOutputStream().write(
-                    successResponse.toString().toByteArray(StandardCharsets.UTF_8)
+                socket.getOutputStream().write(
+                    "$successResponse\n".toByteArray(StandardCharsets.UTF_8)
                 )
-                s.getOutputStream().flush()
+                socket.getOutputStream().flush()
 
+                if (tty != null) terminalConnection = socket
                 tty
+            } finally {
+                if (terminalConnection !== socket) socket.close()
             }
         }
     } catch (e: Exception) {
@@ -649,6 +1395,20 @@ private suspend fun runTerminalHandshake(
     }
 }
 
+/**
+ * Closes [DebugSession.terminalConnection], letting `kdap-launch` exit
+ * and hand the terminal back to its shell.
+ */
+internal fun DebugSession.releaseTerminal() {
+    val connection = terminalConnection ?: return
+    terminalConnection = null
+    try {
+        connection.close()
+    } catch (e: Exception) {
+        log.fine { "Launch: closing terminal connection: ${e.message}" }
+    }
+}
+
 // ── configure_stdio (launch.rs:495) ──────────────────────────────
 
 /**
@@ -723,6 +1483,18 @@ private suspend fun initSourceMap(
     logErrors { debugger.setVariable("target.source-map", args) }
 }
 
+/**
+ * Sets `target.exec-search-paths` to [paths], where LLDB looks for
+ * shared libraries it cannot find at their recorded paths, and
+ * `target.debug-file-search-paths`, for their separate debug info.
+ */
+internal suspend fun initSolibSearchPath(paths: List<String>, debugger: SBDebugger) {
+    if (paths.isEmpty()) return
+    val value = lldbPathList(paths)
+    debugger.setVariable("target.exec-search-paths", value)
+    logErrors { debugger.setVariable("target.debug-file-search-paths", value) }
+}
+
 // ── print_console_mode (launch.rs:623) ───────────────────────────
 
 /**
@@ -768,23 +1540,36 @@ private suspend fun execCommands(
 /**
  * Sends a console output event to the client.
  */
-private suspend fun consoleMessage(message: String, ctx: AsyncRequestContext) {
+internal suspend fun consoleMessage(message: String, ctx: AsyncRequestContext) {
     ctx.sendEventToClient(OutputEvent.console("$message\n").toJson())
 }
 
 /**
- * Sends a success response for a given command.
+ * Reports the properties of [arguments] of [request] that don't match
+ * [ConfigurationSchema] in the debug console. They are warnings: the
+ * session goes on, with such a property ignored or read as its parser can.
+ */
+private suspend fun reportConfigurationProblems(request: String, arguments: JSONObject, ctx: AsyncRequestContext) {
+    for (problem in ConfigurationSchema.validate(request, arguments)) {
+        consoleMessage("Warning: $problem in the $request configuration.", ctx)
+    }
+}
+
+/**
+ * Sends a success response for a given command, with an optional [body].
  */
-private suspend fun sendSuccessResponse(
+internal suspend fun sendSuccessResponse(
     ctx: AsyncRequestContext,
     requestSeq: Int,
     command: String,
+    body: Map<String, Any?> = emptyMap(),
 ) {
     val response = DapResponse(
         seq = 0,
         requestSeq = requestSeq,
         command = command,
         success = true,
+        body = body,
     )
     ctx.sendEventToClient(response.toJson())
 }
@@ -792,7 +1577,7 @@ private suspend fun sendSuccessResponse(
 /**
  * Sends an error response for a given command.
  */
-private suspend fun sendErrorResponse(
+internal suspend fun sendErrorResponse(
     ctx: AsyncRequestContext,
     requestSeq: Int,
     command: String,
@@ -805,7 +1590,7 @@ private suspend fun sendErrorResponse(
 // ── Utility helpers ──────────────────────────────────────────────────
 
 /** Runs [block] and logs any exception without rethrowing. */
-private suspend fun logErrors(block: suspend () -> Unit) {
+internal suspend fun logErrors(block: suspend () -> Unit) {
     try {
         block()
     } catch (e: Exception) {
@@ -813,5 +1598,9 @@ private suspend fun logErrors(block: suspend () -> Unit) {
     }
 }
 
+/** [paths] as the value of an LLDB file list setting: each quoted, separated by spaces. */
+internal fun lldbPathList(paths: List<String>): String =
+    paths.joinToString(" ") { "\"${escapeForLldb(it)}\"" }
+
 private fun escapeForLldb(s: String): String =
     s.replace("\\", "\\\\").replace("\"", "\\\"")
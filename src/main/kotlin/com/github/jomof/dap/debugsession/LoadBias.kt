@@ -0,0 +1,190 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.sb.SBDebugger
+import com.github.jomof.dap.sb.SBError
+import com.github.jomof.dap.sb.pyStr
+import org.json.JSONObject
+import java.nio.ByteBuffer
+import java.nio.ByteOrder
+import java.util.HexFormat
+import java.util.logging.Logger
+
+/**
+ * Load bias: where a position-independent program was loaded. KDAP
+ * extension; CodeLLDB relies on LLDB's dynamic loader alone.
+ *
+ * A PIE executable's symbols and line tables are at file addresses from
+ * 0, and the kernel maps it at a random base (ASLR). LLDB's POSIX
+ * dynamic loader learns that base when it launches or attaches with
+ * the rendezvous, but a process LLDB did not start itself may reach the
+ * session with the program still at its file addresses: one created by
+ * `processCreateCommands`, or one behind a `gdbRemote` stub that reports
+ * no library list, such as QEMU's user-mode stub. Breakpoints set before
+ * it ran then resolve to addresses the program does not occupy and never
+ * hit, unless the session stopped on entry and set them again.
+ *
+ * Once the process exists, stopped at its first stop or running, KDAP
+ * computes the bias itself ([applyLoadBias]): the auxiliary vector's
+ * `AT_ENTRY` (the program's entry point as loaded; from
+ * `/proc/<pid>/auxv` on this machine, else the stub's
+ * `qXfer:auxv:read`) less the ELF header's entry point, or, without an
+ * auxiliary vector, `l_addr` of the first entry of the dynamic linker's
+ * link map (`_r_debug.r_map`). If the program's load address in LLDB
+ * does not match, KDAP slides the whole module there
+ * (`SBTarget::SetModuleLoadAddress`), which moves every symbol and
+ * re-resolves the breakpoints in it, and says so on the console.
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.LoadBias")
+
+/** Auxiliary vector entry with the program's entry point as loaded. */
+internal const val AT_ENTRY = 9L
+
+/**
+ * Parses an auxiliary vector of [pointerSize]-byte words into entry
+ * values by type, up to its `AT_NULL` terminator.
+ */
+internal fun parseAuxv(bytes: ByteArray, pointerSize: Int, littleEndian: Boolean): Map<Long, Long> {
+    val buffer = ByteBuffer.wrap(bytes).order(if (littleEndian) ByteOrder.LITTLE_ENDIAN else ByteOrder.BIG_ENDIAN)
+    fun word(): Long = if (pointerSize == 8) buffer.getLong() else buffer.getInt().toLong() and 0xffffffffL
+    val entries = LinkedHashMap<Long, Long>()
+    while (buffer.remaining() >= 2 * pointerSize) {
+        val type = word()
+        val value = word()
+        if (type == 0L) break
+        entries.putIfAbsent(type, value)
+    }
+    return entries
+}
+
+/**
+ * The bias to slide the program by, from its loaded entry point
+ * [atEntry] and its ELF entry point [fileEntry], or else from the link
+ * map's [linkMapBias]; `null` if neither is known or LLDB already has
+ * the entry point at [loadEntry].
+ */
+internal fun loadBiasCorrection(atEntry: Long?, linkMapBias: Long?, fileEntry: Long, loadEntry: Long?): Long? {
+    val bias = atEntry?.let { it - fileEntry } ?: linkMapBias ?: return null
+    return bias.takeIf { loadEntry != fileEntry + bias }
+}
+
+/**
+ * Slides the selected target's program to its load bias if LLDB has it
+ * elsewhere (see the file header). Programs that are not ELF, and
+ * processes whose bias can't be read, are left alone.
+ */
+internal suspend fun applyLoadBias(debugger: SBDebugger, ctx: AsyncRequestContext) {
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_LOAD_BIAS)})")
+    val info = JSONObject(interpreter.handleCommand("script print(_kdap_load_bias_info())").trim())
+    if (info.has("error")) {
+        log.fine { "LoadBias: ${info.getString("error")}" }
+        return
+    }
+    val auxv = parseAuxv(HexFormat.of().parseHex(info.optString("auxv", "")),
+        info.getInt("pointerSize"), info.getBoolean("littleEndian"))
+    val bias = loadBiasCorrection(auxv[AT_ENTRY], info.optAddress("linkMapBias"),
+        info.optAddress("fileEntry") ?: return, info.optAddress("loadEntry")) ?: return
+    val error = interpreter.handleCommand("script print(_kdap_set_load_bias($bias))").trim()
+    if (error.isNotEmpty()) throw SBError(error)
+    consoleMessage("Loaded ${info.getString("module")} at bias 0x%x; its breakpoints were resolved again".format(bias), ctx)
+}
+
+/** A `0x`-prefixed hex address of this object, if it has [key]. */
+private fun JSONObject.optAddress(key: String): Long? =
+    optString(key, null)?.removePrefix("0x")?.toULongOrNull(16)?.toLong()
+
+/**
+ * Python side of [applyLoadBias]. `_kdap_load_bias_info` returns the
+ * program's name, ELF and loaded entry points, the auxiliary vector as
+ * hex, and the link map's bias if it can be read, or `{"error": ...}`.
+ * Addresses are hex strings. `_kdap_set_load_bias` slides the program
+ * and returns an error message, or nothing.
+ */
+private val PY_LOAD_BIAS = """
+    import json, os, re
+
+    def _kdap_unescape_binary(data):
+        out = bytearray()
+        i = 0
+        while i < len(data):
+            if data[i] == 0x7d and i + 1 < len(data):
+                out.append(data[i + 1] ^ 0x20)
+                i += 2
+            else:
+                out.append(data[i])
+                i += 1
+        return bytes(out)
+
+    def _kdap_auxv_packets():
+        interpreter = lldb.debugger.GetCommandInterpreter()
+        data = b''
+        while len(data) < 1 << 16:
+            result = lldb.SBCommandReturnObject()
+            interpreter.HandleCommand('process plugin packet send qXfer:auxv:read::%x,800' % len(data), result)
+            match = re.search(r'^response: ([lm])(.*)\Z', result.GetOutput() or '', re.M | re.S)
+            if not result.Succeeded() or match is None:
+                return None
+            data += _kdap_unescape_binary(match.group(2).rstrip('\n').encode('utf-8', 'surrogateescape'))
+            if match.group(1) == 'l':
+                return data
+        return data
+
+    def _kdap_auxv(target, process):
+        path = '/proc/%d/auxv' % process.GetProcessID()
+        if target.GetPlatform().IsHost() and os.path.exists(path):
+            try:
+                with open(path, 'rb') as f:
+                    return f.read()
+            except OSError:
+                pass
+        return _kdap_auxv_packets()
+
+    def _kdap_link_map_bias(target, process):
+        found = target.FindSymbols('_r_debug', lldb.eSymbolTypeData)
+        for i in range(found.GetSize()):
+            address = found.GetContextAtIndex(i).GetSymbol().GetStartAddress().GetLoadAddress(target)
+            if address == lldb.LLDB_INVALID_ADDRESS:
+                continue
+            error = lldb.SBError()
+            size = target.GetAddressByteSize()
+            link_map = process.ReadPointerFromMemory(address + size, error)
+            if error.Success() and link_map:
+                bias = process.ReadPointerFromMemory(link_map, error)
+                if error.Success():
+                    return bias
+        return None
+
+    def _kdap_load_bias_info():
+        target = lldb.debugger.GetSelectedTarget()
+        process = target.GetProcess()
+        module = target.GetModuleAtIndex(0)
+        if not process.IsValid() or not module.IsValid():
+            return json.dumps({'error': 'There is no process.'})
+        triple = module.GetTriple()
+        if 'apple' in triple or 'windows' in triple:
+            return json.dumps({'error': 'Not an ELF program.'})
+        entry = module.GetObjectFileEntryPointAddress()
+        if not entry.IsValid():
+            return json.dumps({'error': 'The program has no entry point.'})
+        load = entry.GetLoadAddress(target)
+        info = {
+            'module': module.GetFileSpec().GetFilename(),
+            'fileEntry': '0x%x' % entry.GetFileAddress(),
+            'pointerSize': target.GetAddressByteSize(),
+            'littleEndian': target.GetByteOrder() == lldb.eByteOrderLittle,
+            'auxv': (_kdap_auxv(target, process) or b'').hex(),
+        }
+        if load != lldb.LLDB_INVALID_ADDRESS:
+            info['loadEntry'] = '0x%x' % load
+        bias = _kdap_link_map_bias(target, process)
+        if bias is not None:
+            info['linkMapBias'] = '0x%x' % bias
+        return json.dumps(info)
+
+    def _kdap_set_load_bias(bias):
+        target = lldb.debugger.GetSelectedTarget()
+        error = target.SetModuleLoadAddress(target.GetModuleAtIndex(0), bias & 0xffffffffffffffff)
+        return '' if error.Success() else (error.GetCString() or 'Could not slide the program')
+""".trimIndent()
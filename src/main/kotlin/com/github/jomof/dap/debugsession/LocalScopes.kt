@@ -0,0 +1,281 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.sb.createDebugger
+import com.github.jomof.dap.sb.pyStr
+import com.github.jomof.dap.sb.watched
+import org.json.JSONObject
+import java.util.logging.Logger
+
+/**
+ * Arguments, Locals, and Captures scopes: a frame's variables split by
+ * where DWARF declares them. KDAP extension; CodeLLDB shows all of them
+ * as Local.
+ *
+ * lldb-dap's Locals scope lists a frame's parameters and the locals of
+ * its lexical blocks together. KDAP handles `scopes` itself
+ * ([DebugSession.handleScopes]) and asks the frame, in Python, what each
+ * of those variables is:
+ *
+ * - an argument: a `DW_TAG_formal_parameter` of the function;
+ * - a closure environment: an argument whose type (or pointee type) is
+ *   a Rust `{closure_env#N}`, `{async_fn_env#N}`, `{async_block_env#N}`
+ *   or `{coroutine_env#N}`, or a C++ lambda class; its fields are the
+ *   closure's captured variables;
+ * - a capture: a local of a closure or async body declared, in the same
+ *   file, before the body starts (how rustc describes the upvars it
+ *   reads through the environment);
+ * - otherwise a local.
+ *
+ * The Locals scope keeps lldb-dap's reference and lists only the locals
+ * (and the return value; see ReturnValues.kt). An Arguments scope
+ * (hint `arguments`) before it lists the arguments, and a Captures scope
+ * after it lists the captures and the environments' fields, each scope
+ * only if it has variables. KDAP reads their variables from lldb-dap's
+ * Locals scope, so their children are lldb-dap's. If the frame's
+ * variables can't be read, or lldb-dap lists a different number of
+ * them, the Locals scope lists all of them.
+ *
+ * ## Visibility
+ *
+ * lldb-dap lists the variables in scope at the frame's PC: those of the
+ * lexical blocks (`DW_TAG_lexical_block` ranges) around it, less those
+ * whose `DW_AT_start_scope` it has not reached, as LLDB reads them.
+ * rustc emits no `DW_AT_start_scope`, so a Rust local is in scope from
+ * the start of its block and shows whatever its stack slot held before
+ * its `let` ran. KDAP leaves out locals declared on a later line of the
+ * frame's file than the one it is at ([LocalKind.Unborn]).
+ *
+ * Shadowed bindings (`let x = x.trim();`) are separate variables with
+ * the same name. Each of them is shown, named after its declaration
+ * like lldb-dap names duplicates, e.g. `x @ main.rs:12`; only the
+ * variables left after the unborn ones count as duplicates.
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.LocalScopes")
+
+/** First `variablesReference` of Arguments and Captures scopes, between the chunk references and the Statics scopes. */
+internal const val FIRST_LOCAL_SCOPE_REFERENCE = 5 shl 28
+
+/** Name of lldb-dap's scope that KDAP splits. */
+private const val LLDB_DAP_LOCALS = "Locals"
+
+/** What a variable of lldb-dap's Locals scope is (see the file header). */
+enum class LocalKind { Argument, Environment, Capture, Local, Unborn }
+
+/**
+ * A variable of lldb-dap's Locals scope: its [kind], the [name] to show
+ * if KDAP has one, and whether its value is [unavailable] or why it is
+ * partly optimized out ([optimizedOut]; see OptimizedOut.kt).
+ */
+data class LocalVariable(
+    val kind: LocalKind,
+    val name: String? = null,
+    val unavailable: Boolean = false,
+    val optimizedOut: String? = null,
+)
+
+/** A scope KDAP builds from lldb-dap's Locals scope. */
+enum class LocalScopeKind(val scopeName: String, val presentationHint: String?) {
+    Arguments("Arguments", "arguments"),
+    Locals("Locals", "locals"),
+    Captures("Captures", null),
+}
+
+/** The part [kind] of the frame's lldb-dap Locals scope [localsReference]. */
+data class LocalScope(val localsReference: Int, val kind: LocalScopeKind)
+
+/** The scope that shows a variable of [kind], if any. */
+private val LocalKind.scope: LocalScopeKind?
+    get() = when (this) {
+        LocalKind.Argument -> LocalScopeKind.Arguments
+        LocalKind.Environment, LocalKind.Capture -> LocalScopeKind.Captures
+        LocalKind.Local -> LocalScopeKind.Locals
+        LocalKind.Unborn -> null
+    }
+
+/** Parses the variables printed by `_kdap_locals`, or `null` if it printed an error. */
+internal fun parseLocals(json: String): List<LocalVariable>? {
+    val result = JSONObject(json)
+    val variables = result.optJSONArray("variables") ?: return null
+    return (0 until variables.length()).map { i ->
+        val variable = variables.getJSONObject(i)
+        LocalVariable(
+            kind = LocalKind.valueOf(variable.getString("kind")),
+            name = variable.optString("name", null)?.ifEmpty { null },
+            unavailable = variable.optBoolean("unavailable", false),
+            optimizedOut = variable.optString("optimizedOut", null),
+        )
+    }
+}
+
+/**
+ * The variables of lldb-dap's Locals scope that the scope [kind] shows,
+ * under KDAP's names and with optimized-out values marked, given the
+ * frame's [locals] in lldb-dap's order.
+ * The return value entry belongs to Locals. If the variables don't line
+ * up with [locals], Locals shows all of them and the other scopes none.
+ */
+internal fun partitionLocals(variables: List<*>, locals: List<LocalVariable>, kind: LocalScopeKind): List<Any?> {
+    val isReturnValue = { v: Any? -> (v as? Map<*, *>)?.get("name") == LLDB_DAP_RETURN_VALUE }
+    val others = variables.filterNot(isReturnValue)
+    if (others.size != locals.size) return if (kind == LocalScopeKind.Locals) variables else emptyList()
+    val returnValue = if (kind == LocalScopeKind.Locals) variables.filter(isReturnValue) else emptyList()
+    return returnValue + others.withIndex().filter { (i, _) -> locals[i].kind.scope == kind }.map { (i, variable) ->
+        if (variable !is Map<*, *>) return@map variable
+        val name = locals[i].name
+        withAvailability(if (name != null) variable + ("name" to name) else variable, locals[i])
+    }
+}
+
+/**
+ * Replaces lldb-dap's Locals scope in a `scopes` [response] with the
+ * Arguments, Locals, and Captures scopes given its variables [locals];
+ * `null` leaves the response alone.
+ */
+fun DebugSession.withLocalScopes(response: DapResponse, locals: List<LocalVariable>?): DapResponse {
+    if (locals == null || !response.success) return response
+    val scopes = response.body["scopes"] as? List<*> ?: return response
+    val patched = scopes.flatMap { scope ->
+        if (scope !is Map<*, *> || scope["name"] != LLDB_DAP_LOCALS) return@flatMap listOf(scope)
+        val localsReference = (scope["variablesReference"] as? Number)?.toInt()?.takeIf { it > 0 }
+            ?: return@flatMap listOf(scope)
+        localsPartitions[localsReference] = locals
+        val fields = scope.entries.associate { (k, v) -> k.toString() to v }
+            .minus(listOf("namedVariables", "indexedVariables", "presentationHint"))
+        LocalScopeKind.entries.filter { it == LocalScopeKind.Locals || locals.any { v -> v.kind.scope == it } }.map { kind ->
+            val reference = if (kind == LocalScopeKind.Locals) localsReference
+                else localScopes.handleFor(LocalScope(localsReference, kind))
+            fields + listOfNotNull("name" to kind.scopeName, "variablesReference" to reference,
+                kind.presentationHint?.let { "presentationHint" to it })
+        }
+    }
+    return response.copy(body = response.body + ("scopes" to patched))
+}
+
+/** Forgets the Arguments and Captures scopes and the split Locals scopes; they are only valid while stopped. */
+fun DebugSession.forgetLocalScopes() {
+    localScopes.reset()
+    localsPartitions.clear()
+}
+
+/**
+ * Handles `scopes`: sends it on to lldb-dap and relays the response with
+ * the Locals scope split (see the file header) and KDAP's Statics and
+ * Registers scopes. The scopes' frame is recorded for changed values
+ * (see ValueHistory.kt).
+ */
+suspend fun DebugSession.handleScopes(rawJson: String, ctx: AsyncRequestContext) {
+    val requestSeq = JSONObject(rawJson).optInt("seq", 0)
+    val frameId = pendingScopes[requestSeq]
+    val response = ctx.sendRequestToBackendAndAwait(rawJson).copy(seq = 0, requestSeq = requestSeq)
+    val locals = if (response.success && frameId != null) frameLocals(frameId, ctx) else null
+    val patched = withRegistersScope(withStaticsScope(withLocalScopes(response, locals)))
+    onScopesResponse(response)
+    val scopes = patched.body["scopes"] as? List<*>
+    if (patched.success && frameId != null && scopes != null) recordScopeFrames(scopes, frameId, ctx)
+    ctx.sendEventToClient(patched.toJson())
+}
+
+/** The variables of frame [frameId], or `null` if they can't be read. */
+private suspend fun DebugSession.frameLocals(frameId: Int, ctx: AsyncRequestContext): List<LocalVariable>? = try {
+    val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_OPTIMIZED_OUT)})")
+    interpreter.handleCommand("script exec(${pyStr(PY_LOCAL_SCOPES)})")
+    parseLocals(interpreter.handleCommand("script print(_kdap_locals(${framePython(frameId)}))").trim())
+} catch (e: Exception) {
+    log.warning { "LocalScopes: reading the frame's variables failed: ${e.message}" }
+    null
+}
+
+/**
+ * The variables the scope [kind] shows from lldb-dap's Locals
+ * [variables]; for Captures, the captures followed by the fields of
+ * each closure environment.
+ */
+internal suspend fun localScopeVariables(
+    variables: List<*>,
+    locals: List<LocalVariable>,
+    kind: LocalScopeKind,
+    ctx: AsyncRequestContext,
+): List<Any?> {
+    if (kind != LocalScopeKind.Captures) return partitionLocals(variables, locals, kind)
+    fun only(only: LocalKind) =
+        partitionLocals(variables, locals.map { if (it.kind == only) it else it.copy(kind = LocalKind.Local) }, kind)
+    return only(LocalKind.Capture) + only(LocalKind.Environment).flatMap { environment ->
+        val reference = ((environment as? Map<*, *>)?.get("variablesReference") as? Number)?.toInt()?.takeIf { it > 0 }
+            ?: return@flatMap emptyList()
+        val request = JSONObject().put("type", "request").put("command", "variables")
+            .put("arguments", JSONObject().put("variablesReference", reference))
+        val response = ctx.sendRequestToBackendAndAwait(request.toString())
+        (response.body["variables"] as? List<*>)?.takeIf { response.success } ?: emptyList()
+    }
+}
+
+/**
+ * Python side of [DebugSession.handleScopes]. `_kdap_locals` returns
+ * `{"variables": [{"kind", "name", "unavailable", "optimizedOut"}]}`
+ * with the [LocalKind] name of each variable of the frame, the name to
+ * show, and its availability ([PY_OPTIMIZED_OUT]), in the order of
+ * `GetVariables(True, True, False, True)` (lldb-dap's Locals), or
+ * `{"error": ...}`.
+ */
+private val PY_LOCAL_SCOPES = """
+    import collections, json, re
+
+    _KDAP_ENV_TYPE = re.compile(r"\{(closure|async_fn|async_block|coroutine)_env#\d+\}${'$'}|\(lambda at |'lambda\d*'|::\${'$'}_\d+${'$'}")
+    _KDAP_CLOSURE_BODY = re.compile(r"\{(closure|async_fn|async_block|coroutine)#\d+\}|\{\{closure\}\}|\(lambda at |'lambda\d*'|::\${'$'}_\d+::operator\(\)")
+
+    def _kdap_declaration_key(value):
+        decl = value.GetDeclaration()
+        if not decl.IsValid():
+            return (value.GetName(), None, 0, 0)
+        return (value.GetName(), decl.GetFileSpec().fullpath, decl.GetLine(), decl.GetColumn())
+
+    def _kdap_is_environment(value):
+        t = value.GetType()
+        if t.IsPointerType() or t.IsReferenceType():
+            t = t.GetPointeeType()
+        return _KDAP_ENV_TYPE.search(t.GetUnqualifiedType().GetName() or '') is not None
+
+    def _kdap_is_capture(value, body):
+        decl = value.GetDeclaration()
+        return (body is not None and decl.IsValid() and decl.GetFileSpec().fullpath == body.GetFileSpec().fullpath
+                and 0 < decl.GetLine() < body.GetLine())
+
+    def _kdap_is_unborn(value, here):
+        decl = value.GetDeclaration()
+        return (here.IsValid() and decl.IsValid() and decl.GetFileSpec().fullpath == here.GetFileSpec().fullpath
+                and decl.GetLine() > here.GetLine())
+
+    def _kdap_local_kind(value, arguments, body, here):
+        if _kdap_declaration_key(value) in arguments:
+            return 'Environment' if _kdap_is_environment(value) else 'Argument'
+        if _kdap_is_capture(value, body):
+            return 'Capture'
+        return 'Unborn' if _kdap_is_unborn(value, here) else 'Local'
+
+    def _kdap_locals(frame):
+        if not frame.IsValid():
+            return json.dumps({'error': 'The frame is gone.'})
+        arguments = set(_kdap_declaration_key(v) for v in frame.GetVariables(True, False, False, True))
+        body = None
+        if _KDAP_CLOSURE_BODY.search(frame.GetFunctionName() or '') and frame.GetFunction().IsValid():
+            line_entry = frame.GetFunction().GetStartAddress().GetLineEntry()
+            body = line_entry if line_entry.IsValid() else None
+        values = list(frame.GetVariables(True, True, False, True))
+        kinds = [_kdap_local_kind(v, arguments, body, frame.GetLineEntry()) for v in values]
+        counts = collections.Counter(v.GetName() for v, kind in zip(values, kinds) if kind != 'Unborn')
+        locations = _kdap_variable_locations(frame)
+        variables = []
+        for value, kind in zip(values, kinds):
+            name = value.GetName()
+            decl = value.GetDeclaration()
+            if name and counts[name] > 1 and decl.IsValid():
+                name = '%s @ %s:%d' % (name, decl.GetFileSpec().GetFilename(), decl.GetLine())
+            unavailable, note = _kdap_availability(value, locations)
+            variables.append({'kind': kind, 'name': name, 'unavailable': unavailable, 'optimizedOut': note})
+        return json.dumps({'variables': variables})
+""".trimIndent()
@@ -0,0 +1,151 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.sb.SBDebugger
+import com.github.jomof.dap.sb.SBError
+import com.github.jomof.dap.sb.pyStr
+import org.json.JSONObject
+import java.util.logging.Logger
+
+/**
+ * Memory map: the debuggee's mapped memory regions. KDAP extension;
+ * neither CodeLLDB nor lldb-dap lists them, so a failed `readMemory`
+ * gives no hint of whether the address is unmapped, unreadable, or a
+ * guard page.
+ *
+ * The custom `kdap/memoryMap` request ([handleMemoryMap]) returns the
+ * regions, lowest first:
+ *
+ * ```json
+ * {"regions": [{"start": "0x555555554000", "end": "0x555555559000", "size": 20480,
+ *               "permissions": "r--p", "offset": "0x0", "path": "/work/target/debug/app"}]}
+ * ```
+ *
+ * and the Debug Console command `` `mappings `` ([MEMORY_MAP_COMMAND])
+ * prints them as a table ([formatMemoryMap]). For a process on this host
+ * with `/proc/<pid>/maps`, that file is read ([parseProcMaps]), which
+ * has the offset in the backing file and names such as `[heap]` and
+ * `[stack]`; otherwise the regions are LLDB's
+ * (`SBProcess::GetMemoryRegions`), with permissions `rwx` and no offset.
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.MemoryMap")
+
+/** Debug Console meta-command (`` `mappings ``) that prints the memory map. */
+internal const val MEMORY_MAP_COMMAND = "mappings"
+
+/** A mapped memory region, from [start] to [end] (exclusive). */
+data class MemoryMapping(
+    val start: Long,
+    val end: Long,
+    /** `rwxp`-style permissions, as the platform reports them. */
+    val permissions: String,
+    /** Offset of [start] in the backing file, if known. */
+    val offset: Long? = null,
+    /** Backing file or pseudo-path (`[heap]`), if any. */
+    val path: String? = null,
+) {
+    fun toMap(): Map<String, Any?> = buildMap {
+        put("start", "0x%x".format(start))
+        put("end", "0x%x".format(end))
+        put("size", end - start)
+        put("permissions", permissions)
+        offset?.let { put("offset", "0x%x".format(it)) }
+        path?.let { put("path", it) }
+    }
+}
+
+/** Parses the lines of a `/proc/<pid>/maps` file; lines that don't parse are skipped. */
+internal fun parseProcMaps(text: String): List<MemoryMapping> = text.lines().mapNotNull { line ->
+    val fields = line.trim().split(Regex("\\s+"), limit = 6)
+    val range = fields[0].split('-')
+    if (fields.size < 5 || range.size != 2) return@mapNotNull null
+    MemoryMapping(
+        start = range[0].toULongOrNull(16)?.toLong() ?: return@mapNotNull null,
+        end = range[1].toULongOrNull(16)?.toLong() ?: return@mapNotNull null,
+        permissions = fields[1],
+        offset = fields[2].toULongOrNull(16)?.toLong(),
+        path = fields.getOrNull(5)?.ifEmpty { null },
+    )
+}
+
+/** The memory map as a console table: range, permissions, offset, and path. */
+internal fun formatMemoryMap(mappings: List<MemoryMapping>): String = buildString {
+    val width = mappings.maxOfOrNull { "%x".format(it.end).length }?.coerceAtLeast(8) ?: 8
+    append("Start".padEnd(width + 2)).append("  ").append("End".padEnd(width + 2))
+        .append("  Perm  ").append("Offset".padEnd(10)).append("  Path\n")
+    for (mapping in mappings) {
+        append("0x").append("%x".format(mapping.start).padStart(width, '0')).append("  ")
+        append("0x").append("%x".format(mapping.end).padStart(width, '0')).append("  ")
+        append(mapping.permissions.padEnd(4)).append("  ")
+        append((mapping.offset?.let { "0x%08x".format(it) } ?: "").padEnd(10))
+        mapping.path?.let { append("  ").append(it) }
+        append('\n')
+    }
+}
+
+/** The selected process's memory map (see the file header). */
+internal suspend fun memoryMap(debugger: SBDebugger): List<MemoryMapping> {
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_MEMORY_MAP)})")
+    val result = JSONObject(interpreter.handleCommand("script print(_kdap_memory_map())").trim())
+    result.optString("error", null)?.let { throw SBError(it) }
+    result.optString("maps", null)?.let { return parseProcMaps(it) }
+    val regions = result.getJSONArray("regions")
+    return (0 until regions.length()).map { i ->
+        val region = regions.getJSONObject(i)
+        MemoryMapping(
+            start = region.getLong("start"),
+            end = region.getLong("end"),
+            permissions = region.getString("permissions"),
+            path = region.optString("name", null),
+        )
+    }
+}
+
+/** Handles `kdap/memoryMap` (see the file header). */
+suspend fun DebugSession.handleMemoryMap(rawJson: String, ctx: AsyncRequestContext) {
+    val requestSeq = JSONObject(rawJson).optInt("seq", 0)
+    try {
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        sendSuccessResponse(ctx, requestSeq, "kdap/memoryMap",
+            mapOf("regions" to memoryMap(debugger).map { it.toMap() }))
+    } catch (e: Exception) {
+        log.warning { "MemoryMap: kdap/memoryMap failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "kdap/memoryMap", e.message ?: "Reading the memory map failed")
+    }
+}
+
+/**
+ * Python side of [memoryMap]: the text of `/proc/<pid>/maps` for a
+ * process on this host, else LLDB's memory regions.
+ */
+private val PY_MEMORY_MAP = """
+    import json, os
+
+    def _kdap_memory_map():
+        target = lldb.debugger.GetSelectedTarget()
+        process = target.GetProcess()
+        if not process.IsValid():
+            return json.dumps({'error': 'There is no process.'})
+        maps = '/proc/%d/maps' % process.GetProcessID()
+        if target.GetPlatform().IsHost() and os.path.exists(maps):
+            try:
+                with open(maps) as f:
+                    return json.dumps({'maps': f.read()})
+            except OSError:
+                pass
+        regions = process.GetMemoryRegions()
+        result = []
+        for i in range(regions.GetSize()):
+            info = lldb.SBMemoryRegionInfo()
+            if not regions.GetMemoryRegionAtIndex(i, info) or not info.IsMapped():
+                continue
+            region = {'start': info.GetRegionBase(), 'end': info.GetRegionEnd(),
+                      'permissions': ('r' if info.IsReadable() else '-') + ('w' if info.IsWritable() else '-') +
+                                     ('x' if info.IsExecutable() else '-')}
+            if info.GetName():
+                region['name'] = info.GetName()
+            result.append(region)
+        return json.dumps({'regions': result})
+""".trimIndent()
@@ -0,0 +1,171 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.SearchMemoryRequest
+import com.github.jomof.dap.sb.SBError
+import com.github.jomof.dap.sb.pyStr
+import kotlinx.coroutines.currentCoroutineContext
+import kotlinx.coroutines.ensureActive
+import org.json.JSONArray
+import org.json.JSONObject
+import java.util.logging.Logger
+
+/**
+ * Memory search: finding a byte pattern or string anywhere in the
+ * debuggee's memory. KDAP extension; neither CodeLLDB nor lldb-dap
+ * searches memory (LLDB's `memory find` needs an address range).
+ *
+ * The custom `kdap/searchMemory` request gives either `bytes`, in hex
+ * (`"de ad be ef"`, `"0xdeadbeef"`), or a `string`, searched for as its
+ * UTF-8 bytes ([searchPattern]), and optionally `maxResults`
+ * ([DEFAULT_SEARCH_RESULTS] unless given, at most [MAX_SEARCH_RESULTS]).
+ * [handleSearchMemory] lists the process's readable memory regions
+ * (`SBProcess::GetMemoryRegions`) and searches each in chunks of
+ * [SEARCH_CHUNK_SIZE] ([searchChunks]), one SB API call per chunk, so a
+ * `cancel` stops the search between chunks; while it runs, its progress
+ * is reported (see Progress.kt). Chunks overlap by the pattern's length,
+ * so matches across a chunk boundary are found. The response lists each
+ * match, lowest address first, with the region it is in:
+ *
+ * ```json
+ * {"matches": [{"address": "0x5555555592a0", "memoryReference": "0x5555555592a0",
+ *               "region": {"start": "0x555555559000", "end": "0x55555557a000",
+ *                          "permissions": "rw-", "name": "[heap]"}}],
+ *  "truncated": false}
+ * ```
+ *
+ * `truncated` says the search stopped at `maxResults`. Knowing whether a
+ * buffer is on the heap, on a thread's stack, or in a module's data, and
+ * at which address, is usually what it takes to find the structure that
+ * owns it. The process must be stopped.
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.MemorySearch")
+
+/** Matches returned when the request gives no `maxResults`. */
+internal const val DEFAULT_SEARCH_RESULTS = 100
+
+/** Most matches one request can return. */
+internal const val MAX_SEARCH_RESULTS = 10_000
+
+/** Bytes of a region searched per SB API call. */
+internal const val SEARCH_CHUNK_SIZE = 1uL shl 20
+
+/**
+ * The chunks, as address and size, in which a search for a pattern of
+ * [patternLength] bytes reads the region from [start] to [end]: one every
+ * [SEARCH_CHUNK_SIZE] bytes, each read [patternLength] - 1 bytes further
+ * (within the region) so a match that starts in it is found whole.
+ */
+internal fun searchChunks(start: ULong, end: ULong, patternLength: Int): Sequence<Pair<ULong, ULong>> =
+    generateSequence(start) { it + SEARCH_CHUNK_SIZE }
+        .takeWhile { it < end }
+        .map { address -> address to minOf(SEARCH_CHUNK_SIZE + patternLength.toULong() - 1uL, end - address) }
+
+/**
+ * The bytes [request] searches for. Throws [SBError] unless it gives
+ * exactly one of `bytes` and `string`, non-empty, and `bytes` is an even
+ * number of hex digits (spaces and a `0x` prefix allowed).
+ */
+internal fun searchPattern(request: SearchMemoryRequest): ByteArray {
+    val bytes = request.bytes
+    val string = request.string
+    if ((bytes == null) == (string == null)) throw SBError("Give one of \"bytes\" and \"string\".")
+    if (string != null) {
+        if (string.isEmpty()) throw SBError("\"string\" is empty.")
+        return string.toByteArray(Charsets.UTF_8)
+    }
+    val hex = bytes!!.replace(" ", "").removePrefix("0x").removePrefix("0X")
+    if (hex.isEmpty() || hex.length % 2 != 0 || !hex.all { it.isDigit() || it.lowercaseChar() in 'a'..'f' }) {
+        throw SBError("\"bytes\" must be hex, such as \"de ad be ef\".")
+    }
+    return ByteArray(hex.length / 2) { i -> hex.substring(2 * i, 2 * i + 2).toInt(16).toByte() }
+}
+
+/** Handles `kdap/searchMemory` (see the file header). */
+suspend fun DebugSession.handleSearchMemory(rawJson: String, ctx: AsyncRequestContext) {
+    val requestSeq = JSONObject(rawJson).optInt("seq", 0)
+    try {
+        val request = DapMessage.parse(rawJson) as SearchMemoryRequest
+        val pattern = searchPattern(request)
+        val maxResults = (request.maxResults ?: DEFAULT_SEARCH_RESULTS).coerceIn(1, MAX_SEARCH_RESULTS)
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val interpreter = debugger.commandInterpreter()
+        interpreter.handleCommand("script exec(${pyStr(PY_MEMORY_SEARCH)})")
+        val listing = JSONObject(interpreter.handleCommand("script print(_kdap_search_regions())").trim())
+        listing.optString("error", null)?.let { throw SBError(it) }
+
+        val hex = pyStr(pattern.joinToString("") { "%02x".format(it) })
+        val regions = listing.getJSONArray("regions")
+        val matches = mutableListOf<Map<String, Any?>>()
+        search@ for (i in 0 until regions.length()) {
+            val region = regions.getJSONObject(i)
+            val start = region.getString("start").removePrefix("0x").toULong(16)
+            val end = region.getString("end").removePrefix("0x").toULong(16)
+            val info = DapMessage.jsonObjectToMap(region)
+            for ((address, size) in searchChunks(start, end, pattern.size)) {
+                currentCoroutineContext().ensureActive()
+                val found = JSONArray(interpreter.handleCommand(
+                    "script print(_kdap_search_chunk($address, $size, $hex, ${maxResults - matches.size}))").trim())
+                for (j in 0 until found.length()) {
+                    val match = found.getString(j)
+                    matches += mapOf("address" to match, "memoryReference" to match, "region" to info)
+                }
+                if (matches.size >= maxResults) break@search
+            }
+        }
+        sendSuccessResponse(ctx, requestSeq, "kdap/searchMemory",
+            mapOf("matches" to matches, "truncated" to (matches.size >= maxResults)))
+    } catch (e: Exception) {
+        log.warning { "MemorySearch: kdap/searchMemory failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "kdap/searchMemory", e.message ?: "Searching memory failed")
+    }
+}
+
+/**
+ * Python side of [handleSearchMemory]. `_kdap_search_regions` lists the
+ * readable regions, or an `error`; `_kdap_search_chunk` returns the
+ * addresses, at most `limit`, at which the pattern starts in one chunk.
+ * Chunks that can't be read, such as guard pages inside a readable
+ * region, have no matches.
+ */
+private val PY_MEMORY_SEARCH = """
+    import json
+
+    def _kdap_search_region(info):
+        permissions = ('r' if info.IsReadable() else '-') + ('w' if info.IsWritable() else '-') + \
+            ('x' if info.IsExecutable() else '-')
+        region = {'start': '0x%x' % info.GetRegionBase(), 'end': '0x%x' % info.GetRegionEnd(),
+                  'permissions': permissions}
+        if info.GetName():
+            region['name'] = info.GetName()
+        return region
+
+    def _kdap_search_regions():
+        process = lldb.debugger.GetSelectedTarget().GetProcess()
+        if not process.IsValid():
+            return json.dumps({'error': 'There is no process.'})
+        if process.GetState() != lldb.eStateStopped:
+            return json.dumps({'error': 'The process must be stopped to search its memory.'})
+        regions = process.GetMemoryRegions()
+        readable = []
+        for i in range(regions.GetSize()):
+            info = lldb.SBMemoryRegionInfo()
+            if regions.GetMemoryRegionAtIndex(i, info) and info.IsReadable():
+                readable.append(_kdap_search_region(info))
+        return json.dumps({'regions': readable})
+
+    def _kdap_search_chunk(address, size, pattern_hex, limit):
+        process = lldb.debugger.GetSelectedTarget().GetProcess()
+        pattern = bytes.fromhex(pattern_hex)
+        error = lldb.SBError()
+        data = process.ReadMemory(address, size, error)
+        found = []
+        if error.Success() and data:
+            offset = data.find(pattern)
+            while offset >= 0 and len(found) < limit:
+                found.append('0x%x' % (address + offset))
+                offset = data.find(pattern, offset + 1)
+        return json.dumps(found)
+""".trimIndent()
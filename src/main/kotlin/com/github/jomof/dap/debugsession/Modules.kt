@@ -0,0 +1,251 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.kdapMarker
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.messages.ModuleEvent
+import com.github.jomof.dap.messages.ModulesRequest
+import com.github.jomof.dap.messages.OutputEvent
+import com.github.jomof.dap.sb.SBDebugger
+import com.github.jomof.dap.sb.createDebugger
+import com.github.jomof.dap.sb.pyStr
+import com.github.jomof.dap.sb.watched
+import org.json.JSONArray
+import org.json.JSONObject
+import java.util.logging.Logger
+
+/**
+ * Modules and loaded sources: `module` events, the `modules` request,
+ * and the `loadedSources` request.
+ *
+ * lldb-dap tracks the modules of the target its own `launch` or
+ * `attach` created. KDAP creates the target, so lldb-dap's Modules view
+ * stays empty, and KDAP reports the target's modules instead. A Python
+ * listener on the target's broadcaster ([initModuleEvents]) sees modules load, unload,
+ * and gain symbols, and writes each change to the debugger's output
+ * stream prefixed with [MODULE_EVENT_MARKER], like the KDAP pty's
+ * output; [splitModuleEvents] turns those chunks back into `module`
+ * events with reason `new`, `changed`, or `removed`. Any `module` event
+ * lldb-dap sends itself is dropped.
+ *
+ * Each module has:
+ * - `id` — the module's build ID, or its path when it has none
+ * - `name`, `path`
+ * - `addressRange` — the load address of the object file's header,
+ *   once the module is loaded
+ * - `symbolStatus` — whether LLDB found debug info (compile units),
+ *   only a symbol table, or nothing; a breakpoint can only bind in a
+ *   module with debug info for its file
+ * - `symbolFilePath` — the file the debug info came from, when it is not
+ *   the module itself
+ * - `buildId` — the GNU build ID or Mach-O UUID, in lowercase hex
+ *
+ * `loadedSources` lists the source files of the target's compile units,
+ * with their line tables' other files that exist on disk (Rust module
+ * files, C headers). `loadedSource` events are not sent.
+ *
+ * ## CodeLLDB method mapping
+ *
+ * | CodeLLDB (debug_session.rs)       | KDAP (this file)                   |
+ * |-----------------------------------|------------------------------------|
+ * | `handle_target_event` (modules)   | [initModuleEvents]                 |
+ * | `make_module_detail`              | `_kdap_module_info` (Python)       |
+ * | —                                 | [DebugSession.handleModules]       |
+ * | —                                 | [DebugSession.handleLoadedSources] |
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.Modules")
+
+/** Prefix on module changes written by the module listener (see [kdapMarker]). */
+internal val MODULE_EVENT_MARKER = kdapMarker("module")
+
+/** Advertises `supportsLoadedSourcesRequest` in a successful `initialize` response. */
+fun addLoadedSourcesCapability(response: DapResponse): DapResponse =
+    response.copy(body = response.body + ("supportsLoadedSourcesRequest" to true))
+
+/**
+ * Starts the module listener on the selected target. It first reports
+ * the modules the target already has as `new`.
+ */
+internal suspend fun initModuleEvents(debugger: SBDebugger) {
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_MODULES)})")
+    interpreter.handleCommand("script _kdap_modules_start(${pyStr(MODULE_EVENT_MARKER)})")
+}
+
+/**
+ * Splits debugger [output] carrying [MODULE_EVENT_MARKER] into `module`
+ * events and the other output around them. Each change is a marker,
+ * one JSON object with `reason` and `module`, and a newline; records
+ * that do not parse are skipped.
+ */
+fun splitModuleEvents(output: OutputEvent): List<DapMessage> {
+    val parts = output.output.split(MODULE_EVENT_MARKER)
+    val messages = mutableListOf<DapMessage>()
+    fun other(text: String) {
+        if (text.isNotEmpty()) messages += output.copy(output = text)
+    }
+    other(parts.first())
+    for (part in parts.drop(1)) {
+        val end = part.indexOf('\n').let { if (it < 0) part.length else it }
+        val record = part.substring(0, end)
+        try {
+            val obj = JSONObject(record)
+            messages += ModuleEvent(
+                seq = 0,
+                reason = obj.getString("reason"),
+                module = DapMessage.jsonObjectToMap(obj.getJSONObject("module")),
+            )
+        } catch (e: Exception) {
+            log.warning { "Modules: skipping module change '$record': ${e.message}" }
+        }
+        other(part.substring(minOf(end + 1, part.length)))
+    }
+    return messages
+}
+
+/**
+ * Handles `modules`: lists the selected target's modules (see the file
+ * header), paged by `startModule` and `moduleCount`.
+ */
+suspend fun DebugSession.handleModules(rawJson: String, ctx: AsyncRequestContext) {
+    val request = DapMessage.parse(rawJson) as ModulesRequest
+
+    try {
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val interpreter = debugger.commandInterpreter()
+        interpreter.handleCommand("script exec(${pyStr(PY_MODULES)})")
+        val modules = jsonList(interpreter.handleCommand("script print(_kdap_modules_list())").trim())
+        val start = (request.startModule ?: 0).coerceIn(0, modules.size)
+        val count = request.moduleCount?.takeIf { it > 0 } ?: modules.size
+        sendSuccessResponse(ctx, request.seq, "modules", mapOf(
+            "modules" to modules.drop(start).take(count),
+            "totalModules" to modules.size,
+        ))
+    } catch (e: Exception) {
+        log.warning { "Modules: modules failed: ${e.message}" }
+        sendErrorResponse(ctx, request.seq, "modules", e.message ?: "modules failed")
+    }
+}
+
+/** Handles `loadedSources`: lists the source files of the selected target (see the file header). */
+suspend fun DebugSession.handleLoadedSources(rawJson: String, ctx: AsyncRequestContext) {
+    val requestSeq = JSONObject(rawJson).optInt("seq", 0)
+
+    try {
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val interpreter = debugger.commandInterpreter()
+        interpreter.handleCommand("script exec(${pyStr(PY_MODULES)})")
+        val sources = jsonList(interpreter.handleCommand("script print(_kdap_loaded_sources())").trim())
+        sendSuccessResponse(ctx, requestSeq, "loadedSources", mapOf("sources" to sources))
+    } catch (e: Exception) {
+        log.warning { "Modules: loadedSources failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "loadedSources", e.message ?: "loadedSources failed")
+    }
+}
+
+/** Converts a JSON array of objects printed by [PY_MODULES] to maps. */
+private fun jsonList(json: String): List<Map<String, Any?>> {
+    val array = JSONArray(json)
+    return (0 until array.length()).map { DapMessage.jsonObjectToMap(array.getJSONObject(it)) }
+}
+
+/**
+ * Python side of [initModuleEvents], [handleModules], and
+ * [handleLoadedSources]. The listener thread writes one record per
+ * change, each a single write so records are not interleaved with other
+ * output.
+ */
+private val PY_MODULES = """
+    import json, os, threading
+
+    if '_kdap_modules' not in globals():
+        _kdap_modules = {'known': set(), 'listener': None}
+
+    _KDAP_MODULE_EVENTS = (lldb.SBTarget.eBroadcastBitModulesLoaded
+                           | lldb.SBTarget.eBroadcastBitModulesUnloaded
+                           | lldb.SBTarget.eBroadcastBitSymbolsLoaded)
+
+    def _kdap_module_id(module):
+        uuid = module.GetUUIDString()
+        return uuid if uuid else module.GetFileSpec().fullpath
+
+    def _kdap_module_info(target, module):
+        spec = module.GetFileSpec()
+        info = {'id': _kdap_module_id(module), 'name': spec.GetFilename(), 'path': spec.fullpath}
+        load_address = module.GetObjectFileHeaderAddress().GetLoadAddress(target)
+        if load_address != lldb.LLDB_INVALID_ADDRESS:
+            info['addressRange'] = '0x%x' % load_address
+        if module.GetNumCompileUnits() > 0:
+            info['symbolStatus'] = 'Symbols loaded.'
+            symbol_file = module.GetSymbolFileSpec()
+            if symbol_file.IsValid() and symbol_file.fullpath != spec.fullpath:
+                info['symbolFilePath'] = symbol_file.fullpath
+        elif module.GetNumSymbols() > 0:
+            info['symbolStatus'] = 'No debug info; symbol table only.'
+        else:
+            info['symbolStatus'] = 'Symbols not found.'
+        uuid = module.GetUUIDString()
+        if uuid:
+            info['buildId'] = uuid.replace('-', '').lower()
+        return info
+
+    def _kdap_module_send(target, module, unloaded):
+        module_id = _kdap_module_id(module)
+        if unloaded:
+            if module_id not in _kdap_modules['known']:
+                return
+            _kdap_modules['known'].discard(module_id)
+            reason = 'removed'
+        else:
+            reason = 'changed' if module_id in _kdap_modules['known'] else 'new'
+            _kdap_modules['known'].add(module_id)
+        record = json.dumps({'reason': reason, 'module': _kdap_module_info(target, module)})
+        output = _kdap_modules['output']
+        output.Write((_kdap_modules['marker'] + record + '\n').encode('utf-8'))
+        output.Flush()
+
+    def _kdap_modules_listen(target, listener):
+        event = lldb.SBEvent()
+        while _kdap_modules['listener'] is listener:
+            if not listener.WaitForEvent(1, event):
+                continue
+            unloaded = (event.GetType() & lldb.SBTarget.eBroadcastBitModulesUnloaded) != 0
+            try:
+                for i in range(lldb.SBTarget.GetNumModulesFromEvent(event)):
+                    module = lldb.SBTarget.GetModuleAtIndexFromEvent(i, event)
+                    if module.IsValid():
+                        _kdap_module_send(target, module, unloaded)
+            except Exception:
+                pass
+
+    def _kdap_modules_start(marker):
+        target = lldb.debugger.GetSelectedTarget()
+        listener = lldb.SBListener('kdap.modules')
+        target.GetBroadcaster().AddListener(listener, _KDAP_MODULE_EVENTS)
+        _kdap_modules.update(known=set(), listener=listener, marker=marker,
+                             output=lldb.debugger.GetOutputFile())
+        for module in target.module_iter():
+            _kdap_module_send(target, module, False)
+        threading.Thread(target=_kdap_modules_listen, args=(target, listener), daemon=True).start()
+
+    def _kdap_modules_list():
+        target = lldb.debugger.GetSelectedTarget()
+        return json.dumps([_kdap_module_info(target, module) for module in target.module_iter()])
+
+    def _kdap_loaded_sources():
+        target = lldb.debugger.GetSelectedTarget()
+        sources = {}
+        for module in target.module_iter():
+            for unit in module.compile_unit_iter():
+                main = unit.GetFileSpec().fullpath
+                if main:
+                    sources[main] = True
+                for i in range(unit.GetNumSupportFiles()):
+                    path = unit.GetSupportFileAtIndex(i).fullpath
+                    if path and path not in sources:
+                        sources[path] = os.path.isfile(path)
+        return json.dumps([{'name': os.path.basename(path), 'path': path}
+                           for path, listed in sources.items() if listed])
+""".trimIndent()
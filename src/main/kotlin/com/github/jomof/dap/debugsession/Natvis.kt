@@ -0,0 +1,478 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.messages.OutputEvent
+import com.github.jomof.dap.sb.SBDebugger
+import com.github.jomof.dap.sb.pyStr
+import org.json.JSONArray
+import org.json.JSONObject
+import org.w3c.dom.Element
+import org.xml.sax.ErrorHandler
+import org.xml.sax.SAXParseException
+import java.io.File
+import java.util.logging.Logger
+import javax.xml.XMLConstants
+import javax.xml.parsers.DocumentBuilderFactory
+
+/**
+ * Natvis visualizers (`"visualizers": ["app.natvis", "natvis/"]` on
+ * launch or attach). KDAP extension; CodeLLDB has no counterpart.
+ *
+ * Natvis is the XML format in which Visual Studio describes how to show
+ * user types, and C++ projects coming from MSVC often have large files
+ * of it. KDAP reads each file (or each `*.natvis` file in a directory)
+ * into [NatvisType]s ([parseNatvis]) and installs one Python summary and
+ * one synthetic-children provider in the [NATVIS_CATEGORY] category,
+ * matched by recognizer functions that look a type's name up among the
+ * loaded `<Type Name="...">` patterns ([natvisTypePattern]). Like the
+ * Rust formatters (Formatters.kt) the category is registered before
+ * `initCommands`.
+ *
+ * ## Supported elements
+ *
+ * | Element                                | Shown as                               |
+ * |----------------------------------------|----------------------------------------|
+ * | `<DisplayString>`                      | the summary; `{expr}` is replaced by the value of `expr`, `{{` and `}}` are braces |
+ * | `<Item Name="n">expr</Item>`           | a child `n`                            |
+ * | `<ArrayItems>` (`Size`, `ValuePointer`) | children `[0]`, `[1]`, … read from memory |
+ * | `<IndexListItems>` (`Size`, `ValueNode` with `$i`) | children `[0]`, `[1]`, …   |
+ * | `<LinkedListItems>` (`Size`, `HeadPointer`, `NextPointer`, `ValueNode`) | children `[0]`, `[1]`, … |
+ * | `<ExpandedItem>expr</ExpandedItem>`    | the children of `expr`                 |
+ *
+ * `Condition` attributes choose among `DisplayString`s and skip items;
+ * `AlternativeType` adds names to a type. `*` in a type name matches a
+ * template argument, which expressions refer to as `$T1`, `$T2`, ….
+ * Format specifiers after a comma (`{len,x}`) are honored for `x`, `X`,
+ * `d`, and the string forms (`s`, `sb`, `s8`, `su`, …), and ignored
+ * otherwise. `TreeItems`, `CustomListItems`, `Synthetic`, `Intrinsic`,
+ * `UIVisualizer`, and `Priority` are ignored.
+ *
+ * Expressions are evaluated against the value: a member path (`_Myfirst`,
+ * `_Mypair._Myval2`, `this->size`) is read directly, anything else is
+ * evaluated by LLDB with the value as `this`. Items whose expressions
+ * fail are left out, and a `DisplayString` with one that fails shows
+ * `???` in its place. At most [NATVIS_MAX_ITEMS] children are shown per
+ * item.
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.Natvis")
+
+/** LLDB type category holding KDAP's Natvis providers. */
+internal const val NATVIS_CATEGORY = "kdap-natvis"
+
+/** Most children one `ArrayItems`, `IndexListItems`, or `LinkedListItems` shows. */
+internal const val NATVIS_MAX_ITEMS = 10_000
+
+/** One `<Type>` of a Natvis file: the type [names] it applies to, its summaries, and its children. */
+internal data class NatvisType(
+    val names: List<String>,
+    val displayStrings: List<NatvisDisplayString>,
+    /** The `<Expand>` items, or null if it has none, so the value keeps its own children. */
+    val expand: List<NatvisItem>?,
+)
+
+/** A `<DisplayString>`, used if its [condition] holds. */
+internal data class NatvisDisplayString(val text: String, val condition: String? = null)
+
+/** An item of an `<Expand>` (see the file header). */
+internal sealed class NatvisItem {
+    abstract val condition: String?
+
+    data class Item(val name: String, val expr: String, override val condition: String? = null) : NatvisItem()
+
+    data class ArrayItems(val size: String, val valuePointer: String, override val condition: String? = null) : NatvisItem()
+
+    data class IndexListItems(val size: String, val valueNode: String, override val condition: String? = null) : NatvisItem()
+
+    data class LinkedListItems(
+        val size: String?,
+        val headPointer: String,
+        val nextPointer: String,
+        val valueNode: String,
+        override val condition: String? = null,
+    ) : NatvisItem()
+
+    data class ExpandedItem(val expr: String, override val condition: String? = null) : NatvisItem()
+}
+
+/**
+ * The `<Type>`s of the Natvis document [xml]. Throws
+ * [IllegalArgumentException] if it is not well-formed XML or an element
+ * lacks what it needs. Document type declarations are refused.
+ */
+internal fun parseNatvis(xml: String): List<NatvisType> {
+    val factory = DocumentBuilderFactory.newInstance().apply {
+        setFeature("http://apache.org/xml/features/disallow-doctype-decl", true)
+        setFeature(XMLConstants.FEATURE_SECURE_PROCESSING, true)
+        isExpandEntityReferences = false
+    }
+    val document = try {
+        factory.newDocumentBuilder().apply { setErrorHandler(THROWING_ERROR_HANDLER) }.parse(xml.byteInputStream())
+    } catch (e: Exception) {
+        throw IllegalArgumentException("not a Natvis file: ${e.message}")
+    }
+    val root = document.documentElement
+    require(root.localTag == "AutoVisualizer") { "not a Natvis file: root element is <${root.localTag}>" }
+    return root.children("Type").map { type ->
+        val name = type.getAttribute("Name").ifEmpty { throw IllegalArgumentException("<Type> without a Name") }
+        val alternatives = type.children("AlternativeType").mapNotNull { it.getAttribute("Name").ifEmpty { null } }
+        NatvisType(
+            names = listOf(name) + alternatives,
+            displayStrings = type.children("DisplayString").map {
+                NatvisDisplayString(it.textContent.trim(), it.condition)
+            },
+            expand = type.children("Expand").firstOrNull()?.let { expand ->
+                expand.childElements().mapNotNull { parseNatvisItem(it, name) }
+            },
+        )
+    }
+}
+
+/** Makes parse errors exceptions, without the parser's default printing them. */
+private val THROWING_ERROR_HANDLER = object : ErrorHandler {
+    override fun warning(exception: SAXParseException) {}
+    override fun error(exception: SAXParseException) = throw exception
+    override fun fatalError(exception: SAXParseException) = throw exception
+}
+
+private fun parseNatvisItem(element: Element, typeName: String): NatvisItem? {
+    fun field(tag: String): String = element.children(tag).firstOrNull()?.textContent?.trim()?.ifEmpty { null }
+        ?: throw IllegalArgumentException("<${element.localTag}> of $typeName without <$tag>")
+    return when (element.localTag) {
+        "Item" -> NatvisItem.Item(
+            element.getAttribute("Name").ifEmpty { throw IllegalArgumentException("<Item> of $typeName without a Name") },
+            element.textContent.trim(),
+            element.condition,
+        )
+        "ArrayItems" -> NatvisItem.ArrayItems(field("Size"), field("ValuePointer"), element.condition)
+        "IndexListItems" -> NatvisItem.IndexListItems(field("Size"), field("ValueNode"), element.condition)
+        "LinkedListItems" -> NatvisItem.LinkedListItems(
+            element.children("Size").firstOrNull()?.textContent?.trim()?.ifEmpty { null },
+            field("HeadPointer"),
+            field("NextPointer"),
+            field("ValueNode"),
+            element.condition,
+        )
+        "ExpandedItem" -> NatvisItem.ExpandedItem(element.textContent.trim(), element.condition)
+        else -> null
+    }
+}
+
+private val Element.localTag: String get() = tagName.substringAfter(':')
+
+private val Element.condition: String? get() = getAttribute("Condition").ifEmpty { null }
+
+private fun Element.childElements(): List<Element> =
+    (0 until childNodes.length).mapNotNull { childNodes.item(it) as? Element }
+
+private fun Element.children(tag: String): List<Element> = childElements().filter { it.localTag == tag }
+
+/**
+ * The Python regular expression matching the LLDB type names a Natvis
+ * type [name] stands for: anchored, with each `*` a (lazy) group for a
+ * template argument and whitespace allowed around punctuation, so
+ * `std::vector<*>` matches `std::vector<int, std::allocator<int> >`.
+ */
+internal fun natvisTypePattern(name: String): String = buildString {
+    append('^')
+    for (c in name.filterNot { it.isWhitespace() }) {
+        when {
+            c == '*' -> append("(.+?)")
+            c.isLetterOrDigit() || c == '_' -> append(c)
+            else -> append("\\s*\\").append(c).append("\\s*")
+        }
+    }
+    append('$')
+}
+
+/** [types] as the JSON the Python providers load: one entry per type name. */
+internal fun natvisJson(types: List<NatvisType>): JSONArray = JSONArray().also { array ->
+    for (type in types) {
+        for (name in type.names) {
+            array.put(JSONObject()
+                .put("pattern", natvisTypePattern(name))
+                .put("displayStrings", JSONArray(type.displayStrings.map { d ->
+                    JSONObject().put("text", d.text).put("condition", d.condition ?: JSONObject.NULL)
+                }))
+                .put("expand", type.expand?.let { items -> JSONArray(items.map { natvisItemJson(it) }) } ?: JSONObject.NULL))
+        }
+    }
+}
+
+private fun natvisItemJson(item: NatvisItem): JSONObject = JSONObject().apply {
+    put("condition", item.condition ?: JSONObject.NULL)
+    when (item) {
+        is NatvisItem.Item -> put("kind", "item").put("name", item.name).put("expr", item.expr)
+        is NatvisItem.ArrayItems -> put("kind", "array").put("size", item.size).put("valuePointer", item.valuePointer)
+        is NatvisItem.IndexListItems -> put("kind", "index").put("size", item.size).put("valueNode", item.valueNode)
+        is NatvisItem.LinkedListItems -> put("kind", "list").put("size", item.size ?: JSONObject.NULL)
+            .put("headPointer", item.headPointer).put("nextPointer", item.nextPointer).put("valueNode", item.valueNode)
+        is NatvisItem.ExpandedItem -> put("kind", "expanded").put("expr", item.expr)
+    }
+}
+
+/** The Natvis files [paths] name: files as given, and the `*.natvis` files of directories, sorted. */
+internal fun natvisFiles(paths: List<String>): List<File> = paths.flatMap { path ->
+    val file = File(path)
+    if (file.isDirectory) {
+        file.listFiles { f -> f.isFile && f.extension.equals("natvis", ignoreCase = true) }?.sortedBy { it.name }
+            ?: emptyList()
+    } else {
+        listOf(file)
+    }
+}
+
+/** LLDB commands that register the Natvis providers in [NATVIS_CATEGORY] and enable it. */
+internal fun natvisCommands(): List<String> = listOf(
+    "type summary add -w $NATVIS_CATEGORY -F _kdap_natvis_summary --recognizer-function _kdap_natvis_has_summary",
+    "type synthetic add -w $NATVIS_CATEGORY -l _KdapNatvisProvider --recognizer-function _kdap_natvis_has_expand",
+    "type category enable $NATVIS_CATEGORY",
+)
+
+/**
+ * Loads the Natvis files [paths] name and installs their visualizers.
+ * Files that cannot be read are reported in the Debug Console and skipped.
+ */
+internal suspend fun initNatvis(paths: List<String>, debugger: SBDebugger, ctx: AsyncRequestContext) {
+    val types = natvisFiles(paths).flatMap { file ->
+        try {
+            parseNatvis(file.readText())
+        } catch (e: Exception) {
+            log.warning { "Natvis: ${file.path}: ${e.message}" }
+            ctx.sendEventToClient(OutputEvent(seq = 0, category = "stderr", output = "${file.path}: ${e.message}\n").toJson())
+            emptyList()
+        }
+    }
+    if (types.isEmpty()) return
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_NATVIS)})")
+    interpreter.handleCommand("script _kdap_natvis_load(${pyStr(natvisJson(types).toString())})")
+    for (command in natvisCommands()) {
+        interpreter.handleCommand(command)
+    }
+}
+
+/**
+ * Python side of the Natvis providers: the loaded types, in order, each
+ * with its compiled name pattern; the first whose pattern matches a type
+ * name applies to it.
+ */
+private val PY_NATVIS = """
+    import json
+    import re
+
+    _DOLLAR = '$'
+    _kdap_natvis_types = []
+    _kdap_natvis_cache = {}
+    _KDAP_NATVIS_MAX_ITEMS = $NATVIS_MAX_ITEMS
+    _KDAP_NATVIS_PATH = re.compile(r'^[A-Za-z_]\w*(\s*(\.|->)\s*[A-Za-z_]\w*|\[\d+\])*$')
+    _KDAP_NATVIS_SPEC = re.compile(r'^\s*(x|X|h|H|d|s|sa|sb|s8|s8b|su|sub|s32|s32b|bstr|na|nd|nr|nvo|!|\[.*\]|view\(.*\))\s*$')
+
+    def _kdap_natvis_load(text):
+        global _kdap_natvis_types
+        _kdap_natvis_types = [(re.compile(t['pattern']), t) for t in json.loads(text)]
+        _kdap_natvis_cache.clear()
+
+    def _kdap_natvis_lookup(name):
+        if name in _kdap_natvis_cache:
+            return _kdap_natvis_cache[name]
+        found = None
+        for pattern, natvis in _kdap_natvis_types:
+            m = pattern.match(name)
+            if m:
+                found = (natvis, [g.strip() for g in m.groups()])
+                break
+        _kdap_natvis_cache[name] = found
+        return found
+
+    def _kdap_natvis_for_type(sbtype):
+        sbtype = sbtype.GetUnqualifiedType()
+        found = _kdap_natvis_lookup(sbtype.GetName() or '')
+        if found is None:
+            found = _kdap_natvis_lookup(sbtype.GetCanonicalType().GetUnqualifiedType().GetName() or '')
+        return found
+
+    def _kdap_natvis_has_summary(sbtype, internal_dict):
+        found = _kdap_natvis_for_type(sbtype)
+        return found is not None and bool(found[0]['displayStrings'])
+
+    def _kdap_natvis_has_expand(sbtype, internal_dict):
+        found = _kdap_natvis_for_type(sbtype)
+        return found is not None and found[0]['expand'] is not None
+
+    def _kdap_natvis_eval(v, expr, args, index=None):
+        for i, arg in enumerate(args):
+            expr = expr.replace(_DOLLAR + 'T%d' % (i + 1), arg)
+        if index is not None:
+            expr = expr.replace(_DOLLAR + 'i', str(index))
+        expr = expr.strip()
+        if expr == '*this':
+            return v
+        if expr == 'this':
+            return v.AddressOf()
+        if expr.startswith('this->'):
+            expr = expr[len('this->'):].strip()
+        if _KDAP_NATVIS_PATH.match(expr):
+            result = v.GetValueForExpressionPath('.' + re.sub(r'\s+', '', expr))
+            if result.IsValid() and result.GetError().Success():
+                return result
+        result = v.EvaluateExpression(expr)
+        if result.IsValid() and result.GetError().Success():
+            return result
+        return None
+
+    def _kdap_natvis_holds(v, condition, args):
+        if condition is None:
+            return True
+        result = _kdap_natvis_eval(v, condition, args)
+        return result is not None and result.GetValueAsUnsigned() != 0
+
+    def _kdap_natvis_count(v, expr, args):
+        result = _kdap_natvis_eval(v, expr, args)
+        if result is None:
+            return 0
+        return max(0, min(result.GetValueAsSigned(), _KDAP_NATVIS_MAX_ITEMS))
+
+    def _kdap_natvis_show(v, spec):
+        if spec in ('x', 'h'):
+            return '0x%x' % v.GetValueAsUnsigned()
+        if spec in ('X', 'H'):
+            return '0x%X' % v.GetValueAsUnsigned()
+        if spec == 'd':
+            return str(v.GetValueAsSigned())
+        text = v.GetSummary() or v.GetValue() or '{...}'
+        if spec in ('sb', 's8b', 'sub', 's32b') and len(text) >= 2 and text[0] == text[-1] == '"':
+            return text[1:-1]
+        return text
+
+    def _kdap_natvis_format(v, text, args):
+        out = []
+        i = 0
+        while i < len(text):
+            if text.startswith('{{', i) or text.startswith('}}', i):
+                out.append(text[i])
+                i += 2
+                continue
+            if text[i] != '{':
+                out.append(text[i])
+                i += 1
+                continue
+            end = text.find('}', i)
+            if end < 0:
+                out.append(text[i:])
+                break
+            expr, spec = text[i + 1:end], ''
+            head, comma, tail = expr.rpartition(',')
+            if comma and _KDAP_NATVIS_SPEC.match(tail):
+                expr, spec = head, tail.strip()
+            result = _kdap_natvis_eval(v, expr, args)
+            out.append(_kdap_natvis_show(result, spec) if result is not None else '???')
+            i = end + 1
+        return ''.join(out)
+
+    def _kdap_natvis_summary(valobj, internal_dict):
+        found = _kdap_natvis_for_type(valobj.GetType())
+        if found is None:
+            return None
+        natvis, args = found
+        v = valobj.GetNonSyntheticValue()
+        for display in natvis['displayStrings']:
+            if _kdap_natvis_holds(v, display['condition'], args):
+                return _kdap_natvis_format(v, display['text'], args)
+        return None
+
+    class _KdapNatvisProvider:
+        # Children come in segments: ('value', SBValue), ('array', address,
+        # element type, count), or ('index', ValueNode, count); array and
+        # index segments are read when asked for.
+        def __init__(self, valobj, internal_dict):
+            self.valobj = valobj
+            self.segments = []
+        def update(self):
+            self.segments = []
+            found = _kdap_natvis_for_type(self.valobj.GetType())
+            if found is None:
+                return False
+            natvis, self.args = found
+            self.raw = self.valobj.GetNonSyntheticValue()
+            for item in natvis['expand']:
+                try:
+                    if _kdap_natvis_holds(self.raw, item['condition'], self.args):
+                        self.expand(item)
+                except Exception:
+                    pass
+            return False
+        def expand(self, item):
+            v, args, kind = self.raw, self.args, item['kind']
+            if kind == 'item':
+                result = _kdap_natvis_eval(v, item['expr'], args)
+                if result is not None:
+                    self.segments.append(('value', result.Clone(item['name'])))
+            elif kind == 'array':
+                pointer = _kdap_natvis_eval(v, item['valuePointer'], args)
+                if pointer is None:
+                    return
+                pointer_type = pointer.GetType()
+                if pointer_type.IsArrayType():
+                    element, address = pointer_type.GetArrayElementType(), pointer.GetLoadAddress()
+                else:
+                    element, address = pointer_type.GetPointeeType(), pointer.GetValueAsUnsigned()
+                count = _kdap_natvis_count(v, item['size'], args)
+                if address != 0 and element.GetByteSize() > 0 and count > 0:
+                    self.segments.append(('array', address, element, count))
+            elif kind == 'index':
+                count = _kdap_natvis_count(v, item['size'], args)
+                if count > 0:
+                    self.segments.append(('index', item['valueNode'], count))
+            elif kind == 'list':
+                limit = _KDAP_NATVIS_MAX_ITEMS
+                if item['size'] is not None:
+                    limit = _kdap_natvis_count(v, item['size'], args)
+                node = _kdap_natvis_eval(v, item['headPointer'], args)
+                seen = set()
+                n = 0
+                while node is not None and n < limit:
+                    address = node.GetValueAsUnsigned()
+                    if address == 0 or address in seen:
+                        break
+                    seen.add(address)
+                    target = node.Dereference()
+                    value = _kdap_natvis_eval(target, item['valueNode'], args)
+                    if value is None:
+                        break
+                    self.segments.append(('value', value.Clone('[%d]' % n)))
+                    n += 1
+                    node = _kdap_natvis_eval(target, item['nextPointer'], args)
+            elif kind == 'expanded':
+                result = _kdap_natvis_eval(v, item['expr'], args)
+                if result is not None:
+                    for i in range(result.GetNumChildren()):
+                        self.segments.append(('value', result.GetChildAtIndex(i)))
+        def size(self, segment):
+            return 1 if segment[0] == 'value' else segment[-1]
+        def num_children(self):
+            return sum(self.size(s) for s in self.segments)
+        def has_children(self):
+            return bool(self.segments)
+        def get_child_at_index(self, index):
+            for segment in self.segments:
+                size = self.size(segment)
+                if index >= size:
+                    index -= size
+                    continue
+                if segment[0] == 'value':
+                    return segment[1]
+                if segment[0] == 'array':
+                    _, address, element, _ = segment
+                    return self.raw.CreateValueFromAddress(
+                        '[%d]' % index, address + index * element.GetByteSize(), element)
+                value = _kdap_natvis_eval(self.raw, segment[1], self.args, index)
+                return value.Clone('[%d]' % index) if value is not None else None
+            return None
+        def get_child_index(self, name):
+            for i in range(self.num_children()):
+                child = self.get_child_at_index(i)
+                if child is not None and child.GetName() == name:
+                    return i
+            return -1
+""".trimIndent()
@@ -0,0 +1,128 @@
+package com.github.jomof.dap.debugsession
+
+/**
+ * Optimized-out variables: what a frame's variables show when an
+ * optimized build keeps no value for them here. KDAP extension;
+ * CodeLLDB shows LLDB's error or value as it is.
+ *
+ * LLDB evaluates a variable's DWARF location itself: location lists
+ * (the entry for the frame's PC), registers and values split across
+ * several of them with `DW_OP_piece`, `DW_OP_implicit_value` and
+ * `DW_OP_stack_value` constants, and `DW_OP_entry_value` where the
+ * caller's call site says what was passed. What it can't recover shows
+ * badly: a variable with no location at the PC shows an error, and a
+ * piece with no location (a `DW_OP_piece` with nothing before it) is
+ * filled with zeros, so a half-optimized struct looks like real zeros.
+ *
+ * When KDAP splits a frame's Locals (see LocalScopes.kt), it also reads
+ * the location of each variable at the frame's PC from `image lookup
+ * -va` and LLDB's error for it, and rewrites the variables whose value
+ * is not recoverable ([withAvailability]):
+ *
+ * - no value at all: the value is `<optimized out: reason>`, without
+ *   children; the reason is that there is no location at this PC, that
+ *   the caller keeps no copy of the parameter's entry value, that the
+ *   value lives in a register the callee did not save, or that it is a
+ *   pointer to an optimized-out value (`DW_OP_implicit_pointer`, which
+ *   LLDB does not read);
+ * - some pieces missing: the value is kept and followed by
+ *   `<partly optimized out: bytes 0..4>`, naming the byte ranges LLDB
+ *   filled with zeros. Its children are still LLDB's.
+ *
+ * Only the frame's own variables are checked; children and `evaluate`
+ * results are shown as LLDB reads them.
+ */
+
+/**
+ * [variable], a `variables` entry of a frame's Locals, rewritten for
+ * what [local] says of its value: `<optimized out: …>` without
+ * children if it has none, its value marked as partly optimized out if
+ * some pieces are missing, or unchanged.
+ */
+internal fun withAvailability(variable: Map<*, *>, local: LocalVariable): Map<*, *> {
+    val note = local.optimizedOut ?: return variable
+    return if (local.unavailable) {
+        variable + mapOf(
+            "value" to "<optimized out: $note>",
+            "variablesReference" to 0,
+            "presentationHint" to mapOf("attributes" to listOf("readOnly")),
+        ) - listOf("namedVariables", "indexedVariables", "memoryReference")
+    } else {
+        variable + ("value" to "${variable["value"] ?: ""} <partly optimized out: $note>")
+    }
+}
+
+/**
+ * Python side of [withAvailability], used by the Locals split.
+ * `_kdap_variable_locations` maps each `(name, file, line)` of the
+ * frame's variables to its location text at the frame's PC;
+ * `_kdap_availability` returns `(unavailable, note)` for a value, with
+ * `note` `None` when the value is recoverable.
+ */
+internal val PY_OPTIMIZED_OUT = """
+    import os, re
+
+    _KDAP_LOOKUP_VARIABLE = re.compile(r'^\s*Variable: .*?\bname = "([^"]*)".*?\blocation = (.*?)(?:, decl = (\S+?):(\d+)\S*)?\s*${'$'}')
+    _KDAP_LOCATION_RANGE = re.compile(r'^\[0x[0-9a-fA-F]+, 0x[0-9a-fA-F]+\) -> ')
+    _KDAP_PIECE = re.compile(r'^DW_OP_piece (0x[0-9a-fA-F]+|\d+)${'$'}')
+    _KDAP_BIT_PIECE = re.compile(r'^DW_OP_bit_piece (0x[0-9a-fA-F]+|\d+)')
+    _KDAP_UNAVAILABLE = (
+        (re.compile(r'entry_value', re.I), 'the caller keeps no copy of its value at entry'),
+        (re.compile(r'implicit_pointer', re.I), 'it points to an optimized-out value'),
+        (re.compile(r'register', re.I), 'its register was not saved by the callee'),
+        (re.compile(r'not available|no location|optimi[sz]ed', re.I), 'no location at this PC'),
+    )
+
+    def _kdap_variable_locations(frame):
+        target = frame.GetThread().GetProcess().GetTarget()
+        pc = frame.GetPCAddress().GetLoadAddress(target)
+        if frame.GetFrameID() > 0 and pc > 0:
+            pc -= 1
+        result = lldb.SBCommandReturnObject()
+        lldb.debugger.GetCommandInterpreter().HandleCommand('image lookup -va 0x%x' % pc, result)
+        locations = {}
+        for line in (result.GetOutput() or '').splitlines():
+            match = _KDAP_LOOKUP_VARIABLE.match(line)
+            if match:
+                decl_file = os.path.basename(match.group(3)) if match.group(3) else None
+                key = (match.group(1), decl_file, int(match.group(4) or 0))
+                locations[key] = _KDAP_LOCATION_RANGE.sub('', match.group(2).strip())
+        return locations
+
+    def _kdap_missing_pieces(location):
+        missing, offset, located = [], 0, False
+        for op in (op.strip() for op in location.split(',')):
+            match = _KDAP_PIECE.match(op)
+            if match is None and _KDAP_BIT_PIECE.match(op):
+                return [], 0
+            if match:
+                size = int(match.group(1), 0)
+                if not located:
+                    missing.append((offset, offset + size))
+                offset += size
+                located = False
+            elif op:
+                located = True
+        return missing, offset
+
+    def _kdap_availability(value, locations):
+        decl = value.GetDeclaration()
+        key = (value.GetName(), decl.GetFileSpec().GetFilename() if decl.IsValid() else None,
+               decl.GetLine() if decl.IsValid() else 0)
+        location = locations.get(key)
+        error = value.GetError()
+        if error.Fail():
+            message = '%s %s' % (error.GetCString() or '', location or '')
+            for pattern, reason in _KDAP_UNAVAILABLE:
+                if pattern.search(message):
+                    return True, reason
+            return (True, 'no location at this PC') if location in ('', '<empty>') else (False, None)
+        if not location:
+            return False, None
+        missing, size = _kdap_missing_pieces(location)
+        if missing and sum(end - start for start, end in missing) == size:
+            return True, 'no piece of it has a location at this PC'
+        if missing:
+            return False, 'bytes ' + ', '.join('%d..%d' % piece for piece in missing)
+        return False, None
+""".trimIndent()
@@ -0,0 +1,148 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.kdapMarker
+import com.github.jomof.dap.messages.BreakpointEvent
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.OutputEvent
+import com.github.jomof.dap.sb.SBDebugger
+import com.github.jomof.dap.sb.pyStr
+import org.json.JSONObject
+import java.util.logging.Logger
+
+/**
+ * Pending breakpoints: breakpoints in code that is not loaded yet, such
+ * as a library the debuggee `dlopen`s. KDAP extension; lldb-dap reports
+ * them unverified and, for the target KDAP created, never says when they
+ * bind.
+ *
+ * LLDB's POSIX dynamic loader keeps a breakpoint on the dynamic linker's
+ * rendezvous function (`_dl_debug_state`, the `r_brk` of `r_debug`),
+ * which the dynamic linker calls around every change to its list of
+ * loaded objects. At each hit LLDB loads the new modules and resolves
+ * every breakpoint in them, then broadcasts that the target's modules
+ * and breakpoints changed.
+ *
+ * A Python listener on the target's broadcaster
+ * ([initBreakpointEvents]) keeps the state (`verified`, `line`,
+ * `column`) of each of lldb-dap's breakpoints, re-reads it on every
+ * breakpoint and module change, and writes any breakpoint whose state
+ * changed to the debugger's output stream prefixed with
+ * [BREAKPOINT_EVENT_MARKER], as the module listener does (see
+ * Modules.kt). [splitBreakpointEvents] turns those records into
+ * `breakpoint` events with reason `changed`. A breakpoint is verified
+ * once it has a location; its line and column are those of its first
+ * location.
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.PendingBreakpoints")
+
+/** Prefix on breakpoint changes written by the breakpoint listener (see [kdapMarker]). */
+internal val BREAKPOINT_EVENT_MARKER = kdapMarker("breakpoint")
+
+/** Starts the breakpoint listener on the selected target. */
+internal suspend fun initBreakpointEvents(debugger: SBDebugger) {
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_BREAKPOINT_EVENTS)})")
+    interpreter.handleCommand("script _kdap_bp_events_start(${pyStr(BREAKPOINT_EVENT_MARKER)})")
+}
+
+/**
+ * Splits debugger [output] carrying [BREAKPOINT_EVENT_MARKER] into
+ * `breakpoint` events and the other output around them. Each change is a
+ * marker, one JSON breakpoint object, and a newline; records that do not
+ * parse are skipped.
+ */
+fun splitBreakpointEvents(output: OutputEvent): List<DapMessage> {
+    val parts = output.output.split(BREAKPOINT_EVENT_MARKER)
+    val messages = mutableListOf<DapMessage>()
+    fun other(text: String) {
+        if (text.isNotEmpty()) messages += output.copy(output = text)
+    }
+    other(parts.first())
+    for (part in parts.drop(1)) {
+        val end = part.indexOf('\n').let { if (it < 0) part.length else it }
+        val record = part.substring(0, end)
+        try {
+            messages += BreakpointEvent(
+                seq = 0,
+                reason = "changed",
+                breakpoint = DapMessage.jsonObjectToMap(JSONObject(record)),
+            )
+        } catch (e: Exception) {
+            log.warning { "PendingBreakpoints: skipping breakpoint change '$record': ${e.message}" }
+        }
+        other(part.substring(minOf(end + 1, part.length)))
+    }
+    return messages
+}
+
+/**
+ * Python side of [initBreakpointEvents]. Breakpoints are announced only
+ * when their state changes after it was first seen, so breakpoints are
+ * not reported before lldb-dap's `setBreakpoints` response lists them.
+ */
+private val PY_BREAKPOINT_EVENTS = """
+    import json, threading
+
+    if '_kdap_bp_events' not in globals():
+        _kdap_bp_events = {'known': {}, 'listener': None}
+
+    _KDAP_BP_TARGET_EVENTS = (lldb.SBTarget.eBroadcastBitBreakpointChanged
+                              | lldb.SBTarget.eBroadcastBitModulesLoaded
+                              | lldb.SBTarget.eBroadcastBitModulesUnloaded)
+
+    def _kdap_bp_state(bp):
+        state = {'id': bp.GetID(), 'verified': bp.GetNumLocations() > 0}
+        if state['verified']:
+            entry = bp.GetLocationAtIndex(0).GetAddress().GetLineEntry()
+            if entry.IsValid() and entry.GetLine() > 0:
+                state['line'] = entry.GetLine()
+                if entry.GetColumn() > 0:
+                    state['column'] = entry.GetColumn()
+        return state
+
+    def _kdap_bp_scan(target):
+        known = _kdap_bp_events['known']
+        seen = set()
+        for i in range(target.GetNumBreakpoints()):
+            bp = target.GetBreakpointAtIndex(i)
+            if not bp.MatchesName('$DAP_BREAKPOINT_LABEL'):
+                continue
+            seen.add(bp.GetID())
+            state = _kdap_bp_state(bp)
+            previous = known.get(bp.GetID())
+            known[bp.GetID()] = state
+            if previous is not None and previous != state:
+                output = _kdap_bp_events['output']
+                output.Write((_kdap_bp_events['marker'] + json.dumps(state) + '\n').encode('utf-8'))
+                output.Flush()
+        for bp_id in [bp_id for bp_id in known if bp_id not in seen]:
+            del known[bp_id]
+
+    def _kdap_bp_events_listen(target, listener):
+        event = lldb.SBEvent()
+        while _kdap_bp_events['listener'] is listener:
+            if not listener.WaitForEvent(1, event):
+                continue
+            try:
+                if lldb.SBBreakpoint.EventIsBreakpointEvent(event):
+                    kind = lldb.SBBreakpoint.GetBreakpointEventTypeFromEvent(event)
+                    bp = lldb.SBBreakpoint.GetBreakpointFromEvent(event)
+                    if kind == lldb.eBreakpointEventTypeAdded:
+                        _kdap_bp_events['known'][bp.GetID()] = _kdap_bp_state(bp)
+                        continue
+                    if kind == lldb.eBreakpointEventTypeRemoved:
+                        _kdap_bp_events['known'].pop(bp.GetID(), None)
+                        continue
+                _kdap_bp_scan(target)
+            except Exception:
+                pass
+
+    def _kdap_bp_events_start(marker):
+        target = lldb.debugger.GetSelectedTarget()
+        listener = lldb.SBListener('kdap.breakpoints')
+        target.GetBroadcaster().AddListener(listener, _KDAP_BP_TARGET_EVENTS)
+        _kdap_bp_events.update(known={}, listener=listener, marker=marker,
+                               output=lldb.debugger.GetOutputFile())
+        threading.Thread(target=_kdap_bp_events_listen, args=(target, listener), daemon=True).start()
+""".trimIndent()
@@ -0,0 +1,210 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.messages.LaunchRequestArguments
+import com.github.jomof.dap.sb.SBDebugger
+import com.github.jomof.dap.sb.pyStr
+import org.json.JSONObject
+import java.io.File
+
+/**
+ * Checks of a launch's `program` before the process is created. KDAP
+ * extension; CodeLLDB reports whatever error the launch itself fails
+ * with, which for most of these is a generic spawn failure or a session
+ * without breakpoints.
+ *
+ * In order, the program must:
+ *
+ * 1. Exist, be a file, and be executable ([checkProgramFile]).
+ * 2. Load as an LLDB target.
+ * 3. Be built for an architecture the selected platform runs: the host,
+ *    or a remote platform selected in `initCommands`
+ *    ([architectureProblem]).
+ * 4. Have debug info, separate debug info that `DebugInfo.kt` can find,
+ *    or compressed debug sections it can decompress ([debugInfoProblem]).
+ *    `"requireDebugInfo": false` or `"noDebug": true` skips this check.
+ *
+ * A failed check is a [ProgramError], reported as a structured DAP error
+ * that the client shows to the user, with what to do about it.
+ */
+
+/** What a [ProgramError] is about; [id] is the DAP `Message.id`. */
+internal enum class ProgramProblem(val id: Int) {
+    NotFound(5101),
+    NotAFile(5102),
+    NotExecutable(5103),
+    NotLoadable(5104),
+    WrongArchitecture(5105),
+    NoDebugInfo(5106),
+}
+
+/** A launch `program` that failed a check (see the file header). */
+internal class ProgramError(val problem: ProgramProblem, message: String) : Exception(message) {
+    /** The `launch` error response for this problem. */
+    fun toResponse(requestSeq: Int): DapResponse =
+        DapResponse.userError(requestSeq, "launch", problem.id, message.orEmpty())
+}
+
+/**
+ * What [checkProgramTarget] learns about the selected target's main
+ * module and platform.
+ */
+internal data class ProgramFacts(
+    val triple: String,
+    val platformName: String,
+    val platformTriple: String,
+    val compileUnits: Int,
+    val isElf: Boolean,
+    val hasSymbolTable: Boolean,
+    val hasDebugLink: Boolean,
+    val buildId: String,
+    val hasDebugSections: Boolean,
+)
+
+/**
+ * Throws [ProgramError] unless [program] is an existing file that is
+ * executable. On Windows ([windows]) a missing `.exe` suffix is allowed,
+ * as LLDB adds it, and the executable bit is not checked.
+ */
+internal fun checkProgramFile(program: String, windows: Boolean = isWindowsHost()) {
+    val file = File(program).takeIf { it.exists() || !windows } ?: File("$program.exe")
+    when {
+        !file.exists() -> throw ProgramError(ProgramProblem.NotFound,
+            "Program \"$program\" does not exist — build it first, or fix \"program\" in the launch configuration.")
+        !file.isFile -> throw ProgramError(ProgramProblem.NotAFile,
+            "Program \"$program\" is a directory, not an executable file.")
+        !windows && !file.canExecute() -> throw ProgramError(ProgramProblem.NotExecutable,
+            "Program \"$program\" is not executable — run `chmod +x $program`.")
+    }
+}
+
+/**
+ * Checks the selected target, just created from the launch's `program`,
+ * for the architecture and debug info problems of the file header.
+ */
+internal suspend fun checkProgramTarget(args: LaunchRequestArguments, debugger: SBDebugger) {
+    val program = args.program ?: return
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_PROGRAM_FACTS)})")
+    val facts = JSONObject(interpreter.handleCommand("script print(_kdap_program_facts())").trim()).let {
+        ProgramFacts(
+            triple = it.optString("triple"),
+            platformName = it.optString("platformName"),
+            platformTriple = it.optString("platformTriple"),
+            compileUnits = it.optInt("compileUnits"),
+            isElf = it.optBoolean("elf"),
+            hasSymbolTable = it.optBoolean("symtab"),
+            hasDebugLink = it.optBoolean("debuglink"),
+            buildId = it.optString("buildId"),
+            hasDebugSections = it.optBoolean("debugSections"),
+        )
+    }
+    architectureProblem(program, facts)?.let { throw it }
+    if (args.noDebug == true || args.requireDebugInfo == false) return
+    val directories = args.common.debugFileDirectories ?: listOf(DEFAULT_DEBUG_FILE_DIRECTORY)
+    val urls = debuginfodUrls(args.common.debuginfodUrls, System.getenv("DEBUGINFOD_URLS"))
+    val separate = facts.isElf && facts.hasDebugSections || facts.hasDebugLink || facts.buildId.isNotEmpty() &&
+        (urls.isNotEmpty() || directories.any { buildIdDebugFile(it, facts.buildId).isFile })
+    debugInfoProblem(program, facts, separate)?.let { throw it }
+}
+
+/**
+ * The [ProgramError] for a program ([facts]) built for an architecture
+ * its platform cannot run, or `null`. Unknown triples pass.
+ */
+internal fun architectureProblem(program: String, facts: ProgramFacts): ProgramError? {
+    if (architectureRuns(facts.triple, facts.platformTriple)) return null
+    val arch = normalizedArchitecture(facts.triple)
+    val platformArch = normalizedArchitecture(facts.platformTriple)
+    val where = if (facts.platformName == "host") {
+        "this $platformArch host"
+    } else {
+        "the ${facts.platformName} platform ($platformArch)"
+    }
+    return ProgramError(ProgramProblem.WrongArchitecture,
+        "Program \"$program\" is built for $arch, which $where cannot run — build it for ${facts.platformTriple}, " +
+            "or debug it on a $arch machine through a remote platform.")
+}
+
+/**
+ * Whether a platform with target triple [platform] runs programs built
+ * for [program]: the same architecture, 32-bit x86 or ARM on their
+ * 64-bit counterparts, and x86-64 on AArch64 macOS and Windows, which
+ * emulate it.
+ */
+internal fun architectureRuns(program: String, platform: String): Boolean {
+    val arch = normalizedArchitecture(program)
+    val platformArch = normalizedArchitecture(platform)
+    if (arch.isEmpty() || platformArch.isEmpty() || arch == platformArch) return true
+    val emulates = listOf("apple", "darwin", "macos", "windows").any { it in platform }
+    return when (platformArch) {
+        "x86_64" -> arch == "i386"
+        "aarch64" -> arch == "arm" || (arch == "x86_64" && emulates)
+        else -> false
+    }
+}
+
+/** The architecture of a target [triple], with LLDB's aliases merged, or `""` if it has none. */
+internal fun normalizedArchitecture(triple: String): String {
+    val arch = triple.substringBefore('-').lowercase()
+    return when {
+        arch.isEmpty() || arch == "unknown" -> ""
+        arch in listOf("x86_64", "x86_64h", "amd64") -> "x86_64"
+        arch.matches(Regex("i[3-6]86")) -> "i386"
+        arch.startsWith("arm64") || arch == "aarch64" -> "aarch64"
+        arch.startsWith("arm") || arch.startsWith("thumb") -> "arm"
+        else -> arch
+    }
+}
+
+/**
+ * The [ProgramError] for a program ([facts]) without debug info, or
+ * `null`. A program with [separateDebugInfo] passes: its debug info
+ * turns up once `DebugInfo.kt` finds it, or decompresses debug sections
+ * LLDB could not read.
+ */
+internal fun debugInfoProblem(program: String, facts: ProgramFacts, separateDebugInfo: Boolean): ProgramError? {
+    if (facts.compileUnits > 0 || separateDebugInfo) return null
+    val rebuild = "rebuild it with debug info (Cargo: `debug = true` in the profile; C/C++: `-g`)"
+    val optOut = "or set \"requireDebugInfo\": false to debug it without source lines"
+    val message = if (facts.isElf && !facts.hasSymbolTable) {
+        "Program \"$program\" is stripped — $rebuild and without `strip`, $optOut."
+    } else {
+        "Program \"$program\" has no debug info — $rebuild, $optOut."
+    }
+    return ProgramError(ProgramProblem.NoDebugInfo, message)
+}
+
+/** The file that holds the debug info of build ID [buildId] in build-ID tree [directory]. */
+private fun buildIdDebugFile(directory: String, buildId: String): File =
+    File(directory, ".build-id/${buildId.take(2)}/${buildId.drop(2)}.debug")
+
+/**
+ * Python side of [checkProgramTarget]: the [ProgramFacts] of the
+ * selected target's main module and the selected platform, as JSON.
+ */
+private val PY_PROGRAM_FACTS = """
+    import json
+
+    def _kdap_program_facts():
+        target = lldb.debugger.GetSelectedTarget()
+        module = target.GetModuleAtIndex(0)
+        platform = lldb.debugger.GetSelectedPlatform()
+        header = b''
+        try:
+            with open(module.GetFileSpec().fullpath, 'rb') as f:
+                header = f.read(4)
+        except (OSError, TypeError):
+            pass
+        return json.dumps({
+            'triple': target.GetTriple() or '',
+            'platformName': platform.GetName() or '',
+            'platformTriple': platform.GetTriple() or '',
+            'compileUnits': module.GetNumCompileUnits(),
+            'elf': header == b'\x7fELF',
+            'symtab': module.FindSection('.symtab').IsValid(),
+            'debuglink': module.FindSection('.gnu_debuglink').IsValid(),
+            'buildId': module.GetUUIDString().replace('-', '').lower() if module.GetUUIDString() else '',
+            'debugSections': module.FindSection('.debug_info').IsValid() or module.FindSection('.zdebug_info').IsValid(),
+        })
+""".trimIndent()
@@ -0,0 +1,71 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.messages.ProgressEndEvent
+import com.github.jomof.dap.messages.ProgressStartEvent
+import kotlinx.coroutines.NonCancellable
+import kotlinx.coroutines.cancelAndJoin
+import kotlinx.coroutines.coroutineScope
+import kotlinx.coroutines.delay
+import kotlinx.coroutines.launch
+import kotlinx.coroutines.withContext
+
+/**
+ * Progress reporting for slow KDAP requests. This is a KDAP extension;
+ * CodeLLDB reports no progress of its own.
+ *
+ * lldb-dap already turns LLDB's progress reports (symbol loading, DWARF
+ * indexing) into `progressStart`/`progressUpdate`/`progressEnd` events.
+ * KDAP adds progress for the cancellable requests it handles itself
+ * (`variables`, `evaluate`, `breakpointLocations`, `kdap/searchMemory`):
+ * once one has run for [PROGRESS_DELAY_MS], a cancellable
+ * `progressStart` naming the request is sent, and a `progressEnd`
+ * follows when it finishes. Requests that finish sooner report nothing,
+ * so quick expansions don't flicker in the client. Cancelling the
+ * progress cancels the request (see [DapSession.requestProgressId]).
+ */
+
+/** How long a request runs before its progress is reported. */
+internal const val PROGRESS_DELAY_MS = 500L
+
+/**
+ * Runs [block], the handler for the client request [requestSeq], and
+ * reports its progress under [title] if it outlasts [PROGRESS_DELAY_MS]
+ * and the client supports progress reporting. The progress is
+ * [cancellable] if the request is.
+ */
+internal suspend fun <T> DebugSession.withProgress(
+    ctx: AsyncRequestContext,
+    requestSeq: Int,
+    title: String,
+    cancellable: Boolean = true,
+    block: suspend () -> T,
+): T {
+    if (!clientSupportsProgressReporting) return block()
+    val progressId = DapSession.requestProgressId(requestSeq)
+    return coroutineScope {
+        var started = false
+        val start = launch {
+            delay(PROGRESS_DELAY_MS)
+            ctx.sendEventToClient(
+                ProgressStartEvent(
+                    seq = 0,
+                    progressId = progressId,
+                    title = title,
+                    requestId = requestSeq,
+                    cancellable = cancellable,
+                ).toJson()
+            )
+            started = true
+        }
+        try {
+            block()
+        } finally {
+            withContext(NonCancellable) {
+                start.cancelAndJoin()
+                if (started) ctx.sendEventToClient(ProgressEndEvent(seq = 0, progressId = progressId).toJson())
+            }
+        }
+    }
+}
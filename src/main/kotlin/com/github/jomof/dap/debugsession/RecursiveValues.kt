@@ -0,0 +1,77 @@
+package com.github.jomof.dap.debugsession
+
+/**
+ * Recursive values: guards on expanding linked structures. KDAP
+ * extension; CodeLLDB and lldb-dap expand whatever the client asks for.
+ *
+ * A cyclic structure (an `Rc` cycle, a circular or intrusive list, a
+ * parent pointer) expands without end, and a client that expands
+ * everything it is shown never finishes. KDAP records, for each
+ * reference in a relayed `variables` response, its [ValueAncestry]: how
+ * many levels below its scope it is, and the identities of the values on
+ * the way there, each a value's type and `memoryReference`
+ * ([valueIdentity]). In [guardExpansion]:
+ *
+ * - a child whose identity is one of its ancestors' is that value again:
+ *   its value reads [ALREADY_SHOWN] and it can't be expanded;
+ * - a child `_adapterSettings.maxExpansionDepth` levels below its scope
+ *   ([DEFAULT_MAX_EXPANSION_DEPTH] by default; 0 disables the limit)
+ *   keeps its value but can't be expanded.
+ *
+ * Values without a `memoryReference` (registers, values in registers)
+ * are only depth-limited. The Rust formatters' summaries, which contain
+ * their fields' summaries, nest a bounded number of levels (see
+ * `Formatters.kt`), so an enum cycling through an `Rc` summarizes with
+ * `...` instead of recursing in LLDB.
+ */
+
+/** Levels a variable may be expanded below its scope when the launch configuration sets no depth. */
+internal const val DEFAULT_MAX_EXPANSION_DEPTH = 64
+
+/** The value text of a variable whose value is already shown above it. */
+internal const val ALREADY_SHOWN = "(already shown ↑)"
+
+/**
+ * Where a variables reference sits: [depth] levels below its scope,
+ * below the values with [identities] (see [valueIdentity]).
+ */
+data class ValueAncestry(val depth: Int = 0, val identities: Set<String> = emptySet())
+
+/**
+ * A variable's identity, given as its DAP fields: its type and
+ * `memoryReference`, so a struct and its first field, at one address,
+ * differ. `null` if it has no address.
+ */
+internal fun valueIdentity(fields: Map<String, Any?>): String? {
+    val address = fields["memoryReference"] as? String ?: return null
+    return "${fields["type"] as? String ?: ""}@$address"
+}
+
+/**
+ * [variables], children of lldb-dap's [parentReference], with values
+ * already shown above them and values past [DebugSession.maxExpansionDepth]
+ * made unexpandable (see the file header). The references of the others
+ * are recorded with their ancestry.
+ */
+internal fun DebugSession.guardExpansion(variables: List<Any?>, parentReference: Int): List<Any?> {
+    val parent = valueAncestries[parentReference] ?: ValueAncestry()
+    return variables.map { variable ->
+        val fields = (variable as? Map<*, *>)?.entries?.associate { (k, v) -> k.toString() to v }
+            ?: return@map variable
+        val reference = (fields["variablesReference"] as? Number)?.toInt() ?: 0
+        if (reference <= 0) return@map variable
+        val identity = valueIdentity(fields)
+        when {
+            identity != null && identity in parent.identities -> unexpandable(fields) + ("value" to ALREADY_SHOWN)
+            maxExpansionDepth > 0 && parent.depth + 1 >= maxExpansionDepth -> unexpandable(fields)
+            else -> {
+                valueAncestries[reference] = ValueAncestry(parent.depth + 1, parent.identities + listOfNotNull(identity))
+                variable
+            }
+        }
+    }
+}
+
+/** [fields] without children. */
+private fun unexpandable(fields: Map<String, Any?>): Map<String, Any?> =
+    fields - listOf("indexedVariables", "namedVariables") + ("variablesReference" to 0)
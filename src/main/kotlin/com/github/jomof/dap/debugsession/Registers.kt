@@ -0,0 +1,400 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.sb.createDebugger
+import com.github.jomof.dap.sb.pyStr
+import com.github.jomof.dap.sb.watched
+import org.json.JSONArray
+import org.json.JSONObject
+import java.util.logging.Logger
+
+/**
+ * The Registers scope, grouped and with register fields. KDAP extension;
+ * CodeLLDB shows LLDB's register sets as they are.
+ *
+ * lldb-dap's Registers scope lists LLDB's register sets, including every
+ * alias (`eax`, `ax`, `al` next to `rax`), and shows vectors as byte
+ * lists and flags as plain numbers. KDAP replaces it
+ * ([withRegistersScope]) with groups read from the frame's register
+ * context, which holds the unwound values for frames below the top:
+ *
+ * | Group             | Registers                                          |
+ * |-------------------|----------------------------------------------------|
+ * | General Purpose   | LLDB's general purpose set, without aliases or flags |
+ * | FP/SIMD           | floating point and vector sets (`xmm`, `ymm`, `st`, `v`) |
+ * | Flags             | `rflags`/`eflags`, `mxcsr`, `cpsr`, `fpsr`, `fpcr`, `fpscr` |
+ * | Other             | any other set LLDB reports                         |
+ *
+ * Which registers are aliases, flags, or vectors, and the fields of each
+ * flags register, come from per-architecture tables in [PY_REGISTERS]
+ * (x86-64, i386, AArch64, and 32-bit ARM); other targets get LLDB's sets
+ * grouped by name only.
+ *
+ * A flags register's value lists the one-bit flags that are set
+ * (`0x246 [PF ZF IF]`), and it expands into its fields (`IOPL`, `RMode`,
+ * …). A vector register (`xmm`/`ymm`/`zmm`, NEON `v`/`d`/`q`, SVE `z`)
+ * expands into lane sets, `u8x16` through `f64x2`, each listing its
+ * lanes and expanding into them one by one.
+ *
+ * In the topmost frame `setVariable` writes a register (in the forms
+ * LLDB parses, such as `{0x01 0x02 …}` for vectors), a flags field, or a
+ * single lane; registers of other frames are read-only.
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.Registers")
+
+/** First `variablesReference` of Registers scopes and their nodes, between the Statics scopes and values. */
+internal const val FIRST_REGISTERS_REFERENCE = 13 shl 27
+
+/** Name of lldb-dap's scope that KDAP replaces. */
+private const val LLDB_DAP_REGISTERS = "Registers"
+
+/**
+ * A node of a Registers scope in the frame with lldb-dap ID [frameId]:
+ * the scope itself, one of its [group]s, the fields of a [register], or
+ * its [lanes] of one type (`u8`, `f32`, …).
+ */
+data class RegisterNode(
+    val frameId: Int,
+    val group: String? = null,
+    val register: String? = null,
+    val lanes: String? = null,
+)
+
+/**
+ * Replaces lldb-dap's Registers scope in a `scopes` [response] with
+ * KDAP's for the frame the request asked about. Call before
+ * [onScopesResponse], which forgets the request.
+ */
+fun DebugSession.withRegistersScope(response: DapResponse): DapResponse {
+    val frameId = pendingScopes[response.requestSeq] ?: return response
+    if (!response.success) return response
+    val scopes = response.body["scopes"] as? List<*> ?: return response
+    val patched = scopes.map { scope ->
+        if (scope !is Map<*, *> || scope["name"] != LLDB_DAP_REGISTERS) return@map scope
+        scope.entries.associate { (k, v) -> k.toString() to v }
+            .minus(listOf("namedVariables", "indexedVariables"))
+            .plus("variablesReference" to registerReference(RegisterNode(frameId)))
+    }
+    return response.copy(body = response.body + ("scopes" to patched))
+}
+
+/** Forgets the Registers scopes; like lldb-dap's references, they are only valid while stopped. */
+fun DebugSession.forgetRegisterNodes() {
+    registerNodes.reset()
+}
+
+/** The `variablesReference` of [node], handed out on first use. */
+private fun DebugSession.registerReference(node: RegisterNode): Int = registerNodes.handleFor(node)
+
+/**
+ * Responds to a `variables` request for [node]: the groups of a
+ * Registers scope, the registers of a group, a register's fields or lane
+ * sets, or the lanes of a set.
+ */
+internal suspend fun DebugSession.sendRegisters(requestSeq: Int, node: RegisterNode, ctx: AsyncRequestContext) {
+    val frame = framePython(node.frameId)
+    val result = runRegisters(when {
+        node.group == null -> "_kdap_register_groups($frame)"
+        node.register == null -> "_kdap_register_list($frame, ${pyStr(node.group)})"
+        node.lanes == null -> "_kdap_register_fields($frame, ${pyStr(node.register)})"
+        else -> "_kdap_register_lanes($frame, ${pyStr(node.register)}, ${pyStr(node.lanes)})"
+    }, ctx)
+    if (result.has("error")) {
+        sendErrorResponse(ctx, requestSeq, "variables", result.getString("error"))
+        return
+    }
+    val topmost = node.frameId and ((1 shl FRAME_INDEX_BITS) - 1) == 0
+    val variables = result.getJSONArray("variables").map { item ->
+        val entry = item as JSONObject
+        val name = entry.getString("name")
+        val child = when {
+            node.group == null -> RegisterNode(node.frameId, name)
+            node.register == null && entry.optBoolean("fields") -> RegisterNode(node.frameId, node.group, name)
+            node.lanes == null && entry.has("lanes") ->
+                RegisterNode(node.frameId, node.group, node.register, entry.getString("lanes"))
+            else -> null
+        }
+        buildMap {
+            put("name", name)
+            put("value", entry.optString("value"))
+            entry.optString("type").ifEmpty { null }?.let { put("type", it) }
+            put("variablesReference", child?.let { registerReference(it) } ?: 0)
+            when {
+                node.group == null -> put("presentationHint", mapOf("kind" to "virtual"))
+                !topmost || !entry.optBoolean("writable", true) ->
+                    put("presentationHint", mapOf("attributes" to listOf("readOnly")))
+            }
+        }
+    }
+    sendSuccessResponse(ctx, requestSeq, "variables", mapOf("variables" to variables))
+}
+
+/**
+ * Handles `setVariable` in a Registers scope: writes the register [name]
+ * of [node]'s group, the field [name] of [node]'s register, or the lane
+ * [name] of [node]'s lane set, in the topmost frame.
+ */
+internal suspend fun DebugSession.setRegister(
+    requestSeq: Int,
+    node: RegisterNode,
+    name: String,
+    value: String,
+    ctx: AsyncRequestContext,
+) {
+    val frame = framePython(node.frameId)
+    val result = when {
+        node.group == null -> JSONObject().put("error", "'$name' is a group of registers")
+        node.register == null -> runRegisters("_kdap_register_write($frame, ${pyStr(name)}, None, ${pyStr(value)})", ctx)
+        node.lanes == null ->
+            runRegisters("_kdap_register_write($frame, ${pyStr(node.register)}, ${pyStr(name)}, ${pyStr(value)})", ctx)
+        else -> runRegisters(
+            "_kdap_register_write($frame, ${pyStr(node.register)}, ${pyStr(name)}, ${pyStr(value)}, ${pyStr(node.lanes)})",
+            ctx,
+        )
+    }
+    if (result.has("error")) {
+        sendErrorResponse(ctx, requestSeq, "setVariable", result.getString("error"))
+        return
+    }
+    val fields = if (node.register == null) registerNodes.handleOf(RegisterNode(node.frameId, node.group, name)) else null
+    sendSuccessResponse(ctx, requestSeq, "setVariable", mapOf(
+        "value" to result.getString("value"),
+        "variablesReference" to (fields ?: 0),
+    ))
+}
+
+/** Runs the [PY_REGISTERS] call [call] and returns its JSON result. */
+private suspend fun DebugSession.runRegisters(call: String, ctx: AsyncRequestContext): JSONObject =
+    try {
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val interpreter = debugger.commandInterpreter()
+        interpreter.handleCommand("script exec(${pyStr(PY_REGISTERS)})")
+        JSONObject(interpreter.handleCommand("script print($call)").trim())
+    } catch (e: Exception) {
+        log.warning { "Registers: $call failed: ${e.message}" }
+        JSONObject().put("error", e.message ?: "reading registers failed").put("variables", JSONArray())
+    }
+
+/**
+ * Python side of [sendRegisters] and [setRegister], with the register
+ * description tables. Each function returns a JSON object with the
+ * `variables` it lists (`name`, `value`, and `type`; `fields` for a
+ * register with fields, `lanes` for a lane set) or an `error`.
+ */
+private val PY_REGISTERS = """
+    import json, re, struct
+
+    _KDAP_X86_FLAGS = [('CF', 0, 1), ('PF', 2, 1), ('AF', 4, 1), ('ZF', 6, 1), ('SF', 7, 1), ('TF', 8, 1),
+                       ('IF', 9, 1), ('DF', 10, 1), ('OF', 11, 1), ('IOPL', 12, 2), ('NT', 14, 1), ('RF', 16, 1),
+                       ('VM', 17, 1), ('AC', 18, 1), ('VIF', 19, 1), ('VIP', 20, 1), ('ID', 21, 1)]
+    _KDAP_MXCSR = [('IE', 0, 1), ('DE', 1, 1), ('ZE', 2, 1), ('OE', 3, 1), ('UE', 4, 1), ('PE', 5, 1),
+                   ('DAZ', 6, 1), ('IM', 7, 1), ('DM', 8, 1), ('ZM', 9, 1), ('OM', 10, 1), ('UM', 11, 1),
+                   ('PM', 12, 1), ('RC', 13, 2), ('FZ', 15, 1)]
+    _KDAP_A64_CPSR = [('N', 31, 1), ('Z', 30, 1), ('C', 29, 1), ('V', 28, 1), ('SS', 21, 1), ('IL', 20, 1),
+                      ('D', 9, 1), ('A', 8, 1), ('I', 7, 1), ('F', 6, 1), ('EL', 2, 2), ('SP', 0, 1)]
+    _KDAP_A64_FPSR = [('IOC', 0, 1), ('DZC', 1, 1), ('OFC', 2, 1), ('UFC', 3, 1), ('IXC', 4, 1), ('IDC', 7, 1),
+                      ('QC', 27, 1), ('V', 28, 1), ('C', 29, 1), ('Z', 30, 1), ('N', 31, 1)]
+    _KDAP_A64_FPCR = [('IOE', 8, 1), ('DZE', 9, 1), ('OFE', 10, 1), ('UFE', 11, 1), ('IXE', 12, 1), ('IDE', 15, 1),
+                      ('FZ16', 19, 1), ('RMode', 22, 2), ('FZ', 24, 1), ('DN', 25, 1), ('AHP', 26, 1)]
+    _KDAP_ARM_CPSR = [('N', 31, 1), ('Z', 30, 1), ('C', 29, 1), ('V', 28, 1), ('Q', 27, 1), ('J', 24, 1),
+                      ('GE', 16, 4), ('E', 9, 1), ('A', 8, 1), ('I', 7, 1), ('F', 6, 1), ('T', 5, 1), ('M', 0, 5)]
+    _KDAP_ARM_FPSCR = [('IOC', 0, 1), ('DZC', 1, 1), ('OFC', 2, 1), ('UFC', 3, 1), ('IXC', 4, 1), ('IDC', 7, 1),
+                       ('RMode', 22, 2), ('FZ', 24, 1), ('DN', 25, 1), ('AHP', 26, 1), ('QC', 27, 1),
+                       ('V', 28, 1), ('C', 29, 1), ('Z', 30, 1), ('N', 31, 1)]
+
+    # Per architecture: sub-register aliases to hide, flags registers and
+    # their fields, and the vector registers that have lanes.
+    _KDAP_REGISTER_TABLES = {
+        'x86_64': {
+            'aliases': re.compile(r'^(e(ax|bx|cx|dx|si|di|bp|sp)|[abcd]x|si|di|bp|sp|[abcd][lh]|sil|dil|bpl|spl|r\d+[dwl])${'$'}'),
+            'flags': {'rflags': _KDAP_X86_FLAGS, 'mxcsr': _KDAP_MXCSR},
+            'vectors': re.compile(r'^[xyz]mm\d+${'$'}'),
+        },
+        'i386': {
+            'aliases': re.compile(r'^([abcd]x|si|di|bp|sp|[abcd][lh])${'$'}'),
+            'flags': {'eflags': _KDAP_X86_FLAGS, 'mxcsr': _KDAP_MXCSR},
+            'vectors': re.compile(r'^[xy]mm\d+${'$'}'),
+        },
+        'aarch64': {
+            'aliases': re.compile(r'^(w\d+|[bhsd]\d+)${'$'}'),
+            'flags': {'cpsr': _KDAP_A64_CPSR, 'fpsr': _KDAP_A64_FPSR, 'fpcr': _KDAP_A64_FPCR},
+            'vectors': re.compile(r'^[vz]\d+${'$'}'),
+        },
+        'arm': {
+            'aliases': re.compile(r'^s\d+${'$'}'),
+            'flags': {'cpsr': _KDAP_ARM_CPSR, 'fpscr': _KDAP_ARM_FPSCR},
+            'vectors': re.compile(r'^[dq]\d+${'$'}'),
+        },
+    }
+    _KDAP_REGISTER_GROUPS = ('General Purpose', 'FP/SIMD', 'Flags', 'Other')
+    _KDAP_LANES = (('u8', 'B'), ('u16', 'H'), ('u32', 'I'), ('u64', 'Q'), ('i8', 'b'), ('i16', 'h'),
+                   ('i32', 'i'), ('i64', 'q'), ('f32', 'f'), ('f64', 'd'))
+
+    def _kdap_register_table(frame):
+        arch = frame.GetThread().GetProcess().GetTarget().GetTriple().split('-')[0]
+        if arch == 'arm64' or arch.startswith('aarch64'):
+            arch = 'aarch64'
+        elif arch.startswith('arm') or arch.startswith('thumb'):
+            arch = 'arm'
+        elif re.match(r'^i[3-6]86${'$'}', arch):
+            arch = 'i386'
+        elif arch.startswith('x86_64'):
+            arch = 'x86_64'
+        return _KDAP_REGISTER_TABLES.get(arch, {'aliases': None, 'flags': {}, 'vectors': None})
+
+    def _kdap_register_sets(frame, table):
+        # (group, register) for each register of the frame, aliases left out.
+        for register_set in frame.GetRegisters():
+            set_name = register_set.GetName() or ''
+            for register in register_set:
+                name = register.GetName() or ''
+                if table['aliases'] is not None and table['aliases'].match(name):
+                    continue
+                if name in table['flags']:
+                    group = 'Flags'
+                elif set_name.startswith('General Purpose'):
+                    group = 'General Purpose'
+                elif re.search(r'float|vector|fpu|simd|sve|avx|sse|neon', set_name, re.I):
+                    group = 'FP/SIMD'
+                else:
+                    group = 'Other'
+                yield group, register
+
+    def _kdap_register_is_vector(table, register):
+        name = register.GetName() or ''
+        return table['vectors'] is not None and table['vectors'].match(name) is not None
+
+    def _kdap_register_bytes(register):
+        error = lldb.SBError()
+        data = register.GetData()
+        raw = data.ReadRawData(error, 0, data.GetByteSize())
+        if error.Fail() or not raw:
+            raise ValueError('%s is not available in this frame' % register.GetName())
+        return raw
+
+    def _kdap_lane_format(frame, lane):
+        code = dict(_KDAP_LANES).get(lane)
+        if code is None:
+            raise ValueError('no lanes %s' % lane)
+        little = frame.GetThread().GetProcess().GetByteOrder() != lldb.eByteOrderBig
+        return ('<' if little else '>') + code, struct.calcsize(code)
+
+    def _kdap_lane_values(frame, raw, lane):
+        code, size = _kdap_lane_format(frame, lane)
+        return [struct.unpack_from(code, raw, i * size)[0] for i in range(len(raw) // size)]
+
+    def _kdap_lane_text(lane, x):
+        return '%g' % x if lane.startswith('f') else '%d' % x
+
+    def _kdap_register_value(table, name, register):
+        value = register.GetValue() or ''
+        fields = table['flags'].get(name)
+        if fields is None or not value:
+            return value
+        bits = register.GetValueAsUnsigned()
+        set_flags = [f for f, bit, width in fields if width == 1 and bits >> bit & 1]
+        return '%s [%s]' % (value, ' '.join(set_flags))
+
+    def _kdap_register_find(frame, name):
+        register = frame.FindRegister(name)
+        if not register.IsValid():
+            raise NameError("no register '%s'" % name)
+        return register
+
+    def _kdap_register_groups(frame):
+        try:
+            if not frame.IsValid():
+                raise ValueError('The frame is no longer available')
+            present = {group for group, _ in _kdap_register_sets(frame, _kdap_register_table(frame))}
+            return json.dumps({'variables': [{'name': g, 'value': ''} for g in _KDAP_REGISTER_GROUPS if g in present]})
+        except Exception as e:
+            return json.dumps({'error': str(e)})
+
+    def _kdap_register_list(frame, group):
+        try:
+            table = _kdap_register_table(frame)
+            variables = []
+            for g, register in _kdap_register_sets(frame, table):
+                if g != group:
+                    continue
+                name = register.GetName()
+                has_fields = name in table['flags'] or _kdap_register_is_vector(table, register)
+                variables.append({'name': name, 'value': _kdap_register_value(table, name, register),
+                                  'type': register.GetTypeName() or '', 'fields': has_fields})
+            return json.dumps({'variables': variables})
+        except Exception as e:
+            return json.dumps({'error': str(e)})
+
+    def _kdap_register_fields(frame, name):
+        # The fields of a flags register, or the lane sets of a vector register.
+        try:
+            table = _kdap_register_table(frame)
+            register = _kdap_register_find(frame, name)
+            if name in table['flags']:
+                bits = register.GetValueAsUnsigned()
+                return json.dumps({'variables': [
+                    {'name': f, 'value': str(bits >> bit & ((1 << width) - 1))}
+                    for f, bit, width in table['flags'][name]]})
+            raw = _kdap_register_bytes(register)
+            variables = []
+            for lane, _ in _KDAP_LANES:
+                values = _kdap_lane_values(frame, raw, lane)
+                variables.append({'name': '%sx%d' % (lane, len(values)), 'lanes': lane, 'writable': False,
+                                  'value': '[%s]' % ', '.join(_kdap_lane_text(lane, x) for x in values)})
+            return json.dumps({'variables': variables})
+        except Exception as e:
+            return json.dumps({'error': str(e)})
+
+    def _kdap_register_lanes(frame, name, lane):
+        try:
+            values = _kdap_lane_values(frame, _kdap_register_bytes(_kdap_register_find(frame, name)), lane)
+            return json.dumps({'variables': [
+                {'name': '[%d]' % i, 'value': _kdap_lane_text(lane, x), 'type': lane} for i, x in enumerate(values)]})
+        except Exception as e:
+            return json.dumps({'error': str(e)})
+
+    def _kdap_register_set(register, text):
+        error = lldb.SBError()
+        if not register.SetValueFromCString(text, error) or error.Fail():
+            raise ValueError(error.GetCString() or 'cannot write %s' % register.GetName())
+
+    def _kdap_register_write(frame, name, field, text, lane=None):
+        # Writes register name, its flags field, or (with lane) the lane
+        # field ('[i]') of its lane set lane.
+        try:
+            if frame.GetFrameID() != 0:
+                raise ValueError('Registers can only be changed in the topmost frame')
+            table = _kdap_register_table(frame)
+            register = _kdap_register_find(frame, name)
+            text = text.strip()
+            if lane is not None:
+                code, size = _kdap_lane_format(frame, lane)
+                raw = bytearray(_kdap_register_bytes(register))
+                match = re.match(r'^\[(\d+)\]${'$'}', field)
+                index = int(match.group(1)) if match else -1
+                if not 0 <= index < len(raw) // size:
+                    raise ValueError('no lane %s' % field)
+                x = float(text) if lane.startswith('f') else int(text, 0)
+                struct.pack_into(code, raw, index * size, x)
+                _kdap_register_set(register, '{%s}' % ' '.join('0x%02x' % b for b in raw))
+                values = _kdap_lane_values(frame, _kdap_register_bytes(_kdap_register_find(frame, name)), lane)
+                return json.dumps({'value': _kdap_lane_text(lane, values[index])})
+            if field is None:
+                _kdap_register_set(register, text)
+                return json.dumps({'value': _kdap_register_value(table, name, _kdap_register_find(frame, name))})
+            fields = {f: (bit, width) for f, bit, width in table['flags'].get(name, ())}
+            if field not in fields:
+                raise ValueError('%s cannot be changed' % field)
+            bit, width = fields[field]
+            mask = (1 << width) - 1
+            x = int(text, 0)
+            if not 0 <= x <= mask:
+                raise ValueError('%s takes a value from 0 to %d' % (field, mask))
+            _kdap_register_set(register, str(register.GetValueAsUnsigned() & ~(mask << bit) | x << bit))
+            bits = _kdap_register_find(frame, name).GetValueAsUnsigned()
+            return json.dumps({'value': str(bits >> bit & mask)})
+        except Exception as e:
+            return json.dumps({'error': str(e)})
+""".trimIndent()
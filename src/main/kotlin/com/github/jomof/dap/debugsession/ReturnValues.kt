@@ -0,0 +1,227 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.messages.ScopesRequest
+import com.github.jomof.dap.sb.SBDebugger
+import com.github.jomof.dap.sb.createDebugger
+import com.github.jomof.dap.sb.pyStr
+import com.github.jomof.dap.sb.watched
+import org.json.JSONObject
+import java.util.logging.Logger
+
+/**
+ * Return values after `stepOut` and `next`, mirroring the return value
+ * CodeLLDB shows in the locals of the frame a step returned to.
+ *
+ * lldb-dap shows a `(Return Value)` local only when LLDB's own ABI code
+ * can compute one, which it rarely can for Rust. KDAP installs a
+ * scripted stop hook instead ([initReturnValues]). At every stop it
+ * remembers, for each thread with a stop reason, the function in its top
+ * frame and that frame's return address. If the next stop is the end of
+ * a step (`stepOut`, or `next` off the end of the function) at that
+ * return address and with the caller's frame, the function has just
+ * returned and its return registers still hold the result: the hook
+ * decodes them against the function's DWARF return type and keeps the
+ * value for the thread.
+ *
+ * The value is shown as a `(return) fn_name` entry at the top of the
+ * top frame's Locals scope, replacing lldb-dap's `(Return Value)`. KDAP
+ * learns which `variablesReference` is that scope from the `scopes`
+ * requests and responses it relays.
+ *
+ * Only values returned in registers are decoded: integers, `bool`,
+ * `char`, C-like enums, pointers and references, `f32`/`f64`, and
+ * structs made of one such field or of two 8-byte integer or pointer
+ * fields (`&str`, slices, `u128`), on x86-64 and AArch64. Values returned
+ * through memory, and enums with data, are not shown. A `next` that
+ * steps over calls within a line shows nothing for them.
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.ReturnValues")
+
+/** Name lldb-dap gives the return value it adds to the locals. */
+internal const val LLDB_DAP_RETURN_VALUE = "(Return Value)"
+
+/** Installs the [PY_RETURN_VALUES] stop hook on the selected target. */
+internal suspend fun initReturnValues(debugger: SBDebugger) {
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_RETURN_VALUES)})")
+    interpreter.handleCommand("target stop-hook add -P _KdapReturnValueHook")
+}
+
+/** Remembers which thread's top frame [request] asks about, if any. */
+fun DebugSession.onScopesRequest(request: ScopesRequest) {
+    if (isTaskFrame(request.frameId)) return
+    pendingScopes[request.seq] = request.frameId
+}
+
+/**
+ * Records the Locals reference in a `scopes` response: as a top frame's
+ * (mapped to its thread's index ID) or, for other frames, as not one.
+ */
+fun DebugSession.onScopesResponse(response: DapResponse) {
+    val frameId = pendingScopes.remove(response.requestSeq) ?: return
+    if (!response.success) return
+    val scopes = response.body["scopes"] as? List<*> ?: return
+    val locals = scopes.filterIsInstance<Map<*, *>>().firstOrNull {
+        it["presentationHint"] == "locals" || it["name"] == "Locals"
+    } ?: return
+    val reference = (locals["variablesReference"] as? Number)?.toInt()?.takeIf { it > 0 } ?: return
+    if (frameId and ((1 shl FRAME_INDEX_BITS) - 1) == 0) {
+        returnValueScopes[reference] = frameId ushr FRAME_INDEX_BITS
+    } else {
+        returnValueScopes.remove(reference)
+    }
+}
+
+/** Forgets the top frames' Locals references; lldb-dap's are only valid until the next stop. */
+fun DebugSession.forgetReturnValueScopes() {
+    returnValueScopes.clear()
+}
+
+/**
+ * The `(return) fn_name` variable for the thread with index ID
+ * [threadIndexId], or `null` if its last step returned nothing KDAP
+ * could decode.
+ */
+internal suspend fun DebugSession.returnValueVariable(threadIndexId: Int, ctx: AsyncRequestContext): Map<String, Any?>? =
+    try {
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val output = debugger.commandInterpreter()
+            .handleCommand("script print(_kdap_return_entry($threadIndexId))").trim()
+        if (output.isEmpty()) {
+            null
+        } else {
+            val entry = JSONObject(output)
+            mapOf(
+                "name" to entry.getString("name"),
+                "value" to entry.optString("value"),
+                "type" to entry.optString("type"),
+                "variablesReference" to 0,
+                "presentationHint" to mapOf("kind" to "virtual", "attributes" to listOf("readOnly")),
+            )
+        }
+    } catch (e: Exception) {
+        log.warning { "ReturnValues: reading the return value failed: ${e.message}" }
+        null
+    }
+
+/** Puts [entry] first in a Locals scope's [variables], in place of lldb-dap's own return value. */
+internal fun withReturnValue(variables: List<Any?>, entry: Map<String, Any?>): List<Any?> =
+    listOf(entry) + variables.filterNot { (it as? Map<*, *>)?.get("name") == LLDB_DAP_RETURN_VALUE }
+
+/**
+ * Python side of [initReturnValues]. `_KdapReturnValueHook` runs at
+ * every stop; `_kdap_return_entry` prints the thread's return value as a
+ * JSON object with `name`, `value`, and `type`, or nothing.
+ */
+internal val PY_RETURN_VALUES = """
+    import json, struct
+
+    _kdap_return = {'callees': {}, 'values': {}}
+    _KDAP_INT_RETURN = {'x86_64': ('rax', 'rdx'), 'aarch64': ('x0', 'x1'), 'arm64': ('x0', 'x1')}
+    _KDAP_FLOAT_RETURN = {'x86_64': 'xmm0', 'aarch64': 'v0', 'arm64': 'v0'}
+
+    def _kdap_return_class(value_type):
+        value_type = value_type.GetCanonicalType()
+        size = value_type.GetByteSize()
+        if size == 0 or size > 16:
+            return None
+        type_class = value_type.GetTypeClass()
+        if type_class == lldb.eTypeClassBuiltin:
+            if value_type.GetBasicType() in (lldb.eBasicTypeFloat, lldb.eBasicTypeDouble):
+                return 'float'
+            return 'int'
+        if type_class in (lldb.eTypeClassPointer, lldb.eTypeClassReference, lldb.eTypeClassEnumeration):
+            return 'int'
+        if type_class in (lldb.eTypeClassStruct, lldb.eTypeClassClass):
+            fields = [value_type.GetFieldAtIndex(i) for i in range(value_type.GetNumberOfFields())]
+            if len(fields) == 1 and fields[0].GetOffsetInBytes() == 0:
+                return _kdap_return_class(fields[0].GetType())
+            if len(fields) == 2 and [f.GetOffsetInBytes() for f in fields] == [0, 8] and all(
+                    f.GetType().GetByteSize() == 8 and _kdap_return_class(f.GetType()) == 'int' for f in fields):
+                return 'int'
+        return None
+
+    def _kdap_return_decode(frame, function):
+        target = frame.GetThread().GetProcess().GetTarget()
+        return_type = function.GetType().GetFunctionReturnType()
+        if not return_type.IsValid():
+            return None
+        kind = _kdap_return_class(return_type)
+        arch = target.GetTriple().split('-')[0]
+        size = return_type.GetByteSize()
+        error = lldb.SBError()
+        if kind == 'float':
+            register = frame.FindRegister(_KDAP_FLOAT_RETURN.get(arch, ''))
+            if not register.IsValid():
+                return None
+            raw = register.GetData().ReadRawData(error, 0, size)
+        elif kind == 'int':
+            raw = b''
+            for name in _KDAP_INT_RETURN.get(arch, ())[:(size + 7) // 8]:
+                register = frame.FindRegister(name)
+                if not register.IsValid():
+                    return None
+                raw += struct.pack('<Q', register.GetValueAsUnsigned())
+            raw = raw[:size]
+        else:
+            return None
+        if error.Fail() or raw is None or len(raw) != size:
+            return None
+        data = lldb.SBData()
+        data.SetData(error, raw, target.GetByteOrder(), target.GetAddressByteSize())
+        if error.Fail():
+            return None
+        name = '(return) ' + (function.GetDisplayName() or function.GetName())
+        return target.CreateValueFromData(name, data, return_type)
+
+    def _kdap_return_on_stop(process):
+        callees = _kdap_return['callees']
+        values = {}
+        callees_now = {}
+        selected = process.GetSelectedThread().GetThreadID()
+        for thread in process:
+            thread_id = thread.GetThreadID()
+            reason = thread.GetStopReason()
+            frame = thread.GetFrameAtIndex(0)
+            callee = callees.get(thread_id)
+            returned = reason == lldb.eStopReasonPlanComplete and callee is not None
+            if returned and (frame.GetPC(), frame.GetCFA()) == callee[1:]:
+                value = _kdap_return_decode(frame, callee[0])
+                if value is not None and value.IsValid():
+                    values[thread_id] = value
+            if reason in (lldb.eStopReasonNone, lldb.eStopReasonInvalid) and thread_id != selected:
+                continue
+            function = frame.GetFunction()
+            caller = thread.GetFrameAtIndex(1)
+            if function.IsValid() and caller.IsValid():
+                callees_now[thread_id] = (function, caller.GetPC(), caller.GetCFA())
+        _kdap_return['callees'] = callees_now
+        _kdap_return['values'] = values
+
+    class _KdapReturnValueHook:
+        def __init__(self, target, extra_args, internal_dict):
+            pass
+
+        def handle_stop(self, exe_ctx, stream):
+            try:
+                _kdap_return_on_stop(exe_ctx.GetProcess())
+            except Exception:
+                _kdap_return['callees'] = {}
+                _kdap_return['values'] = {}
+            return True
+
+    def _kdap_return_entry(thread_index_id):
+        process = lldb.debugger.GetSelectedTarget().GetProcess()
+        thread = process.GetThreadByIndexID(thread_index_id)
+        value = _kdap_return['values'].get(thread.GetThreadID()) if thread.IsValid() else None
+        if value is None:
+            return ''
+        return json.dumps({
+            'name': value.GetName(),
+            'value': value.GetSummary() or value.GetValue() or '',
+            'type': value.GetDisplayTypeName(),
+        })
+""".trimIndent()
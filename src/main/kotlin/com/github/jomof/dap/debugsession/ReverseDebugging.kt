@@ -0,0 +1,343 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.messages.*
+import com.github.jomof.dap.sb.*
+import kotlinx.coroutines.Dispatchers
+import kotlinx.coroutines.coroutineScope
+import kotlinx.coroutines.delay
+import kotlinx.coroutines.launch
+import kotlinx.coroutines.runInterruptible
+import kotlinx.coroutines.sync.withLock
+import org.json.JSONObject
+import java.io.File
+import java.io.InputStream
+import java.net.InetAddress
+import java.net.ServerSocket
+import java.nio.charset.StandardCharsets
+import java.nio.file.Files
+import java.nio.file.Path
+import java.util.logging.Logger
+
+/**
+ * Reverse debugging with `rr`: the `"record": true` launch option and
+ * the `reverseContinue` / `stepBack` requests. CodeLLDB only has the
+ * latter (`reverseDebugging`, against a gdbserver the user starts).
+ *
+ * ## Recording
+ *
+ * A `"record": true` launch runs the program to completion under
+ * `rr record`, relaying its stdout and stderr as output events, and then
+ * debugs the recording instead of a live process: KDAP starts
+ * `rr replay` as a gdbserver and connects LLDB to it, the way a
+ * `gdbRemote` attach does ([recordDebuggee], [startReplay]). The replay
+ * starts where the program began; `stopOnEntry` stops there, otherwise
+ * it runs forward to the first breakpoint. The replay executes exactly
+ * what the recorded run did, so stdin is not read and writes to memory
+ * or registers do not change the outcome. The trace is deleted at
+ * disconnect.
+ *
+ * `supportsStepBack` is advertised when `rr` is on KDAP's `PATH`.
+ * `"reverseDebugging": true` enables the reverse requests for a session
+ * whose server the user provides (for example `rr replay -s PORT` and a
+ * `gdbRemote` attach), as in CodeLLDB.
+ *
+ * ## Reverse execution
+ *
+ * `reverseContinue` resumes the process backwards with
+ * `SBProcess::ContinueInDirection`, which LLDB runs with the gdbserver's
+ * reverse-continue packet. It stops at the last breakpoint hit before the
+ * current position, or at the start of the recording.
+ *
+ * `stepBack` (line granularity) is a reverse `next`: KDAP puts
+ * temporary breakpoints on the other lines of the thread's function,
+ * conditioned on the frame being the one stepped, and on the call
+ * instruction in its caller, and continues backwards. The thread stops at
+ * the start of the line executed before the current one, or at the call
+ * that entered the function, and the stop is reported with reason
+ * `step`. The breakpoints are removed on the next resume (see
+ * [handleStepBack]). LLDB has no reverse single-step, so instruction
+ * granularity is refused.
+ *
+ * ## CodeLLDB method mapping
+ *
+ * | CodeLLDB (debug_session.rs) | KDAP (this file)                     |
+ * |-----------------------------|--------------------------------------|
+ * | `handle_reverse_continue`   | [DebugSession.handleReverseContinue] |
+ * | `handle_step_back`          | [DebugSession.handleStepBack]        |
+ * | —                           | [DebugSession.recordDebuggee]        |
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.ReverseDebugging")
+
+/** Number of times KDAP tries to connect to `rr replay` before giving up. */
+private const val REPLAY_CONNECT_ATTEMPTS = 50
+
+/** Delay between attempts to connect to `rr replay`. */
+private const val REPLAY_CONNECT_DELAY_MS = 200L
+
+/** Error for reverse requests in sessions that cannot run backwards. */
+private const val NO_REVERSE_EXECUTION =
+    "Reverse execution needs a \"record\": true launch or \"reverseDebugging\": true."
+
+/** Path of the `rr` executable on KDAP's `PATH`, or `null` if there is none. */
+internal val rrExecutable: String? by lazy {
+    if (!System.getProperty("os.name").orEmpty().startsWith("Linux")) return@lazy null
+    System.getenv("PATH").orEmpty().split(File.pathSeparator)
+        .filter { it.isNotEmpty() }
+        .map { File(it, "rr") }
+        .firstOrNull { it.isFile && it.canExecute() }
+        ?.path
+}
+
+/**
+ * Advertises `supportsStepBack` in a successful `initialize` response
+ * when [rrAvailable].
+ */
+fun addStepBackCapability(response: DapResponse, rrAvailable: Boolean = rrExecutable != null): DapResponse =
+    if (rrAvailable) response.copy(body = response.body + ("supportsStepBack" to true)) else response
+
+// ── Recording ────────────────────────────────────────────────────
+
+/**
+ * Runs [program] with the launch's arguments, environment, and working
+ * directory under `rr record` until it exits, relaying its output.
+ *
+ * @return the trace directory
+ */
+internal suspend fun DebugSession.recordDebuggee(
+    program: String,
+    args: LaunchRequestArguments,
+    ctx: AsyncRequestContext,
+): Path {
+    val rr = rrExecutable ?: throw SBError("\"record\" needs rr, which was not found on PATH.")
+    args.cwd?.let { checkWorkingDirectory(it) }
+    val environment = launchEnvironment(args)
+    val trace = Files.createTempDirectory("kdap-rr").also { traceDirectory = it }.resolve("trace")
+    val command = listOf(rr, "record", "--output-trace-dir=$trace", program) + args.args.orEmpty()
+    consoleMessage("Recording: ${command.joinToString(" ")}", ctx)
+
+    val process = ProcessBuilder(command).apply {
+        args.cwd?.let { directory(File(it)) }
+        environment().putAll(environment)
+    }.start()
+    process.outputStream.close()
+    coroutineScope {
+        launch(Dispatchers.IO) { relayOutput(process.inputStream, "stdout", ctx) }
+        launch(Dispatchers.IO) { relayOutput(process.errorStream, "stderr", ctx) }
+    }
+    val exitCode = runInterruptible(Dispatchers.IO) { process.waitFor() }
+    consoleMessage("Recorded process exited with code $exitCode.", ctx)
+    return trace
+}
+
+/** Sends everything read from [stream] to the client as [category] output. */
+private suspend fun relayOutput(stream: InputStream, category: String, ctx: AsyncRequestContext) {
+    val reader = stream.reader(StandardCharsets.UTF_8)
+    val buffer = CharArray(4096)
+    while (true) {
+        val count = runInterruptible { reader.read(buffer) }
+        if (count < 0) break
+        ctx.sendEventToClient(OutputEvent(seq = 0, category = category, output = String(buffer, 0, count)).toJson())
+    }
+}
+
+/**
+ * Starts `rr replay` of [trace] as a gdbserver and connects [target] to
+ * it. rr only listens once the replay has reached the program's start,
+ * so the connection is retried.
+ *
+ * @return the replayed process, stopped at the start of the recording
+ */
+internal suspend fun DebugSession.startReplay(
+    trace: Path,
+    target: SBTarget,
+    ctx: AsyncRequestContext,
+): SBProcess {
+    val rr = rrExecutable ?: throw SBError("\"record\" needs rr, which was not found on PATH.")
+    val port = ServerSocket(0, 1, InetAddress.getLoopbackAddress()).use { it.localPort }
+    val server = ProcessBuilder(rr, "replay", "--dbgport=$port", trace.toString())
+        .redirectErrorStream(true)
+        .redirectOutput(ProcessBuilder.Redirect.DISCARD)
+        .start()
+    replayServer = server
+    consoleMessage("Replaying the recording from $trace", ctx)
+
+    var lastError: Exception? = null
+    repeat(REPLAY_CONNECT_ATTEMPTS) {
+        if (!server.isAlive) throw SBError("rr replay exited with code ${server.exitValue()}.")
+        try {
+            return target.connectRemote("connect://127.0.0.1:$port")
+        } catch (e: Exception) {
+            lastError = e
+            delay(REPLAY_CONNECT_DELAY_MS)
+        }
+    }
+    throw SBError("Could not connect to rr replay: ${lastError?.message}")
+}
+
+/** Stops the `rr replay` server, if any, and deletes the recording. */
+internal fun DebugSession.releaseRecording() {
+    replayServer?.let { server ->
+        replayServer = null
+        server.destroy()
+    }
+    traceDirectory?.let { directory ->
+        traceDirectory = null
+        try {
+            directory.toFile().deleteRecursively()
+        } catch (e: Exception) {
+            log.fine { "ReverseDebugging: deleting $directory: ${e.message}" }
+        }
+    }
+}
+
+// ── Reverse execution ────────────────────────────────────────────
+
+/** A running `stepBack` on [threadId], stopping at [breakpointIds]. */
+data class StepBack(val threadId: Int, val breakpointIds: Set<Int>)
+
+/**
+ * Mirrors CodeLLDB's `handle_reverse_continue`: resumes the process
+ * backwards.
+ */
+suspend fun DebugSession.handleReverseContinue(rawJson: String, ctx: AsyncRequestContext) {
+    val requestSeq = JSONObject(rawJson).optInt("seq", 0)
+
+    try {
+        if (!reverseExecution) throw SBError(NO_REVERSE_EXECUTION)
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        releaseStepState(debugger)
+        reverseContinue(debugger)
+        sendSuccessResponse(ctx, requestSeq, "reverseContinue")
+    } catch (e: Exception) {
+        log.warning { "ReverseDebugging: reverseContinue failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "reverseContinue", e.message ?: "reverseContinue failed")
+    }
+}
+
+/**
+ * Mirrors CodeLLDB's `handle_step_back`: sets the temporary breakpoints
+ * of a reverse `next` (see the file header) and resumes the process
+ * backwards.
+ */
+suspend fun DebugSession.handleStepBack(rawJson: String, ctx: AsyncRequestContext) {
+    val requestSeq = JSONObject(rawJson).optInt("seq", 0)
+    val request = DapMessage.parse(rawJson) as StepBackRequest
+
+    try {
+        if (!reverseExecution) throw SBError(NO_REVERSE_EXECUTION)
+        if (request.granularity == INSTRUCTION_GRANULARITY) {
+            throw SBError("Stepping back by instruction is not supported.")
+        }
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        stateLock.withLock {
+            releaseStepStateLocked(debugger)
+            val interpreter = debugger.commandInterpreter()
+            interpreter.handleCommand("script exec(${pyStr(PY_STEP_BACK)})")
+            val ids = interpreter.handleCommand("script print(_kdap_step_back_set(${request.threadId}))").trim()
+                .split(',').mapNotNull { it.trim().toIntOrNull() }.toSet()
+            if (ids.isNotEmpty()) stepBack = StepBack(request.threadId, ids)
+            reverseContinue(debugger)
+        }
+        sendSuccessResponse(ctx, requestSeq, "stepBack")
+    } catch (e: Exception) {
+        log.warning { "ReverseDebugging: stepBack failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "stepBack", e.message ?: "stepBack failed")
+    }
+}
+
+/** Resumes the selected target's process backwards. */
+private suspend fun reverseContinue(debugger: SBDebugger) {
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_STEP_BACK)})")
+    val error = interpreter.handleCommand("script print(_kdap_reverse_continue())").trim()
+    if (error.isNotEmpty()) throw SBError(error)
+}
+
+/**
+ * Reports a stop at a `stepBack`'s breakpoints as the end of the step.
+ * Stops at other breakpoints are left alone.
+ */
+fun DebugSession.rewriteStepBackStop(event: StoppedEvent): StoppedEvent {
+    val step = stepBack ?: return event
+    if (event.threadId != step.threadId || event.reason != "breakpoint") return event
+    val hit = event.hitBreakpointIds
+    if (hit != null && !step.breakpointIds.containsAll(hit)) return event
+    return event.copy(reason = "step", description = null, hitBreakpointIds = null)
+}
+
+/**
+ * Python side of [handleStepBack] and [handleReverseContinue].
+ * `_kdap_step_back_set` returns the IDs of the breakpoints it set,
+ * comma-separated; each only stops in the frame with the CFA recorded
+ * for it. The caller's breakpoint is on the instruction whose end is the
+ * return address, else at the start of the caller's line.
+ * `_kdap_reverse_continue` returns an error message, or nothing.
+ */
+private val PY_STEP_BACK = """
+    if '_kdap_step_back' not in globals():
+        _kdap_step_back = {'cfa': {}}
+
+    def _kdap_step_back_clear():
+        target = lldb.debugger.GetSelectedTarget()
+        for bp_id in _kdap_step_back['cfa']:
+            target.BreakpointDelete(bp_id)
+        _kdap_step_back['cfa'] = {}
+
+    def _kdap_step_back_breakpoint(target, address, thread_id, cfa):
+        bp = target.BreakpointCreateByAddress(address)
+        bp.SetThreadID(thread_id)
+        bp.SetScriptCallbackFunction('_kdap_step_back_hit')
+        _kdap_step_back['cfa'][bp.GetID()] = cfa
+        return bp.GetID()
+
+    def _kdap_step_back_hit(frame, bp_loc, internal_dict):
+        return frame.GetCFA() == _kdap_step_back['cfa'].get(bp_loc.GetBreakpoint().GetID())
+
+    def _kdap_call_address(target, caller):
+        pc = caller.GetPC()
+        function = caller.GetFunction()
+        scope = function if function.IsValid() else caller.GetSymbol()
+        if scope.IsValid():
+            for inst in scope.GetInstructions(target):
+                address = inst.GetAddress().GetLoadAddress(target)
+                if address + inst.GetByteSize() == pc:
+                    return address
+        entry = caller.GetLineEntry()
+        if entry.IsValid():
+            return entry.GetStartAddress().GetLoadAddress(target)
+        return None
+
+    def _kdap_step_back_set(thread_id):
+        _kdap_step_back_clear()
+        target = lldb.debugger.GetSelectedTarget()
+        thread = target.GetProcess().GetThreadByID(thread_id)
+        frame = thread.GetFrameAtIndex(0)
+        function = frame.GetFunction()
+        ids = []
+        if function.IsValid():
+            start = function.GetStartAddress()
+            low = start.GetLoadAddress(target)
+            high = function.GetEndAddress().GetLoadAddress(target)
+            skip = (0, frame.GetLineEntry().GetLine(), start.GetLineEntry().GetLine())
+            unit = start.GetCompileUnit()
+            addresses = set()
+            for i in range(unit.GetNumLineEntries()):
+                entry = unit.GetLineEntryAtIndex(i)
+                address = entry.GetStartAddress().GetLoadAddress(target)
+                if not low <= address < high or entry.GetLine() in skip or address in addresses:
+                    continue
+                addresses.add(address)
+                ids.append(_kdap_step_back_breakpoint(target, address, thread_id, frame.GetCFA()))
+        caller = thread.GetFrameAtIndex(1)
+        if caller.IsValid():
+            address = _kdap_call_address(target, caller)
+            if address is not None and address != lldb.LLDB_INVALID_ADDRESS:
+                ids.append(_kdap_step_back_breakpoint(target, address, thread_id, caller.GetCFA()))
+        return ','.join(str(bp_id) for bp_id in ids)
+
+    def _kdap_reverse_continue():
+        error = lldb.debugger.GetSelectedTarget().GetProcess().ContinueInDirection(lldb.eRunReverse)
+        return error.GetCString() or 'Reverse continue failed' if error.Fail() else ''
+""".trimIndent()
@@ -0,0 +1,422 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.messages.OutputEvent
+import com.github.jomof.dap.messages.StoppedEvent
+import com.github.jomof.dap.sb.SBCommandReturnObject
+import kotlinx.coroutines.launch
+import kotlinx.coroutines.runBlocking
+import org.json.JSONObject
+import org.luaj.vm2.Globals
+import org.luaj.vm2.LoadState
+import org.luaj.vm2.LuaError
+import org.luaj.vm2.LuaTable
+import org.luaj.vm2.LuaValue
+import org.luaj.vm2.Varargs
+import org.luaj.vm2.compiler.LuaC
+import org.luaj.vm2.lib.Bit32Lib
+import org.luaj.vm2.lib.PackageLib
+import org.luaj.vm2.lib.StringLib
+import org.luaj.vm2.lib.TableLib
+import org.luaj.vm2.lib.VarArgFunction
+import org.luaj.vm2.lib.jse.JseBaseLib
+import org.luaj.vm2.lib.jse.JseMathLib
+import java.io.File
+import java.util.Base64
+import java.util.logging.Logger
+
+/**
+ * Lua scripting (`kdap.init`): custom value summaries, stop hooks, and
+ * Debug Console commands. KDAP extension, in the spirit of LLDB's Python
+ * scripting; CodeLLDB has no counterpart.
+ *
+ * At launch or attach KDAP runs the script named by `"initScript"`
+ * ([loadInitScript]); a `kdap.init` in the working directory is not run
+ * unless it is named, since a checked-out project could bring one. The
+ * script has Lua's base, string, table, math, and bit32 libraries, but
+ * not `io`, `os`, `package`, `dofile`, or `loadfile`; what it does
+ * outside Lua goes through the `kdap` API. Errors in it are shown in the
+ * Debug Console and the session goes on without it. It registers its
+ * hooks on the `kdap` table:
+ *
+ * ```lua
+ * kdap.summary("^geo::Point$", function(v)
+ *   return "(" .. kdap.child(v, "x").value .. ", " .. kdap.child(v, "y").value .. ")"
+ * end)
+ * kdap.on_stop(function(e) kdap.print("stopped: " .. e.reason .. "\n") end)
+ * kdap.command("hexdump", function(args) ... end)
+ * ```
+ *
+ * ## Hooks
+ *
+ * | Function                    | Called                                        |
+ * |-----------------------------|-----------------------------------------------|
+ * | `kdap.summary(regex, fn)`   | for each variable whose type matches `regex`; the string `fn(value)` returns is shown as its value |
+ * | `kdap.on_stop(fn)`          | with the `stopped` event at each stop, after the client is told |
+ * | `kdap.command(name, fn)`    | for `` `name args `` in the Debug Console, with `args`; a string it returns is printed |
+ *
+ * `regex` is a Java regular expression, found anywhere in the type name;
+ * the first summary registered for a type wins. Summaries apply to the
+ * children `variables` lists, not to `evaluate` results, and a summary
+ * that fails or returns nothing leaves the value as it was. Script
+ * commands take precedence over LLDB's. Stop hooks only observe the stop:
+ * the client may already be reading the stopped thread.
+ *
+ * ## API
+ *
+ * Value tables have the fields of a DAP `Variable`: `name`, `value`,
+ * `type`, `variablesReference`, `evaluateName`, and `memoryReference`.
+ *
+ * | Function                            | Returns                                       |
+ * |-------------------------------------|-----------------------------------------------|
+ * | `kdap.evaluate(expr [, frameId])`   | the result as a value table, as `evaluate` would |
+ * | `kdap.variables(ref)`               | the children of `ref` as value tables         |
+ * | `kdap.child(value, name)`           | the child of `value` named `name`, or `nil`   |
+ * | `kdap.read_memory(address, count)`  | up to `count` bytes at `address`, as a string |
+ * | `kdap.lldb(command)`                | the output of an LLDB command                 |
+ * | `kdap.break_at(file, line)`         | the ID of a new breakpoint at a line          |
+ * | `kdap.break_on(function)`           | the ID of a new breakpoint on a function      |
+ * | `kdap.delete_breakpoint(id)`        | whether the breakpoint existed                |
+ * | `kdap.print(text)`                  | nothing; `text` is shown in the Debug Console |
+ *
+ * Functions that fail raise a Lua error, which a script can catch with
+ * `pcall`. `print` writes to the Debug Console as well. Breakpoints made
+ * by a script are LLDB's alone: the client does not list them.
+ *
+ * The script is called from one thread at a time ([KdapScript] is
+ * synchronized), so hooks need no locking of their own.
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.Scripting")
+
+/** What the Debug Console calls the init script in its errors. */
+internal const val INIT_SCRIPT_NAME = "kdap.init"
+
+/** What a script's API calls do, blocking until they are done. */
+interface ScriptHost {
+    /** Evaluates [expression] in the frame [frameId], or the selected frame; the `evaluate` response body. */
+    fun evaluate(expression: String, frameId: Int?): Map<String, Any?>
+
+    /** The children of lldb-dap's variables [reference], as `Variable` maps. */
+    fun variables(reference: Int): List<Map<String, Any?>>
+
+    /** Up to [count] bytes of the debuggee's memory at [address]. */
+    fun readMemory(address: Long, count: Int): ByteArray
+
+    /** Runs the LLDB [command]. */
+    fun lldb(command: String): SBCommandReturnObject
+
+    /** Sets a breakpoint at [line] of [file] and returns its ID. */
+    fun breakAt(file: String, line: Int): Int
+
+    /** Sets a breakpoint on every function named [function] and returns its ID. */
+    fun breakOn(function: String): Int
+
+    /** Deletes the breakpoint [id]; whether it existed. */
+    fun deleteBreakpoint(id: Int): Boolean
+
+    /** Shows [text] in the Debug Console. */
+    fun print(text: String)
+}
+
+/** An error raised by a script, or by loading it. */
+class ScriptError(message: String) : Exception(message)
+
+/**
+ * A `kdap.init` script's Lua state and the hooks it registered (see the
+ * file header). Every call into Lua holds the script's lock.
+ */
+class KdapScript(val host: ScriptHost) {
+    private val lock = Any()
+    private val globals = sandboxedGlobals()
+    private val summaries = mutableListOf<Pair<Regex, LuaValue>>()
+    private val stopHooks = mutableListOf<LuaValue>()
+    private val commands = linkedMapOf<String, LuaValue>()
+
+    init {
+        val kdap = LuaTable()
+        kdap.set("summary", function { args ->
+            val pattern = args.checkjstring(1)
+            val regex = try {
+                Regex(pattern)
+            } catch (e: IllegalArgumentException) {
+                throw LuaError("bad summary pattern '$pattern': ${e.message}")
+            }
+            summaries += regex to args.checkfunction(2)
+            LuaValue.NONE
+        })
+        kdap.set("on_stop", function { args ->
+            stopHooks += args.checkfunction(1)
+            LuaValue.NONE
+        })
+        kdap.set("command", function { args ->
+            commands[args.checkjstring(1)] = args.checkfunction(2)
+            LuaValue.NONE
+        })
+        kdap.set("evaluate", function { args ->
+            val frameId = if (args.isnil(2)) null else args.checkint(2)
+            toLua(host.evaluate(args.checkjstring(1), frameId))
+        })
+        kdap.set("variables", function { args -> toLua(host.variables(args.checkint(1))) })
+        kdap.set("child", function { args ->
+            val reference = args.checktable(1).get("variablesReference").optint(0)
+            val name = args.checkjstring(2)
+            val child = if (reference > 0) host.variables(reference).firstOrNull { it["name"] == name } else null
+            child?.let { toLua(it) } ?: LuaValue.NIL
+        })
+        kdap.set("read_memory", function { args ->
+            LuaValue.valueOf(host.readMemory(args.checklong(1), args.checkint(2)))
+        })
+        kdap.set("lldb", function { args ->
+            val result = host.lldb(args.checkjstring(1))
+            if (!result.succeeded) throw LuaError(result.error.trim().ifEmpty { "command failed" })
+            LuaValue.valueOf(result.output)
+        })
+        kdap.set("break_at", function { args -> LuaValue.valueOf(host.breakAt(args.checkjstring(1), args.checkint(2))) })
+        kdap.set("break_on", function { args -> LuaValue.valueOf(host.breakOn(args.checkjstring(1))) })
+        kdap.set("delete_breakpoint", function { args -> LuaValue.valueOf(host.deleteBreakpoint(args.checkint(1))) })
+        kdap.set("print", function { args ->
+            host.print(args.checkjstring(1))
+            LuaValue.NONE
+        })
+        globals.set("kdap", kdap)
+        globals.set("print", function { args ->
+            host.print((1..args.narg()).joinToString("\t") { args.arg(it).tojstring() } + "\n")
+            LuaValue.NONE
+        })
+    }
+
+    /** Runs the script [source], named [name] in its errors. Throws [ScriptError] if it fails. */
+    fun load(source: String, name: String) {
+        synchronized(lock) {
+            lua { globals.load(source, "@$name").call() }
+        }
+    }
+
+    /** Whether the script registered any summaries. */
+    val hasSummaries: Boolean get() = synchronized(lock) { summaries.isNotEmpty() }
+
+    /** Whether the script registered any stop hooks. */
+    val hasStopHooks: Boolean get() = synchronized(lock) { stopHooks.isNotEmpty() }
+
+    /** Whether the script registered the Debug Console command [name]. */
+    fun hasCommand(name: String): Boolean = synchronized(lock) { name in commands }
+
+    /**
+     * The summary of [variable] (a `Variable` map) from the first summary
+     * whose pattern matches its type, or null if none matches or it
+     * returns nothing. Throws [ScriptError] if the summary fails.
+     */
+    fun summarize(variable: Map<String, Any?>): String? {
+        val type = variable["type"] as? String ?: return null
+        synchronized(lock) {
+            val fn = summaries.firstOrNull { it.first.containsMatchIn(type) }?.second ?: return null
+            val result = lua { fn.call(toLua(variable)) }
+            return if (result.isnil()) null else result.tojstring()
+        }
+    }
+
+    /** Calls each stop hook with [event] (a `stopped` event body). Throws [ScriptError] at the first that fails. */
+    fun onStop(event: Map<String, Any?>) {
+        synchronized(lock) {
+            for (hook in stopHooks) lua { hook.call(toLua(event)) }
+        }
+    }
+
+    /**
+     * Runs the command [name] with [args] and returns the text it returned,
+     * or null if it returned none. Throws [ScriptError] if there is no
+     * such command or it fails.
+     */
+    fun runCommand(name: String, args: String): String? {
+        synchronized(lock) {
+            val fn = commands[name] ?: throw ScriptError("no script command '$name'")
+            val result = lua { fn.call(LuaValue.valueOf(args)) }
+            return if (result.isnil()) null else result.tojstring()
+        }
+    }
+
+    private inline fun <T> lua(block: () -> T): T = try {
+        block()
+    } catch (e: LuaError) {
+        throw ScriptError(e.message ?: "script error")
+    }
+
+    private fun function(body: (Varargs) -> Varargs): LuaValue = object : VarArgFunction() {
+        override fun invoke(args: Varargs): Varargs = try {
+            body(args)
+        } catch (e: LuaError) {
+            throw e
+        } catch (e: Exception) {
+            throw LuaError(e.message ?: e.toString())
+        }
+    }
+}
+
+/**
+ * Lua globals with the libraries a script gets (see the file header):
+ * none that reach files, processes, or Java. The package library is
+ * loaded only for the others to register in, then removed.
+ */
+private fun sandboxedGlobals(): Globals = Globals().apply {
+    load(JseBaseLib())
+    load(PackageLib())
+    load(Bit32Lib())
+    load(TableLib())
+    load(StringLib())
+    load(JseMathLib())
+    LoadState.install(this)
+    LuaC.install(this)
+    for (name in listOf("dofile", "loadfile", "package", "require")) set(name, LuaValue.NIL)
+}
+
+/** [value] (JSON-like: maps, lists, strings, numbers, booleans) as a Lua value; lists are indexed from 1. */
+internal fun toLua(value: Any?): LuaValue = when (value) {
+    null, JSONObject.NULL -> LuaValue.NIL
+    is Map<*, *> -> LuaTable().also { table ->
+        for ((k, v) in value) if (k != null) table.set(k.toString(), toLua(v))
+    }
+    is List<*> -> LuaTable().also { table -> value.forEachIndexed { i, v -> table.set(i + 1, toLua(v)) } }
+    is Boolean -> LuaValue.valueOf(value)
+    is Int -> LuaValue.valueOf(value)
+    is Number -> LuaValue.valueOf(value.toDouble())
+    else -> LuaValue.valueOf(value.toString())
+}
+
+/** The breakpoint ID in the output of `breakpoint set`. */
+private val BREAKPOINT_SET_OUTPUT = Regex("""^Breakpoint (\d+):""", RegexOption.MULTILINE)
+
+/**
+ * [ScriptHost] for a session: API calls go to lldb-dap and LLDB through
+ * [ctx], the session's context, blocking the script's thread.
+ */
+private class SessionScriptHost(
+    private val session: DebugSession,
+    private val ctx: AsyncRequestContext,
+) : ScriptHost {
+    private fun <T> sb(block: suspend () -> T): T = runBlocking { block() }
+
+    private fun interpreter() = sb {
+        createDebugger(ctx).let { d -> session.sbWatcher?.let { d.watched(it) } ?: d }.commandInterpreter()
+    }
+
+    private fun backend(command: String, arguments: JSONObject): DapResponse = sb {
+        val request = JSONObject().put("type", "request").put("command", command).put("arguments", arguments)
+        ctx.sendRequestToBackendAndAwait(request.toString())
+    }.also { if (!it.success) throw ScriptError(it.message ?: "$command failed") }
+
+    override fun evaluate(expression: String, frameId: Int?): Map<String, Any?> {
+        val args = JSONObject().put("expression", expression).put("context", "watch")
+        if (frameId != null) args.put("frameId", frameId)
+        val response = sb { session.evaluateResponse(args, ctx) }
+        if (!response.success) throw ScriptError(response.message ?: "evaluate failed")
+        return response.body
+    }
+
+    override fun variables(reference: Int): List<Map<String, Any?>> =
+        (backend("variables", JSONObject().put("variablesReference", reference)).body["variables"] as? List<*>)
+            ?.mapNotNull { v -> (v as? Map<*, *>)?.entries?.associate { (k, value) -> k.toString() to value } }
+            ?: emptyList()
+
+    override fun readMemory(address: Long, count: Int): ByteArray {
+        val body = backend("readMemory", JSONObject().put("memoryReference", "0x%x".format(address)).put("count", count)).body
+        return (body["data"] as? String)?.let { Base64.getDecoder().decode(it) } ?: ByteArray(0)
+    }
+
+    override fun lldb(command: String): SBCommandReturnObject = sb { interpreter().handleCommandWithResult(command) }
+
+    override fun breakAt(file: String, line: Int): Int {
+        val result = lldb("breakpoint set --file ${quoteCommandArgument(file)} --line $line")
+        return BREAKPOINT_SET_OUTPUT.find(result.output)?.groupValues?.get(1)?.toInt()
+            ?: throw ScriptError(result.error.trim().ifEmpty { "no breakpoint set at $file:$line" })
+    }
+
+    override fun breakOn(function: String): Int = sb {
+        createDebugger(ctx).let { d -> session.sbWatcher?.let { d.watched(it) } ?: d }
+            .selectedTarget().breakpointCreateByName(function).id()
+    }
+
+    override fun deleteBreakpoint(id: Int): Boolean = sb {
+        createDebugger(ctx).let { d -> session.sbWatcher?.let { d.watched(it) } ?: d }
+            .selectedTarget().breakpointDelete(id)
+    }
+
+    override fun print(text: String) {
+        sb { ctx.sendEventToClient(OutputEvent.console(text).toJson()) }
+    }
+}
+
+/** [text] quoted for an LLDB command line. */
+private fun quoteCommandArgument(text: String): String =
+    "\"" + text.replace("\\", "\\\\").replace("\"", "\\\"") + "\""
+
+/**
+ * Loads the session's `kdap.init` script (see the file header) from the
+ * [initScript] path, if one is configured. A script that fails to load
+ * is reported in the Debug Console.
+ */
+internal suspend fun DebugSession.loadInitScript(initScript: String?, ctx: AsyncRequestContext) {
+    val file = File(initScript ?: return)
+    val script = KdapScript(SessionScriptHost(this, ctx))
+    try {
+        script.load(file.readText(), file.path)
+    } catch (e: Exception) {
+        log.warning { "Scripting: loading ${file.path} failed: ${e.message}" }
+        ctx.sendEventToClient(OutputEvent(seq = 0, category = "stderr", output = "$INIT_SCRIPT_NAME: ${e.message}\n").toJson())
+        return
+    }
+    this.script = script
+}
+
+/** [variables] from a `variables` response with the script's summaries as their values. */
+internal fun DebugSession.applyScriptSummaries(variables: List<Any?>): List<Any?> {
+    val script = script?.takeIf { it.hasSummaries } ?: return variables
+    return variables.map { variable ->
+        @Suppress("UNCHECKED_CAST")
+        val fields = variable as? Map<String, Any?> ?: return@map variable
+        val summary = try {
+            script.summarize(fields)
+        } catch (e: ScriptError) {
+            log.fine { "Scripting: summary of ${fields["name"]} failed: ${e.message}" }
+            null
+        }
+        if (summary == null) fields else fields + ("value" to summary)
+    }
+}
+
+/**
+ * Runs the script's stop hooks with [event] on the script's own
+ * coroutine, so the backend reader is not held up. Failures are shown in
+ * the Debug Console.
+ */
+fun DebugSession.onScriptStop(event: StoppedEvent) {
+    val script = script?.takeIf { it.hasStopHooks } ?: return
+    val body = JSONObject(event.toJson()).optJSONObject("body")?.toMap() ?: emptyMap()
+    scriptScope.launch {
+        try {
+            script.onStop(body)
+        } catch (e: ScriptError) {
+            script.host.print("$INIT_SCRIPT_NAME: ${e.message}\n")
+        }
+    }
+}
+
+/**
+ * Runs the Debug Console [command] if its first word names a script
+ * command, printing what it returned; false if it does not.
+ */
+internal suspend fun DebugSession.runScriptCommand(command: String, ctx: AsyncRequestContext): Boolean {
+    val script = script ?: return false
+    val name = command.substringBefore(' ')
+    if (!script.hasCommand(name)) return false
+    val args = command.substringAfter(' ', "").trim()
+    try {
+        script.runCommand(name, args)?.let { output ->
+            ctx.sendEventToClient(OutputEvent.console(if (output.endsWith("\n")) output else "$output\n").toJson())
+        }
+    } catch (e: ScriptError) {
+        ctx.sendEventToClient(OutputEvent(seq = 0, category = "stderr", output = "${e.message}\n").toJson())
+    }
+    return true
+}
@@ -0,0 +1,162 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.messages.SignalDisposition
+import com.github.jomof.dap.messages.toStringStringMap
+import com.github.jomof.dap.sb.*
+import org.json.JSONArray
+import org.json.JSONObject
+import java.util.logging.Logger
+
+/**
+ * Signal dispositions: what happens when the debuggee receives a signal.
+ * KDAP extension; CodeLLDB keeps LLDB's defaults, under which a program
+ * that signals itself often (`SIGUSR1`, `SIGPROF`, `SIGALRM`) stops the
+ * session every time.
+ *
+ * | Disposition | Stops | Console note | Delivered to the debuggee |
+ * |-------------|-------|--------------|---------------------------|
+ * | `stop`      | yes   | yes          | yes, on continue          |
+ * | `pass`      | no    | no           | yes                       |
+ * | `ignore`    | no    | no           | no                        |
+ *
+ * The table is set by `"signals": {"SIGUSR1": "pass"}` in the launch or
+ * attach configuration, applied once the process exists
+ * ([applySignalDispositions]), and changed while debugging by the custom
+ * `kdap/signals` request with the same `signals` argument
+ * ([handleSignals]). Its response lists every signal of the process with
+ * its disposition. Signals not in the table keep LLDB's defaults.
+ *
+ * Signal exception filters (Breakpoints.kt) take precedence: a filtered
+ * signal stops whatever its disposition, and goes back to its
+ * disposition when the filter is cleared.
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.Signals")
+
+/**
+ * Parses a `signals` object of signal names to dispositions. Throws
+ * [SBError] naming the first entry whose value is not a disposition.
+ */
+internal fun parseSignalDispositions(signals: Map<String, String>): Map<String, SignalDisposition> =
+    signals.mapValues { (name, value) ->
+        SignalDisposition.fromJson(value)
+            ?: throw SBError("\"signals\" value of \"$name\" must be \"pass\", \"stop\", or \"ignore\".")
+    }
+
+/** The disposition of a signal LLDB is set to [stop] or [suppress] for. */
+internal fun signalDisposition(stop: Boolean, suppress: Boolean): SignalDisposition = when {
+    stop -> SignalDisposition.Stop
+    suppress -> SignalDisposition.Ignore
+    else -> SignalDisposition.Pass
+}
+
+/**
+ * Applies [DebugSession.signalDispositions] to [process]. Call before
+ * [applySignalFilters], so filters remember the disposition's stop
+ * setting as the one to restore.
+ */
+internal suspend fun DebugSession.applySignalDispositions(process: SBProcess, ctx: AsyncRequestContext) {
+    if (signalDispositions.isEmpty()) return
+    val signals = process.unixSignals()
+    for ((name, disposition) in signalDispositions) {
+        val signo = signals.signalNumberFromName(name)
+        if (signo != null) setSignalDisposition(signals, signo, disposition) else consoleMessage("Unknown signal: $name", ctx)
+    }
+}
+
+/**
+ * Handles `kdap/signals` (see the file header). Without a process the
+ * dispositions are kept for the launch or attach; with one, an unknown
+ * signal name fails the request before anything changes.
+ */
+suspend fun DebugSession.handleSignals(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    try {
+        val requested = parseSignalDispositions(
+            obj.optJSONObject("arguments")?.optJSONObject("signals")?.toStringStringMap() ?: emptyMap())
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val process = debugger.selectedTarget().process()
+        if (!process.isValid()) {
+            signalDispositions.putAll(requested)
+            val table = signalDispositions.map { (name, disposition) ->
+                mapOf("name" to name, "disposition" to disposition.name.lowercase())
+            }
+            sendSuccessResponse(ctx, requestSeq, "kdap/signals", mapOf("signals" to table))
+            return
+        }
+        val signals = process.unixSignals()
+        val numbers = requested.keys.associateWith { name ->
+            signals.signalNumberFromName(name) ?: throw SBError("Unknown signal: $name")
+        }
+        signalDispositions.putAll(requested)
+        for ((name, disposition) in requested) setSignalDisposition(signals, numbers.getValue(name), disposition)
+        sendSuccessResponse(ctx, requestSeq, "kdap/signals", mapOf("signals" to signalTable(debugger)))
+    } catch (e: Exception) {
+        log.warning { "Signals: kdap/signals failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "kdap/signals", e.message ?: "Setting signal dispositions failed")
+    }
+}
+
+/**
+ * Sets LLDB's handling of [signo] for [disposition]. A signal a filter
+ * makes stop keeps stopping; the disposition's stop setting is what the
+ * filter restores.
+ */
+private suspend fun DebugSession.setSignalDisposition(
+    signals: SBUnixSignals,
+    signo: Int,
+    disposition: SignalDisposition,
+) {
+    val stop = disposition == SignalDisposition.Stop
+    signals.setShouldSuppress(signo, disposition == SignalDisposition.Ignore)
+    signals.setShouldNotify(signo, stop)
+    if (signalStopOverrides.containsKey(signo)) {
+        signalStopOverrides[signo] = stop
+    } else {
+        signals.setShouldStop(signo, stop)
+    }
+}
+
+/**
+ * Every signal of the selected process with its disposition, for the
+ * `kdap/signals` response. Filtered signals report their disposition,
+ * not the stop the filter forces.
+ */
+private suspend fun DebugSession.signalTable(debugger: SBDebugger): List<Map<String, Any>> {
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_SIGNAL_TABLE)})")
+    val table = JSONArray(interpreter.handleCommand("script print(_kdap_signal_table())").trim())
+    return (0 until table.length()).map { i ->
+        val entry = table.getJSONObject(i)
+        val signo = entry.getInt("number")
+        val stop = signalStopOverrides[signo] ?: entry.getBoolean("stop")
+        mapOf(
+            "name" to entry.getString("name"),
+            "number" to signo,
+            "disposition" to signalDisposition(stop, entry.getBoolean("suppress")).name.lowercase(),
+        )
+    }
+}
+
+/**
+ * Python side of [signalTable]: each signal of the selected process's
+ * platform, with LLDB's stop and suppress settings, as JSON.
+ */
+private val PY_SIGNAL_TABLE = """
+    import json
+
+    def _kdap_signal_table():
+        signals = lldb.debugger.GetSelectedTarget().GetProcess().GetUnixSignals()
+        table = []
+        for i in range(signals.GetNumSignals()):
+            signo = signals.GetSignalAtIndex(i)
+            table.append({
+                'name': signals.GetSignalAsCString(signo) or str(signo),
+                'number': signo,
+                'stop': signals.GetShouldStop(signo),
+                'suppress': signals.GetShouldSuppress(signo),
+            })
+        return json.dumps(table)
+""".trimIndent()
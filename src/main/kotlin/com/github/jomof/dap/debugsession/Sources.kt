@@ -0,0 +1,361 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.sb.SBError
+import com.github.jomof.dap.sb.pyStr
+import org.json.JSONArray
+import org.json.JSONObject
+import java.io.File
+import java.nio.file.Files
+import java.nio.file.Path
+import java.util.concurrent.TimeUnit
+import java.util.logging.Logger
+
+/**
+ * Source fetch: the text of stack frame sources that are not on disk,
+ * served with the `source` request. KDAP extension; CodeLLDB and
+ * lldb-dap only show such frames (the standard library of a release
+ * toolchain, code generated at build time, binaries built elsewhere) as
+ * missing files.
+ *
+ * When a `stackTrace` response (see StackFrames.kt) has frames whose
+ * `source.path` is not a file here, KDAP gives each such source a
+ * `sourceReference` ([assignFetchedSources]), keeping its path and name,
+ * so the client asks for its text with `source` instead of opening the
+ * path. References are numbered from [FETCHED_SOURCE_REFERENCE_BASE], far
+ * above those lldb-dap hands out for disassembly, and last the session;
+ * `source` requests for others go to lldb-dap. [handleSource] looks for
+ * the text, in order:
+ *
+ * 1. rust-src: a standard library path (`/rustc/<commit>/library/…`) is
+ *    read from the `rust-src` component of the rustup toolchain built
+ *    from that commit, under `RUSTUP_HOME` (default `~/.rustup`)
+ *    ([rustSourceDirectories]). Only the toolchain of the same commit
+ *    is used, so lines match.
+ * 2. Embedded source: DWARF 5 line tables can carry each file's text
+ *    with `DW_LNCT_LLVM_source` (`clang -gembed-source`, and generated
+ *    code whose build tools embed it). KDAP reads the `.debug_line` of
+ *    the frame's module for the file.
+ * 3. Source servers: `GET` on each URL template in `sourceServers`, with
+ *    `{buildId}` replaced by the module's build ID and `{path}` by the
+ *    path ([sourceServerUrls]). By default these are the debuginfod
+ *    servers' source endpoints, `<url>/buildid/{buildId}/source{path}`.
+ *
+ * If none has it, the `source` request fails with the path.
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.Sources")
+
+/** First `sourceReference` KDAP gives a fetched source. */
+internal const val FETCHED_SOURCE_REFERENCE_BASE = 1 shl 30
+
+/** Timeout of each source server request. */
+private const val SOURCE_SERVER_TIMEOUT_SECONDS = 10
+
+/** A standard library source path: the toolchain's commit and the path under `library/`. */
+private val RUST_SOURCE_PATH = Regex("(?:^|/)rustc/([0-9a-f]{40})/library/(.+)$")
+
+/** A fetched source: its [path] as the frame gave it, and the [module] the frame was in, if known. */
+data class FetchedSource(val path: String, val module: String?)
+
+/**
+ * The source server URL templates to use: [configured] if given, else
+ * the source endpoint of each debuginfod server in [debuginfodUrls].
+ */
+internal fun sourceServerUrls(configured: List<String>?, debuginfodUrls: List<String>): List<String> =
+    configured ?: debuginfodUrls.map { it.trimEnd('/') + "/buildid/{buildId}/source{path}" }
+
+/**
+ * The `library` directories of the rustup toolchains with `rust-src`
+ * installed, by the commit each toolchain's `rustc -vV` reports.
+ */
+internal fun rustSourceDirectories(environment: Map<String, String>, home: String): Map<String, Path> {
+    val rustupHome = environment["RUSTUP_HOME"]?.takeIf { it.isNotEmpty() } ?: "$home/.rustup"
+    val toolchains = File(rustupHome, "toolchains").listFiles().orEmpty()
+    val directories = mutableMapOf<String, Path>()
+    for (toolchain in toolchains.sortedBy { it.name }) {
+        val library = toolchain.toPath().resolve("lib/rustlib/src/rust/library")
+        if (!Files.isDirectory(library)) continue
+        val rustc = listOf("rustc", "rustc.exe").map { File(toolchain, "bin/$it") }.firstOrNull { it.isFile } ?: continue
+        val commit = try {
+            val process = ProcessBuilder(rustc.path, "-vV").redirectErrorStream(true).start()
+            val output = process.inputStream.bufferedReader().readText()
+            process.waitFor(10, TimeUnit.SECONDS)
+            rustcCommit(output)
+        } catch (e: Exception) {
+            log.warning { "Sources: reading ${rustc.path} -vV failed: ${e.message}" }
+            null
+        }
+        if (commit != null) directories.putIfAbsent(commit, library)
+    }
+    return directories
+}
+
+/** The `commit-hash` line of `rustc -vV` [output], if it has one. */
+internal fun rustcCommit(output: String): String? =
+    Regex("^commit-hash: ([0-9a-f]{40})$", RegexOption.MULTILINE).find(output)?.groupValues?.get(1)
+
+/**
+ * Where standard library source [path] is in the `rust-src` of
+ * [directories] (see [rustSourceDirectories]), or `null` if it is not a
+ * standard library path or no toolchain of its commit has it.
+ */
+internal fun rustSourceFile(path: String, directories: Map<String, Path>): Path? {
+    val match = RUST_SOURCE_PATH.find(path.replace('\\', '/')) ?: return null
+    val library = directories[match.groupValues[1]] ?: return null
+    return library.resolve(match.groupValues[2]).takeIf { Files.isRegularFile(it) }
+}
+
+/**
+ * Gives the sources of [frames] (a `stackTrace` response's) that are not
+ * on disk a KDAP `sourceReference` (see the file header). Sources of
+ * frames whose module can't be read are fetched without one.
+ */
+internal suspend fun DebugSession.assignFetchedSources(frames: List<Any?>, ctx: AsyncRequestContext): List<Any?> {
+    val missing = frames.mapNotNull { frame ->
+        val map = frame as? Map<*, *> ?: return@mapNotNull null
+        val source = map["source"] as? Map<*, *> ?: return@mapNotNull null
+        val path = source["path"] as? String ?: return@mapNotNull null
+        val reference = (source["sourceReference"] as? Number)?.toInt() ?: 0
+        if (reference > 0 || File(path).isFile) null else (map["id"] as? Number)?.toInt()
+    }
+    if (missing.isEmpty()) return frames
+    val modules = try {
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val interpreter = debugger.commandInterpreter()
+        interpreter.handleCommand("script exec(${pyStr(PY_SOURCES)})")
+        JSONObject(interpreter.handleCommand(
+            "script print(_kdap_frame_modules(${pyStr(JSONArray(missing).toString())}, $FRAME_INDEX_BITS))").trim())
+    } catch (e: Exception) {
+        log.warning { "Sources: reading frame modules failed: ${e.message}" }
+        JSONObject()
+    }
+    return frames.map { frame ->
+        val map = frame as? Map<*, *> ?: return@map frame
+        val id = (map["id"] as? Number)?.toInt()
+        if (id == null || id !in missing) return@map frame
+        val source = map["source"] as Map<*, *>
+        val fetched = FetchedSource(source["path"] as String, modules.optString(id.toString(), null))
+        val reference = fetchedSourceReferences.computeIfAbsent(fetched) {
+            (FETCHED_SOURCE_REFERENCE_BASE + nextFetchedSource.getAndIncrement()).also { fetchedSources[it] = fetched }
+        }
+        map + ("source" to source + ("sourceReference" to reference))
+    }
+}
+
+/** Whether [sourceReference] is one of KDAP's fetched sources. */
+fun DebugSession.isFetchedSource(sourceReference: Int): Boolean = sourceReference in fetchedSources
+
+/** Handles `source` for a fetched source: looks for its text (see the file header). */
+suspend fun DebugSession.handleSource(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val args = obj.optJSONObject("arguments")
+    val reference = args?.optJSONObject("source")?.optInt("sourceReference")?.takeIf { it > 0 }
+        ?: args?.optInt("sourceReference") ?: 0
+    val fetched = fetchedSources[reference]
+    if (fetched == null) {
+        sendErrorResponse(ctx, requestSeq, "source", "Unknown source reference $reference")
+        return
+    }
+
+    try {
+        val rustSource = rustSourceFile(fetched.path, rustSources)
+        if (rustSource != null) {
+            sendSuccessResponse(ctx, requestSeq, "source", mapOf("content" to Files.readString(rustSource)))
+            return
+        }
+        val request = JSONObject()
+            .put("path", fetched.path)
+            .put("module", fetched.module ?: "")
+            .put("servers", JSONArray(sourceServers))
+            .put("timeout", SOURCE_SERVER_TIMEOUT_SECONDS)
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val interpreter = debugger.commandInterpreter()
+        interpreter.handleCommand("script exec(${pyStr(PY_SOURCES)})")
+        val content = interpreter.handleCommand(
+            "script print(_kdap_fetch_source(${pyStr(request.toString())}))").trim()
+        if (content.isEmpty()) throw SBError(missingSourceMessage(fetched.path))
+        sendSuccessResponse(ctx, requestSeq, "source", mapOf("content" to JSONObject(content).getString("content")))
+    } catch (e: Exception) {
+        log.warning { "Sources: source failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "source", e.message ?: "Could not read ${fetched.path}")
+    }
+}
+
+/** Why the text of [path] could not be found, with what would provide it. */
+internal fun missingSourceMessage(path: String): String {
+    val commit = RUST_SOURCE_PATH.find(path.replace('\\', '/'))?.groupValues?.get(1)
+    return if (commit != null) {
+        "Could not find $path: no rustup toolchain of commit ${commit.take(9)} has rust-src " +
+            "(rustup component add rust-src --toolchain <that version>)."
+    } else {
+        "Could not find $path here, embedded in the debug info, or on a source server."
+    }
+}
+
+/**
+ * Python side of [assignFetchedSources] and [handleSource].
+ * `_kdap_frame_modules` maps the frame IDs to their modules' paths.
+ * `_kdap_fetch_source` returns `{"content": …}`, or nothing: from the
+ * module's `.debug_line` (version 5 units whose file entries have
+ * `DW_LNCT_LLVM_source`, matched by full path, else by the path's end),
+ * then from the source servers.
+ */
+private val PY_SOURCES = """
+    import json, os, struct, urllib.parse, urllib.request
+
+    _KDAP_LNCT_PATH = 1
+    _KDAP_LNCT_DIRECTORY_INDEX = 2
+    _KDAP_LNCT_LLVM_SOURCE = 0x2001
+    _KDAP_FORM_SIZES = {0x0b: 1, 0x05: 2, 0x06: 4, 0x07: 8, 0x1e: 16, 0x25: 1, 0x26: 2, 0x27: 3, 0x28: 4}
+    _KDAP_FORM_BLOCKS = {0x0a: 1, 0x03: 2, 0x04: 4}
+
+    def _kdap_frame_modules(frame_ids_json, bits):
+        process = lldb.debugger.GetSelectedTarget().GetProcess()
+        modules = {}
+        for frame_id in json.loads(frame_ids_json):
+            thread = process.GetThreadByIndexID(frame_id >> bits)
+            frame = thread.GetFrameAtIndex(frame_id & ((1 << bits) - 1))
+            if frame.IsValid() and frame.GetModule().IsValid():
+                modules[str(frame_id)] = frame.GetModule().GetFileSpec().fullpath
+        return json.dumps(modules)
+
+    def _kdap_section_bytes(module, name):
+        section = module.FindSection(name)
+        if not section.IsValid():
+            return b''
+        error = lldb.SBError()
+        data = section.GetSectionData()
+        if data.GetByteSize() == 0:
+            return b''
+        raw = data.ReadRawData(error, 0, data.GetByteSize())
+        return raw if error.Success() and raw else b''
+
+    def _kdap_uleb(data, offset):
+        result = shift = 0
+        while True:
+            byte = data[offset]
+            offset += 1
+            result |= (byte & 0x7f) << shift
+            shift += 7
+            if byte < 0x80:
+                return result, offset
+
+    def _kdap_cstr(data, offset):
+        end = data.index(b'\0', offset)
+        return data[offset:end].decode('utf-8', 'replace'), end + 1
+
+    def _kdap_line_form(data, offset, form, order, offset_size, strings):
+        if form == 0x08:
+            return _kdap_cstr(data, offset)
+        if form in (0x1f, 0x0e):
+            (value,) = struct.unpack_from(order + ('Q' if offset_size == 8 else 'I'), data, offset)
+            table = strings[form]
+            return (_kdap_cstr(table, value)[0] if value < len(table) else None), offset + offset_size
+        if form in (0x0f, 0x1a):
+            value, offset = _kdap_uleb(data, offset)
+            return (value if form == 0x0f else None), offset
+        if form in _KDAP_FORM_SIZES:
+            size = _KDAP_FORM_SIZES[form]
+            value = int.from_bytes(data[offset:offset + size], 'big' if order == '>' else 'little')
+            return (value if form in (0x0b, 0x05, 0x06, 0x07) else None), offset + size
+        if form == 0x09:
+            size, offset = _kdap_uleb(data, offset)
+            return None, offset + size
+        if form in _KDAP_FORM_BLOCKS:
+            width = _KDAP_FORM_BLOCKS[form]
+            size = int.from_bytes(data[offset:offset + width], 'big' if order == '>' else 'little')
+            return None, offset + width + size
+        raise ValueError('unsupported form %#x' % form)
+
+    def _kdap_line_entries(data, offset, order, offset_size, strings):
+        formats = []
+        count = data[offset]
+        offset += 1
+        for _ in range(count):
+            kind, offset = _kdap_uleb(data, offset)
+            form, offset = _kdap_uleb(data, offset)
+            formats.append((kind, form))
+        entries = []
+        count, offset = _kdap_uleb(data, offset)
+        for _ in range(count):
+            entry = {}
+            for kind, form in formats:
+                entry[kind], offset = _kdap_line_form(data, offset, form, order, offset_size, strings)
+            entries.append(entry)
+        return entries, offset
+
+    def _kdap_embedded_sources(module):
+        data = _kdap_section_bytes(module, '.debug_line')
+        strings = {0x1f: _kdap_section_bytes(module, '.debug_line_str'), 0x0e: _kdap_section_bytes(module, '.debug_str')}
+        order = '>' if module.GetByteOrder() == lldb.eByteOrderBig else '<'
+        offset = 0
+        while offset + 4 <= len(data):
+            (length,) = struct.unpack_from(order + 'I', data, offset)
+            offset += 4
+            offset_size = 4
+            if length == 0xffffffff:
+                (length,) = struct.unpack_from(order + 'Q', data, offset)
+                offset += 8
+                offset_size = 8
+            end = offset + length
+            (version,) = struct.unpack_from(order + 'H', data, offset)
+            if version >= 5:
+                try:
+                    header = offset + 4 + offset_size
+                    cursor = header + 6 + data[header + 5] - 1
+                    directories, cursor = _kdap_line_entries(data, cursor, order, offset_size, strings)
+                    files, cursor = _kdap_line_entries(data, cursor, order, offset_size, strings)
+                except (IndexError, ValueError, struct.error):
+                    files = []
+                for entry in files:
+                    source = entry.get(_KDAP_LNCT_LLVM_SOURCE)
+                    if not source:
+                        continue
+                    index = entry.get(_KDAP_LNCT_DIRECTORY_INDEX) or 0
+                    directory = directories[index].get(_KDAP_LNCT_PATH) or '' if index < len(directories) else ''
+                    if not os.path.isabs(directory) and directories:
+                        directory = os.path.join(directories[0].get(_KDAP_LNCT_PATH) or '', directory)
+                    yield os.path.normpath(os.path.join(directory, entry.get(_KDAP_LNCT_PATH) or '')), source
+            offset = end
+
+    def _kdap_embedded_source(module, path):
+        path = os.path.normpath(path)
+        best = None
+        for name, source in _kdap_embedded_sources(module):
+            if name == path:
+                return source
+            if best is None and (path.endswith(os.sep + name.lstrip(os.sep)) or name.endswith(os.sep + path.lstrip(os.sep))):
+                best = source
+        return best
+
+    def _kdap_server_source(servers, build_id, path, timeout):
+        for template in servers:
+            if '{buildId}' in template and not build_id:
+                continue
+            url = template.replace('{buildId}', build_id).replace('{path}', urllib.parse.quote(path))
+            try:
+                request = urllib.request.Request(url, headers={'User-Agent': 'kdap'})
+                with urllib.request.urlopen(request, timeout=timeout) as response:
+                    return response.read().decode('utf-8', 'replace')
+            except Exception:
+                pass
+        return None
+
+    def _kdap_fetch_source(request_json):
+        request = json.loads(request_json)
+        target = lldb.debugger.GetSelectedTarget()
+        module = target.FindModule(lldb.SBFileSpec(request['module'], False)) if request['module'] else lldb.SBModule()
+        content = None
+        build_id = ''
+        if module.IsValid():
+            try:
+                content = _kdap_embedded_source(module, request['path'])
+            except Exception:
+                content = None
+            build_id = module.GetUUIDString().replace('-', '').lower()
+        if content is None:
+            content = _kdap_server_source(request['servers'], build_id, request['path'], request['timeout'])
+        return '' if content is None else json.dumps({'content': content})
+""".trimIndent()
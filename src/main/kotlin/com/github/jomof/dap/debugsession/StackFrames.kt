@@ -0,0 +1,239 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.messages.InternalFrames
+import com.github.jomof.dap.sb.pyStr
+import org.json.JSONArray
+import org.json.JSONObject
+import java.util.concurrent.ConcurrentHashMap
+import java.util.logging.Logger
+
+/**
+ * Stack traces: lldb-dap's `stackTrace` responses with inlined frames
+ * marked and runtime internals set apart. KDAP extension; CodeLLDB shows
+ * every frame alike.
+ *
+ * LLDB splits each frame it unwinds into the chain of calls inlined at
+ * its PC, from the `DW_TAG_inlined_subroutine` entries of the function's
+ * DWARF, so an optimized Rust build shows the logical call chain:
+ * `Iterator::fold`, the closure inlined into it, and the function that
+ * called `fold`, all at one machine frame. lldb-dap lists these frames but
+ * does not say which are inlined. KDAP sends `stackTrace` on itself
+ * ([handleStackTrace]) and marks each inlined frame with
+ * `presentationHint: "subtle"` and [INLINED_FRAME_SUFFIX] after its name
+ * ([markInlinedFrames]), so the client shows which calls have no frame of
+ * their own.
+ *
+ * Stepping treats inlined frames as real frames. LLDB steps into an
+ * inlined call at its call site, and out of one at the end of its code,
+ * without running the debuggee past it; so `stepIn` on a line that calls
+ * an inlined function enters it, and `stepOut` from an inlined frame
+ * stops at the next line of the function it was inlined into. KDAP's
+ * step-in targets (Stepping.kt) list the inlined calls on the line next
+ * to the direct ones.
+ *
+ * ## Runtime internals
+ *
+ * A panic stops in `rust_panic` under a dozen frames of `std::panicking`,
+ * `core::panicking`, and the unwinder before the user's code. Frames of
+ * the Rust standard library (`std`, `core`, `alloc`, their sources under
+ * `/rustc/<commit>/library/`), the panic runtime and unwinder, the
+ * allocator shims, the C++ standard library and ABI runtime (`std::`,
+ * `__gnu_cxx::`, `__cxxabiv1::`, `__cxa_throw`, headers under
+ * `include/c++/`), the Kotlin/Native standard library, bridges, and
+ * runtime (`kotlin.`, `Konan_`; see KotlinNative.kt), wasmtime and its
+ * Cranelift compiler (`wasmtime::`, `cranelift_codegen::`; see
+ * Wasm.kt), the stack protector's and UBSan's error reporters, and
+ * libc's start-up, thread, and signal code are runtime internals
+ * ([isInternalFrame]).
+ * `_adapterSettings.internalFrames` picks how they are shown
+ * ([InternalFrames]):
+ *
+ * | Setting    | Internal frames                                          |
+ * |------------|----------------------------------------------------------|
+ * | `show`     | as lldb-dap reports them                                 |
+ * | `subtle`   | `presentationHint: "subtle"`, source `"deemphasize"`     |
+ * | `collapse` | each run of two or more replaced by [INTERNAL_FRAMES_LABEL] |
+ *
+ * `subtle` is the default; VS Code groups de-emphasized frames and
+ * selects the first frame that is not. A collapsed run becomes a `label`
+ * frame with the ID of its first frame, and a single internal frame is
+ * shown as with `subtle`. Since the client pages by the frames it was
+ * sent, KDAP remembers for each thread where in lldb-dap's frames each
+ * page ends ([DebugSession.collapsedFramePages]) and asks lldb-dap for
+ * the next page from there, lowering `totalFrames` by the frames hidden
+ * so far.
+ *
+ * At a `pause` stop (see Stepping.kt) a thread's top frame is where it
+ * is blocked, usually a libc system call wrapper such as
+ * `__GI___clock_nanosleep`; it is never collapsed, only made subtle.
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.StackFrames")
+
+/** Appended to the name of an inlined frame. */
+internal const val INLINED_FRAME_SUFFIX = " [inlined]"
+
+/** Name of the frame that stands for a collapsed run of internal frames. */
+internal const val INTERNAL_FRAMES_LABEL = "[std internals]"
+
+/**
+ * Function name prefixes of runtime internals. Trait impls such as
+ * `<alloc::boxed::Box<F> as core::ops::function::Fn<A>>::call` are
+ * matched on their self type. C++ names are the demangled ones LLDB
+ * reports, e.g. `std::vector<int>::push_back(int const&)`, and Kotlin
+ * names the ones [demangleKotlinName] makes; Kotlin bridges
+ * (`Foo.$<bridge-UNN>bar(…)`) match anywhere in the name.
+ */
+internal val INTERNAL_FRAME_NAME = Regex(
+    "^<*((std|core|alloc|panic_unwind|panic_abort|__gnu_cxx|__cxxabiv1|kotlin|wasmtime\\w*|cranelift\\w*)::|" +
+        "__cxa_|__gxx_personality|__cxx_|wasmtime_|" +
+        "kotlin\\.|Konan_|Kotlin_|Init_and_run_start\\b|__rust_|__rdl_|__rg_|rust_panic|rust_begin_unwind|" +
+        "rust_eh_personality|_Unwind_|__gcc_personality|__libc_|__GI_|_dl_|__pthread|pthread_kill|start_thread|" +
+        "__clone|clone3?\\b|__restore_rt|_start\\b|raise\\b|abort\\b|malloc\\b|calloc\\b|realloc\\b|free\\b|" +
+        "__stack_chk_fail|__ubsan_handle_)|\\$<bridge-"
+)
+
+/**
+ * Source paths of the Rust standard library, as remapped in release
+ * toolchains or in a `rust-src` checkout, and of the libstdc++ and
+ * libc++ headers (`/usr/include/c++/13/`, `…/include/c++/v1/`).
+ */
+internal val INTERNAL_FRAME_PATH = Regex("(^|/)rustc/[0-9a-f]{40}/library/|/lib/rustlib/src/rust/library/|/include/c\\+\\+/")
+
+/**
+ * Handles `stackTrace`: forwards it to lldb-dap, from the lldb-dap frame
+ * the client's `startFrame` stands for, then marks the inlined frames of
+ * the response and presents its runtime internals (see the file header),
+ * shows frames without source as disassembly (see Disassembly.kt), and
+ * gives sources not on disk a reference to fetch them by (see
+ * Sources.kt). Kotlin/Native names are demangled first. If the inlined
+ * frames can't be read, none are marked.
+ */
+suspend fun DebugSession.handleStackTrace(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val args = obj.optJSONObject("arguments") ?: JSONObject()
+    val threadId = args.optInt("threadId")
+    val clientStart = args.optInt("startFrame", 0)
+    val pages = if (internalFrames == InternalFrames.Collapse) {
+        collapsedFramePages.getOrPut(threadId) { ConcurrentHashMap() }
+    } else {
+        null
+    }
+    val backendStart = pages?.get(clientStart) ?: clientStart
+    if (backendStart != clientStart) obj.put("arguments", args.put("startFrame", backendStart))
+    val response = ctx.sendRequestToBackendAndAwait(obj.toString()).copy(seq = 0, requestSeq = requestSeq)
+    onStackTraceResponse(response)
+    val frames = response.body["stackFrames"] as? List<*>
+    if (!response.success || frames.isNullOrEmpty()) {
+        ctx.sendEventToClient(response.toJson())
+        return
+    }
+    val inlined = try {
+        val ids = frames.mapNotNull { ((it as? Map<*, *>)?.get("id") as? Number)?.toInt() }
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val interpreter = debugger.commandInterpreter()
+        interpreter.handleCommand("script exec(${pyStr(PY_STACK_FRAMES)})")
+        val output = interpreter.handleCommand(
+            "script print(_kdap_inlined_frames(${pyStr(JSONArray(ids).toString())}, $FRAME_INDEX_BITS))").trim()
+        JSONArray(output).let { array -> (0 until array.length()).map { array.getInt(it) }.toSet() }
+    } catch (e: Exception) {
+        log.warning { "StackFrames: reading inlined frames failed: ${e.message}" }
+        emptySet()
+    }
+    val presented = presentInternalFrames(markInlinedFrames(demangleKotlinFrames(frames), inlined), internalFrames,
+        keepTop = pausedStop && backendStart == 0)
+    val disassembled = assignDisassemblySources(presented, threadId.takeIf { clientStart == 0 }, ctx)
+    val marked = assignFetchedSources(disassembled, ctx)
+    val body = response.body.toMutableMap()
+    body["stackFrames"] = marked
+    if (pages != null) {
+        pages[clientStart + marked.size] = backendStart + frames.size
+        val hidden = backendStart + frames.size - (clientStart + marked.size)
+        (body["totalFrames"] as? Number)?.let { body["totalFrames"] = it.toInt() - hidden }
+    }
+    ctx.sendEventToClient(response.copy(body = body).toJson())
+}
+
+/**
+ * Whether the `stackTrace` [frame] is a runtime internal (see the file
+ * header): its function name is one of the runtime's, or its source is
+ * the standard library's.
+ */
+internal fun isInternalFrame(frame: Map<*, *>): Boolean {
+    val name = frame["name"] as? String ?: ""
+    val path = (frame["source"] as? Map<*, *>)?.get("path") as? String ?: ""
+    return INTERNAL_FRAME_NAME.containsMatchIn(name) || INTERNAL_FRAME_PATH.containsMatchIn(path.replace('\\', '/'))
+}
+
+/**
+ * [frames] with their runtime internals shown as [mode] says (see the
+ * file header). With [keepTop], the first frame is never collapsed.
+ */
+internal fun presentInternalFrames(frames: List<Any?>, mode: InternalFrames, keepTop: Boolean = false): List<Any?> {
+    if (mode == InternalFrames.Show) return frames
+    val result = mutableListOf<Any?>()
+    var run = mutableListOf<Map<*, *>>()
+    fun flush() {
+        if (mode == InternalFrames.Collapse && run.size >= 2) {
+            result += mapOf(
+                "id" to run.first()["id"],
+                "name" to INTERNAL_FRAMES_LABEL,
+                "line" to 0,
+                "column" to 0,
+                "presentationHint" to "label",
+            )
+        } else {
+            run.mapTo(result) { subtleFrame(it) }
+        }
+        run = mutableListOf()
+    }
+    for ((index, frame) in frames.withIndex()) {
+        if (frame is Map<*, *> && isInternalFrame(frame)) {
+            if (index == 0 && keepTop) result += subtleFrame(frame) else run += frame
+        } else {
+            flush()
+            result += frame
+        }
+    }
+    flush()
+    return result
+}
+
+/** [frame] de-emphasized: a subtle frame whose source, if any, is de-emphasized. */
+private fun subtleFrame(frame: Map<*, *>): Map<*, *> {
+    val source = frame["source"] as? Map<*, *>
+    val subtle = frame + ("presentationHint" to "subtle")
+    return if (source == null) subtle else subtle + ("source" to source + ("presentationHint" to "deemphasize"))
+}
+
+/**
+ * [frames] from a `stackTrace` response, with the frames whose IDs are in
+ * [inlined] marked as inlined (see the file header).
+ */
+internal fun markInlinedFrames(frames: List<*>, inlined: Set<Int>): List<Any?> = frames.map { frame ->
+    val map = frame as? Map<*, *> ?: return@map frame
+    val id = (map["id"] as? Number)?.toInt()
+    if (id == null || id !in inlined) return@map frame
+    map + mapOf("name" to "${map["name"] ?: ""}$INLINED_FRAME_SUFFIX", "presentationHint" to "subtle")
+}
+
+/**
+ * Python side of [handleStackTrace]: which of the lldb-dap frame IDs in
+ * `frame_ids` (thread index ID above `bits` bits of frame index) are
+ * inlined frames.
+ */
+private val PY_STACK_FRAMES = """
+    import json
+
+    def _kdap_inlined_frames(frame_ids_json, bits):
+        process = lldb.debugger.GetSelectedTarget().GetProcess()
+        inlined = []
+        for frame_id in json.loads(frame_ids_json):
+            thread = process.GetThreadByIndexID(frame_id >> bits)
+            frame = thread.GetFrameAtIndex(frame_id & ((1 << bits) - 1))
+            if frame.IsValid() and frame.IsInlined():
+                inlined.append(frame_id)
+        return json.dumps(inlined)
+""".trimIndent()
@@ -0,0 +1,256 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.messages.StaticsScope
+import com.github.jomof.dap.sb.createDebugger
+import com.github.jomof.dap.sb.pyStr
+import com.github.jomof.dap.sb.watched
+import org.json.JSONObject
+import java.util.logging.Logger
+
+/**
+ * The Statics scope: global and static variables of a frame. KDAP
+ * extension; CodeLLDB shows them in its Static and Global scopes.
+ *
+ * lldb-dap's Globals scope lists the statics of the frame's compile unit
+ * that are in scope. A Rust `thread_local!` shows there as an opaque
+ * `LocalKey` holding an accessor function; its value lives in a
+ * `#[thread_local]` static that std generates inside the macro
+ * expansion. KDAP replaces the scope with its own ([withStaticsScope]),
+ * read in Python from the frame ([sendStatics]):
+ *
+ * - Every static of the frame's compile unit, including function
+ *   statics, sorted by qualified name. They are decoded through their
+ *   DWARF locations in the frame's thread, so TLS-relative statics show
+ *   that thread's value.
+ * - A `LocalKey` static shows the value of its storage static, unwrapped
+ *   from std's storage types: `<uninitialized>` before the thread first
+ *   used it, `<destroyed>` after its destructor ran.
+ * - With `_adapterSettings.staticsScope` set to `"all"`, the statics of
+ *   all other modules too, at most [MAX_STATICS] in all. LLDB reads those
+ *   without a thread, so their thread-locals are not shown.
+ *
+ * Each static has its qualified name as `evaluateName`, so
+ * `setExpression` writes it. Children are read by KDAP, not lldb-dap:
+ * the Python side keeps the `SBValue` behind each reference from
+ * [FIRST_STATIC_VALUE_REFERENCE] up until the process resumes.
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.Statics")
+
+/** First `variablesReference` of Statics scopes, above the Arguments and Captures scopes. */
+internal const val FIRST_STATICS_REFERENCE = 3 shl 29
+
+/** First `variablesReference` of values under a Statics scope, handed out by the Python side. */
+internal const val FIRST_STATIC_VALUE_REFERENCE = 7 shl 28
+
+/** Statics listed at most in one Statics scope. */
+internal const val MAX_STATICS = 5_000
+
+/** Name of lldb-dap's scope that the Statics scope replaces. */
+private const val LLDB_DAP_GLOBALS = "Globals"
+
+/**
+ * Replaces lldb-dap's Globals scope in a `scopes` [response] with a
+ * Statics scope for the frame the request asked about. Call before
+ * [onScopesResponse], which forgets the request.
+ */
+fun DebugSession.withStaticsScope(response: DapResponse): DapResponse {
+    val frameId = pendingScopes[response.requestSeq] ?: return response
+    if (!response.success) return response
+    val scopes = response.body["scopes"] as? List<*> ?: return response
+    val patched = scopes.map { scope ->
+        if (scope !is Map<*, *> || scope["name"] != LLDB_DAP_GLOBALS) return@map scope
+        val reference = staticsScopes.handleFor(frameId)
+        scope.entries.associate { (k, v) -> k.toString() to v }
+            .minus(listOf("namedVariables", "indexedVariables"))
+            .plus(listOf("name" to "Statics", "variablesReference" to reference))
+    }
+    return response.copy(body = response.body + ("scopes" to patched))
+}
+
+/** Forgets the Statics scopes; like lldb-dap's references, they are only valid while stopped. */
+fun DebugSession.forgetStaticsScopes() {
+    staticsScopes.reset()
+}
+
+/** Whether [reference] is a Statics scope or a value under one. */
+internal fun isStaticsReference(reference: Int): Boolean = reference >= FIRST_STATICS_REFERENCE
+
+/**
+ * Responds to a `variables` request for a Statics scope (all of its
+ * statics) or a value under one ([count] children from [start], or all).
+ */
+internal suspend fun DebugSession.sendStatics(
+    requestSeq: Int,
+    reference: Int,
+    start: Int,
+    count: Int,
+    ctx: AsyncRequestContext,
+) {
+    val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_STATICS)})")
+    val frameId = staticsScopes[reference]
+    val command = if (frameId != null) {
+        val everywhere = if (staticsScope == StaticsScope.All) "True" else "False"
+        "script print(_kdap_statics(${framePython(frameId)}, $everywhere, $MAX_STATICS, $FIRST_STATIC_VALUE_REFERENCE))"
+    } else {
+        "script print(_kdap_static_children($reference, $start, $count, $FIRST_STATIC_VALUE_REFERENCE))"
+    }
+    val result = JSONObject(interpreter.handleCommand(command).trim())
+    if (result.has("error")) {
+        log.fine { "Statics: ${result.getString("error")}" }
+        sendErrorResponse(ctx, requestSeq, "variables", result.getString("error"))
+        return
+    }
+    val variables = result.getJSONArray("variables").map { (it as JSONObject).toMap() }
+    for (variable in variables) {
+        val indexed = (variable["indexedVariables"] as? Number)?.toInt() ?: continue
+        (variable["variablesReference"] as? Number)?.toInt()?.let { indexedVariableCounts[it] = indexed }
+    }
+    sendSuccessResponse(ctx, requestSeq, "variables", mapOf("variables" to variables))
+}
+
+/**
+ * Python side of [sendStatics]. `_kdap_statics` lists a frame's statics
+ * and `_kdap_static_children` a value's children, each as a JSON object
+ * with the `variables` of a `variables` response or an `error`. Values
+ * with children are kept in `_kdap_statics_nodes` until the stop ID
+ * changes. Uses the Rust formatters' `_kdap_display` and enum helpers.
+ */
+private val PY_STATICS = """
+    import json, re
+
+    if '_kdap_statics_nodes' not in globals():
+        _kdap_statics_nodes = {'stop': None, 'next': 0, 'values': {}}
+
+    _KDAP_STATIC_HASH = re.compile(r'::h[0-9a-f]{16}${'$'}')
+    _KDAP_STATIC_PATH = re.compile(r'^[A-Za-z_]\w*(::[A-Za-z_]\w*)*${'$'}')
+    _KDAP_TLS_KEY = re.compile(r'^std::thread::local::LocalKey<')
+    _KDAP_TLS_STORAGE = re.compile(r'^std::(sys|thread)::\S*(Storage|Key|KeyInner)<')
+    _KDAP_TLS_CELL = re.compile(r'::(VAL|__KEY)${'$'}')
+
+    def _kdap_statics_state(process, first):
+        stop = process.GetStopID()
+        if _kdap_statics_nodes['stop'] != stop:
+            _kdap_statics_nodes.update(stop=stop, next=first, values={})
+        return _kdap_statics_nodes
+
+    def _kdap_static_name(v):
+        return _KDAP_STATIC_HASH.sub('', v.GetName() or '')
+
+    def _kdap_tls_unwrap(v):
+        # The value in a thread-local storage static, or None and the
+        # state name if the thread holds no value.
+        inside = False
+        for _ in range(8):
+            name = v.GetTypeName() or ''
+            raw = v.GetNonSyntheticValue()
+            if _KDAP_TLS_STORAGE.match(name):
+                inside = True
+                for field in ('val', 'state', 'inner'):
+                    child = raw.GetChildMemberWithName(field)
+                    if child.IsValid():
+                        v = child
+                        break
+                else:
+                    return v, None
+            elif name.startswith('core::cell::UnsafeCell<'):
+                v = raw.GetChildMemberWithName('value')
+            elif inside and _kdap_is_rust_enum(raw.GetType().GetCanonicalType(), None):
+                active = _kdap_enum_active(raw)
+                if active is None:
+                    return v, None
+                variant, fields = active
+                if variant not in ('Alive', 'Some') or not fields:
+                    return None, 'uninitialized' if variant in ('Initial', 'None') else 'destroyed'
+                v = fields[0]
+                inside = False
+            else:
+                return v, None
+        return v, None
+
+    def _kdap_static_child_path(parent, child):
+        name = child.GetName() or ''
+        if parent is None or not name:
+            return None
+        if name.startswith('['):
+            return parent + name
+        if name.startswith('*'):
+            return '(*%s)' % parent
+        if name.startswith('__') and name[2:].isdigit():
+            return '%s.%s' % (parent, name[2:])
+        if re.match(r'^[A-Za-z_]\w*${'$'}', name):
+            return '%s.%s' % (parent, name)
+        return None
+
+    def _kdap_static_entry(state, name, v, path):
+        entry = {'name': name, 'type': v.GetDisplayTypeName() or '', 'variablesReference': 0}
+        if v.GetError().Fail():
+            entry['value'] = '<%s>' % v.GetError().GetCString()
+            return entry
+        entry['value'] = _kdap_display(v)
+        if path is not None:
+            entry['evaluateName'] = path
+        if v.MightHaveChildren():
+            reference = state['next']
+            state['next'] += 1
+            state['values'][reference] = (v, path)
+            entry['variablesReference'] = reference
+            count = v.GetNumChildren()
+            if count > 0 and v.GetChildAtIndex(0).GetName() == '[0]':
+                entry['indexedVariables'] = count
+        return entry
+
+    def _kdap_statics(frame, everywhere, limit, first):
+        try:
+            state = _kdap_statics_state(frame.GetThread().GetProcess(), first)
+            values = list(frame.GetVariables(False, False, True, False))
+            if everywhere:
+                target = frame.GetThread().GetProcess().GetTarget()
+                found = target.FindGlobalVariables('.', limit, lldb.eMatchTypeRegex)
+                values += [found.GetValueAtIndex(i) for i in range(found.GetSize())]
+            statics = {}
+            for v in values:
+                statics.setdefault(_kdap_static_name(v), v)
+            storage = set()
+            entries = []
+            for name, v in sorted(statics.items()):
+                if len(entries) >= limit:
+                    break
+                if not name or '{vtable}' in name or name in storage:
+                    continue
+                if _KDAP_TLS_KEY.match(v.GetTypeName() or ''):
+                    cell = next((n for n in statics if n.startswith(name + '::') and _KDAP_TLS_CELL.search(n)), None)
+                    if cell is not None:
+                        storage.add(cell)
+                        value, missing = _kdap_tls_unwrap(statics[cell])
+                        if value is None:
+                            entries.append({'name': name, 'value': '<%s>' % missing,
+                                            'type': v.GetDisplayTypeName() or '', 'variablesReference': 0})
+                        else:
+                            entries.append(_kdap_static_entry(state, name, value, None))
+                        continue
+                path = name if _KDAP_STATIC_PATH.match(name) else None
+                entries.append(_kdap_static_entry(state, name, v, path))
+            return json.dumps({'variables': entries})
+        except Exception as e:
+            return json.dumps({'error': str(e)})
+
+    def _kdap_static_children(reference, start, count, first):
+        try:
+            state = _kdap_statics_state(lldb.debugger.GetSelectedTarget().GetProcess(), first)
+            node = state['values'].get(reference)
+            if node is None:
+                return json.dumps({'error': 'The value is no longer available'})
+            v, path = node
+            n = v.GetNumChildren()
+            end = n if count <= 0 else min(n, start + count)
+            children = (v.GetChildAtIndex(i) for i in range(start, end))
+            return json.dumps({'variables': [
+                _kdap_static_entry(state, c.GetName() or '', c, _kdap_static_child_path(path, c)) for c in children]})
+        except Exception as e:
+            return json.dumps({'error': str(e)})
+""".trimIndent()
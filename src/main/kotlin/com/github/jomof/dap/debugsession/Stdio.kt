@@ -0,0 +1,175 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.LldbDapProcess
+import com.github.jomof.dap.kdapMarker
+import com.github.jomof.dap.sb.*
+import org.json.JSONObject
+import java.util.logging.Logger
+
+/**
+ * Debuggee stdio extensions: PTY-backed output (`"pty": true` on launch)
+ * and stdin forwarding (the `kdap/stdin` request). These are KDAP
+ * extensions; CodeLLDB has no counterpart.
+ *
+ * ## PTY-backed stdio
+ *
+ * KDAP opens a pseudo-terminal in lldb-dap's process and launches the
+ * debuggee with its secondary side as stdin, stdout, and stderr, the way
+ * [configureStdio] uses a client terminal's TTY. A reader thread copies
+ * everything written to the primary side, in the order it was written
+ * and with carriage returns intact, to the debugger's output stream
+ * prefixed with [PTY_OUTPUT_MARKER];
+ * [com.github.jomof.dap.interception.OutputCategoryNormalizer] turns
+ * those chunks into `stdout` output events. The debuggee sees a terminal,
+ * so `isatty` checks and progress output behave as they would in a shell.
+ *
+ * Output post-processing (`OPOST`) is turned off so `\n` is not rewritten
+ * to `\r\n`, and echo is off because the client already shows what it
+ * sends. Both streams share the terminal, so stderr is reported as
+ * `stdout` too. Windows has no pseudo-terminals of this kind, so a `pty`
+ * launch fails there with an error naming the alternatives.
+ *
+ * ## Stdin forwarding
+ *
+ * `kdap/stdin` (`{"text": "..."}`) writes its text to the debuggee's
+ * stdin: through the KDAP pty when there is one, otherwise with
+ * `SBProcess::PutSTDIN`, which reaches the terminal LLDB itself creates
+ * for a local launch without stdio redirection. Programs launched in a
+ * client terminal read that terminal instead.
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.Stdio")
+
+/** Prefix on debuggee output copied from the PTY (see [kdapMarker]). */
+internal val PTY_OUTPUT_MARKER = kdapMarker("pty")
+
+/**
+ * Opens the stdio pseudo-terminal and returns the path of its secondary
+ * side, to be used for the debuggee's unspecified stdio streams.
+ */
+internal suspend fun DebugSession.openStdioPty(debugger: SBDebugger): String {
+    if (isWindowsHost()) {
+        throw SBError("\"pty\" is not supported on Windows; use \"terminal\" or kdap/stdin instead.")
+    }
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_PTY_STDIO)})")
+    interpreter.handleCommand("script _KDAP_PTY_MARKER = ${pyStr(PTY_OUTPUT_MARKER)}")
+    val path = interpreter.handleCommand("script print(_kdap_pty_open())").trim()
+    stdioPty = true
+    return path
+}
+
+/** Whether KDAP (and so lldb-dap) runs on Windows. */
+internal fun isWindowsHost(): Boolean =
+    LldbDapProcess.currentPlatformId()?.startsWith("win32") == true
+
+/**
+ * Starts copying debuggee output from the pseudo-terminal opened by
+ * [openStdioPty]. Call once the debuggee has been launched.
+ */
+internal suspend fun startStdioPty(debugger: SBDebugger) {
+    debugger.commandInterpreter().handleCommand("script _kdap_pty_start()")
+}
+
+/**
+ * Handles `kdap/stdin` by writing the request's text to the debuggee's
+ * stdin (see the file header). Responds with an error if there is no
+ * process or nothing could be written.
+ */
+suspend fun DebugSession.handleStdin(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val text = obj.optJSONObject("arguments")?.optString("text", "") ?: ""
+    try {
+        if (text.isEmpty()) {
+            sendSuccessResponse(ctx, requestSeq, "kdap/stdin")
+            return
+        }
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val written = if (stdioPty) {
+            debugger.commandInterpreter()
+                .handleCommand("script print(_kdap_pty_write(${pyStr(text)}))")
+                .trim().toIntOrNull() ?: 0
+        } else {
+            val process = debugger.selectedTarget().process()
+            if (!process.isValid()) throw SBError("No process is running.")
+            process.putStdin(text)
+        }
+        if (written == 0) throw SBError("The debuggee's stdin is not available.")
+        sendSuccessResponse(ctx, requestSeq, "kdap/stdin")
+    } catch (e: Exception) {
+        log.warning { "Stdio: stdin write failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "kdap/stdin", e.message ?: "Writing stdin failed")
+    }
+}
+
+/**
+ * Python side of [openStdioPty], [startStdioPty], and the pty path of
+ * [handleStdin]. The secondary side stays open in lldb-dap until the
+ * debuggee has it, then is closed so the reader sees end-of-file (`EIO`)
+ * once the debuggee exits.
+ */
+private val PY_PTY_STDIO = """
+    import codecs, os, termios, threading
+
+    _kdap_pty = {}
+
+    def _kdap_pty_close():
+        for key in ('primary', 'secondary'):
+            fd = _kdap_pty.pop(key, None)
+            if fd is not None:
+                try:
+                    os.close(fd)
+                except OSError:
+                    pass
+
+    def _kdap_pty_open():
+        _kdap_pty_close()
+        primary, secondary = os.openpty()
+        attrs = termios.tcgetattr(secondary)
+        attrs[1] &= ~termios.OPOST
+        attrs[3] &= ~termios.ECHO
+        termios.tcsetattr(secondary, termios.TCSANOW, attrs)
+        _kdap_pty['primary'] = primary
+        _kdap_pty['secondary'] = secondary
+        _kdap_pty['output'] = lldb.debugger.GetOutputFile()
+        return os.ttyname(secondary)
+
+    def _kdap_pty_read(primary, output):
+        decoder = codecs.getincrementaldecoder('utf-8')('replace')
+        while True:
+            try:
+                data = os.read(primary, 1024)
+            except OSError:
+                data = b''
+            text = decoder.decode(data, not data)
+            if text:
+                output.Write((_KDAP_PTY_MARKER + text).encode('utf-8'))
+                output.Flush()
+            if not data:
+                break
+
+    def _kdap_pty_write(text):
+        primary = _kdap_pty.get('primary')
+        if primary is None:
+            return 0
+        data = text.encode('utf-8')
+        written = 0
+        try:
+            while written < len(data):
+                written += os.write(primary, data[written:])
+        except OSError:
+            pass
+        return written
+
+    def _kdap_pty_start():
+        secondary = _kdap_pty.pop('secondary', None)
+        if secondary is not None:
+            os.close(secondary)
+        threading.Thread(
+            target=_kdap_pty_read,
+            args=(_kdap_pty['primary'], _kdap_pty['output']),
+            daemon=True,
+        ).start()
+""".trimIndent()
@@ -0,0 +1,1257 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.messages.*
+import com.github.jomof.dap.sb.*
+import kotlinx.coroutines.CompletableDeferred
+import kotlinx.coroutines.Dispatchers
+import kotlinx.coroutines.runInterruptible
+import kotlinx.coroutines.sync.withLock
+import kotlinx.coroutines.withTimeoutOrNull
+import org.json.JSONArray
+import org.json.JSONObject
+import java.util.logging.Logger
+
+/**
+ * Stepping logic as extension functions on [DebugSession], mirroring the
+ * `handle_next` / `handle_step_in` step-granularity handling in
+ * CodeLLDB's `debug_session.rs`.
+ *
+ * lldb-dap already honors an explicit `granularity: "instruction"` on
+ * `next` and `stepIn` (one machine instruction, stepping over or into
+ * calls) and forwards `stepOut` as a normal step out, so those requests
+ * pass through. What it lacks is CodeLLDB's disassembly behavior: when
+ * the thread's top frame has no source line (the client is showing
+ * disassembly), a line step is meaningless, so KDAP rewrites it to an
+ * instruction step.
+ *
+ * KDAP learns which frames lack source from the `stackTrace` responses
+ * it relays; no extra backend round-trip is needed per step. Such frames
+ * are shown as a document of their function's disassembly (see
+ * Disassembly.kt), whose line follows each instruction step.
+ *
+ * lldb-dap enumerates threads, serves per-thread stack traces, and puts
+ * the stopping thread's `threadId` on `stopped` events. For `continue`
+ * with `singleThread: true` KDAP suspends every other thread before
+ * resuming the process, so only the requested thread runs, and reports
+ * the matching `continued` event as covering that thread alone. The
+ * other threads stay suspended while stopped and are released before
+ * the next resume request is forwarded.
+ *
+ * ## Async stepping
+ *
+ * `next` in the body of a Rust `async fn` or `async` block (a frame
+ * named `…::{async_fn#N}` or `…::{async_block#N}`, the `poll` of the
+ * `{async_fn_env#N}` state machine) is KDAP's own; lldb-dap's `next`
+ * would follow a pending `.await` out through the executor. KDAP puts
+ * temporary breakpoints on every other line of the body, conditioned on
+ * the state machine being the one that was stepped (the body's pinned
+ * `self` pointer), and one on the return address. The thread stops at
+ * the next line this future reaches, whenever it is polled again and on
+ * whichever thread, and stepping off the end of a finished future stops
+ * in its caller as usual. A return while the future is still pending
+ * continues silently. The stop is reported with reason `step`, and the
+ * breakpoints are removed on the next resume (see [handleAsyncNext]).
+ *
+ * ## Step-in targets
+ *
+ * lldb-dap answers `stepInTargets` but steps into a target by function
+ * name, so with several calls to the same function on a line (chained
+ * iterator adapters, say) `stepIn` lands in the first. KDAP answers
+ * `stepInTargets` itself: the direct calls on the top frame's line that
+ * are still ahead of the PC, labeled with the callee and the call's
+ * column, and the calls inlined at that line, labeled with the inlined
+ * function and [INLINED_FRAME_SUFFIX] (see StackFrames.kt). `stepIn`
+ * with one of their `targetId`s runs a scripted thread plan that runs to
+ * that call instruction, or the first instruction of the inlined code,
+ * and steps in from there with LLDB's own step-in over just that
+ * instruction, so trampolines, prologues, and callees without debug info
+ * are handled as in a normal step, and an inlined call is entered as a
+ * frame (see [handleStepInTarget]). If the call is never reached, the
+ * thread runs until something else stops it.
+ *
+ * ## Just my code
+ *
+ * With `"justMyCode": true` in the launch configuration, `stepIn` does
+ * not stop in code the user did not write: frames without source, the
+ * runtime internals of StackFrames.kt, and sources in external
+ * directories (Cargo's registry and git checkouts, and the
+ * configuration's `externalCodePaths`; see [externalCodePaths]). KDAP
+ * steps in under a scripted thread plan that, wherever LLDB's step-in
+ * stops outside user code, steps out to the first user frame and, if
+ * that is the frame the step started in, steps on through the rest of
+ * the line, so `stepIn` on `v.iter().map(f).sum()` goes past the
+ * iterator code (see [handleJustMyCodeStepIn]). User code the external
+ * code calls back into, such as the closure `f`, runs without stopping.
+ *
+ * The custom `kdap/stepIntoSkipped` request is the escape hatch: it
+ * resumes the thread until it next enters the call its last step
+ * skipped, and reports that stop as a step (see
+ * [handleStepIntoSkipped]). The skipped call has returned by then, so
+ * this stops at its next call, such as on the next turn of a loop.
+ *
+ * ## Goto
+ *
+ * `gotoTargets` and `goto` (set next statement) are KDAP's. lldb-dap
+ * looks the line up only in compile units whose main file is the source
+ * (missing Rust modules other than the crate root), and its `goto` lets
+ * LLDB move the PC into another function, which corrupts the stack. KDAP
+ * lists only the line's addresses inside the function of the selected
+ * thread's top frame, one target per column, and `goto` refuses a target
+ * outside the function the thread is in. Having set the PC, KDAP reports
+ * a `stopped` event with reason `goto` so the client refreshes the stack
+ * and scopes (see [handleGoto]).
+ *
+ * ## Restart frame
+ *
+ * `restartFrame` is KDAP's; lldb-dap has none. KDAP pops the frame and
+ * every frame above it with LLDB's `thread return`, which restores the
+ * caller's registers, stack pointer included, as the unwinder recovers
+ * them from the frame's CFA, then re-enters the function as its caller
+ * did: the return address is pushed (x86-64) or put in the link register
+ * (AArch64), the parameters' current values are loaded into the argument
+ * registers, and the PC is set to the function's entry. The thread is
+ * reported stopped with reason `restart` (see [handleRestartFrame]).
+ *
+ * Only frames whose parameters are all passed in registers (the kinds of
+ * values listed in [ReturnValues.kt]) can be restarted, on x86-64
+ * outside Windows and on AArch64; inlined frames cannot. Side effects of
+ * the partial run, such as writes through pointers, are not undone.
+ *
+ * ## Pause
+ *
+ * `pause` is sent on to lldb-dap ahead of queued requests (see
+ * DapSession.kt), whose interrupt stops a thread blocked in `sleep`,
+ * `read`, or a futex wait inside the system call: `SIGSTOP` on Linux and
+ * macOS, a break-in thread on Windows. lldb-dap reports that stop as an
+ * `exception` (`signal SIGSTOP`) on whichever thread took the interrupt.
+ * KDAP reports it as a `pause` on the thread the client was last shown
+ * stopped, or, if that one has exited, on the thread that stopped, or
+ * the first thread of the last `threads` response when the stopped one
+ * is not among them, like Windows' break-in thread (see
+ * [rewritePauseStop]). The top frame of each thread is then inside the
+ * system call wrapper, and is never collapsed (see StackFrames.kt).
+ *
+ * If the process has not stopped [PAUSE_GRACE_MS] after lldb-dap
+ * answered, KDAP sends it `SIGSTOP` itself: by pid when it runs on this
+ * machine, otherwise through LLDB (see [handlePause]).
+ *
+ * ## CodeLLDB method mapping
+ *
+ * | CodeLLDB (debug_session.rs) | KDAP (this file)                   |
+ * |-----------------------------|------------------------------------|
+ * | `handle_next`               | [DebugSession.stepGranularity]     |
+ * | `handle_step_in`            | [DebugSession.stepGranularity]     |
+ * | `in_disassembly`            | [DebugSession.onStackTraceResponse]|
+ * | `handle_continue`           | [DebugSession.handleSingleThreadContinue] |
+ * | —                           | [DebugSession.handleAsyncNext]     |
+ * | —                           | [DebugSession.handleStepInTargets] |
+ * | —                           | [DebugSession.handleJustMyCodeStepIn] |
+ * | —                           | [DebugSession.handleGotoTargets]   |
+ * | —                           | [DebugSession.handleRestartFrame]  |
+ * | —                           | [DebugSession.handlePause]         |
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.Stepping")
+
+/** Granularity that single-steps one machine instruction. */
+internal const val INSTRUCTION_GRANULARITY = "instruction"
+
+/** Remembers [request]'s thread so its response can be attributed. */
+fun DebugSession.onStackTraceRequest(request: StackTraceRequest) {
+    if ((request.startFrame ?: 0) == 0) pendingStackTraces[request.seq] = request.threadId
+}
+
+/**
+ * Records whether the top frame in a `stackTrace` response has a source
+ * line. Frames lldb-dap can only show as disassembly have no `path`.
+ */
+fun DebugSession.onStackTraceResponse(response: DapResponse) {
+    val threadId = pendingStackTraces.remove(response.requestSeq) ?: return
+    if (!response.success) return
+    val top = (response.body["stackFrames"] as? List<*>)?.firstOrNull() as? Map<*, *> ?: return
+    val path = (top["source"] as? Map<*, *>)?.get("path")
+    val line = (top["line"] as? Number)?.toInt() ?: 0
+    if (path != null && line > 0) {
+        threadsWithoutSource.remove(threadId)
+    } else {
+        threadsWithoutSource.add(threadId)
+    }
+    if (isAsyncBody(top["name"] as? String ?: "")) {
+        threadsInAsyncBody.add(threadId)
+    } else {
+        threadsInAsyncBody.remove(threadId)
+    }
+}
+
+/** Forgets per-stop frame state; the client re-requests stack traces after each stop. */
+fun DebugSession.onStopped() {
+    threadsWithoutSource.clear()
+    threadsInAsyncBody.clear()
+    collapsedFramePages.clear()
+    stepInTargets.clear()
+    gotoTargets.clear()
+    singleThreadContinue = null
+    pausedStop = false
+}
+
+/**
+ * Mirrors CodeLLDB's `handle_next` / `handle_step_in` granularity choice.
+ *
+ * Returns the granularity to forward for a step on [threadId], or `null`
+ * to forward the request unchanged.
+ */
+fun DebugSession.stepGranularity(threadId: Int, requested: String?): String? =
+    if (requested != INSTRUCTION_GRANULARITY && threadId in threadsWithoutSource) {
+        INSTRUCTION_GRANULARITY
+    } else {
+        null
+    }
+
+// ── handle_continue (debug_session.rs) ───────────────────────────
+
+/** Whether [request] asks to resume only its own thread. */
+fun isSingleThreadContinue(request: ContinueRequest): Boolean =
+    request.singleThread == true && request.threadId != null
+
+/**
+ * Handles `continue` with `singleThread: true`: suspends every thread
+ * but the requested one, then resumes the process. The suspended threads
+ * are recorded in [DebugSession.suspendedThreads].
+ */
+suspend fun DebugSession.handleSingleThreadContinue(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val threadId = obj.optJSONObject("arguments")?.optLong("threadId") ?: 0L
+
+    try {
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val process = debugger.selectedTarget().process()
+        val threads = process.threads().associateBy { it.threadId() }
+        val resumed = threads[threadId] ?: throw SBError("Invalid thread ID $threadId")
+        stateLock.withLock {
+            for ((id, thread) in threads) {
+                if (id == threadId) continue
+                thread.suspend()
+                suspendedThreads.add(id)
+            }
+            resumed.resume()
+            suspendedThreads.remove(threadId)
+        }
+
+        singleThreadContinue = threadId.toInt()
+        process.resume()
+        sendSuccessResponse(ctx, requestSeq, "continue", mapOf("allThreadsContinued" to false))
+    } catch (e: Exception) {
+        singleThreadContinue = null
+        log.warning { "Stepping: single-thread continue failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "continue", e.message ?: "continue failed")
+    }
+}
+
+/**
+ * Releases the threads a single-thread `continue` suspended and removes
+ * an async step's breakpoints, then forwards the resume request [json]
+ * to lldb-dap.
+ */
+suspend fun DebugSession.resumeAllThreads(json: String, ctx: AsyncRequestContext) {
+    try {
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        releaseStepState(debugger)
+    } catch (e: Exception) {
+        log.warning { "Stepping: releasing suspended threads failed: ${e.message}" }
+    }
+    ctx.forwardToBackend(json)
+}
+
+/**
+ * Resumes [DebugSession.suspendedThreads] and removes the breakpoints of
+ * [DebugSession.asyncStep], [DebugSession.stepBack], and
+ * [DebugSession.skippedStepIn], holding [DebugSession.stateLock].
+ */
+internal suspend fun DebugSession.releaseStepState(debugger: SBDebugger) {
+    stateLock.withLock { releaseStepStateLocked(debugger) }
+}
+
+/**
+ * [releaseStepState] for a caller that already holds
+ * [DebugSession.stateLock] to set new step state under the same lock.
+ */
+internal suspend fun DebugSession.releaseStepStateLocked(debugger: SBDebugger) {
+    if (suspendedThreads.isNotEmpty()) {
+        val threads = debugger.selectedTarget().process().threads().associateBy { it.threadId() }
+        for (id in suspendedThreads.toList()) {
+            threads[id]?.resume()
+            suspendedThreads.remove(id)
+        }
+    }
+    if (asyncStep != null) {
+        debugger.commandInterpreter().handleCommand("script _kdap_async_clear()")
+        asyncStep = null
+    }
+    if (stepBack != null) {
+        debugger.commandInterpreter().handleCommand("script _kdap_step_back_clear()")
+        stepBack = null
+    }
+    if (skippedStepIn != null) {
+        debugger.commandInterpreter().handleCommand("script _kdap_jmc_clear()")
+        skippedStepIn = null
+    }
+}
+
+/**
+ * lldb-dap reports every resume as `allThreadsContinued`; while a
+ * single-thread `continue` runs, only its thread did.
+ */
+fun DebugSession.rewriteContinued(event: ContinuedEvent): ContinuedEvent {
+    val threadId = singleThreadContinue ?: return event
+    return event.copy(threadId = threadId, allThreadsContinued = false)
+}
+
+// ── Async stepping ───────────────────────────────────────────────
+
+/** Matches the body of an `async fn` or `async` block in a frame name. */
+private val ASYNC_BODY = Regex("""\{async_(fn|block|closure)#\d+\}(<.*>)?$""")
+
+/**
+ * Whether [functionName] (a frame's `name`) is a Rust async body: the
+ * `poll` of an `{async_fn_env#N}` state machine.
+ */
+internal fun isAsyncBody(functionName: String): Boolean =
+    ASYNC_BODY.containsMatchIn(functionName.substringBefore('('))
+
+/** A running async-aware `next` on [threadId], stopping at [breakpointIds]. */
+data class AsyncStep(val threadId: Int, val breakpointIds: Set<Int>)
+
+/** Whether a `next` on [threadId] should be KDAP's async-aware step. */
+fun DebugSession.isAsyncNext(threadId: Int, granularity: String?): Boolean =
+    granularity != INSTRUCTION_GRANULARITY && threadId in threadsInAsyncBody
+
+/**
+ * Handles `next` in an async body (see the file header): sets the
+ * temporary breakpoints and resumes the process. Falls back to
+ * lldb-dap's `next` if the frame has no state machine to follow.
+ */
+suspend fun DebugSession.handleAsyncNext(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val threadId = obj.optJSONObject("arguments")?.optInt("threadId") ?: 0
+
+    try {
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val stepping = stateLock.withLock {
+            releaseStepStateLocked(debugger)
+            val interpreter = debugger.commandInterpreter()
+            interpreter.handleCommand("script exec(${pyStr(PY_ASYNC_STEP)})")
+            val ids = interpreter.handleCommand("script print(_kdap_async_next($threadId))").trim()
+                .split(',').mapNotNull { it.trim().toIntOrNull() }.toSet()
+            if (ids.isNotEmpty()) {
+                asyncStep = AsyncStep(threadId, ids)
+                debugger.selectedTarget().process().resume()
+            }
+            ids.isNotEmpty()
+        }
+        if (!stepping) {
+            ctx.forwardToBackend(rawJson)
+            return
+        }
+        sendSuccessResponse(ctx, requestSeq, "next")
+    } catch (e: Exception) {
+        log.warning { "Stepping: async next failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "next", e.message ?: "next failed")
+    }
+}
+
+/**
+ * Reports a stop at an async step's breakpoints as the end of the step,
+ * on whichever thread it happened: the executor may poll the future on
+ * another thread than the one that was stepped. A stop is the step's if
+ * every breakpoint it hit is one of the step's; stops at other
+ * breakpoints are left alone.
+ */
+fun DebugSession.rewriteAsyncStepStop(event: StoppedEvent): StoppedEvent {
+    val step = asyncStep ?: return event
+    val hit = event.hitBreakpointIds
+    if (hit.isNullOrEmpty() || !step.breakpointIds.containsAll(hit)) return event
+    return event.copy(reason = "step", description = null, hitBreakpointIds = null)
+}
+
+/**
+ * Python side of [handleAsyncNext]. `_kdap_async_next` returns the IDs
+ * of the breakpoints it set, comma-separated (empty if the frame isn't a
+ * state machine's `poll`). The state machine's identity is its address,
+ * read from the body's first argument (`Pin<&mut {async_fn_env#N}>`).
+ * Whether it has finished is read from its active variant with the
+ * [initRustFormatters] helpers: `Returned` or `Panicked`.
+ */
+private val PY_ASYNC_STEP = """
+    if '_kdap_async_step' not in globals():
+        _kdap_async_step = {'future': None, 'env_type': None, 'ids': []}
+
+    def _kdap_async_self(frame):
+        args = frame.GetVariables(True, False, False, False)
+        if args.GetSize() == 0:
+            return None
+        return _kdap_first_pointer(args.GetValueAtIndex(0))
+
+    def _kdap_async_clear():
+        target = lldb.debugger.GetSelectedTarget()
+        for bp_id in _kdap_async_step['ids']:
+            target.BreakpointDelete(bp_id)
+        _kdap_async_step['ids'] = []
+        _kdap_async_step['future'] = None
+
+    def _kdap_async_next(thread_id):
+        target = lldb.debugger.GetSelectedTarget()
+        thread = target.GetProcess().GetThreadByID(thread_id)
+        frame = thread.GetFrameAtIndex(0)
+        function = frame.GetFunction()
+        future = _kdap_async_self(frame)
+        if not function.IsValid() or future is None:
+            return ''
+        start = function.GetStartAddress()
+        low = start.GetLoadAddress(target)
+        high = function.GetEndAddress().GetLoadAddress(target)
+        skip = (0, frame.GetLineEntry().GetLine(), start.GetLineEntry().GetLine())
+        unit = start.GetCompileUnit()
+        ids = []
+        addresses = set()
+        for i in range(unit.GetNumLineEntries()):
+            entry = unit.GetLineEntryAtIndex(i)
+            address = entry.GetStartAddress().GetLoadAddress(target)
+            if not low <= address < high or entry.GetLine() in skip or address in addresses:
+                continue
+            addresses.add(address)
+            bp = target.BreakpointCreateByAddress(address)
+            bp.SetScriptCallbackFunction('_kdap_async_resumed')
+            ids.append(bp.GetID())
+        caller = thread.GetFrameAtIndex(1)
+        if caller.IsValid():
+            bp = target.BreakpointCreateByAddress(caller.GetPC())
+            bp.SetThreadID(thread_id)
+            bp.SetScriptCallbackFunction('_kdap_async_returned')
+            ids.append(bp.GetID())
+        _kdap_async_step['future'] = future.GetValueAsUnsigned()
+        _kdap_async_step['env_type'] = future.GetType().GetPointeeType()
+        _kdap_async_step['ids'] = ids
+        return ','.join(str(bp_id) for bp_id in ids)
+
+    def _kdap_async_resumed(frame, bp_loc, internal_dict):
+        future = _kdap_async_self(frame)
+        return future is not None and future.GetValueAsUnsigned() == _kdap_async_step['future']
+
+    def _kdap_async_returned(frame, bp_loc, internal_dict):
+        target = frame.GetThread().GetProcess().GetTarget()
+        address = lldb.SBAddress(_kdap_async_step['future'], target)
+        env = target.CreateValueFromAddress('env', address, _kdap_async_step['env_type'])
+        variant = _kdap_enum_variant(env)
+        if variant is None:
+            return True
+        return _kdap_variant_name(variant) in ('Returned', 'Panicked')
+""".trimIndent()
+
+// ── Step-in targets ──────────────────────────────────────────────
+
+/**
+ * Advertises `supportsStepInTargetsRequest`, `supportsGotoTargetsRequest`,
+ * and `supportsRestartFrame` in a successful `initialize` response.
+ * lldb-dap only advertises the first two after `launch` or `attach`,
+ * which KDAP runs itself.
+ */
+fun addSteppingCapabilities(response: DapResponse): DapResponse =
+    response.copy(body = response.body + mapOf(
+        "supportsStepInTargetsRequest" to true,
+        "supportsGotoTargetsRequest" to true,
+        "supportsRestartFrame" to true,
+    ))
+
+/** Python class name of the step-in target thread plan. */
+private const val STEP_IN_TARGET_PLAN = "_KdapStepInTarget"
+
+/**
+ * A call listed by `stepInTargets`: the call instruction's load
+ * [address] and [size], its callee's [label], and its source position.
+ */
+data class StepInTarget(val address: Long, val size: Int, val label: String, val line: Int, val column: Int)
+
+/** Parses the calls printed by `_kdap_step_in_targets`. */
+internal fun parseStepInTargets(json: String): List<StepInTarget> {
+    val array = JSONArray(json)
+    return (0 until array.length()).map { i ->
+        val call = array.getJSONObject(i)
+        StepInTarget(
+            address = call.getLong("address"),
+            size = call.getInt("size"),
+            label = call.getString("label"),
+            line = call.optInt("line"),
+            column = call.optInt("column"),
+        )
+    }
+}
+
+/**
+ * Handles `stepInTargets`: lists the direct calls left on the frame's
+ * line (see the file header). Frames other than a thread's top frame
+ * have none.
+ */
+suspend fun DebugSession.handleStepInTargets(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val frameId = obj.optJSONObject("arguments")?.optInt("frameId") ?: 0
+
+    try {
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val interpreter = debugger.commandInterpreter()
+        interpreter.handleCommand("script exec(${pyStr(PY_STEP_IN_TARGETS)})")
+        val threadIndexId = frameId ushr FRAME_INDEX_BITS
+        val frameIndex = frameId and ((1 shl FRAME_INDEX_BITS) - 1)
+        val output = interpreter
+            .handleCommand("script print(json.dumps(_kdap_step_in_targets($threadIndexId, $frameIndex)))").trim()
+        val targets = parseStepInTargets(output).map { target ->
+            val id = nextStepInTargetId.getAndIncrement()
+            stepInTargets[id] = target
+            mapOf("id" to id, "label" to target.label, "line" to target.line, "column" to target.column)
+        }
+        sendSuccessResponse(ctx, requestSeq, "stepInTargets", mapOf("targets" to targets))
+    } catch (e: Exception) {
+        log.warning { "Stepping: stepInTargets failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "stepInTargets", e.message ?: "stepInTargets failed")
+    }
+}
+
+/** Whether [request] steps into a target KDAP listed since the last stop. */
+fun DebugSession.isStepInTarget(request: StepInRequest): Boolean =
+    request.targetId != null && request.targetId in stepInTargets
+
+/**
+ * Handles `stepIn` with a KDAP `targetId`: steps the thread into that
+ * call under the [PY_STEP_IN_TARGETS] thread plan.
+ */
+suspend fun DebugSession.handleStepInTarget(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val args = obj.optJSONObject("arguments")
+    val threadId = args?.optLong("threadId") ?: 0L
+
+    try {
+        val target = stepInTargets[args?.optInt("targetId") ?: 0]
+            ?: throw SBError("Unknown step-in target")
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        releaseStepState(debugger)
+        val thread = debugger.selectedTarget().process().threads().firstOrNull { it.threadId() == threadId }
+            ?: throw SBError("Invalid thread ID $threadId")
+        val interpreter = debugger.commandInterpreter()
+        interpreter.handleCommand("script exec(${pyStr(PY_STEP_IN_TARGETS)})")
+        interpreter.handleCommand("script _kdap_step_in_target = (${target.address}, ${target.size})")
+        thread.stepUsingScriptedThreadPlan(STEP_IN_TARGET_PLAN)
+        sendSuccessResponse(ctx, requestSeq, "stepIn")
+    } catch (e: Exception) {
+        log.warning { "Stepping: stepIn to a target failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "stepIn", e.message ?: "stepIn failed")
+    }
+}
+
+/**
+ * Python side of [handleStepInTargets] and [handleStepInTarget].
+ * `_kdap_step_in_targets` returns the calls at or after the PC whose
+ * instructions belong to the frame's line. Only direct calls (an
+ * immediate target address) are listed; the callee is the function or
+ * symbol at that address, with a legacy Rust `::h<hash>` removed.
+ * Inlined calls are the inlined blocks nested in the frame's block,
+ * outside other inlined blocks, whose call site is the frame's line and
+ * whose code starts at or after the PC; the target is their first
+ * instruction. The thread plan steps into the call in
+ * `_kdap_step_in_target` (address, size).
+ */
+private val PY_STEP_IN_TARGETS = """
+    import json, re
+
+    _KDAP_CALL_MNEMONICS = ('bl', 'blr', 'blx', 'blraa', 'blrab')
+
+    def _kdap_step_in_callee(target, inst):
+        operands = inst.GetOperands(target)
+        match = re.search(r'0x[0-9a-fA-F]+', operands)
+        if match is None or '*' in operands or '[' in operands:
+            return None
+        callee = target.ResolveLoadAddress(int(match.group(0), 16))
+        function = callee.GetFunction()
+        name = function.GetDisplayName() if function.IsValid() else callee.GetSymbol().GetDisplayName()
+        return re.sub(r'::h[0-9a-f]{16}$', '', name) if name else None
+
+    def _kdap_step_in_targets(thread_index_id, frame_index):
+        target = lldb.debugger.GetSelectedTarget()
+        thread = target.GetProcess().GetThreadByIndexID(thread_index_id)
+        frame = thread.GetFrameAtIndex(frame_index)
+        function = frame.GetFunction()
+        line = frame.GetLineEntry()
+        if frame_index != 0 or not function.IsValid() or not line.IsValid():
+            return []
+        pc = frame.GetPC()
+        path = line.GetFileSpec().fullpath
+        targets = []
+        for inst in function.GetInstructions(target):
+            address = inst.GetAddress()
+            entry = address.GetLineEntry()
+            if address.GetLoadAddress(target) < pc or entry.GetLine() != line.GetLine():
+                continue
+            mnemonic = inst.GetMnemonic(target)
+            if entry.GetFileSpec().fullpath != path or not (
+                    mnemonic.startswith('call') or mnemonic in _KDAP_CALL_MNEMONICS):
+                continue
+            label = _kdap_step_in_callee(target, inst)
+            if label is None:
+                continue
+            targets.append({
+                'address': address.GetLoadAddress(target),
+                'size': inst.GetByteSize(),
+                'label': label,
+                'line': entry.GetLine(),
+                'column': entry.GetColumn(),
+            })
+        targets += _kdap_inlined_calls(target, frame.GetFrameBlock(), line, pc)
+        return sorted(targets, key=lambda t: t['address'])
+
+    def _kdap_inlined_calls(target, block, line, pc):
+        calls = []
+        child = block.GetFirstChild()
+        while child.IsValid():
+            if not child.IsInlined():
+                calls += _kdap_inlined_calls(target, child, line, pc)
+            elif (child.GetInlinedCallSiteLine() == line.GetLine() and child.GetNumRanges() > 0
+                    and child.GetInlinedCallSiteFile().fullpath == line.GetFileSpec().fullpath):
+                start = child.GetRangeStartAddress(0)
+                instructions = target.ReadInstructions(start, 1)
+                if start.GetLoadAddress(target) >= pc and instructions.GetSize() > 0:
+                    name = re.sub(r'::h[0-9a-f]{16}$', '', child.GetInlinedName() or '?')
+                    calls.append({
+                        'address': start.GetLoadAddress(target),
+                        'size': instructions.GetInstructionAtIndex(0).GetByteSize(),
+                        'label': name + '$INLINED_FRAME_SUFFIX',
+                        'line': line.GetLine(),
+                        'column': child.GetInlinedCallSiteColumn(),
+                    })
+            child = child.GetSibling()
+        return calls
+
+    _kdap_step_in_target = (0, 0)
+
+    class $STEP_IN_TARGET_PLAN:
+        def __init__(self, thread_plan, args, internal_dict):
+            self.plan = thread_plan
+            self.address, self.size = _kdap_step_in_target
+            self.call = lldb.SBAddress(self.address, thread_plan.GetThread().GetProcess().GetTarget())
+            self.stepping_in = False
+            if thread_plan.GetThread().GetFrameAtIndex(0).GetPC() == self.address:
+                self.step_in()
+            else:
+                self.child = thread_plan.QueueThreadPlanForRunToAddress(self.call)
+        def step_in(self):
+            self.stepping_in = True
+            self.child = self.plan.QueueThreadPlanForStepInRange(self.call, self.size)
+        def explains_stop(self, event):
+            return False
+        def should_stop(self, event):
+            if not self.stepping_in and self.plan.GetThread().GetFrameAtIndex(0).GetPC() == self.address:
+                self.step_in()
+                return False
+            self.plan.SetPlanComplete(True)
+            return True
+        def should_step(self):
+            return False
+""".trimIndent()
+
+// ── Just my code ─────────────────────────────────────────────────
+
+/** Python class name of the just-my-code step-in thread plan. */
+private const val JUST_MY_CODE_PLAN = "_KdapJustMyCodeStep"
+
+/** The custom request that steps into the call a just-my-code `stepIn` skipped. */
+internal const val STEP_INTO_SKIPPED_COMMAND = "kdap/stepIntoSkipped"
+
+/** A running `kdap/stepIntoSkipped` on [threadId], stopping at [breakpointId]. */
+data class SkippedStepIn(val threadId: Int, val breakpointId: Int)
+
+/**
+ * Directories whose sources are external code for just-my-code
+ * stepping: Cargo's registry and git checkouts under `CARGO_HOME`
+ * (default `~/.cargo`), then [configured]. Each ends with `/`.
+ */
+internal fun externalCodePaths(configured: List<String>?, environment: Map<String, String>, home: String): List<String> {
+    fun directory(path: String) = path.replace('\\', '/').trimEnd('/') + "/"
+    val cargoHome = directory(environment["CARGO_HOME"]?.ifEmpty { null } ?: "$home/.cargo")
+    return listOf("${cargoHome}registry/", "${cargoHome}git/") + configured.orEmpty().map { directory(it) }
+}
+
+/**
+ * Turns on just-my-code stepping (`"justMyCode": true`; see the file
+ * header), with the directories of [externalCodePaths].
+ */
+internal suspend fun DebugSession.initJustMyCode(configured: List<String>?, debugger: SBDebugger) {
+    val config = JSONObject()
+        .put("external", JSONArray(externalCodePaths(configured, System.getenv(), System.getProperty("user.home"))))
+        .put("names", INTERNAL_FRAME_NAME.pattern)
+        .put("paths", INTERNAL_FRAME_PATH.pattern)
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_JUST_MY_CODE)})")
+    interpreter.handleCommand("script _kdap_jmc_configure(${pyStr(config.toString())})")
+    justMyCode = true
+}
+
+/**
+ * Whether [request] is a `stepIn` KDAP runs as a just-my-code step: one
+ * by line or statement, without a target, in a frame with source.
+ */
+fun DebugSession.isJustMyCodeStepIn(request: StepInRequest): Boolean =
+    justMyCode && request.targetId == null && request.granularity != INSTRUCTION_GRANULARITY &&
+        request.threadId !in threadsWithoutSource
+
+/**
+ * Handles `stepIn` with just my code on: steps the thread under the
+ * [PY_JUST_MY_CODE] thread plan, which steps back out of external code
+ * (see the file header).
+ */
+suspend fun DebugSession.handleJustMyCodeStepIn(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val threadId = obj.optJSONObject("arguments")?.optLong("threadId") ?: 0L
+
+    try {
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        releaseStepState(debugger)
+        val thread = debugger.selectedTarget().process().threads().firstOrNull { it.threadId() == threadId }
+            ?: throw SBError("Invalid thread ID $threadId")
+        thread.stepUsingScriptedThreadPlan(JUST_MY_CODE_PLAN)
+        sendSuccessResponse(ctx, requestSeq, "stepIn")
+    } catch (e: Exception) {
+        log.warning { "Stepping: just-my-code stepIn failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "stepIn", e.message ?: "stepIn failed")
+    }
+}
+
+/**
+ * Handles `kdap/stepIntoSkipped`: resumes the process with a one-shot
+ * breakpoint, for the thread alone, at the entry of the call the
+ * thread's last just-my-code step skipped. The response names it.
+ */
+suspend fun DebugSession.handleStepIntoSkipped(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val threadId = obj.optJSONObject("arguments")?.optInt("threadId") ?: 0
+
+    try {
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val skipped = stateLock.withLock {
+            releaseStepStateLocked(debugger)
+            val interpreter = debugger.commandInterpreter()
+            interpreter.handleCommand("script exec(${pyStr(PY_JUST_MY_CODE)})")
+            val output = interpreter.handleCommand("script print(_kdap_jmc_step_into_skipped($threadId))").trim()
+            if (output.isEmpty()) throw SBError("No call was skipped on thread $threadId.")
+            val call = JSONObject(output)
+            skippedStepIn = SkippedStepIn(threadId, call.getInt("breakpoint"))
+            debugger.selectedTarget().process().resume()
+            call
+        }
+        sendSuccessResponse(ctx, requestSeq, STEP_INTO_SKIPPED_COMMAND, mapOf("function" to skipped.getString("function")))
+    } catch (e: Exception) {
+        log.warning { "Stepping: $STEP_INTO_SKIPPED_COMMAND failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, STEP_INTO_SKIPPED_COMMAND, e.message ?: "Stepping into the skipped call failed")
+    }
+}
+
+/**
+ * Reports the stop at a `kdap/stepIntoSkipped` breakpoint as a step.
+ * Stops at other breakpoints are left alone.
+ */
+fun DebugSession.rewriteSkippedStepInStop(event: StoppedEvent): StoppedEvent {
+    val step = skippedStepIn ?: return event
+    if (event.threadId != step.threadId || event.reason != "breakpoint") return event
+    val hit = event.hitBreakpointIds
+    if (hit != null && hit != listOf(step.breakpointId)) return event
+    return event.copy(reason = "step", description = null, hitBreakpointIds = null)
+}
+
+/**
+ * Python side of [initJustMyCode], [handleJustMyCodeStepIn], and
+ * [handleStepIntoSkipped]. A frame is user code if it has a line, its
+ * source is outside the external directories, and neither its function
+ * nor its source is a runtime internal (the patterns of StackFrames.kt).
+ * The thread plan steps through the rest of the line with LLDB's
+ * step-in; whenever that stops outside user code it steps out to the
+ * first user frame, and carries on through the line if that is the frame
+ * it started in. The outermost external function stepped out of is kept
+ * per thread for `_kdap_jmc_step_into_skipped`.
+ */
+private val PY_JUST_MY_CODE = """
+    import json, re
+
+    if '_kdap_jmc' not in globals():
+        _kdap_jmc = {'external': [], 'names': None, 'paths': None, 'skipped': {}, 'breakpoint': None}
+
+    def _kdap_jmc_configure(config_json):
+        config = json.loads(config_json)
+        _kdap_jmc.update(external=config['external'], names=re.compile(config['names']),
+                         paths=re.compile(config['paths']))
+
+    def _kdap_jmc_user(frame):
+        entry = frame.GetLineEntry()
+        path = entry.GetFileSpec().fullpath if entry.IsValid() and entry.GetLine() > 0 else None
+        if not path:
+            return False
+        path = path.replace('\\', '/')
+        if any(path.startswith(prefix) for prefix in _kdap_jmc['external']):
+            return False
+        name = frame.GetDisplayFunctionName() or ''
+        return not (_kdap_jmc['names'].search(name) or _kdap_jmc['paths'].search(path))
+
+    def _kdap_jmc_clear():
+        if _kdap_jmc['breakpoint'] is not None:
+            lldb.debugger.GetSelectedTarget().BreakpointDelete(_kdap_jmc['breakpoint'])
+            _kdap_jmc['breakpoint'] = None
+
+    def _kdap_jmc_step_into_skipped(thread_id):
+        skipped = _kdap_jmc['skipped'].get(thread_id)
+        if skipped is None:
+            return ''
+        target = lldb.debugger.GetSelectedTarget()
+        bp = target.BreakpointCreateByAddress(skipped['address'])
+        bp.SetThreadID(thread_id)
+        bp.SetOneShot(True)
+        _kdap_jmc['breakpoint'] = bp.GetID()
+        return json.dumps({'breakpoint': bp.GetID(), 'function': skipped['function']})
+
+    class $JUST_MY_CODE_PLAN:
+        def __init__(self, thread_plan, args, internal_dict):
+            self.plan = thread_plan
+            frame = thread_plan.GetThread().GetFrameAtIndex(0)
+            self.cfa = frame.GetCFA()
+            self.line = frame.GetLineEntry().GetLine()
+            self.step_line(frame)
+        def step_line(self, frame):
+            end = frame.GetLineEntry().GetEndAddress().GetLoadAddress(frame.GetThread().GetProcess().GetTarget())
+            size = end - frame.GetPC() if end != lldb.LLDB_INVALID_ADDRESS and end > frame.GetPC() else 1
+            self.child = self.plan.QueueThreadPlanForStepInRange(frame.GetPCAddress(), size)
+        def explains_stop(self, event):
+            return False
+        def should_stop(self, event):
+            thread = self.plan.GetThread()
+            frame = thread.GetFrameAtIndex(0)
+            user = next((i for i in range(thread.GetNumFrames()) if _kdap_jmc_user(thread.GetFrameAtIndex(i))), None)
+            if user == 0:
+                if frame.GetCFA() == self.cfa and frame.GetLineEntry().GetLine() == self.line and self.child is None:
+                    self.step_line(frame)
+                    return False
+                self.plan.SetPlanComplete(True)
+                return True
+            if user is None:
+                self.plan.SetPlanComplete(True)
+                return True
+            callee = thread.GetFrameAtIndex(user - 1)
+            if thread.GetFrameAtIndex(user).GetCFA() == self.cfa:
+                function = callee.GetFunction()
+                start = function.GetStartAddress() if function.IsValid() else callee.GetSymbol().GetStartAddress()
+                _kdap_jmc['skipped'][thread.GetThreadID()] = {
+                    'function': callee.GetDisplayFunctionName() or '?',
+                    'address': start.GetLoadAddress(thread.GetProcess().GetTarget()),
+                }
+            self.child = None
+            self.plan.QueueThreadPlanForStepOut(user - 1)
+            return False
+        def should_step(self):
+            return False
+""".trimIndent()
+
+// ── Goto ─────────────────────────────────────────────────────────
+
+/**
+ * A `goto` target: the load [address] of a line-table entry at [line] and
+ * [column], in the function starting at [functionStart].
+ */
+data class GotoTarget(val address: Long, val line: Int, val column: Int, val functionStart: Long)
+
+/** Parses the targets printed by `_kdap_goto_targets`. */
+internal fun parseGotoTargets(json: String): List<GotoTarget> {
+    val array = JSONArray(json)
+    return (0 until array.length()).map { i ->
+        val target = array.getJSONObject(i)
+        GotoTarget(
+            address = target.getLong("address"),
+            line = target.getInt("line"),
+            column = target.optInt("column"),
+            functionStart = target.getLong("function"),
+        )
+    }
+}
+
+/** The label of a `goto` target in the client's picker. */
+internal fun gotoTargetLabel(target: GotoTarget): String =
+    if (target.column > 0) "Line ${target.line}, column ${target.column}" else "Line ${target.line}"
+
+/**
+ * Handles `gotoTargets`: the addresses of the requested line inside the
+ * selected thread's current function (see the file header).
+ */
+suspend fun DebugSession.handleGotoTargets(rawJson: String, ctx: AsyncRequestContext) {
+    val requestSeq = JSONObject(rawJson).optInt("seq", 0)
+    val request = DapMessage.parse(rawJson) as GotoTargetsRequest
+
+    try {
+        val path = request.source.path ?: throw SBError("gotoTargets needs a source path")
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val interpreter = debugger.commandInterpreter()
+        interpreter.handleCommand("script exec(${pyStr(PY_GOTO)})")
+        val output = interpreter
+            .handleCommand("script print(json.dumps(_kdap_goto_targets(${pyStr(path)}, ${request.line})))").trim()
+        val targets = parseGotoTargets(output)
+            .filter { request.column == null || it.column == 0 || it.column >= request.column }
+            .map { target ->
+                val id = nextGotoTargetId.getAndIncrement()
+                gotoTargets[id] = target
+                mapOf(
+                    "id" to id,
+                    "label" to gotoTargetLabel(target),
+                    "line" to target.line,
+                    "column" to target.column,
+                    "instructionPointerReference" to "0x%x".format(target.address),
+                )
+            }
+        sendSuccessResponse(ctx, requestSeq, "gotoTargets", mapOf("targets" to targets))
+    } catch (e: Exception) {
+        log.warning { "Stepping: gotoTargets failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "gotoTargets", e.message ?: "gotoTargets failed")
+    }
+}
+
+/**
+ * Handles `goto`: moves the PC of the thread's top frame to the target,
+ * if it lies in the function the thread is in, and reports the new
+ * position as a `goto` stop.
+ */
+suspend fun DebugSession.handleGoto(rawJson: String, ctx: AsyncRequestContext) {
+    val requestSeq = JSONObject(rawJson).optInt("seq", 0)
+    val request = DapMessage.parse(rawJson) as GotoRequest
+
+    try {
+        val target = gotoTargets[request.targetId] ?: throw SBError("Unknown goto target")
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val interpreter = debugger.commandInterpreter()
+        interpreter.handleCommand("script exec(${pyStr(PY_GOTO)})")
+        val error = interpreter.handleCommand(
+            "script print(_kdap_goto(${request.threadId}, ${target.address}, ${target.functionStart}))").trim()
+        if (error.isNotEmpty()) throw SBError(error)
+
+        forgetFrameState()
+        sendSuccessResponse(ctx, requestSeq, "goto")
+        ctx.sendEventToClient(
+            StoppedEvent(seq = 0, reason = "goto", threadId = request.threadId, allThreadsStopped = true).toJson()
+        )
+    } catch (e: Exception) {
+        log.warning { "Stepping: goto failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "goto", e.message ?: "goto failed")
+    }
+}
+
+/**
+ * Forgets KDAP's per-stop state after KDAP itself moved a thread.
+ * lldb-dap's is rebuilt from the client's requests after the stop event.
+ */
+internal fun DebugSession.forgetFrameState() {
+    onStopped()
+    forgetVariableChunks()
+    forgetReturnValueScopes()
+}
+
+/**
+ * Python side of [handleGotoTargets] and [handleGoto].
+ * `_kdap_goto_targets` reads the line table of the current function's
+ * compile unit, which also lists the lines of its other files (Rust
+ * modules, inlined code); files are matched by full path, else by file
+ * name. `_kdap_goto` returns an error message, or nothing once the PC is
+ * set. A return value shown for the old position is dropped.
+ */
+private val PY_GOTO = """
+    import json, os
+
+    def _kdap_goto_targets(path, line):
+        target = lldb.debugger.GetSelectedTarget()
+        frame = target.GetProcess().GetSelectedThread().GetFrameAtIndex(0)
+        function = frame.GetFunction()
+        if not function.IsValid():
+            return []
+        start = function.GetStartAddress()
+        low = start.GetLoadAddress(target)
+        high = function.GetEndAddress().GetLoadAddress(target)
+        unit = start.GetCompileUnit()
+        name = os.path.basename(path)
+        exact, by_name = {}, {}
+        for i in range(unit.GetNumLineEntries()):
+            entry = unit.GetLineEntryAtIndex(i)
+            spec = entry.GetFileSpec()
+            address = entry.GetStartAddress().GetLoadAddress(target)
+            if entry.GetLine() != line or spec.GetFilename() != name or not low <= address < high:
+                continue
+            found = exact if spec.fullpath == path else by_name
+            column = entry.GetColumn()
+            found[column] = min(address, found.get(column, address))
+        found = exact or by_name
+        return [{'address': found[column], 'line': line, 'column': column, 'function': low}
+                for column in sorted(found)]
+
+    def _kdap_goto(thread_id, address, function_start):
+        target = lldb.debugger.GetSelectedTarget()
+        thread = target.GetProcess().GetThreadByID(thread_id)
+        if not thread.IsValid():
+            return 'Invalid thread ID %d' % thread_id
+        frame = thread.GetFrameAtIndex(0)
+        function = frame.GetFunction()
+        if not function.IsValid() or function.GetStartAddress().GetLoadAddress(target) != function_start:
+            return 'Cannot jump to a line in another function'
+        if not frame.SetPC(address):
+            return 'Could not set the PC'
+        if '_kdap_return' in globals():
+            _kdap_return['callees'] = {}
+            _kdap_return['values'] = {}
+        return ''
+""".trimIndent()
+
+// ── Restart frame ────────────────────────────────────────────────
+
+/**
+ * Handles `restartFrame`: re-enters the frame's function from its entry
+ * with the parameters' current values (see the file header), then
+ * reports the thread as stopped with reason `restart`.
+ */
+suspend fun DebugSession.handleRestartFrame(rawJson: String, ctx: AsyncRequestContext) {
+    val requestSeq = JSONObject(rawJson).optInt("seq", 0)
+    val request = DapMessage.parse(rawJson) as RestartFrameRequest
+
+    try {
+        if (isTaskFrame(request.frameId)) throw SBError("Frames of async tasks cannot be restarted")
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val interpreter = debugger.commandInterpreter()
+        // The return value helpers classify parameters; re-running them
+        // also drops a return value shown for the old position.
+        interpreter.handleCommand("script exec(${pyStr(PY_RETURN_VALUES)})")
+        interpreter.handleCommand("script exec(${pyStr(PY_RESTART_FRAME)})")
+        val threadIndexId = request.frameId ushr FRAME_INDEX_BITS
+        val frameIndex = request.frameId and ((1 shl FRAME_INDEX_BITS) - 1)
+        val result = JSONObject(interpreter
+            .handleCommand("script print(json.dumps(_kdap_restart_frame($threadIndexId, $frameIndex)))").trim())
+        if (result.has("error")) throw SBError(result.getString("error"))
+
+        forgetFrameState()
+        sendSuccessResponse(ctx, requestSeq, "restartFrame")
+        ctx.sendEventToClient(
+            StoppedEvent(
+                seq = 0, reason = "restart", threadId = result.getInt("threadId"), allThreadsStopped = true,
+            ).toJson()
+        )
+    } catch (e: Exception) {
+        log.warning { "Stepping: restartFrame failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "restartFrame", e.message ?: "restartFrame failed")
+    }
+}
+
+/**
+ * Python side of [handleRestartFrame]. `_kdap_restart_frame` returns
+ * `{"threadId": ...}`, or `{"error": ...}` if the frame cannot be
+ * restarted. Parameters are checked and read before anything is
+ * changed; registers are written with `register write`, which discards
+ * the thread's cached frames.
+ */
+private val PY_RESTART_FRAME = """
+    import json, struct
+
+    _KDAP_ARG_REGISTERS = {
+        'x86_64': (('rdi', 'rsi', 'rdx', 'rcx', 'r8', 'r9'), tuple('xmm%d' % i for i in range(8))),
+        'aarch64': (tuple('x%d' % i for i in range(8)), tuple('v%d' % i for i in range(8))),
+    }
+    _KDAP_ARG_REGISTERS['arm64'] = _KDAP_ARG_REGISTERS['aarch64']
+
+    def _kdap_restart_arguments(frame, arch):
+        int_registers, float_registers = _KDAP_ARG_REGISTERS[arch]
+        ints, floats = list(int_registers), list(float_registers)
+        writes = []
+        error = lldb.SBError()
+        for value in frame.GetVariables(True, False, False, True):
+            value_type = value.GetType().GetCanonicalType()
+            size = value_type.GetByteSize()
+            if size == 0:
+                continue
+            raw = value.GetData().ReadRawData(error, 0, size) if value.GetError().Success() else None
+            if raw is None or error.Fail() or len(raw) != size:
+                raise RuntimeError('The value of %s is not available' % value.GetName())
+            kind = _kdap_return_class(value_type)
+            if kind == 'float' and floats:
+                writes.append((floats.pop(0), raw))
+                continue
+            count = (size + 7) // 8
+            if (arch != 'x86_64' and size == 16 and value_type.GetTypeClass() == lldb.eTypeClassBuiltin
+                    and (len(int_registers) - len(ints)) % 2 and ints):
+                ints.pop(0)
+            if kind != 'int' or len(ints) < count:
+                raise RuntimeError('Cannot restart a function that takes %s in memory' % value.GetName())
+            for i in range(count):
+                writes.append((ints.pop(0), raw[8 * i:8 * i + 8]))
+        return writes
+
+    def _kdap_register_write(thread, name, raw):
+        frame = thread.GetFrameAtIndex(0)
+        register = frame.FindRegister(name)
+        if not register.IsValid():
+            raise RuntimeError('Could not write register %s' % name)
+        size = register.GetByteSize()
+        raw = raw[:size].ljust(size, b'\0')
+        if size > 8:
+            value = '{' + ' '.join('0x%02x' % b for b in raw) + '}'
+        else:
+            value = '0x%x' % int.from_bytes(raw, 'little')
+        result = lldb.SBCommandReturnObject()
+        lldb.debugger.GetCommandInterpreter().HandleCommand(
+            'register write %s "%s"' % (name, value), lldb.SBExecutionContext(frame), result)
+        if not result.Succeeded():
+            raise RuntimeError(result.GetError().strip() or 'Could not write register %s' % name)
+
+    def _kdap_restart_frame(thread_index_id, frame_index):
+        target = lldb.debugger.GetSelectedTarget()
+        process = target.GetProcess()
+        thread = process.GetThreadByIndexID(thread_index_id)
+        frame = thread.GetFrameAtIndex(frame_index)
+        caller = thread.GetFrameAtIndex(frame_index + 1)
+        arch = target.GetTriple().split('-')[0]
+        if not frame.IsValid():
+            return {'error': 'Invalid frame'}
+        if arch not in _KDAP_ARG_REGISTERS or 'windows' in target.GetTriple():
+            return {'error': 'restartFrame is not supported on %s' % target.GetTriple()}
+        function = frame.GetFunction()
+        if not function.IsValid() or frame.IsInlined():
+            return {'error': 'Only frames of functions with debug info, not inlined ones, can be restarted'}
+        if not caller.IsValid():
+            return {'error': 'The frame has no caller to return to'}
+        try:
+            writes = _kdap_restart_arguments(frame, arch)
+        except RuntimeError as e:
+            return {'error': str(e)}
+        entry = function.GetStartAddress().GetLoadAddress(target)
+        return_address = caller.GetPC()
+        error = thread.ReturnFromFrame(frame, lldb.SBValue())
+        if error.Fail():
+            return {'error': error.GetCString() or 'Could not pop the frame'}
+        try:
+            if arch == 'x86_64':
+                sp = thread.GetFrameAtIndex(0).GetSP() - 8
+                process.WriteMemory(sp, struct.pack('<Q', return_address), error)
+                if error.Fail():
+                    raise RuntimeError('Could not push the return address')
+                _kdap_register_write(thread, 'rsp', struct.pack('<Q', sp))
+            else:
+                _kdap_register_write(thread, 'lr', struct.pack('<Q', return_address))
+            for name, raw in writes:
+                _kdap_register_write(thread, name, raw)
+            _kdap_register_write(thread, 'pc', struct.pack('<Q', entry))
+        except RuntimeError as e:
+            return {'error': '%s; the thread is stopped in the caller' % e}
+        return {'threadId': thread.GetThreadID()}
+""".trimIndent()
+
+// ── Pause ────────────────────────────────────────────────────────
+
+/** How long after lldb-dap answers `pause` KDAP waits for the stop before signaling the process. */
+internal const val PAUSE_GRACE_MS = 2_000L
+
+/** Descriptions of the stops LLDB's interrupt causes: `SIGSTOP`, `SIGINT`, or Windows' break-in. */
+private val PAUSE_STOP_DESCRIPTION = Regex("""\bSIG(STOP|INT)\b|0x80000003""")
+
+/** A `pause` waiting for its stop; [focusThreadId] is the thread last reported stopped. */
+class PendingPause(val focusThreadId: Int?) {
+    /** Completed by the first stop after the `pause`. */
+    val stopped = CompletableDeferred<Unit>()
+}
+
+/** Remembers the threads of a successful `threads` response. */
+fun DebugSession.onThreadsResponse(response: DapResponse) {
+    if (!response.success) return
+    val threads = response.body["threads"] as? List<*> ?: return
+    knownThreads.clear()
+    threads.mapNotNullTo(knownThreads) { ((it as? Map<*, *>)?.get("id") as? Number)?.toInt() }
+}
+
+/**
+ * Handles `pause`: sends it on to lldb-dap, relays the response, and
+ * signals the process if it has not stopped [PAUSE_GRACE_MS] later (see
+ * the file header).
+ */
+suspend fun DebugSession.handlePause(rawJson: String, ctx: AsyncRequestContext) {
+    val requestSeq = JSONObject(rawJson).optInt("seq", 0)
+    val pause = PendingPause(lastStoppedThreadId)
+    pendingPause = pause
+    val response = ctx.sendRequestToBackendAndAwait(rawJson).copy(seq = 0, requestSeq = requestSeq)
+    if (!response.success && pendingPause === pause) pendingPause = null
+    ctx.sendEventToClient(response.toJson())
+    if (!response.success || withTimeoutOrNull(PAUSE_GRACE_MS) { pause.stopped.await() } != null) return
+
+    try {
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val process = debugger.selectedTarget().process()
+        if (pendingPause !== pause || !process.state().isRunning()) return
+        log.info { "Stepping: the process did not stop ${PAUSE_GRACE_MS} ms after pause; sending SIGSTOP" }
+        interruptProcess(debugger, process)
+    } catch (e: Exception) {
+        log.warning { "Stepping: interrupting the process failed: ${e.message}" }
+    }
+}
+
+/**
+ * Sends [process] `SIGSTOP`: by pid if it runs on this machine, through
+ * LLDB if that fails or it runs elsewhere. Processes without signals
+ * (Windows) are left to LLDB's interrupt.
+ */
+private suspend fun interruptProcess(debugger: SBDebugger, process: SBProcess) {
+    val signals = process.unixSignals()
+    val signo = (if (signals.isValid()) signals.signalNumberFromName("SIGSTOP") else null) ?: return
+    if (debugger.selectedPlatform().name() == "host") {
+        val kill = ProcessBuilder("kill", "-STOP", process.processId().toString()).start()
+        if (runInterruptible(Dispatchers.IO) { kill.waitFor() } == 0) return
+    }
+    process.signal(signo)
+}
+
+/** Whether [event] looks like the stop of LLDB's interrupt rather than a breakpoint, step, or fault. */
+internal fun isPauseStop(event: StoppedEvent): Boolean = when (event.reason) {
+    "pause" -> true
+    null, "signal", "exception", "unknown" -> event.description.let { it == null || PAUSE_STOP_DESCRIPTION.containsMatchIn(it) }
+    else -> false
+}
+
+/**
+ * The thread to report a pause on: the [focus] thread if it is still
+ * [known], else the [stopped] one if it is, else the first known thread.
+ * Without known threads, the [stopped] one.
+ */
+internal fun pauseThread(stopped: Int?, focus: Int?, known: Set<Int>): Int? = when {
+    known.isEmpty() -> stopped ?: focus
+    focus != null && focus in known -> focus
+    stopped != null && stopped in known -> stopped
+    else -> known.min()
+}
+
+/**
+ * Reports the stop that ends a `pause` as a `pause` (see the file
+ * header). Every stop ends a pending pause and is remembered as
+ * [DebugSession.lastStoppedThreadId].
+ */
+fun DebugSession.rewritePauseStop(event: StoppedEvent): StoppedEvent {
+    val pause = pendingPause
+    pendingPause = null
+    pause?.stopped?.complete(Unit)
+    val rewritten = if (pause != null && isPauseStop(event)) {
+        pausedStop = true
+        event.copy(
+            reason = "pause", description = null, text = null, hitBreakpointIds = null, allThreadsStopped = true,
+            threadId = pauseThread(event.threadId, pause.focusThreadId, knownThreads),
+        )
+    } else {
+        event
+    }
+    rewritten.threadId?.let { lastStoppedThreadId = it }
+    return rewritten
+}
@@ -0,0 +1,191 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.kdapMarker
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.OutputEvent
+import com.github.jomof.dap.messages.SymbolStatusEvent
+import com.github.jomof.dap.sb.SBDebugger
+import com.github.jomof.dap.sb.pyStr
+import org.json.JSONObject
+import java.nio.file.Path
+import java.util.logging.Logger
+
+/**
+ * Symbol index cache: the name and line indexes LLDB builds for each
+ * module, kept on disk between sessions. KDAP extension; CodeLLDB leaves
+ * LLDB's index cache off.
+ *
+ * Without accelerator tables (`.debug_names`), LLDB indexes a module's
+ * DWARF by reading every compile unit the first time it looks a name up,
+ * which takes seconds for a large Rust binary, on every launch. LLDB can
+ * save that index, with the module's symbol table, to its index cache
+ * (`symbols.enable-lldb-index-cache`), each entry signed with the
+ * module's UUID (build ID) and modification time, and uses a saved entry
+ * only if both still match. KDAP turns the cache on, in
+ * `<kdap cache>/index` ([symbolIndexCacheDirectory]), unless the launch
+ * configuration sets `"symbolIndexCache": false`. It is set before
+ * `initCommands`, which can pick another path; LLDB prunes the cache
+ * itself (`symbols.lldb-index-cache-max-byte-size`,
+ * `-max-percent`, `-expiration-days`).
+ *
+ * A Python warm-up ([initSymbolIndexWarmUp]) then indexes each module
+ * the target has or loads with a lookup of a name no module defines. The
+ * first launch of a build pays for indexing in the background, while the
+ * client sends its breakpoints, and writes the cache; later launches read
+ * it back at once.
+ *
+ * ## Parallel indexing
+ *
+ * LLDB already spreads one module's indexing across its thread pool: the
+ * compile units are parsed and their names indexed in parallel, and the
+ * per-unit tables merged. The warm-up adds
+ * parallelism across modules, indexing up to
+ * [SYMBOL_INDEX_WORKERS] modules at once, so a program with many shared
+ * libraries does not index them one after another.
+ *
+ * Progress is reported with the custom `kdap/symbolStatus` event
+ * ([SymbolStatusEvent]): one when a module starts (`"indexing"`) and one
+ * when it is done (`"indexed"`), each with how many of the modules seen so
+ * far are done, so a client can show indexing progress. The warm-up
+ * writes them to the debugger's output stream prefixed with
+ * [SYMBOL_STATUS_MARKER], as the module listener does (see Modules.kt),
+ * and [splitSymbolStatusEvents] turns them into events. lldb-dap's own
+ * progress events for LLDB's indexing are forwarded as they are.
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.SymbolIndex")
+
+/** Prefix on status records written by the index warm-up (see [kdapMarker]). */
+internal val SYMBOL_STATUS_MARKER = kdapMarker("symbols")
+
+/** How many modules the warm-up indexes at once. */
+internal val SYMBOL_INDEX_WORKERS = Runtime.getRuntime().availableProcessors().coerceIn(1, 8)
+
+/** Where the symbol index cache is kept. */
+internal fun symbolIndexCacheDirectory(environment: Map<String, String>, home: String): Path =
+    kdapCacheDirectory(environment, home).resolve("index")
+
+/**
+ * Turns LLDB's index cache on in [symbolIndexCacheDirectory], or off if
+ * [enabled] is `false`.
+ */
+internal suspend fun initSymbolIndexCache(enabled: Boolean?, debugger: SBDebugger) {
+    if (enabled == false) {
+        debugger.setVariable("symbols.enable-lldb-index-cache", "false")
+        return
+    }
+    val directory = symbolIndexCacheDirectory(System.getenv(), System.getProperty("user.home"))
+    debugger.setVariable("symbols.lldb-index-cache-path", directory.toString())
+    debugger.setVariable("symbols.enable-lldb-index-cache", "true")
+}
+
+/** Starts the index warm-up thread on the selected target, unless [enabled] is `false`. */
+internal suspend fun initSymbolIndexWarmUp(enabled: Boolean?, debugger: SBDebugger) {
+    if (enabled == false) return
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_SYMBOL_INDEX)})")
+    interpreter.handleCommand(
+        "script _kdap_index_start(${pyStr(SYMBOL_STATUS_MARKER)}, $SYMBOL_INDEX_WORKERS)")
+}
+
+/**
+ * Splits debugger [output] carrying [SYMBOL_STATUS_MARKER] into
+ * `kdap/symbolStatus` events and the other output around them. Each
+ * record is a marker, one JSON object, and a newline; records that do not
+ * parse are skipped.
+ */
+fun splitSymbolStatusEvents(output: OutputEvent): List<DapMessage> {
+    val parts = output.output.split(SYMBOL_STATUS_MARKER)
+    val messages = mutableListOf<DapMessage>()
+    fun other(text: String) {
+        if (text.isNotEmpty()) messages += output.copy(output = text)
+    }
+    other(parts.first())
+    for (part in parts.drop(1)) {
+        val end = part.indexOf('\n').let { if (it < 0) part.length else it }
+        val record = part.substring(0, end)
+        try {
+            val status = JSONObject(record)
+            messages += SymbolStatusEvent(
+                seq = 0,
+                state = status.getString("state"),
+                module = status.getString("module"),
+                modulesIndexed = status.getInt("modulesIndexed"),
+                modulesTotal = status.getInt("modulesTotal"),
+            )
+        } catch (e: Exception) {
+            log.warning { "SymbolIndex: skipping status record '$record': ${e.message}" }
+        }
+        other(part.substring(minOf(end + 1, part.length)))
+    }
+    return messages
+}
+
+/**
+ * Python side of [initSymbolIndexWarmUp]. A listener thread queues the
+ * modules that load; the dispatch thread hands each to a pool of
+ * `workers` threads, which look the warm-up name up in it and so have
+ * LLDB build, or read back, the module's index. LLDB's Python calls
+ * release the GIL, so the pool's lookups run side by side.
+ */
+private val PY_SYMBOL_INDEX = """
+    import concurrent.futures, json, queue, threading
+
+    if '_kdap_index' not in globals():
+        _kdap_index = {'listener': None}
+
+    def _kdap_index_say(state, name):
+        with _kdap_index['lock']:
+            record = {'state': state, 'module': name,
+                      'modulesIndexed': _kdap_index['indexed'], 'modulesTotal': _kdap_index['total']}
+            output = _kdap_index['output']
+            output.Write((_kdap_index['marker'] + json.dumps(record) + '\n').encode('utf-8'))
+            output.Flush()
+
+    def _kdap_index_module(module):
+        name = module.GetFileSpec().GetFilename() or ''
+        _kdap_index_say('indexing', name)
+        try:
+            module.FindFunctions('_kdap_index_warm_up')
+        except Exception:
+            pass
+        with _kdap_index['lock']:
+            _kdap_index['indexed'] += 1
+        _kdap_index_say('indexed', name)
+
+    def _kdap_index_work(work, workers):
+        seen = set()
+        with concurrent.futures.ThreadPoolExecutor(max_workers=workers) as pool:
+            while True:
+                module = work.get()
+                if module is None:
+                    return
+                key = module.GetFileSpec().fullpath
+                if key in seen:
+                    continue
+                seen.add(key)
+                with _kdap_index['lock']:
+                    _kdap_index['total'] += 1
+                pool.submit(_kdap_index_module, module)
+
+    def _kdap_index_listen(listener, work):
+        event = lldb.SBEvent()
+        while _kdap_index['listener'] is listener:
+            if not listener.WaitForEvent(1, event):
+                continue
+            for i in range(lldb.SBTarget.GetNumModulesFromEvent(event)):
+                work.put(lldb.SBTarget.GetModuleAtIndexFromEvent(i, event))
+        work.put(None)
+
+    def _kdap_index_start(marker, workers):
+        target = lldb.debugger.GetSelectedTarget()
+        listener = lldb.SBListener('kdap.symbol-index')
+        target.GetBroadcaster().AddListener(listener, lldb.SBTarget.eBroadcastBitModulesLoaded)
+        _kdap_index.update(listener=listener, marker=marker, output=lldb.debugger.GetOutputFile(),
+                           lock=threading.Lock(), indexed=0, total=0)
+        work = queue.Queue()
+        for module in target.module_iter():
+            work.put(module)
+        threading.Thread(target=_kdap_index_work, args=(work, workers), daemon=True).start()
+        threading.Thread(target=_kdap_index_listen, args=(listener, work), daemon=True).start()
+""".trimIndent()
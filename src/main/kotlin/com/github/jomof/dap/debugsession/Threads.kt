@@ -0,0 +1,210 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.kdapMarker
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.OutputEvent
+import com.github.jomof.dap.messages.ThreadEvent
+import com.github.jomof.dap.sb.SBDebugger
+import com.github.jomof.dap.sb.createDebugger
+import com.github.jomof.dap.sb.pyStr
+import com.github.jomof.dap.sb.watched
+import org.json.JSONObject
+import java.util.logging.Logger
+
+/**
+ * Threads: the `threads` request's thread names, and `thread` events.
+ *
+ * lldb-dap names a thread after its LLDB name, or `Thread #N` when it
+ * has none. KDAP names them the way CodeLLDB does ([threadLabel]): the
+ * thread's index, its OS thread ID, and its name in quotes, e.g.
+ * `2: tid=4242 "worker-1"`. The name is LLDB's (`pthread_setname_np`,
+ * as the debug server reports it), or else, for a process on this
+ * machine, `/proc/<pid>/task/<tid>/comm`. When the session lists tokio
+ * tasks, their pseudo-threads follow the OS threads (see AsyncTasks.kt).
+ *
+ * lldb-dap only learns of threads at stops, and its `thread` events
+ * are dropped. Once the process exists, a Python listener on the
+ * process's broadcaster ([initThreadEvents]) compares the process's
+ * threads with the ones it reported, at every stop and, for a process
+ * on this machine, every second while it runs (from `/proc/<pid>/task`),
+ * and writes each change to the debugger's output stream prefixed with
+ * [THREAD_EVENT_MARKER], like the module listener (see Modules.kt);
+ * [splitThreadEvents] turns those chunks back into `thread` events with
+ * reason `started` or `exited`. The threads the process has when the
+ * listener starts are reported as `started`.
+ *
+ * ## CodeLLDB method mapping
+ *
+ * | CodeLLDB (debug_session.rs)       | KDAP (this file)                   |
+ * |-----------------------------------|------------------------------------|
+ * | `handle_threads`                  | [DebugSession.handleThreads]       |
+ * | `handle_process_event` (threads)  | [initThreadEvents]                 |
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.Threads")
+
+/** Prefix on thread changes written by the thread listener (see [kdapMarker]). */
+internal val THREAD_EVENT_MARKER = kdapMarker("thread")
+
+/** The `threads` name of thread [indexId] with OS thread ID [tid] and LLDB or OS [name]. */
+internal fun threadLabel(indexId: Int, tid: Long, name: String?): String =
+    if (name.isNullOrEmpty()) "$indexId: tid=$tid" else "$indexId: tid=$tid \"$name\""
+
+/**
+ * Renames lldb-dap's [threads] from [names], the `_kdap_thread_names`
+ * output keyed by thread ID. Threads without an entry keep their name.
+ */
+internal fun nameThreads(threads: List<*>, names: JSONObject): List<Any?> = threads.map { thread ->
+    val map = thread as? Map<*, *> ?: return@map thread
+    val id = (map["id"] as? Number)?.toLong() ?: return@map thread
+    val info = names.optJSONObject(id.toString()) ?: return@map thread
+    map + ("name" to threadLabel(info.getInt("index"), id, info.optString("name", null)))
+}
+
+/**
+ * Starts the thread listener on the selected target's process. It first
+ * reports the threads the process already has as `started`.
+ */
+internal suspend fun initThreadEvents(debugger: SBDebugger) {
+    val interpreter = debugger.commandInterpreter()
+    interpreter.handleCommand("script exec(${pyStr(PY_THREADS)})")
+    interpreter.handleCommand("script _kdap_threads_start(${pyStr(THREAD_EVENT_MARKER)})")
+}
+
+/**
+ * Splits debugger [output] carrying [THREAD_EVENT_MARKER] into `thread`
+ * events and the other output around them. Each change is a marker,
+ * one JSON object with `reason` and `threadId`, and a newline; records
+ * that do not parse are skipped.
+ */
+fun splitThreadEvents(output: OutputEvent): List<DapMessage> {
+    val parts = output.output.split(THREAD_EVENT_MARKER)
+    val messages = mutableListOf<DapMessage>()
+    fun other(text: String) {
+        if (text.isNotEmpty()) messages += output.copy(output = text)
+    }
+    other(parts.first())
+    for (part in parts.drop(1)) {
+        val end = part.indexOf('\n').let { if (it < 0) part.length else it }
+        val record = part.substring(0, end)
+        try {
+            val obj = JSONObject(record)
+            messages += ThreadEvent(seq = 0, reason = obj.getString("reason"), threadId = obj.getInt("threadId"))
+        } catch (e: Exception) {
+            log.warning { "Threads: skipping thread change '$record': ${e.message}" }
+        }
+        other(part.substring(minOf(end + 1, part.length)))
+    }
+    return messages
+}
+
+/**
+ * Handles `threads`: lldb-dap's threads under KDAP's names, followed by
+ * the tokio task pseudo-threads when they are listed (see the file
+ * header). If the names can't be read, lldb-dap's are kept.
+ */
+suspend fun DebugSession.handleThreads(rawJson: String, ctx: AsyncRequestContext) {
+    val requestSeq = JSONObject(rawJson).optInt("seq", 0)
+    val response = ctx.sendRequestToBackendAndAwait(rawJson)
+    onThreadsResponse(response)
+    val threads = response.body["threads"] as? List<*>
+    if (!response.success || threads == null) {
+        ctx.sendEventToClient(response.copy(seq = 0, requestSeq = requestSeq).toJson())
+        return
+    }
+    val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+    val named = try {
+        val interpreter = debugger.commandInterpreter()
+        interpreter.handleCommand("script exec(${pyStr(PY_THREADS)})")
+        nameThreads(threads, JSONObject(interpreter.handleCommand("script print(_kdap_thread_names())").trim()))
+    } catch (e: Exception) {
+        log.warning { "Threads: reading thread names failed: ${e.message}" }
+        threads
+    }
+    val taskThreads = if (tokioTasks) taskThreads(debugger) else emptyList()
+    sendSuccessResponse(ctx, requestSeq, "threads", mapOf("threads" to named + taskThreads))
+}
+
+/**
+ * Python side of [initThreadEvents] and [handleThreads].
+ * `_kdap_thread_names` returns `{tid: {"index", "name"}}` for the
+ * process's threads; `name` is `null` when neither LLDB nor `/proc`
+ * has one. The listener stops when the process exits or detaches, or
+ * when a later session starts another.
+ */
+private val PY_THREADS = """
+    import json, os, threading
+
+    if '_kdap_threads' not in globals():
+        _kdap_threads = {'known': set(), 'listener': None}
+
+    def _kdap_thread_on_host(process):
+        return process.GetTarget().GetPlatform().IsHost()
+
+    def _kdap_thread_comm(process, tid):
+        if not _kdap_thread_on_host(process):
+            return None
+        try:
+            with open('/proc/%d/task/%d/comm' % (process.GetProcessID(), tid)) as f:
+                return f.read().rstrip('\n') or None
+        except OSError:
+            return None
+
+    def _kdap_thread_names():
+        process = lldb.debugger.GetSelectedTarget().GetProcess()
+        names = {}
+        for thread in process:
+            tid = thread.GetThreadID()
+            names[str(tid)] = {'index': thread.GetIndexID(), 'name': thread.GetName() or _kdap_thread_comm(process, tid)}
+        return json.dumps(names)
+
+    def _kdap_live_threads(process):
+        state = process.GetState()
+        if state == lldb.eStateStopped:
+            return set(thread.GetThreadID() for thread in process)
+        path = '/proc/%d/task' % process.GetProcessID()
+        if state == lldb.eStateRunning and _kdap_thread_on_host(process) and os.path.isdir(path):
+            try:
+                return set(int(tid) for tid in os.listdir(path))
+            except (OSError, ValueError):
+                return None
+        return None
+
+    def _kdap_threads_send(reason, tid):
+        record = json.dumps({'reason': reason, 'threadId': tid})
+        output = _kdap_threads['output']
+        output.Write((_kdap_threads['marker'] + record + '\n').encode('utf-8'))
+        output.Flush()
+
+    def _kdap_threads_update(process):
+        live = _kdap_live_threads(process)
+        if live is None:
+            return
+        known = _kdap_threads['known']
+        for tid in sorted(live - known):
+            _kdap_threads_send('started', tid)
+        for tid in sorted(known - live):
+            _kdap_threads_send('exited', tid)
+        _kdap_threads['known'] = live
+
+    def _kdap_threads_listen(process, listener):
+        event = lldb.SBEvent()
+        while _kdap_threads['listener'] is listener:
+            listener.WaitForEvent(1, event)
+            if process.GetState() in (lldb.eStateExited, lldb.eStateDetached, lldb.eStateInvalid):
+                break
+            try:
+                _kdap_threads_update(process)
+            except Exception:
+                pass
+
+    def _kdap_threads_start(marker):
+        process = lldb.debugger.GetSelectedTarget().GetProcess()
+        listener = lldb.SBListener('kdap.threads')
+        process.GetBroadcaster().AddListener(listener, lldb.SBProcess.eBroadcastBitStateChanged)
+        _kdap_threads.update(known=set(), listener=listener, marker=marker,
+                             output=lldb.debugger.GetOutputFile())
+        _kdap_threads_update(process)
+        threading.Thread(target=_kdap_threads_listen, args=(process, listener), daemon=True).start()
+""".trimIndent()
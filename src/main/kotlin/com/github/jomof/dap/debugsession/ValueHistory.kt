@@ -0,0 +1,185 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.sb.createDebugger
+import com.github.jomof.dap.sb.pyStr
+import com.github.jomof.dap.sb.watched
+import org.json.JSONArray
+import org.json.JSONObject
+import java.util.logging.Logger
+
+/**
+ * Changed values: a frame's variables that changed since the previous
+ * stop are marked so clients can highlight them. KDAP extension;
+ * CodeLLDB and lldb-dap report values without history.
+ *
+ * Each `scopes` response records which frame its scopes belong to
+ * ([ValueFrame]): lldb-dap's frame ID, for reading values, and a key
+ * made of the thread, the frame's CFA, and its function, which outlasts
+ * the stop while the frame is live. The children of a scope's variables
+ * (and chunk nodes) belong to the same frame. In each `variables`
+ * response of a scope or its children, every variable with an
+ * `evaluateName` is recorded in the [ValueHistory] under the frame key
+ * and that name:
+ *
+ * - a scalar by its value text;
+ * - a container by a digest of its value, its summary, and its
+ *   children's names and values down to `_adapterSettings.changedValuesDepth`
+ *   levels (1 by default; 0 compares the summary only), so a `Vec`
+ *   whose element changed is marked while it is collapsed.
+ *
+ * A variable whose record differs from the one of the last stop it was
+ * shown at gets the presentation hint attribute `changed`
+ * ([markChanged]); a variable shown for the first time is not marked.
+ * Statics, Registers, return values, and `evaluate` results (watches
+ * included) are not tracked.
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.ValueHistory")
+
+/** Child levels compared for containers when the launch configuration sets no depth. */
+internal const val DEFAULT_CHANGED_VALUES_DEPTH = 1
+
+/** Values kept from earlier stops; the least recently shown are forgotten first. */
+internal const val VALUE_HISTORY_LIMIT = 10_000
+
+/** Children compared per container level, so huge containers stay cheap. */
+internal const val VALUE_DIGEST_MAX_CHILDREN = 256
+
+/** The presentation hint attribute of a value that changed since the previous stop. */
+internal const val CHANGED_ATTRIBUTE = "changed"
+
+/** The frame a variables reference belongs to: lldb-dap's [frameId] and the frame's stable [key]. */
+data class ValueFrame(val frameId: Int, val key: String)
+
+/**
+ * The values shown at the current stop and the ones shown at earlier
+ * stops, by frame key and `evaluateName`.
+ */
+class ValueHistory(private val limit: Int = VALUE_HISTORY_LIMIT) {
+    private val previous = LinkedHashMap<String, String>()
+    private val current = HashMap<String, String>()
+
+    /**
+     * Records [signature] as the value of [key] at this stop. Returns
+     * whether it differs from the value at the last stop [key] was shown.
+     */
+    @Synchronized
+    fun record(key: String, signature: String): Boolean {
+        current[key] = signature
+        val before = previous[key]
+        return before != null && before != signature
+    }
+
+    /** Ends the current stop: its values become the ones later stops compare with. */
+    @Synchronized
+    fun rotate() {
+        for ((key, signature) in current) {
+            previous.remove(key)
+            previous[key] = signature
+        }
+        current.clear()
+        val oldest = previous.keys.iterator()
+        repeat(maxOf(0, previous.size - limit)) {
+            oldest.next()
+            oldest.remove()
+        }
+    }
+}
+
+/** [variable] with the presentation hint attribute [CHANGED_ATTRIBUTE], keeping its other hints. */
+internal fun markChanged(variable: Map<*, *>): Map<*, *> {
+    val hint = variable["presentationHint"] as? Map<*, *> ?: emptyMap<String, Any?>()
+    val attributes = hint["attributes"] as? List<*> ?: emptyList<Any?>()
+    if (CHANGED_ATTRIBUTE in attributes) return variable
+    return variable + ("presentationHint" to hint + ("attributes" to attributes + CHANGED_ATTRIBUTE))
+}
+
+/**
+ * Records the frame of each scope in a `scopes` response for frame
+ * [frameId], so the scopes' variables are tracked. Does nothing if the
+ * frame can't be read.
+ */
+internal suspend fun DebugSession.recordScopeFrames(scopes: List<*>, frameId: Int, ctx: AsyncRequestContext) {
+    val key = frameKey(frameId, ctx) ?: return
+    for (scope in scopes) {
+        val reference = ((scope as? Map<*, *>)?.get("variablesReference") as? Number)?.toInt() ?: continue
+        if (reference > 0) valueFrames[reference] = ValueFrame(frameId, key)
+    }
+}
+
+/**
+ * [variables], children of a reference of [frame], with the ones that
+ * changed since the previous stop marked (see the file header). Their
+ * own references are recorded as belonging to [frame]. [digests] holds
+ * the container digests by `evaluateName`.
+ */
+internal fun DebugSession.trackValues(variables: List<Any?>, frame: ValueFrame, digests: Map<String, String>): List<Any?> =
+    variables.map { variable ->
+        val fields = variable as? Map<*, *> ?: return@map variable
+        val reference = (fields["variablesReference"] as? Number)?.toInt() ?: 0
+        if (reference > 0) valueFrames[reference] = frame
+        val path = fields["evaluateName"] as? String ?: return@map variable
+        val signature = digests[path] ?: fields["value"] as? String ?: return@map variable
+        if (valueHistory.record("${frame.key}/$path", signature)) markChanged(fields) else variable
+    }
+
+/**
+ * Digests of the containers among [variables] in [frame], by
+ * `evaluateName`, down to [DebugSession.changedValuesDepth] child
+ * levels. Empty when the depth is 0 or they can't be read.
+ */
+internal suspend fun DebugSession.valueDigests(variables: List<Any?>, frame: ValueFrame, ctx: AsyncRequestContext): Map<String, String> {
+    if (changedValuesDepth <= 0) return emptyMap()
+    val paths = variables.mapNotNull { variable ->
+        val fields = variable as? Map<*, *> ?: return@mapNotNull null
+        val reference = (fields["variablesReference"] as? Number)?.toInt() ?: 0
+        (fields["evaluateName"] as? String)?.takeIf { reference > 0 }
+    }
+    if (paths.isEmpty()) return emptyMap()
+    return try {
+        val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+        val interpreter = debugger.commandInterpreter()
+        interpreter.handleCommand("script exec(${pyStr(PY_VALUE_HISTORY)})")
+        val output = interpreter.handleCommand(
+            "script print(_kdap_value_digests(${framePython(frame.frameId)}, " +
+                "${pyStr(JSONArray(paths).toString())}, $changedValuesDepth, $VALUE_DIGEST_MAX_CHILDREN))",
+        )
+        val obj = JSONObject(output.trim())
+        obj.keySet().associateWith { obj.getString(it) }
+    } catch (e: Exception) {
+        log.warning { "ValueHistory: digesting containers failed: ${e.message}" }
+        emptyMap()
+    }
+}
+
+/** Ends the stop for the value history and forgets the frames of its references. */
+fun DebugSession.rotateValueHistory() {
+    valueHistory.rotate()
+    valueFrames.clear()
+}
+
+/**
+ * Python side of [valueDigests]. `_kdap_value_digests` returns
+ * `{evaluateName: digest}` for the paths that resolve in the frame.
+ */
+private val PY_VALUE_HISTORY = """
+    import hashlib, json
+
+    def _kdap_value_digest(value, depth, max_children):
+        parts = [value.GetValue() or '', value.GetSummary() or '']
+        if depth > 0:
+            for i in range(min(value.GetNumChildren(), max_children)):
+                child = value.GetChildAtIndex(i)
+                parts.append(child.GetName() or '')
+                parts.append(_kdap_value_digest(child, depth - 1, max_children))
+        return hashlib.sha1('\0'.join(parts).encode('utf-8', 'replace')).hexdigest()
+
+    def _kdap_value_digests(frame, paths, depth, max_children):
+        digests = {}
+        for path in json.loads(paths):
+            value = frame.GetValueForVariablePath(path)
+            if value.IsValid() and not value.GetError().Fail():
+                digests[path] = _kdap_value_digest(value, depth, max_children)
+        return json.dumps(digests)
+""".trimIndent()
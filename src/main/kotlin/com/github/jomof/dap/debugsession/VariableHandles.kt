@@ -0,0 +1,122 @@
+package com.github.jomof.dap.debugsession
+
+import java.util.TreeSet
+
+/**
+ * KDAP's own `variablesReference`s: handles into per-stop tables. KDAP
+ * extension; CodeLLDB keeps its references in a `HandleTree` that is
+ * reset when the process resumes.
+ *
+ * KDAP hands out references for nodes lldb-dap knows nothing about, each
+ * kind from a [HandleTable] over its own range, above lldb-dap's
+ * references:
+ *
+ * | Range                                               | Nodes                        |
+ * |-----------------------------------------------------|------------------------------|
+ * | [FIRST_WATCH_REFERENCE]…[FIRST_ARRAY_REFERENCE]     | watches, kept across stops (Watches.kt) |
+ * | [FIRST_ARRAY_REFERENCE]…[FIRST_CHUNK_REFERENCE]     | pointers viewed as arrays (Variables.kt) |
+ * | [FIRST_CHUNK_REFERENCE]…[FIRST_LOCAL_SCOPE_REFERENCE] | chunks of large containers (Variables.kt) |
+ * | [FIRST_LOCAL_SCOPE_REFERENCE]…[FIRST_STATICS_REFERENCE] | Arguments and Captures scopes (LocalScopes.kt) |
+ * | [FIRST_STATICS_REFERENCE]…[FIRST_REGISTERS_REFERENCE] | Statics scopes (Statics.kt) |
+ * | [FIRST_REGISTERS_REFERENCE]…[FIRST_STATIC_VALUE_REFERENCE] | Registers scopes and their nodes (Registers.kt) |
+ *
+ * Values under a Statics scope, from [FIRST_STATIC_VALUE_REFERENCE] up,
+ * are handed out by the Python side.
+ *
+ * A table hands out one reference per node ([HandleTable.handleFor]):
+ * a client that asks for a frame's scopes or a container's chunks again
+ * at the same stop gets the references it already has. Like lldb-dap's,
+ * the references are only valid while stopped: every table but the
+ * watches' is reset at each stop and when the process resumes
+ * (`continued` events and the responses to `continue` and the steps; see
+ * `VariablesHandler`). The watches' table instead drops the watches no
+ * longer asked for ([HandleTable.retain]; see Watches.kt).
+ *
+ * ## Generations
+ *
+ * A table holds at most [MAX_HANDLES_PER_STOP] nodes, looked up by
+ * subtracting the table's base from the reference. Each stop at which a
+ * table was used starts a new generation of it, whose references follow
+ * the last generation's: a reference of an earlier stop then names
+ * nothing rather than some node of this stop, until the generations wrap
+ * around at the end of the range. References are the same from run to
+ * run for the same requests. Past [MAX_HANDLES_PER_STOP] nodes in one
+ * stop, nodes get reference 0 and show without children.
+ */
+
+/** Nodes a [HandleTable] holds at most between two resets. */
+internal const val MAX_HANDLES_PER_STOP = 1 shl 16
+
+/** DAP commands whose success means the process resumed. */
+internal val RESUME_COMMANDS = setOf("continue", "next", "stepIn", "stepOut", "stepBack", "reverseContinue", "goto")
+
+/**
+ * References from [first] (inclusive) to [end] (exclusive) for nodes of
+ * type [T], valid until the next [reset] (see the file header). Reads as
+ * a map from the references of the current generation to their nodes.
+ * The references of nodes dropped by [retain] are handed out again,
+ * lowest first.
+ */
+class HandleTable<T : Any>(
+    private val first: Int,
+    private val end: Int,
+    private val capacity: Int = MAX_HANDLES_PER_STOP,
+) : AbstractMap<Int, T>() {
+    private val generations = maxOf(1, (end - first) / capacity)
+    private val nodes = ArrayList<T?>()
+    private val handles = HashMap<T, Int>()
+    private val free = TreeSet<Int>()
+    private var generation = 0
+
+    /** The reference of [node], handed out on first use; 0 once the table is full. */
+    @Synchronized
+    fun handleFor(node: T): Int {
+        handles[node]?.let { return it }
+        val index = free.pollFirst() ?: nodes.size.takeIf { it < capacity }?.also { nodes += null } ?: return 0
+        nodes[index] = node
+        val handle = base() + index
+        handles[node] = handle
+        return handle
+    }
+
+    /** Forgets the nodes [keep] rejects, and nothing else; their references may name other nodes later. */
+    @Synchronized
+    fun retain(keep: (T) -> Boolean) {
+        for ((index, node) in nodes.withIndex()) {
+            if (node == null || keep(node)) continue
+            nodes[index] = null
+            handles.remove(node)
+            free += index
+        }
+    }
+
+    /** The reference of [node] if it has one, without handing one out. */
+    @Synchronized
+    fun handleOf(node: T): Int? = handles[node]
+
+    /** Forgets every node; the next reference handed out starts a new generation. */
+    @Synchronized
+    fun reset() {
+        if (nodes.isEmpty()) return
+        nodes.clear()
+        handles.clear()
+        free.clear()
+        generation = (generation + 1) % generations
+    }
+
+    @Synchronized
+    override fun get(key: Int): T? {
+        val index = key.toLong() - base()
+        return if (index >= 0 && index < nodes.size) nodes[index.toInt()] else null
+    }
+
+    override fun containsKey(key: Int): Boolean = get(key) != null
+
+    override val size: Int
+        @Synchronized get() = handles.size
+
+    override val entries: Set<Map.Entry<Int, T>>
+        @Synchronized get() = handles.entries.associate { (node, handle) -> handle to node }.entries
+
+    private fun base(): Int = first + generation * capacity
+}
@@ -0,0 +1,900 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.messages.OutputEvent
+import com.github.jomof.dap.sb.*
+import kotlinx.coroutines.sync.withLock
+import org.json.JSONObject
+import java.util.logging.Logger
+
+/**
+ * Variables logic as extension functions on [DebugSession], mirroring
+ * CodeLLDB's `debug_session/variables.rs`.
+ *
+ * lldb-dap builds every `variables` response and serves `readMemory` /
+ * `writeMemory` itself. KDAP relays `variables` so it can fill in a
+ * `memoryReference` (which makes VS Code offer "View Binary Data") for
+ * values that point at memory but that lldb-dap doesn't mark:
+ *
+ * - Pointers and references: the address at the start of the value text.
+ * - Rust owning and fat pointers (`Box`, `Rc`, `Arc`, `Vec`, `String`,
+ *   slices, `&str`), which DWARF describes as structs: KDAP follows the
+ *   value's data pointer field through the wrapper structs (see
+ *   [dataAddress]).
+ * - C++ `std::vector`, `std::string`, and smart pointers, under libstdc++
+ *   or libc++: the same, through the library's member names.
+ *
+ * ## Large containers
+ *
+ * lldb-dap honors `start`/`count` on `variables` but only reports
+ * `indexedVariables` for C arrays, so a client fetches every element of
+ * a Rust `Vec` or slice, or of a C++ container, at once. KDAP adds
+ * `indexedVariables` (read from the `(N)` prefix of the Rust formatter
+ * summary, or LLDB's `size=N`) so clients page them; VS
+ * Code then shows `[0..99]`-style groups of its own. For clients that
+ * ask for all children of a container with more than
+ * [VARIABLE_CHUNK_SIZE] elements, KDAP answers with such groups itself
+ * ([chunkRanges]): each is a virtual variable whose children KDAP
+ * fetches from lldb-dap as a `start`/`count` window.
+ *
+ * `evaluate` requests whose expression is a simple expression (see
+ * `Expressions.kt`) are evaluated by KDAP in the requested frame. When
+ * the result is a place with children, KDAP asks lldb-dap to evaluate
+ * its LLDB expression path so the client can still expand it. In the
+ * debug console KDAP also runs backtick meta-commands (streaming their
+ * output as `output` events), `?` expressions, and assignments.
+ *
+ * ## Array views
+ *
+ * An expression with an array suffix (`buf@len`, `data,[16]`; see
+ * [splitArraySuffix]) views a raw pointer as an array, for FFI buffers
+ * that DWARF only knows as `*const u8`. KDAP answers its `evaluate` with
+ * the first elements and a `variablesReference` of its own, from
+ * [FIRST_ARRAY_REFERENCE] up ([ArrayView]), whose `variables` KDAP reads
+ * itself, `start`/`count` windows and chunk nodes included. Each element
+ * has the `evaluateName` `base[i]`, so `setExpression` writes it, and an
+ * element with children is expanded through lldb-dap.
+ *
+ * `setVariable` and `setExpression` write values with the same
+ * simple-expression runtime, which encodes them from the target's type
+ * layout (see `Expressions.kt`). KDAP advertises `supportsSetExpression`,
+ * which lldb-dap lacks.
+ *
+ * ## CodeLLDB method mapping
+ *
+ * | CodeLLDB (variables.rs) | KDAP (this file)                |
+ * |-------------------------|---------------------------------|
+ * | `handle_variables`      | [DebugSession.handleVariables]  |
+ * | `get_mem_ref_for_var`   | [memoryReferenceFor]            |
+ * | `handle_evaluate`       | [DebugSession.handleEvaluate]   |
+ * | `handle_set_variable`   | [DebugSession.handleSetVariable] |
+ * | —                       | [DebugSession.handleSetExpression] |
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.Variables")
+
+/** Leading address in a pointer's value text, e.g. `0x00007ffe…` or `0x… "text"`. */
+private val POINTER_VALUE = Regex("""^0x([0-9a-fA-F]+)\b""")
+
+/** Rust types whose data lives behind a pointer field. */
+private val RUST_DATA_POINTER_TYPES = listOf(
+    Regex("""^alloc::boxed::Box<"""),
+    Regex("""^alloc::rc::Rc<"""),
+    Regex("""^alloc::sync::Arc<"""),
+    Regex("""^alloc::vec::Vec<"""),
+    Regex("""^alloc::string::String$"""),
+    Regex("""^&(mut )?\["""),
+    Regex("""^&(mut )?str$"""),
+)
+
+/**
+ * C++ standard library types whose data lives behind a pointer member,
+ * in libstdc++ (`std::`, `std::__cxx11::`) and libc++ (`std::__1::`)
+ * spelling. A libc++ string held inline (the short string optimization)
+ * has no data pointer.
+ */
+private val CPP_DATA_POINTER_TYPES = listOf(
+    Regex("""^std::(__1::)?vector<"""),
+    Regex("""^std::(__1::)?(unique_ptr|shared_ptr|weak_ptr)<"""),
+    Regex("""^std::(__cxx11::|__1::)?(basic_string<|(w|u8|u16|u32)?string$)"""),
+)
+
+/**
+ * Field names that hold the data pointer, tried before the first child:
+ * Rust's, then libstdc++'s (`_M_impl._M_start`, `_M_dataplus._M_p`,
+ * `_M_ptr`, `_M_t._M_t._M_head_impl`), then libc++'s (`__begin_`,
+ * `__ptr_`, `__value_`). Members of base classes are found by name.
+ */
+private val DATA_POINTER_FIELDS = listOf(
+    "data_ptr", "pointer", "ptr",
+    "_M_impl", "_M_start", "_M_dataplus", "_M_p", "_M_ptr", "_M_t", "_M_head_impl",
+    "__begin_", "__ptr_", "__value_",
+)
+
+/** Rust types whose children are all indexed (`[0]`, `[1]`, …). */
+private val RUST_SEQUENCE_TYPES = listOf(
+    Regex("""^alloc::vec::Vec<"""),
+    Regex("""^&(mut )?\["""),
+)
+
+/** Element count at the start of a sequence summary, e.g. `(3) vec![…]`. */
+private val SEQUENCE_LENGTH = Regex("""^\((\d+)\) """)
+
+/** C++ containers whose children are all indexed, under LLDB's libstdc++ and libc++ formatters. */
+private val CPP_SEQUENCE_TYPES = listOf(
+    Regex("""^std::(__cxx11::|__1::)?(vector|deque|list|forward_list)<"""),
+    Regex("""^std::(__1::)?(multi)?(set|map)<"""),
+    Regex("""^std::(__1::)?unordered_(multi)?(set|map)<"""),
+)
+
+/** Element count in LLDB's C++ container summaries, e.g. `size=3`. */
+private val CPP_SEQUENCE_LENGTH = Regex("""^size=(\d+)\b""")
+
+/** Smallest chunk of a large container, and the factor between chunk levels (as in VS Code). */
+internal const val VARIABLE_CHUNK_SIZE = 100
+
+/** First `variablesReference` of KDAP chunk nodes, above the range lldb-dap hands out. */
+internal const val FIRST_CHUNK_REFERENCE = 1 shl 30
+
+/** How many wrapper structs [dataAddress] descends through. */
+private const val MAX_WRAPPER_DEPTH = 8
+
+/** Whether [type] names a pointer or reference whose value text is an address. */
+internal fun isPointerType(type: String): Boolean =
+    type.endsWith("*") || type.startsWith("*const ") || type.startsWith("*mut ") ||
+        (type.startsWith("&") && !RUST_DATA_POINTER_TYPES.any { it.containsMatchIn(type) })
+
+/** Whether [type] is a Rust type whose data pointer is a nested field. */
+internal fun isRustDataPointerType(type: String): Boolean =
+    RUST_DATA_POINTER_TYPES.any { it.containsMatchIn(type) }
+
+/** Whether [type] is a C++ standard library type whose data pointer is a nested member. */
+internal fun isCppDataPointerType(type: String): Boolean =
+    CPP_DATA_POINTER_TYPES.any { it.containsMatchIn(type) }
+
+/** Parses the address at the start of a pointer's value text. */
+internal fun pointerValueAddress(value: String): Long? =
+    POINTER_VALUE.find(value)?.groupValues?.get(1)?.toULongOrNull(16)?.toLong()?.takeIf { it != 0L }
+
+/**
+ * Follows [value] through wrapper structs (`Unique`, `NonNull`, `RawVec`,
+ * `_Vector_impl`, `__compressed_pair`, …) to the first raw pointer and
+ * returns its target address. The walk starts from the raw layout, since
+ * synthetic children (`[0]`, `[1]`, …) hide the members.
+ */
+internal suspend fun dataAddress(value: SBValue): Long? {
+    var current = value.nonSyntheticValue()
+    repeat(MAX_WRAPPER_DEPTH) {
+        if (!current.isValid()) return null
+        if (current.isPointerType()) return current.valueAsUnsigned().takeIf { it != 0L }
+        current = DATA_POINTER_FIELDS.firstNotNullOfOrNull { name ->
+            current.childMemberWithName(name).takeIf { it.isValid() }
+        } ?: if (current.numChildren() > 0) current.childAtIndex(0) else return null
+    }
+    return null
+}
+
+/**
+ * The number of indexed children of a variable with DAP fields
+ * [fields]: lldb-dap's `indexedVariables`, or the length in a Rust
+ * sequence's or C++ container's summary. `null` if it has none or the
+ * count is unknown.
+ */
+internal fun indexedChildCount(fields: Map<String, Any?>): Int? {
+    (fields["indexedVariables"] as? Number)?.let { return it.toInt() }
+    if (((fields["variablesReference"] as? Number)?.toInt() ?: 0) <= 0) return null
+    val type = fields["type"] as? String ?: return null
+    val value = fields["value"] as? String ?: return null
+    val length = when {
+        RUST_SEQUENCE_TYPES.any { it.containsMatchIn(type) } -> SEQUENCE_LENGTH
+        CPP_SEQUENCE_TYPES.any { it.containsMatchIn(type) } -> CPP_SEQUENCE_LENGTH
+        else -> return null
+    }
+    return length.find(value)?.groupValues?.get(1)?.toIntOrNull()
+}
+
+/** A group of a large container's elements: [count] children of lldb-dap's [reference] from [start]. */
+data class VariableChunk(val reference: Int, val start: Int, val count: Int)
+
+/**
+ * Splits [count] elements from [start] into chunks, as `(start, count)`
+ * pairs. Chunks hold [VARIABLE_CHUNK_SIZE] elements, multiplied by
+ * [VARIABLE_CHUNK_SIZE] until there are at most that many chunks.
+ */
+internal fun chunkRanges(start: Int, count: Int): List<Pair<Int, Int>> {
+    var size = VARIABLE_CHUNK_SIZE
+    while (count.toLong() > size.toLong() * VARIABLE_CHUNK_SIZE) size *= VARIABLE_CHUNK_SIZE
+    return (0 until count step size).map { offset -> (start + offset) to minOf(size, count - offset) }
+}
+
+/** Forgets container sizes, ancestries, chunks, and array views; lldb-dap's references are only valid while stopped. */
+fun DebugSession.forgetVariableChunks() {
+    indexedVariableCounts.clear()
+    valueAncestries.clear()
+    variableChunks.reset()
+    arrayViews.reset()
+}
+
+/**
+ * How many indexed children [reference] (lldb-dap's, or a chunk's) has
+ * from [start] on, or `null` if its size is not known.
+ */
+internal fun DebugSession.indexedVariablesFrom(reference: Int, start: Int): Int? {
+    val total = variableChunks[reference]?.count ?: indexedVariableCounts[reference] ?: return null
+    return total - start
+}
+
+// ── handle_variables (variables.rs) ──────────────────────────────
+
+/**
+ * Mirrors CodeLLDB's `handle_variables`.
+ *
+ * Forwards the request to lldb-dap (for a chunk node, as a window of its
+ * container) and adds a `memoryReference` to each variable that points
+ * at memory and lacks one, and `indexedVariables` to Rust sequences. A
+ * request for all children of a large container is answered with chunk
+ * nodes instead (see the file header). The top frame's Locals start with
+ * the return value of the step that stopped there, if any (see
+ * `ReturnValues.kt`). Variables of a frame's scopes that changed since
+ * the previous stop are marked (see `ValueHistory.kt`), and values
+ * already shown above them are not expanded again (see
+ * `RecursiveValues.kt`). A watch's reference stands for lldb-dap's
+ * reference at this stop (see `Watches.kt`). Array views and the
+ * Statics and Registers scopes are read by KDAP ([sendArrayElements],
+ * [sendStatics], [sendRegisters]).
+ */
+suspend fun DebugSession.handleVariables(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+
+    try {
+        val args = JSONObject((obj.optJSONObject("arguments") ?: JSONObject()).toString())
+        val valueFrame = valueFrames[args.optInt("variablesReference", 0)]
+        val reference = backendVariablesReference(args.optInt("variablesReference", 0), ctx)
+        args.put("variablesReference", reference)
+        val start = args.optInt("start", 0)
+        val count = args.optInt("count", 0)
+        val chunk = variableChunks[reference]
+        val indexed = indexedVariablesFrom(reference, start)
+        if (count == 0 && !args.has("filter") && indexed != null && indexed > VARIABLE_CHUNK_SIZE) {
+            sendChunks(requestSeq, chunk?.reference ?: reference, (chunk?.start ?: 0) + start, indexed, valueFrame, ctx)
+            return
+        }
+        if (chunk != null) {
+            args.put("variablesReference", chunk.reference)
+            args.put("start", chunk.start + start)
+            args.put("count", if (count > 0) minOf(count, chunk.count - start) else chunk.count - start)
+            args.put("filter", "indexed")
+        }
+        arrayViews[args.getInt("variablesReference")]?.let { view ->
+            sendArrayElements(requestSeq, view, args.optInt("start", 0), args.optInt("count", 0), ctx)
+            return
+        }
+        registerNodes[args.getInt("variablesReference")]?.let { node ->
+            sendRegisters(requestSeq, node, ctx)
+            return
+        }
+        if (isStaticsReference(args.getInt("variablesReference"))) {
+            sendStatics(requestSeq, args.getInt("variablesReference"), args.optInt("start", 0), args.optInt("count", 0), ctx)
+            return
+        }
+        // A split Locals scope is read whole and paged after the split (see LocalScopes.kt).
+        val localScope = localScopes[reference]
+        val localsReference = localScope?.localsReference ?: reference
+        val locals = localsPartitions[localsReference]
+        if (locals != null) {
+            args.put("variablesReference", localsReference)
+            args.remove("start")
+            args.remove("count")
+        }
+
+        val backendRequest = JSONObject().apply {
+            put("type", "request")
+            put("command", "variables")
+            put("arguments", args)
+        }
+        val response = ctx.sendRequestToBackendAndAwait(backendRequest.toString())
+        val backendVariables = response.body["variables"] as? List<*>
+        if (!response.success || backendVariables == null) {
+            ctx.sendEventToClient(response.copy(seq = 0, requestSeq = requestSeq).toJson())
+            return
+        }
+        val variables = if (locals != null) {
+            localScopeVariables(backendVariables, locals, localScope?.kind ?: LocalScopeKind.Locals, ctx)
+                .drop(start).let { if (count > 0) it.take(count) else it }
+        } else {
+            backendVariables
+        }
+
+        // lldb-dap selects the frame whose scopes the client expanded, so
+        // evaluateName paths resolve against the selected frame.
+        var frame: SBFrame? = null
+        val patched = variables.map { variable ->
+            val fields = (variable as? Map<*, *>)?.entries?.associate { (k, v) -> k.toString() to v }
+                ?: return@map variable
+            var result = fields
+            indexedChildCount(fields)?.let { n ->
+                (fields["variablesReference"] as? Number)?.toInt()?.takeIf { it > 0 }
+                    ?.let { indexedVariableCounts[it] = n }
+                if (fields["indexedVariables"] == null) result = result + ("indexedVariables" to n)
+            }
+            if (fields["memoryReference"] != null) return@map result
+            val address = memoryReferenceFor(fields) { path ->
+                val f = frame ?: selectedFrame(ctx).also { frame = it }
+                f.valueForVariablePath(path)
+            }
+            if (address == null) result else result + ("memoryReference" to "0x%x".format(address))
+        }
+        val threadIndexId = returnValueScopes[reference]?.takeIf { chunk == null && start == 0 }
+        val returnValue = threadIndexId?.let { returnValueVariable(it, ctx) }
+
+        val guarded = guardExpansion(patched, chunk?.reference ?: reference)
+        val summarized = applyScriptSummaries(guarded).let { applied ->
+            if (valueFrame == null) applied else trackValues(applied, valueFrame, valueDigests(applied, valueFrame, ctx))
+        }
+        val body = response.body + ("variables" to (returnValue?.let { withReturnValue(summarized, it) } ?: summarized))
+        ctx.sendEventToClient(response.copy(seq = 0, requestSeq = requestSeq, body = body).toJson())
+
+    } catch (e: Exception) {
+        log.warning { "Variables: variables failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "variables", e.message ?: "variables failed")
+    }
+}
+
+/**
+ * Responds to a `variables` request with chunk nodes covering [count]
+ * children of lldb-dap's [reference] from [start]. The nodes belong to
+ * [valueFrame], if any (see ValueHistory.kt).
+ */
+private suspend fun DebugSession.sendChunks(
+    requestSeq: Int,
+    reference: Int,
+    start: Int,
+    count: Int,
+    valueFrame: ValueFrame?,
+    ctx: AsyncRequestContext,
+) {
+    val nodes = chunkRanges(start, count).map { (chunkStart, chunkCount) ->
+        val chunkReference = variableChunks.handleFor(VariableChunk(reference, chunkStart, chunkCount))
+        valueFrame?.let { valueFrames[chunkReference] = it }
+        mapOf(
+            "name" to "[$chunkStart..${chunkStart + chunkCount - 1}]",
+            "value" to "",
+            "variablesReference" to chunkReference,
+            "indexedVariables" to chunkCount,
+            "presentationHint" to mapOf("kind" to "virtual"),
+        )
+    }
+    sendSuccessResponse(ctx, requestSeq, "variables", mapOf("variables" to nodes))
+}
+
+// ── get_mem_ref_for_var (variables.rs) ───────────────────────────
+
+/**
+ * Returns the address a variable (given as its DAP fields) points at, or
+ * `null` if it isn't pointer-like. [resolve] looks a variable up by its
+ * `evaluateName`.
+ */
+internal suspend fun memoryReferenceFor(
+    fields: Map<String, Any?>,
+    resolve: suspend (String) -> SBValue,
+): Long? {
+    val type = fields["type"] as? String ?: return null
+    val value = fields["value"] as? String
+    if (value != null && isPointerType(type)) return pointerValueAddress(value)
+    val path = fields["evaluateName"] as? String ?: return null
+    if (!isRustDataPointerType(type) && !isCppDataPointerType(type)) return null
+    return logErrorsOrNull { dataAddress(resolve(path)) }
+}
+
+// ── handle_evaluate (variables.rs) ───────────────────────────────
+
+/**
+ * lldb-dap frame IDs carry the thread's index ID above the low
+ * [FRAME_INDEX_BITS] bits, which hold the frame index.
+ */
+internal const val FRAME_INDEX_BITS = 19
+
+/**
+ * Mirrors CodeLLDB's `handle_evaluate` outside the debug console.
+ *
+ * Handles what lldb-dap cannot evaluate as sent (see
+ * [evaluateResponse]): simple expressions, which run compiled in the
+ * requested frame, and expressions with a format suffix.
+ */
+suspend fun DebugSession.handleEvaluate(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val args = obj.optJSONObject("arguments") ?: JSONObject()
+
+    try {
+        ctx.sendEventToClient(evaluateResponse(args, ctx).copy(requestSeq = requestSeq).toJson())
+    } catch (e: Exception) {
+        log.warning { "Variables: evaluate failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "evaluate", e.message ?: "evaluate failed")
+    }
+}
+
+/**
+ * Handles debug console (`repl`) input that KDAP owns (see
+ * [classifyReplInput]): backtick meta-commands, whose output is streamed
+ * as `output` events, `?` expressions, and assignments.
+ */
+suspend fun DebugSession.handleReplEvaluate(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val args = obj.optJSONObject("arguments") ?: JSONObject()
+
+    try {
+        val response = when (val input = classifyReplInput(args.optString("expression"))) {
+            is ReplInput.MetaCommand -> {
+                runMetaCommand(requestSeq, input.command, ctx)
+                return
+            }
+            is ReplInput.Simple -> simpleEvaluateResponse(args, input.expr, input.format, ctx)
+            // `watch` makes lldb-dap evaluate an expression even when the
+            // text starts with a command name.
+            is ReplInput.NativeExpression -> nativeEvaluateResponse(args.put("context", "watch"), input.text, input.format, ctx)
+            is ReplInput.Invalid -> DapResponse.error(0, "evaluate", input.message)
+            ReplInput.Passthrough -> {
+                ctx.forwardToBackend(rawJson)
+                return
+            }
+        }
+        ctx.sendEventToClient(response.copy(requestSeq = requestSeq).toJson())
+    } catch (e: Exception) {
+        log.warning { "Variables: repl evaluate failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "evaluate", e.message ?: "evaluate failed")
+    }
+}
+
+/**
+ * Runs an LLDB command for the debug console, streaming its output.
+ * [MEMORY_MAP_COMMAND] prints the memory map instead, and a `kdap.init`
+ * command runs the script's (see Scripting.kt).
+ */
+private suspend fun DebugSession.runMetaCommand(requestSeq: Int, command: String, ctx: AsyncRequestContext) {
+    val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+    val result = if (runScriptCommand(command, ctx)) {
+        SBCommandReturnObject(succeeded = true, output = "", error = "")
+    } else if (command == MEMORY_MAP_COMMAND) {
+        try {
+            SBCommandReturnObject(succeeded = true, output = formatMemoryMap(memoryMap(debugger)), error = "")
+        } catch (e: Exception) {
+            SBCommandReturnObject(succeeded = false, output = "", error = "${e.message}\n")
+        }
+    } else {
+        debugger.commandInterpreter().handleCommandWithResult(command)
+    }
+    if (result.output.isNotEmpty()) {
+        ctx.sendEventToClient(OutputEvent.console(result.output).toJson())
+    }
+    if (result.error.isNotEmpty()) {
+        ctx.sendEventToClient(OutputEvent(seq = 0, category = "stderr", output = result.error).toJson())
+    }
+    val response = DapResponse(
+        seq = 0,
+        requestSeq = requestSeq,
+        command = "evaluate",
+        success = true,
+        body = mapOf("result" to "", "variablesReference" to 0),
+    )
+    ctx.sendEventToClient(response.toJson())
+}
+
+/**
+ * Evaluates the expression in [args] outside the debug console and
+ * returns the `evaluate` response, with `request_seq` 0. A format suffix
+ * is split off first (see [splitFormatSuffix]), then an array suffix
+ * ([arrayEvaluateResponse]). Native expressions and
+ * bare variable names go to lldb-dap unless their format is one it
+ * cannot show; simple expressions run in KDAP.
+ */
+internal suspend fun DebugSession.evaluateResponse(args: JSONObject, ctx: AsyncRequestContext): DapResponse {
+    val (withoutFormat, format) = splitFormatSuffix(args.optString("expression"))
+    val (text, length) = splitArraySuffix(withoutFormat)
+    val prepared = try {
+        prepareExpression(text)
+    } catch (e: ExpressionError) {
+        return DapResponse.error(0, "evaluate", e.message ?: "Invalid expression")
+    }
+    if (length != null) return arrayEvaluateResponse(args, text, prepared, length, format, ctx)
+    val native = lldbDapExpression(text, prepared)
+    return when {
+        native != null && format?.lldbDapCanShow != false -> backendEvaluateResponse(args, native, format, ctx)
+        prepared is PreparedExpression.Simple -> simpleEvaluateResponse(args, prepared.expr, format, ctx)
+        else -> nativeEvaluateResponse(args, native ?: text, format, ctx)
+    }
+}
+
+/**
+ * Evaluates the native expression [text] in the frame named by [args]:
+ * by lldb-dap, or by KDAP when [format] is one lldb-dap cannot show.
+ */
+private suspend fun DebugSession.nativeEvaluateResponse(
+    args: JSONObject,
+    text: String,
+    format: ValueFormat?,
+    ctx: AsyncRequestContext,
+): DapResponse =
+    if (format?.lldbDapCanShow != false) {
+        backendEvaluateResponse(args, text, format, ctx)
+    } else {
+        resultResponse(args, runPython("lambda frame: _kdap_se_native(frame, ${pyStr(text)})", args, format, ctx), format, ctx)
+    }
+
+/**
+ * Evaluates [expr] in the frame named by [args] and returns the
+ * `evaluate` response, with `request_seq` 0. Places with children are
+ * re-evaluated by lldb-dap through their expression path, so the
+ * response carries a `variablesReference`; other results are returned as
+ * plain values, shown in [format] or as `format.hex` asks.
+ */
+internal suspend fun DebugSession.simpleEvaluateResponse(
+    args: JSONObject,
+    expr: SimpleExpr,
+    format: ValueFormat?,
+    ctx: AsyncRequestContext,
+): DapResponse = resultResponse(args, runPython("lambda frame: ${expr.toPython("frame")}", args, format, ctx), format, ctx)
+
+/** Builds the `evaluate` response for a `_kdap_se_run` [result] (see [simpleEvaluateResponse]). */
+private suspend fun DebugSession.resultResponse(
+    args: JSONObject,
+    result: JSONObject,
+    format: ValueFormat?,
+    ctx: AsyncRequestContext,
+): DapResponse {
+    if (result.has("error")) return DapResponse.error(0, "evaluate", result.getString("error"))
+
+    // A byte buffer read as text is shown as KDAP rendered it; lldb-dap would list its bytes.
+    val path = if (result.isNull("path")) null else result.optString("path").ifEmpty { null }
+    if (path != null && format != ValueFormat.Text) {
+        val response = backendEvaluateResponse(args, path, format, ctx)
+        if (response.success) return response
+    }
+
+    return DapResponse(
+        seq = 0,
+        requestSeq = 0,
+        command = "evaluate",
+        success = true,
+        body = mapOf(
+            "result" to result.optString("result"),
+            "type" to result.optString("type"),
+            "variablesReference" to 0,
+        ),
+    )
+}
+
+/**
+ * Has lldb-dap evaluate [expression] with the other arguments in
+ * [args]. A hexadecimal or decimal [format] sets `format.hex`.
+ */
+private suspend fun backendEvaluateResponse(
+    args: JSONObject,
+    expression: String,
+    format: ValueFormat?,
+    ctx: AsyncRequestContext,
+): DapResponse {
+    val backendArgs = JSONObject(args.toString()).put("expression", expression)
+    if (format != null && format.lldbDapCanShow) {
+        backendArgs.put("format", JSONObject().put("hex", format == ValueFormat.Hex))
+    }
+    val backendRequest = JSONObject().apply {
+        put("type", "request")
+        put("command", "evaluate")
+        put("arguments", backendArgs)
+    }
+    return ctx.sendRequestToBackendAndAwait(backendRequest.toString()).copy(seq = 0, requestSeq = 0)
+}
+
+/**
+ * Runs the Python function [fn] on the frame named by [args] (or the
+ * selected frame) and returns the `_kdap_se_run` JSON result, shown in
+ * [format], else hexadecimal if the request's `format.hex` is set. It
+ * may call functions if the request's context is one of
+ * [FUNCTION_CALL_CONTEXTS] (see FunctionCalls.kt).
+ */
+private suspend fun DebugSession.runPython(
+    fn: String,
+    args: JSONObject,
+    format: ValueFormat?,
+    ctx: AsyncRequestContext,
+): JSONObject {
+    val frameId = if (args.has("frameId")) args.optInt("frameId") else null
+    val hex = args.optJSONObject("format")?.optBoolean("hex") == true
+    val lldbFormat = (format ?: ValueFormat.Hex.takeIf { hex })?.lldbFormat ?: "None"
+    val calls = if (args.optString("context") in FUNCTION_CALL_CONTEXTS) evaluationTimeout.toString() else "None"
+    val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+    val output = stateLock.withLock {
+        debugger.commandInterpreter().handleCommand(
+            "script print(_kdap_se_run($fn, ${framePython(frameId)}, $lldbFormat, $calls))")
+    }
+    return JSONObject(output.trim())
+}
+
+// ── Array views (KDAP) ───────────────────────────────────────────
+
+/** First `variablesReference` of array views, between the watch and chunk references. */
+internal const val FIRST_ARRAY_REFERENCE = 3 shl 28
+
+/** Elements shown in the `evaluate` result of an array view. */
+private const val ARRAY_PREVIEW_ELEMENTS = 8
+
+/**
+ * A pointer viewed as an array (see the file header): Python functions
+ * of the frame with lldb-dap ID [frameId] returning the [base] value and
+ * the array [length]. Element `i` has the `evaluateName`
+ * `"$elementBase[i]"` when [elementBase] is set.
+ */
+internal data class ArrayView(
+    val frameId: Int?,
+    val base: String,
+    val length: String,
+    val elementBase: String?,
+    val format: ValueFormat?,
+)
+
+/**
+ * Views [text] ([prepared]) as an array of [length] elements and returns
+ * the `evaluate` response: the first elements as the result, and an
+ * [ArrayView] reference for the rest.
+ */
+private suspend fun DebugSession.arrayEvaluateResponse(
+    args: JSONObject,
+    text: String,
+    prepared: PreparedExpression,
+    length: SimpleExpr,
+    format: ValueFormat?,
+    ctx: AsyncRequestContext,
+): DapResponse {
+    val base = when (prepared) {
+        is PreparedExpression.Simple -> "lambda frame: ${prepared.expr.toPython("frame")}"
+        is PreparedExpression.Native -> "lambda frame: _kdap_se_native(frame, ${pyStr(prepared.text)})"
+    }
+    val elementBase = when {
+        prepared !is PreparedExpression.Simple -> null
+        prepared.expr is SimpleExpr.Variable || prepared.expr is SimpleExpr.Field ||
+            prepared.expr is SimpleExpr.Index -> text.trim()
+        text.trim().startsWith("/") -> null
+        else -> "(${text.trim()})"
+    }
+    val frameId = if (args.has("frameId")) args.optInt("frameId") else null
+    val view = ArrayView(frameId, base, "lambda frame: ${length.toPython("frame")}", elementBase, format)
+    val result = runArray(view, 0, ARRAY_PREVIEW_ELEMENTS, ctx)
+    if (result.has("error")) return DapResponse.error(0, "evaluate", result.getString("error"))
+
+    val count = result.getInt("length")
+    val elements = result.getJSONArray("elements")
+    val preview = (0 until elements.length()).map { elements.getJSONObject(it).optString("result") } +
+        listOfNotNull("...".takeIf { count > elements.length() })
+    val body = buildMap {
+        put("result", "($count) [${preview.joinToString(", ")}]")
+        put("type", result.getString("type"))
+        put("memoryReference", "0x%x".format(result.getLong("address")))
+        val reference = if (count > 0) arrayViews.handleFor(view) else 0
+        if (reference > 0) {
+            indexedVariableCounts[reference] = count
+            put("variablesReference", reference)
+            put("indexedVariables", count)
+        } else {
+            put("variablesReference", 0)
+        }
+    }
+    return DapResponse(seq = 0, requestSeq = 0, command = "evaluate", success = true, body = body)
+}
+
+/**
+ * Responds to a `variables` request for [view] with [count] elements
+ * from [start] (all of the rest if [count] is 0). Elements with children
+ * are evaluated by lldb-dap through their expression path.
+ */
+private suspend fun DebugSession.sendArrayElements(
+    requestSeq: Int,
+    view: ArrayView,
+    start: Int,
+    count: Int,
+    ctx: AsyncRequestContext,
+) {
+    val result = runArray(view, start, if (count > 0) count else Int.MAX_VALUE, ctx)
+    if (result.has("error")) {
+        sendErrorResponse(ctx, requestSeq, "variables", result.getString("error"))
+        return
+    }
+    val indexable = result.optBoolean("indexable")
+    val elements = result.getJSONArray("elements")
+    val frameArgs = JSONObject().put("context", "watch")
+    view.frameId?.let { frameArgs.put("frameId", it) }
+    val variables = (0 until elements.length()).map { i ->
+        val element = elements.getJSONObject(i)
+        val path = if (element.isNull("path")) null else element.optString("path").ifEmpty { null }
+        val backend = path?.let { backendEvaluateResponse(frameArgs, it, view.format, ctx) }?.takeIf { it.success }
+        buildMap {
+            put("name", "[${start + i}]")
+            put("value", element.optString("result"))
+            put("type", element.optString("type"))
+            if (indexable && view.elementBase != null) put("evaluateName", "${view.elementBase}[${start + i}]")
+            put("variablesReference", backend?.body?.get("variablesReference") ?: 0)
+            backend?.body?.get("memoryReference")?.let { put("memoryReference", it) }
+        }
+    }
+    sendSuccessResponse(ctx, requestSeq, "variables", mapOf("variables" to variables))
+}
+
+/** Runs `_kdap_se_array_run` for [view]: [count] elements from [start]. */
+private suspend fun DebugSession.runArray(view: ArrayView, start: Int, count: Int, ctx: AsyncRequestContext): JSONObject {
+    val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+    val output = debugger.commandInterpreter().handleCommand(
+        "script print(_kdap_se_array_run(${view.base}, ${view.length}, ${framePython(view.frameId)}, " +
+            "$start, $count, ${view.format?.lldbFormat ?: "None"}))",
+    )
+    return JSONObject(output.trim())
+}
+
+// ── handle_set_variable (variables.rs) ───────────────────────────
+
+/**
+ * Mirrors CodeLLDB's `handle_set_variable`.
+ *
+ * Looks the variable up in lldb-dap's `variables` response and writes
+ * the new value through its `evaluateName` with a simple-expression
+ * assignment. The response is the variable as lldb-dap shows it after
+ * the write, so an enum whose variant changed gets fresh children.
+ * Variables without an `evaluateName`, and values that are native
+ * expressions, are left to lldb-dap. Registers are written by
+ * [setRegister].
+ */
+suspend fun DebugSession.handleSetVariable(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val args = obj.optJSONObject("arguments") ?: JSONObject()
+
+    try {
+        val reference = args.optInt("variablesReference")
+        val name = args.optString("name")
+        registerNodes[reference]?.let { node ->
+            setRegister(requestSeq, node, name, args.optString("value"), ctx)
+            return
+        }
+        val path = findVariable(reference, name, ctx)?.get("evaluateName") as? String
+        val assignment = path?.let {
+            try {
+                prepareAssignment(it, args.optString("value")) as? PreparedExpression.Simple
+            } catch (e: ExpressionError) {
+                null
+            }
+        }
+        if (assignment == null) {
+            ctx.forwardToBackend(rawJson)
+            return
+        }
+
+        // evaluateName paths resolve against the selected frame (see handleVariables).
+        val result = runSimple(assignment.expr, null, ctx)
+        if (result.has("error")) {
+            sendErrorResponse(ctx, requestSeq, "setVariable", result.getString("error"))
+            return
+        }
+
+        val updated = findVariable(reference, name, ctx).orEmpty()
+        val body = buildMap {
+            put("value", updated["value"] ?: result.optString("result"))
+            put("type", updated["type"] ?: result.optString("type"))
+            put("variablesReference", updated["variablesReference"] ?: 0)
+            updated["memoryReference"]?.let { put("memoryReference", it) }
+        }
+        sendSuccessResponse(ctx, requestSeq, "setVariable", body)
+
+    } catch (e: Exception) {
+        log.warning { "Variables: setVariable failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "setVariable", e.message ?: "setVariable failed")
+    }
+}
+
+/** The DAP fields of the child [name] of lldb-dap's [reference], if present. */
+private suspend fun findVariable(reference: Int, name: String, ctx: AsyncRequestContext): Map<String, Any?>? {
+    val backendRequest = JSONObject().apply {
+        put("type", "request")
+        put("command", "variables")
+        put("arguments", JSONObject().put("variablesReference", reference))
+    }
+    val response = ctx.sendRequestToBackendAndAwait(backendRequest.toString())
+    val variables = (response.body["variables"] as? List<*>).takeIf { response.success } ?: return null
+    return variables.filterIsInstance<Map<*, *>>()
+        .firstOrNull { it["name"] == name }
+        ?.entries?.associate { (k, v) -> k.toString() to v }
+}
+
+// ── handle_set_expression (variables.rs) ─────────────────────────
+
+/** Advertises `supportsSetExpression` in a successful `initialize` response. */
+fun addSetExpressionCapability(response: DapResponse): DapResponse =
+    response.copy(body = response.body + ("supportsSetExpression" to true))
+
+/**
+ * Handles `setExpression`, which lldb-dap doesn't implement.
+ *
+ * Simple assignments (see [prepareAssignment]) are written by KDAP in
+ * the requested frame; anything else is evaluated by lldb-dap as the
+ * native expression `expression = value`. Places with children are
+ * re-evaluated through lldb-dap so the response carries a
+ * `variablesReference`.
+ */
+suspend fun DebugSession.handleSetExpression(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val args = obj.optJSONObject("arguments") ?: JSONObject()
+
+    try {
+        val frameId = if (args.has("frameId")) args.optInt("frameId") else null
+        val expression = when (val prepared = prepareAssignment(args.optString("expression"), args.optString("value"))) {
+            is PreparedExpression.Native -> prepared.text
+            is PreparedExpression.Simple -> {
+                val result = runSimple(prepared.expr, frameId, ctx)
+                if (result.has("error")) {
+                    sendErrorResponse(ctx, requestSeq, "setExpression", result.getString("error"))
+                    return
+                }
+                val path = if (result.isNull("path")) null else result.optString("path").ifEmpty { null }
+                if (path == null) {
+                    val body = mapOf(
+                        "value" to result.optString("result"),
+                        "type" to result.optString("type"),
+                        "variablesReference" to 0,
+                    )
+                    sendSuccessResponse(ctx, requestSeq, "setExpression", body)
+                    return
+                }
+                path
+            }
+        }
+
+        val backendArgs = JSONObject().put("expression", expression).put("context", "watch")
+        frameId?.let { backendArgs.put("frameId", it) }
+        val backendRequest = JSONObject().apply {
+            put("type", "request")
+            put("command", "evaluate")
+            put("arguments", backendArgs)
+        }
+        val response = ctx.sendRequestToBackendAndAwait(backendRequest.toString())
+        if (!response.success) {
+            sendErrorResponse(ctx, requestSeq, "setExpression", response.message ?: "setExpression failed")
+            return
+        }
+        val body = buildMap {
+            put("value", response.body["result"] ?: "")
+            response.body["type"]?.let { put("type", it) }
+            put("variablesReference", response.body["variablesReference"] ?: 0)
+            response.body["memoryReference"]?.let { put("memoryReference", it) }
+        }
+        sendSuccessResponse(ctx, requestSeq, "setExpression", body)
+
+    } catch (e: Exception) {
+        log.warning { "Variables: setExpression failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "setExpression", e.message ?: "setExpression failed")
+    }
+}
+
+/** Python expression for the `SBFrame` with lldb-dap frame ID [frameId], or the selected frame. */
+internal fun framePython(frameId: Int?): String {
+    val process = "lldb.debugger.GetSelectedTarget().GetProcess()"
+    if (frameId == null) return "$process.GetSelectedThread().GetSelectedFrame()"
+    val threadIndexId = frameId ushr FRAME_INDEX_BITS
+    val frameIndex = frameId and ((1 shl FRAME_INDEX_BITS) - 1)
+    return "$process.GetThreadByIndexID($threadIndexId).GetFrameAtIndex($frameIndex)"
+}
+
+private suspend fun DebugSession.selectedFrame(ctx: AsyncRequestContext): SBFrame {
+    val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+    return debugger.selectedTarget().process().selectedThread().selectedFrame()
+}
+
+/** Runs [block], returning `null` (and logging) if it throws. */
+private suspend fun <T> logErrorsOrNull(block: suspend () -> T?): T? =
+    try {
+        block()
+    } catch (e: Exception) {
+        log.fine { "Variables: ${e.message}" }
+        null
+    }
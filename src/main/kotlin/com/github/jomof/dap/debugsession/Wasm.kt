@@ -0,0 +1,130 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.LaunchRequestArguments
+import com.github.jomof.dap.sb.SBError
+import java.io.File
+import java.io.IOException
+
+/**
+ * WebAssembly launch: a `launch` whose `program` is a WebAssembly module
+ * (a file starting with `\0asm`) runs it under `wasmtime` (KDAP
+ * extension). KDAP launches
+ *
+ * ```
+ * wasmtime run -D debug-info=y -O opt-level=0 --dir=. [--env NAME]… <module> [args]…
+ * ```
+ *
+ * in place of the module ([wasmLaunchArguments]). With `debug-info`,
+ * wasmtime translates the DWARF sections embedded in the module to the
+ * native code it compiles and registers each module through the GDB JIT
+ * interface, which LLDB's `plugin.jit-loader.gdb` reads (KDAP turns it
+ * on for these launches). Source breakpoints are pending until the
+ * module is compiled and then bind (PendingBreakpoints.kt); stepping and
+ * locals work on the compiled code like on any native function.
+ * Optimizations are off so that locals stay in the places the DWARF
+ * says.
+ *
+ * WASI gives the module only what it is granted: the working directory
+ * is preopened as `.`, and the variables of `env` and `envFile` are
+ * passed through by name. wasmtime's own frames (`wasmtime::…`,
+ * `cranelift_…`) are runtime internals (StackFrames.kt).
+ *
+ * ## Checks
+ *
+ * As for native programs (ProgramChecks.kt), the module must exist and
+ * have debug info: a `.debug_info` custom section, or an
+ * `external_debug_info` section naming a separate file
+ * ([wasmCustomSections]), unless `"requireDebugInfo": false` or
+ * `"noDebug": true`. The wasmtime executable is not checked for debug
+ * info.
+ */
+
+/** The first bytes of every WebAssembly module: `\0asm`. */
+private val WASM_MAGIC = byteArrayOf(0x00, 0x61, 0x73, 0x6d)
+
+/** Custom sections that mean a module has DWARF, embedded or in a separate file. */
+private val WASM_DEBUG_SECTIONS = setOf(".debug_info", "external_debug_info")
+
+/** `wasmtime` options ahead of the module: DWARF translated, optimizations off, the working directory preopened. */
+private val WASMTIME_RUN_OPTIONS = listOf("run", "-D", "debug-info=y", "-O", "opt-level=0", "--dir=.")
+
+/** Whether [path] is a WebAssembly module (starts with [WASM_MAGIC]). */
+internal fun isWasmModule(path: String): Boolean = try {
+    File(path).inputStream().use { it.readNBytes(WASM_MAGIC.size) }.contentEquals(WASM_MAGIC)
+} catch (_: IOException) {
+    false
+}
+
+/**
+ * Names of the custom sections of WebAssembly [module], in order. Each
+ * section is an id byte and a LEB128 size; a custom section (id 0)
+ * starts with its LEB128-prefixed UTF-8 name.
+ */
+internal fun wasmCustomSections(module: ByteArray): List<String> {
+    val names = mutableListOf<String>()
+    var offset = 8 // magic and version
+    fun leb128(): Int {
+        var result = 0L
+        var shift = 0
+        while (true) {
+            if (offset >= module.size) throw SBError("Truncated WebAssembly module.")
+            val byte = module[offset++].toInt() and 0xff
+            result = result or ((byte and 0x7f).toLong() shl shift)
+            if (byte and 0x80 == 0) break
+            shift += 7
+            if (shift > 28) throw SBError("Malformed WebAssembly module.")
+        }
+        return result.toInt()
+    }
+    while (offset < module.size) {
+        val id = module[offset++].toInt()
+        val size = leb128()
+        val end = offset + size
+        if (end > module.size || size < 0) throw SBError("Truncated WebAssembly module.")
+        if (id == 0) {
+            val length = leb128()
+            if (offset + length > end) throw SBError("Malformed WebAssembly module.")
+            names += String(module, offset, length, Charsets.UTF_8)
+        }
+        offset = end
+    }
+    return names
+}
+
+/** The `wasmtime` to run modules under: [configured], on `PATH`, or in `~/.wasmtime/bin`. */
+internal fun wasmtimeExecutable(configured: String?, env: Map<String, String>): String? {
+    if (configured != null) return configured
+    val name = if (isWindowsHost()) "wasmtime.exe" else "wasmtime"
+    val directories = env["PATH"].orEmpty().split(File.pathSeparator).filter { it.isNotEmpty() } +
+        listOfNotNull(System.getProperty("user.home")?.let { "$it/.wasmtime/bin" })
+    return directories.map { File(it, name) }.firstOrNull { it.isFile && it.canExecute() }?.path
+}
+
+/**
+ * [args] with a WebAssembly `program` replaced by `wasmtime` running it
+ * (see the file header), or [args] itself for any other program. The
+ * module is checked here, as wasmtime rather than the module becomes the
+ * target.
+ */
+internal fun wasmLaunchArguments(
+    args: LaunchRequestArguments,
+    env: Map<String, String> = System.getenv(),
+): LaunchRequestArguments {
+    val module = args.program?.takeIf { isWasmModule(it) } ?: return args
+    if (args.noDebug != true && args.requireDebugInfo != false &&
+        wasmCustomSections(File(module).readBytes()).none { it in WASM_DEBUG_SECTIONS }
+    ) {
+        throw ProgramError(ProgramProblem.NoDebugInfo,
+            "WebAssembly module \"$module\" has no debug info — build it with debug info " +
+                "(Cargo: `debug = true` in the profile; clang: `-g`), " +
+                "or set \"requireDebugInfo\": false to debug it without source lines.")
+    }
+    val wasmtime = wasmtimeExecutable(args.wasmRuntime, env)
+        ?: throw SBError("WebAssembly modules run under wasmtime, which was not found on PATH; set \"wasmRuntime\".")
+    val passed = launchEnvironment(args, env).keys.flatMap { listOf("--env", it) }
+    return args.copy(
+        program = wasmtime,
+        args = WASMTIME_RUN_OPTIONS + passed + File(module).absolutePath + args.args.orEmpty(),
+        requireDebugInfo = false,
+    )
+}
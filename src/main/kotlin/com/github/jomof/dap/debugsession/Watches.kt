@@ -0,0 +1,179 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.messages.WatchRefresh
+import com.github.jomof.dap.sb.createDebugger
+import com.github.jomof.dap.sb.watched
+import org.json.JSONObject
+import java.util.logging.Logger
+
+/**
+ * Watch expressions: `evaluate` requests with context `watch`, cached
+ * per stop. KDAP extension; CodeLLDB evaluates every watch request.
+ *
+ * Clients evaluate every watch at each stop and again whenever the user
+ * selects another thread or frame and comes back. KDAP evaluates a watch
+ * (its expression, frame, and `format`) once per stop, the way a plain
+ * `evaluate` would be (see `Expressions.kt`), and answers repeats from
+ * [DebugSession.watchResults]. The results of the current stop are
+ * dropped when a value may have been written while stopped: on
+ * `setVariable`, `setExpression`, `writeMemory`, and debug console input.
+ *
+ * A watch whose value has children gets a `variablesReference` of its
+ * own from [DebugSession.watchReferences], a [HandleTable] from
+ * [FIRST_WATCH_REFERENCE] up that is not reset, so the reference stays
+ * the same across stops; lldb-dap hands out new references at every
+ * stop. The client keeps the watch expanded and can diff its children
+ * between stops. `variables` on such a reference goes to lldb-dap's
+ * reference for the current stop ([backendVariablesReference]),
+ * evaluating the watch again when it has none yet.
+ *
+ * At each stop, the watches the client did not ask for at the stop
+ * before (a removed watch, or one of a frame that is gone) are forgotten
+ * with their references and results ([onWatchStop]), so the table only
+ * holds the watches in use. A stop at which the client asked for no
+ * watches at all forgets none. Past [MAX_HANDLES_PER_STOP] watches at
+ * one stop, new ones show without children.
+ *
+ * ## Refresh
+ *
+ * `_adapterSettings.watchRefresh` chooses when a watch is evaluated
+ * again:
+ *
+ * | Value             | Evaluated again                                  |
+ * |-------------------|--------------------------------------------------|
+ * | `"stop"` (default)| at every stop                                    |
+ * | `"frame"`         | when its frame changed: another thread, function, or CFA in the frame it was evaluated in |
+ *
+ * With `"frame"`, a watch keeps the value of its first stop in a frame
+ * until that frame returns, even if the frame changes the value (a loop
+ * counter); checking the frame is one SB API call instead of one
+ * evaluation per watch. It suits many or slow watches over values that
+ * are fixed for a call, such as arguments.
+ */
+
+private val log = Logger.getLogger("com.github.jomof.dap.debugsession.Watches")
+
+/** First `variablesReference` of watches, below the chunk references ([FIRST_CHUNK_REFERENCE]). */
+internal const val FIRST_WATCH_REFERENCE = 1 shl 29
+
+/** What identifies a watch: its `evaluate` arguments other than `context`. */
+data class WatchKey(val expression: String, val frameId: Int?, val format: String?)
+
+/**
+ * A watch's `evaluate` response, with its own `variablesReference`,
+ * evaluated or last reused at [stop]. [frameKey] identifies its frame
+ * when `watchRefresh` is `"frame"`.
+ */
+data class WatchResult(val response: DapResponse, val stop: Int, val frameKey: String?)
+
+/**
+ * The watch behind a watch `variablesReference`: its `evaluate`
+ * arguments, and lldb-dap's reference for its value at [stop].
+ */
+data class WatchTarget(val arguments: String, val backendReference: Int, val stop: Int)
+
+/**
+ * Counts a stop: the watch results and references of earlier stops are
+ * out of date, and the watches not asked for at the stop that ended are
+ * forgotten (see the file header).
+ */
+fun DebugSession.onWatchStop() {
+    val ended = watchStop.getAndIncrement()
+    val asked = watchRequests.filterValues { it == ended }.keys
+    if (asked.isEmpty()) return
+    watchRequests.keys.retainAll(asked)
+    watchResults.keys.retainAll(asked)
+    watchReferences.retain { it in asked }
+    watchTargets.keys.retainAll { it in watchReferences }
+}
+
+/** Forgets the cached watch results, after a value may have been written. */
+fun DebugSession.forgetWatchResults() {
+    watchResults.clear()
+}
+
+/**
+ * Handles `evaluate` in the `watch` context: answers from the cache when
+ * the watch was evaluated at this stop, or, with `watchRefresh` set to
+ * `"frame"`, in the same frame (see the file header).
+ */
+suspend fun DebugSession.handleWatch(rawJson: String, ctx: AsyncRequestContext) {
+    val obj = JSONObject(rawJson)
+    val requestSeq = obj.optInt("seq", 0)
+    val args = obj.optJSONObject("arguments") ?: JSONObject()
+
+    try {
+        val frameId = if (args.has("frameId")) args.optInt("frameId") else null
+        val key = WatchKey(args.optString("expression"), frameId, args.optJSONObject("format")?.toString())
+        val stop = watchStop.get()
+        watchRequests[key] = stop
+        val cached = watchResults[key]
+        val response = when {
+            cached == null -> evaluateWatch(key, args, stop, ctx)
+            cached.stop == stop -> cached.response
+            watchRefresh == WatchRefresh.Frame && cached.frameKey != null &&
+                cached.frameKey == frameKey(frameId, ctx) -> {
+                watchResults[key] = cached.copy(stop = stop)
+                cached.response
+            }
+            else -> evaluateWatch(key, args, stop, ctx)
+        }
+        ctx.sendEventToClient(response.copy(requestSeq = requestSeq).toJson())
+    } catch (e: Exception) {
+        log.warning { "Watches: evaluate failed: ${e.message}" }
+        sendErrorResponse(ctx, requestSeq, "evaluate", e.message ?: "evaluate failed")
+    }
+}
+
+/**
+ * lldb-dap's `variablesReference` for [reference]: [reference] itself,
+ * unless it is a watch reference, which is resolved for the current
+ * stop. Throws if the watch no longer has children.
+ */
+internal suspend fun DebugSession.backendVariablesReference(reference: Int, ctx: AsyncRequestContext): Int {
+    val target = watchTargets[reference] ?: return reference
+    val stop = watchStop.get()
+    if (target.stop == stop) return target.backendReference
+    val response = evaluateResponse(JSONObject(target.arguments), ctx)
+    val backendReference = (response.body["variablesReference"] as? Number)?.toInt()
+        ?.takeIf { response.success && it > 0 }
+        ?: throw ExpressionError(response.message ?: "The watch has no children at this stop")
+    watchTargets[reference] = target.copy(backendReference = backendReference, stop = stop)
+    return backendReference
+}
+
+/** Evaluates the watch [key] at [stop] and caches the result. */
+private suspend fun DebugSession.evaluateWatch(
+    key: WatchKey,
+    args: JSONObject,
+    stop: Int,
+    ctx: AsyncRequestContext,
+): DapResponse {
+    val frameKey = if (watchRefresh == WatchRefresh.Frame) frameKey(key.frameId, ctx) else null
+    val response = evaluateResponse(args, ctx)
+    val backendReference = (response.body["variablesReference"] as? Number)?.toInt() ?: 0
+    val result = if (response.success && backendReference > 0) {
+        val reference = watchReferences.handleFor(key)
+        if (reference > 0) watchTargets[reference] = WatchTarget(args.toString(), backendReference, stop)
+        response.copy(body = response.body + ("variablesReference" to reference))
+    } else {
+        response
+    }
+    watchResults[key] = WatchResult(result, stop, frameKey)
+    return result
+}
+
+/**
+ * Identifies the frame with lldb-dap frame ID [frameId] (or the selected
+ * frame) by thread, CFA, and function, or `null` if there is none.
+ */
+internal suspend fun DebugSession.frameKey(frameId: Int?, ctx: AsyncRequestContext): String? {
+    val debugger = createDebugger(ctx).let { d -> sbWatcher?.let { d.watched(it) } ?: d }
+    val output = debugger.commandInterpreter().handleCommand(
+        "script print((lambda f: '%d:%x:%s' % (f.GetThread().GetIndexID(), f.GetCFA(), f.GetFunctionName()) " +
+            "if f.IsValid() else '')(${framePython(frameId)}))",
+    )
+    return output.trim().ifEmpty { null }
+}
@@ -0,0 +1,38 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.isTaskFrame
+import com.github.jomof.dap.debugsession.isTaskThread
+import com.github.jomof.dap.debugsession.taskStackTrace
+import com.github.jomof.dap.messages.DapRequest
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.messages.ScopesRequest
+import com.github.jomof.dap.messages.StackTraceRequest
+
+/**
+ * Lists tokio tasks as pseudo-threads when the session was launched or
+ * attached with `"tokioTasks": true`.
+ *
+ * `threads` is answered by `ThreadsHandler`, which lists the tasks after
+ * the OS threads; `stackTrace` for a task pseudo-thread is answered
+ * from the tasks the last `threads` response listed
+ * ([DebugSession.taskStackTrace][taskStackTrace]), and `scopes` for
+ * their frames is empty. Everything else, including requests for OS
+ * threads, is forwarded.
+ */
+class AsyncTasksHandler(private val session: DebugSession) : InterceptionHandler {
+    override fun onRequest(request: DapRequest): RequestAction {
+        if (!session.tokioTasks) return RequestAction.Forward
+        return when {
+            request is StackTraceRequest && isTaskThread(request.threadId) ->
+                RequestAction.Respond(session.taskStackTrace(request))
+            request is ScopesRequest && isTaskFrame(request.frameId) ->
+                RequestAction.Respond(DapResponse(
+                    seq = 0, requestSeq = request.seq, command = "scopes", success = true,
+                    body = mapOf("scopes" to emptyList<Any>()),
+                ))
+            else -> RequestAction.Forward
+        }
+    }
+}
@@ -0,0 +1,28 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.handleBreakpointFile
+import com.github.jomof.dap.debugsession.rememberBreakpoints
+import com.github.jomof.dap.messages.BreakpointsRequest
+import com.github.jomof.dap.messages.DapRequest
+
+/**
+ * Intercepts KDAP's `kdap/breakpoints` request and handles it
+ * asynchronously by delegating to
+ * [DebugSession.handleBreakpointFile][handleBreakpointFile]. lldb-dap has
+ * no such request, so it is never forwarded. Every `setBreakpoints` and
+ * `setFunctionBreakpoints` is observed, so an export has the client's
+ * breakpoints ([rememberBreakpoints]).
+ */
+class BreakpointFileHandler(private val session: DebugSession) : InterceptionHandler {
+    override fun onRequest(request: DapRequest): RequestAction {
+        session.rememberBreakpoints(request)
+        return when (request) {
+            is BreakpointsRequest -> RequestAction.HandleAsync { rawJson, ctx ->
+                session.handleBreakpointFile(rawJson, ctx)
+            }
+            else -> RequestAction.Forward
+        }
+    }
+}
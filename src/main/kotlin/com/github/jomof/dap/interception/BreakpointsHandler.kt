@@ -0,0 +1,95 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.BREAKPOINT_EVENT_MARKER
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.addInstructionBreakpointsCapability
+import com.github.jomof.dap.debugsession.handleBreakpointLocations
+import com.github.jomof.dap.debugsession.handleSetBreakpoints
+import com.github.jomof.dap.debugsession.handleSetFunctionBreakpoints
+import com.github.jomof.dap.debugsession.handleSetInstructionBreakpoints
+import com.github.jomof.dap.debugsession.markChangedSourceBreakpoint
+import com.github.jomof.dap.debugsession.ownsBreakpoints
+import com.github.jomof.dap.debugsession.ownsFunctionBreakpoints
+import com.github.jomof.dap.debugsession.ownsInstructionBreakpoints
+import com.github.jomof.dap.debugsession.rewriteHardwareBreakpointStop
+import com.github.jomof.dap.debugsession.splitBreakpointEvents
+import com.github.jomof.dap.debugsession.withProgress
+import com.github.jomof.dap.messages.BreakpointEvent
+import com.github.jomof.dap.messages.BreakpointLocationsRequest
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.DapRequest
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.messages.OutputEvent
+import com.github.jomof.dap.messages.SetBreakpointsRequest
+import com.github.jomof.dap.messages.SetFunctionBreakpointsRequest
+import com.github.jomof.dap.messages.SetInstructionBreakpointsRequest
+import com.github.jomof.dap.messages.StoppedEvent
+
+/**
+ * Handles `setBreakpoints` requests that need KDAP breakpoint behavior
+ * (e.g., Python conditions, hit conditions, logpoints) by delegating to
+ * [DebugSession.handleSetBreakpoints][handleSetBreakpoints], and
+ * `setFunctionBreakpoints` requests with wildcard or regex names by
+ * delegating to
+ * [DebugSession.handleSetFunctionBreakpoints][handleSetFunctionBreakpoints].
+ * Disassembly view breakpoints (`setInstructionBreakpoints`) get the
+ * same condition handling as source breakpoints through
+ * [DebugSession.handleSetInstructionBreakpoints][handleSetInstructionBreakpoints],
+ * and `supportsInstructionBreakpoints` is advertised. `breakpointLocations`
+ * for source files is answered by
+ * [DebugSession.handleBreakpointLocations][handleBreakpointLocations].
+ * Stops at KDAP's hardware breakpoints are reported as stops at the
+ * breakpoints they stand in for, and the breakpoint listener's marked
+ * output becomes `breakpoint` events (see PendingBreakpoints.kt); like
+ * [ModulesHandler], it must come before handlers that inspect output.
+ * `breakpoint` events keep the mark of breakpoints in sources changed
+ * since the program was built.
+ *
+ * Requests lldb-dap can handle on its own are forwarded unchanged.
+ */
+class BreakpointsHandler(private val session: DebugSession) : InterceptionHandler {
+    override fun onRequest(request: DapRequest): RequestAction = when (request) {
+        is SetBreakpointsRequest -> if (session.ownsBreakpoints(request)) {
+            RequestAction.HandleAsync { rawJson, ctx ->
+                session.handleSetBreakpoints(rawJson, ctx)
+            }
+        } else {
+            RequestAction.Forward
+        }
+        is SetFunctionBreakpointsRequest -> if (session.ownsFunctionBreakpoints(request)) {
+            RequestAction.HandleAsync { rawJson, ctx ->
+                session.handleSetFunctionBreakpoints(rawJson, ctx)
+            }
+        } else {
+            RequestAction.Forward
+        }
+        is SetInstructionBreakpointsRequest -> if (session.ownsInstructionBreakpoints(request)) {
+            RequestAction.HandleAsync { rawJson, ctx ->
+                session.handleSetInstructionBreakpoints(rawJson, ctx)
+            }
+        } else {
+            RequestAction.Forward
+        }
+        is BreakpointLocationsRequest -> if (request.source.path != null) {
+            RequestAction.HandleAsync(cancellable = true) { rawJson, ctx ->
+                session.withProgress(ctx, request.seq, "Finding breakpoint locations") {
+                    session.handleBreakpointLocations(rawJson, ctx)
+                }
+            }
+        } else {
+            RequestAction.Forward
+        }
+        else -> RequestAction.Forward
+    }
+
+    override fun onBackendMessage(message: DapMessage): List<DapMessage> = when {
+        message is DapResponse && message.command == "initialize" && message.success ->
+            listOf(addInstructionBreakpointsCapability(message))
+        message is StoppedEvent -> listOf(session.rewriteHardwareBreakpointStop(message))
+        message is OutputEvent && BREAKPOINT_EVENT_MARKER in message.output ->
+            splitBreakpointEvents(message).map { if (it is BreakpointEvent) session.markChangedSourceBreakpoint(it) else it }
+        message is BreakpointEvent -> listOf(session.markChangedSourceBreakpoint(message))
+        else -> listOf(message)
+    }
+}
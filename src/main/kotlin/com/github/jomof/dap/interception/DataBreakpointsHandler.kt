@@ -0,0 +1,40 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.handleContinueWithSoftwareWatchpoints
+import com.github.jomof.dap.debugsession.handleSetDataBreakpoints
+import com.github.jomof.dap.debugsession.rewriteSoftwareWatchStop
+import com.github.jomof.dap.messages.ContinueRequest
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.DapRequest
+import com.github.jomof.dap.messages.SetDataBreakpointsRequest
+import com.github.jomof.dap.messages.StoppedEvent
+
+/**
+ * Handles data breakpoints via [DebugSession.handleSetDataBreakpoints][handleSetDataBreakpoints],
+ * which falls back to software watchpoints when lldb-dap runs out of
+ * hardware ones.
+ *
+ * While software watchpoints are active, `continue` is handled by
+ * [DebugSession.handleContinueWithSoftwareWatchpoints][handleContinueWithSoftwareWatchpoints]
+ * and the resulting stop is reported as a data breakpoint hit.
+ */
+class DataBreakpointsHandler(private val session: DebugSession) : InterceptionHandler {
+    override fun onRequest(request: DapRequest): RequestAction = when (request) {
+        is SetDataBreakpointsRequest -> RequestAction.HandleAsync { rawJson, ctx ->
+            session.handleSetDataBreakpoints(rawJson, ctx)
+        }
+        is ContinueRequest -> if (session.softwareWatchpoints.isNotEmpty()) {
+            RequestAction.HandleAsync { rawJson, ctx ->
+                session.handleContinueWithSoftwareWatchpoints(rawJson, ctx)
+            }
+        } else {
+            RequestAction.Forward
+        }
+        else -> RequestAction.Forward
+    }
+
+    override fun onBackendMessage(message: DapMessage): List<DapMessage> =
+        if (message is StoppedEvent) listOf(session.rewriteSoftwareWatchStop(message)) else listOf(message)
+}
@@ -0,0 +1,132 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.ExpressionError
+import com.github.jomof.dap.debugsession.ReplInput
+import com.github.jomof.dap.debugsession.addClipboardCapability
+import com.github.jomof.dap.debugsession.classifyReplInput
+import com.github.jomof.dap.debugsession.completionContext
+import com.github.jomof.dap.debugsession.forgetWatchResults
+import com.github.jomof.dap.debugsession.handleClipboardEvaluate
+import com.github.jomof.dap.debugsession.handleCompletions
+import com.github.jomof.dap.debugsession.handleEvaluate
+import com.github.jomof.dap.debugsession.handleReplEvaluate
+import com.github.jomof.dap.debugsession.handleWatch
+import com.github.jomof.dap.debugsession.lldbDapExpression
+import com.github.jomof.dap.debugsession.onWatchStop
+import com.github.jomof.dap.debugsession.prepareExpression
+import com.github.jomof.dap.debugsession.splitArraySuffix
+import com.github.jomof.dap.debugsession.splitFormatSuffix
+import com.github.jomof.dap.debugsession.withProgress
+import com.github.jomof.dap.messages.CompletionsRequest
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.DapRequest
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.messages.EvaluateRequest
+import com.github.jomof.dap.messages.SetExpressionRequest
+import com.github.jomof.dap.messages.SetVariableRequest
+import com.github.jomof.dap.messages.StoppedEvent
+import com.github.jomof.dap.messages.WriteMemoryRequest
+
+/**
+ * Evaluates expressions with KDAP's simple expression evaluator.
+ *
+ * - Hover and other non-console contexts go to
+ *   [DebugSession.handleEvaluate][handleEvaluate]. Native expressions
+ *   (`/nat`, or anything that isn't a valid simple expression) and bare
+ *   variable names go to lldb-dap, with the `/nat` prefix stripped,
+ *   unless they end in a format suffix (`,x`, `,b`, `,d`, `,c`, `,s`) or an
+ *   array suffix (`@len`, `,[N]`).
+ * - Watches go to [DebugSession.handleWatch][handleWatch], which
+ *   evaluates them the same way once per stop. Its cache is dropped on
+ *   `setVariable`, `setExpression`, `writeMemory`, and console input,
+ *   which may write the values watched. Watches and console input may
+ *   call functions of the debuggee (see FunctionCalls.kt), whose effects
+ *   can't be undone, so they are not cancellable; other evaluations are.
+ * - In the `repl` context, backtick meta-commands, `?` expressions, and
+ *   assignments go to [DebugSession.handleReplEvaluate][handleReplEvaluate];
+ *   everything else (LLDB commands, native expressions) goes to lldb-dap.
+ * - "Copy Value" (`clipboard`) goes to
+ *   [DebugSession.handleClipboardEvaluate][handleClipboardEvaluate] for a
+ *   full rendering; `supportsClipboardContext` is added to lldb-dap's
+ *   `initialize` response.
+ * - `completions` for backtick commands and `?` expressions go to
+ *   [DebugSession.handleCompletions][handleCompletions]; lldb-dap
+ *   completes plain LLDB commands and advertises `supportsCompletionsRequest`.
+ *
+ * CodeLLDB's `_command` context is left to [EvaluateContextRewriter].
+ */
+class EvaluateHandler(private val session: DebugSession) : InterceptionHandler {
+    override fun onRequest(request: DapRequest): RequestAction {
+        if (request is CompletionsRequest) return onCompletions(request)
+        if (request is SetVariableRequest || request is SetExpressionRequest || request is WriteMemoryRequest) {
+            session.forgetWatchResults()
+            return RequestAction.Forward
+        }
+        if (request !is EvaluateRequest) return RequestAction.Forward
+        return when (request.context) {
+            "_command" -> RequestAction.Forward
+            "repl" -> {
+                session.forgetWatchResults()
+                if (classifyReplInput(request.expression) == ReplInput.Passthrough) {
+                    RequestAction.Forward
+                } else {
+                    RequestAction.HandleAsync { rawJson, ctx -> session.handleReplEvaluate(rawJson, ctx) }
+                }
+            }
+            "clipboard" -> RequestAction.HandleAsync(cancellable = true) { rawJson, ctx ->
+                session.withProgress(ctx, request.seq, "Copying value") {
+                    session.handleClipboardEvaluate(rawJson, ctx)
+                }
+            }
+            "watch" -> RequestAction.HandleAsync { rawJson, ctx ->
+                session.withProgress(ctx, request.seq, "Evaluating expression", cancellable = false) {
+                    session.handleWatch(rawJson, ctx)
+                }
+            }
+            else -> onExpression(request)
+        }
+    }
+
+    override fun onBackendMessage(message: DapMessage): List<DapMessage> = when {
+        message is DapResponse && message.command == "initialize" && message.success ->
+            listOf(addClipboardCapability(message))
+        message is StoppedEvent -> {
+            session.onWatchStop()
+            listOf(message)
+        }
+        else -> listOf(message)
+    }
+
+    private fun onExpression(request: EvaluateRequest): RequestAction {
+        val (withoutFormat, format) = splitFormatSuffix(request.expression)
+        if (format != null || splitArraySuffix(withoutFormat).second != null) return evaluateAsync(request)
+        val native = try {
+            lldbDapExpression(request.expression, prepareExpression(request.expression))
+        } catch (e: ExpressionError) {
+            // Invalid `/se` expression: let handleEvaluate report the error.
+            return evaluateAsync(request)
+        }
+        return when (native) {
+            null -> evaluateAsync(request)
+            request.expression -> RequestAction.Forward
+            else -> RequestAction.ForwardModified(request.copy(expression = native))
+        }
+    }
+
+    private fun onCompletions(request: CompletionsRequest): RequestAction =
+        if (completionContext(request.text, request.column) == null) {
+            RequestAction.Forward
+        } else {
+            RequestAction.HandleAsync(cancellable = true) { rawJson, ctx -> session.handleCompletions(rawJson, ctx) }
+        }
+
+    /** Evaluates [request] in KDAP; cancellable, since expressions can be slow and call no functions here. */
+    private fun evaluateAsync(request: EvaluateRequest) =
+        RequestAction.HandleAsync(cancellable = true) { rawJson, ctx ->
+            session.withProgress(ctx, request.seq, "Evaluating expression") {
+                session.handleEvaluate(rawJson, ctx)
+            }
+        }
+}
@@ -0,0 +1,62 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.addExceptionFilters
+import com.github.jomof.dap.debugsession.handleExceptionInfo
+import com.github.jomof.dap.debugsession.handleSetExceptionBreakpoints
+import com.github.jomof.dap.debugsession.observePanicOutput
+import com.github.jomof.dap.debugsession.ownsExceptionBreakpoints
+import com.github.jomof.dap.debugsession.ownsExceptionInfo
+import com.github.jomof.dap.debugsession.rewriteCrashStop
+import com.github.jomof.dap.debugsession.rewritePanicStop
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.DapRequest
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.messages.ExceptionInfoRequest
+import com.github.jomof.dap.messages.OutputEvent
+import com.github.jomof.dap.messages.SetExceptionBreakpointsRequest
+import com.github.jomof.dap.messages.StoppedEvent
+
+/**
+ * Implements KDAP's exception filters (Rust panics, crashes, signals).
+ *
+ * - Advertises the filters by appending them to lldb-dap's `initialize`
+ *   response.
+ * - Handles `setExceptionBreakpoints` via
+ *   [DebugSession.handleSetExceptionBreakpoints][handleSetExceptionBreakpoints]
+ *   when a KDAP filter is involved.
+ * - Watches debuggee stderr for the panic message and reports panic
+ *   and crash breakpoint hits as `exception` stops, answering
+ *   `exceptionInfo` for them.
+ */
+class ExceptionBreakpointsHandler(private val session: DebugSession) : InterceptionHandler {
+    override fun onRequest(request: DapRequest): RequestAction = when (request) {
+        is SetExceptionBreakpointsRequest -> if (session.ownsExceptionBreakpoints(request)) {
+            RequestAction.HandleAsync { rawJson, ctx ->
+                session.handleSetExceptionBreakpoints(rawJson, ctx)
+            }
+        } else {
+            RequestAction.Forward
+        }
+        is ExceptionInfoRequest -> if (session.ownsExceptionInfo(request)) {
+            RequestAction.HandleAsync { rawJson, ctx ->
+                session.handleExceptionInfo(rawJson, ctx)
+            }
+        } else {
+            RequestAction.Forward
+        }
+        else -> RequestAction.Forward
+    }
+
+    override fun onBackendMessage(message: DapMessage): List<DapMessage> = when {
+        message is DapResponse && message.command == "initialize" && message.success ->
+            listOf(addExceptionFilters(message))
+        message is OutputEvent -> {
+            session.observePanicOutput(message)
+            listOf(message)
+        }
+        message is StoppedEvent -> listOf(session.rewriteCrashStop(session.rewritePanicStop(message)))
+        else -> listOf(message)
+    }
+}
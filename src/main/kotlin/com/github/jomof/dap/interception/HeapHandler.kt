@@ -0,0 +1,21 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.handleHeap
+import com.github.jomof.dap.messages.DapRequest
+import com.github.jomof.dap.messages.HeapRequest
+
+/**
+ * Intercepts KDAP's `kdap/heap` request and handles it asynchronously by
+ * delegating to [DebugSession.handleHeap][handleHeap]. lldb-dap has no
+ * such request, so it is never forwarded.
+ */
+class HeapHandler(private val session: DebugSession) : InterceptionHandler {
+    override fun onRequest(request: DapRequest): RequestAction = when (request) {
+        is HeapRequest -> RequestAction.HandleAsync { rawJson, ctx ->
+            session.handleHeap(rawJson, ctx)
+        }
+        else -> RequestAction.Forward
+    }
+}
@@ -0,0 +1,22 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.handleHotReplace
+import com.github.jomof.dap.messages.DapRequest
+import com.github.jomof.dap.messages.HotReplaceRequest
+
+/**
+ * Intercepts KDAP's experimental `kdap/hotReplace` request and handles
+ * it asynchronously by delegating to
+ * [DebugSession.handleHotReplace][handleHotReplace]. lldb-dap has no
+ * such request, so it is never forwarded.
+ */
+class HotReplaceHandler(private val session: DebugSession) : InterceptionHandler {
+    override fun onRequest(request: DapRequest): RequestAction = when (request) {
+        is HotReplaceRequest -> RequestAction.HandleAsync { rawJson, ctx ->
+            session.handleHotReplace(rawJson, ctx)
+        }
+        else -> RequestAction.Forward
+    }
+}
@@ -2,19 +2,42 @@ package com.github.jomof.dap.interception
 
 import com.github.jomof.dap.DapSession.RequestAction
 import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.addRestartCapability
 import com.github.jomof.dap.debugsession.handleLaunch
+import com.github.jomof.dap.debugsession.handleRestart
+import com.github.jomof.dap.debugsession.rewriteEntryStop
+import com.github.jomof.dap.messages.DapMessage
 import com.github.jomof.dap.messages.DapRequest
+import com.github.jomof.dap.messages.DapResponse
 import com.github.jomof.dap.messages.LaunchRequest
+import com.github.jomof.dap.messages.RestartRequest
+import com.github.jomof.dap.messages.StoppedEvent
 
 /**
  * Intercepts the `launch` request and handles it asynchronously by
  * delegating to [DebugSession.handleLaunch][handleLaunch].
+ *
+ * `restart` relaunches the debuggee via
+ * [DebugSession.handleRestart][handleRestart], and the `initialize`
+ * response advertises `supportsRestartRequest`. The stop at the
+ * breakpoint of `"stopOnEntry": "main"` is reported as an `entry` stop
+ * ([rewriteEntryStop]).
  */
 class LaunchHandler(private val session: DebugSession) : InterceptionHandler {
     override fun onRequest(request: DapRequest): RequestAction = when (request) {
         is LaunchRequest -> RequestAction.HandleAsync { rawJson, ctx ->
             session.handleLaunch(rawJson, ctx)
         }
+        is RestartRequest -> RequestAction.HandleAsync { rawJson, ctx ->
+            session.handleRestart(rawJson, ctx)
+        }
         else -> RequestAction.Forward
     }
+
+    override fun onBackendMessage(message: DapMessage): List<DapMessage> = when {
+        message is DapResponse && message.command == "initialize" && message.success ->
+            listOf(addRestartCapability(message))
+        message is StoppedEvent -> listOf(session.rewriteEntryStop(message))
+        else -> listOf(message)
+    }
 }
@@ -0,0 +1,22 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.handleLoadSymbols
+import com.github.jomof.dap.messages.DapRequest
+import com.github.jomof.dap.messages.LoadSymbolsRequest
+
+/**
+ * Intercepts KDAP's `kdap/loadSymbols` request and handles it
+ * asynchronously by delegating to
+ * [DebugSession.handleLoadSymbols][handleLoadSymbols]. lldb-dap has no
+ * such request, so it is never forwarded.
+ */
+class LoadSymbolsHandler(private val session: DebugSession) : InterceptionHandler {
+    override fun onRequest(request: DapRequest): RequestAction = when (request) {
+        is LoadSymbolsRequest -> RequestAction.HandleAsync { rawJson, ctx ->
+            session.handleLoadSymbols(rawJson, ctx)
+        }
+        else -> RequestAction.Forward
+    }
+}
@@ -0,0 +1,22 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.handleMemoryMap
+import com.github.jomof.dap.messages.DapRequest
+import com.github.jomof.dap.messages.MemoryMapRequest
+
+/**
+ * Intercepts KDAP's `kdap/memoryMap` request and handles it
+ * asynchronously by delegating to
+ * [DebugSession.handleMemoryMap][handleMemoryMap]. lldb-dap has no such
+ * request, so it is never forwarded.
+ */
+class MemoryMapHandler(private val session: DebugSession) : InterceptionHandler {
+    override fun onRequest(request: DapRequest): RequestAction = when (request) {
+        is MemoryMapRequest -> RequestAction.HandleAsync { rawJson, ctx ->
+            session.handleMemoryMap(rawJson, ctx)
+        }
+        else -> RequestAction.Forward
+    }
+}
@@ -0,0 +1,26 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.handleSearchMemory
+import com.github.jomof.dap.debugsession.withProgress
+import com.github.jomof.dap.messages.DapRequest
+import com.github.jomof.dap.messages.SearchMemoryRequest
+
+/**
+ * Intercepts KDAP's `kdap/searchMemory` request and handles it
+ * asynchronously by delegating to
+ * [DebugSession.handleSearchMemory][handleSearchMemory]. lldb-dap has no
+ * such request, so it is never forwarded. A search is cancellable and
+ * reports its progress.
+ */
+class MemorySearchHandler(private val session: DebugSession) : InterceptionHandler {
+    override fun onRequest(request: DapRequest): RequestAction = when (request) {
+        is SearchMemoryRequest -> RequestAction.HandleAsync(cancellable = true) { rawJson, ctx ->
+            session.withProgress(ctx, request.seq, "Searching memory") {
+                session.handleSearchMemory(rawJson, ctx)
+            }
+        }
+        else -> RequestAction.Forward
+    }
+}
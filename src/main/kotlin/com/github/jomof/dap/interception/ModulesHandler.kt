@@ -0,0 +1,54 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.MODULE_EVENT_MARKER
+import com.github.jomof.dap.debugsession.SYMBOL_STATUS_MARKER
+import com.github.jomof.dap.debugsession.addLoadedSourcesCapability
+import com.github.jomof.dap.debugsession.handleLoadedSources
+import com.github.jomof.dap.debugsession.handleModules
+import com.github.jomof.dap.debugsession.splitModuleEvents
+import com.github.jomof.dap.debugsession.splitSymbolStatusEvents
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.DapRequest
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.messages.LoadedSourcesRequest
+import com.github.jomof.dap.messages.ModuleEvent
+import com.github.jomof.dap.messages.ModulesRequest
+import com.github.jomof.dap.messages.OutputEvent
+
+/**
+ * Reports the target's modules and sources (see Modules.kt).
+ *
+ * - Handles `modules` and `loadedSources` via
+ *   [DebugSession.handleModules][handleModules] and
+ *   [DebugSession.handleLoadedSources][handleLoadedSources].
+ * - Turns the module listener's marked output into `module` events and
+ *   drops lldb-dap's own `module` events.
+ * - Turns the symbol index warm-up's marked output into
+ *   `kdap/symbolStatus` events (see SymbolIndex.kt).
+ * - Advertises `supportsLoadedSourcesRequest`; lldb-dap already
+ *   advertises `supportsModulesRequest`.
+ *
+ * **Must be registered before handlers that inspect output events** so
+ * they never see the listener's records.
+ */
+class ModulesHandler(private val session: DebugSession) : InterceptionHandler {
+    override fun onRequest(request: DapRequest): RequestAction = when (request) {
+        is ModulesRequest -> RequestAction.HandleAsync { rawJson, ctx -> session.handleModules(rawJson, ctx) }
+        is LoadedSourcesRequest -> RequestAction.HandleAsync { rawJson, ctx ->
+            session.handleLoadedSources(rawJson, ctx)
+        }
+        else -> RequestAction.Forward
+    }
+
+    override fun onBackendMessage(message: DapMessage): List<DapMessage> = when {
+        message is DapResponse && message.command == "initialize" && message.success ->
+            listOf(addLoadedSourcesCapability(message))
+        message is OutputEvent && MODULE_EVENT_MARKER in message.output ->
+            splitModuleEvents(message).flatMap { if (it is OutputEvent) onBackendMessage(it) else listOf(it) }
+        message is OutputEvent && SYMBOL_STATUS_MARKER in message.output -> splitSymbolStatusEvents(message)
+        message is ModuleEvent -> emptyList()
+        else -> listOf(message)
+    }
+}
@@ -1,6 +1,8 @@
 package com.github.jomof.dap.interception
 
 import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.LOGPOINT_MARKER
+import com.github.jomof.dap.debugsession.PTY_OUTPUT_MARKER
 import com.github.jomof.dap.messages.DapMessage
 import com.github.jomof.dap.messages.OutputEvent
 
@@ -16,6 +18,12 @@ import com.github.jomof.dap.messages.OutputEvent
  * [DebugSession.processRunning] is `true`, **except** for exit-status
  * messages (which [ExitStatusHandler] needs to stay as `console`).
  *
+ * Logpoint output printed by KDAP breakpoint callbacks carries
+ * [LOGPOINT_MARKER]; the marker is stripped and the output is always
+ * reported as `console`, since it comes from the adapter, not the debuggee.
+ * Debuggee output copied from a KDAP pty (`"pty": true`) carries
+ * [PTY_OUTPUT_MARKER] and is reported as `stdout` the same way.
+ *
  * On macOS/Linux this is effectively a no-op — debuggee output already
  * arrives as `stdout`, so the reclassification never triggers.
  *
@@ -27,6 +35,20 @@ class OutputCategoryNormalizer(
 ) : InterceptionHandler {
 
     override fun onBackendMessage(message: DapMessage): List<DapMessage> {
+        if (message is OutputEvent && LOGPOINT_MARKER in message.output) {
+            return listOf(OutputEvent(
+                seq = message.seq,
+                category = "console",
+                output = message.output.replace(LOGPOINT_MARKER, ""),
+            ))
+        }
+        if (message is OutputEvent && PTY_OUTPUT_MARKER in message.output) {
+            return listOf(OutputEvent(
+                seq = message.seq,
+                category = "stdout",
+                output = message.output.replace(PTY_OUTPUT_MARKER, ""),
+            ))
+        }
         if (session.processRunning
             && message is OutputEvent
             && message.category == "console"
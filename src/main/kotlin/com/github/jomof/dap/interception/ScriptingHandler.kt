@@ -0,0 +1,22 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.onScriptStop
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.StoppedEvent
+
+/**
+ * Runs the `kdap.init` script's stop hooks (see Scripting.kt) for each
+ * `stopped` event, as the client receives it, via
+ * [DebugSession.onScriptStop][onScriptStop]. The event itself is passed
+ * on unchanged.
+ *
+ * **Must be registered after handlers that rewrite stopped events** so
+ * the hooks see what the client sees.
+ */
+class ScriptingHandler(private val session: DebugSession) : InterceptionHandler {
+    override fun onBackendMessage(message: DapMessage): List<DapMessage> {
+        if (message is StoppedEvent) session.onScriptStop(message)
+        return listOf(message)
+    }
+}
@@ -0,0 +1,21 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.handleSignals
+import com.github.jomof.dap.messages.DapRequest
+import com.github.jomof.dap.messages.SignalsRequest
+
+/**
+ * Intercepts KDAP's `kdap/signals` request and handles it asynchronously
+ * by delegating to [DebugSession.handleSignals][handleSignals]. lldb-dap
+ * has no such request, so it is never forwarded.
+ */
+class SignalsHandler(private val session: DebugSession) : InterceptionHandler {
+    override fun onRequest(request: DapRequest): RequestAction = when (request) {
+        is SignalsRequest -> RequestAction.HandleAsync { rawJson, ctx ->
+            session.handleSignals(rawJson, ctx)
+        }
+        else -> RequestAction.Forward
+    }
+}
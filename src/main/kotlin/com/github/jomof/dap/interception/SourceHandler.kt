@@ -0,0 +1,28 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.handleDisassemblySource
+import com.github.jomof.dap.debugsession.handleSource
+import com.github.jomof.dap.debugsession.isDisassemblySource
+import com.github.jomof.dap.debugsession.isFetchedSource
+import com.github.jomof.dap.messages.DapRequest
+import com.github.jomof.dap.messages.SourceRequest
+
+/**
+ * Handles `source` for the sources KDAP gave references to in stack
+ * traces, via [DebugSession.handleSource][handleSource] (see
+ * Sources.kt) and, for frames shown as disassembly,
+ * [DebugSession.handleDisassemblySource][handleDisassemblySource] (see
+ * Disassembly.kt). Other references, such as lldb-dap's disassembly, are
+ * forwarded.
+ */
+class SourceHandler(private val session: DebugSession) : InterceptionHandler {
+    override fun onRequest(request: DapRequest): RequestAction = when {
+        request is SourceRequest && session.isFetchedSource(request.sourceReference) ->
+            RequestAction.HandleAsync { rawJson, ctx -> session.handleSource(rawJson, ctx) }
+        request is SourceRequest && session.isDisassemblySource(request.sourceReference) ->
+            RequestAction.HandleAsync { rawJson, ctx -> session.handleDisassemblySource(rawJson, ctx) }
+        else -> RequestAction.Forward
+    }
+}
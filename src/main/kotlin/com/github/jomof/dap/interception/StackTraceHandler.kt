@@ -0,0 +1,25 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.handleStackTrace
+import com.github.jomof.dap.debugsession.isTaskThread
+import com.github.jomof.dap.messages.DapRequest
+import com.github.jomof.dap.messages.StackTraceRequest
+
+/**
+ * Handles `stackTrace` for OS threads via
+ * [DebugSession.handleStackTrace][handleStackTrace], which marks inlined
+ * frames (see StackFrames.kt). Tokio task pseudo-threads are left to
+ * [AsyncTasksHandler].
+ *
+ * **Must be registered after [SteppingHandler]**, which observes the
+ * request; [handleStackTrace] hands it the response.
+ */
+class StackTraceHandler(private val session: DebugSession) : InterceptionHandler {
+    override fun onRequest(request: DapRequest): RequestAction = when {
+        request is StackTraceRequest && !(session.tokioTasks && isTaskThread(request.threadId)) ->
+            RequestAction.HandleAsync { rawJson, ctx -> session.handleStackTrace(rawJson, ctx) }
+        else -> RequestAction.Forward
+    }
+}
@@ -0,0 +1,21 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.handleStdin
+import com.github.jomof.dap.messages.DapRequest
+import com.github.jomof.dap.messages.StdinRequest
+
+/**
+ * Intercepts KDAP's `kdap/stdin` request and handles it asynchronously by
+ * delegating to [DebugSession.handleStdin][handleStdin]. lldb-dap has no
+ * such request, so it is never forwarded.
+ */
+class StdinHandler(private val session: DebugSession) : InterceptionHandler {
+    override fun onRequest(request: DapRequest): RequestAction = when (request) {
+        is StdinRequest -> RequestAction.HandleAsync { rawJson, ctx ->
+            session.handleStdin(rawJson, ctx)
+        }
+        else -> RequestAction.Forward
+    }
+}
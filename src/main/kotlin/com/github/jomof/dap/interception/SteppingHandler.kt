@@ -0,0 +1,170 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.addStepBackCapability
+import com.github.jomof.dap.debugsession.addSteppingCapabilities
+import com.github.jomof.dap.debugsession.handleAsyncNext
+import com.github.jomof.dap.debugsession.handleGoto
+import com.github.jomof.dap.debugsession.handleJustMyCodeStepIn
+import com.github.jomof.dap.debugsession.handlePause
+import com.github.jomof.dap.debugsession.handleGotoTargets
+import com.github.jomof.dap.debugsession.handleRestartFrame
+import com.github.jomof.dap.debugsession.handleReverseContinue
+import com.github.jomof.dap.debugsession.handleStepBack
+import com.github.jomof.dap.debugsession.handleSingleThreadContinue
+import com.github.jomof.dap.debugsession.handleStepInTarget
+import com.github.jomof.dap.debugsession.handleStepInTargets
+import com.github.jomof.dap.debugsession.handleStepIntoSkipped
+import com.github.jomof.dap.debugsession.isAsyncNext
+import com.github.jomof.dap.debugsession.isJustMyCodeStepIn
+import com.github.jomof.dap.debugsession.isSingleThreadContinue
+import com.github.jomof.dap.debugsession.isStepInTarget
+import com.github.jomof.dap.debugsession.onStackTraceRequest
+import com.github.jomof.dap.debugsession.onStackTraceResponse
+import com.github.jomof.dap.debugsession.onStopped
+import com.github.jomof.dap.debugsession.onThreadsResponse
+import com.github.jomof.dap.debugsession.resumeAllThreads
+import com.github.jomof.dap.debugsession.rewriteAsyncStepStop
+import com.github.jomof.dap.debugsession.rewriteContinued
+import com.github.jomof.dap.debugsession.rewritePauseStop
+import com.github.jomof.dap.debugsession.rewriteSkippedStepInStop
+import com.github.jomof.dap.debugsession.rewriteStepBackStop
+import com.github.jomof.dap.debugsession.stepGranularity
+import com.github.jomof.dap.messages.*
+
+/**
+ * Switches `next` / `stepIn` to instruction granularity when the thread
+ * is stopped in a frame without source, as CodeLLDB does in disassembly.
+ *
+ * Observes `stackTrace` requests and responses to learn which threads
+ * are stopped without source (see [DebugSession.stepGranularity][stepGranularity]).
+ * Explicit `granularity` values are forwarded unchanged.
+ *
+ * Also runs `continue` with `singleThread: true` via
+ * [DebugSession.handleSingleThreadContinue][handleSingleThreadContinue];
+ * while threads are suspended by one, the next resume request first
+ * releases them ([DebugSession.resumeAllThreads][resumeAllThreads]).
+ *
+ * `next` in a Rust async body is run by
+ * [DebugSession.handleAsyncNext][handleAsyncNext], and the stop it ends
+ * with is reported as a step; the next resume request removes its
+ * breakpoints.
+ *
+ * `stepInTargets` is answered by
+ * [DebugSession.handleStepInTargets][handleStepInTargets], and `stepIn`
+ * with one of its target IDs runs
+ * [DebugSession.handleStepInTarget][handleStepInTarget].
+ *
+ * With just my code on, other line steps in run
+ * [DebugSession.handleJustMyCodeStepIn][handleJustMyCodeStepIn], and
+ * `kdap/stepIntoSkipped` runs
+ * [DebugSession.handleStepIntoSkipped][handleStepIntoSkipped], whose
+ * stop is reported as a step.
+ *
+ * `gotoTargets` and `goto` are run by
+ * [DebugSession.handleGotoTargets][handleGotoTargets] and
+ * [DebugSession.handleGoto][handleGoto], and `restartFrame` by
+ * [DebugSession.handleRestartFrame][handleRestartFrame]. The `initialize`
+ * response advertises them, with `stepInTargets`.
+ *
+ * `reverseContinue` and `stepBack` are run by
+ * [DebugSession.handleReverseContinue][handleReverseContinue] and
+ * [DebugSession.handleStepBack][handleStepBack]; a step back ends with a
+ * `step` stop, like an async `next`. `supportsStepBack` is advertised
+ * when rr is available.
+ *
+ * `pause` is run by [DebugSession.handlePause][handlePause], and the
+ * stop it ends with is reported as a `pause`
+ * ([DebugSession.rewritePauseStop][rewritePauseStop]) on a thread of the
+ * last `threads` response.
+ */
+class SteppingHandler(private val session: DebugSession) : InterceptionHandler {
+    override fun onRequest(request: DapRequest): RequestAction = when (request) {
+        is StackTraceRequest -> {
+            session.onStackTraceRequest(request)
+            RequestAction.Forward
+        }
+        is ContinueRequest -> if (isSingleThreadContinue(request)) {
+            RequestAction.HandleAsync { rawJson, ctx -> session.handleSingleThreadContinue(rawJson, ctx) }
+        } else {
+            releasingSuspendedThreads(RequestAction.Forward)
+        }
+        is NextRequest -> if (session.isAsyncNext(request.threadId, request.granularity)) {
+            RequestAction.HandleAsync { rawJson, ctx -> session.handleAsyncNext(rawJson, ctx) }
+        } else {
+            releasingSuspendedThreads(
+                session.stepGranularity(request.threadId, request.granularity)
+                    ?.let { RequestAction.ForwardModified(request.copy(granularity = it)) }
+                    ?: RequestAction.Forward)
+        }
+        is StepInRequest -> if (session.isStepInTarget(request)) {
+            RequestAction.HandleAsync { rawJson, ctx -> session.handleStepInTarget(rawJson, ctx) }
+        } else if (session.isJustMyCodeStepIn(request)) {
+            RequestAction.HandleAsync { rawJson, ctx -> session.handleJustMyCodeStepIn(rawJson, ctx) }
+        } else {
+            releasingSuspendedThreads(
+                session.stepGranularity(request.threadId, request.granularity)
+                    ?.let { RequestAction.ForwardModified(request.copy(granularity = it)) }
+                    ?: RequestAction.Forward)
+        }
+        is StepInTargetsRequest -> RequestAction.HandleAsync { rawJson, ctx ->
+            session.handleStepInTargets(rawJson, ctx)
+        }
+        is GotoTargetsRequest -> RequestAction.HandleAsync { rawJson, ctx ->
+            session.handleGotoTargets(rawJson, ctx)
+        }
+        is GotoRequest -> RequestAction.HandleAsync { rawJson, ctx -> session.handleGoto(rawJson, ctx) }
+        is RestartFrameRequest -> RequestAction.HandleAsync { rawJson, ctx ->
+            session.handleRestartFrame(rawJson, ctx)
+        }
+        is ReverseContinueRequest -> RequestAction.HandleAsync { rawJson, ctx ->
+            session.handleReverseContinue(rawJson, ctx)
+        }
+        is StepBackRequest -> RequestAction.HandleAsync { rawJson, ctx -> session.handleStepBack(rawJson, ctx) }
+        is StepIntoSkippedRequest -> RequestAction.HandleAsync { rawJson, ctx ->
+            session.handleStepIntoSkipped(rawJson, ctx)
+        }
+        is StepOutRequest -> releasingSuspendedThreads(RequestAction.Forward)
+        is PauseRequest -> RequestAction.HandleAsync { rawJson, ctx -> session.handlePause(rawJson, ctx) }
+        else -> RequestAction.Forward
+    }
+
+    override fun onBackendMessage(message: DapMessage): List<DapMessage> =
+        when {
+            message is DapResponse && message.command == "initialize" && message.success ->
+                listOf(addStepBackCapability(addSteppingCapabilities(message)))
+            message is DapResponse && message.command == "stackTrace" -> {
+                session.onStackTraceResponse(message)
+                listOf(message)
+            }
+            message is DapResponse && message.command == "threads" -> {
+                session.onThreadsResponse(message)
+                listOf(message)
+            }
+            message is StoppedEvent -> {
+                session.onStopped()
+                listOf(session.rewritePauseStop(session.rewriteSkippedStepInStop(
+                    session.rewriteStepBackStop(session.rewriteAsyncStepStop(message)))))
+            }
+            message is ContinuedEvent -> {
+                session.pendingPause = null
+                listOf(session.rewriteContinued(message))
+            }
+            else -> listOf(message)
+        }
+
+    /**
+     * Wraps a forwarding [action] so it first releases suspended threads
+     * and removes async step, step back, and skipped step-in breakpoints,
+     * if any.
+     */
+    private fun releasingSuspendedThreads(action: RequestAction): RequestAction {
+        if (session.suspendedThreads.isEmpty() && session.asyncStep == null && session.stepBack == null &&
+            session.skippedStepIn == null) return action
+        val modified = (action as? RequestAction.ForwardModified)?.modifiedRequest
+        return RequestAction.HandleAsync { rawJson, ctx ->
+            session.resumeAllThreads(modified?.toJson() ?: rawJson, ctx)
+        }
+    }
+}
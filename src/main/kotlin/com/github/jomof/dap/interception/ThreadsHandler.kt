@@ -0,0 +1,37 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.THREAD_EVENT_MARKER
+import com.github.jomof.dap.debugsession.handleThreads
+import com.github.jomof.dap.debugsession.splitThreadEvents
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.DapRequest
+import com.github.jomof.dap.messages.OutputEvent
+import com.github.jomof.dap.messages.ThreadEvent
+import com.github.jomof.dap.messages.ThreadsRequest
+
+/**
+ * Names threads and reports them coming and going (see Threads.kt).
+ *
+ * - Handles `threads` via [DebugSession.handleThreads][handleThreads],
+ *   which also lists tokio tasks when they are enabled.
+ * - Turns the thread listener's marked output into `thread` events and
+ *   drops lldb-dap's own `thread` events.
+ *
+ * **Must be registered before handlers that inspect output events** so
+ * they never see the listener's records.
+ */
+class ThreadsHandler(private val session: DebugSession) : InterceptionHandler {
+    override fun onRequest(request: DapRequest): RequestAction = when (request) {
+        is ThreadsRequest -> RequestAction.HandleAsync { rawJson, ctx -> session.handleThreads(rawJson, ctx) }
+        else -> RequestAction.Forward
+    }
+
+    override fun onBackendMessage(message: DapMessage): List<DapMessage> = when {
+        message is OutputEvent && THREAD_EVENT_MARKER in message.output ->
+            splitThreadEvents(message).flatMap { if (it is OutputEvent) onBackendMessage(it) else listOf(it) }
+        message is ThreadEvent -> emptyList()
+        else -> listOf(message)
+    }
+}
@@ -0,0 +1,91 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.RESUME_COMMANDS
+import com.github.jomof.dap.debugsession.addSetExpressionCapability
+import com.github.jomof.dap.debugsession.forgetLocalScopes
+import com.github.jomof.dap.debugsession.forgetRegisterNodes
+import com.github.jomof.dap.debugsession.forgetReturnValueScopes
+import com.github.jomof.dap.debugsession.forgetStaticsScopes
+import com.github.jomof.dap.debugsession.forgetVariableChunks
+import com.github.jomof.dap.debugsession.handleScopes
+import com.github.jomof.dap.debugsession.handleSetExpression
+import com.github.jomof.dap.debugsession.handleSetVariable
+import com.github.jomof.dap.debugsession.handleVariables
+import com.github.jomof.dap.debugsession.onScopesRequest
+import com.github.jomof.dap.debugsession.rotateValueHistory
+import com.github.jomof.dap.debugsession.withProgress
+import com.github.jomof.dap.debugsession.withRegistersScope
+import com.github.jomof.dap.debugsession.withStaticsScope
+import com.github.jomof.dap.messages.ContinuedEvent
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.DapRequest
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.messages.ScopesRequest
+import com.github.jomof.dap.messages.SetExpressionRequest
+import com.github.jomof.dap.messages.SetVariableRequest
+import com.github.jomof.dap.messages.StoppedEvent
+import com.github.jomof.dap.messages.VariablesRequest
+
+/**
+ * Handles the variables pane.
+ *
+ * - `variables` via [DebugSession.handleVariables][handleVariables],
+ *   which adds a `memoryReference` to pointers, references, and Rust
+ *   owning/fat pointers so the client can open them in its memory viewer,
+ *   and pages large containers. KDAP's references (`VariableHandles.kt`)
+ *   are forgotten on each stop and resume. Variables that changed since
+ *   the previous stop are marked (`ValueHistory.kt`).
+ *   The top frame's Locals start with the last step's return value;
+ *   `scopes` requests and responses are observed to find them.
+ * - `scopes` via [DebugSession.handleScopes][handleScopes], which splits
+ *   lldb-dap's Locals into Arguments, Locals, and Captures
+ *   (`LocalScopes.kt`) and puts KDAP's Statics and Registers scopes in
+ *   place of lldb-dap's Globals and Registers
+ *   ([DebugSession.withStaticsScope][withStaticsScope],
+ *   [DebugSession.withRegistersScope][withRegistersScope]).
+ * - `setVariable` via [DebugSession.handleSetVariable][handleSetVariable]
+ *   and `setExpression` via
+ *   [DebugSession.handleSetExpression][handleSetExpression], which write
+ *   values using their type layout. `supportsSetExpression` is added to
+ *   lldb-dap's `initialize` response.
+ *
+ * `readMemory` and `writeMemory` themselves are served by lldb-dap.
+ */
+class VariablesHandler(private val session: DebugSession) : InterceptionHandler {
+    override fun onRequest(request: DapRequest): RequestAction = when (request) {
+        is VariablesRequest -> RequestAction.HandleAsync(cancellable = true) { rawJson, ctx ->
+            session.withProgress(ctx, request.seq, "Expanding variables") {
+                session.handleVariables(rawJson, ctx)
+            }
+        }
+        is SetVariableRequest -> RequestAction.HandleAsync { rawJson, ctx ->
+            session.handleSetVariable(rawJson, ctx)
+        }
+        is SetExpressionRequest -> RequestAction.HandleAsync { rawJson, ctx ->
+            session.handleSetExpression(rawJson, ctx)
+        }
+        is ScopesRequest -> {
+            session.onScopesRequest(request)
+            RequestAction.HandleAsync { rawJson, ctx -> session.handleScopes(rawJson, ctx) }
+        }
+        else -> RequestAction.Forward
+    }
+
+    override fun onBackendMessage(message: DapMessage): List<DapMessage> = when {
+        message is DapResponse && message.command == "initialize" && message.success ->
+            listOf(addSetExpressionCapability(message))
+        message is StoppedEvent || message is ContinuedEvent ||
+            (message is DapResponse && message.command in RESUME_COMMANDS && message.success) -> {
+            session.forgetVariableChunks()
+            session.forgetReturnValueScopes()
+            session.forgetStaticsScopes()
+            session.forgetRegisterNodes()
+            session.forgetLocalScopes()
+            session.rotateValueHistory()
+            listOf(message)
+        }
+        else -> listOf(message)
+    }
+}
@@ -0,0 +1,300 @@
+package com.github.jomof.dap.messages
+
+import org.json.JSONArray
+import org.json.JSONObject
+
+/**
+ * The launch and attach configuration as a schema: every property
+ * [LaunchRequestArguments], [AttachRequestArguments], and
+ * [AdapterSettings] read, with its type and description.
+ *
+ * `kdap --generate-schema` prints it ([configurationAttributes]) as the
+ * `configurationAttributes` block of a VS Code `package.json` debugger
+ * contribution, whose `launch` and `attach` entries are JSON Schemas, so
+ * IDE extensions list the options this adapter actually has. `launch`
+ * and `attach` check their arguments against it ([validate]) and report
+ * what is wrong in the debug console; unknown properties are left
+ * alone, as clients and lldb-dap add their own.
+ *
+ * A property added to the argument classes is added here too.
+ */
+
+/** The type of a configuration property, as JSON Schema describes it and [validate] checks it. */
+sealed class SchemaType {
+    /** This type as a JSON Schema. */
+    abstract fun toJsonObject(): JSONObject
+
+    /** What a value of this type is, for messages: "a string", "one of …". */
+    abstract val expected: String
+
+    /**
+     * The problems of [value], as `org.json` parsed it, at [path]; empty
+     * if it has this type.
+     */
+    open fun problems(path: String, value: Any?): List<String> =
+        if (accepts(value)) emptyList() else listOf("`$path` must be $expected")
+
+    /** Whether [value] has this type, not looking inside arrays and objects. */
+    protected abstract fun accepts(value: Any?): Boolean
+
+    data object Str : SchemaType() {
+        override fun toJsonObject(): JSONObject = JSONObject().put("type", "string")
+        override val expected get() = "a string"
+        override fun accepts(value: Any?) = value is String
+    }
+
+    data object Bool : SchemaType() {
+        override fun toJsonObject(): JSONObject = JSONObject().put("type", "boolean")
+        override val expected get() = "a boolean"
+        override fun accepts(value: Any?) = value is Boolean
+    }
+
+    data object Integer : SchemaType() {
+        override fun toJsonObject(): JSONObject = JSONObject().put("type", "integer")
+        override val expected get() = "an integer"
+        override fun accepts(value: Any?) = value is Number && value.toDouble().let { it == Math.floor(it) && !it.isInfinite() }
+    }
+
+    data object Num : SchemaType() {
+        override fun toJsonObject(): JSONObject = JSONObject().put("type", "number")
+        override val expected get() = "a number"
+        override fun accepts(value: Any?) = value is Number
+    }
+
+    data object Null : SchemaType() {
+        override fun toJsonObject(): JSONObject = JSONObject().put("type", "null")
+        override val expected get() = "null"
+        override fun accepts(value: Any?) = value == null || value == JSONObject.NULL
+    }
+
+    /** Any JSON object, e.g. settings passed through to a script. */
+    data object AnyObject : SchemaType() {
+        override fun toJsonObject(): JSONObject = JSONObject().put("type", "object")
+        override val expected get() = "an object"
+        override fun accepts(value: Any?) = value is JSONObject
+    }
+
+    /** One of [values] (strings, or `true`). */
+    data class OneOf(val values: List<Any>) : SchemaType() {
+        override fun toJsonObject(): JSONObject = JSONObject().put("enum", JSONArray(values))
+        override val expected get() = "one of " + values.joinToString(", ") { if (it is String) "\"$it\"" else "$it" }
+        override fun accepts(value: Any?) = value in values
+    }
+
+    /** An array of [items]. */
+    data class ArrayOf(val items: SchemaType) : SchemaType() {
+        override fun toJsonObject(): JSONObject = JSONObject().put("type", "array").put("items", items.toJsonObject())
+        override val expected get() = "an array"
+        override fun accepts(value: Any?) = value is JSONArray
+        override fun problems(path: String, value: Any?): List<String> {
+            if (value !is JSONArray) return super.problems(path, value)
+            return (0 until value.length()).flatMap { i -> items.problems("$path[$i]", value.opt(i)) }
+        }
+    }
+
+    /** An object whose every value is a [values]. */
+    data class MapOf(val values: SchemaType) : SchemaType() {
+        override fun toJsonObject(): JSONObject =
+            JSONObject().put("type", "object").put("additionalProperties", values.toJsonObject())
+        override val expected get() = "an object"
+        override fun accepts(value: Any?) = value is JSONObject
+        override fun problems(path: String, value: Any?): List<String> {
+            if (value !is JSONObject) return super.problems(path, value)
+            return value.keySet().sorted().flatMap { key -> values.problems("$path.$key", value.opt(key)) }
+        }
+    }
+
+    /** An object with [properties]; the [required] ones must be present, others are not checked. */
+    data class ObjectOf(val properties: List<ConfigurationProperty>, val required: List<String> = emptyList()) : SchemaType() {
+        override fun toJsonObject(): JSONObject = JSONObject().apply {
+            put("type", "object")
+            put("properties", propertiesJson(properties))
+            if (required.isNotEmpty()) put("required", JSONArray(required))
+        }
+        override val expected get() = "an object"
+        override fun accepts(value: Any?) = value is JSONObject
+        override fun problems(path: String, value: Any?): List<String> {
+            if (value !is JSONObject) return super.problems(path, value)
+            return required.filter { !value.has(it) }.map { "`$path.$it` is required" } +
+                propertyProblems(properties, value, "$path.")
+        }
+    }
+
+    /** Any of [types]. */
+    data class AnyOf(val types: List<SchemaType>) : SchemaType() {
+        override fun toJsonObject(): JSONObject = JSONObject().put("anyOf", JSONArray(types.map { it.toJsonObject() }))
+        override val expected get() = types.joinToString(" or ") { it.expected }
+        override fun accepts(value: Any?) = types.any { it.problems("", value).isEmpty() }
+        override fun problems(path: String, value: Any?): List<String> {
+            if (types.any { it.problems(path, value).isEmpty() }) return emptyList()
+            // An array or object of the right shape is reported inside.
+            return types.firstOrNull { it is ArrayOf && value is JSONArray || it is MapOf && value is JSONObject }
+                ?.problems(path, value) ?: super.problems(path, value)
+        }
+    }
+}
+
+/** A configuration property: its JSON [name], [type], [description], and [default], if it has one. */
+data class ConfigurationProperty(
+    val name: String,
+    val type: SchemaType,
+    val description: String,
+    val default: Any? = null,
+)
+
+private fun propertiesJson(properties: List<ConfigurationProperty>): JSONObject = JSONObject().apply {
+    for (property in properties) {
+        put(property.name, property.type.toJsonObject().apply {
+            put("description", property.description)
+            property.default?.let { put("default", it) }
+        })
+    }
+}
+
+private fun propertyProblems(properties: List<ConfigurationProperty>, obj: JSONObject, prefix: String): List<String> =
+    properties.filter { obj.has(it.name) }.flatMap { it.type.problems(prefix + it.name, obj.opt(it.name)) }
+
+private fun property(name: String, type: SchemaType, description: String, default: Any? = null) =
+    ConfigurationProperty(name, type, description, default)
+
+private val STRINGS = SchemaType.ArrayOf(SchemaType.Str)
+
+private fun commands(name: String, description: String) = property(name, STRINGS, description)
+
+object ConfigurationSchema {
+
+    /** The `_adapterSettings` properties ([AdapterSettings]). */
+    val adapterSettings: List<ConfigurationProperty> = listOf(
+        property("displayFormat", SchemaType.OneOf(listOf("auto", "hex", "decimal", "binary")), "Default format of numeric values."),
+        property("showDisassembly", SchemaType.OneOf(listOf("auto", "always", "never")),
+            "Which frames show disassembly: those without source (auto), every frame, or none.", "auto"),
+        property("dereferencePointers", SchemaType.Bool, "Show the values pointers point to."),
+        property("containerSummary", SchemaType.Bool, "Summarize container contents in their value."),
+        property("evaluationTimeout", SchemaType.Num, "Seconds an expression, or a function call in one, may run.", 5),
+        property("summaryTimeout", SchemaType.Num, "Seconds a value summary may take."),
+        property("suppressMissingSourceFiles", SchemaType.Bool, "Show disassembly rather than missing source files."),
+        property("consoleMode", SchemaType.OneOf(listOf("commands", "evaluate", "split")), "How debug console input is read."),
+        property("sourceLanguages", STRINGS, "Source languages to enable language-specific features for."),
+        property("scriptConfig", SchemaType.AnyObject, "Settings passed to scripts."),
+        property("evaluateForHovers", SchemaType.Bool, "Evaluate expressions under the mouse pointer."),
+        property("commandCompletions", SchemaType.Bool, "Complete LLDB commands in the debug console."),
+        property("watchRefresh", SchemaType.OneOf(listOf("stop", "frame")),
+            "Evaluate watches again at every stop, or only when their frame changed.", "stop"),
+        property("clipboardMaxElements", SchemaType.Integer, "Elements rendered at most when copying a value.", 10_000),
+        property("staticsScope", SchemaType.OneOf(listOf("unit", "all")),
+            "Which statics the Statics scope lists: the frame's compile unit's, or every module's too.", "unit"),
+        property("internalFrames", SchemaType.OneOf(listOf("show", "subtle", "collapse")),
+            "How stack traces show runtime internals.", "subtle"),
+        property("changedValuesDepth", SchemaType.Integer,
+            "How deep containers' children are compared when marking values changed since the last stop.", 1),
+        property("maxExpansionDepth", SchemaType.Integer,
+            "Levels a variable may be expanded below its scope; 0 for no limit. Values already shown above are never expanded again.", 64),
+        property("stringPreviewLength", SchemaType.Integer,
+            "Bytes of a string shown in its value; the whole string is under its [raw bytes] child.", 4096),
+    )
+
+    /** Properties of both `launch` and `attach` ([CommonLaunchFields]). */
+    val common: List<ConfigurationProperty> = listOf(
+        property("sourceMap", SchemaType.MapOf(SchemaType.AnyOf(listOf(SchemaType.Str, SchemaType.Null))),
+            "Source path remapping: each key is a prefix of compiled-in paths, each value its local prefix."),
+        property("sysroot", SchemaType.Str, "Local copy of the target's root filesystem, where its shared libraries are found."),
+        property("solibSearchPath", STRINGS, "Directories searched for the debuggee's shared libraries."),
+        property("debugFileDirectories", STRINGS, "Directories searched for separate debug info.", listOf("/usr/lib/debug")),
+        property("debuginfodUrls", STRINGS, "debuginfod servers to download missing debug info from; DEBUGINFOD_URLS if not set."),
+        property("symbolIndexCache", SchemaType.Bool, "Keep LLDB's symbol indexes on disk between sessions.", true),
+        property("sourceServers", STRINGS, "URL templates, with {buildId} and {path}, of servers to fetch missing sources from."),
+        property("expressions", SchemaType.OneOf(listOf("simple", "python", "native")), "The default expression evaluator."),
+        commands("initCommands", "Commands executed upon debugger startup."),
+        commands("preRunCommands", "Commands executed just before the debuggee is launched or attached to."),
+        commands("postRunCommands", "Commands executed just after the debuggee has been launched or attached to."),
+        property("gracefulShutdown", SchemaType.AnyOf(listOf(SchemaType.Str, STRINGS)),
+            "How to end the debuggee: a signal name to send, or LLDB commands to run."),
+        commands("preTerminateCommands", "Commands executed just before the debuggee is terminated or disconnected from."),
+        commands("exitCommands", "Commands executed at the end of the session, after the debuggee has been terminated."),
+        property("sourceLanguages", STRINGS, "Source languages to enable language-specific features for."),
+        property("reverseDebugging", SchemaType.Bool, "Enable reverse debugging."),
+        property("relativePathBase", SchemaType.Str, "Base directory used for resolution of relative source paths."),
+        property("breakpointMode", SchemaType.OneOf(listOf("path", "file")),
+            "Match source breakpoints by full path or by file name only.", "path"),
+        property("_adapterSettings", SchemaType.ObjectOf(adapterSettings), "Adapter settings."),
+        property("followChildProcesses", SchemaType.Bool,
+            "Attach to child processes the debuggee forks or spawns, each in a new debug session."),
+        property("tokioTasks", SchemaType.Bool, "List live tokio tasks as extra threads with their await stacks."),
+        property("justMyCode", SchemaType.Bool, "Make stepIn step over external code and runtime internals."),
+        property("externalCodePaths", STRINGS, "Directories whose sources justMyCode treats as external."),
+        property("stopOnExec", SchemaType.Bool, "Stop when the debuggee execs a new program."),
+        property("trackAllocations", SchemaType.Bool, "Record live heap allocations with their backtraces."),
+        property("signals", SchemaType.MapOf(SchemaType.OneOf(listOf("pass", "stop", "ignore"))),
+            "What happens when the debuggee receives each named signal, as {\"SIGUSR1\": \"pass\"}."),
+        property("initScript", SchemaType.Str, "Lua kdap.init script to load at launch or attach."),
+        property("visualizers", STRINGS, "Natvis files, or directories of them, describing how to show user types."),
+    )
+
+    /** Properties of `launch` only ([LaunchRequestArguments]). */
+    val launchOnly: List<ConfigurationProperty> = listOf(
+        property("noDebug", SchemaType.Bool, "Run the program without debugging."),
+        property("program", SchemaType.Str, "Path to the program to debug."),
+        property("coreDump", SchemaType.Str, "Path to a core dump to debug post-mortem instead of launching the program."),
+        property("args", STRINGS, "Program arguments."),
+        property("cwd", SchemaType.Str, "Program working directory."),
+        property("env", SchemaType.MapOf(SchemaType.Str), "Additional environment variables; \${env:NAME} in a value expands to NAME."),
+        property("envFile", SchemaType.Str, "Dotenv file to read environment variables from; env overrides them."),
+        property("stdio", SchemaType.AnyOf(listOf(SchemaType.Str, SchemaType.ArrayOf(SchemaType.AnyOf(listOf(SchemaType.Str, SchemaType.Null))))),
+            "File for all of stdin, stdout, and stderr, or a list of one per stream (null: the terminal)."),
+        property("pty", SchemaType.Bool, "Connect unspecified stdio streams to a pseudo-terminal owned by the adapter."),
+        property("record", SchemaType.Bool, "Record the debuggee under rr and debug the recording, with reverse execution."),
+        property("requireDebugInfo", SchemaType.Bool, "Refuse to launch a program without debug info.", true),
+        property("wasmRuntime", SchemaType.Str, "wasmtime to run a WebAssembly program under."),
+        property("stopOnEntry", SchemaType.AnyOf(listOf(SchemaType.Bool, SchemaType.OneOf(listOf("entry", "main")))),
+            "Stop after launch: at the program's entry point (true or \"entry\") or at main (\"main\")."),
+        property("terminal", SchemaType.AnyOf(listOf(SchemaType.OneOf(listOf("integrated", "external", "console")), SchemaType.Str, SchemaType.Integer)),
+            "Terminal for the debuggee, or the TTY path or process ID of one."),
+        property("console", SchemaType.OneOf(listOf("integratedTerminal", "externalTerminal", "internalConsole")),
+            "Terminal for the debuggee (compatibility alias of terminal)."),
+        commands("targetCreateCommands", "Commands that create the debug target."),
+        commands("processCreateCommands", "Commands that create the debuggee process."),
+    )
+
+    /** The `android` properties of `attach` ([AndroidAttachOptions]). */
+    val android: List<ConfigurationProperty> = listOf(
+        property("package", SchemaType.Str, "Package name of the app; its process must be running."),
+        property("device", SchemaType.Str, "Serial of the device or emulator, as adb devices lists it."),
+        property("adb", SchemaType.Str, "Path to adb; found in the Android SDK or on PATH if not set."),
+        property("ndk", SchemaType.Str, "Android NDK directory."),
+        property("server", SchemaType.Str, "Host path of the lldb-server or gdbserver to run on the device."),
+        property("port", SchemaType.Integer, "Device port gdbserver listens on."),
+        property("symbols", STRINGS, "Host directories with the app's unstripped native libraries."),
+    )
+
+    /** Properties of `attach` only ([AttachRequestArguments]). */
+    val attachOnly: List<ConfigurationProperty> = listOf(
+        property("program", SchemaType.Str, "Path to the program to attach to."),
+        property("pid", SchemaType.AnyOf(listOf(SchemaType.Integer, SchemaType.Str)),
+            "Process ID to attach to, or a process name pattern (* and ? wildcards) matching one running process."),
+        property("gdbRemote", SchemaType.Str, "Address of a gdbserver to attach to: host:port, or an LLDB connect URL."),
+        property("android", SchemaType.ObjectOf(android, required = listOf("package")), "Android app to attach to over adb."),
+        property("waitFor", SchemaType.Bool, "Wait for the process to launch (macOS only)."),
+        property("stopOnEntry", SchemaType.Bool, "Stop the debuggee after attaching."),
+        commands("targetCreateCommands", "Commands that create the debug target."),
+        commands("processCreateCommands", "Commands that create the debuggee process."),
+    )
+
+    /** The properties of [request] (`launch` or `attach`), in the order they are listed. */
+    fun properties(request: String): List<ConfigurationProperty> =
+        (if (request == "attach") attachOnly else launchOnly) + common
+
+    /** The JSON Schema of [request]'s arguments. */
+    fun jsonSchema(request: String): JSONObject = SchemaType.ObjectOf(properties(request)).toJsonObject()
+
+    /** The `configurationAttributes` of a VS Code debugger contribution: `launch` and `attach` schemas. */
+    fun configurationAttributes(): JSONObject =
+        JSONObject().put("launch", jsonSchema("launch")).put("attach", jsonSchema("attach"))
+
+    /**
+     * The problems of [arguments], the arguments of a [request] (`launch`
+     * or `attach`), as messages naming the property; empty if every
+     * property it knows has the right type.
+     */
+    fun validate(request: String, arguments: JSONObject): List<String> =
+        propertyProblems(properties(request), arguments, "")
+}
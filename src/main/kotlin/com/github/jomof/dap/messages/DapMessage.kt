@@ -70,6 +70,25 @@ enum class DisplayFormat {
     }
 }
 
+/**
+ * When watch expressions are evaluated again (KDAP extension).
+ *
+ * - [Stop]: at every stop
+ * - [Frame]: only when the frame a watch was evaluated in changed
+ */
+enum class WatchRefresh {
+    Stop,
+    Frame;
+
+    companion object {
+        fun fromJson(value: String?): WatchRefresh? = when (value) {
+            "stop" -> Stop
+            "frame" -> Frame
+            else -> null
+        }
+    }
+}
+
 /** When to show disassembly. */
 enum class ShowDisassembly {
     /** Always show disassembly, even if source is available. */
@@ -184,6 +203,53 @@ enum class ConsoleKind {
     }
 }
 
+/**
+ * Where a launch first stops (`stopOnEntry`; `"main"` is a KDAP
+ * extension).
+ *
+ * - [Entry]: the program's first instruction (`true` or `"entry"`)
+ * - [Main]: `main`, when the program has one, else as [Entry] (`"main"`)
+ */
+enum class EntryStop {
+    Entry,
+    Main;
+
+    companion object {
+        fun fromJson(value: Any?): EntryStop? = when (value) {
+            true, "entry" -> Entry
+            "main" -> Main
+            else -> null
+        }
+    }
+
+    /** Serializes back to the JSON representation. */
+    fun toJsonValue(): Any = when (this) {
+        Entry -> true
+        Main -> "main"
+    }
+}
+
+/**
+ * What happens when the debuggee receives a signal, per entry of
+ * `signals` and `kdap/signals` (KDAP extension).
+ *
+ * - [Pass]: delivered to the debuggee without stopping (`"pass"`)
+ * - [Stop]: stops the session, and is delivered on continue (`"stop"`)
+ * - [Ignore]: discarded without stopping; the debuggee never sees it (`"ignore"`)
+ */
+enum class SignalDisposition {
+    Pass, Stop, Ignore;
+
+    companion object {
+        fun fromJson(value: String?): SignalDisposition? = when (value) {
+            "pass" -> Pass
+            "stop" -> Stop
+            "ignore" -> Ignore
+            else -> null
+        }
+    }
+}
+
 /** The default evaluator type used for expressions. */
 enum class Expressions {
     Simple, Python, Native;
@@ -198,6 +264,48 @@ enum class Expressions {
     }
 }
 
+/**
+ * Which statics the Statics scope lists (KDAP extension).
+ *
+ * - [Unit]: those of the frame's compile unit
+ * - [All]: those of every module too
+ */
+enum class StaticsScope {
+    Unit,
+    All;
+
+    companion object {
+        fun fromJson(value: String?): StaticsScope? = when (value) {
+            "unit" -> Unit
+            "all" -> All
+            else -> null
+        }
+    }
+}
+
+/**
+ * How stack traces show runtime internals: frames of the Rust standard
+ * library, panic machinery, allocator, and libc (KDAP extension).
+ *
+ * - [Show]: as lldb-dap reports them
+ * - [Subtle]: de-emphasized, so clients select the first user frame
+ * - [Collapse]: each run of them replaced by one `[std internals]` label
+ */
+enum class InternalFrames {
+    Show,
+    Subtle,
+    Collapse;
+
+    companion object {
+        fun fromJson(value: String?): InternalFrames? = when (value) {
+            "show" -> Show
+            "subtle" -> Subtle
+            "collapse" -> Collapse
+            else -> null
+        }
+    }
+}
+
 /**
  * Internal adapter settings passed via `_adapterSettings` in the launch
  * configuration.
@@ -217,6 +325,20 @@ data class AdapterSettings(
     val scriptConfig: Map<String, Any?>? = null,
     val evaluateForHovers: Boolean? = null,
     val commandCompletions: Boolean? = null,
+    /** When watch expressions are evaluated again (KDAP extension). */
+    val watchRefresh: WatchRefresh? = null,
+    /** Elements rendered at most when copying a value (KDAP extension). */
+    val clipboardMaxElements: Int? = null,
+    /** Which statics the Statics scope lists (KDAP extension). */
+    val staticsScope: StaticsScope? = null,
+    /** How stack traces show runtime internals (KDAP extension). */
+    val internalFrames: InternalFrames? = null,
+    /** How deep containers' children are compared for changed values (KDAP extension). */
+    val changedValuesDepth: Int? = null,
+    /** Levels a variable may be expanded below its scope (KDAP extension). */
+    val maxExpansionDepth: Int? = null,
+    /** Bytes of a string shown in its summary (KDAP extension). */
+    val stringPreviewLength: Int? = null,
 ) {
     companion object {
         fun fromJson(obj: JSONObject?): AdapterSettings? {
@@ -234,6 +356,13 @@ data class AdapterSettings(
                 scriptConfig = obj.optJSONObject("scriptConfig")?.toStringAnyMap(),
                 evaluateForHovers = obj.optNullableBoolean("evaluateForHovers"),
                 commandCompletions = obj.optNullableBoolean("commandCompletions"),
+                watchRefresh = WatchRefresh.fromJson(obj.optString("watchRefresh", null)),
+                clipboardMaxElements = if (obj.has("clipboardMaxElements")) obj.optInt("clipboardMaxElements") else null,
+                staticsScope = StaticsScope.fromJson(obj.optString("staticsScope", null)),
+                internalFrames = InternalFrames.fromJson(obj.optString("internalFrames", null)),
+                changedValuesDepth = if (obj.has("changedValuesDepth")) obj.optInt("changedValuesDepth") else null,
+                maxExpansionDepth = if (obj.has("maxExpansionDepth")) obj.optInt("maxExpansionDepth") else null,
+                stringPreviewLength = if (obj.has("stringPreviewLength")) obj.optInt("stringPreviewLength") else null,
             )
         }
     }
@@ -248,6 +377,39 @@ data class CommonLaunchFields(
     val name: String? = null,
     /** Source path remapping. Each entry maps a remote prefix to a local prefix. */
     val sourceMap: Map<String, String?>? = null,
+    /**
+     * Local copy of the target's root filesystem, where shared libraries of
+     * a cross-compiled or remote debuggee are found (KDAP extension).
+     */
+    val sysroot: String? = null,
+    /**
+     * Directories searched for the debuggee's shared libraries, like GDB's
+     * `solib-search-path` (KDAP extension; also accepted under that name).
+     */
+    val solibSearchPath: List<String>? = null,
+    /**
+     * Directories searched for separate debug info by `.gnu_debuglink`
+     * name and build ID, like GDB's `debug-file-directory`, and for split
+     * DWARF `.dwo` files and `.dwp` packages (KDAP extension). Defaults to
+     * `/usr/lib/debug`.
+     */
+    val debugFileDirectories: List<String>? = null,
+    /**
+     * debuginfod servers to download missing debug info from (KDAP
+     * extension). Defaults to `DEBUGINFOD_URLS`; empty disables downloads.
+     */
+    val debuginfodUrls: List<String>? = null,
+    /**
+     * Keep LLDB's symbol indexes on disk between sessions (KDAP
+     * extension). Defaults to `true`.
+     */
+    val symbolIndexCache: Boolean? = null,
+    /**
+     * URL templates of servers to fetch sources not on disk from, with
+     * `{buildId}` and `{path}` placeholders (KDAP extension). Defaults to
+     * the source endpoints of the debuginfod servers.
+     */
+    val sourceServers: List<String>? = null,
     /** The default evaluator type used for expressions. */
     val expressions: Expressions? = null,
     /** Initialization commands executed upon debugger startup. */
@@ -276,12 +438,54 @@ data class CommonLaunchFields(
     val breakpointMode: BreakpointMode? = null,
     /** Internal adapter settings. */
     val adapterSettings: AdapterSettings? = null,
+    /** Attach to child processes the debuggee forks or spawns, each in a new debug session. */
+    val followChildProcesses: Boolean? = null,
+    /** List live tokio tasks as extra threads with their await stacks (KDAP extension). */
+    val tokioTasks: Boolean? = null,
+    /** Make `stepIn` step over external code and runtime internals (KDAP extension). */
+    val justMyCode: Boolean? = null,
+    /**
+     * Directories whose sources `justMyCode` treats as external, besides
+     * Cargo's registry and git checkouts (KDAP extension).
+     */
+    val externalCodePaths: List<String>? = null,
+    /**
+     * Stop when the debuggee execs a new program, or, for a followed
+     * child, right after the exec it was spawned with (KDAP extension).
+     */
+    val stopOnExec: Boolean? = null,
+    /**
+     * Record live heap allocations with their backtraces, for the
+     * `kdap/heap` request (KDAP extension).
+     */
+    val trackAllocations: Boolean? = null,
+    /**
+     * Signal dispositions by signal name, as `{"SIGUSR1": "pass"}`
+     * (KDAP extension). Signals not listed keep LLDB's defaults.
+     */
+    val signals: Map<String, SignalDisposition>? = null,
+    /**
+     * Lua `kdap.init` script of summaries, stop hooks, and console
+     * commands to load at launch or attach (KDAP extension).
+     */
+    val initScript: String? = null,
+    /**
+     * Natvis files, or directories of them, describing how to show user
+     * types (KDAP extension).
+     */
+    val visualizers: List<String>? = null,
 ) {
     companion object {
         fun fromJson(obj: JSONObject): CommonLaunchFields {
             return CommonLaunchFields(
                 name = obj.optString("name", null),
                 sourceMap = obj.optJSONObject("sourceMap")?.toStringNullableStringMap(),
+                sysroot = obj.optString("sysroot", null),
+                solibSearchPath = obj.optStringList("solibSearchPath") ?: obj.optStringList("solib-search-path"),
+                debugFileDirectories = obj.optStringList("debugFileDirectories"),
+                debuginfodUrls = obj.optStringList("debuginfodUrls"),
+                symbolIndexCache = obj.optNullableBoolean("symbolIndexCache"),
+                sourceServers = obj.optStringList("sourceServers"),
                 expressions = Expressions.fromJson(obj.optString("expressions", null)),
                 initCommands = obj.optStringList("initCommands"),
                 preRunCommands = obj.optStringList("preRunCommands"),
@@ -294,6 +498,17 @@ data class CommonLaunchFields(
                 relativePathBase = obj.optString("relativePathBase", null),
                 breakpointMode = BreakpointMode.fromJson(obj.optString("breakpointMode", null)),
                 adapterSettings = AdapterSettings.fromJson(obj.optJSONObject("_adapterSettings")),
+                followChildProcesses = obj.optNullableBoolean("followChildProcesses"),
+                tokioTasks = obj.optNullableBoolean("tokioTasks"),
+                justMyCode = obj.optNullableBoolean("justMyCode"),
+                externalCodePaths = obj.optStringList("externalCodePaths"),
+                stopOnExec = obj.optNullableBoolean("stopOnExec"),
+                trackAllocations = obj.optNullableBoolean("trackAllocations"),
+                signals = obj.optJSONObject("signals")?.toStringStringMap()
+                    ?.mapNotNull { (name, value) -> SignalDisposition.fromJson(value)?.let { name to it } }
+                    ?.toMap(),
+                initScript = obj.optString("initScript", null),
+                visualizers = obj.optStringList("visualizers"),
             )
         }
 
@@ -323,13 +538,18 @@ data class LaunchRequestArguments(
     val noDebug: Boolean? = null,
     /** Path to the program to debug. */
     val program: String? = null,
+    /**
+     * Path to a core dump (ELF core or minidump) to debug post-mortem
+     * instead of launching [program] (KDAP extension).
+     */
+    val coreDump: String? = null,
     /** Program arguments. */
     val args: List<String>? = null,
     /** Program working directory. */
     val cwd: String? = null,
-    /** Additional environment variables. */
+    /** Additional environment variables; `${env:NAME}` in a value expands to `NAME`. */
     val env: Map<String, String>? = null,
-    /** File to read the environment variables from. */
+    /** Dotenv file to read environment variables from; [env] overrides them. */
     val envFile: String? = null,
     /**
      * Destination for stdio streams:
@@ -338,8 +558,28 @@ data class LaunchRequestArguments(
      * - A list = individual paths for stdin, stdout, stderr
      */
     val stdio: Either<String, List<String?>>? = null,
-    /** Automatically stop debuggee after launch. */
-    val stopOnEntry: Boolean? = null,
+    /**
+     * Connect unspecified stdio streams to a pseudo-terminal owned by KDAP
+     * and report its output in order, in place of a client terminal.
+     */
+    val pty: Boolean? = null,
+    /**
+     * Record the debuggee under `rr` and debug the recording, with reverse
+     * execution (KDAP extension).
+     */
+    val record: Boolean? = null,
+    /**
+     * Refuse to launch a [program] without debug info (KDAP extension;
+     * default `true`).
+     */
+    val requireDebugInfo: Boolean? = null,
+    /**
+     * `wasmtime` to run a WebAssembly [program] under (KDAP extension;
+     * found on `PATH` or in `~/.wasmtime/bin` if not set).
+     */
+    val wasmRuntime: String? = null,
+    /** Automatically stop debuggee after launch: at its entry point or at `main`. */
+    val stopOnEntry: EntryStop? = null,
     /** Terminal type to use. */
     val terminal: TerminalKind? = null,
     /** Terminal type to use (compatibility alias of [terminal]). */
@@ -359,12 +599,17 @@ data class LaunchRequestArguments(
                 common = CommonLaunchFields.fromJson(obj),
                 noDebug = obj.optNullableBoolean("noDebug"),
                 program = obj.optString("program", null),
+                coreDump = obj.optString("coreDump", null),
                 args = obj.optStringList("args"),
                 cwd = obj.optString("cwd", null),
                 env = obj.optJSONObject("env")?.toStringStringMap(),
                 envFile = obj.optString("envFile", null),
                 stdio = parseStdio(obj),
-                stopOnEntry = obj.optNullableBoolean("stopOnEntry"),
+                pty = obj.optNullableBoolean("pty"),
+                record = obj.optNullableBoolean("record"),
+                requireDebugInfo = obj.optNullableBoolean("requireDebugInfo"),
+                wasmRuntime = obj.optString("wasmRuntime", null),
+                stopOnEntry = EntryStop.fromJson(obj.opt("stopOnEntry")),
                 terminal = parseTerminal(obj),
                 console = ConsoleKind.fromJson(obj.optString("console", null)),
                 targetCreateCommands = obj.optStringList("targetCreateCommands"),
@@ -405,6 +650,12 @@ data class LaunchRequestArguments(
                 for ((k, v) in map) put(k, v ?: JSONObject.NULL)
             })
         }
+        common.sysroot?.let { put("sysroot", it) }
+        common.solibSearchPath?.let { put("solibSearchPath", JSONArray(it)) }
+        common.debugFileDirectories?.let { put("debugFileDirectories", JSONArray(it)) }
+        common.debuginfodUrls?.let { put("debuginfodUrls", JSONArray(it)) }
+        common.symbolIndexCache?.let { put("symbolIndexCache", it) }
+        common.sourceServers?.let { put("sourceServers", JSONArray(it)) }
         common.expressions?.let { put("expressions", it.name.lowercase()) }
         common.initCommands?.let { put("initCommands", JSONArray(it)) }
         common.preRunCommands?.let { put("preRunCommands", JSONArray(it)) }
@@ -421,11 +672,23 @@ data class LaunchRequestArguments(
         common.reverseDebugging?.let { put("reverseDebugging", it) }
         common.relativePathBase?.let { put("relativePathBase", it) }
         common.breakpointMode?.let { put("breakpointMode", it.name.lowercase()) }
+        common.followChildProcesses?.let { put("followChildProcesses", it) }
+        common.tokioTasks?.let { put("tokioTasks", it) }
+        common.justMyCode?.let { put("justMyCode", it) }
+        common.externalCodePaths?.let { put("externalCodePaths", JSONArray(it)) }
+        common.stopOnExec?.let { put("stopOnExec", it) }
+        common.trackAllocations?.let { put("trackAllocations", it) }
+        common.initScript?.let { put("initScript", it) }
+        common.visualizers?.let { put("visualizers", JSONArray(it)) }
+        common.signals?.let { signals ->
+            put("signals", JSONObject(signals.mapValues { it.value.name.lowercase() }))
+        }
         // _adapterSettings intentionally omitted from serialization (internal)
 
         // LaunchRequestArguments fields
         noDebug?.let { put("noDebug", it) }
         program?.let { put("program", it) }
+        coreDump?.let { put("coreDump", it) }
         args?.let { put("args", JSONArray(it)) }
         cwd?.let { put("cwd", it) }
         env?.let { map ->
@@ -444,7 +707,11 @@ data class LaunchRequestArguments(
                 }
             }
         }
-        stopOnEntry?.let { put("stopOnEntry", it) }
+        pty?.let { put("pty", it) }
+        record?.let { put("record", it) }
+        requireDebugInfo?.let { put("requireDebugInfo", it) }
+        wasmRuntime?.let { put("wasmRuntime", it) }
+        stopOnEntry?.let { put("stopOnEntry", it.toJsonValue()) }
         terminal?.let { put("terminal", it.toJsonValue()) }
         console?.let { put("console", it.toJsonValue()) }
         targetCreateCommands?.let { put("targetCreateCommands", JSONArray(it)) }
@@ -452,6 +719,55 @@ data class LaunchRequestArguments(
     }
 }
 
+/**
+ * The `android` object of an `attach` request: which app to attach to
+ * over `adb`, and where the tools and symbols are (KDAP extension; see
+ * `Android.kt`).
+ */
+data class AndroidAttachOptions(
+    /** Package name of the app, e.g. `com.example.app`; its process must be running. */
+    val packageName: String,
+    /** Serial of the device or emulator, as `adb devices` lists it; `adb`'s default if not set. */
+    val device: String? = null,
+    /** Path to `adb`; found in the Android SDK or on `PATH` if not set. */
+    val adb: String? = null,
+    /** Android NDK directory; found from `ANDROID_NDK_HOME`, `ANDROID_NDK_ROOT`, or the SDK if not set. */
+    val ndk: String? = null,
+    /** Host path of the `lldb-server` or `gdbserver` to run on the device; the NDK's `lldb-server` if not set. */
+    val server: String? = null,
+    /** Device port `gdbserver` listens on (`lldb-server` uses an abstract socket instead). */
+    val port: Int? = null,
+    /** Host directories with the app's unstripped native libraries, searched before the device's copies. */
+    val symbols: List<String>? = null,
+) {
+    companion object {
+        /** Parses an [AndroidAttachOptions], or `null` if [obj] is absent or names no package. */
+        fun fromJson(obj: JSONObject?): AndroidAttachOptions? {
+            val packageName = obj?.optString("package", null) ?: return null
+            return AndroidAttachOptions(
+                packageName = packageName,
+                device = obj.optString("device", null),
+                adb = obj.optString("adb", null),
+                ndk = obj.optString("ndk", null),
+                server = obj.optString("server", null),
+                port = if (obj.has("port")) obj.optInt("port") else null,
+                symbols = obj.optStringList("symbols"),
+            )
+        }
+    }
+
+    /** Serializes these options as the `android` object of an attach request. */
+    fun toJsonObject(): JSONObject = JSONObject().apply {
+        put("package", packageName)
+        device?.let { put("device", it) }
+        adb?.let { put("adb", it) }
+        ndk?.let { put("ndk", it) }
+        server?.let { put("server", it) }
+        port?.let { put("port", it) }
+        symbols?.let { put("symbols", JSONArray(it)) }
+    }
+}
+
 /**
  * Arguments for the DAP `attach` request.
  *
@@ -461,12 +777,29 @@ data class AttachRequestArguments(
     val common: CommonLaunchFields = CommonLaunchFields(),
     /** Path to the program to attach to. */
     val program: String? = null,
-    /** Process id to attach to (number or string). */
+    /**
+     * Process to attach to. A number (or numeric string) is a process id;
+     * any other string is a process name pattern (`*` and `?` wildcards)
+     * that must match exactly one running process.
+     */
     val pid: Either<Long, String>? = null,
+    /**
+     * Address of a `gdbserver` or `lldb-server gdbserver` to attach to over
+     * the GDB remote protocol: `host:port`, or an LLDB connect URL such as
+     * `connect://host:port` (KDAP extension).
+     */
+    val gdbRemote: String? = null,
+    /** Android app to attach to over `adb` (KDAP extension). */
+    val android: AndroidAttachOptions? = null,
     /** Wait for the process to launch (macOS only). */
     val waitFor: Boolean? = null,
     /** Automatically stop debuggee after attach. */
     val stopOnEntry: Boolean? = null,
+    /**
+     * Set by KDAP on the attach configuration of a followed child that has
+     * already exec'd, as after `posix_spawn` (see [CommonLaunchFields.stopOnExec]).
+     */
+    val childExeced: Boolean? = null,
     /** Commands that create the debug target. */
     val targetCreateCommands: List<String>? = null,
     /** Commands that create the debuggee process. */
@@ -482,8 +815,11 @@ data class AttachRequestArguments(
                 common = CommonLaunchFields.fromJson(obj),
                 program = obj.optString("program", null),
                 pid = parsePid(obj),
+                gdbRemote = obj.optString("gdbRemote", null),
+                android = AndroidAttachOptions.fromJson(obj.optJSONObject("android")),
                 waitFor = obj.optNullableBoolean("waitFor"),
                 stopOnEntry = obj.optNullableBoolean("stopOnEntry"),
+                childExeced = obj.optNullableBoolean("__childExeced"),
                 targetCreateCommands = obj.optStringList("targetCreateCommands"),
                 processCreateCommands = obj.optStringList("processCreateCommands"),
             )
@@ -499,6 +835,51 @@ data class AttachRequestArguments(
             }
         }
     }
+
+    /**
+     * Serializes this [AttachRequestArguments] to a [JSONObject]. Only the
+     * general [common] fields are flattened; see [LaunchRequestArguments.toJsonObject].
+     */
+    fun toJsonObject(): JSONObject = JSONObject().apply {
+        common.name?.let { put("name", it) }
+        common.sysroot?.let { put("sysroot", it) }
+        common.solibSearchPath?.let { put("solibSearchPath", JSONArray(it)) }
+        common.debugFileDirectories?.let { put("debugFileDirectories", JSONArray(it)) }
+        common.debuginfodUrls?.let { put("debuginfodUrls", JSONArray(it)) }
+        common.symbolIndexCache?.let { put("symbolIndexCache", it) }
+        common.sourceServers?.let { put("sourceServers", JSONArray(it)) }
+        common.initCommands?.let { put("initCommands", JSONArray(it)) }
+        common.preRunCommands?.let { put("preRunCommands", JSONArray(it)) }
+        common.postRunCommands?.let { put("postRunCommands", JSONArray(it)) }
+        common.preTerminateCommands?.let { put("preTerminateCommands", JSONArray(it)) }
+        common.exitCommands?.let { put("exitCommands", JSONArray(it)) }
+        common.followChildProcesses?.let { put("followChildProcesses", it) }
+        common.tokioTasks?.let { put("tokioTasks", it) }
+        common.justMyCode?.let { put("justMyCode", it) }
+        common.externalCodePaths?.let { put("externalCodePaths", JSONArray(it)) }
+        common.stopOnExec?.let { put("stopOnExec", it) }
+        common.trackAllocations?.let { put("trackAllocations", it) }
+        common.initScript?.let { put("initScript", it) }
+        common.visualizers?.let { put("visualizers", JSONArray(it)) }
+        common.signals?.let { signals ->
+            put("signals", JSONObject(signals.mapValues { it.value.name.lowercase() }))
+        }
+
+        program?.let { put("program", it) }
+        pid?.let { p ->
+            when (p) {
+                is Either.First<*> -> put("pid", p.value)
+                is Either.Second<*> -> put("pid", p.value)
+            }
+        }
+        gdbRemote?.let { put("gdbRemote", it) }
+        android?.let { put("android", it.toJsonObject()) }
+        waitFor?.let { put("waitFor", it) }
+        stopOnEntry?.let { put("stopOnEntry", it) }
+        childExeced?.let { put("__childExeced", it) }
+        targetCreateCommands?.let { put("targetCreateCommands", JSONArray(it)) }
+        processCreateCommands?.let { put("processCreateCommands", JSONArray(it)) }
+    }
 }
 
 // ══════════════════════════════════════════════════════════════════════
@@ -605,51 +986,180 @@ sealed class DapMessage {
                     expression = args?.optString("expression", "") ?: "",
                     context = args?.optString("context", null),
                     frameId = if (args?.has("frameId") == true) args.optInt("frameId") else null,
+                    hex = args?.optJSONObject("format")?.optNullableBoolean("hex"),
                 )
                 // Standard DAP commands (forwarded, minimal fields)
                 "initialize" -> InitializeRequest(
                     seq = seq,
                     supportsRunInTerminalRequest = args?.optBoolean("supportsRunInTerminalRequest", false) ?: false,
+                    supportsProgressReporting = args?.optBoolean("supportsProgressReporting", false) ?: false,
+                )
+                "attach" -> AttachRequest(
+                    seq = seq,
+                    arguments = if (args != null) AttachRequestArguments.fromJson(args) else AttachRequestArguments(),
                 )
-                "attach" -> AttachRequest(seq)
                 "restart" -> RestartRequest(seq)
-                "disconnect" -> DisconnectRequest(seq)
+                "disconnect" -> DisconnectRequest(
+                    seq = seq,
+                    restart = args?.optNullableBoolean("restart"),
+                    terminateDebuggee = args?.optNullableBoolean("terminateDebuggee"),
+                    suspendDebuggee = args?.optNullableBoolean("suspendDebuggee"),
+                )
                 "terminate" -> TerminateRequest(seq)
-                "setBreakpoints" -> SetBreakpointsRequest(seq)
-                "setFunctionBreakpoints" -> SetFunctionBreakpointsRequest(seq)
-                "setExceptionBreakpoints" -> SetExceptionBreakpointsRequest(seq)
-                "setDataBreakpoints" -> SetDataBreakpointsRequest(seq)
-                "setInstructionBreakpoints" -> SetInstructionBreakpointsRequest(seq)
+                "setBreakpoints" -> SetBreakpointsRequest(
+                    seq = seq,
+                    source = Source.fromJson(args?.optJSONObject("source")),
+                    breakpoints = args?.optJSONArray("breakpoints")?.let { arr ->
+                        (0 until arr.length()).map { SourceBreakpoint.fromJson(arr.getJSONObject(it)) }
+                    } ?: emptyList(),
+                    sourceModified = args?.optNullableBoolean("sourceModified"),
+                )
+                "setFunctionBreakpoints" -> SetFunctionBreakpointsRequest(
+                    seq = seq,
+                    breakpoints = args?.optJSONArray("breakpoints")?.let { arr ->
+                        (0 until arr.length()).map { FunctionBreakpoint.fromJson(arr.getJSONObject(it)) }
+                    } ?: emptyList(),
+                )
+                "setExceptionBreakpoints" -> SetExceptionBreakpointsRequest(
+                    seq = seq,
+                    filters = args?.optJSONArray("filters")?.let { arr ->
+                        (0 until arr.length()).map { arr.getString(it) }
+                    } ?: emptyList(),
+                    filterOptions = args?.optJSONArray("filterOptions")?.let { arr ->
+                        (0 until arr.length()).map { ExceptionFilterOptions.fromJson(arr.getJSONObject(it)) }
+                    } ?: emptyList(),
+                )
+                "setDataBreakpoints" -> SetDataBreakpointsRequest(
+                    seq = seq,
+                    breakpoints = args?.optJSONArray("breakpoints")?.let { arr ->
+                        (0 until arr.length()).map { DataBreakpoint.fromJson(arr.getJSONObject(it)) }
+                    } ?: emptyList(),
+                )
+                "setInstructionBreakpoints" -> SetInstructionBreakpointsRequest(
+                    seq = seq,
+                    breakpoints = args?.optJSONArray("breakpoints")?.let { arr ->
+                        (0 until arr.length()).map { InstructionBreakpoint.fromJson(arr.getJSONObject(it)) }
+                    } ?: emptyList(),
+                )
                 "configurationDone" -> ConfigurationDoneRequest(seq)
-                "continue" -> ContinueRequest(seq)
-                "next" -> NextRequest(seq)
-                "stepIn" -> StepInRequest(seq)
-                "stepOut" -> StepOutRequest(seq)
-                "stepBack" -> StepBackRequest(seq)
-                "reverseContinue" -> ReverseContinueRequest(seq)
+                "continue" -> ContinueRequest(
+                    seq = seq,
+                    threadId = if (args?.has("threadId") == true) args.optInt("threadId") else null,
+                    singleThread = args?.optNullableBoolean("singleThread"),
+                )
+                "next" -> NextRequest(
+                    seq = seq,
+                    threadId = args?.optInt("threadId", 0) ?: 0,
+                    singleThread = args?.optNullableBoolean("singleThread"),
+                    granularity = args?.optString("granularity", null),
+                )
+                "stepIn" -> StepInRequest(
+                    seq = seq,
+                    threadId = args?.optInt("threadId", 0) ?: 0,
+                    singleThread = args?.optNullableBoolean("singleThread"),
+                    targetId = if (args?.has("targetId") == true) args.optInt("targetId") else null,
+                    granularity = args?.optString("granularity", null),
+                )
+                "stepOut" -> StepOutRequest(
+                    seq = seq,
+                    threadId = args?.optInt("threadId", 0) ?: 0,
+                    singleThread = args?.optNullableBoolean("singleThread"),
+                    granularity = args?.optString("granularity", null),
+                )
+                "stepBack" -> StepBackRequest(
+                    seq = seq,
+                    threadId = args?.optInt("threadId", 0) ?: 0,
+                    granularity = args?.optString("granularity", null),
+                )
+                "reverseContinue" -> ReverseContinueRequest(seq, threadId = args?.optInt("threadId", 0) ?: 0)
                 "pause" -> PauseRequest(seq)
-                "restartFrame" -> RestartFrameRequest(seq)
+                "restartFrame" -> RestartFrameRequest(seq, frameId = args?.optInt("frameId", 0) ?: 0)
                 "threads" -> ThreadsRequest(seq)
-                "stackTrace" -> StackTraceRequest(seq)
-                "scopes" -> ScopesRequest(seq)
+                "stackTrace" -> StackTraceRequest(
+                    seq = seq,
+                    threadId = args?.optInt("threadId", 0) ?: 0,
+                    startFrame = if (args?.has("startFrame") == true) args.optInt("startFrame") else null,
+                    levels = if (args?.has("levels") == true) args.optInt("levels") else null,
+                )
+                "scopes" -> ScopesRequest(seq, frameId = args?.optInt("frameId", 0) ?: 0)
                 "variables" -> VariablesRequest(seq)
                 "setVariable" -> SetVariableRequest(seq)
-                "source" -> SourceRequest(seq)
-                "modules" -> ModulesRequest(seq)
-                "completions" -> CompletionsRequest(seq)
-                "exceptionInfo" -> ExceptionInfoRequest(seq)
+                "source" -> SourceRequest(
+                    seq = seq,
+                    sourceReference = args?.optJSONObject("source")?.optInt("sourceReference", 0)?.takeIf { it > 0 }
+                        ?: args?.optInt("sourceReference", 0) ?: 0,
+                )
+                "modules" -> ModulesRequest(
+                    seq = seq,
+                    startModule = if (args?.has("startModule") == true) args.optInt("startModule") else null,
+                    moduleCount = if (args?.has("moduleCount") == true) args.optInt("moduleCount") else null,
+                )
+                "completions" -> CompletionsRequest(
+                    seq = seq,
+                    text = args?.optString("text", "") ?: "",
+                    column = args?.optInt("column", 0) ?: 0,
+                    frameId = if (args?.has("frameId") == true) args.optInt("frameId") else null,
+                )
+                "exceptionInfo" -> ExceptionInfoRequest(seq, threadId = args?.optInt("threadId", 0) ?: 0)
                 "readMemory" -> ReadMemoryRequest(seq)
                 "writeMemory" -> WriteMemoryRequest(seq)
                 "disassemble" -> DisassembleRequest(seq)
                 "dataBreakpointInfo" -> DataBreakpointInfoRequest(seq)
-                "gotoTargets" -> GotoTargetsRequest(seq)
-                "goto" -> GotoRequest(seq)
-                "stepInTargets" -> StepInTargetsRequest(seq)
-                "cancel" -> CancelRequest(seq)
-                "breakpointLocations" -> BreakpointLocationsRequest(seq)
+                "gotoTargets" -> GotoTargetsRequest(
+                    seq = seq,
+                    source = Source.fromJson(args?.optJSONObject("source")),
+                    line = args?.optInt("line", 0) ?: 0,
+                    column = if (args?.has("column") == true) args.optInt("column") else null,
+                )
+                "goto" -> GotoRequest(
+                    seq = seq,
+                    threadId = args?.optInt("threadId", 0) ?: 0,
+                    targetId = args?.optInt("targetId", 0) ?: 0,
+                )
+                "stepInTargets" -> StepInTargetsRequest(seq, frameId = args?.optInt("frameId", 0) ?: 0)
+                "cancel" -> CancelRequest(
+                    seq = seq,
+                    requestId = if (args?.has("requestId") == true) args.optInt("requestId") else null,
+                    progressId = args?.optString("progressId", null),
+                )
+                "breakpointLocations" -> BreakpointLocationsRequest(
+                    seq = seq,
+                    source = Source.fromJson(args?.optJSONObject("source")),
+                    line = args?.optInt("line", 0) ?: 0,
+                    column = if (args?.has("column") == true) args.optInt("column") else null,
+                    endLine = if (args?.has("endLine") == true) args.optInt("endLine") else null,
+                    endColumn = if (args?.has("endColumn") == true) args.optInt("endColumn") else null,
+                )
                 "loadedSources" -> LoadedSourcesRequest(seq)
                 "terminateThreads" -> TerminateThreadsRequest(seq)
                 "setExpression" -> SetExpressionRequest(seq)
+                // KDAP custom requests
+                "kdap/stdin" -> StdinRequest(seq, text = args?.optString("text", "") ?: "")
+                "kdap/signals" -> SignalsRequest(seq,
+                    signals = args?.optJSONObject("signals")?.toStringStringMap() ?: emptyMap())
+                "kdap/stepIntoSkipped" -> StepIntoSkippedRequest(seq, threadId = args?.optInt("threadId", 0) ?: 0)
+                "kdap/searchMemory" -> SearchMemoryRequest(seq,
+                    bytes = args?.optString("bytes", null),
+                    string = args?.optString("string", null),
+                    maxResults = if (args?.has("maxResults") == true) args.optInt("maxResults") else null,
+                )
+                "kdap/memoryMap" -> MemoryMapRequest(seq)
+                "kdap/heap" -> HeapRequest(seq,
+                    address = args?.opt("address")?.takeIf { it != JSONObject.NULL }?.toString(),
+                    limit = if (args?.has("limit") == true) args.optInt("limit") else null,
+                )
+                "kdap/hotReplace" -> HotReplaceRequest(seq,
+                    objectFile = args?.optString("objectFile", "") ?: "",
+                    functions = args?.optStringList("functions"),
+                )
+                "kdap/breakpoints" -> BreakpointsRequest(seq,
+                    action = args?.optString("action", "") ?: "",
+                    path = args?.optString("path", "") ?: "",
+                )
+                "kdap/loadSymbols" -> LoadSymbolsRequest(seq,
+                    module = args?.optString("module", "") ?: "",
+                    path = args?.optString("path", "") ?: "",
+                )
                 // Reverse requests (adapter -> client)
                 "runInTerminal" -> {
                     val arguments = obj.optJSONObject("arguments")
@@ -688,7 +1198,18 @@ sealed class DapMessage {
                     output = body?.optString("output", "") ?: "",
                 )
                 // Standard DAP events (forwarded, minimal fields)
-                "stopped" -> StoppedEvent(seq)
+                "stopped" -> StoppedEvent(
+                    seq = seq,
+                    reason = body?.optString("reason", null),
+                    description = body?.optString("description", null),
+                    threadId = if (body?.has("threadId") == true) body.optInt("threadId") else null,
+                    preserveFocusHint = body?.optNullableBoolean("preserveFocusHint"),
+                    text = body?.optString("text", null),
+                    allThreadsStopped = body?.optNullableBoolean("allThreadsStopped"),
+                    hitBreakpointIds = body?.optJSONArray("hitBreakpointIds")?.let { arr ->
+                        (0 until arr.length()).map { arr.getInt(it) }
+                    },
+                )
                 "continued" -> ContinuedEvent(
                     seq = seq,
                     threadId = body?.optInt("threadId", 0) ?: 0,
@@ -702,9 +1223,21 @@ sealed class DapMessage {
                     seq = seq,
                     restart = if (body?.has("restart") == true) jsonValueToKotlin(body.get("restart")) else null,
                 )
-                "thread" -> ThreadEvent(seq)
-                "breakpoint" -> BreakpointEvent(seq)
-                "module" -> ModuleEvent(seq)
+                "thread" -> ThreadEvent(
+                    seq = seq,
+                    reason = body?.optString("reason", "") ?: "",
+                    threadId = body?.optInt("threadId", 0) ?: 0,
+                )
+                "breakpoint" -> BreakpointEvent(
+                    seq = seq,
+                    reason = body?.optString("reason", null),
+                    breakpoint = body?.optJSONObject("breakpoint")?.let { jsonObjectToMap(it) } ?: emptyMap(),
+                )
+                "module" -> ModuleEvent(
+                    seq = seq,
+                    reason = body?.optString("reason", null),
+                    module = body?.optJSONObject("module")?.let { jsonObjectToMap(it) } ?: emptyMap(),
+                )
                 "loadedSource" -> LoadedSourceEvent(seq)
                 "capabilities" -> CapabilitiesEvent(
                     seq = seq,
@@ -714,9 +1247,26 @@ sealed class DapMessage {
                 )
                 "invalidated" -> InvalidatedEvent(seq)
                 "memory" -> MemoryEvent(seq)
-                "progressStart" -> ProgressStartEvent(seq)
-                "progressUpdate" -> ProgressUpdateEvent(seq)
-                "progressEnd" -> ProgressEndEvent(seq)
+                "progressStart" -> ProgressStartEvent(
+                    seq = seq,
+                    progressId = body?.optString("progressId", "") ?: "",
+                    title = body?.optString("title", "") ?: "",
+                    requestId = if (body?.has("requestId") == true) body.optInt("requestId") else null,
+                    cancellable = body?.optNullableBoolean("cancellable"),
+                    message = body?.optString("message", null),
+                    percentage = body?.optNullableFloat("percentage"),
+                )
+                "progressUpdate" -> ProgressUpdateEvent(
+                    seq = seq,
+                    progressId = body?.optString("progressId", "") ?: "",
+                    message = body?.optString("message", null),
+                    percentage = body?.optNullableFloat("percentage"),
+                )
+                "progressEnd" -> ProgressEndEvent(
+                    seq = seq,
+                    progressId = body?.optString("progressId", "") ?: "",
+                    message = body?.optString("message", null),
+                )
                 // Narrow catch-all
                 else -> UnknownEvent(seq, event)
             }
@@ -820,12 +1370,192 @@ data class EvaluateRequest(
     val expression: String,
     val context: String?,
     val frameId: Int? = null,
+    /** `format.hex`: show integers in hexadecimal. */
+    val hex: Boolean? = null,
 ) : DapRequest() {
     override val command get() = "evaluate"
     override fun toJson(): String = buildRequestJson(JSONObject().apply {
         put("expression", expression)
         if (context != null) put("context", context)
         if (frameId != null) put("frameId", frameId)
+        if (hex != null) put("format", JSONObject().put("hex", hex))
+    })
+}
+
+/** DAP `Checksum`: a digest of a source's contents as the client has it. */
+data class Checksum(
+    /** `"MD5"`, `"SHA1"`, `"SHA256"`, or `"timestamp"`. */
+    val algorithm: String,
+    /** Hex digest, or for `"timestamp"` the modification time. */
+    val checksum: String,
+) {
+    fun toJsonObject(): JSONObject = JSONObject().put("algorithm", algorithm).put("checksum", checksum)
+
+    companion object {
+        fun fromJson(obj: JSONObject): Checksum =
+            Checksum(algorithm = obj.optString("algorithm"), checksum = obj.optString("checksum"))
+    }
+}
+
+/** DAP `Source` descriptor, as sent in breakpoint and stack frame payloads. */
+data class Source(
+    val name: String? = null,
+    val path: String? = null,
+    val sourceReference: Int? = null,
+    /** Checksums of the client's copy of the source. */
+    val checksums: List<Checksum>? = null,
+) {
+    /** Stable identity for per-source state: the path, else the name or reference. */
+    val key: String get() = path ?: name ?: "sourceReference:${sourceReference ?: 0}"
+
+    fun toJsonObject(): JSONObject = JSONObject().apply {
+        if (name != null) put("name", name)
+        if (path != null) put("path", path)
+        if (sourceReference != null) put("sourceReference", sourceReference)
+        if (checksums != null) put("checksums", JSONArray(checksums.map { it.toJsonObject() }))
+    }
+
+    companion object {
+        fun fromJson(obj: JSONObject?): Source {
+            if (obj == null) return Source()
+            return Source(
+                name = obj.optString("name", null),
+                path = obj.optString("path", null),
+                sourceReference = if (obj.has("sourceReference")) obj.optInt("sourceReference") else null,
+                checksums = obj.optJSONArray("checksums")?.let { array ->
+                    (0 until array.length()).mapNotNull { array.optJSONObject(it)?.let(Checksum::fromJson) }
+                },
+            )
+        }
+    }
+}
+
+/** DAP `SourceBreakpoint`: one entry of a `setBreakpoints` request. */
+data class SourceBreakpoint(
+    val line: Int,
+    val column: Int? = null,
+    /** Expression that must be truthy for the breakpoint to stop. */
+    val condition: String? = null,
+    /** Expression controlling how many hits are ignored. */
+    val hitCondition: String? = null,
+    /** If set, the breakpoint logs this message instead of stopping. */
+    val logMessage: String? = null,
+    /** Use a hardware breakpoint rather than a software one (KDAP extension). */
+    val hardware: Boolean? = null,
+) {
+    fun toJsonObject(): JSONObject = JSONObject().apply {
+        put("line", line)
+        if (column != null) put("column", column)
+        if (condition != null) put("condition", condition)
+        if (hitCondition != null) put("hitCondition", hitCondition)
+        if (logMessage != null) put("logMessage", logMessage)
+        if (hardware != null) put("hardware", hardware)
+    }
+
+    companion object {
+        fun fromJson(obj: JSONObject): SourceBreakpoint = SourceBreakpoint(
+            line = obj.optInt("line", 0),
+            column = if (obj.has("column")) obj.optInt("column") else null,
+            condition = obj.optString("condition", null),
+            hitCondition = obj.optString("hitCondition", null),
+            logMessage = obj.optString("logMessage", null),
+            hardware = obj.optNullableBoolean("hardware"),
+        )
+    }
+}
+
+/**
+ * DAP `setBreakpoints` request. KDAP takes over the parts of each
+ * [SourceBreakpoint] that lldb-dap cannot express (see
+ * [com.github.jomof.dap.debugsession.handleSetBreakpoints]).
+ */
+data class SetBreakpointsRequest(
+    override val seq: Int,
+    val source: Source = Source(),
+    val breakpoints: List<SourceBreakpoint> = emptyList(),
+    val sourceModified: Boolean? = null,
+) : DapRequest() {
+    override val command get() = "setBreakpoints"
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        put("source", source.toJsonObject())
+        put("breakpoints", JSONArray(breakpoints.map { it.toJsonObject() }))
+        if (sourceModified != null) put("sourceModified", sourceModified)
+    })
+}
+
+/** DAP `DataBreakpoint`: one entry of a `setDataBreakpoints` request. */
+data class DataBreakpoint(
+    /** Opaque ID from a `dataBreakpointInfo` response. */
+    val dataId: String,
+    /** `"read"`, `"write"`, or `"readWrite"`; `null` means `"write"`. */
+    val accessType: String? = null,
+    val condition: String? = null,
+    val hitCondition: String? = null,
+) {
+    fun toJsonObject(): JSONObject = JSONObject().apply {
+        put("dataId", dataId)
+        if (accessType != null) put("accessType", accessType)
+        if (condition != null) put("condition", condition)
+        if (hitCondition != null) put("hitCondition", hitCondition)
+    }
+
+    companion object {
+        fun fromJson(obj: JSONObject): DataBreakpoint = DataBreakpoint(
+            dataId = obj.optString("dataId", ""),
+            accessType = obj.optString("accessType", null),
+            condition = obj.optString("condition", null),
+            hitCondition = obj.optString("hitCondition", null),
+        )
+    }
+}
+
+/**
+ * DAP `setDataBreakpoints` request. KDAP relays it to lldb-dap and falls
+ * back to software watchpoints when hardware ones run out (see
+ * [com.github.jomof.dap.debugsession.handleSetDataBreakpoints]).
+ */
+data class SetDataBreakpointsRequest(
+    override val seq: Int,
+    val breakpoints: List<DataBreakpoint> = emptyList(),
+) : DapRequest() {
+    override val command get() = "setDataBreakpoints"
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        put("breakpoints", JSONArray(breakpoints.map { it.toJsonObject() }))
+    })
+}
+
+/** DAP `ExceptionFilterOptions`: an enabled filter with an optional condition. */
+data class ExceptionFilterOptions(
+    val filterId: String,
+    val condition: String? = null,
+) {
+    fun toJsonObject(): JSONObject = JSONObject().apply {
+        put("filterId", filterId)
+        if (condition != null) put("condition", condition)
+    }
+
+    companion object {
+        fun fromJson(obj: JSONObject): ExceptionFilterOptions = ExceptionFilterOptions(
+            filterId = obj.optString("filterId", ""),
+            condition = obj.optString("condition", null),
+        )
+    }
+}
+
+/**
+ * DAP `setExceptionBreakpoints` request. KDAP implements its own filters
+ * (Rust panics, signals) and forwards the rest to lldb-dap (see
+ * [com.github.jomof.dap.debugsession.handleSetExceptionBreakpoints]).
+ */
+data class SetExceptionBreakpointsRequest(
+    override val seq: Int,
+    val filters: List<String> = emptyList(),
+    val filterOptions: List<ExceptionFilterOptions> = emptyList(),
+) : DapRequest() {
+    override val command get() = "setExceptionBreakpoints"
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        put("filters", JSONArray(filters))
+        if (filterOptions.isNotEmpty()) put("filterOptions", JSONArray(filterOptions.map { it.toJsonObject() }))
     })
 }
 
@@ -835,18 +1565,29 @@ data class InitializeRequest(
     override val seq: Int = 0,
     /** Whether the client supports the `runInTerminal` reverse request. */
     val supportsRunInTerminalRequest: Boolean = false,
+    /** Whether the client supports `progressStart`/`progressUpdate`/`progressEnd` events. */
+    val supportsProgressReporting: Boolean = false,
 ) : DapRequest() {
     override val command get() = "initialize"
     override fun toJson(): String = buildRequestJson(JSONObject().apply {
         put("adapterID", "lldb")
         put("pathFormat", "path")
         if (supportsRunInTerminalRequest) put("supportsRunInTerminalRequest", true)
+        if (supportsProgressReporting) put("supportsProgressReporting", true)
     })
 }
 
-data class AttachRequest(override val seq: Int) : DapRequest() {
+/**
+ * DAP `attach` request. KDAP handles attach itself (see
+ * [com.github.jomof.dap.debugsession.handleAttach]) so the arguments
+ * are parsed in full.
+ */
+data class AttachRequest(
+    override val seq: Int = 0,
+    val arguments: AttachRequestArguments = AttachRequestArguments(),
+) : DapRequest() {
     override val command get() = "attach"
-    override fun toJson(): String = buildRequestJson()
+    override fun toJson(): String = buildRequestJson(arguments.toJsonObject())
 }
 
 data class RestartRequest(override val seq: Int) : DapRequest() {
@@ -854,9 +1595,21 @@ data class RestartRequest(override val seq: Int) : DapRequest() {
     override fun toJson(): String = buildRequestJson()
 }
 
-data class DisconnectRequest(override val seq: Int) : DapRequest() {
+data class DisconnectRequest(
+    override val seq: Int,
+    /** Whether the disconnect is part of a restart sequence. */
+    val restart: Boolean? = null,
+    /** Kill (true) or detach from (false) the debuggee; `null` uses the session default. */
+    val terminateDebuggee: Boolean? = null,
+    /** When detaching, leave the debuggee stopped instead of resuming it. */
+    val suspendDebuggee: Boolean? = null,
+) : DapRequest() {
     override val command get() = "disconnect"
-    override fun toJson(): String = buildRequestJson()
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        if (restart != null) put("restart", restart)
+        if (terminateDebuggee != null) put("terminateDebuggee", terminateDebuggee)
+        if (suspendDebuggee != null) put("suspendDebuggee", suspendDebuggee)
+    })
 }
 
 data class TerminateRequest(override val seq: Int) : DapRequest() {
@@ -864,29 +1617,88 @@ data class TerminateRequest(override val seq: Int) : DapRequest() {
     override fun toJson(): String = buildRequestJson()
 }
 
-data class SetBreakpointsRequest(override val seq: Int) : DapRequest() {
-    override val command get() = "setBreakpoints"
-    override fun toJson(): String = buildRequestJson()
+/** DAP `FunctionBreakpoint`: one entry of a `setFunctionBreakpoints` request. */
+data class FunctionBreakpoint(
+    /** Function name, wildcard pattern, or `/re <regex>`. */
+    val name: String,
+    /** Expression that must be truthy for the breakpoint to stop. */
+    val condition: String? = null,
+    /** Expression controlling how many hits are ignored. */
+    val hitCondition: String? = null,
+) {
+    fun toJsonObject(): JSONObject = JSONObject().apply {
+        put("name", name)
+        if (condition != null) put("condition", condition)
+        if (hitCondition != null) put("hitCondition", hitCondition)
+    }
+
+    companion object {
+        fun fromJson(obj: JSONObject): FunctionBreakpoint = FunctionBreakpoint(
+            name = obj.optString("name", ""),
+            condition = obj.optString("condition", null),
+            hitCondition = obj.optString("hitCondition", null),
+        )
+    }
 }
 
-data class SetFunctionBreakpointsRequest(override val seq: Int) : DapRequest() {
+/**
+ * DAP `setFunctionBreakpoints` request. KDAP creates the pattern
+ * breakpoints (see
+ * [com.github.jomof.dap.debugsession.handleSetFunctionBreakpoints]);
+ * plain names go to lldb-dap.
+ */
+data class SetFunctionBreakpointsRequest(
+    override val seq: Int,
+    val breakpoints: List<FunctionBreakpoint> = emptyList(),
+) : DapRequest() {
     override val command get() = "setFunctionBreakpoints"
-    override fun toJson(): String = buildRequestJson()
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        put("breakpoints", JSONArray(breakpoints.map { it.toJsonObject() }))
+    })
 }
 
-data class SetExceptionBreakpointsRequest(override val seq: Int) : DapRequest() {
-    override val command get() = "setExceptionBreakpoints"
-    override fun toJson(): String = buildRequestJson()
-}
+/** DAP `InstructionBreakpoint`: one entry of a `setInstructionBreakpoints` request. */
+data class InstructionBreakpoint(
+    /** Memory reference of the instruction, as returned by `disassemble`. */
+    val instructionReference: String,
+    /** Byte offset from [instructionReference]. */
+    val offset: Int? = null,
+    /** Expression that must be truthy for the breakpoint to stop. */
+    val condition: String? = null,
+    /** Expression controlling how many hits are ignored. */
+    val hitCondition: String? = null,
+) {
+    fun toJsonObject(): JSONObject = JSONObject().apply {
+        put("instructionReference", instructionReference)
+        if (offset != null) put("offset", offset)
+        if (condition != null) put("condition", condition)
+        if (hitCondition != null) put("hitCondition", hitCondition)
+    }
 
-data class SetDataBreakpointsRequest(override val seq: Int) : DapRequest() {
-    override val command get() = "setDataBreakpoints"
-    override fun toJson(): String = buildRequestJson()
+    companion object {
+        fun fromJson(obj: JSONObject): InstructionBreakpoint = InstructionBreakpoint(
+            instructionReference = obj.optString("instructionReference", ""),
+            offset = if (obj.has("offset")) obj.optInt("offset") else null,
+            condition = obj.optString("condition", null),
+            hitCondition = obj.optString("hitCondition", null),
+        )
+    }
 }
 
-data class SetInstructionBreakpointsRequest(override val seq: Int) : DapRequest() {
+/**
+ * DAP `setInstructionBreakpoints` request, sent by the disassembly view.
+ * Conditions are split between lldb-dap and KDAP as for source
+ * breakpoints (see
+ * [com.github.jomof.dap.debugsession.handleSetInstructionBreakpoints]).
+ */
+data class SetInstructionBreakpointsRequest(
+    override val seq: Int,
+    val breakpoints: List<InstructionBreakpoint> = emptyList(),
+) : DapRequest() {
     override val command get() = "setInstructionBreakpoints"
-    override fun toJson(): String = buildRequestJson()
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        put("breakpoints", JSONArray(breakpoints.map { it.toJsonObject() }))
+    })
 }
 
 data class ConfigurationDoneRequest(override val seq: Int = 0) : DapRequest() {
@@ -894,34 +1706,81 @@ data class ConfigurationDoneRequest(override val seq: Int = 0) : DapRequest() {
     override fun toJson(): String = buildRequestJson()
 }
 
-data class ContinueRequest(override val seq: Int) : DapRequest() {
+data class ContinueRequest(
+    override val seq: Int,
+    val threadId: Int? = null,
+    /** Resume only [threadId], leaving the other threads stopped. */
+    val singleThread: Boolean? = null,
+) : DapRequest() {
     override val command get() = "continue"
-    override fun toJson(): String = buildRequestJson()
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        if (threadId != null) put("threadId", threadId)
+        if (singleThread != null) put("singleThread", singleThread)
+    })
 }
 
-data class NextRequest(override val seq: Int) : DapRequest() {
+data class NextRequest(
+    override val seq: Int,
+    val threadId: Int = 0,
+    val singleThread: Boolean? = null,
+    /** `"statement"`, `"line"`, or `"instruction"`; `null` means `"statement"`. */
+    val granularity: String? = null,
+) : DapRequest() {
     override val command get() = "next"
-    override fun toJson(): String = buildRequestJson()
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        put("threadId", threadId)
+        if (singleThread != null) put("singleThread", singleThread)
+        if (granularity != null) put("granularity", granularity)
+    })
 }
 
-data class StepInRequest(override val seq: Int) : DapRequest() {
+data class StepInRequest(
+    override val seq: Int,
+    val threadId: Int = 0,
+    val singleThread: Boolean? = null,
+    val targetId: Int? = null,
+    /** `"statement"`, `"line"`, or `"instruction"`; `null` means `"statement"`. */
+    val granularity: String? = null,
+) : DapRequest() {
     override val command get() = "stepIn"
-    override fun toJson(): String = buildRequestJson()
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        put("threadId", threadId)
+        if (singleThread != null) put("singleThread", singleThread)
+        if (targetId != null) put("targetId", targetId)
+        if (granularity != null) put("granularity", granularity)
+    })
 }
 
-data class StepOutRequest(override val seq: Int) : DapRequest() {
+data class StepOutRequest(
+    override val seq: Int,
+    val threadId: Int = 0,
+    val singleThread: Boolean? = null,
+    /** `"statement"`, `"line"`, or `"instruction"`; `null` means `"statement"`. */
+    val granularity: String? = null,
+) : DapRequest() {
     override val command get() = "stepOut"
-    override fun toJson(): String = buildRequestJson()
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        put("threadId", threadId)
+        if (singleThread != null) put("singleThread", singleThread)
+        if (granularity != null) put("granularity", granularity)
+    })
 }
 
-data class StepBackRequest(override val seq: Int) : DapRequest() {
+data class StepBackRequest(
+    override val seq: Int,
+    val threadId: Int = 0,
+    val granularity: String? = null,
+) : DapRequest() {
     override val command get() = "stepBack"
-    override fun toJson(): String = buildRequestJson()
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        put("threadId", threadId)
+        if (granularity != null) put("granularity", granularity)
+    })
 }
 
-data class ReverseContinueRequest(override val seq: Int) : DapRequest() {
+data class ReverseContinueRequest(override val seq: Int, val threadId: Int = 0) : DapRequest() {
     override val command get() = "reverseContinue"
-    override fun toJson(): String = buildRequestJson()
+    override fun toJson(): String = buildRequestJson(JSONObject().put("threadId", threadId))
 }
 
 data class PauseRequest(override val seq: Int) : DapRequest() {
@@ -929,9 +1788,9 @@ data class PauseRequest(override val seq: Int) : DapRequest() {
     override fun toJson(): String = buildRequestJson()
 }
 
-data class RestartFrameRequest(override val seq: Int) : DapRequest() {
+data class RestartFrameRequest(override val seq: Int, val frameId: Int = 0) : DapRequest() {
     override val command get() = "restartFrame"
-    override fun toJson(): String = buildRequestJson()
+    override fun toJson(): String = buildRequestJson(JSONObject().put("frameId", frameId))
 }
 
 data class ThreadsRequest(override val seq: Int) : DapRequest() {
@@ -939,14 +1798,23 @@ data class ThreadsRequest(override val seq: Int) : DapRequest() {
     override fun toJson(): String = buildRequestJson()
 }
 
-data class StackTraceRequest(override val seq: Int) : DapRequest() {
+data class StackTraceRequest(
+    override val seq: Int,
+    val threadId: Int = 0,
+    val startFrame: Int? = null,
+    val levels: Int? = null,
+) : DapRequest() {
     override val command get() = "stackTrace"
-    override fun toJson(): String = buildRequestJson()
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        put("threadId", threadId)
+        if (startFrame != null) put("startFrame", startFrame)
+        if (levels != null) put("levels", levels)
+    })
 }
 
-data class ScopesRequest(override val seq: Int) : DapRequest() {
+data class ScopesRequest(override val seq: Int, val frameId: Int = 0) : DapRequest() {
     override val command get() = "scopes"
-    override fun toJson(): String = buildRequestJson()
+    override fun toJson(): String = buildRequestJson(JSONObject().put("frameId", frameId))
 }
 
 data class VariablesRequest(override val seq: Int) : DapRequest() {
@@ -959,24 +1827,48 @@ data class SetVariableRequest(override val seq: Int) : DapRequest() {
     override fun toJson(): String = buildRequestJson()
 }
 
-data class SourceRequest(override val seq: Int) : DapRequest() {
+data class SourceRequest(override val seq: Int, val sourceReference: Int = 0) : DapRequest() {
     override val command get() = "source"
-    override fun toJson(): String = buildRequestJson()
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        put("sourceReference", sourceReference)
+    })
 }
 
-data class ModulesRequest(override val seq: Int) : DapRequest() {
+data class ModulesRequest(
+    override val seq: Int,
+    val startModule: Int? = null,
+    val moduleCount: Int? = null,
+) : DapRequest() {
     override val command get() = "modules"
-    override fun toJson(): String = buildRequestJson()
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        if (startModule != null) put("startModule", startModule)
+        if (moduleCount != null) put("moduleCount", moduleCount)
+    })
 }
 
-data class CompletionsRequest(override val seq: Int) : DapRequest() {
+data class CompletionsRequest(
+    override val seq: Int,
+    val text: String = "",
+    /** 1-based cursor position in [text]. */
+    val column: Int = 0,
+    val frameId: Int? = null,
+) : DapRequest() {
     override val command get() = "completions"
-    override fun toJson(): String = buildRequestJson()
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        put("text", text)
+        put("column", column)
+        if (frameId != null) put("frameId", frameId)
+    })
 }
 
-data class ExceptionInfoRequest(override val seq: Int) : DapRequest() {
+data class ExceptionInfoRequest(
+    override val seq: Int,
+    val threadId: Int = 0,
+) : DapRequest() {
     override val command get() = "exceptionInfo"
-    override fun toJson(): String = buildRequestJson()
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        put("threadId", threadId)
+    })
 }
 
 data class ReadMemoryRequest(override val seq: Int) : DapRequest() {
@@ -999,29 +1891,69 @@ data class DataBreakpointInfoRequest(override val seq: Int) : DapRequest() {
     override fun toJson(): String = buildRequestJson()
 }
 
-data class GotoTargetsRequest(override val seq: Int) : DapRequest() {
+data class GotoTargetsRequest(
+    override val seq: Int,
+    val source: Source = Source(),
+    val line: Int = 0,
+    val column: Int? = null,
+) : DapRequest() {
     override val command get() = "gotoTargets"
-    override fun toJson(): String = buildRequestJson()
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        put("source", source.toJsonObject())
+        put("line", line)
+        if (column != null) put("column", column)
+    })
 }
 
-data class GotoRequest(override val seq: Int) : DapRequest() {
+data class GotoRequest(override val seq: Int, val threadId: Int = 0, val targetId: Int = 0) : DapRequest() {
     override val command get() = "goto"
-    override fun toJson(): String = buildRequestJson()
+    override fun toJson(): String = buildRequestJson(JSONObject().put("threadId", threadId).put("targetId", targetId))
 }
 
-data class StepInTargetsRequest(override val seq: Int) : DapRequest() {
+data class StepInTargetsRequest(override val seq: Int, val frameId: Int = 0) : DapRequest() {
     override val command get() = "stepInTargets"
-    override fun toJson(): String = buildRequestJson()
+    override fun toJson(): String = buildRequestJson(JSONObject().put("frameId", frameId))
 }
 
-data class CancelRequest(override val seq: Int) : DapRequest() {
+/**
+ * DAP `cancel` request for the request [requestId] or the progress
+ * [progressId]. KDAP cancels its own cancellable handlers (see
+ * [com.github.jomof.dap.DapSession.RequestAction.HandleAsync]) and
+ * forwards the rest to lldb-dap.
+ */
+data class CancelRequest(
+    override val seq: Int,
+    val requestId: Int? = null,
+    val progressId: String? = null,
+) : DapRequest() {
     override val command get() = "cancel"
-    override fun toJson(): String = buildRequestJson()
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        if (requestId != null) put("requestId", requestId)
+        if (progressId != null) put("progressId", progressId)
+    })
 }
 
-data class BreakpointLocationsRequest(override val seq: Int) : DapRequest() {
+/**
+ * DAP `breakpointLocations` request: where breakpoints can bind between
+ * [line]/[column] and [endLine]/[endColumn] (inclusive) in [source].
+ */
+data class BreakpointLocationsRequest(
+    override val seq: Int,
+    val source: Source = Source(),
+    val line: Int = 0,
+    val column: Int? = null,
+    /** Last line of the range; `null` means [line]. */
+    val endLine: Int? = null,
+    val endColumn: Int? = null,
+) : DapRequest() {
     override val command get() = "breakpointLocations"
-    override fun toJson(): String = buildRequestJson()
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        put("source", source.toJsonObject())
+        put("line", line)
+        if (column != null) put("column", column)
+        if (endLine != null) put("endLine", endLine)
+        if (endColumn != null) put("endColumn", endColumn)
+    })
 }
 
 data class LoadedSourcesRequest(override val seq: Int) : DapRequest() {
@@ -1039,6 +1971,124 @@ data class SetExpressionRequest(override val seq: Int) : DapRequest() {
     override fun toJson(): String = buildRequestJson()
 }
 
+// KDAP custom requests
+
+/** Input for the debuggee's stdin (`kdap/stdin`). */
+data class StdinRequest(
+    override val seq: Int,
+    /** Text to write, as is; include the `\n` a terminal would send. */
+    val text: String = "",
+) : DapRequest() {
+    override val command get() = "kdap/stdin"
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        put("text", text)
+    })
+}
+
+/** Signal dispositions to change (`kdap/signals`). */
+data class SignalsRequest(
+    override val seq: Int,
+    /** Dispositions by signal name, as given (`"pass"`, `"stop"`, or `"ignore"`). */
+    val signals: Map<String, String> = emptyMap(),
+) : DapRequest() {
+    override val command get() = "kdap/signals"
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        put("signals", JSONObject(signals))
+    })
+}
+
+/** Step into the call the thread's last just-my-code `stepIn` skipped (`kdap/stepIntoSkipped`). */
+data class StepIntoSkippedRequest(override val seq: Int, val threadId: Int = 0) : DapRequest() {
+    override val command get() = "kdap/stepIntoSkipped"
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        put("threadId", threadId)
+    })
+}
+
+/** A byte pattern or string to find in the debuggee's memory (`kdap/searchMemory`). */
+data class SearchMemoryRequest(
+    override val seq: Int,
+    /** Bytes to find, in hex (`"de ad be ef"`); exclusive with [string]. */
+    val bytes: String? = null,
+    /** Text to find, as UTF-8. */
+    val string: String? = null,
+    /** Most matches to return. */
+    val maxResults: Int? = null,
+) : DapRequest() {
+    override val command get() = "kdap/searchMemory"
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        bytes?.let { put("bytes", it) }
+        string?.let { put("string", it) }
+        maxResults?.let { put("maxResults", it) }
+    })
+}
+
+/** The debuggee's memory regions (`kdap/memoryMap`). */
+data class MemoryMapRequest(override val seq: Int) : DapRequest() {
+    override val command get() = "kdap/memoryMap"
+    override fun toJson(): String = buildRequestJson(JSONObject())
+}
+
+/** Live heap allocations, or the one containing an address (`kdap/heap`). */
+data class HeapRequest(
+    override val seq: Int,
+    /** Address to find the allocation of, as given: a number or a `"0x..."` string. */
+    val address: String? = null,
+    /** Most allocations to return when no [address] is given. */
+    val limit: Int? = null,
+) : DapRequest() {
+    override val command get() = "kdap/heap"
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        address?.let { put("address", it) }
+        limit?.let { put("limit", it) }
+    })
+}
+
+/** Replaces functions of the stopped debuggee with those of a rebuilt object (`kdap/hotReplace`). */
+data class HotReplaceRequest(
+    override val seq: Int,
+    /** Path to the rebuilt object file (`.o`) or shared library. */
+    val objectFile: String,
+    /** Names of the functions to replace; every function the object shares with the debuggee if not set. */
+    val functions: List<String>? = null,
+) : DapRequest() {
+    override val command get() = "kdap/hotReplace"
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        put("objectFile", objectFile)
+        functions?.let { put("functions", JSONArray(it)) }
+    })
+}
+
+/** Saves the session's breakpoints to, or loads them from, a JSON file (`kdap/breakpoints`). */
+data class BreakpointsRequest(
+    override val seq: Int,
+    /** `"export"` or `"import"`. */
+    val action: String,
+    /** Path of the breakpoint file. */
+    val path: String,
+) : DapRequest() {
+    override val command get() = "kdap/breakpoints"
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        put("action", action)
+        put("path", path)
+    })
+}
+
+/** Adds a symbol file to a loaded module (`kdap/loadSymbols`). */
+data class LoadSymbolsRequest(
+    override val seq: Int,
+    /** The module: its path, file name, or build ID. */
+    val module: String,
+    /** Path of the symbol file. */
+    val path: String,
+) : DapRequest() {
+    override val command get() = "kdap/loadSymbols"
+    override fun toJson(): String = buildRequestJson(JSONObject().apply {
+        put("module", module)
+        put("path", path)
+    })
+}
+
 // Reverse requests (adapter -> client)
 
 data class RunInTerminalRequest(
@@ -1154,9 +2204,31 @@ data class OutputEvent(
 
 // ── Standard DAP events (forwarded, minimal fields) ──────────────────
 
-data class StoppedEvent(override val seq: Int) : DapEvent() {
+/**
+ * DAP `stopped` event. KDAP creates these for stops that LLDB does not
+ * report on its own (e.g., the initial stop after `attach`).
+ */
+data class StoppedEvent(
+    override val seq: Int,
+    /** Stop reason, e.g. `"breakpoint"`, `"step"`, `"signal"`, `"entry"`. */
+    val reason: String? = null,
+    val description: String? = null,
+    val threadId: Int? = null,
+    val preserveFocusHint: Boolean? = null,
+    val text: String? = null,
+    val allThreadsStopped: Boolean? = null,
+    val hitBreakpointIds: List<Int>? = null,
+) : DapEvent() {
     override val event get() = "stopped"
-    override fun toJson(): String = buildEventJson()
+    override fun toJson(): String = buildEventJson(JSONObject().apply {
+        if (reason != null) put("reason", reason)
+        if (description != null) put("description", description)
+        if (threadId != null) put("threadId", threadId)
+        if (preserveFocusHint != null) put("preserveFocusHint", preserveFocusHint)
+        if (text != null) put("text", text)
+        if (allThreadsStopped != null) put("allThreadsStopped", allThreadsStopped)
+        if (hitBreakpointIds != null) put("hitBreakpointIds", JSONArray(hitBreakpointIds))
+    })
 }
 
 data class ContinuedEvent(
@@ -1195,19 +2267,43 @@ data class TerminatedEvent(
     }
 }
 
-data class ThreadEvent(override val seq: Int) : DapEvent() {
+data class ThreadEvent(
+    override val seq: Int,
+    /** `"started"` or `"exited"`. */
+    val reason: String = "",
+    val threadId: Int = 0,
+) : DapEvent() {
     override val event get() = "thread"
-    override fun toJson(): String = buildEventJson()
+    override fun toJson(): String = buildEventJson(JSONObject().apply {
+        put("reason", reason)
+        put("threadId", threadId)
+    })
 }
 
-data class BreakpointEvent(override val seq: Int) : DapEvent() {
+data class BreakpointEvent(
+    override val seq: Int,
+    /** `"changed"`, `"new"`, or `"removed"`. */
+    val reason: String? = null,
+    val breakpoint: Map<String, Any?> = emptyMap(),
+) : DapEvent() {
     override val event get() = "breakpoint"
-    override fun toJson(): String = buildEventJson()
+    override fun toJson(): String = buildEventJson(JSONObject().apply {
+        if (reason != null) put("reason", reason)
+        put("breakpoint", DapMessage.mapToJsonObject(breakpoint))
+    })
 }
 
-data class ModuleEvent(override val seq: Int) : DapEvent() {
+data class ModuleEvent(
+    override val seq: Int,
+    /** `"new"`, `"changed"`, or `"removed"`. */
+    val reason: String? = null,
+    val module: Map<String, Any?> = emptyMap(),
+) : DapEvent() {
     override val event get() = "module"
-    override fun toJson(): String = buildEventJson()
+    override fun toJson(): String = buildEventJson(JSONObject().apply {
+        if (reason != null) put("reason", reason)
+        put("module", DapMessage.mapToJsonObject(module))
+    })
 }
 
 data class LoadedSourceEvent(override val seq: Int) : DapEvent() {
@@ -1235,19 +2331,71 @@ data class MemoryEvent(override val seq: Int) : DapEvent() {
     override fun toJson(): String = buildEventJson()
 }
 
-data class ProgressStartEvent(override val seq: Int) : DapEvent() {
+data class ProgressStartEvent(
+    override val seq: Int,
+    val progressId: String = "",
+    val title: String = "",
+    val requestId: Int? = null,
+    val cancellable: Boolean? = null,
+    val message: String? = null,
+    val percentage: Float? = null,
+) : DapEvent() {
     override val event get() = "progressStart"
-    override fun toJson(): String = buildEventJson()
+    override fun toJson(): String = buildEventJson(JSONObject().apply {
+        put("progressId", progressId)
+        put("title", title)
+        if (requestId != null) put("requestId", requestId)
+        if (cancellable != null) put("cancellable", cancellable)
+        if (message != null) put("message", message)
+        if (percentage != null) put("percentage", percentage)
+    })
 }
 
-data class ProgressUpdateEvent(override val seq: Int) : DapEvent() {
+data class ProgressUpdateEvent(
+    override val seq: Int,
+    val progressId: String = "",
+    val message: String? = null,
+    val percentage: Float? = null,
+) : DapEvent() {
     override val event get() = "progressUpdate"
-    override fun toJson(): String = buildEventJson()
+    override fun toJson(): String = buildEventJson(JSONObject().apply {
+        put("progressId", progressId)
+        if (message != null) put("message", message)
+        if (percentage != null) put("percentage", percentage)
+    })
 }
 
-data class ProgressEndEvent(override val seq: Int) : DapEvent() {
+data class ProgressEndEvent(
+    override val seq: Int,
+    val progressId: String = "",
+    val message: String? = null,
+) : DapEvent() {
     override val event get() = "progressEnd"
-    override fun toJson(): String = buildEventJson()
+    override fun toJson(): String = buildEventJson(JSONObject().apply {
+        put("progressId", progressId)
+        if (message != null) put("message", message)
+    })
+}
+
+/**
+ * Symbol indexing progress (KDAP extension; see SymbolIndex.kt): [module]
+ * started (`"indexing"`) or finished (`"indexed"`) [state], with
+ * [modulesIndexed] of the [modulesTotal] modules seen so far done.
+ */
+data class SymbolStatusEvent(
+    override val seq: Int,
+    val state: String,
+    val module: String,
+    val modulesIndexed: Int,
+    val modulesTotal: Int,
+) : DapEvent() {
+    override val event get() = "kdap/symbolStatus"
+    override fun toJson(): String = buildEventJson(JSONObject().apply {
+        put("state", state)
+        put("module", module)
+        put("modulesIndexed", modulesIndexed)
+        put("modulesTotal", modulesTotal)
+    })
 }
 
 // ── Narrow catch-all ─────────────────────────────────────────────────
@@ -1304,6 +2452,43 @@ data class DapResponse(
                 message = message,
             )
 
+        /**
+         * Factory for error responses with a structured `error` message
+         * ([id], [message]) that clients show to the user.
+         */
+        fun userError(requestSeq: Int, command: String, id: Int, message: String) =
+            DapResponse(
+                seq = 0,
+                requestSeq = requestSeq,
+                command = command,
+                success = false,
+                message = message,
+                body = mapOf("error" to mapOf("id" to id, "format" to message, "showUser" to true)),
+            )
+
+        /** Error id of [timedOut] responses. */
+        const val TIMED_OUT_ERROR_ID = 5201
+
+        /**
+         * Factory for the error response of a request that got no answer
+         * within [timeoutMillis]: message `timeout`, and a structured
+         * `error` naming the command and the timeout, not shown to the user.
+         */
+        fun timedOut(requestSeq: Int, command: String, timeoutMillis: Long) =
+            DapResponse(
+                seq = 0,
+                requestSeq = requestSeq,
+                command = command,
+                success = false,
+                message = "timeout",
+                body = mapOf("error" to mapOf(
+                    "id" to TIMED_OUT_ERROR_ID,
+                    "format" to "'{command}' timed out after {timeout} ms",
+                    "variables" to mapOf("command" to command, "timeout" to timeoutMillis.toString()),
+                    "showUser" to false,
+                )),
+            )
+
         // Delegate to DapMessage companion for Map/JSONObject conversion
         private fun mapToJsonObject(map: Map<String, Any?>): JSONObject =
             DapMessage.mapToJsonObject(map)
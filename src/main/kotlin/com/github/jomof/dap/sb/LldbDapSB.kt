@@ -1,8 +1,10 @@
 package com.github.jomof.dap.sb
 
 import com.github.jomof.dap.DapSession.AsyncRequestContext
+import com.github.jomof.dap.LldbDapProcess
 import com.github.jomof.dap.messages.DapResponse
 import org.json.JSONObject
+import java.io.File
 import java.util.logging.Logger
 
 /**
@@ -199,7 +201,7 @@ private fun stripPyRepr(s: String): String {
  * Produces a Python string literal with proper escaping.
  * Uses single quotes: `'value'`.
  */
-private fun pyStr(s: String): String {
+internal fun pyStr(s: String): String {
     val escaped = s
         .replace("\\", "\\\\")
         .replace("'", "\\'")
@@ -242,6 +244,12 @@ private class LldbDapDebugger(private val ctx: AsyncRequestContext) : SBDebugger
         return output.ifEmpty { null }
     }
 
+    override suspend fun setCurrentPlatformSdkRoot(sysroot: String) {
+        evalPyVoid(ctx,
+            "None if lldb.debugger.SetCurrentPlatformSDKRoot(${pyStr(sysroot)}) else " +
+                "exec('raise Exception(\"No current platform to set the sysroot of\")')")
+    }
+
     override suspend fun createTarget(
         program: String?,
         triple: String?,
@@ -267,6 +275,36 @@ private class LldbDapDebugger(private val ctx: AsyncRequestContext) : SBDebugger
     override suspend fun selectedTarget(): SBTarget = LldbDapTarget(ctx)
 
     override suspend fun selectedPlatform(): SBPlatform = LldbDapPlatform(ctx)
+
+    override suspend fun commandInterpreter(): SBCommandInterpreter = LldbDapCommandInterpreter(ctx)
+}
+
+// ══════════════════════════════════════════════════════════════════════
+// SBCommandInterpreter implementation
+// ══════════════════════════════════════════════════════════════════════
+
+private class LldbDapCommandInterpreter(private val ctx: AsyncRequestContext) : SBCommandInterpreter {
+
+    override suspend fun handleCommand(command: String): String {
+        val result = handleCommandWithResult(command)
+        if (!result.succeeded) {
+            throw SBError(result.error.trim().ifEmpty { "Command failed: $command" })
+        }
+        return result.output
+    }
+
+    override suspend fun handleCommandWithResult(command: String): SBCommandReturnObject {
+        // HandleCommand returns a ReturnStatus; assign it so auto-display
+        // stays silent. The return object keeps output off the console.
+        evalPyVoid(ctx,
+            "_kdap_ro = lldb.SBCommandReturnObject(); " +
+                "_ = lldb.debugger.GetCommandInterpreter().HandleCommand(${pyStr(command)}, _kdap_ro)")
+        return SBCommandReturnObject(
+            succeeded = evalPy(ctx, "_kdap_ro.Succeeded()").trim() == "True",
+            output = evalPyStr(ctx, "_kdap_ro.GetOutput() or ''"),
+            error = evalPyStr(ctx, "_kdap_ro.GetError() or ''"),
+        )
+    }
 }
 
 // ══════════════════════════════════════════════════════════════════════
@@ -279,18 +317,21 @@ private class LldbDapTarget(private val ctx: AsyncRequestContext) : SBTarget {
         return evalPy(ctx, "lldb.target.IsValid()").trim() == "True"
     }
 
+    /**
+     * On macOS, tells LLDB which debugserver to use (see [debugServerPath]).
+     * Sets the setting directly since env var LLDB_DEBUGSERVER_PATH may
+     * not reach LLDB's core.
+     */
+    private suspend fun configureDebugServer() {
+        val path = debugServerPath() ?: return
+        evalPyVoid(ctx,
+            "lldb.debugger.HandleCommand(" +
+                pyStr("settings set platform.plugin.darwin.debugserver-path \"$path\"") + ")")
+    }
+
     override suspend fun launch(launchInfo: SBLaunchInfo): SBProcess {
         val v = (launchInfo as LldbDapLaunchInfo).pythonVarName
-        // On macOS, ensure LLDB knows where to find debugserver for
-        // remote platform launches. Set the internal setting directly
-        // since env var LLDB_DEBUGSERVER_PATH may not reach LLDB's core.
-        val debugServerPath = System.getenv("LLDB_DEBUGSERVER_PATH")
-        if (debugServerPath != null) {
-            evalPyVoid(ctx,
-                "lldb.debugger.HandleCommand(" +
-                    "'settings set platform.plugin.darwin.debugserver-path $debugServerPath')"
-            )
-        }
+        configureDebugServer()
         // Launch returns (SBProcess, SBError). Check the SBError, then
         // validate the process is valid (remote platforms can return
         // success in the SBError but an invalid process when debugserver
@@ -305,8 +346,26 @@ private class LldbDapTarget(private val ctx: AsyncRequestContext) : SBTarget {
         return LldbDapProcess(ctx, cachedPid = pid)
     }
 
+    override suspend fun relaunch(launchInfo: SBLaunchInfo): SBProcess {
+        val v = (launchInfo as LldbDapLaunchInfo).pythonVarName
+        configureDebugServer()
+        // Launch replaces the killed process in the same evaluation, so by
+        // the time lldb-dap's event thread handles the exit, the old
+        // process is gone and lldb-dap treats the exit as a restart's.
+        val output = evalPy(ctx,
+            "_e = lldb.SBError(); _ = lldb.target.GetProcess().Kill(); " +
+                "_p = lldb.target.Launch($v, _e); " +
+                "_kdap_check(_e); " +
+                "str(_p.GetProcessID()) if _p.IsValid() else " +
+                "exec('raise Exception(\"Process is not valid after launch. \" + " +
+                    "\"state=\" + str(_p.GetState()))')")
+        val pid = output.trim().toLongOrNull()
+        return LldbDapProcess(ctx, cachedPid = pid)
+    }
+
     override suspend fun attach(attachInfo: SBAttachInfo): SBProcess {
         val v = (attachInfo as LldbDapAttachInfo).pythonVarName
+        configureDebugServer()
         val output = evalPy(ctx,
             "_e = lldb.SBError(); _p = lldb.target.Attach($v, _e); " +
                 "_kdap_check(_e); _p.GetProcessID()")
@@ -314,6 +373,23 @@ private class LldbDapTarget(private val ctx: AsyncRequestContext) : SBTarget {
         return LldbDapProcess(ctx, cachedPid = pid)
     }
 
+    override suspend fun connectRemote(url: String): SBProcess {
+        val output = evalPy(ctx,
+            "_e = lldb.SBError(); " +
+                "_p = lldb.target.ConnectRemote(lldb.debugger.GetListener(), ${pyStr(url)}, 'gdb-remote', _e); " +
+                "_kdap_check(_e); _p.GetProcessID()")
+        val pid = output.trim().toLongOrNull()
+        return LldbDapProcess(ctx, cachedPid = pid)
+    }
+
+    override suspend fun loadCore(corePath: String): SBProcess {
+        val output = evalPy(ctx,
+            "_e = lldb.SBError(); _p = lldb.target.LoadCore(${pyStr(corePath)}, _e); " +
+                "_kdap_check(_e); _p.GetProcessID()")
+        val pid = output.trim().toLongOrNull()
+        return LldbDapProcess(ctx, cachedPid = pid)
+    }
+
     override suspend fun launchInfo(): SBLaunchInfo {
         // Create a persistent Python-side SBLaunchInfo variable.
         evalPyVoid(ctx, "_kdap_li = lldb.target.GetLaunchInfo()")
@@ -335,6 +411,79 @@ private class LldbDapTarget(private val ctx: AsyncRequestContext) : SBTarget {
     }
 
     override suspend fun platform(): SBPlatform = LldbDapPlatform(ctx)
+
+    override suspend fun findBreakpointById(id: Int): SBBreakpoint = LldbDapBreakpoint(ctx, id)
+
+    override suspend fun breakpointCreateByName(symbolName: String): SBBreakpoint {
+        val output = evalPy(ctx,
+            "_b = lldb.target.BreakpointCreateByName(${pyStr(symbolName)}); " +
+                "_b.GetID() if _b.IsValid() else -1")
+        val id = output.trim().toIntOrNull()?.takeIf { it > 0 }
+            ?: throw SBError("Could not create breakpoint on $symbolName")
+        return LldbDapBreakpoint(ctx, id)
+    }
+
+    override suspend fun breakpointCreateByRegex(regex: String): SBBreakpoint {
+        val output = evalPy(ctx,
+            "_b = lldb.target.BreakpointCreateByRegex(${pyStr(regex)}); " +
+                "_b.GetID() if _b.IsValid() else -1")
+        val id = output.trim().toIntOrNull()?.takeIf { it > 0 }
+            ?: throw SBError("Could not create breakpoint on /$regex/")
+        return LldbDapBreakpoint(ctx, id)
+    }
+
+    override suspend fun breakpointDelete(id: Int): Boolean {
+        return evalPy(ctx, "lldb.target.BreakpointDelete($id)").trim() == "True"
+    }
+}
+
+// ══════════════════════════════════════════════════════════════════════
+// SBBreakpoint implementation
+// ══════════════════════════════════════════════════════════════════════
+
+private class LldbDapBreakpoint(
+    private val ctx: AsyncRequestContext,
+    private val bpId: Int,
+) : SBBreakpoint {
+    private val bp = "lldb.target.FindBreakpointByID($bpId)"
+
+    override suspend fun isValid(): Boolean {
+        return evalPy(ctx, "$bp.IsValid()").trim() == "True"
+    }
+
+    override suspend fun id(): Int = bpId
+
+    override suspend fun numLocations(): Int {
+        val output = evalPy(ctx, "$bp.GetNumLocations()")
+        return output.trim().toIntOrNull() ?: 0
+    }
+
+    override suspend fun setCondition(condition: String) {
+        evalPyVoid(ctx, "$bp.SetCondition(${pyStr(condition)})")
+    }
+
+    override suspend fun setIgnoreCount(count: Int) {
+        evalPyVoid(ctx, "$bp.SetIgnoreCount($count)")
+    }
+
+    override suspend fun addName(name: String) {
+        evalPyVoid(ctx,
+            "_e = $bp.AddNameWithErrorHandling(${pyStr(name)}); _kdap_check(_e)")
+    }
+
+    override suspend fun setScriptCallbackBody(body: String) {
+        evalPyVoid(ctx,
+            "_e = $bp.SetScriptCallbackBody(${pyStr(body)}); _kdap_check(_e)")
+    }
+
+    override suspend fun clearCallback() {
+        // Run through a private return object so the command's output
+        // does not reach the debug console.
+        evalPyVoid(ctx,
+            "_r = lldb.SBCommandReturnObject(); " +
+                "lldb.debugger.GetCommandInterpreter()" +
+                ".HandleCommand('breakpoint command delete $bpId', _r)")
+    }
 }
 
 // ══════════════════════════════════════════════════════════════════════
@@ -383,12 +532,26 @@ private class LldbDapProcess(
             "_e = lldb.target.GetProcess().Detach(${pyBool(keepStopped)}); _kdap_check(_e)")
     }
 
+    override suspend fun putStdin(text: String): Int {
+        val output = evalPy(ctx, "lldb.target.GetProcess().PutSTDIN(${pyStr(text)})")
+        return output.trim().toIntOrNull() ?: 0
+    }
+
     override suspend fun signal(signo: Int) {
         evalPyVoid(ctx,
             "_e = lldb.target.GetProcess().Signal($signo); _kdap_check(_e)")
     }
 
     override suspend fun unixSignals(): SBUnixSignals = LldbDapUnixSignals(ctx)
+
+    override suspend fun selectedThread(): SBThread = LldbDapThread(ctx)
+
+    override suspend fun threads(): List<SBThread> {
+        val output = evalPyStr(ctx, "' '.join(str(t.GetThreadID()) for t in lldb.target.GetProcess())")
+        return output.split(' ').mapNotNull { it.toLongOrNull() }.map { id ->
+            LldbDapThread(ctx, "lldb.target.GetProcess().GetThreadByID($id)")
+        }
+    }
 }
 
 /**
@@ -413,6 +576,129 @@ private fun stateFromLldbInt(state: Int): ProcessState = when (state) {
     else -> error("Unknown LLDB StateType: $state")
 }
 
+// ══════════════════════════════════════════════════════════════════════
+// SBThread implementation
+// ══════════════════════════════════════════════════════════════════════
+
+/**
+ * A thread identified by the Python expression [thread] that yields it.
+ * The default, the process's selected thread, is resolved on every call
+ * so it tracks LLDB's selection rather than a snapshot.
+ */
+private class LldbDapThread(
+    private val ctx: AsyncRequestContext,
+    private val thread: String = "lldb.target.GetProcess().GetSelectedThread()",
+) : SBThread {
+
+    override suspend fun isValid(): Boolean {
+        return evalPy(ctx, "$thread.IsValid()").trim() == "True"
+    }
+
+    override suspend fun threadId(): Long {
+        val output = evalPy(ctx, "$thread.GetThreadID()")
+        return output.trim().toLongOrNull()
+            ?: throw SBError("Could not parse thread ID from: $output")
+    }
+
+    override suspend fun stopReason(): StopReason {
+        val output = evalPy(ctx, "$thread.GetStopReason()")
+        return stopReasonFromLldbInt(output.trim().toInt())
+    }
+
+    override suspend fun stopDescription(): String {
+        return evalPyStr(ctx, "$thread.GetStopDescription(256) or ''")
+    }
+
+    override suspend fun stepUsingScriptedThreadPlan(className: String) {
+        evalPyVoid(ctx,
+            "_e = $thread.StepUsingScriptedThreadPlan(${pyStr(className)}, True); _kdap_check(_e)")
+    }
+
+    override suspend fun selectedFrame(): SBFrame = LldbDapFrame(ctx, "$thread.GetSelectedFrame()")
+
+    override suspend fun suspend() {
+        evalPyVoid(ctx,
+            "_e = lldb.SBError(); $thread.Suspend(_e); _kdap_check(_e)")
+    }
+
+    override suspend fun resume() {
+        evalPyVoid(ctx,
+            "_e = lldb.SBError(); $thread.Resume(_e); _kdap_check(_e)")
+    }
+}
+
+// ══════════════════════════════════════════════════════════════════════
+// SBFrame implementation
+// ══════════════════════════════════════════════════════════════════════
+
+/** A frame identified by the Python expression [frame] that yields it. */
+private class LldbDapFrame(
+    private val ctx: AsyncRequestContext,
+    private val frame: String,
+) : SBFrame {
+
+    override suspend fun isValid(): Boolean {
+        return evalPy(ctx, "$frame.IsValid()").trim() == "True"
+    }
+
+    override suspend fun valueForVariablePath(path: String): SBValue =
+        LldbDapValue(ctx, "$frame.GetValueForVariablePath(${pyStr(path)})")
+}
+
+// ══════════════════════════════════════════════════════════════════════
+// SBValue implementation
+// ══════════════════════════════════════════════════════════════════════
+
+/**
+ * A value identified by the Python expression [value] that yields it.
+ * Children extend the expression, so no Python-side state is kept.
+ */
+private class LldbDapValue(
+    private val ctx: AsyncRequestContext,
+    private val value: String,
+) : SBValue {
+
+    override suspend fun isValid(): Boolean {
+        return evalPy(ctx, "$value.IsValid()").trim() == "True"
+    }
+
+    override suspend fun typeName(): String {
+        return evalPyStr(ctx, "$value.GetTypeName() or ''")
+    }
+
+    override suspend fun isPointerType(): Boolean {
+        return evalPy(ctx, "$value.GetType().IsPointerType()").trim() == "True"
+    }
+
+    override suspend fun valueAsUnsigned(): Long {
+        // Addresses can exceed Long.MAX_VALUE; keep the bit pattern.
+        val output = evalPy(ctx, "$value.GetValueAsUnsigned()")
+        return output.trim().toULongOrNull()?.toLong() ?: 0L
+    }
+
+    override suspend fun numChildren(): Int {
+        return evalPy(ctx, "$value.GetNumChildren()").trim().toIntOrNull() ?: 0
+    }
+
+    override suspend fun childAtIndex(index: Int): SBValue =
+        LldbDapValue(ctx, "$value.GetChildAtIndex($index)")
+
+    override suspend fun childMemberWithName(name: String): SBValue =
+        LldbDapValue(ctx, "$value.GetChildMemberWithName(${pyStr(name)})")
+
+    override suspend fun nonSyntheticValue(): SBValue =
+        LldbDapValue(ctx, "$value.GetNonSyntheticValue()")
+}
+
+/**
+ * Maps LLDB's `lldb::StopReason` integer values to our [StopReason] enum.
+ *
+ * Values correspond to `lldb.eStopReasonInvalid` (0) through
+ * `lldb.eStopReasonInterrupt` (15).
+ */
+private fun stopReasonFromLldbInt(reason: Int): StopReason =
+    StopReason.entries.getOrElse(reason) { StopReason.Invalid }
+
 // ══════════════════════════════════════════════════════════════════════
 // SBLaunchInfo implementation
 // ══════════════════════════════════════════════════════════════════════
@@ -588,6 +874,39 @@ private class LldbDapPlatform(private val ctx: AsyncRequestContext) : SBPlatform
                 ".GetFilePermissions(lldb.SBFileSpec(${pyStr(path)}))")
         return output.trim().toInt()
     }
+
+    override suspend fun allProcesses(): List<SBProcessInfo> {
+        // GetAllProcesses fills an SBProcessInfoList; flatten it to JSON
+        // pairs in a single round-trip.
+        evalPyVoid(ctx,
+            "exec(\"def _kdap_procs():\\n" +
+                " e = lldb.SBError()\\n" +
+                " l = lldb.debugger.GetSelectedPlatform().GetAllProcesses(e)\\n" +
+                " _kdap_check(e)\\n" +
+                " r = []\\n" +
+                " for i in range(l.GetSize()):\\n" +
+                "  pi = lldb.SBProcessInfo()\\n" +
+                "  if l.GetProcessInfoAtIndex(i, pi): r.append([pi.GetProcessID(), pi.GetName()])\\n" +
+                " return r\")")
+        val output = evalPyStr(ctx, "import json; json.dumps(_kdap_procs())")
+        val arr = org.json.JSONArray(output.trim().ifEmpty { "[]" })
+        return (0 until arr.length()).map { i ->
+            val entry = arr.getJSONArray(i)
+            StaticProcessInfo(entry.getLong(0), entry.optString(1, ""))
+        }
+    }
+}
+
+// ══════════════════════════════════════════════════════════════════════
+// SBProcessInfo implementation
+// ══════════════════════════════════════════════════════════════════════
+
+private data class StaticProcessInfo(
+    private val pid: Long,
+    private val processName: String,
+) : SBProcessInfo {
+    override suspend fun processId(): Long = pid
+    override suspend fun name(): String = processName
 }
 
 // ══════════════════════════════════════════════════════════════════════
@@ -639,6 +958,37 @@ private class LldbDapUnixSignals(private val ctx: AsyncRequestContext) : SBUnixS
             "lldb.target.GetProcess().GetUnixSignals()" +
                 ".SetShouldNotify($signo, ${pyBool(notify)})")
     }
+
+    override suspend fun shouldStop(signo: Int): Boolean {
+        return evalPy(ctx,
+            "lldb.target.GetProcess().GetUnixSignals().GetShouldStop($signo)").trim() == "True"
+    }
+
+    override suspend fun signalNumbers(): List<Int> {
+        val output = evalPyStr(ctx,
+            "_s = lldb.target.GetProcess().GetUnixSignals(); " +
+                "','.join(str(_s.GetSignalAtIndex(i)) for i in range(_s.GetNumSignals()))")
+        return output.split(',').mapNotNull { it.trim().toIntOrNull() }
+    }
 }
 
 private val log = Logger.getLogger("com.github.jomof.dap.sb.LldbDapSB")
+
+/** Apple's signed debugserver, from the Command Line Tools or Xcode. */
+private val APPLE_DEBUGSERVER_PATHS = listOf(
+    "/Library/Developer/CommandLineTools/Library/PrivateFrameworks/LLDB.framework/Versions/A/Resources/debugserver",
+    "/Applications/Xcode.app/Contents/SharedFrameworks/LLDB.framework/Versions/A/Resources/debugserver",
+)
+
+/**
+ * The debugserver for local macOS debugging: `LLDB_DEBUGSERVER_PATH`, or
+ * else Apple's copy. debugserver controls the debuggee through Mach
+ * (`task_for_pid`, exception ports, thread state), which needs Apple's
+ * code-signing entitlements; one built from source with lldb-dap lacks
+ * them. `null` elsewhere, where LLDB debugs natively or via lldb-server.
+ */
+private fun debugServerPath(): String? {
+    System.getenv("LLDB_DEBUGSERVER_PATH")?.let { return it }
+    if (LldbDapProcess.currentPlatformId()?.startsWith("darwin") != true) return null
+    return APPLE_DEBUGSERVER_PATHS.firstOrNull { File(it).canExecute() }
+}
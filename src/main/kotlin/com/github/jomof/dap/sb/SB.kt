@@ -71,6 +71,30 @@ enum class ProcessState {
     }
 }
 
+/**
+ * Why a thread stopped.
+ *
+ * Maps to LLDB's `lldb::StopReason`.
+ */
+enum class StopReason {
+    Invalid,
+    None,
+    Trace,
+    Breakpoint,
+    Watchpoint,
+    Signal,
+    Exception,
+    Exec,
+    PlanComplete,
+    ThreadExiting,
+    Instrumentation,
+    ProcessorTrace,
+    Fork,
+    VFork,
+    VForkDone,
+    Interrupt,
+}
+
 // ══════════════════════════════════════════════════════════════════════
 // SBDebugger
 // ══════════════════════════════════════════════════════════════════════
@@ -102,6 +126,16 @@ interface SBDebugger {
      */
     suspend fun getVariable(name: String): String?
 
+    /**
+     * Sets the sysroot of the currently selected platform, where LLDB looks
+     * for the target's shared libraries.
+     *
+     * Maps to `SBDebugger::SetCurrentPlatformSDKRoot`.
+     *
+     * @throws SBError if there is no current platform
+     */
+    suspend fun setCurrentPlatformSdkRoot(sysroot: String)
+
     /**
      * Creates a debug target from a program path.
      *
@@ -134,8 +168,58 @@ interface SBDebugger {
      * Maps to `SBDebugger::GetSelectedPlatform`.
      */
     suspend fun selectedPlatform(): SBPlatform
+
+    /**
+     * Returns the debugger's command interpreter.
+     *
+     * Maps to `SBDebugger::GetCommandInterpreter`.
+     */
+    suspend fun commandInterpreter(): SBCommandInterpreter
 }
 
+// ══════════════════════════════════════════════════════════════════════
+// SBCommandInterpreter
+// ══════════════════════════════════════════════════════════════════════
+
+/**
+ * LLDB's command interpreter.
+ *
+ * Maps to LLDB's `SBCommandInterpreter`.
+ */
+interface SBCommandInterpreter {
+    /**
+     * Runs an LLDB command, capturing its output instead of echoing it to
+     * the debug console.
+     *
+     * Maps to `SBCommandInterpreter::HandleCommand` with a private
+     * `SBCommandReturnObject`.
+     *
+     * @return the command's output
+     * @throws SBError with the command's error text if it fails
+     */
+    suspend fun handleCommand(command: String): String
+
+    /**
+     * Runs an LLDB command like [handleCommand], but reports failure in
+     * the result instead of throwing, so error output can be shown.
+     *
+     * Maps to `SBCommandInterpreter::HandleCommand`.
+     */
+    suspend fun handleCommandWithResult(command: String): SBCommandReturnObject
+}
+
+/**
+ * Outcome of an LLDB command.
+ *
+ * Maps to LLDB's `SBCommandReturnObject` (`Succeeded`, `GetOutput`,
+ * `GetError`).
+ */
+data class SBCommandReturnObject(
+    val succeeded: Boolean,
+    val output: String,
+    val error: String,
+)
+
 // ══════════════════════════════════════════════════════════════════════
 // SBTarget
 // ══════════════════════════════════════════════════════════════════════
@@ -159,6 +243,17 @@ interface SBTarget {
      */
     suspend fun launch(launchInfo: SBLaunchInfo): SBProcess
 
+    /**
+     * Kills the target's current process, if any, and launches a new one
+     * using the given launch info, in a single backend call.
+     *
+     * Maps to `SBProcess::Kill` followed by `SBTarget::Launch`.
+     *
+     * @return the launched process
+     * @throws SBError if launch fails
+     */
+    suspend fun relaunch(launchInfo: SBLaunchInfo): SBProcess
+
     /**
      * Attaches to an existing process.
      *
@@ -169,6 +264,28 @@ interface SBTarget {
      */
     suspend fun attach(attachInfo: SBAttachInfo): SBProcess
 
+    /**
+     * Connects to a GDB remote protocol server (`gdbserver`,
+     * `lldb-server gdbserver`, `debugserver`) at [url] and attaches to the
+     * process it controls.
+     *
+     * Maps to `SBTarget::ConnectRemote` with the `gdb-remote` plugin.
+     *
+     * @return the remote process
+     * @throws SBError if the connection fails
+     */
+    suspend fun connectRemote(url: String): SBProcess
+
+    /**
+     * Loads a core dump (ELF core or minidump) as this target's process.
+     *
+     * Maps to `SBTarget::LoadCore`.
+     *
+     * @return the post-mortem process, stopped as it was when the dump was written
+     * @throws SBError if the core cannot be loaded
+     */
+    suspend fun loadCore(corePath: String): SBProcess
+
     /**
      * Returns the current launch info for this target.
      *
@@ -203,6 +320,108 @@ interface SBTarget {
      * Maps to `SBTarget::GetPlatform`.
      */
     suspend fun platform(): SBPlatform
+
+    /**
+     * Returns the breakpoint with the given ID. The result may be invalid
+     * if no such breakpoint exists.
+     *
+     * Maps to `SBTarget::FindBreakpointByID`.
+     */
+    suspend fun findBreakpointById(id: Int): SBBreakpoint
+
+    /**
+     * Creates a breakpoint on every function named [symbolName].
+     *
+     * Maps to `SBTarget::BreakpointCreateByName`.
+     *
+     * @throws SBError if the breakpoint could not be created
+     */
+    suspend fun breakpointCreateByName(symbolName: String): SBBreakpoint
+
+    /**
+     * Creates a breakpoint on every function whose name matches [regex].
+     * Locations are added as matching modules load.
+     *
+     * Maps to `SBTarget::BreakpointCreateByRegex`.
+     *
+     * @throws SBError if the breakpoint could not be created
+     */
+    suspend fun breakpointCreateByRegex(regex: String): SBBreakpoint
+
+    /**
+     * Deletes the breakpoint with the given ID.
+     *
+     * Maps to `SBTarget::BreakpointDelete`.
+     *
+     * @return `true` if the breakpoint existed
+     */
+    suspend fun breakpointDelete(id: Int): Boolean
+}
+
+// ══════════════════════════════════════════════════════════════════════
+// SBBreakpoint
+// ══════════════════════════════════════════════════════════════════════
+
+/**
+ * A logical breakpoint (one or more resolved locations).
+ *
+ * Maps to LLDB's `SBBreakpoint`.
+ */
+interface SBBreakpoint {
+    /** Whether this breakpoint object is valid. Maps to `SBBreakpoint::IsValid`. */
+    suspend fun isValid(): Boolean
+
+    /** Returns the breakpoint ID. Maps to `SBBreakpoint::GetID`. */
+    suspend fun id(): Int
+
+    /**
+     * Returns the number of resolved locations.
+     *
+     * Maps to `SBBreakpoint::GetNumLocations`.
+     */
+    suspend fun numLocations(): Int
+
+    /**
+     * Sets the native expression that must be true for the breakpoint to
+     * stop, or clears it if [condition] is empty.
+     *
+     * Maps to `SBBreakpoint::SetCondition`.
+     */
+    suspend fun setCondition(condition: String)
+
+    /**
+     * Sets how many hits are skipped before the breakpoint stops.
+     *
+     * Maps to `SBBreakpoint::SetIgnoreCount`.
+     */
+    suspend fun setIgnoreCount(count: Int)
+
+    /**
+     * Adds [name] to the breakpoint's names (labels).
+     *
+     * Maps to `SBBreakpoint::AddNameWithErrorHandling`.
+     *
+     * @throws SBError if [name] is not a valid breakpoint name
+     */
+    suspend fun addName(name: String)
+
+    /**
+     * Installs a Python callback run on every hit. [body] is the body of a
+     * function taking `(frame, bp_loc, extra_args, internal_dict)`; it
+     * returns `False` to auto-continue instead of stopping.
+     *
+     * Maps to `SBBreakpoint::SetScriptCallbackBody`.
+     *
+     * @throws SBError if the body fails to compile
+     */
+    suspend fun setScriptCallbackBody(body: String)
+
+    /**
+     * Removes any callback or command list from this breakpoint.
+     *
+     * Maps to `breakpoint command delete` (the SB API has no direct equivalent).
+     */
+    suspend fun clearCallback()
 }
 
 // ══════════════════════════════════════════════════════════════════════
@@ -268,6 +487,16 @@ interface SBProcess {
      */
     suspend fun detach(keepStopped: Boolean = false)
 
+    /**
+     * Writes [text] to the process's stdin, when LLDB owns the process's
+     * stdio (a local launch without stdin redirection).
+     *
+     * Maps to `SBProcess::PutSTDIN`.
+     *
+     * @return the number of bytes written
+     */
+    suspend fun putStdin(text: String): Int
+
     /**
      * Sends a Unix signal to the process.
      *
@@ -284,6 +513,159 @@ interface SBProcess {
      * Maps to `SBProcess::GetUnixSignals`.
      */
     suspend fun unixSignals(): SBUnixSignals
+
+    /**
+     * Returns the currently selected thread.
+     *
+     * Maps to `SBProcess::GetSelectedThread`.
+     */
+    suspend fun selectedThread(): SBThread
+
+    /**
+     * Returns all threads of the process, in LLDB's index order.
+     *
+     * Maps to `SBProcess::GetNumThreads` / `SBProcess::GetThreadAtIndex`.
+     */
+    suspend fun threads(): List<SBThread>
+}
+
+// ══════════════════════════════════════════════════════════════════════
+// SBThread
+// ══════════════════════════════════════════════════════════════════════
+
+/**
+ * A thread of a debugged process.
+ *
+ * Maps to LLDB's `SBThread`.
+ */
+interface SBThread {
+    /** Whether this thread object is valid. Maps to `SBThread::IsValid`. */
+    suspend fun isValid(): Boolean
+
+    /**
+     * Returns the globally unique thread ID (the value reported to DAP clients).
+     *
+     * Maps to `SBThread::GetThreadID`.
+     */
+    suspend fun threadId(): Long
+
+    /**
+     * Returns why the thread last stopped.
+     *
+     * Maps to `SBThread::GetStopReason`.
+     */
+    suspend fun stopReason(): StopReason
+
+    /**
+     * Returns a human-readable description of the last stop
+     * (e.g., `"signal SIGSTOP"`, `"breakpoint 1.1"`).
+     *
+     * Maps to `SBThread::GetStopDescription`.
+     */
+    suspend fun stopDescription(): String
+
+    /**
+     * Resumes this thread under a Python scripted thread plan whose class
+     * is already defined in LLDB's script interpreter.
+     *
+     * Maps to `SBThread::StepUsingScriptedThreadPlan` (resuming immediately).
+     *
+     * @throws SBError if the plan cannot be queued
+     */
+    suspend fun stepUsingScriptedThreadPlan(className: String)
+
+    /**
+     * Returns the thread's selected frame (lldb-dap selects the frame a
+     * client asks `scopes` for).
+     *
+     * Maps to `SBThread::GetSelectedFrame`.
+     */
+    suspend fun selectedFrame(): SBFrame
+
+    /**
+     * Keeps this thread stopped when the process next resumes. The
+     * setting persists across stops until [resume] is called.
+     *
+     * Maps to `SBThread::Suspend`.
+     *
+     * @throws SBError if the thread cannot be suspended
+     */
+    suspend fun suspend()
+
+    /**
+     * Lets this thread run when the process next resumes; it does not
+     * resume the process itself.
+     *
+     * Maps to `SBThread::Resume`.
+     *
+     * @throws SBError if the thread cannot be resumed
+     */
+    suspend fun resume()
+}
+
+// ══════════════════════════════════════════════════════════════════════
+// SBFrame
+// ══════════════════════════════════════════════════════════════════════
+
+/**
+ * A stack frame of a stopped thread.
+ *
+ * Maps to LLDB's `SBFrame`.
+ */
+interface SBFrame {
+    /** Whether this frame object is valid. Maps to `SBFrame::IsValid`. */
+    suspend fun isValid(): Boolean
+
+    /**
+     * Looks up a variable by path (e.g., `"self.items[0].name"`).
+     *
+     * Maps to `SBFrame::GetValueForVariablePath`.
+     */
+    suspend fun valueForVariablePath(path: String): SBValue
+}
+
+// ══════════════════════════════════════════════════════════════════════
+// SBValue
+// ══════════════════════════════════════════════════════════════════════
+
+/**
+ * A value in the debuggee (variable, member, or element).
+ *
+ * Maps to LLDB's `SBValue`.
+ */
+interface SBValue {
+    /** Whether this value object is valid. Maps to `SBValue::IsValid`. */
+    suspend fun isValid(): Boolean
+
+    /** Returns the value's type name. Maps to `SBValue::GetTypeName`. */
+    suspend fun typeName(): String
+
+    /**
+     * Whether the value's type is a pointer.
+     *
+     * Maps to `SBValue::GetType` / `SBType::IsPointerType`.
+     */
+    suspend fun isPointerType(): Boolean
+
+    /** Returns the value as an unsigned integer. Maps to `SBValue::GetValueAsUnsigned`. */
+    suspend fun valueAsUnsigned(): Long
+
+    /** Returns the number of children. Maps to `SBValue::GetNumChildren`. */
+    suspend fun numChildren(): Int
+
+    /** Returns the child at [index]. Maps to `SBValue::GetChildAtIndex`. */
+    suspend fun childAtIndex(index: Int): SBValue
+
+    /** Returns the member named [name]. Maps to `SBValue::GetChildMemberWithName`. */
+    suspend fun childMemberWithName(name: String): SBValue
+
+    /**
+     * Returns the value without its synthetic children provider, whose
+     * children are the type's members.
+     *
+     * Maps to `SBValue::GetNonSyntheticValue`.
+     */
+    suspend fun nonSyntheticValue(): SBValue
 }
 
 // ══════════════════════════════════════════════════════════════════════
@@ -473,6 +855,32 @@ interface SBPlatform {
      * path does not exist or is inaccessible.
      */
     suspend fun getFilePermissions(path: String): Int
+
+    /**
+     * Lists the processes running on this platform.
+     *
+     * Maps to `SBPlatform::GetAllProcesses`.
+     *
+     * @throws SBError if the platform cannot enumerate processes
+     */
+    suspend fun allProcesses(): List<SBProcessInfo>
+}
+
+// ══════════════════════════════════════════════════════════════════════
+// SBProcessInfo
+// ══════════════════════════════════════════════════════════════════════
+
+/**
+ * Summary information about a process running on a platform.
+ *
+ * Maps to LLDB's `SBProcessInfo`.
+ */
+interface SBProcessInfo {
+    /** Maps to `SBProcessInfo::GetProcessID`. */
+    suspend fun processId(): Long
+
+    /** Executable base name. Maps to `SBProcessInfo::GetName`. */
+    suspend fun name(): String
 }
 
 // ══════════════════════════════════════════════════════════════════════
@@ -531,6 +939,20 @@ interface SBUnixSignals {
      */
     suspend fun setShouldStop(signo: Int, stop: Boolean)
 
+    /**
+     * Returns whether the debugger stops when a signal is received.
+     *
+     * Maps to `SBUnixSignals::GetShouldStop`.
+     */
+    suspend fun shouldStop(signo: Int): Boolean
+
+    /**
+     * Returns the numbers of all signals known to the platform.
+     *
+     * Maps to `SBUnixSignals::GetNumSignals` / `GetSignalAtIndex`.
+     */
+    suspend fun signalNumbers(): List<Int>
+
     /**
      * Configures whether the debugger should notify the user when a signal
      * is received.
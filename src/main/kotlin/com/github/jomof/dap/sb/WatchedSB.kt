@@ -79,6 +79,11 @@ private class WatchedDebugger(
             real.getVariable(name)
         }
 
+    override suspend fun setCurrentPlatformSdkRoot(sysroot: String) =
+        watchSuspend(watcher, "SBDebugger", "setCurrentPlatformSdkRoot", "sysroot=$sysroot") {
+            real.setCurrentPlatformSdkRoot(sysroot)
+        }
+
     override suspend fun createTarget(
         program: String?,
         triple: String?,
@@ -107,6 +112,32 @@ private class WatchedDebugger(
         watchSuspend(watcher, "SBDebugger", "selectedPlatform", resultToString = { "SBPlatform" }) {
             WatchedPlatform(real.selectedPlatform(), watcher)
         }
+
+    override suspend fun commandInterpreter(): SBCommandInterpreter =
+        watchSuspend(watcher, "SBDebugger", "commandInterpreter",
+            resultToString = { "SBCommandInterpreter" }) {
+            WatchedCommandInterpreter(real.commandInterpreter(), watcher)
+        }
+}
+
+// ══════════════════════════════════════════════════════════════════════
+// SBCommandInterpreter decorator
+// ══════════════════════════════════════════════════════════════════════
+
+private class WatchedCommandInterpreter(
+    private val real: SBCommandInterpreter,
+    private val watcher: SBWatcher,
+) : SBCommandInterpreter {
+
+    override suspend fun handleCommand(command: String): String =
+        watchSuspend(watcher, "SBCommandInterpreter", "handleCommand", "command=$command") {
+            real.handleCommand(command)
+        }
+
+    override suspend fun handleCommandWithResult(command: String): SBCommandReturnObject =
+        watchSuspend(watcher, "SBCommandInterpreter", "handleCommandWithResult", "command=$command") {
+            real.handleCommandWithResult(command)
+        }
 }
 
 // ══════════════════════════════════════════════════════════════════════
@@ -129,6 +160,14 @@ private class WatchedTarget(
         }
     }
 
+    override suspend fun relaunch(launchInfo: SBLaunchInfo): SBProcess {
+        val innerInfo = if (launchInfo is WatchedLaunchInfo) launchInfo.real else launchInfo
+        return watchSuspend(watcher, "SBTarget", "relaunch", "launchInfo=SBLaunchInfo",
+            resultToString = { "SBProcess" }) {
+            WatchedProcess(real.relaunch(innerInfo), watcher)
+        }
+    }
+
     override suspend fun attach(attachInfo: SBAttachInfo): SBProcess {
         val innerInfo = if (attachInfo is WatchedAttachInfo) attachInfo.real else attachInfo
         return watchSuspend(watcher, "SBTarget", "attach", "attachInfo=SBAttachInfo",
@@ -137,6 +176,18 @@ private class WatchedTarget(
         }
     }
 
+    override suspend fun connectRemote(url: String): SBProcess =
+        watchSuspend(watcher, "SBTarget", "connectRemote", "url=$url",
+            resultToString = { "SBProcess" }) {
+            WatchedProcess(real.connectRemote(url), watcher)
+        }
+
+    override suspend fun loadCore(corePath: String): SBProcess =
+        watchSuspend(watcher, "SBTarget", "loadCore", "corePath=$corePath",
+            resultToString = { "SBProcess" }) {
+            WatchedProcess(real.loadCore(corePath), watcher)
+        }
+
     override suspend fun launchInfo(): SBLaunchInfo =
         watchSuspend(watcher, "SBTarget", "launchInfo", resultToString = { "SBLaunchInfo" }) {
             WatchedLaunchInfo(real.launchInfo(), watcher)
@@ -164,6 +215,77 @@ private class WatchedTarget(
         watchSuspend(watcher, "SBTarget", "platform", resultToString = { "SBPlatform" }) {
             WatchedPlatform(real.platform(), watcher)
         }
+
+    override suspend fun findBreakpointById(id: Int): SBBreakpoint =
+        watchSuspend(watcher, "SBTarget", "findBreakpointById", "id=$id",
+            resultToString = { "SBBreakpoint" }) {
+            WatchedBreakpoint(real.findBreakpointById(id), watcher)
+        }
+
+    override suspend fun breakpointCreateByName(symbolName: String): SBBreakpoint =
+        watchSuspend(watcher, "SBTarget", "breakpointCreateByName", "symbolName=$symbolName",
+            resultToString = { "SBBreakpoint" }) {
+            WatchedBreakpoint(real.breakpointCreateByName(symbolName), watcher)
+        }
+
+    override suspend fun breakpointCreateByRegex(regex: String): SBBreakpoint =
+        watchSuspend(watcher, "SBTarget", "breakpointCreateByRegex", "regex=$regex",
+            resultToString = { "SBBreakpoint" }) {
+            WatchedBreakpoint(real.breakpointCreateByRegex(regex), watcher)
+        }
+
+    override suspend fun breakpointDelete(id: Int): Boolean =
+        watchSuspend(watcher, "SBTarget", "breakpointDelete", "id=$id") {
+            real.breakpointDelete(id)
+        }
+}
+
+// ══════════════════════════════════════════════════════════════════════
+// SBBreakpoint decorator
+// ══════════════════════════════════════════════════════════════════════
+
+private class WatchedBreakpoint(
+    private val real: SBBreakpoint,
+    private val watcher: SBWatcher,
+) : SBBreakpoint {
+
+    override suspend fun isValid(): Boolean =
+        watchSuspend(watcher, "SBBreakpoint", "isValid") { real.isValid() }
+
+    override suspend fun id(): Int =
+        watchSuspend(watcher, "SBBreakpoint", "id") { real.id() }
+
+    override suspend fun numLocations(): Int =
+        watchSuspend(watcher, "SBBreakpoint", "numLocations") { real.numLocations() }
+
+    override suspend fun setCondition(condition: String) =
+        watchSuspend(watcher, "SBBreakpoint", "setCondition", "condition=$condition",
+            resultToString = { "Unit" }) {
+            real.setCondition(condition)
+        }
+
+    override suspend fun setIgnoreCount(count: Int) =
+        watchSuspend(watcher, "SBBreakpoint", "setIgnoreCount", "count=$count",
+            resultToString = { "Unit" }) {
+            real.setIgnoreCount(count)
+        }
+
+    override suspend fun addName(name: String) =
+        watchSuspend(watcher, "SBBreakpoint", "addName", "name=$name",
+            resultToString = { "Unit" }) {
+            real.addName(name)
+        }
+
+    override suspend fun setScriptCallbackBody(body: String) =
+        watchSuspend(watcher, "SBBreakpoint", "setScriptCallbackBody",
+            "body=${body.lines().size} lines", resultToString = { "Unit" }) {
+            real.setScriptCallbackBody(body)
+        }
+
+    override suspend fun clearCallback() =
+        watchSuspend(watcher, "SBBreakpoint", "clearCallback", resultToString = { "Unit" }) {
+            real.clearCallback()
+        }
 }
 
 // ══════════════════════════════════════════════════════════════════════
@@ -203,6 +325,11 @@ private class WatchedProcess(
             real.detach(keepStopped)
         }
 
+    override suspend fun putStdin(text: String): Int =
+        watchSuspend(watcher, "SBProcess", "putStdin", "text=$text") {
+            real.putStdin(text)
+        }
+
     override suspend fun signal(signo: Int) =
         watchSuspend(watcher, "SBProcess", "signal", "signo=$signo",
             resultToString = { "Unit" }) {
@@ -214,6 +341,122 @@ private class WatchedProcess(
             resultToString = { "SBUnixSignals" }) {
             WatchedUnixSignals(real.unixSignals(), watcher)
         }
+
+    override suspend fun selectedThread(): SBThread =
+        watchSuspend(watcher, "SBProcess", "selectedThread",
+            resultToString = { "SBThread" }) {
+            WatchedThread(real.selectedThread(), watcher)
+        }
+
+    override suspend fun threads(): List<SBThread> =
+        watchSuspend(watcher, "SBProcess", "threads",
+            resultToString = { "List<SBThread>(${it.size})" }) {
+            real.threads().map { WatchedThread(it, watcher) }
+        }
+}
+
+// ══════════════════════════════════════════════════════════════════════
+// SBThread decorator
+// ══════════════════════════════════════════════════════════════════════
+
+private class WatchedThread(
+    private val real: SBThread,
+    private val watcher: SBWatcher,
+) : SBThread {
+
+    override suspend fun isValid(): Boolean =
+        watchSuspend(watcher, "SBThread", "isValid") { real.isValid() }
+
+    override suspend fun threadId(): Long =
+        watchSuspend(watcher, "SBThread", "threadId") { real.threadId() }
+
+    override suspend fun stopReason(): StopReason =
+        watchSuspend(watcher, "SBThread", "stopReason") { real.stopReason() }
+
+    override suspend fun stopDescription(): String =
+        watchSuspend(watcher, "SBThread", "stopDescription") { real.stopDescription() }
+
+    override suspend fun stepUsingScriptedThreadPlan(className: String) =
+        watchSuspend(watcher, "SBThread", "stepUsingScriptedThreadPlan",
+            "className=$className", resultToString = { "Unit" }) {
+            real.stepUsingScriptedThreadPlan(className)
+        }
+
+    override suspend fun selectedFrame(): SBFrame =
+        watchSuspend(watcher, "SBThread", "selectedFrame", resultToString = { "SBFrame" }) {
+            WatchedFrame(real.selectedFrame(), watcher)
+        }
+
+    override suspend fun suspend() =
+        watchSuspend(watcher, "SBThread", "suspend", resultToString = { "Unit" }) {
+            real.suspend()
+        }
+
+    override suspend fun resume() =
+        watchSuspend(watcher, "SBThread", "resume", resultToString = { "Unit" }) {
+            real.resume()
+        }
+}
+
+// ══════════════════════════════════════════════════════════════════════
+// SBFrame decorator
+// ══════════════════════════════════════════════════════════════════════
+
+private class WatchedFrame(
+    private val real: SBFrame,
+    private val watcher: SBWatcher,
+) : SBFrame {
+
+    override suspend fun isValid(): Boolean =
+        watchSuspend(watcher, "SBFrame", "isValid") { real.isValid() }
+
+    override suspend fun valueForVariablePath(path: String): SBValue =
+        watchSuspend(watcher, "SBFrame", "valueForVariablePath", "path=$path",
+            resultToString = { "SBValue" }) {
+            WatchedValue(real.valueForVariablePath(path), watcher)
+        }
+}
+
+// ══════════════════════════════════════════════════════════════════════
+// SBValue decorator
+// ══════════════════════════════════════════════════════════════════════
+
+private class WatchedValue(
+    private val real: SBValue,
+    private val watcher: SBWatcher,
+) : SBValue {
+
+    override suspend fun isValid(): Boolean =
+        watchSuspend(watcher, "SBValue", "isValid") { real.isValid() }
+
+    override suspend fun typeName(): String =
+        watchSuspend(watcher, "SBValue", "typeName") { real.typeName() }
+
+    override suspend fun isPointerType(): Boolean =
+        watchSuspend(watcher, "SBValue", "isPointerType") { real.isPointerType() }
+
+    override suspend fun valueAsUnsigned(): Long =
+        watchSuspend(watcher, "SBValue", "valueAsUnsigned") { real.valueAsUnsigned() }
+
+    override suspend fun numChildren(): Int =
+        watchSuspend(watcher, "SBValue", "numChildren") { real.numChildren() }
+
+    override suspend fun childAtIndex(index: Int): SBValue =
+        watchSuspend(watcher, "SBValue", "childAtIndex", "index=$index",
+            resultToString = { "SBValue" }) {
+            WatchedValue(real.childAtIndex(index), watcher)
+        }
+
+    override suspend fun childMemberWithName(name: String): SBValue =
+        watchSuspend(watcher, "SBValue", "childMemberWithName", "name=$name",
+            resultToString = { "SBValue" }) {
+            WatchedValue(real.childMemberWithName(name), watcher)
+        }
+
+    override suspend fun nonSyntheticValue(): SBValue =
+        watchSuspend(watcher, "SBValue", "nonSyntheticValue", resultToString = { "SBValue" }) {
+            WatchedValue(real.nonSyntheticValue(), watcher)
+        }
 }
 
 // ══════════════════════════════════════════════════════════════════════
@@ -350,6 +593,12 @@ private class WatchedPlatform(
         watchSuspend(watcher, "SBPlatform", "getFilePermissions", "path=$path") {
             real.getFilePermissions(path)
         }
+
+    override suspend fun allProcesses(): List<SBProcessInfo> =
+        watchSuspend(watcher, "SBPlatform", "allProcesses",
+            resultToString = { "${it.size} processes" }) {
+            real.allProcesses()
+        }
 }
 
 // ══════════════════════════════════════════════════════════════════════
@@ -403,4 +652,12 @@ private class WatchedUnixSignals(
             "signo=$signo, notify=$notify", resultToString = { "Unit" }) {
             real.setShouldNotify(signo, notify)
         }
+
+    override suspend fun shouldStop(signo: Int): Boolean =
+        watchSuspend(watcher, "SBUnixSignals", "shouldStop", "signo=$signo") {
+            real.shouldStop(signo)
+        }
+
+    override suspend fun signalNumbers(): List<Int> =
+        watchSuspend(watcher, "SBUnixSignals", "signalNumbers") { real.signalNumbers() }
 }
@@ -77,16 +77,19 @@ class DapAttachSequenceTest {
         ),
 
         // ── After configurationDone ──────────────────────────────────
-        // CodeLLDB: reports the stop reason before the stopped event.
+        // Both servers report the stop reason before the stopped event.
         ExpectedEvent(
-            "stop reason output", codelldbOnly,
+            "stop reason output", both,
             event = OutputEvent(seq = 0, category = "stderr", output = "Stop reason: signal SIGSTOP\n"),
         ),
 
         // ── Stop on entry ────────────────────────────────────────────
+        // The thread id is dynamic; the description duplicates the
+        // stop reason output above.
         ExpectedEvent(
             "stopped event (entry)", both,
-            event = StoppedEvent(seq = 0),
+            event = StoppedEvent(seq = 0, reason = "signal", allThreadsStopped = true),
+            skip = setOf("threadId", "description", "text", "preserveFocusHint", "hitBreakpointIds"),
         ),
 
         // Both servers report the attached process after the stopped event.
@@ -106,9 +109,9 @@ class DapAttachSequenceTest {
         // Both servers send disconnect response and terminated event.
         ExpectedResponse("disconnect response", both, command = "disconnect"),
 
-        // CodeLLDB: sends a detach notification.
+        // Both servers send a detach notification.
         ExpectedEvent(
-            "detached notification", codelldbOnly,
+            "detached notification", both,
             event = OutputEvent(seq = 0, category = "console", output = "Detached from debuggee.\n"),
         ),
 
@@ -0,0 +1,94 @@
+package com.github.jomof
+
+import com.github.jomof.dap.DapFraming
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Assumptions.assumeTrue
+import org.junit.jupiter.api.Test
+import org.junit.jupiter.api.Timeout
+import org.junit.jupiter.api.io.TempDir
+import java.io.File
+import java.io.OutputStream
+import java.util.concurrent.TimeUnit
+
+/**
+ * Verifies that a `"coreDump"` launch loads a core saved from a crashed
+ * C++ debuggee and reports its process as stopped, with a stack the
+ * client can browse. The core is saved through LLDB's
+ * `process save-core`, whose minidump writer only runs on Linux.
+ */
+@Timeout(value = 120, unit = TimeUnit.SECONDS)
+class DapCoreDumpTest {
+
+    @TempDir
+    lateinit var dir: File
+
+    private val isLinux = System.getProperty("os.name").lowercase().contains("linux")
+
+    @Test
+    fun `core dump launch shows the crashed thread's stack`() {
+        assumeTrue(isLinux, "process save-core writes minidumps only on Linux")
+        val debuggee = Debuggee.CPP.resolve()
+        val core = File(dir, "crash.dmp")
+        saveCrashDump(debuggee, core)
+        assertTrue(core.isFile, "save-core wrote no core to $core")
+
+        ConnectionMode.STDIO.connect().use { ctx ->
+            val input = ctx.inputStream
+            val output = ctx.outputStream
+            if (ctx.initializeResponse == null) {
+                DapTestUtils.sendInitializeRequest(output)
+                DapTestUtils.readDapMessage(input)
+            }
+            DapTestUtils.sendLaunchRequest(output, seq = 2, program = debuggee.absolutePath,
+                extraArgs = mapOf("coreDump" to core.absolutePath))
+            DapTestUtils.readEventOfType(input, "initialized")
+            DapTestUtils.sendConfigurationDoneRequest(output, seq = 3)
+            val stopped = JSONObject(DapTestUtils.readEventOfType(input, "stopped", maxMessages = 500))
+            val threadId = stopped.getJSONObject("body").getLong("threadId")
+
+            sendRequest(output, seq = 4, command = "stackTrace", arguments = JSONObject().put("threadId", threadId))
+            val response = JSONObject(DapTestUtils.readResponseForRequestSeq(input, requestSeq = 4, maxMessages = 500))
+            assertTrue(response.getBoolean("success"), "stackTrace: $response\n${ctx.diagnostics()}")
+            val frames = response.getJSONObject("body").getJSONArray("stackFrames")
+            val names = (0 until frames.length()).map { frames.getJSONObject(it).optString("name") }
+            assertTrue(names.any { "main" in it }, "no main frame in $names")
+
+            DapTestUtils.sendDisconnectRequest(output, seq = 5)
+        }
+    }
+
+    /** Runs the debuggee's `crash` testcase to its fault and saves a core of it to [core]. */
+    private fun saveCrashDump(debuggee: File, core: File) {
+        ConnectionMode.STDIO.connect().use { ctx ->
+            val input = ctx.inputStream
+            val output = ctx.outputStream
+            if (ctx.initializeResponse == null) {
+                DapTestUtils.sendInitializeRequest(output)
+                DapTestUtils.readDapMessage(input)
+            }
+            DapTestUtils.sendLaunchRequest(output, seq = 2, program = debuggee.absolutePath,
+                extraArgs = mapOf("terminal" to "console", "args" to listOf("crash")))
+            DapTestUtils.readEventOfType(input, "initialized")
+            DapTestUtils.sendConfigurationDoneRequest(output, seq = 3)
+            DapTestUtils.readEventOfType(input, "stopped", maxMessages = 500)
+
+            DapTestUtils.sendEvaluateCommandRequest(output, seq = 4,
+                expression = "process save-core --plugin-name=minidump --style=full \"${core.absolutePath}\"")
+            val saved = JSONObject(DapTestUtils.readResponseForRequestSeq(input, requestSeq = 4, maxMessages = 500))
+            assertTrue(saved.getBoolean("success"), "save-core: $saved\n${ctx.diagnostics()}")
+
+            DapTestUtils.sendDisconnectRequest(output, seq = 5, terminateDebuggee = true)
+            DapTestUtils.readResponseForRequestSeq(input, requestSeq = 5, maxMessages = 500)
+        }
+    }
+
+    private fun sendRequest(output: OutputStream, seq: Int, command: String, arguments: JSONObject) {
+        DapFraming.writeMessage(output, JSONObject()
+            .put("type", "request")
+            .put("seq", seq)
+            .put("command", command)
+            .put("arguments", arguments)
+            .toString())
+    }
+}
@@ -18,11 +18,57 @@ import java.util.concurrent.TimeUnit
 class DapInitializeTest {
 
     /**
-     * Current capabilities from our KDAP server. KDAP is a transparent proxy in front
-     * of lldb-dap, so capabilities are identical to lldb-dap's. If KDAP ever augments
-     * capabilities (e.g. adding CodeLLDB-specific ones), this baseline should diverge.
+     * Current capabilities from our KDAP server. KDAP is a proxy in front of lldb-dap,
+     * so capabilities are lldb-dap's, plus the exception filters KDAP implements itself
+     * (Rust panics and signals), `setExpression`, `restartFrame`, `loadedSources`, and the `clipboard`
+     * evaluate context; `stepInTargets`, `gotoTargets`, and `restart` are lldb-dap's too, but it
+     * only advertises them after launch.
      */
-    private val expectedOurCapabilitiesBaseline get() = expectedLldbDapInitializeCapabilitiesBaseline
+    private val expectedOurCapabilitiesBaseline = """
+        {
+          "supportTerminateDebuggee": true,
+          "supportsCancelRequest": true,
+          "supportsClipboardContext": true,
+          "supportsBreakpointLocationsRequest": true,
+          "supportsCompletionsRequest": true,
+          "supportsConditionalBreakpoints": true,
+          "supportsConfigurationDoneRequest": true,
+          "supportsDataBreakpoints": true,
+          "supportsDelayedStackTraceLoading": true,
+          "supportsDisassembleRequest": true,
+          "supportsEvaluateForHovers": true,
+          "supportsExceptionFilterOptions": true,
+          "supportsExceptionInfoRequest": true,
+          "supportsFunctionBreakpoints": true,
+          "supportsGotoTargetsRequest": true,
+          "supportsHitConditionalBreakpoints": true,
+          "supportsInstructionBreakpoints": true,
+          "supportsLoadedSourcesRequest": true,
+          "supportsLogPoints": true,
+          "supportsModulesRequest": true,
+          "supportsReadMemoryRequest": true,
+          "supportsRestartFrame": true,
+          "supportsRestartRequest": true,
+          "supportsSetExpression": true,
+          "supportsSetVariable": true,
+          "supportsStepInTargetsRequest": true,
+          "supportsSteppingGranularity": true,
+          "supportsValueFormattingOptions": true,
+          "supportsWriteMemoryRequest": true,
+          "completionTriggerCharacters": [".", " ", "\t"],
+          "exceptionBreakpointFilters": [
+            { "filter": "cpp_catch",  "supportsCondition": true, "description": "C++ Catch",            "label": "C++ Catch" },
+            { "filter": "cpp_throw",  "supportsCondition": true, "description": "C++ Throw",            "label": "C++ Throw" },
+            { "filter": "objc_catch", "supportsCondition": true, "description": "Objective-C Catch",    "label": "Objective-C Catch" },
+            { "filter": "objc_throw", "supportsCondition": true, "description": "Objective-C Throw",    "label": "Objective-C Throw" },
+            { "filter": "rust_panic",     "default": true,  "description": "Stop when a Rust panic begins",             "label": "Rust: on panic" },
+            { "filter": "signal_SIGSEGV", "default": false, "description": "Stop when the debuggee receives SIGSEGV", "label": "Signal: SIGSEGV" },
+            { "filter": "signal_SIGABRT", "default": false, "description": "Stop when the debuggee receives SIGABRT", "label": "Signal: SIGABRT" },
+            { "filter": "signal_all",     "default": false, "description": "Stop when the debuggee receives any signal", "label": "Signal: all" }
+          ],
+          "${'$'}__lldb_version": "lldb version 21.1.8 (https://github.com/llvm/llvm-project revision 2078da43e25a4623cab2d0d60decddf709aaea28)\n  clang revision 2078da43e25a4623cab2d0d60decddf709aaea28\n  llvm revision 2078da43e25a4623cab2d0d60decddf709aaea28"
+        }
+    """.trimIndent()
 
     /** Current capabilities from lldb-dap (LLVM 21.1.8). */
     private val expectedLldbDapInitializeCapabilitiesBaseline = """
@@ -113,9 +159,13 @@ class DapInitializeTest {
                 // $__lldb_version is platform-specific: macOS/Linux include git revision
                 // details, Windows only returns "lldb version X.Y.Z". Exclude from strict
                 // comparison; the field is still in the baseline for documentation.
-                val diff = DapTestUtils.compareJsonStrict(
-                    actualCapabilities, baseline, excludeKeys = setOf("\$__lldb_version")
-                )
+                // KDAP advertises supportsStepBack only when rr is installed.
+                val excludeKeys = if (mode.serverKind == ServerKind.OUR_SERVER) {
+                    setOf("\$__lldb_version", "supportsStepBack")
+                } else {
+                    setOf("\$__lldb_version")
+                }
+                val diff = DapTestUtils.compareJsonStrict(actualCapabilities, baseline, excludeKeys = excludeKeys)
                 Assertions.assertTrue(diff == null) {
                     "Initialize capabilities differ from baseline ($mode):\n$diff\n\nActual capabilities:\n${actualCapabilities.toString(2)}"
                 }
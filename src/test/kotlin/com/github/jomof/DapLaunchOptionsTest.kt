@@ -0,0 +1,157 @@
+package com.github.jomof
+
+import com.github.jomof.dap.DapFraming
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Assumptions.assumeTrue
+import org.junit.jupiter.api.Test
+import org.junit.jupiter.api.Timeout
+import org.junit.jupiter.api.io.TempDir
+import java.io.File
+import java.util.concurrent.TimeUnit
+
+/**
+ * Tests that KDAP's launch options take effect: each test launches the
+ * C++ debuggee stopped at entry with options set, then reads back what
+ * they changed through LLDB commands and KDAP's requests.
+ */
+@Timeout(value = 120, unit = TimeUnit.SECONDS)
+class DapLaunchOptionsTest {
+
+    @TempDir
+    lateinit var dir: File
+
+    private val isWindows = System.getProperty("os.name").lowercase().contains("win")
+
+    @Test
+    fun `sysroot and solibSearchPath set the platform sysroot and search paths`() {
+        launchStopped(mapOf("sysroot" to "/opt/kdap-sysroot", "solibSearchPath" to listOf("/opt/kdap-lib"))) {
+            val status = command("platform status")
+            assertTrue(status.contains("Sysroot: /opt/kdap-sysroot"), status)
+            val execPaths = command("settings show target.exec-search-paths")
+            assertTrue(execPaths.contains("/opt/kdap-lib"), execPaths)
+            val debugPaths = command("settings show target.debug-file-search-paths")
+            assertTrue(debugPaths.contains("/opt/kdap-lib"), debugPaths)
+        }
+    }
+
+    @Test
+    fun `signals set the dispositions of the launched process`() {
+        assumeTrue(!isWindows, "Windows processes have no POSIX signals")
+        launchStopped(mapOf("signals" to mapOf("SIGUSR1" to "ignore", "SIGUSR2" to "pass"))) {
+            val table = request("kdap/signals", JSONObject().put("signals", JSONObject()))
+                .getJSONObject("body").getJSONArray("signals")
+            val dispositions = (0 until table.length()).map { table.getJSONObject(it) }
+                .associate { it.getString("name") to it.getString("disposition") }
+            assertEquals("ignore", dispositions["SIGUSR1"], "$dispositions")
+            assertEquals("pass", dispositions["SIGUSR2"], "$dispositions")
+        }
+    }
+
+    @Test
+    fun `trackAllocations turns on allocation queries`() {
+        launchStopped(mapOf("trackAllocations" to true)) {
+            val body = request("kdap/heap").getJSONObject("body")
+            assertTrue(body.has("count"), "$body")
+        }
+        launchStopped(emptyMap()) {
+            assertFalse(response(send("kdap/heap")).getBoolean("success"))
+        }
+    }
+
+    @Test
+    fun `initScript loads the script's console commands`() {
+        val script = File(dir, "debug.lua").apply {
+            writeText("kdap.command('kdap_ping', function(args) return 'pong ' .. args end)\n")
+        }
+        launchStopped(mapOf("initScript" to script.absolutePath)) {
+            send("evaluate", JSONObject().put("expression", "`kdap_ping 1").put("context", "repl"))
+            expectOutput("pong 1")
+        }
+    }
+
+    @Test
+    fun `visualizers load the types of their Natvis files`() {
+        val natvis = File(dir, "app.natvis").apply { writeText(NATVIS) }
+        launchStopped(mapOf("visualizers" to listOf(natvis.absolutePath))) {
+            assertEquals("1", command("script print(len(_kdap_natvis_types))").trim())
+        }
+    }
+
+    /**
+     * Launches the C++ debuggee with [options], stopped at entry, and runs
+     * [check] against it before disconnecting.
+     */
+    private fun launchStopped(options: Map<String, Any>, check: Stopped.() -> Unit) {
+        val debuggee = Debuggee.CPP.resolve()
+        ConnectionMode.STDIO.connect().use { ctx ->
+            DapTestUtils.sendInitializeRequest(ctx.outputStream)
+            DapTestUtils.readDapMessage(ctx.inputStream)
+            DapTestUtils.sendLaunchRequest(ctx.outputStream, seq = 2, program = debuggee.absolutePath,
+                extraArgs = options + mapOf("stopOnEntry" to true, "terminal" to "console"))
+            DapTestUtils.readEventOfType(ctx.inputStream, "initialized", maxMessages = 500)
+            DapTestUtils.sendConfigurationDoneRequest(ctx.outputStream, seq = 3)
+            DapTestUtils.readEventOfType(ctx.inputStream, "stopped", maxMessages = 500)
+            val stopped = Stopped(ctx)
+            stopped.check()
+            stopped.disconnect()
+        }
+    }
+
+    /** A session launched by [launchStopped], stopped at entry. */
+    private class Stopped(private val ctx: ConnectionContext) {
+        private var seq = 4
+
+        /** Sends the [command] request and returns its `seq`. */
+        fun send(command: String, arguments: JSONObject = JSONObject()): Int {
+            val requestSeq = seq++
+            val json = JSONObject().put("type", "request").put("seq", requestSeq).put("command", command)
+                .put("arguments", arguments).toString()
+            DapFraming.writeMessage(ctx.outputStream, json)
+            return requestSeq
+        }
+
+        /** The response to the request [requestSeq]. */
+        fun response(requestSeq: Int): JSONObject =
+            JSONObject(DapTestUtils.readResponseForRequestSeq(ctx.inputStream, requestSeq, maxMessages = 500))
+
+        /** Sends the [command] request and returns its response, which must succeed. */
+        fun request(command: String, arguments: JSONObject = JSONObject()): JSONObject {
+            val response = response(send(command, arguments))
+            assertTrue(response.getBoolean("success"), "$command failed: $response\n${ctx.diagnostics()}")
+            return response
+        }
+
+        /** Runs the LLDB [command] and returns its output. */
+        fun command(command: String): String =
+            request("evaluate", JSONObject().put("expression", command).put("context", "_command"))
+                .getJSONObject("body").getString("result")
+
+        /** Reads messages until an `output` event that contains [text]. */
+        fun expectOutput(text: String) {
+            repeat(500) {
+                val message = JSONObject(DapTestUtils.readDapMessage(ctx.inputStream))
+                if (message.optString("event") == "output" &&
+                    message.getJSONObject("body").optString("output").contains(text)) return
+            }
+            fail<Unit>("no output containing \"$text\"\n${ctx.diagnostics()}")
+        }
+
+        /** Ends the session, terminating the debuggee. */
+        fun disconnect() {
+            val requestSeq = seq++
+            DapTestUtils.sendDisconnectRequest(ctx.outputStream, requestSeq, terminateDebuggee = true)
+            DapTestUtils.readResponseForRequestSeq(ctx.inputStream, requestSeq, maxMessages = 500)
+        }
+    }
+
+    private companion object {
+        /** A Natvis file with one type. */
+        val NATVIS = """
+            <?xml version="1.0" encoding="utf-8"?>
+            <AutoVisualizer xmlns="http://schemas.microsoft.com/vstudio/debugger/natvis/2010">
+              <Type Name="Handle"><DisplayString>{id,x}</DisplayString></Type>
+            </AutoVisualizer>
+        """.trimIndent()
+    }
+}
@@ -443,6 +443,24 @@ class DapLaunchSequenceTest {
         runLaunchAndAssert(ConnectionMode.STDIO_CODELDB, Server.CODELLDB, "codelldb-rust", debuggee = Debuggee.RUST)
     }
 
+    // ── Tests (Kotlin/Native debuggee) ────────────────────────────────────
+    // Same expected sequences; skipped when kotlinc-native was not available.
+
+    @Test
+    fun `kdap produces expected launch events (kotlin)`() {
+        assumeTrue(Debuggee.KOTLIN.isAvailable(), "kotlin-debuggee was not built")
+        runLaunchAndAssert(ConnectionMode.STDIO, Server.KDAP, "kdap-kotlin", debuggee = Debuggee.KOTLIN)
+    }
+
+    // ── Tests (WebAssembly debuggee) ──────────────────────────────────────
+    // The Rust debuggee under wasmtime; skipped when it was not built.
+
+    @Test
+    fun `kdap produces expected launch events (wasm)`() {
+        assumeTrue(Debuggee.WASM.isAvailable(), "rust-debuggee.wasm was not built")
+        runLaunchAndAssert(ConnectionMode.STDIO, Server.KDAP, "kdap-wasm", debuggee = Debuggee.WASM)
+    }
+
     // --- stdio testcase (rust) ---
 
     @Test
@@ -522,7 +540,7 @@ class DapLaunchSequenceTest {
      * Runs a full launch lifecycle and asserts the message stream matches
      * [expected].
      *
-     * @param debuggee which debuggee binary to launch ([Debuggee.CPP] or [Debuggee.RUST]).
+     * @param debuggee which debuggee binary to launch ([Debuggee.CPP], [Debuggee.RUST], [Debuggee.KOTLIN], or [Debuggee.WASM]).
      * @param launchArgs extra arguments merged into the DAP `launch` request
      *   (e.g. `"name"`, `"terminal"`, `"args"`). These are passed directly to
      *   [DapTestUtils.sendLaunchRequest] as `extraArgs`.
@@ -0,0 +1,81 @@
+package com.github.jomof
+
+import com.github.jomof.dap.DapFraming
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+import org.junit.jupiter.api.Timeout
+import java.io.InputStream
+import java.io.OutputStream
+import java.util.concurrent.TimeUnit
+
+/**
+ * Tests that `pause` stops a debuggee blocked in a system call: the
+ * Rust `sleep` and `inf_loop` testcases stop with reason `pause` well
+ * before their sleep returns, and the stopped thread's top frame is the
+ * libc sleep wrapper.
+ */
+@Timeout(value = 120, unit = TimeUnit.SECONDS)
+class DapPauseTest {
+
+    @Test
+    fun `pause stops a sleeping debuggee`() {
+        assertPausesInSleep("sleep")
+    }
+
+    @Test
+    fun `pause stops a looping debuggee between sleeps`() {
+        assertPausesInSleep("inf_loop")
+    }
+
+    private fun assertPausesInSleep(testcase: String) {
+        val debuggee = Debuggee.RUST.resolve()
+        ConnectionMode.STDIO.connect().use { ctx ->
+            launchRunning(ctx.inputStream, ctx.outputStream, debuggee.absolutePath, testcase)
+            Thread.sleep(500)
+            val start = System.nanoTime()
+            sendRequest(ctx.outputStream, seq = 4, command = "pause")
+            val response = JSONObject(DapTestUtils.readResponseForRequestSeq(ctx.inputStream, 4, maxMessages = 500))
+            assertTrue(response.getBoolean("success"), "pause failed: $response\n${ctx.diagnostics()}")
+            val stopped = JSONObject(DapTestUtils.readEventOfType(ctx.inputStream, "stopped", maxMessages = 500))
+                .getJSONObject("body")
+            val elapsedMs = (System.nanoTime() - start) / 1_000_000
+            assertEquals("pause", stopped.getString("reason"), "stopped: $stopped")
+            assertTrue(elapsedMs < 5_000, "pause took $elapsedMs ms")
+
+            sendRequest(ctx.outputStream, seq = 5, command = "stackTrace",
+                arguments = JSONObject().put("threadId", stopped.getInt("threadId")).put("levels", 20))
+            val frames = JSONObject(DapTestUtils.readResponseForRequestSeq(ctx.inputStream, 5, maxMessages = 500))
+                .getJSONObject("body").getJSONArray("stackFrames")
+            val top = frames.getJSONObject(0).getString("name")
+            assertTrue(SLEEP_WRAPPER.containsMatchIn(top), "top frame: $top\n$frames")
+            assertTrue((0 until frames.length()).any { frames.getJSONObject(it).getString("name").contains("main") },
+                "no main frame: $frames")
+
+            DapTestUtils.sendDisconnectRequest(ctx.outputStream, seq = 6, terminateDebuggee = true)
+            DapTestUtils.readResponseForRequestSeq(ctx.inputStream, 6, maxMessages = 500)
+        }
+    }
+
+    /** Launches [program] with [testcase] and waits until it was resumed. */
+    private fun launchRunning(input: InputStream, output: OutputStream, program: String, testcase: String) {
+        DapTestUtils.sendInitializeRequest(output)
+        DapTestUtils.readDapMessage(input)
+        DapTestUtils.sendLaunchRequest(output, seq = 2, program = program,
+            extraArgs = mapOf("args" to listOf(testcase), "terminal" to "console"))
+        DapTestUtils.readEventOfType(input, "initialized", maxMessages = 500)
+        DapTestUtils.sendConfigurationDoneRequest(output, seq = 3)
+        DapTestUtils.readResponseForRequestSeq(input, 3, maxMessages = 500)
+    }
+
+    private fun sendRequest(output: OutputStream, seq: Int, command: String, arguments: JSONObject = JSONObject()) {
+        val json = JSONObject().put("type", "request").put("seq", seq).put("command", command)
+            .put("arguments", arguments).toString()
+        DapFraming.writeMessage(output, json)
+    }
+
+    private companion object {
+        /** Sleep system call wrappers: glibc and musl, macOS, and Windows. */
+        val SLEEP_WRAPPER = Regex("nanosleep|__semwait_signal|NtDelayExecution", RegexOption.IGNORE_CASE)
+    }
+}
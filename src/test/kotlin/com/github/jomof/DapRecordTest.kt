@@ -0,0 +1,70 @@
+package com.github.jomof
+
+import com.github.jomof.dap.DapFraming
+import com.github.jomof.dap.debugsession.rrExecutable
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Assumptions.assumeTrue
+import org.junit.jupiter.api.Test
+import org.junit.jupiter.api.Timeout
+import java.io.InputStream
+import java.io.OutputStream
+import java.util.concurrent.TimeUnit
+
+/**
+ * Verifies that a `"record": true` launch runs the C++ debuggee under
+ * `rr record`, relays its output, and then debugs the replay: the session
+ * stops at the start of the recording and runs to the recorded exit.
+ * Skipped when `rr` is not on `PATH`.
+ */
+@Timeout(value = 120, unit = TimeUnit.SECONDS)
+class DapRecordTest {
+
+    @Test
+    fun `recorded launch relays output and replays to the recorded exit`() {
+        assumeTrue(rrExecutable != null, "rr is not on PATH")
+        val debuggee = Debuggee.CPP.resolve()
+        ConnectionMode.STDIO.connect().use { ctx ->
+            val input = ctx.inputStream
+            val output = ctx.outputStream
+            if (ctx.initializeResponse == null) {
+                DapTestUtils.sendInitializeRequest(output)
+                DapTestUtils.readDapMessage(input)
+            }
+            DapTestUtils.sendLaunchRequest(output, seq = 2, program = debuggee.absolutePath,
+                extraArgs = mapOf("record" to true, "stopOnEntry" to true, "args" to listOf("stdio")))
+            val (text, stopped) = outputUntilStopped(input, output, configurationDoneSeq = 3)
+            assertTrue("stdout" in text, "recorded output not relayed: $text\n${ctx.diagnostics()}")
+
+            val threadId = stopped.getJSONObject("body").getLong("threadId")
+            DapFraming.writeMessage(output, JSONObject()
+                .put("type", "request")
+                .put("seq", 4)
+                .put("command", "continue")
+                .put("arguments", JSONObject().put("threadId", threadId))
+                .toString())
+            val exited = JSONObject(DapTestUtils.readEventOfType(input, "exited", maxMessages = 500))
+            assertEquals(0, exited.getJSONObject("body").getInt("exitCode"))
+
+            DapTestUtils.sendDisconnectRequest(output, seq = 5)
+        }
+    }
+
+    /**
+     * Reads messages until the `stopped` event, answering `initialized`
+     * with `configurationDone`, and returns the output text sent before it
+     * together with the event.
+     */
+    private fun outputUntilStopped(input: InputStream, output: OutputStream, configurationDoneSeq: Int): Pair<String, JSONObject> {
+        val text = StringBuilder()
+        repeat(500) {
+            val message = JSONObject(DapTestUtils.readDapMessage(input))
+            when (message.optString("event")) {
+                "initialized" -> DapTestUtils.sendConfigurationDoneRequest(output, configurationDoneSeq)
+                "output" -> text.append(message.getJSONObject("body").optString("output"))
+                "stopped" -> return text.toString() to message
+            }
+        }
+        throw AssertionError("no stopped event; output so far: $text")
+    }
+}
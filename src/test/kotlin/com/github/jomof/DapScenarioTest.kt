@@ -0,0 +1,180 @@
+package com.github.jomof
+
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+import org.junit.jupiter.api.Timeout
+import java.io.File
+import java.util.concurrent.TimeUnit
+
+/**
+ * End-to-end tests of the Rust debuggee's scenarios through a live KDAP
+ * session ([KdapTestSession]): the variables at the `#BP_primitives`,
+ * `#BP_enums`, `#BP_strings`, `#BP_boxes`, and `#BP_maps` markers,
+ * stepping into nested calls, watches calling debuggee functions at
+ * `#BP_calls` (one that returns, one that times out, one that aborts), a
+ * panic stopping on the `rust_panic` filter, and a debuggee that spawns
+ * a child process.
+ */
+@Timeout(value = 120, unit = TimeUnit.SECONDS)
+class DapScenarioTest {
+
+    private fun stoppedAt(marker: String): KdapTestSession {
+        val session = KdapTestSession.launch(Debuggee.RUST.resolve())
+            .args("vars")
+            .breakpoint(RUST_SOURCE, KdapTestSession.markerLine(RUST_SOURCE, marker))
+            .start()
+        session.expectStopped("breakpoint")
+        return session
+    }
+
+    /** The error message of a watch of [expression], which must fail. */
+    private fun failedCall(session: KdapTestSession, expression: String): String {
+        val response = session.tryRequest("evaluate", JSONObject().put("expression", expression).put("context", "watch")
+            .put("frameId", session.topFrame().getInt("id")))
+        assertFalse(response.optBoolean("success"), "evaluate: $response")
+        return response.optString("message")
+    }
+
+    @Test
+    fun `primitives show their values`() {
+        stoppedAt("#BP_primitives").use { session ->
+            session.assertVariable("locals", "bool_", "true")
+            session.assertVariable("locals", "i32_", "-32")
+            session.assertVariable("locals", "u32_", "32")
+            session.assertVariable("locals", "i64_", "-64")
+            session.assertVariable("locals", "usize_", "2")
+            assertEquals("-30", session.evaluate("i32_ + 2"))
+        }
+    }
+
+    @Test
+    fun `enums show their active variant`() {
+        stoppedAt("#BP_enums").use { session ->
+            session.assertVariable("locals", "reg_enum2", "B(100, 200)")
+            session.assertVariable("locals", "opt_str1", "Some(\"string\")")
+            session.assertVariable("locals", "opt_str2", "None")
+            session.assertVariable("locals", "result_ok", "Ok(\"ok\")")
+            session.assertVariable("locals", "result_err", "Err(\"err\")")
+        }
+    }
+
+    @Test
+    fun `long strings are cut to the preview length with their raw bytes below`() {
+        KdapTestSession.launch(Debuggee.RUST.resolve())
+            .args("vars")
+            .launchArgument("_adapterSettings", JSONObject().put("stringPreviewLength", 16))
+            .breakpoint(RUST_SOURCE, KdapTestSession.markerLine(RUST_SOURCE, "#BP_strings"))
+            .start().use { session ->
+                session.expectStopped("breakpoint")
+                val long = session.variable("locals", "long_string")
+                assertEquals("\"${"x".repeat(16)}\"... (10000 bytes)", long.getString("value"))
+                assertTrue(long.has("memoryReference"), "long_string: $long")
+                val raw = session.children(long.getInt("variablesReference")).single()
+                assertEquals("[raw bytes]", raw.getString("name"))
+                assertEquals(10_000, raw.optInt("indexedVariables"), "raw bytes: $raw")
+                assertEquals("(5) b\"hello\"", session.evaluate("bytes,s"))
+                assertEquals("(5) 68 65 6c 6c 6f", session.evaluate("bytes,x"))
+            }
+    }
+
+    @Test
+    fun `trait objects show their concrete value`() {
+        stoppedAt("#BP_boxes").use { session ->
+            session.assertVariable("locals", "dyn_box", "(3) vec![1, 2, 3]")
+            session.assertVariable("locals", "dyn_ref", "(3) vec![12, 34, 56]")
+            val children = session.children(session.variable("locals", "dyn_box").getInt("variablesReference"))
+            assertEquals(listOf("[0]", "[1]", "[2]"), children.map { it.getString("name") })
+        }
+    }
+
+    @Test
+    fun `maps show their entries`() {
+        stoppedAt("#BP_maps").use { session ->
+            session.assertVariable("locals", "btree", """(4) {"Conan": 29, "Einar": 25, "Harald": 12, "Olaf": 24}""")
+            val hash = session.variable("locals", "hash")
+            assertTrue(hash.getString("value").startsWith("(4) "), "hash: $hash")
+            val entries = session.children(hash.getInt("variablesReference")).map { it.getString("name") }
+            assertTrue(entries.containsAll(listOf("[\"Einar\"]", "[\"Olaf\"]", "[\"Harald\"]", "[\"Conan\"]")), "entries: $entries")
+        }
+    }
+
+    @Test
+    fun `step in enters each nested call`() {
+        KdapTestSession.launch(Debuggee.RUST.resolve())
+            .args("vars")
+            .breakpoint(RUST_SOURCE, KdapTestSession.markerLine(RUST_SOURCE, "add3(get1(),"))
+            .start().use { session ->
+                session.expectStopped("breakpoint")
+                session.stepIn()
+                assertTrue(session.topFrame().getString("name").contains("get1"), "frame: ${session.topFrame()}")
+                session.stepOut()
+                session.stepIn()
+                assertTrue(session.topFrame().getString("name").contains("get2"), "frame: ${session.topFrame()}")
+            }
+    }
+
+    @Test
+    fun `a watch calls a debuggee function`() {
+        stoppedAt("#BP_calls").use { session ->
+            assertEquals("5", session.evaluate("call_sum(a, b)"))
+            assertEquals("12", session.evaluate("call_sum(call_sum(a, b), 7)"))
+            session.assertVariable("locals", "sum", "5")
+        }
+    }
+
+    @Test
+    fun `a call that does not return times out and leaves the thread where it was`() {
+        KdapTestSession.launch(Debuggee.RUST.resolve())
+            .args("vars")
+            .launchArgument("_adapterSettings", JSONObject().put("evaluationTimeout", 1))
+            .breakpoint(RUST_SOURCE, KdapTestSession.markerLine(RUST_SOURCE, "#BP_calls"))
+            .start().use { session ->
+                session.expectStopped("breakpoint")
+                val line = session.topFrame().getInt("line")
+                val message = failedCall(session, "call_spin()")
+                assertTrue(message.contains("did not return within 1 s"), "evaluate: $message")
+                assertEquals(line, session.topFrame().getInt("line"))
+                assertEquals("5", session.evaluate("call_sum(a, b)"))
+            }
+    }
+
+    @Test
+    fun `a call that aborts is abandoned and leaves the thread where it was`() {
+        stoppedAt("#BP_calls").use { session ->
+            val line = session.topFrame().getInt("line")
+            val message = failedCall(session, "call_abort()")
+            assertTrue(message.contains("stopped before returning"), "evaluate: $message")
+            assertEquals(line, session.topFrame().getInt("line"))
+            session.assertVariable("locals", "sum", "5")
+        }
+    }
+
+    @Test
+    fun `a panic stops on the rust_panic filter with its message`() {
+        KdapTestSession.launch(Debuggee.RUST.resolve())
+            .args("panic")
+            .exceptionFilters("rust_panic")
+            .start().use { session ->
+                session.expectStopped("exception")
+                val info = session.request("exceptionInfo", JSONObject().put("threadId", session.threadId))
+                assertEquals("rust_panic", info.getString("exceptionId"))
+                assertTrue(info.getString("description").contains("Oops!!!"), "exceptionInfo: $info")
+            }
+    }
+
+    @Test
+    fun `a spawning debuggee runs its child to completion`() {
+        KdapTestSession.launch(Debuggee.RUST.resolve())
+            .args("spawn")
+            .start().use { session ->
+                val pid = session.expectOutput(Regex("""pid = (\d+)""")).groupValues[1].toLong()
+                assertTrue(pid > 0)
+                assertEquals(0, session.expectEvent("exited").getInt("exitCode"))
+            }
+    }
+
+    private companion object {
+        val RUST_SOURCE = File(System.getProperty("user.dir"), "debuggee/rust/src/rust-debuggee.rs")
+    }
+}
@@ -3,6 +3,8 @@ package com.github.jomof
 import com.github.jomof.dap.DapFraming
 import com.github.jomof.dap.DapSession
 import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.Watchdog
+import com.github.jomof.dap.kdapMarker
 import com.github.jomof.dap.messages.*
 import kotlinx.coroutines.*
 import org.json.JSONObject
@@ -11,6 +13,7 @@ import org.junit.jupiter.api.Test
 import org.junit.jupiter.api.Timeout
 import java.io.PipedInputStream
 import java.io.PipedOutputStream
+import java.util.concurrent.CountDownLatch
 import java.util.concurrent.TimeUnit
 
 /**
@@ -37,6 +40,9 @@ class DapSessionTest {
      */
     private class TestPipes(
         interceptor: DapSession.Interceptor = DapSession.Interceptor.PASS_THROUGH,
+        requestTimeouts: Map<String, Long> = DapSession.DEFAULT_REQUEST_TIMEOUTS,
+        watchdog: Watchdog = Watchdog(),
+        stopBackend: () -> Unit = {},
     ) : AutoCloseable {
         // Client → Session
         private val clientWritePipe = PipedOutputStream()
@@ -72,6 +78,9 @@ class DapSessionTest {
             backendInput = sessionReadBackend,
             backendOutput = sessionWriteBackend,
             interceptor = interceptor,
+            requestTimeouts = requestTimeouts,
+            watchdog = watchdog,
+            stopBackend = stopBackend,
         )
 
         override fun close() {
@@ -329,6 +338,297 @@ class DapSessionTest {
         }
     }
 
+    // ── Cancellation tests ───────────────────────────────────────────────
+
+    /** Cancel request JSON naming [requestId]. */
+    private fun cancelRequest(seq: Int, requestId: Int): String =
+        """{"type":"request","seq":$seq,"command":"cancel","arguments":{"requestId":$requestId}}"""
+
+    @Test
+    fun `cancel stops a cancellable async handler`() = runBlocking {
+        val neverDone = CompletableDeferred<Unit>()
+        val handlerCancelled = CompletableDeferred<Unit>()
+        val interceptor = DapSession.Interceptor { request ->
+            if (request is CancelRequest) {
+                RequestAction.Forward
+            } else {
+                RequestAction.HandleAsync(cancellable = true) { _, _ ->
+                    try {
+                        neverDone.await()
+                    } finally {
+                        handlerCancelled.complete(Unit)
+                    }
+                }
+            }
+        }
+
+        TestPipes(interceptor).use { pipes ->
+            val job = launch { pipes.session.run() }
+
+            DapFraming.writeMessage(pipes.clientOut, dapRequest(1, "variables"))
+            DapFraming.writeMessage(pipes.clientOut, cancelRequest(2, requestId = 1))
+
+            val cancelled = JSONObject(readMessage(pipes.clientIn)!!)
+            assertEquals(1, cancelled.getInt("request_seq"))
+            assertEquals("variables", cancelled.getString("command"))
+            assertFalse(cancelled.getBoolean("success"))
+            assertEquals("cancelled", cancelled.getString("message"))
+
+            val cancelResponse = JSONObject(readMessage(pipes.clientIn)!!)
+            assertEquals(2, cancelResponse.getInt("request_seq"))
+            assertEquals("cancel", cancelResponse.getString("command"))
+            assertTrue(cancelResponse.getBoolean("success"))
+
+            withTimeout(5_000) { handlerCancelled.await() }
+            shutdownAndJoin(pipes, job)
+            assertEquals(0, pipes.backendIn.available(), "Neither request should reach the backend")
+        }
+    }
+
+    @Test
+    fun `cancel for a request KDAP does not own is forwarded`() = runBlocking {
+        val interceptor = DapSession.Interceptor { request ->
+            if (request is CancelRequest) {
+                RequestAction.Forward
+            } else {
+                RequestAction.HandleAsync { _, _ -> awaitCancellation() }
+            }
+        }
+
+        TestPipes(interceptor).use { pipes ->
+            val job = launch { pipes.session.run() }
+
+            // Seq 1 is not cancellable; seq 7 was never seen.
+            DapFraming.writeMessage(pipes.clientOut, dapRequest(1, "launch"))
+            val forLaunch = cancelRequest(2, requestId = 1)
+            DapFraming.writeMessage(pipes.clientOut, forLaunch)
+            assertEquals(forLaunch, readMessage(pipes.backendIn))
+            val forUnknown = cancelRequest(3, requestId = 7)
+            DapFraming.writeMessage(pipes.clientOut, forUnknown)
+            assertEquals(forUnknown, readMessage(pipes.backendIn))
+
+            job.cancel()
+        }
+    }
+
+    // ── Concurrency and timeout tests ────────────────────────────────────
+
+    @Test
+    fun `async handlers run in parallel`() = runBlocking {
+        val secondAnswered = CountDownLatch(1)
+        val interceptor = DapSession.Interceptor { request ->
+            RequestAction.HandleAsync { _, ctx ->
+                // The first blocks its thread: only a parallel handler can release it.
+                if (request.seq == 1) assertTrue(secondAnswered.await(5, TimeUnit.SECONDS))
+                ctx.sendEventToClient(dapResponse(request.seq, request.command))
+                if (request.seq == 2) secondAnswered.countDown()
+            }
+        }
+
+        TestPipes(interceptor).use { pipes ->
+            val job = launch { pipes.session.run() }
+
+            DapFraming.writeMessage(pipes.clientOut, dapRequest(1, "stackTrace"))
+            DapFraming.writeMessage(pipes.clientOut, dapRequest(2, "threads"))
+            val answered = listOf(readMessage(pipes.clientIn), readMessage(pipes.clientIn))
+                .map { JSONObject(it!!).getInt("request_seq") }
+            assertEquals(listOf(2, 1), answered)
+
+            shutdownAndJoin(pipes, job)
+        }
+    }
+
+    @Test
+    fun `forwarded requests time out and are cancelled in the backend`() = runBlocking {
+        TestPipes(requestTimeouts = mapOf("stackTrace" to 100L)).use { pipes ->
+            val job = launch { pipes.session.run() }
+
+            DapFraming.writeMessage(pipes.clientOut, dapRequest(1, "stackTrace"))
+            readMessage(pipes.backendIn)
+
+            val timedOut = JSONObject(readMessage(pipes.clientIn)!!)
+            assertEquals(1, timedOut.getInt("request_seq"))
+            assertFalse(timedOut.getBoolean("success"))
+            assertEquals("timeout", timedOut.getString("message"))
+            val error = timedOut.getJSONObject("body").getJSONObject("error")
+            assertEquals(DapResponse.TIMED_OUT_ERROR_ID, error.getInt("id"))
+            assertEquals("100", error.getJSONObject("variables").getString("timeout"))
+
+            val cancel = JSONObject(readMessage(pipes.backendIn)!!)
+            assertEquals("cancel", cancel.getString("command"))
+            assertEquals(1, cancel.getJSONObject("arguments").getInt("requestId"))
+
+            // The cancel's and the late stackTrace's responses are dropped.
+            DapFraming.writeMessage(pipes.backendOut, dapResponse(cancel.getInt("seq"), "cancel"))
+            DapFraming.writeMessage(pipes.backendOut, dapResponse(1, "stackTrace"))
+            DapFraming.writeMessage(pipes.backendOut, dapEvent("stopped"))
+            assertEquals(dapEvent("stopped"), readMessage(pipes.clientIn))
+
+            shutdownAndJoin(pipes, job)
+        }
+    }
+
+    @Test
+    fun `responses in time stop the timer`() = runBlocking {
+        TestPipes(requestTimeouts = mapOf("threads" to 300L)).use { pipes ->
+            val job = launch { pipes.session.run() }
+
+            DapFraming.writeMessage(pipes.clientOut, dapRequest(1, "threads"))
+            readMessage(pipes.backendIn)
+            DapFraming.writeMessage(pipes.backendOut, dapResponse(1, "threads"))
+            assertEquals(dapResponse(1, "threads"), readMessage(pipes.clientIn))
+
+            delay(600)
+            DapFraming.writeMessage(pipes.backendOut, dapEvent("stopped"))
+            assertEquals(dapEvent("stopped"), readMessage(pipes.clientIn))
+
+            shutdownAndJoin(pipes, job)
+        }
+    }
+
+    @Test
+    fun `cancellable async handlers time out`() = runBlocking {
+        val handlerCancelled = CompletableDeferred<Unit>()
+        val interceptor = DapSession.Interceptor {
+            RequestAction.HandleAsync(cancellable = true) { _, _ ->
+                try {
+                    awaitCancellation()
+                } finally {
+                    handlerCancelled.complete(Unit)
+                }
+            }
+        }
+
+        TestPipes(interceptor, requestTimeouts = mapOf("variables" to 100L)).use { pipes ->
+            val job = launch { pipes.session.run() }
+
+            DapFraming.writeMessage(pipes.clientOut, dapRequest(1, "variables"))
+            val timedOut = JSONObject(readMessage(pipes.clientIn)!!)
+            assertEquals(1, timedOut.getInt("request_seq"))
+            assertEquals("variables", timedOut.getString("command"))
+            assertEquals("timeout", timedOut.getString("message"))
+
+            withTimeout(5_000) { handlerCancelled.await() }
+            shutdownAndJoin(pipes, job)
+            assertEquals(0, pipes.backendIn.available(), "The request should not reach the backend")
+        }
+    }
+
+    @Test
+    fun `a stalled request is reported and disconnect force-detaches`() = runBlocking {
+        val stopped = CountDownLatch(1)
+        TestPipes(watchdog = Watchdog(stallTimeoutMs = 50), stopBackend = { stopped.countDown() }).use { pipes ->
+            val job = launch { pipes.session.run() }
+
+            DapFraming.writeMessage(pipes.clientOut, dapRequest(1, "next"))
+            readMessage(pipes.backendIn)
+
+            val output = JSONObject(readMessage(pipes.clientIn)!!)
+            assertEquals("output", output.getString("event"))
+            assertTrue(output.getJSONObject("body").getString("output").contains("'next'"), "output: $output")
+
+            DapFraming.writeMessage(pipes.clientOut, dapRequest(2, "disconnect"))
+            val failed = JSONObject(readMessage(pipes.clientIn)!!)
+            assertEquals(1, failed.getInt("request_seq"))
+            assertFalse(failed.getBoolean("success"))
+            val disconnected = JSONObject(readMessage(pipes.clientIn)!!)
+            assertEquals(2, disconnected.getInt("request_seq"))
+            assertTrue(disconnected.getBoolean("success"))
+            assertEquals("terminated", JSONObject(readMessage(pipes.clientIn)!!).getString("event"))
+
+            job.join()
+            assertTrue(stopped.await(1, TimeUnit.SECONDS), "the backend was not stopped")
+        }
+    }
+
+    @Test
+    fun `a stalled request KDAP handles is reported and its handler cancelled by a force-detach`() = runBlocking {
+        val handlerCancelled = CompletableDeferred<Unit>()
+        val interceptor = DapSession.Interceptor { request ->
+            if (request.command != "threads") {
+                RequestAction.Forward
+            } else {
+                RequestAction.HandleAsync { _, ctx ->
+                    try {
+                        ctx.sendRequestToBackendAndAwait(dapRequest(0, "threads"))
+                        ctx.sendEventToClient(dapResponse(request.seq, request.command))
+                    } finally {
+                        handlerCancelled.complete(Unit)
+                    }
+                }
+            }
+        }
+        TestPipes(interceptor, watchdog = Watchdog(stallTimeoutMs = 50)).use { pipes ->
+            val job = launch { pipes.session.run() }
+
+            DapFraming.writeMessage(pipes.clientOut, dapRequest(1, "threads"))
+            readMessage(pipes.backendIn)
+
+            val output = JSONObject(readMessage(pipes.clientIn)!!)
+            assertTrue(output.getJSONObject("body").getString("output").contains("'threads'"), "output: $output")
+
+            DapFraming.writeMessage(pipes.clientOut, dapRequest(2, "disconnect"))
+            val failed = JSONObject(readMessage(pipes.clientIn)!!)
+            assertEquals(1, failed.getInt("request_seq"))
+            assertFalse(failed.getBoolean("success"))
+            assertEquals(2, JSONObject(readMessage(pipes.clientIn)!!).getInt("request_seq"))
+            assertEquals("terminated", JSONObject(readMessage(pipes.clientIn)!!).getString("event"))
+
+            job.join()
+            withTimeout(5_000) { handlerCancelled.await() }
+        }
+    }
+
+    @Test
+    fun `disconnect is forwarded while nothing is stalled`() = runBlocking {
+        TestPipes(watchdog = Watchdog(stallTimeoutMs = 60_000)).use { pipes ->
+            val job = launch { pipes.session.run() }
+
+            DapFraming.writeMessage(pipes.clientOut, dapRequest(1, "disconnect"))
+            assertEquals(dapRequest(1, "disconnect"), readMessage(pipes.backendIn))
+
+            shutdownAndJoin(pipes, job)
+        }
+    }
+
+    @Test
+    fun `request progress ids map back to their request`() {
+        assertEquals(42, DapSession.progressRequestSeq(DapSession.requestProgressId(42)))
+        assertNull(DapSession.progressRequestSeq("lldb-dap-progress-3"))
+    }
+
+    // ── Silent request tests ─────────────────────────────────────────────
+
+    @Test
+    fun `marked output is forwarded while a silent request is in flight`() = runBlocking {
+        val interceptor = DapSession.Interceptor { request ->
+            RequestAction.HandleAsync { _, ctx ->
+                ctx.sendSilentRequestToBackendAndAwait(
+                    """{"type":"request","seq":0,"command":"evaluate","arguments":{"expression":"script 1","context":"repl"}}""")
+                ctx.sendEventToClient(dapResponse(request.seq, request.command))
+            }
+        }
+
+        TestPipes(interceptor).use { pipes ->
+            val job = launch { pipes.session.run() }
+
+            DapFraming.writeMessage(pipes.clientOut, dapRequest(1, "threads"))
+            val evaluate = JSONObject(readMessage(pipes.backendIn)!!)
+
+            // The evaluate's auto-display is dropped; debuggee output copied
+            // from the pty meanwhile is not.
+            val ptyOutput = OutputEvent(seq = 0, category = "console", output = "${kdapMarker("pty")}hello\n").toJson()
+            DapFraming.writeMessage(pipes.backendOut, OutputEvent(seq = 0, category = "console", output = "1\n").toJson())
+            DapFraming.writeMessage(pipes.backendOut, ptyOutput)
+            DapFraming.writeMessage(pipes.backendOut, dapResponse(evaluate.getInt("seq"), "evaluate"))
+
+            assertEquals(ptyOutput, readMessage(pipes.clientIn))
+            assertEquals(1, JSONObject(readMessage(pipes.clientIn)!!).getInt("request_seq"))
+
+            shutdownAndJoin(pipes, job)
+        }
+    }
+
     // ── Disconnect tests ─────────────────────────────────────────────────
 
     @Test
@@ -0,0 +1,55 @@
+package com.github.jomof
+
+import com.github.jomof.dap.DapFraming
+import com.github.jomof.dap.messages.StdinRequest
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+import org.junit.jupiter.api.Timeout
+import java.util.concurrent.TimeUnit
+
+/**
+ * Verifies that `kdap/stdin` reaches a debuggee launched on the KDAP pty
+ * and that what the debuggee writes back reaches the client. The Rust
+ * debuggee's `echo` testcase answers each line it reads right away, while
+ * KDAP's own write to the pty may still be in flight, so the reply has to
+ * get past the suppression of silent SB API calls' console output.
+ */
+@Timeout(value = 120, unit = TimeUnit.SECONDS)
+class DapStdinTest {
+
+    @Test
+    fun `a line written with kdap stdin is echoed back by the debuggee`() {
+        val debuggee = Debuggee.RUST.resolve()
+        ConnectionMode.STDIO.connect().use { ctx ->
+            val input = ctx.inputStream
+            val output = ctx.outputStream
+            if (ctx.initializeResponse == null) {
+                DapTestUtils.sendInitializeRequest(output)
+                DapTestUtils.readDapMessage(input)
+            }
+            DapTestUtils.sendLaunchRequest(output, seq = 2, program = debuggee.absolutePath,
+                extraArgs = mapOf("terminal" to "console", "pty" to true, "args" to listOf("echo")))
+            DapTestUtils.readEventOfType(input, "initialized")
+            DapTestUtils.sendConfigurationDoneRequest(output, seq = 3)
+            DapTestUtils.readResponseForRequestSeq(input, requestSeq = 3, maxMessages = 500)
+
+            DapFraming.writeMessage(output, StdinRequest(seq = 4, text = "hello\n").toJson())
+            val echoed = StringBuilder()
+            var answered = false
+            var remaining = 500
+            while (!answered || "echo: hello" !in echoed) {
+                check(remaining-- > 0) { "no echo; output so far: $echoed\n${ctx.diagnostics()}" }
+                val message = JSONObject(DapTestUtils.readDapMessage(input))
+                if (message.optString("event") == "output") {
+                    echoed.append(message.getJSONObject("body").optString("output"))
+                } else if (message.optString("type") == "response" && message.optInt("request_seq") == 4) {
+                    assertTrue(message.getBoolean("success"), "kdap/stdin: $message")
+                    answered = true
+                }
+            }
+
+            DapTestUtils.sendDisconnectRequest(output, seq = 5, terminateDebuggee = true)
+        }
+    }
+}
@@ -0,0 +1,61 @@
+package com.github.jomof
+
+import com.github.jomof.dap.DapFraming
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+import org.junit.jupiter.api.Timeout
+import java.io.InputStream
+import java.io.OutputStream
+import java.util.concurrent.TimeUnit
+
+/**
+ * Tests that KDAP ends sessions whose debuggee keeps running: `terminate`
+ * kills a debuggee that ignores `SIGTERM` once its grace period is over,
+ * and `disconnect` with `terminateDebuggee` kills one that is sleeping.
+ * Both end with a `terminated` event.
+ */
+@Timeout(value = 120, unit = TimeUnit.SECONDS)
+class DapTerminateTest {
+
+    @Test
+    fun `terminate escalates when the debuggee ignores SIGTERM`() {
+        val debuggee = DapTestUtils.resolveDebuggeeBinary()
+        ConnectionMode.STDIO.connect().use { ctx ->
+            launchRunning(ctx.inputStream, ctx.outputStream, debuggee.absolutePath, "ignore_sigterm")
+            sendRequest(ctx.outputStream, seq = 4, command = "terminate")
+            val response = JSONObject(DapTestUtils.readResponseForRequestSeq(ctx.inputStream, 4, maxMessages = 500))
+            assertTrue(response.getBoolean("success"), "terminate failed: $response\n${ctx.diagnostics()}")
+            DapTestUtils.readEventOfType(ctx.inputStream, "terminated", maxMessages = 500)
+        }
+    }
+
+    @Test
+    fun `disconnect kills a sleeping debuggee`() {
+        val debuggee = Debuggee.RUST.resolve()
+        ConnectionMode.STDIO.connect().use { ctx ->
+            launchRunning(ctx.inputStream, ctx.outputStream, debuggee.absolutePath, "sleep")
+            DapTestUtils.sendDisconnectRequest(ctx.outputStream, seq = 4, terminateDebuggee = true)
+            val response = JSONObject(DapTestUtils.readResponseForRequestSeq(ctx.inputStream, 4, maxMessages = 500))
+            assertTrue(response.getBoolean("success"), "disconnect failed: $response\n${ctx.diagnostics()}")
+            DapTestUtils.readEventOfType(ctx.inputStream, "terminated", maxMessages = 500)
+        }
+    }
+
+    /** Launches [program] with [testcase] and waits until it was resumed. */
+    private fun launchRunning(input: InputStream, output: OutputStream, program: String, testcase: String) {
+        DapTestUtils.sendInitializeRequest(output)
+        DapTestUtils.readDapMessage(input)
+        DapTestUtils.sendLaunchRequest(output, seq = 2, program = program,
+            extraArgs = mapOf("args" to listOf(testcase), "terminal" to "console"))
+        DapTestUtils.readEventOfType(input, "initialized", maxMessages = 500)
+        DapTestUtils.sendConfigurationDoneRequest(output, seq = 3)
+        DapTestUtils.readResponseForRequestSeq(input, 3, maxMessages = 500)
+    }
+
+    private fun sendRequest(output: OutputStream, seq: Int, command: String) {
+        val json = JSONObject().put("type", "request").put("seq", seq).put("command", command)
+            .put("arguments", JSONObject()).toString()
+        DapFraming.writeMessage(output, json)
+    }
+}
@@ -16,15 +16,21 @@ enum class Debuggee {
     /** C++ debuggee (always available — built by cmake). */
     CPP,
     /** Rust debuggee (available when cargo was present at cmake time). */
-    RUST;
+    RUST,
+    /** Kotlin/Native debuggee (available when kotlinc-native was present at cmake time). */
+    KOTLIN,
+    /** Rust debuggee as a WASI module (available when cargo and wasmtime were present at cmake time). */
+    WASM;
 
     /**
      * Resolves the debuggee binary, or throws if not found.
-     * For [RUST], throws with a hint about needing cargo installed.
+     * For [RUST], [KOTLIN], and [WASM], throws with a hint about the toolchain needed.
      */
     fun resolve(): File = when (this) {
         CPP  -> DapTestUtils.resolveDebuggeeBinary()
         RUST -> DapTestUtils.resolveRustDebuggeeBinary()
+        KOTLIN -> DapTestUtils.resolveKotlinDebuggeeBinary()
+        WASM -> DapTestUtils.resolveWasmDebuggeeModule()
     }
 
     /** True if the debuggee binary exists and is executable. */
@@ -62,6 +68,29 @@ object DapTestUtils {
             ?: error("rust-debuggee binary not found — install cargo (rustup) and rebuild: cmake -B debuggee/build debuggee && cmake --build debuggee/build")
     }
 
+    /**
+     * Resolves the Kotlin/Native debuggee binary built by cmake (via kotlinc-native).
+     * Throws if not found — requires kotlinc-native at cmake time.
+     */
+    fun resolveKotlinDebuggeeBinary(): File {
+        val cwd = File(System.getProperty("user.dir"))
+        return listOf(
+            File(cwd, "debuggee/build/kotlin-debuggee.kexe"),
+            File(cwd, "debuggee/build/kotlin-debuggee.exe"),       // Windows
+        ).firstOrNull { it.isFile && it.canExecute() }
+            ?: error("kotlin-debuggee binary not found — install kotlinc-native and rebuild: cmake -B debuggee/build debuggee && cmake --build debuggee/build")
+    }
+
+    /**
+     * Resolves the WebAssembly build of the Rust debuggee built by cmake (via cargo).
+     * Throws if not found — requires cargo, the wasm32-wasip1 target, and wasmtime at cmake time.
+     */
+    fun resolveWasmDebuggeeModule(): File {
+        val cwd = File(System.getProperty("user.dir"))
+        return File(cwd, "debuggee/build/wasm/rust-debuggee.wasm").takeIf { it.isFile }
+            ?: error("rust-debuggee.wasm not found — install wasmtime and the wasm32-wasip1 target and rebuild: cmake -B debuggee/build debuggee && cmake --build debuggee/build")
+    }
+
     private const val CONTENT_LENGTH_PREFIX = "Content-Length: "
 
     /**
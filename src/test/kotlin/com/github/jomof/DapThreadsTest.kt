@@ -0,0 +1,50 @@
+package com.github.jomof
+
+import com.github.jomof.dap.DapFraming
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+import org.junit.jupiter.api.Timeout
+import java.io.OutputStream
+import java.util.concurrent.TimeUnit
+
+/**
+ * Tests that `threads` names threads like CodeLLDB: the paused Rust
+ * `sleep` testcase's main thread is listed as `1: tid=<pid> "<name>"`.
+ */
+@Timeout(value = 120, unit = TimeUnit.SECONDS)
+class DapThreadsTest {
+
+    @Test
+    fun `threads are named by index, tid, and name`() {
+        val debuggee = Debuggee.RUST.resolve()
+        ConnectionMode.STDIO.connect().use { ctx ->
+            DapTestUtils.sendInitializeRequest(ctx.outputStream)
+            DapTestUtils.readDapMessage(ctx.inputStream)
+            DapTestUtils.sendLaunchRequest(ctx.outputStream, seq = 2, program = debuggee.absolutePath,
+                extraArgs = mapOf("args" to listOf("sleep"), "terminal" to "console"))
+            DapTestUtils.readEventOfType(ctx.inputStream, "initialized", maxMessages = 500)
+            DapTestUtils.sendConfigurationDoneRequest(ctx.outputStream, seq = 3)
+            DapTestUtils.readResponseForRequestSeq(ctx.inputStream, 3, maxMessages = 500)
+
+            sendRequest(ctx.outputStream, seq = 4, command = "pause")
+            DapTestUtils.readEventOfType(ctx.inputStream, "stopped", maxMessages = 500)
+            sendRequest(ctx.outputStream, seq = 5, command = "threads")
+            val response = JSONObject(DapTestUtils.readResponseForRequestSeq(ctx.inputStream, 5, maxMessages = 500))
+            assertTrue(response.getBoolean("success"), "threads failed: $response\n${ctx.diagnostics()}")
+            val threads = response.getJSONObject("body").getJSONArray("threads")
+            val main = threads.getJSONObject(0)
+            assertTrue(Regex("""^1: tid=${main.getInt("id")}( ".+")?$""").matches(main.getString("name")),
+                "threads: $threads")
+
+            DapTestUtils.sendDisconnectRequest(ctx.outputStream, seq = 6, terminateDebuggee = true)
+            DapTestUtils.readResponseForRequestSeq(ctx.inputStream, 6, maxMessages = 500)
+        }
+    }
+
+    private fun sendRequest(output: OutputStream, seq: Int, command: String) {
+        val json = JSONObject().put("type", "request").put("seq", seq).put("command", command)
+            .put("arguments", JSONObject()).toString()
+        DapFraming.writeMessage(output, json)
+    }
+}
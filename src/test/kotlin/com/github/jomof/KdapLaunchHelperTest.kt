@@ -86,6 +86,33 @@ class KdapLaunchHelperTest {
         assertFalse(thread.isAlive, "kdap-launch should have exited")
     }
 
+    @Test
+    fun `holds the terminal until the adapter closes the connection`() {
+        val server = ServerSocket(0)
+        val port = server.localPort
+
+        val thread = Thread {
+            kdapLaunchMain(arrayOf("--connect=127.0.0.1:$port"))
+        }
+        thread.start()
+
+        val socket = server.accept()
+        socket.soTimeout = 5_000
+        socket.getInputStream().bufferedReader(StandardCharsets.UTF_8).readText()
+        socket.getOutputStream().write(
+            "{\"success\":true}\n".toByteArray(StandardCharsets.UTF_8)
+        )
+        socket.getOutputStream().flush()
+
+        thread.join(500)
+        assertTrue(thread.isAlive, "kdap-launch should wait while the session is open")
+
+        socket.close()
+        server.close()
+        thread.join(5_000)
+        assertFalse(thread.isAlive, "kdap-launch should exit once the session ends")
+    }
+
     // ── Role 2 rejection ─────────────────────────────────────────────────
 
     @Test
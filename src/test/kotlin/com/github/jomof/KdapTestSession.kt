@@ -0,0 +1,274 @@
+package com.github.jomof
+
+import com.github.jomof.dap.DapFraming
+import org.json.JSONArray
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.assertEquals
+import org.junit.jupiter.api.Assertions.assertTrue
+import java.io.File
+
+/**
+ * End-to-end test harness: a live DAP session with a spawned adapter,
+ * driven by requests and expectations instead of raw message reads.
+ *
+ * [launch] returns a [Builder]; its [Builder.start] connects, sends
+ * `initialize` and `launch`, sets the breakpoints and exception filters
+ * configured on the builder, and sends `configurationDone`:
+ *
+ * ```
+ * KdapTestSession.launch(Debuggee.RUST.resolve())
+ *     .args("vars")
+ *     .breakpoint(RUST_SOURCE, KdapTestSession.markerLine(RUST_SOURCE, "#BP_primitives"))
+ *     .start().use { session ->
+ *         session.expectStopped("breakpoint")
+ *         session.assertVariable("locals", "i32_", "-32")
+ *     }
+ * ```
+ *
+ * Events read while waiting for a response are kept, so an
+ * [expectEvent] after the request that caused it still sees it, and so
+ * are responses read while waiting for an event.
+ * [request] fails the test on an error response; [close] disconnects
+ * with `terminateDebuggee` and stops the adapter.
+ */
+class KdapTestSession private constructor(private val connection: ConnectionContext) : AutoCloseable {
+
+    /** Configures a launch; [start] runs it. */
+    class Builder internal constructor(private val program: File) {
+        private var mode = ConnectionMode.STDIO
+        private val arguments = JSONObject().put("name", "test").put("terminal", "console")
+        private val breakpoints = linkedMapOf<String, MutableList<Int>>()
+        private val exceptionFilters = mutableListOf<String>()
+
+        /** Connects through [mode] instead of stdio. */
+        fun connection(mode: ConnectionMode) = apply { this.mode = mode }
+
+        /** The debuggee's command-line arguments. */
+        fun args(vararg args: String) = apply { arguments.put("args", JSONArray(args.toList())) }
+
+        /** Adds [key] to the `launch` arguments. */
+        fun launchArgument(key: String, value: Any) = apply { arguments.put(key, value) }
+
+        /** A source breakpoint set before `configurationDone`. */
+        fun breakpoint(file: File, line: Int) = apply { breakpoints.getOrPut(file.absolutePath) { mutableListOf() } += line }
+
+        /** Exception filters enabled before `configurationDone`. */
+        fun exceptionFilters(vararg filters: String) = apply { exceptionFilters += filters }
+
+        /** Starts the adapter and the launch; the debuggee runs until its first stop. */
+        fun start(): KdapTestSession {
+            val session = KdapTestSession(mode.connect())
+            try {
+                session.request("initialize", JSONObject().put("adapterID", "lldb").put("pathFormat", "path")
+                    .put("linesStartAt1", true).put("columnsStartAt1", true))
+                val launchSeq = session.send("launch", arguments.put("program", program.absolutePath))
+                session.expectEvent("initialized")
+                for ((path, lines) in breakpoints) session.setBreakpoints(File(path), lines)
+                if (exceptionFilters.isNotEmpty()) {
+                    session.request("setExceptionBreakpoints", JSONObject().put("filters", JSONArray(exceptionFilters)))
+                }
+                session.request("configurationDone")
+                session.checkSuccess(session.response(launchSeq))
+            } catch (e: Throwable) {
+                session.connection.close()
+                throw e
+            }
+            return session
+        }
+    }
+
+    private var nextSeq = 1
+    private val pendingEvents = ArrayDeque<JSONObject>()
+    private val pendingResponses = mutableMapOf<Int, JSONObject>()
+    private val breakpointLines = mutableMapOf<String, List<Int>>()
+
+    /** The thread of the last stop [expectStopped] saw. */
+    var threadId: Int = 0
+        private set
+
+    /** Sends [command] and returns its sequence number, without waiting for the response. */
+    fun send(command: String, arguments: JSONObject = JSONObject()): Int {
+        val seq = nextSeq++
+        DapFraming.writeMessage(connection.outputStream,
+            JSONObject().put("type", "request").put("seq", seq).put("command", command).put("arguments", arguments).toString())
+        return seq
+    }
+
+    /** Sends [command] and returns the body of its successful response. */
+    fun request(command: String, arguments: JSONObject = JSONObject()): JSONObject =
+        checkSuccess(response(send(command, arguments))).optJSONObject("body") ?: JSONObject()
+
+    /** Sends [command] and returns its response, successful or not. */
+    fun tryRequest(command: String, arguments: JSONObject = JSONObject()): JSONObject = response(send(command, arguments))
+
+    /** Reads until the response of request [seq], keeping the events read on the way. */
+    fun response(seq: Int): JSONObject {
+        pendingResponses.remove(seq)?.let { return it }
+        repeat(MAX_MESSAGES) {
+            val message = receive()
+            when (message.optString("type")) {
+                "response" -> if (message.optInt("request_seq") == seq) return message
+                "event" -> pendingEvents.addLast(message)
+            }
+        }
+        error("No response for request $seq within $MAX_MESSAGES messages\n${connection.diagnostics()}")
+    }
+
+    /** The next [event] event (kept or read), skipping others; returns its body. */
+    fun expectEvent(event: String): JSONObject {
+        pendingEvents.firstOrNull { it.optString("event") == event }?.let { kept ->
+            pendingEvents.remove(kept)
+            return kept.optJSONObject("body") ?: JSONObject()
+        }
+        val seen = mutableListOf<String>()
+        repeat(MAX_MESSAGES) {
+            val message = receive()
+            when (message.optString("type")) {
+                "event" -> {
+                    if (message.optString("event") == event) return message.optJSONObject("body") ?: JSONObject()
+                    seen += message.optString("event")
+                }
+                "response" -> pendingResponses[message.optInt("request_seq")] = message
+            }
+        }
+        error("No '$event' event within $MAX_MESSAGES messages; saw $seen\n${connection.diagnostics()}")
+    }
+
+    /**
+     * Waits for a `stopped` event with [reason] and selects its thread;
+     * returns its body.
+     */
+    fun expectStopped(reason: String): JSONObject {
+        val stopped = expectEvent("stopped")
+        assertEquals(reason, stopped.optString("reason"), "stopped: $stopped")
+        threadId = stopped.getInt("threadId")
+        return stopped
+    }
+
+    /** Waits for debuggee output matching [pattern] and returns the match. */
+    fun expectOutput(pattern: Regex): MatchResult {
+        val output = StringBuilder()
+        repeat(MAX_MESSAGES) {
+            val body = expectEvent("output")
+            if (body.optString("category") == "stdout" || body.optString("category") == "stderr") {
+                output.append(body.optString("output"))
+                pattern.find(output)?.let { return it }
+            }
+        }
+        error("No output matching $pattern; saw \"$output\"")
+    }
+
+    /**
+     * Sets the breakpoints of [file] to the ones already there plus
+     * [lines], and returns the `breakpoints` of the response.
+     */
+    fun setBreakpoints(file: File, lines: List<Int>): JSONArray {
+        val all = (breakpointLines[file.absolutePath].orEmpty() + lines).distinct()
+        breakpointLines[file.absolutePath] = all
+        return request("setBreakpoints", JSONObject()
+            .put("source", JSONObject().put("path", file.absolutePath))
+            .put("breakpoints", JSONArray(all.map { JSONObject().put("line", it) })))
+            .getJSONArray("breakpoints")
+    }
+
+    /** Sets a breakpoint at [file]:[line] and returns it, as the response reports it. */
+    fun setBreakpoint(file: File, line: Int): JSONObject {
+        val breakpoints = setBreakpoints(file, listOf(line))
+        return (0 until breakpoints.length()).map { breakpoints.getJSONObject(it) }.firstOrNull { it.optInt("line") == line }
+            ?: breakpoints.getJSONObject(breakpoints.length() - 1)
+    }
+
+    /** Resumes the stopped thread. */
+    fun resume() {
+        request("continue", JSONObject().put("threadId", threadId))
+    }
+
+    /** Steps into the call on the current line and waits for the stop. */
+    fun stepIn(): JSONObject = step("stepIn")
+
+    /** Steps over the current line and waits for the stop. */
+    fun next(): JSONObject = step("next")
+
+    /** Runs until the current function returns and waits for the stop. */
+    fun stepOut(): JSONObject = step("stepOut")
+
+    private fun step(command: String): JSONObject {
+        request(command, JSONObject().put("threadId", threadId))
+        return expectStopped("step")
+    }
+
+    /** The stack of the stopped thread, innermost frame first. */
+    fun stackFrames(levels: Int = 20): List<JSONObject> {
+        val frames = request("stackTrace", JSONObject().put("threadId", threadId).put("levels", levels))
+            .getJSONArray("stackFrames")
+        return (0 until frames.length()).map { frames.getJSONObject(it) }
+    }
+
+    /** The innermost frame of the stopped thread. */
+    fun topFrame(): JSONObject = stackFrames(levels = 1).first()
+
+    /** The variables of the scope named [scope] (case-insensitive) in the top frame. */
+    fun variables(scope: String): List<JSONObject> {
+        val scopes = request("scopes", JSONObject().put("frameId", topFrame().getInt("id"))).getJSONArray("scopes")
+        val match = (0 until scopes.length()).map { scopes.getJSONObject(it) }
+            .firstOrNull { it.getString("name").equals(scope, ignoreCase = true) }
+            ?: error("No '$scope' scope in $scopes")
+        return children(match.getInt("variablesReference"))
+    }
+
+    /** The variables of [reference]. */
+    fun children(reference: Int): List<JSONObject> {
+        val variables = request("variables", JSONObject().put("variablesReference", reference)).getJSONArray("variables")
+        return (0 until variables.length()).map { variables.getJSONObject(it) }
+    }
+
+    /** The variable named [name] in [scope] of the top frame. */
+    fun variable(scope: String, name: String): JSONObject {
+        val variables = variables(scope)
+        return variables.firstOrNull { it.getString("name") == name }
+            ?: error("No '$name' in $scope: ${variables.map { it.getString("name") }}")
+    }
+
+    /** Asserts that [name] in [scope] of the top frame shows [value]. */
+    fun assertVariable(scope: String, name: String, value: String) {
+        assertEquals(value, variable(scope, name).getString("value"), "$scope.$name")
+    }
+
+    /** Evaluates [expression] in the top frame and returns its `result`. */
+    fun evaluate(expression: String, context: String = "watch"): String =
+        request("evaluate", JSONObject().put("expression", expression).put("context", context)
+            .put("frameId", topFrame().getInt("id"))).getString("result")
+
+    override fun close() {
+        try {
+            tryRequest("disconnect", JSONObject().put("terminateDebuggee", true))
+        } catch (_: Exception) {
+            // The adapter may already be gone; stopping it below is enough.
+        } finally {
+            connection.close()
+        }
+    }
+
+    private fun receive(): JSONObject = JSONObject(DapTestUtils.readDapMessage(connection.inputStream))
+
+    private fun checkSuccess(response: JSONObject): JSONObject {
+        assertTrue(response.optBoolean("success"),
+            "${response.optString("command")} failed: $response\n${connection.diagnostics()}")
+        return response
+    }
+
+    companion object {
+        /** Messages read at most while waiting for one response or event. */
+        private const val MAX_MESSAGES = 2_000
+
+        /** Starts configuring a launch of [program]. */
+        fun launch(program: File) = Builder(program)
+
+        /** The 1-based line of [file] containing [marker], e.g. `#BP_primitives`. */
+        fun markerLine(file: File, marker: String): Int {
+            val index = file.readLines().indexOfFirst { marker in it }
+            require(index >= 0) { "'$marker' not found in $file" }
+            return index + 1
+        }
+    }
+}
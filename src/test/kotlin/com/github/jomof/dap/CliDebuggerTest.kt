@@ -0,0 +1,132 @@
+package com.github.jomof.dap
+
+import org.json.JSONArray
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.assertEquals
+import org.junit.jupiter.api.Assertions.assertNull
+import org.junit.jupiter.api.Assertions.assertTrue
+import org.junit.jupiter.api.Test
+import org.junit.jupiter.api.Timeout
+import java.io.ByteArrayOutputStream
+import java.io.OutputStream
+import java.io.PipedInputStream
+import java.io.PipedOutputStream
+import java.io.PrintStream
+import java.util.concurrent.TimeUnit
+import kotlin.concurrent.thread
+
+@Timeout(10, unit = TimeUnit.SECONDS)
+class CliDebuggerTest {
+
+    @Test
+    fun `commands parse with their short names`() {
+        assertEquals(DebugCommand.Break(BreakLocation.Line("src/main.rs", 12)), parseDebugCommand("b src/main.rs:12"))
+        assertEquals(DebugCommand.Break(BreakLocation.Line(null, 7)), parseDebugCommand("break 7"))
+        assertEquals(DebugCommand.Break(BreakLocation.Function("my_crate::run")), parseDebugCommand("b my_crate::run"))
+        assertEquals(DebugCommand.Delete(2), parseDebugCommand("d 2"))
+        assertEquals(DebugCommand.Delete(null), parseDebugCommand("delete"))
+        assertEquals(DebugCommand.ListBreakpoints, parseDebugCommand("info b"))
+        assertEquals(DebugCommand.SaveBreakpoints("bps.json"), parseDebugCommand("save breakpoints bps.json"))
+        assertEquals(DebugCommand.Step, parseDebugCommand("s"))
+        assertEquals(DebugCommand.Backtrace, parseDebugCommand("bt"))
+        assertEquals(DebugCommand.Frame(3), parseDebugCommand("f 3"))
+        assertEquals(DebugCommand.Print("v.len() + 1"), parseDebugCommand("  p v.len() + 1 "))
+        assertEquals(DebugCommand.Lldb("image list"), parseDebugCommand("lldb image list"))
+    }
+
+    @Test
+    fun `blank lines and comments are skipped, bad input is invalid`() {
+        assertNull(parseDebugCommand("   "))
+        assertNull(parseDebugCommand("# set up"))
+        assertTrue(parseDebugCommand("print") is DebugCommand.Invalid)
+        assertTrue(parseDebugCommand("frame x") is DebugCommand.Invalid)
+        assertTrue(parseDebugCommand("jump 3") is DebugCommand.Invalid)
+        assertTrue(parseDebugCommand("save breakpoints") is DebugCommand.Invalid)
+    }
+
+    @Test
+    fun `frames, breakpoints, and variables format like a debugger`() {
+        val frame = JSONObject("""{"id":1,"name":"main","source":{"path":"/src/main.rs"},"line":5,"column":9}""")
+        assertEquals("#0  main at /src/main.rs:5:9", formatFrame(0, frame))
+        assertEquals("#2  __libc_start_main at 0x7ffff7829d90",
+            formatFrame(2, JSONObject("""{"id":3,"name":"__libc_start_main","instructionPointerReference":"0x7ffff7829d90"}""")))
+        assertEquals("Breakpoint 1: main.rs:6", formatBreakpoint(1, BreakLocation.Line("main.rs", 5), true, 6))
+        assertEquals("Breakpoint 2: run (pending)", formatBreakpoint(2, BreakLocation.Function("run"), false, null))
+        assertEquals("x: i32 = 42", formatVariable(JSONObject("""{"name":"x","type":"i32","value":"42"}""")))
+    }
+
+    @Test
+    fun `a script drives the session through breakpoints, stops, and exit`() {
+        val toAdapter = PipedOutputStream()
+        val adapterInput = PipedInputStream(toAdapter)
+        val fromAdapter = PipedInputStream()
+        val adapterOutput = PipedOutputStream(fromAdapter)
+        val adapter = thread { fakeAdapter(adapterInput, adapterOutput) }
+        val bytes = ByteArrayOutputStream()
+        val out = PrintStream(bytes, true)
+        val script = "break main.rs:5\nrun\nbt\np x\nstep\nc\nbt\nquit\n"
+
+        val exitCode = CliDebugger(fromAdapter, toAdapter, out, timeoutMs = 5_000).use {
+            it.run("/bin/app", listOf("--fast"), script.reader().buffered(), interactive = false)
+        }
+        adapter.join()
+
+        val transcript = bytes.toString()
+        assertEquals(3, exitCode, transcript)
+        for (expected in listOf(
+            "(kdap) break main.rs:5\nBreakpoint 1: main.rs:5\n",
+            "Thread 1 stopped: breakpoint\n#0  main at /src/main.rs:5:9\n",
+            "* #0  main at /src/main.rs:5:9\n  #1  start\n",
+            "x = 42\n",
+            "stepIn: not now\n",
+            "hello\nProcess exited with code 3.\n",
+            "(kdap) bt\nThe program is not being run.\n",
+        )) {
+            assertTrue(transcript.contains(expected), "missing '$expected' in:\n$transcript")
+        }
+    }
+
+    /** Answers the requests for one breakpoint hit, then a run to exit on `continue`; `stepIn` fails. */
+    private fun fakeAdapter(input: PipedInputStream, output: OutputStream) = output.use { out ->
+        fun send(json: String) = DapFraming.writeMessage(out, json)
+        fun respond(request: JSONObject, body: JSONObject = JSONObject(), success: Boolean = true) = send(JSONObject()
+            .put("type", "response").put("request_seq", request.getInt("seq")).put("command", request.getString("command"))
+            .put("success", success).put("body", body).apply { if (!success) put("message", "not now") }.toString())
+        val frames = listOf(
+            JSONObject("""{"id":1000,"name":"main","source":{"path":"/src/main.rs"},"line":5,"column":9}"""),
+            JSONObject("""{"id":1001,"name":"start"}"""),
+        )
+        while (true) {
+            val request = JSONObject(DapFraming.readMessage(input) ?: break)
+            val arguments = request.optJSONObject("arguments") ?: JSONObject()
+            when (request.getString("command")) {
+                "initialize" -> {
+                    respond(request)
+                    send("""{"type":"event","event":"initialized"}""")
+                }
+                "setBreakpoints" -> respond(request, JSONObject().put("breakpoints", JSONArray(
+                    (0 until arguments.getJSONArray("breakpoints").length()).map {
+                        JSONObject().put("verified", true).put("line", 5)
+                    })))
+                "configurationDone" -> {
+                    respond(request)
+                    send("""{"type":"event","event":"stopped","body":{"reason":"breakpoint","threadId":1}}""")
+                }
+                "stackTrace" -> {
+                    val start = arguments.optInt("startFrame", 0)
+                    val levels = arguments.optInt("levels", frames.size)
+                    respond(request, JSONObject().put("stackFrames", JSONArray(frames.drop(start).take(levels))))
+                }
+                "evaluate" -> respond(request, JSONObject().put("result", "42"))
+                "stepIn" -> respond(request, success = false)
+                "continue" -> {
+                    respond(request)
+                    send("""{"type":"event","event":"output","body":{"category":"stdout","output":"hello\n"}}""")
+                    send("""{"type":"event","event":"exited","body":{"exitCode":3}}""")
+                    send("""{"type":"event","event":"terminated"}""")
+                }
+                else -> respond(request)
+            }
+        }
+    }
+}
@@ -33,6 +33,22 @@ class CliTest {
         assertNull(Cli.parse(arrayOf("--connect", "2", "--port", "1")))
     }
 
+    @Test
+    fun `--server serves sessions on --port or --socket`() {
+        assertEquals(Transport.TcpListen(4711, server = true), Cli.parse(arrayOf("--server", "--port", "4711"))?.transport)
+        assertEquals(Transport.UnixListen("/tmp/kdap.sock", server = true),
+            Cli.parse(arrayOf("--socket", "/tmp/kdap.sock", "--server"))?.transport)
+        assertEquals(Transport.UnixListen("/tmp/kdap.sock"), Cli.parse(arrayOf("--socket", "/tmp/kdap.sock"))?.transport)
+    }
+
+    @Test
+    fun `--server needs a listener`() {
+        assertNull(Cli.parse(arrayOf("--server")))
+        assertNull(Cli.parse(arrayOf("--server", "--connect", "2")))
+        assertNull(Cli.parse(arrayOf("--socket", "/tmp/kdap.sock", "--port", "1")))
+        assertNull(Cli.parse(arrayOf("--socket")))
+    }
+
     @Test
     fun `--port with missing value returns null`() {
         assertNull(Cli.parse(arrayOf("--port")))
@@ -89,4 +105,59 @@ class CliTest {
         val config = Cli.parse(emptyArray())
         assertNull(config?.lldbDapPath)
     }
+
+    @Test
+    fun `--log sets path and categories, over the environment`() {
+        val env = mapOf("KDAP_LOG" to "/tmp/env.jsonl", "KDAP_LOG_CATEGORIES" to "sb")
+        val config = Cli.parse(arrayOf("--log", "/tmp/kdap.jsonl", "--log-categories", "client,backend"), env)
+        assertEquals("/tmp/kdap.jsonl", config?.logPath)
+        assertEquals(setOf(ProtocolLog.Category.Client, ProtocolLog.Category.Backend), config?.logCategories)
+    }
+
+    @Test
+    fun `KDAP_LOG enables the log with every category`() {
+        val config = Cli.parse(emptyArray(), mapOf("KDAP_LOG" to "/tmp/env.jsonl"))
+        assertEquals("/tmp/env.jsonl", config?.logPath)
+        assertEquals(ProtocolLog.Category.entries.toSet(), config?.logCategories)
+        assertNull(Cli.parse(emptyArray(), emptyMap())?.logPath)
+    }
+
+    @Test
+    fun `unknown log category returns null`() {
+        assertNull(Cli.parse(arrayOf("--log", "/tmp/kdap.jsonl", "--log-categories", "client,wire"), emptyMap()))
+        assertNull(Cli.parse(arrayOf("--log"), emptyMap()))
+    }
+
+    @Test
+    fun `--record and --replay set capture paths, but not both`() {
+        assertEquals("/tmp/s.dapcap", Cli.parse(arrayOf("--record", "/tmp/s.dapcap"), emptyMap())?.recordPath)
+        assertEquals("/tmp/s.dapcap", Cli.parse(arrayOf("--replay", "/tmp/s.dapcap"), emptyMap())?.replayPath)
+        assertNull(Cli.parse(arrayOf("--record", "/tmp/a.dapcap", "--replay", "/tmp/b.dapcap"), emptyMap()))
+        assertNull(Cli.parse(arrayOf("--replay"), emptyMap()))
+    }
+
+    @Test
+    fun `debug takes the program and every argument after it`() {
+        val config = Cli.parse(arrayOf("--lldb-dap", "/usr/bin/lldb-dap", "debug", "target/debug/app", "--port", "1"))
+        assertEquals(listOf("target/debug/app", "--port", "1"), config?.debugCommand)
+        assertEquals(Transport.Stdio, config?.transport)
+        assertEquals("/usr/bin/lldb-dap", config?.lldbDapPath)
+        assertNull(Cli.parse(arrayOf("debug")))
+        assertNull(Cli.parse(emptyArray())?.debugCommand)
+    }
+
+    @Test
+    fun `--breakpoints sets a breakpoint file for debug only`() {
+        val config = Cli.parse(arrayOf("--breakpoints", "/tmp/bps.json", "debug", "target/debug/app"), emptyMap())
+        assertEquals("/tmp/bps.json", config?.breakpointsPath)
+        assertNull(Cli.parse(arrayOf("--breakpoints", "/tmp/bps.json"), emptyMap()))
+        assertNull(Cli.parse(arrayOf("--breakpoints"), emptyMap()))
+    }
+
+    @Test
+    fun `--generate-schema prints the schema instead of serving`() {
+        assertEquals(true, Cli.parse(arrayOf("--generate-schema"), emptyMap())?.generateSchema)
+        assertEquals(false, Cli.parse(emptyArray(), emptyMap())?.generateSchema)
+        assertNull(Cli.parse(arrayOf("--generate-schema", "debug", "target/debug/app"), emptyMap()))
+    }
 }
@@ -0,0 +1,160 @@
+package com.github.jomof.dap
+
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.assertEquals
+import org.junit.jupiter.api.Assertions.assertFalse
+import org.junit.jupiter.api.Assertions.assertThrows
+import org.junit.jupiter.api.Assertions.assertTrue
+import org.junit.jupiter.api.Test
+import org.junit.jupiter.api.Timeout
+import org.junit.jupiter.api.io.TempDir
+import java.io.File
+import java.io.PipedInputStream
+import java.io.PipedOutputStream
+import java.util.concurrent.TimeUnit
+import kotlin.concurrent.thread
+
+@Timeout(10, unit = TimeUnit.SECONDS)
+class DapReplayTest {
+
+    @TempDir
+    lateinit var dir: File
+
+    private fun client(timeMs: Long, json: String) = CapturedMessage(timeMs, true, JSONObject(json))
+    private fun adapter(timeMs: Long, json: String) = CapturedMessage(timeMs, false, JSONObject(json))
+
+    /** A capture where the client asks for the stack of the thread a `stopped` event named. */
+    private val capture = listOf(
+        client(0, """{"seq":1,"type":"request","command":"threads"}"""),
+        adapter(3, """{"seq":1,"type":"response","request_seq":1,"command":"threads","success":true,
+            "body":{"threads":[{"id":1,"name":"main"}]}}"""),
+        adapter(5, """{"seq":2,"type":"event","event":"stopped","body":{"reason":"breakpoint","threadId":1}}"""),
+        adapter(6, """{"seq":3,"type":"event","event":"output","body":{"output":"Process 4242 stopped\n"}}"""),
+        client(9, """{"seq":2,"type":"request","command":"stackTrace","arguments":{"threadId":1}}"""),
+        adapter(12, """{"seq":4,"type":"response","request_seq":2,"command":"stackTrace","success":true,
+            "body":{"stackFrames":[{"id":100,"name":"main","line":3}]}}"""),
+    )
+
+    @Test
+    fun `capture writer records client traffic with times`() {
+        val file = File(dir, "session.dapcap")
+        var now = 0L
+        val writer = DapCaptureWriter(file) { now }
+        writer.message(WireDirection.FromClient, """{"seq":1,"type":"request","command":"threads"}""")
+        now = 4_000_000
+        writer.message(WireDirection.ToBackend, """{"seq":1,"type":"request","command":"threads"}""")
+        writer.message(WireDirection.ToClient, """{"seq":1,"type":"response","request_seq":1,"success":true}""")
+        writer.close()
+
+        val captured = CapturedMessage.readCapture(file)
+        assertEquals(2, captured.size)
+        assertEquals(listOf(0L, 4L), captured.map { it.timeMs })
+        assertEquals(listOf(true, false), captured.map { it.fromClient })
+        assertEquals("threads", captured[0].message.getString("command"))
+        assertEquals("response", captured[1].type)
+    }
+
+    @Test
+    fun `server sessions after the first get numbered captures`() {
+        assertEquals(File(dir, "s.dapcap"), DapCaptureWriter.sessionFile(File(dir, "s.dapcap"), 1))
+        assertEquals(File(dir, "s.3.dapcap"), DapCaptureWriter.sessionFile(File(dir, "s.dapcap"), 3))
+        assertEquals(File(dir, "capture.2"), DapCaptureWriter.sessionFile(File(dir, "capture"), 2))
+    }
+
+    @Test
+    fun `bad capture lines name their line`() {
+        val file = File(dir, "bad.dapcap")
+        file.writeText("""{"timeMs":0,"from":"client","message":{"seq":1}}""" + "\n" + """{"from":"server"}""" + "\n")
+        val e = assertThrows(IllegalArgumentException::class.java) { CapturedMessage.readCapture(file) }
+        assertTrue(e.message!!.contains("bad.dapcap:2"), e.message)
+    }
+
+    @Test
+    fun `replayed responses match modulo ids`() {
+        val replayed = listOf(
+            adapter(1, """{"seq":10,"type":"response","request_seq":1,"command":"threads","success":true,
+                "body":{"threads":[{"id":7,"name":"main"}]}}"""),
+            adapter(2, """{"seq":11,"type":"event","event":"stopped","body":{"reason":"breakpoint","threadId":7}}"""),
+            adapter(2, """{"seq":12,"type":"response","request_seq":2,"command":"stackTrace","success":true,
+                "body":{"stackFrames":[{"id":200,"name":"main","line":3}]}}"""),
+        )
+        assertEquals(emptyList<String>(), DapReplay.mismatches(capture, replayed))
+        DapReplay.assertMatches(capture, replayed)
+    }
+
+    @Test
+    fun `differences and missing messages are reported`() {
+        val replayed = listOf(
+            adapter(1, """{"seq":10,"type":"response","request_seq":1,"command":"threads","success":true,
+                "body":{"threads":[{"id":7,"name":"worker"}]}}"""),
+            adapter(2, """{"seq":11,"type":"event","event":"exited","body":{"exitCode":0}}"""),
+        )
+        val mismatches = DapReplay.mismatches(capture, replayed)
+        assertEquals(4, mismatches.size, mismatches.toString())
+        assertTrue(mismatches[0].startsWith("response to 'threads' (request 1): expected"), mismatches[0])
+        assertEquals("missing event 'stopped'", mismatches[1])
+        assertEquals("missing response to 'stackTrace' (request 2)", mismatches[2])
+        assertEquals("unexpected event 'exited'", mismatches[3])
+        assertThrows(AssertionError::class.java) { DapReplay.assertMatches(capture, replayed) }
+    }
+
+    @Test
+    fun `replay waits for events and sends the replay's ids`() {
+        val toAdapter = PipedOutputStream()
+        val adapterInput = PipedInputStream(toAdapter)
+        val fromAdapter = PipedInputStream()
+        val adapterOutput = PipedOutputStream(fromAdapter)
+        val requests = mutableListOf<JSONObject>()
+        // Numbers its threads and frames differently from the capture.
+        val fakeAdapter = thread {
+            adapterOutput.use { out ->
+                while (true) {
+                    val request = JSONObject(DapFraming.readMessage(adapterInput) ?: break)
+                    requests += request
+                    val seq = request.getInt("seq")
+                    when (request.getString("command")) {
+                        "threads" -> {
+                            DapFraming.writeMessage(out, """{"seq":1,"type":"response","request_seq":$seq,
+                                "command":"threads","success":true,"body":{"threads":[{"id":7,"name":"main"}]}}""")
+                            Thread.sleep(50)
+                            DapFraming.writeMessage(out,
+                                """{"seq":2,"type":"event","event":"stopped","body":{"reason":"breakpoint","threadId":7}}""")
+                        }
+                        "stackTrace" -> DapFraming.writeMessage(out, """{"seq":3,"type":"response",
+                            "request_seq":$seq,"command":"stackTrace","success":true,
+                            "body":{"stackFrames":[{"id":200,"name":"main","line":3}]}}""")
+                    }
+                }
+            }
+        }
+
+        val replayed = DapReplay.replay(capture, toAdapter, fromAdapter, timeoutMs = 5_000)
+        fakeAdapter.join()
+
+        assertEquals(listOf("threads", "stackTrace"), requests.map { it.getString("command") })
+        assertEquals(7, requests[1].getJSONObject("arguments").getInt("threadId"))
+        assertEquals(3, replayed.size)
+        DapReplay.assertMatches(capture, replayed)
+    }
+
+    @Test
+    fun `replay stops when the adapter does not catch up`() {
+        val toAdapter = PipedOutputStream()
+        val adapterInput = PipedInputStream(toAdapter)
+        val fromAdapter = PipedInputStream()
+        val adapterOutput = PipedOutputStream(fromAdapter)
+        val requests = mutableListOf<String>()
+        val silentAdapter = thread {
+            adapterOutput.use {
+                while (true) requests += JSONObject(DapFraming.readMessage(adapterInput) ?: break).getString("command")
+            }
+        }
+
+        val replayed = DapReplay.replay(capture, toAdapter, fromAdapter, timeoutMs = 200)
+        silentAdapter.join()
+
+        assertEquals(listOf("threads"), requests)
+        assertTrue(replayed.isEmpty())
+        assertFalse(DapReplay.mismatches(capture, replayed).isEmpty())
+    }
+}
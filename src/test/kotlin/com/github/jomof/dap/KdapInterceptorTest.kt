@@ -33,6 +33,14 @@ class KdapInterceptorTest {
         assertInstanceOf(RequestAction.Forward::class.java, interceptor.onRequest(request))
     }
 
+    @Test
+    fun `disassemble request is forwarded unchanged`() {
+        // lldb-dap implements disassemble (symbols and source locations
+        // included); KDAP must not get in the way of the disassembly view.
+        val request = DisassembleRequest(seq = 7)
+        assertInstanceOf(RequestAction.Forward::class.java, interceptor.onRequest(request))
+    }
+
     @Test
     fun `non-intercepted backend messages are forwarded unchanged`() {
         val event = StoppedEvent(seq = 10)
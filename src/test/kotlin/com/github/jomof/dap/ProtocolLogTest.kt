@@ -0,0 +1,85 @@
+package com.github.jomof.dap
+
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+import org.junit.jupiter.api.io.TempDir
+import java.io.File
+import java.time.Instant
+
+class ProtocolLogTest {
+
+    @TempDir
+    lateinit var dir: File
+
+    private val clock = { Instant.parse("2026-10-14T09:30:00.125Z") }
+
+    private fun records(file: File) = file.readLines().map { JSONObject(it) }
+
+    @Test
+    fun `messages are recorded with category, direction, and response time`() {
+        val file = File(dir, "kdap.jsonl")
+        val log = ProtocolLog(file, clock = clock)
+        log.message(WireDirection.FromClient, """{"seq":3,"type":"request","command":"threads"}""")
+        log.message(WireDirection.ToClient,
+            """{"seq":0,"type":"response","request_seq":3,"command":"threads","success":true}""")
+        log.message(WireDirection.FromBackend, """{"seq":9,"type":"event","event":"stopped"}""")
+        log.close()
+
+        val (request, response, event) = records(file)
+        assertEquals("2026-10-14T09:30:00.125Z", request.getString("time"))
+        assertEquals("client", request.getString("category"))
+        assertEquals("client→kdap", request.getString("direction"))
+        assertEquals("threads", request.getString("command"))
+        assertEquals(3, request.getJSONObject("message").getInt("seq"))
+        assertEquals(3, response.getInt("request_seq"))
+        assertTrue(response.getDouble("durationMs") >= 0.0)
+        assertEquals("backend", event.getString("category"))
+        assertEquals("stopped", event.getString("event"))
+        assertFalse(event.has("durationMs"))
+    }
+
+    @Test
+    fun `launch environment values are redacted`() {
+        val file = File(dir, "kdap.jsonl")
+        val log = ProtocolLog(file, clock = clock)
+        log.message(WireDirection.FromClient,
+            """{"seq":2,"type":"request","command":"launch","arguments":{"program":"/bin/app","env":{"TOKEN":"s3cret"}}}""")
+        log.close()
+
+        val arguments = records(file).single().getJSONObject("message").getJSONObject("arguments")
+        assertEquals("/bin/app", arguments.getString("program"))
+        assertEquals(ProtocolLog.REDACTED, arguments.getJSONObject("env").getString("TOKEN"))
+    }
+
+    @Test
+    fun `only the chosen categories are recorded`() {
+        val file = File(dir, "kdap.jsonl")
+        val log = ProtocolLog(file, categories = setOf(ProtocolLog.Category.Sb), clock = clock)
+        log.message(WireDirection.ToBackend, """{"seq":1,"type":"request","command":"launch"}""")
+        log.onCall("SBTarget", "launch", "program=/bin/true", "SBProcess")
+        log.close()
+
+        val record = records(file).single()
+        assertEquals("sb", record.getString("category"))
+        assertEquals("SBTarget", record.getString("interface"))
+        assertEquals("SBProcess", record.getString("result"))
+    }
+
+    @Test
+    fun `the log rotates at its size limit and on start`() {
+        val file = File(dir, "kdap.jsonl")
+        file.writeText("{\"earlier\":true}\n")
+        val log = ProtocolLog(file, maxBytes = 200, keep = 2, clock = clock)
+        repeat(6) { log.message(WireDirection.FromClient, """{"seq":$it,"type":"event","event":"e$it"}""") }
+        log.close()
+
+        assertTrue(file.length() <= 200)
+        assertTrue(File(dir, "kdap.jsonl.1").exists())
+        assertTrue(File(dir, "kdap.jsonl.2").exists())
+        assertFalse(File(dir, "kdap.jsonl.3").exists())
+        val all = listOf(File(dir, "kdap.jsonl.2"), File(dir, "kdap.jsonl.1"), file).flatMap { records(it) }
+        assertEquals("e5", all.last().getString("event"))
+        assertFalse(all.any { it.has("earlier") })
+    }
+}
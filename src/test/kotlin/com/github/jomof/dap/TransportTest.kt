@@ -1,11 +1,19 @@
 package com.github.jomof.dap
 
 import org.junit.jupiter.api.Assertions.assertArrayEquals
+import org.junit.jupiter.api.Assertions.assertFalse
 import org.junit.jupiter.api.Assertions.assertSame
+import org.junit.jupiter.api.Assertions.assertTrue
 import org.junit.jupiter.api.Test
+import org.junit.jupiter.api.io.TempDir
+import java.io.File
 import java.net.ServerSocket
 import java.net.Socket
+import java.net.UnixDomainSocketAddress
+import java.nio.channels.Channels
+import java.nio.channels.SocketChannel
 import java.util.concurrent.CountDownLatch
+import java.util.concurrent.atomic.AtomicInteger
 import kotlin.concurrent.thread
 
 class TransportTest {
@@ -66,4 +74,50 @@ class TransportTest {
         clientThread.join(2000)
         server.close()
     }
+
+    @Test
+    fun `TcpListen server serves one session after another`() {
+        val portReady = CountDownLatch(1)
+        var boundPort = 0
+        val sessions = AtomicInteger()
+        val serverThread = thread(isDaemon = true) {
+            Transport.TcpListen(0, onBound = { port ->
+                boundPort = port
+                portReady.countDown()
+            }, server = true).run { _, output ->
+                output.write("s${sessions.incrementAndGet()}".toByteArray())
+                output.flush()
+            }
+        }
+        portReady.await()
+        for (n in 1..3) {
+            Socket("127.0.0.1", boundPort).use {
+                it.soTimeout = 5000
+                val received = ByteArray(2)
+                it.getInputStream().readNBytes(received, 0, 2)
+                assertArrayEquals("s$n".toByteArray(), received)
+            }
+        }
+        assertTrue(serverThread.isAlive)
+        serverThread.interrupt()
+    }
+
+    @Test
+    fun `UnixListen run invokes block with accepted socket streams`(@TempDir dir: File) {
+        val path = File(dir, "kdap.sock").path
+        val bound = CountDownLatch(1)
+        val serverThread = thread {
+            Transport.UnixListen(path, onBound = { bound.countDown() }).run { input, output ->
+                output.write(input.readNBytes(2))
+                output.flush()
+            }
+        }
+        bound.await()
+        SocketChannel.open(UnixDomainSocketAddress.of(path)).use { channel ->
+            Channels.newOutputStream(channel).apply { write("ok".toByteArray()); flush() }
+            assertArrayEquals("ok".toByteArray(), Channels.newInputStream(channel).readNBytes(2))
+        }
+        serverThread.join(2000)
+        assertFalse(File(path).exists())
+    }
 }
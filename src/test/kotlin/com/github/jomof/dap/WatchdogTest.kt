@@ -0,0 +1,97 @@
+package com.github.jomof.dap
+
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [Watchdog]. Verifies that client requests are watched
+ * from arrival until their response reaches the client, whether KDAP
+ * forwards them or handles them itself, that the requests KDAP sends the
+ * backend are not watched on their own, that a stall is reported once
+ * with the debuggee's state, and that `/proc` process states are read
+ * and described.
+ */
+class WatchdogTest {
+
+    private var now = 0L
+    private val states = mutableMapOf<Long, Watchdog.ProcessState>()
+    private val watchdog = Watchdog(stallTimeoutMs = 1_000, stateOf = { states[it] }, clock = { now })
+
+    private fun request(seq: Int, command: String, fromClient: Boolean = true) {
+        val json = """{"type":"request","seq":$seq,"command":"$command","arguments":{}}"""
+        if (fromClient) watchdog.message(WireDirection.FromClient, json)
+        watchdog.message(WireDirection.ToBackend, json)
+    }
+
+    private fun response(seq: Int, command: String, direction: WireDirection = WireDirection.ToClient) =
+        watchdog.message(direction, """{"type":"response","seq":0,"request_seq":$seq,"command":"$command","success":true}""")
+
+    @Test
+    fun `a request is stalled only while unanswered past the timeout`() {
+        request(1, "next")
+        now = 999
+        assertFalse(watchdog.isStalled)
+        now = 1_000
+        assertEquals(listOf(1), watchdog.stalled().map { it.seq })
+        response(1, "next")
+        assertFalse(watchdog.isStalled)
+    }
+
+    @Test
+    fun `requests KDAP sends itself are not watched`() {
+        request(2_000_001, "evaluate", fromClient = false)
+        now = 10_000
+        assertFalse(watchdog.isStalled)
+        assertNull(watchdog.check())
+    }
+
+    @Test
+    fun `a request KDAP handles is watched until its response reaches the client`() {
+        watchdog.message(WireDirection.FromClient, """{"type":"request","seq":3,"command":"variables","arguments":{}}""")
+        request(2_000_002, "evaluate", fromClient = false)
+        response(2_000_002, "evaluate", WireDirection.FromBackend)
+        now = 1_000
+        assertEquals(listOf(3), watchdog.stalled().map { it.seq })
+        assertTrue(watchdog.check()!!.contains("'variables' has had no response for 1 s"))
+        response(3, "variables")
+        assertFalse(watchdog.isStalled)
+    }
+
+    @Test
+    fun `attach and launch are not watched`() {
+        request(1, "attach")
+        request(2, "launch")
+        now = 10_000
+        assertFalse(watchdog.isStalled)
+        assertNull(watchdog.check())
+    }
+
+    @Test
+    fun `a stall is reported once with the debuggee's state`() {
+        watchdog.message(WireDirection.ToClient, """{"type":"event","seq":0,"event":"process","body":{"name":"app","systemProcessId":4321}}""")
+        states[4321] = Watchdog.ProcessState('S', mapOf(4322L to 'Z'))
+        request(7, "stepIn")
+        now = 5_000
+        val diagnostic = watchdog.check()!!
+        assertTrue(diagnostic.contains("'stepIn' has had no response for 5 s"), diagnostic)
+        assertTrue(diagnostic.contains("The debuggee (process 4321) is sleeping; its child process 4322 is a zombie."), diagnostic)
+        assertNull(watchdog.check())
+        assertEquals(listOf(7), watchdog.drain().map { it.seq })
+        assertFalse(watchdog.isStalled)
+    }
+
+    @Test
+    fun `the stat state follows the command however it is named`() {
+        assertEquals('Z', Watchdog.parseStatState("4321 (rust (debug) ee) Z 1 4321 4321 0 -1"))
+        assertEquals('D', Watchdog.parseStatState("12 (sleep) D 1 12 12 0 -1"))
+    }
+
+    @Test
+    fun `process states are described`() {
+        assertEquals("The debuggee (process 9) no longer exists.", Watchdog.describeProcess(9, null))
+        assertEquals("The debuggee (process 9) is a zombie: it has exited, but has not been reaped.",
+            Watchdog.describeProcess(9, Watchdog.ProcessState('Z')))
+        assertEquals("The debuggee (process 9) is in uninterruptible sleep, blocked in the kernel; its child processes 10, 11 are zombies.",
+            Watchdog.describeProcess(9, Watchdog.ProcessState('D', mapOf(11L to 'Z', 10L to 'Z', 12L to 'S'))))
+    }
+}
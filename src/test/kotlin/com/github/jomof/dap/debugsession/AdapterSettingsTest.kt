@@ -0,0 +1,54 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.AdapterSettings
+import com.github.jomof.dap.messages.InternalFrames
+import com.github.jomof.dap.messages.StaticsScope
+import com.github.jomof.dap.messages.WatchRefresh
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [applyAdapterSettings]. Verifies that a launch's KDAP
+ * adapter settings reach the session, and that settings left out or out
+ * of range keep the session's defaults.
+ */
+class AdapterSettingsTest {
+
+    private val session = DebugSession()
+
+    @Test
+    fun `watch refresh is set on the session`() {
+        session.applyAdapterSettings(AdapterSettings())
+        assertEquals(WatchRefresh.Stop, session.watchRefresh)
+        session.applyAdapterSettings(AdapterSettings(watchRefresh = WatchRefresh.Frame))
+        assertEquals(WatchRefresh.Frame, session.watchRefresh)
+    }
+
+    @Test
+    fun `clipboard limits must be positive`() {
+        session.applyAdapterSettings(AdapterSettings(clipboardMaxElements = 0))
+        assertEquals(DEFAULT_CLIPBOARD_MAX_ELEMENTS, session.clipboardMaxElements)
+        session.applyAdapterSettings(AdapterSettings(clipboardMaxElements = 500))
+        assertEquals(500, session.clipboardMaxElements)
+    }
+
+    @Test
+    fun `statics scope is set on the session`() {
+        session.applyAdapterSettings(AdapterSettings(staticsScope = StaticsScope.All))
+        assertEquals(StaticsScope.All, session.staticsScope)
+    }
+
+    @Test
+    fun `internal frames display is set on the session`() {
+        session.applyAdapterSettings(AdapterSettings(internalFrames = InternalFrames.Collapse))
+        assertEquals(InternalFrames.Collapse, session.internalFrames)
+    }
+
+    @Test
+    fun `changed values depth may be zero but not negative`() {
+        session.applyAdapterSettings(AdapterSettings(changedValuesDepth = -1))
+        assertEquals(DEFAULT_CHANGED_VALUES_DEPTH, session.changedValuesDepth)
+        session.applyAdapterSettings(AdapterSettings(changedValuesDepth = 0))
+        assertEquals(0, session.changedValuesDepth)
+    }
+}
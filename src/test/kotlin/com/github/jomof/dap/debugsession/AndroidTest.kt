@@ -0,0 +1,100 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.AndroidAttachOptions
+import com.github.jomof.dap.sb.SBError
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+import org.junit.jupiter.api.io.TempDir
+import java.io.File
+
+/**
+ * Unit tests for the Android attach helpers in Android.kt. Verifies that
+ * ABIs map to the NDK's triples, that `adb` and the NDK are found from
+ * the environment, that the NDK's newest `lldb-server` is picked, and
+ * that servers are started with the right `run-as` command line.
+ */
+class AndroidTest {
+
+    @Test
+    fun `ABIs map to triples and lldb-server architectures`() {
+        assertEquals(AndroidAbi("aarch64-linux-android", "aarch64"), androidAbi("arm64-v8a"))
+        assertEquals(AndroidAbi("arm-linux-androideabi", "arm"), androidAbi("armeabi-v7a"))
+        assertEquals(AndroidAbi("i686-linux-android", "i386"), androidAbi("x86"))
+        assertEquals(AndroidAbi("x86_64-linux-android", "x86_64"), androidAbi("x86_64"))
+        assertThrows(SBError::class.java) { androidAbi("mips") }
+    }
+
+    @Test
+    fun `pidof output gives the first pid`() {
+        assertEquals(4321L, parsePidof("4321\n"))
+        assertEquals(4321L, parsePidof("4321 4400\n"))
+        assertNull(parsePidof(""))
+        assertNull(parsePidof("pidof: not found"))
+    }
+
+    @Test
+    fun `adb is the configured one, the SDK's, or on PATH`(@TempDir sdk: File) {
+        assertEquals("/opt/adb", adbExecutable("/opt/adb", mapOf("ANDROID_HOME" to sdk.path)))
+        assertTrue(adbExecutable(null, mapOf("ANDROID_HOME" to sdk.path)).startsWith("adb"))
+        val adb = File(sdk, "platform-tools/${if (isWindowsHost()) "adb.exe" else "adb"}")
+        adb.parentFile.mkdirs()
+        adb.writeText("")
+        assertEquals(adb.path, adbExecutable(null, mapOf("ANDROID_SDK_ROOT" to sdk.path)))
+    }
+
+    @Test
+    fun `NDK is found from the environment or the newest SDK ndk`(@TempDir sdk: File) {
+        assertNull(findNdk(null, emptyMap()))
+        File(sdk, "ndk/9.0.1").mkdirs()
+        File(sdk, "ndk/26.1.10909125").mkdirs()
+        File(sdk, "ndk/25.2.9519653").mkdirs()
+        assertEquals(File(sdk, "ndk/26.1.10909125"), findNdk(null, mapOf("ANDROID_HOME" to sdk.path)))
+        val home = File(sdk, "ndk/9.0.1").path
+        assertEquals(File(home), findNdk(null, mapOf("ANDROID_NDK_HOME" to home, "ANDROID_HOME" to sdk.path)))
+        assertEquals(File("/opt/ndk"), findNdk("/opt/ndk", mapOf("ANDROID_HOME" to sdk.path)))
+    }
+
+    @Test
+    fun `lldb-server and sysroot libraries come from the NDK`(@TempDir ndk: File) {
+        val abi = androidAbi("arm64-v8a")
+        val prebuilt = File(ndk, "toolchains/llvm/prebuilt/linux-x86_64")
+        assertNull(ndkLldbServer(ndk, abi))
+        for (version in listOf("9.0.9", "17")) {
+            val server = File(prebuilt, "lib/clang/$version/lib/linux/aarch64/lldb-server")
+            server.parentFile.mkdirs()
+            server.writeText("")
+        }
+        assertEquals(File(prebuilt, "lib/clang/17/lib/linux/aarch64/lldb-server"), ndkLldbServer(ndk, abi))
+        assertNull(ndkLldbServer(ndk, androidAbi("x86")))
+
+        assertEquals(emptyList<String>(), ndkSymbolPaths(ndk, abi))
+        File(prebuilt, "sysroot/usr/lib/aarch64-linux-android").mkdirs()
+        assertEquals(listOf(File(prebuilt, "sysroot/usr/lib/aarch64-linux-android").path), ndkSymbolPaths(ndk, abi))
+    }
+
+    @Test
+    fun `servers run as the package and attach to the pid`() {
+        assertEquals(
+            listOf("run-as", "com.example", "./kdap-lldb-server", "gdbserver",
+                "unix-abstract-accept://kdap-sock", "--attach", "42"),
+            androidServerCommand("com.example", "kdap-lldb-server", 42, "kdap-sock", 5039),
+        )
+        assertEquals(
+            listOf("run-as", "com.example", "./kdap-gdbserver", "--attach", ":5040", "42"),
+            androidServerCommand("com.example", "kdap-gdbserver", 42, "kdap-sock", 5040),
+        )
+    }
+
+    @Test
+    fun `android options round-trip through JSON`() {
+        val json = JSONObject("""{"package": "com.example", "device": "emulator-5554", "port": 5040,
+            "symbols": ["/app/lib/arm64-v8a"]}""")
+        val options = AndroidAttachOptions.fromJson(json)!!
+        assertEquals(AndroidAttachOptions("com.example", device = "emulator-5554", port = 5040,
+            symbols = listOf("/app/lib/arm64-v8a")), options)
+        assertEquals(options, AndroidAttachOptions.fromJson(options.toJsonObject()))
+        assertNull(AndroidAttachOptions.fromJson(JSONObject("""{"device": "emulator-5554"}""")))
+        assertNull(AndroidAttachOptions.fromJson(null))
+    }
+}
@@ -0,0 +1,51 @@
+package com.github.jomof.dap.debugsession
+
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for the tokio task helpers. Verifies that the task list read
+ * from LLDB is parsed, and that tasks and await-stack levels are named
+ * and numbered as pseudo-threads and frames.
+ */
+class AsyncTasksTest {
+
+    private val json = """
+        [{"id": 7, "stage": "Running", "levels": [
+            {"type": "app::serve::{async_fn_env#0}", "state": "Suspend1"},
+            {"type": "tokio::time::sleep::Sleep", "state": null}]},
+         {"id": 9, "stage": "Finished", "levels": []}]
+    """.trimIndent()
+
+    @Test
+    fun `task list is parsed`() {
+        val tasks = parseAsyncTasks(json)
+        assertEquals(listOf(7L, 9L), tasks.map { it.id })
+        assertEquals(
+            listOf(AwaitLevel("app::serve::{async_fn_env#0}", "Suspend1"), AwaitLevel("tokio::time::sleep::Sleep", null)),
+            tasks[0].levels,
+        )
+    }
+
+    @Test
+    fun `tasks are named after their outermost future`() {
+        val (running, finished) = parseAsyncTasks(json)
+        assertEquals("task 7: app::serve::{async_fn#0}", taskThreadName(running))
+        assertEquals("task 9 (finished)", taskThreadName(finished))
+    }
+
+    @Test
+    fun `await levels show the body and its suspend point`() {
+        assertEquals("app::serve::{async_fn#0} [Suspend1]", awaitFrameName(AwaitLevel("app::serve::{async_fn_env#0}", "Suspend1")))
+        assertEquals("app::main::{async_block#0} [Unresumed]", awaitFrameName(AwaitLevel("app::main::{async_block_env#0}", "Unresumed")))
+        assertEquals("tokio::time::sleep::Sleep", awaitFrameName(AwaitLevel("tokio::time::sleep::Sleep", null)))
+    }
+
+    @Test
+    fun `task pseudo-threads are numbered above OS threads`() {
+        assertEquals(TASK_THREAD_BASE + 7, taskThreadId(7))
+        assertTrue(isTaskThread(taskThreadId(7)!!))
+        assertFalse(isTaskThread(123456))
+        assertNull(taskThreadId(Int.MAX_VALUE.toLong()))
+    }
+}
@@ -0,0 +1,69 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.FunctionBreakpoint
+import com.github.jomof.dap.messages.SetBreakpointsRequest
+import com.github.jomof.dap.messages.Source
+import com.github.jomof.dap.messages.SourceBreakpoint
+import com.github.jomof.dap.sb.SBError
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [formatBreakpointFile], [parseBreakpointFile],
+ * [mergeSourceBreakpoints], and [mergeFunctionBreakpoints]. Verifies
+ * that breakpoint files keep conditions, hit conditions, and log
+ * messages, that newer or malformed files are rejected, and that
+ * imported breakpoints replace the ones at their sites.
+ */
+class BreakpointFileTest {
+
+    private val main = SetBreakpointsRequest(seq = 0, source = Source(path = "/src/main.rs"), breakpoints = listOf(
+        SourceBreakpoint(line = 12, condition = "/py n > 3", hitCondition = ">= 2"),
+        SourceBreakpoint(line = 20, column = 9, logMessage = "n = {n}"),
+    ))
+    private val lib = SetBreakpointsRequest(seq = 0, source = Source(path = "/src/lib.rs"),
+        breakpoints = listOf(SourceBreakpoint(line = 3, hardware = true)))
+
+    @Test
+    fun `a breakpoint file reads back what was written`() {
+        val file = BreakpointFile(listOf(main, lib), listOf(FunctionBreakpoint("rust_debuggee::step_*", condition = "x == 1")))
+        val parsed = parseBreakpointFile(formatBreakpointFile(file))
+        assertEquals(listOf(lib, main), parsed.sources)
+        assertEquals(file.functions, parsed.functions)
+    }
+
+    @Test
+    fun `entries without a source, line, or name are skipped`() {
+        val parsed = parseBreakpointFile("""{"version": 1,
+            "sourceBreakpoints": [{"breakpoints": [{"line": 4}]},
+                                  {"source": {"path": "/src/a.rs"}, "breakpoints": [{"line": 0}, {"line": 7}]}],
+            "functionBreakpoints": [{"name": ""}, {"name": "main"}]}""")
+        assertEquals(listOf(SetBreakpointsRequest(seq = 0, source = Source(path = "/src/a.rs"),
+            breakpoints = listOf(SourceBreakpoint(line = 7)))), parsed.sources)
+        assertEquals(listOf(FunctionBreakpoint("main")), parsed.functions)
+    }
+
+    @Test
+    fun `newer and malformed files are rejected`() {
+        assertThrows(SBError::class.java) { parseBreakpointFile("""{"version": ${BREAKPOINT_FILE_VERSION + 1}}""") }
+        assertThrows(SBError::class.java) { parseBreakpointFile("""{"sourceBreakpoints": []}""") }
+        assertThrows(SBError::class.java) { parseBreakpointFile("breakpoints") }
+    }
+
+    @Test
+    fun `imported breakpoints replace the ones at their line and column`() {
+        val current = listOf(SourceBreakpoint(line = 5), SourceBreakpoint(line = 12), SourceBreakpoint(line = 12, column = 4))
+        val imported = listOf(SourceBreakpoint(line = 12, condition = "x > 1"), SourceBreakpoint(line = 30))
+        assertEquals(listOf(SourceBreakpoint(line = 5), SourceBreakpoint(line = 12, column = 4)) + imported,
+            mergeSourceBreakpoints(current, imported))
+    }
+
+    @Test
+    fun `imported function breakpoints replace those with the same name`() {
+        val merged = mergeFunctionBreakpoints(
+            listOf(FunctionBreakpoint("main"), FunctionBreakpoint("run")),
+            listOf(FunctionBreakpoint("run", hitCondition = "3")),
+        )
+        assertEquals(listOf(FunctionBreakpoint("main"), FunctionBreakpoint("run", hitCondition = "3")), merged)
+    }
+}
@@ -0,0 +1,38 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.BreakpointLocationsRequest
+import com.github.jomof.dap.messages.Source
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [filterBreakpointLocations]. Verifies that line-table
+ * entries are narrowed to the requested range, sorted, and deduplicated.
+ */
+class BreakpointLocationsTest {
+
+    private val source = Source(path = "/src/lib.rs")
+
+    @Test
+    fun `entries are sorted and deduplicated`() {
+        val request = BreakpointLocationsRequest(seq = 1, source = source, line = 10, endLine = 12)
+        val entries = listOf(12 to 5, 10 to 9, 10 to 5, 12 to 5, 11 to 0)
+        assertEquals(
+            listOf(10 to 5, 10 to 9, 11 to null, 12 to 5),
+            filterBreakpointLocations(entries, request))
+    }
+
+    @Test
+    fun `unknown column is dropped on lines with known columns`() {
+        val request = BreakpointLocationsRequest(seq = 1, source = source, line = 10)
+        assertEquals(listOf(10 to 5), filterBreakpointLocations(listOf(10 to 0, 10 to 5), request))
+    }
+
+    @Test
+    fun `column bounds apply to the first and last lines`() {
+        val request = BreakpointLocationsRequest(
+            seq = 1, source = source, line = 10, column = 8, endLine = 11, endColumn = 20)
+        val entries = listOf(10 to 5, 10 to 9, 11 to 3, 11 to 21, 12 to 1)
+        assertEquals(listOf(10 to 9, 11 to 3), filterBreakpointLocations(entries, request))
+    }
+}
@@ -0,0 +1,26 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.SourceBreakpoint
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [sourceBreakpointSite]. Verifies that breakpoints on
+ * the same line but different columns keep separate callback state.
+ */
+class BreakpointSiteTest {
+
+    @Test
+    fun `line breakpoints are keyed by line`() {
+        val site = sourceBreakpointSite("/src/main.rs", SourceBreakpoint(line = 10))
+        assertEquals(BreakpointSite("/src/main.rs:10", "on line 10"), site)
+    }
+
+    @Test
+    fun `column breakpoints on one line have distinct keys`() {
+        val outer = sourceBreakpointSite("/src/main.rs", SourceBreakpoint(line = 10, column = 5))
+        val closure = sourceBreakpointSite("/src/main.rs", SourceBreakpoint(line = 10, column = 30))
+        assertNotEquals(outer.key, closure.key)
+        assertEquals("on line 10, column 30", closure.label)
+    }
+}
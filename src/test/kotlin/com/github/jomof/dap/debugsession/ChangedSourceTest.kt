@@ -0,0 +1,87 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.BreakpointEvent
+import com.github.jomof.dap.messages.Checksum
+import com.github.jomof.dap.messages.Source
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+import org.junit.jupiter.api.io.TempDir
+import java.io.File
+import java.time.Instant
+
+/**
+ * Unit tests for [sourceChangedSinceBuild] and
+ * [markChangedSourceBreakpoint]. Verifies that a source counts as
+ * changed when the client says so, when its checksums don't match the
+ * file, or when it is newer than the build, and that `changed` events
+ * keep the mark of its breakpoints.
+ */
+class ChangedSourceTest {
+
+    private fun source(dir: File, modified: Long): File =
+        File(dir, "main.rs").apply {
+            writeText("fn main() {}\n")
+            setLastModified(modified)
+        }
+
+    @Test
+    fun `sources newer than the build have changed`(@TempDir dir: File) {
+        val file = source(dir, 2_000_000)
+        assertTrue(sourceChangedSinceBuild(file, null, emptyList(), builtAt = 1_000_000))
+        assertFalse(sourceChangedSinceBuild(file, null, emptyList(), builtAt = 3_000_000))
+        assertFalse(sourceChangedSinceBuild(file, null, emptyList(), builtAt = null))
+        assertFalse(sourceChangedSinceBuild(File(dir, "missing.rs"), null, emptyList(), builtAt = 1_000_000))
+    }
+
+    @Test
+    fun `sourceModified means changed`(@TempDir dir: File) {
+        assertTrue(sourceChangedSinceBuild(source(dir, 1_000_000), true, emptyList(), builtAt = 3_000_000))
+        assertFalse(sourceChangedSinceBuild(source(dir, 1_000_000), false, emptyList(), builtAt = 3_000_000))
+    }
+
+    @Test
+    fun `checksums that don't match the file mean changed`(@TempDir dir: File) {
+        val file = source(dir, 1_000_000)
+        val md5 = Checksum("MD5", "639e04c270fef8589636e0416761a67b")
+        val edited = Checksum("SHA256", "9c5f3cd2f2f4857b3ef7e1e0e1c1f0a2d7f2bb6ae43d9d6e6c5d5c7c1b2f5e0a")
+        assertFalse(sourceChangedSinceBuild(file, null, listOf(md5), builtAt = 3_000_000))
+        val actual = Checksum("SHA1", "c135f41eadc2f7248d34e03e64a04e8420bf5e4b")
+        assertFalse(sourceChangedSinceBuild(file, null, listOf(actual), builtAt = 3_000_000))
+        assertFalse(sourceChangedSinceBuild(file, null, listOf(actual.copy(checksum = actual.checksum.uppercase())),
+            builtAt = 3_000_000))
+        assertTrue(sourceChangedSinceBuild(file, null, listOf(actual, edited), builtAt = 3_000_000))
+        assertFalse(sourceChangedSinceBuild(file, null, listOf(Checksum("CRC32", "0")), builtAt = 3_000_000))
+    }
+
+    @Test
+    fun `timestamp checksums newer than the build mean changed`(@TempDir dir: File) {
+        val file = source(dir, 1_000_000)
+        assertTrue(sourceChangedSinceBuild(file, null, listOf(Checksum("timestamp", "4000000")), builtAt = 3_000_000))
+        val iso = Instant.ofEpochMilli(4_000_000).toString()
+        assertTrue(sourceChangedSinceBuild(file, null, listOf(Checksum("timestamp", iso)), builtAt = 3_000_000))
+        assertFalse(sourceChangedSinceBuild(file, null, listOf(Checksum("timestamp", "2000000")), builtAt = 3_000_000))
+    }
+
+    @Test
+    fun `changed events keep the mark of breakpoints in changed sources`() {
+        val session = DebugSession()
+        session.changedSourceBreakpoints["/src/main.rs"] = setOf(3)
+        val event = BreakpointEvent(seq = 0, reason = "changed", breakpoint = mapOf("id" to 3, "verified" to true, "line" to 10))
+        assertEquals(mapOf("id" to 3, "verified" to false, "line" to 10, "message" to SOURCE_CHANGED_MESSAGE),
+            session.markChangedSourceBreakpoint(event).breakpoint)
+        val other = event.copy(breakpoint = mapOf("id" to 4, "verified" to true))
+        assertSame(other, session.markChangedSourceBreakpoint(other))
+        val removed = event.copy(reason = "removed")
+        assertSame(removed, session.markChangedSourceBreakpoint(removed))
+    }
+
+    @Test
+    fun `source checksums are parsed`() {
+        val source = Source.fromJson(JSONObject("""{"path": "/src/main.rs",
+            "checksums": [{"algorithm": "MD5", "checksum": "abc"}, {"algorithm": "timestamp", "checksum": "1"}]}"""))
+        assertEquals(listOf(Checksum("MD5", "abc"), Checksum("timestamp", "1")), source.checksums)
+        assertEquals(source, Source.fromJson(source.toJsonObject()))
+        assertNull(Source.fromJson(JSONObject("""{"path": "/src/main.rs"}""")).checksums)
+    }
+}
@@ -0,0 +1,51 @@
+package com.github.jomof.dap.debugsession
+
+import org.json.JSONArray
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [childProcessConfiguration]. Verifies that a child's
+ * `attach` configuration keeps the session-wide settings of its parent
+ * and drops the ones that describe the parent process.
+ */
+class ChildProcessesTest {
+
+    @Test
+    fun `parent process arguments are dropped`() {
+        val parent = JSONObject()
+            .put("program", "/bin/parent")
+            .put("args", JSONArray(listOf("a")))
+            .put("cwd", "/tmp")
+            .put("env", JSONObject().put("A", "1"))
+            .put("stopOnEntry", true)
+            .put("pid", 42)
+            .put("__sessionId", "abc")
+            .put("__childExeced", true)
+        val config = childProcessConfiguration(parent)
+        assertEquals(setOf("followChildProcesses"), config.keySet())
+    }
+
+    @Test
+    fun `session settings are inherited`() {
+        val parent = JSONObject()
+            .put("type", "kdap")
+            .put("name", "Debug server")
+            .put("initCommands", JSONArray(listOf("settings set target.x86-disassembly-flavor intel")))
+            .put("sourceMap", JSONObject().put("/build", "/src"))
+            .put("stopOnExec", true)
+        val config = childProcessConfiguration(parent)
+        assertEquals("kdap", config.getString("type"))
+        assertEquals("Debug server", config.getString("name"))
+        assertEquals(1, config.getJSONArray("initCommands").length())
+        assertEquals("/src", config.getJSONObject("sourceMap").getString("/build"))
+        assertTrue(config.getBoolean("stopOnExec"))
+    }
+
+    @Test
+    fun `children follow their own children`() {
+        val parent = JSONObject().put("followChildProcesses", false)
+        assertTrue(childProcessConfiguration(parent).getBoolean("followChildProcesses"))
+    }
+}
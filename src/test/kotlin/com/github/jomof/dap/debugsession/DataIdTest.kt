@@ -0,0 +1,30 @@
+package com.github.jomof.dap.debugsession
+
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [parseDataId]. Verifies that lldb-dap's
+ * `"<hex address>/<size>"` data breakpoint IDs are decoded and anything
+ * else is rejected.
+ */
+class DataIdTest {
+
+    @Test
+    fun `hex address and size are parsed`() {
+        assertEquals(SoftwareWatchpoint(0x7ffe0010, 8), parseDataId("7ffe0010/8"))
+    }
+
+    @Test
+    fun `0x prefix is accepted`() {
+        assertEquals(SoftwareWatchpoint(0x1000, 4), parseDataId("0x1000/4"))
+    }
+
+    @Test
+    fun `malformed ids are rejected`() {
+        assertNull(parseDataId("variable:x"))
+        assertNull(parseDataId("zz/4"))
+        assertNull(parseDataId("1000/0"))
+        assertNull(parseDataId("1000/4/2"))
+    }
+}
@@ -0,0 +1,78 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.CommonLaunchFields
+import kotlinx.coroutines.runBlocking
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Assumptions.assumeTrue
+import org.junit.jupiter.api.Test
+import java.nio.file.Paths
+
+/**
+ * Unit tests for [debuginfodUrls], [debuginfodCacheDirectory],
+ * [kdapCacheDirectory], [symbolIndexCacheDirectory], [lldbPathList],
+ * [initSeparateDebugInfo], and [initSymbolIndexCache]. Verifies that
+ * servers and the caches default to the debuginfod and XDG environment
+ * variables, that debug file directories are quoted for LLDB's search
+ * path settings, and that the launch's options reach LLDB and the debug
+ * info worker.
+ */
+class DebuginfodTest {
+
+    @Test
+    fun `servers come from the configuration, else DEBUGINFOD_URLS`() {
+        assertEquals(listOf("https://a.example"), debuginfodUrls(listOf("https://a.example"), "https://b.example"))
+        assertEquals(emptyList<String>(), debuginfodUrls(emptyList(), "https://b.example"))
+        assertEquals(listOf("https://b.example", "https://c.example"),
+            debuginfodUrls(null, " https://b.example\thttps://c.example "))
+        assertEquals(emptyList<String>(), debuginfodUrls(null, null))
+    }
+
+    @Test
+    fun `cache follows DEBUGINFOD_CACHE_PATH, then XDG_CACHE_HOME`() {
+        assertEquals(Paths.get("/tmp/di"),
+            debuginfodCacheDirectory(mapOf("DEBUGINFOD_CACHE_PATH" to "/tmp/di", "XDG_CACHE_HOME" to "/x"), "/home/u"))
+        assertEquals(Paths.get("/x/debuginfod_client"), debuginfodCacheDirectory(mapOf("XDG_CACHE_HOME" to "/x"), "/home/u"))
+        assertEquals(Paths.get("/home/u/.cache/debuginfod_client"), debuginfodCacheDirectory(emptyMap(), "/home/u"))
+    }
+
+    @Test
+    fun `KDAP cache follows XDG_CACHE_HOME`() {
+        assertEquals(Paths.get("/x/kdap"), kdapCacheDirectory(mapOf("XDG_CACHE_HOME" to "/x"), "/home/u"))
+        assertEquals(Paths.get("/home/u/.cache/kdap"), kdapCacheDirectory(mapOf("XDG_CACHE_HOME" to ""), "/home/u"))
+        assertEquals(Paths.get("/x/kdap/index"), symbolIndexCacheDirectory(mapOf("XDG_CACHE_HOME" to "/x"), "/home/u"))
+    }
+
+    @Test
+    fun `search path lists quote each directory`() {
+        assertEquals("\"/usr/lib/debug\" \"/build dir/out\"", lldbPathList(listOf("/usr/lib/debug", "/build dir/out")))
+        assertEquals("\"C:\\\\sym\\\"s\"", lldbPathList(listOf("C:\\sym\"s")))
+    }
+
+    @Test
+    fun `the worker starts with the launch's directories and servers`() = runBlocking {
+        assumeTrue(!isWindowsHost(), "separate debug info is not looked up on Windows")
+        val debugger = RecordingDebugger()
+        val common = CommonLaunchFields(
+            debugFileDirectories = listOf("/opt/debug"),
+            debuginfodUrls = listOf("https://debuginfod.example"),
+        )
+        initSeparateDebugInfo(common, debugger)
+        val start = debugger.commands.last()
+        assertTrue(start.startsWith("script _kdap_debug_info_start("), start)
+        assertTrue("/opt/debug" in start, start)
+        assertTrue("https://debuginfod.example" in start, start)
+    }
+
+    @Test
+    fun `the symbol index cache is persisted unless disabled`() = runBlocking {
+        val disabled = RecordingDebugger()
+        initSymbolIndexCache(false, disabled)
+        assertEquals(mapOf("symbols.enable-lldb-index-cache" to "false"), disabled.settings)
+
+        val debugger = RecordingDebugger()
+        initSymbolIndexCache(null, debugger)
+        assertEquals("true", debugger.settings["symbols.enable-lldb-index-cache"])
+        assertEquals(symbolIndexCacheDirectory(System.getenv(), System.getProperty("user.home")).toString(),
+            debugger.settings["symbols.lldb-index-cache-path"])
+    }
+}
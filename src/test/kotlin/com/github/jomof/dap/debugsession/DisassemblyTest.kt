@@ -0,0 +1,78 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.ShowDisassembly
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [needsDisassembly], [formatDisassembly],
+ * [disassemblyLine], [disassemblyFrame], and [parseDisassembledRange].
+ * Verifies which frames each `showDisassembly` setting shows as
+ * disassembly, and that a frame's line is that of the instruction at
+ * its PC in the document's text.
+ */
+class DisassemblyTest {
+
+    private val range = DisassembledRange(
+        name = "memcpy",
+        module = "libc.so.6",
+        start = 0x1000,
+        end = 0x1007,
+        instructions = listOf(
+            DisassembledInstruction(0x1000, "f3 0f 1e fa", "endbr64", ""),
+            DisassembledInstruction(0x1004, "48 89 f8", "mov", "rax, rdi", comment = "dst"),
+        ),
+    )
+
+    private val withoutSource = mapOf("id" to 1, "name" to "memcpy", "line" to 0, "instructionPointerReference" to "0x1004")
+    private val withSource = mapOf("id" to 2, "name" to "main", "source" to mapOf("path" to "/src/main.rs"),
+        "line" to 5, "instructionPointerReference" to "0x2000")
+
+    @Test
+    fun `auto shows frames without a source line, always every frame, never none`() {
+        assertTrue(needsDisassembly(withoutSource, ShowDisassembly.Auto))
+        assertFalse(needsDisassembly(withSource, ShowDisassembly.Auto))
+        assertTrue(needsDisassembly(withSource, ShowDisassembly.Always))
+        assertFalse(needsDisassembly(withoutSource, ShowDisassembly.Never))
+        assertFalse(needsDisassembly(mapOf("id" to 3, "name" to "[std internals]"), ShowDisassembly.Always))
+    }
+
+    @Test
+    fun `the document lists each instruction after the function and module`() {
+        assertEquals("""
+            ; memcpy
+            ; libc.so.6
+            0x1000: f3 0f 1e fa              endbr64
+            0x1004: 48 89 f8                 mov      rax, rdi  ; dst
+
+        """.trimIndent(), formatDisassembly(range))
+        assertTrue(range.contains(0x1006) && !range.contains(0x1007))
+    }
+
+    @Test
+    fun `a frame's line is the instruction at or before its PC`() {
+        assertEquals(3, disassemblyLine(range, 0x1000))
+        assertEquals(4, disassemblyLine(range, 0x1004))
+        assertEquals(3, disassemblyLine(range, 0x1002))
+        assertEquals(2, disassemblyLine(range.copy(module = null), 0x1000))
+    }
+
+    @Test
+    fun `a disassembly frame refers to the document at the PC's line`() {
+        val frame = disassemblyFrame(withoutSource + ("endLine" to 9), DISASSEMBLY_SOURCE_REFERENCE_BASE, range, 0x1004)
+        assertEquals(mapOf("name" to "@memcpy", "sourceReference" to DISASSEMBLY_SOURCE_REFERENCE_BASE, "origin" to "disassembly"),
+            frame["source"])
+        assertEquals(4, frame["line"])
+        assertFalse("endLine" in frame)
+    }
+
+    @Test
+    fun `ranges parse from the Python side's JSON`() {
+        val parsed = parseDisassembledRange(JSONObject("""{"name":"memcpy","module":"libc.so.6","start":4096,"end":4103,
+            "instructions":[{"address":4096,"bytes":"f3 0f 1e fa","mnemonic":"endbr64","operands":"","comment":""},
+                            {"address":4100,"bytes":"48 89 f8","mnemonic":"mov","operands":"rax, rdi","comment":"dst"}]}"""))
+        assertEquals(range, parsed)
+        assertNull(parseDisassembledRange(JSONObject("""{"name":"0x10","module":null,"start":16,"end":17,"instructions":[]}""")).module)
+    }
+}
@@ -0,0 +1,47 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.EntryStop
+import com.github.jomof.dap.messages.LaunchRequestArguments
+import com.github.jomof.dap.messages.StoppedEvent
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for the initial stops. Verifies that `stopOnEntry` takes
+ * its boolean and string forms and that the stop at the `main`
+ * breakpoint is reported as an `entry` stop, once.
+ */
+class EntryStopsTest {
+
+    @Test
+    fun `stopOnEntry takes booleans and stop names`() {
+        fun parse(value: Any) = LaunchRequestArguments.fromJson(JSONObject().put("stopOnEntry", value)).stopOnEntry
+        assertEquals(EntryStop.Entry, parse(true))
+        assertEquals(EntryStop.Entry, parse("entry"))
+        assertEquals(EntryStop.Main, parse("main"))
+        assertNull(parse(false))
+        assertEquals("main", LaunchRequestArguments(stopOnEntry = EntryStop.Main).toJsonObject().get("stopOnEntry"))
+        assertEquals(true, LaunchRequestArguments(stopOnEntry = EntryStop.Entry).toJsonObject().get("stopOnEntry"))
+    }
+
+    @Test
+    fun `the main breakpoint stop is an entry stop`() {
+        val session = DebugSession()
+        session.entryBreakpointId = 7
+        val hit = StoppedEvent(seq = 0, reason = "breakpoint", description = "breakpoint 7.1", threadId = 1,
+            hitBreakpointIds = listOf(7))
+        assertEquals(StoppedEvent(seq = 0, reason = "entry", threadId = 1), session.rewriteEntryStop(hit))
+        assertNull(session.entryBreakpointId)
+        assertEquals(hit, session.rewriteEntryStop(hit))
+    }
+
+    @Test
+    fun `other stops are left alone`() {
+        val session = DebugSession()
+        session.entryBreakpointId = 7
+        val other = StoppedEvent(seq = 0, reason = "breakpoint", description = "breakpoint 3.1", hitBreakpointIds = listOf(3))
+        assertEquals(other, session.rewriteEntryStop(other))
+        assertEquals(7, session.entryBreakpointId)
+    }
+}
@@ -0,0 +1,41 @@
+package com.github.jomof.dap.debugsession
+
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [functionNameRegex]. Verifies that wildcard and `/re`
+ * function breakpoint names become regexes and plain names are left to
+ * lldb-dap.
+ */
+class FunctionNameRegexTest {
+
+    @Test
+    fun `plain and mangled names are not patterns`() {
+        assertNull(functionNameRegex("main"))
+        assertNull(functionNameRegex("rust_debuggee::step_in"))
+        assertNull(functionNameRegex("_ZN13rust_debuggee7step_in17h0123456789abcdefE"))
+    }
+
+    @Test
+    fun `wildcards match the whole name with an optional rust hash`() {
+        val regex = Regex(functionNameRegex("rust_debuggee::step_in*")!!)
+        assertTrue(regex.matches("rust_debuggee::step_in"))
+        assertTrue(regex.matches("rust_debuggee::step_in_nested"))
+        assertTrue(regex.matches("rust_debuggee::step_in::h0123456789abcdef"))
+        assertFalse(regex.matches("other::rust_debuggee::step_in"))
+    }
+
+    @Test
+    fun `question mark matches one character and specials are escaped`() {
+        val regex = Regex(functionNameRegex("vec<?>.push")!!)
+        assertTrue(regex.matches("vec<T>.push"))
+        assertFalse(regex.matches("vec<T>xpush"))
+    }
+
+    @Test
+    fun `re prefix gives the regex directly`() {
+        assertEquals("^step_.*", functionNameRegex("/re ^step_.*"))
+        assertNull(functionNameRegex("/re "))
+    }
+}
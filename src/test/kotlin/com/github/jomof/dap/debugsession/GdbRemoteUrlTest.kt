@@ -0,0 +1,23 @@
+package com.github.jomof.dap.debugsession
+
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [gdbRemoteUrl]. Verifies that `gdbRemote` addresses
+ * become LLDB connect URLs.
+ */
+class GdbRemoteUrlTest {
+
+    @Test
+    fun `host and port get the connect scheme`() {
+        assertEquals("connect://192.168.1.20:2345", gdbRemoteUrl("192.168.1.20:2345"))
+        assertEquals("connect://localhost:5039", gdbRemoteUrl("localhost:5039"))
+    }
+
+    @Test
+    fun `URLs with a scheme are used as is`() {
+        assertEquals("connect://device:1234", gdbRemoteUrl("connect://device:1234"))
+        assertEquals("unix-abstract-connect:///gdbserver", gdbRemoteUrl("unix-abstract-connect:///gdbserver"))
+    }
+}
@@ -0,0 +1,28 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.sb.SBError
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [parseHeapAddress]. Verifies that `kdap/heap` addresses
+ * are read in hex or decimal, including addresses above the signed
+ * 64-bit range, and that other text is rejected.
+ */
+class HeapTrackingTest {
+
+    @Test
+    fun `addresses are read in hex or decimal`() {
+        assertEquals(0x5555555592a0, parseHeapAddress("0x5555555592a0"))
+        assertEquals(0x5555555592a0, parseHeapAddress(" 0X5555555592A0 "))
+        assertEquals(4096L, parseHeapAddress("4096"))
+        assertEquals("ffff800000001000", "%x".format(parseHeapAddress("0xffff800000001000")))
+    }
+
+    @Test
+    fun `other text is rejected`() {
+        assertThrows(SBError::class.java) { parseHeapAddress("heap") }
+        assertThrows(SBError::class.java) { parseHeapAddress("0x") }
+        assertThrows(SBError::class.java) { parseHeapAddress("-1") }
+    }
+}
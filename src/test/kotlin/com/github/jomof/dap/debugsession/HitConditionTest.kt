@@ -0,0 +1,57 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.debugsession.HitCondition.Op
+import com.github.jomof.dap.messages.SourceBreakpoint
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [HitCondition] parsing. Verifies CodeLLDB's hit condition
+ * syntax and that invalid hit conditions are reported rather than applied.
+ */
+class HitConditionTest {
+
+    @Test
+    fun `plain number means at least N hits`() {
+        assertEquals(HitCondition(Op.GE, 5), HitCondition.parse("5"))
+    }
+
+    @Test
+    fun `comparison operators are parsed`() {
+        assertEquals(HitCondition(Op.LT, 3), HitCondition.parse("<3"))
+        assertEquals(HitCondition(Op.LE, 3), HitCondition.parse("<= 3"))
+        assertEquals(HitCondition(Op.EQ, 3), HitCondition.parse("=3"))
+        assertEquals(HitCondition(Op.EQ, 3), HitCondition.parse("== 3"))
+        assertEquals(HitCondition(Op.GE, 3), HitCondition.parse(">=3"))
+        assertEquals(HitCondition(Op.GT, 3), HitCondition.parse(" > 3 "))
+    }
+
+    @Test
+    fun `modulo renders as divisibility check`() {
+        val condition = HitCondition.parse("%4")
+        assertEquals(HitCondition(Op.MOD, 4), condition)
+        assertEquals("n % 4 == 0", condition!!.toPython("n"))
+    }
+
+    @Test
+    fun `invalid hit conditions are rejected`() {
+        assertNull(HitCondition.parse("often"))
+        assertNull(HitCondition.parse(">="))
+        assertNull(HitCondition.parse("%0"))
+        assertNull(HitCondition.parse("-1"))
+    }
+
+    @Test
+    fun `invalid hit condition is recorded in the plan and not scripted`() {
+        val plan = planSourceBreakpoint(SourceBreakpoint(line = 1, hitCondition = "sometimes"))
+        assertEquals("sometimes", plan.invalidHitCondition)
+        assertFalse(plan.isScripted)
+    }
+
+    @Test
+    fun `valid hit condition makes the breakpoint scripted`() {
+        val plan = planSourceBreakpoint(SourceBreakpoint(line = 1, hitCondition = ">= 2"))
+        assertEquals(HitCondition(Op.GE, 2), plan.hitCondition)
+        assertTrue(plan.isScripted)
+    }
+}
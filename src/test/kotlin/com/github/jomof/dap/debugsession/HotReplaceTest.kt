@@ -0,0 +1,57 @@
+package com.github.jomof.dap.debugsession
+
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [jumpPatch], [planHotReplace], and [linkCommand].
+ * Verifies that the jumps encode the replacement's address, and that
+ * functions too small for the jump or on other architectures are
+ * skipped with a reason.
+ */
+class HotReplaceTest {
+
+    private fun hex(bytes: ByteArray?): String? = bytes?.joinToString(" ") { "%02x".format(it) }
+
+    @Test
+    fun `x86-64 jumps through rax`() {
+        assertEquals("48 b8 09 91 fb f7 ff 7f 00 00 ff e0", hex(jumpPatch("x86_64-unknown-linux-gnu", 0x7ffff7fb9109)))
+    }
+
+    @Test
+    fun `AArch64 jumps through x16 with the address after the code`() {
+        assertEquals("50 00 00 58 00 02 1f d6 00 40 3c f7 ff ff 00 00",
+            hex(jumpPatch("aarch64-unknown-linux-gnu", 0xfffff73c4000)))
+        assertEquals(16, jumpPatch("arm64-apple-macosx", 0)?.size)
+    }
+
+    @Test
+    fun `other architectures cannot be patched`() {
+        assertNull(jumpPatch("riscv64-unknown-linux-gnu", 0x1000))
+        assertNull(jumpPatch("", 0x1000))
+    }
+
+    @Test
+    fun `small functions and unsupported targets are skipped`() {
+        val functions = listOf(
+            HotFunction("shade", address = 0x401136, size = 40, replacement = 0x7ffff7fb9109),
+            HotFunction("tiny", address = 0x401160, size = 4, replacement = 0x7ffff7fb9140),
+        )
+        val plan = planHotReplace(functions, "x86_64-unknown-linux-gnu")
+        assertEquals(listOf("shade"), plan.patches.map { it.name })
+        assertEquals(0x401136L, plan.patches.single().address)
+        assertEquals(mapOf("tiny" to "is 4 bytes, too small for a 12-byte jump"), plan.skipped)
+
+        val other = planHotReplace(functions, "riscv64-unknown-linux-gnu")
+        assertTrue(other.patches.isEmpty())
+        assertEquals("cannot be patched on riscv64", other.skipped["shade"])
+    }
+
+    @Test
+    fun `objects are linked into shared libraries`() {
+        assertTrue(isObjectFile("/work/build/shade.o"))
+        assertFalse(isObjectFile("/work/build/libshade.so"))
+        assertEquals(listOf("cc", "-shared", "-o", "/tmp/kdap-hot-1.so", "/work/build/shade.o"),
+            linkCommand("cc", "/work/build/shade.o", "/tmp/kdap-hot-1.so"))
+    }
+}
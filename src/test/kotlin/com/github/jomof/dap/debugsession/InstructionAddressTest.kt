@@ -0,0 +1,24 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.InstructionBreakpoint
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [instructionAddress]. Verifies that an instruction
+ * breakpoint's reference and offset are combined into one address.
+ */
+class InstructionAddressTest {
+
+    @Test
+    fun `offset is added to the reference`() {
+        assertEquals("0x1004", instructionAddress(InstructionBreakpoint("0x1000", offset = 4)))
+        assertEquals("0xffc", instructionAddress(InstructionBreakpoint("0x1000", offset = -4)))
+        assertEquals("0x1000", instructionAddress(InstructionBreakpoint("0x1000")))
+    }
+
+    @Test
+    fun `non-address references are kept as sent`() {
+        assertEquals("main+8", instructionAddress(InstructionBreakpoint("main", offset = 8)))
+    }
+}
@@ -0,0 +1,64 @@
+package com.github.jomof.dap.debugsession
+
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [demangleKotlinName], [demangleKotlinFrames], and
+ * [kotlinFormatterCommands]. Verifies that `kfun:` symbols read as Kotlin
+ * signatures, that other names are left alone, and that the formatters
+ * are registered by recognizer before the category is enabled.
+ */
+class KotlinNativeTest {
+
+    @Test
+    fun `function symbols are demangled`() {
+        assertEquals("main(Array<String>)", demangleKotlinName("kfun:#main(kotlin.Array<kotlin.String>){}"))
+        assertEquals("kdap.debuggee.Circle.area()", demangleKotlinName("kfun:kdap.debuggee.Circle#area(){}kotlin.Double"))
+        assertEquals("kdap.debuggee.add2(Int, Int)",
+            demangleKotlinName("kfun:kdap.debuggee#add2(kotlin.Int;kotlin.Int){}kotlin.Int"))
+        assertEquals("kdap.debuggee.Circle.<init>(Double)",
+            demangleKotlinName("kfun:kdap.debuggee.Circle#<init>(kotlin.Double){}"))
+    }
+
+    @Test
+    fun `receivers, generics, and library types are kept readable`() {
+        assertEquals("kdap.debuggee.foo@String()", demangleKotlinName("kfun:kdap.debuggee#foo@kotlin.String(){}"))
+        assertEquals("kotlin.collections.filter@kotlin.collections.Iterable<0:0>(Function1<0:0,Boolean>)",
+            demangleKotlinName("kfun:kotlin.collections#filter@kotlin.collections.Iterable<0:0>" +
+                "(kotlin.Function1<0:0,kotlin.Boolean>){0§<kotlin.Any?>}kotlin.collections.List<0:0>"))
+        assertEquals("kdap.debuggee.show(kotlin.collections.Map<String,Int>?, kdap.debuggee.Point)",
+            demangleKotlinName("kfun:kdap.debuggee#show(kotlin.collections.Map<kotlin.String,kotlin.Int>?;kdap.debuggee.Point){}"))
+    }
+
+    @Test
+    fun `other names are unchanged`() {
+        assertEquals("main", demangleKotlinName("main"))
+        assertEquals("std::vector<int>::push_back(int const&)", demangleKotlinName("std::vector<int>::push_back(int const&)"))
+        assertEquals("kfun:broken(", demangleKotlinName("kfun:broken("))
+    }
+
+    @Test
+    fun `frames are demangled and otherwise kept`() {
+        val frames = listOf(
+            mapOf("id" to 1, "name" to "kfun:#main(kotlin.Array<kotlin.String>){}", "line" to 3),
+            mapOf("id" to 2, "name" to "Konan_run_start"),
+            "not a frame",
+        )
+        assertEquals(listOf(
+            mapOf("id" to 1, "name" to "main(Array<String>)", "line" to 3),
+            mapOf("id" to 2, "name" to "Konan_run_start"),
+            "not a frame",
+        ), demangleKotlinFrames(frames))
+    }
+
+    @Test
+    fun `commands register the formatters by recognizer then enable the category`() {
+        val commands = kotlinFormatterCommands()
+        assertEquals(listOf(
+            "type summary add -w kdap-kotlin -F _kdap_kt_summary --recognizer-function _kdap_kt_is_object",
+            "type synthetic add -w kdap-kotlin -l _KdapKotlinProvider --recognizer-function _kdap_kt_is_object",
+            "type category enable kdap-kotlin",
+        ), commands)
+    }
+}
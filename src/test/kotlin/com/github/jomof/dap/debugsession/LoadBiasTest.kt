@@ -0,0 +1,56 @@
+package com.github.jomof.dap.debugsession
+
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+import java.nio.ByteBuffer
+import java.nio.ByteOrder
+
+/**
+ * Unit tests for [parseAuxv] and [loadBiasCorrection]. Verifies that
+ * auxiliary vectors of either word size and byte order are read up to
+ * their terminator, and that a program is only slid when LLDB has its
+ * entry point somewhere other than where it was loaded.
+ */
+class LoadBiasTest {
+
+    private fun auxv(vararg words: Long, pointerSize: Int = 8, order: ByteOrder = ByteOrder.LITTLE_ENDIAN): ByteArray {
+        val buffer = ByteBuffer.allocate(words.size * pointerSize).order(order)
+        for (word in words) if (pointerSize == 8) buffer.putLong(word) else buffer.putInt(word.toInt())
+        return buffer.array()
+    }
+
+    @Test
+    fun `auxiliary vectors are read up to AT_NULL`() {
+        val bytes = auxv(6, 4096, AT_ENTRY, 0x555555555040, 0, 0, AT_ENTRY, 1)
+        assertEquals(mapOf(6L to 4096L, AT_ENTRY to 0x555555555040L), parseAuxv(bytes, 8, littleEndian = true))
+    }
+
+    @Test
+    fun `32-bit and big-endian vectors are read`() {
+        val bytes = auxv(AT_ENTRY, 0xf7fd1230, pointerSize = 4, order = ByteOrder.BIG_ENDIAN)
+        assertEquals(0xf7fd1230L, parseAuxv(bytes, 4, littleEndian = false)[AT_ENTRY])
+        assertTrue(parseAuxv(ByteArray(3), 8, littleEndian = true).isEmpty())
+    }
+
+    @Test
+    fun `a program at its file addresses is slid to the loaded entry point`() {
+        assertEquals(0x555555554000L,
+            loadBiasCorrection(atEntry = 0x555555555040, linkMapBias = null, fileEntry = 0x1040, loadEntry = 0x1040))
+        assertEquals(0x555555554000L,
+            loadBiasCorrection(atEntry = 0x555555555040, linkMapBias = null, fileEntry = 0x1040, loadEntry = null))
+    }
+
+    @Test
+    fun `the link map is used without an auxiliary vector`() {
+        assertEquals(0x7f0000000000L,
+            loadBiasCorrection(atEntry = null, linkMapBias = 0x7f0000000000, fileEntry = 0x1040, loadEntry = 0x1040))
+        assertNull(loadBiasCorrection(atEntry = null, linkMapBias = null, fileEntry = 0x1040, loadEntry = 0x1040))
+    }
+
+    @Test
+    fun `programs LLDB already placed are left alone`() {
+        assertNull(loadBiasCorrection(atEntry = 0x555555555040, linkMapBias = null, fileEntry = 0x1040,
+            loadEntry = 0x555555555040))
+        assertNull(loadBiasCorrection(atEntry = 0x401040, linkMapBias = null, fileEntry = 0x401040, loadEntry = 0x401040))
+    }
+}
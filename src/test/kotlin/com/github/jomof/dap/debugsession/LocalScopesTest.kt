@@ -0,0 +1,94 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.DapResponse
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for the Arguments, Locals, and Captures scopes. Verifies
+ * that lldb-dap's Locals scope is split into the scopes that have
+ * variables, that each scope shows its part of lldb-dap's variables,
+ * that unborn locals are left out and shadowed ones renamed, and that
+ * variables that don't line up all stay in Locals.
+ */
+class LocalScopesTest {
+
+    private val session = DebugSession()
+
+    private val scopesResponse = DapResponse(
+        seq = 1, requestSeq = 5, command = "scopes", success = true,
+        body = mapOf("scopes" to listOf(
+            mapOf("name" to "Locals", "presentationHint" to "locals", "variablesReference" to 1,
+                "namedVariables" to 4, "expensive" to false),
+            mapOf("name" to "Globals", "variablesReference" to 2),
+        )),
+    )
+
+    private fun variable(name: String) = mapOf("name" to name, "value" to "1", "variablesReference" to 0)
+
+    private fun locals(vararg kinds: LocalKind) = kinds.map { LocalVariable(it) }
+
+    @Test
+    fun `locals scope is split into arguments, locals, and captures`() {
+        val kinds = locals(LocalKind.Environment, LocalKind.Argument, LocalKind.Capture, LocalKind.Local)
+        val scopes = session.withLocalScopes(scopesResponse, kinds).body["scopes"] as List<*>
+        assertEquals(listOf(
+            mapOf("name" to "Arguments", "variablesReference" to FIRST_LOCAL_SCOPE_REFERENCE,
+                "expensive" to false, "presentationHint" to "arguments"),
+            mapOf("name" to "Locals", "variablesReference" to 1, "expensive" to false, "presentationHint" to "locals"),
+            mapOf("name" to "Captures", "variablesReference" to FIRST_LOCAL_SCOPE_REFERENCE + 1, "expensive" to false),
+            mapOf("name" to "Globals", "variablesReference" to 2),
+        ), scopes)
+        assertEquals(LocalScope(1, LocalScopeKind.Captures), session.localScopes[FIRST_LOCAL_SCOPE_REFERENCE + 1])
+        assertEquals(kinds, session.localsPartitions[1])
+        session.forgetLocalScopes()
+        assertTrue(session.localScopes.isEmpty())
+        assertTrue(session.localsPartitions.isEmpty())
+    }
+
+    @Test
+    fun `scopes without variables of their kind are left out`() {
+        val scopes = session.withLocalScopes(scopesResponse, locals(LocalKind.Local, LocalKind.Unborn)).body["scopes"] as List<*>
+        assertEquals(listOf("Locals", "Globals"), scopes.map { (it as Map<*, *>)["name"] })
+        assertEquals(scopesResponse, session.withLocalScopes(scopesResponse, null))
+    }
+
+    @Test
+    fun `each scope shows its part of the locals`() {
+        val variables = listOf(variable(LLDB_DAP_RETURN_VALUE), variable("self"), variable("n"), variable("x"), variable("sum"))
+        val kinds = locals(LocalKind.Environment, LocalKind.Argument, LocalKind.Capture, LocalKind.Local)
+        assertEquals(listOf(variable("n")), partitionLocals(variables, kinds, LocalScopeKind.Arguments))
+        assertEquals(listOf(variable(LLDB_DAP_RETURN_VALUE), variable("sum")),
+            partitionLocals(variables, kinds, LocalScopeKind.Locals))
+        assertEquals(listOf(variable("self"), variable("x")), partitionLocals(variables, kinds, LocalScopeKind.Captures))
+    }
+
+    @Test
+    fun `variables that do not line up stay in locals`() {
+        val variables = listOf(variable("a"), variable("b"))
+        val kinds = locals(LocalKind.Argument)
+        assertEquals(variables, partitionLocals(variables, kinds, LocalScopeKind.Locals))
+        assertEquals(emptyList<Any>(), partitionLocals(variables, kinds, LocalScopeKind.Arguments))
+    }
+
+    @Test
+    fun `unborn locals are left out and shadowed ones are named after their declaration`() {
+        val variables = listOf(variable("x @ main.rs:3"), variable("x @ main.rs:4"), variable("x @ main.rs:9"))
+        val kinds = listOf(
+            LocalVariable(LocalKind.Local, "x @ main.rs:3"),
+            LocalVariable(LocalKind.Local, "x @ main.rs:4"),
+            LocalVariable(LocalKind.Unborn, "x"),
+        )
+        assertEquals(listOf(variable("x @ main.rs:3"), variable("x @ main.rs:4")),
+            partitionLocals(variables, kinds, LocalScopeKind.Locals))
+        assertEquals(listOf(variable("x")), partitionLocals(variables.take(2),
+            listOf(LocalVariable(LocalKind.Local, "x"), LocalVariable(LocalKind.Unborn, "x")), LocalScopeKind.Locals))
+    }
+
+    @Test
+    fun `variables are parsed from the python side`() {
+        assertEquals(listOf(LocalVariable(LocalKind.Argument, "n"), LocalVariable(LocalKind.Unborn)),
+            parseLocals("""{"variables":[{"kind":"Argument","name":"n"},{"kind":"Unborn","name":null}]}"""))
+        assertNull(parseLocals("""{"error":"The frame is gone."}"""))
+    }
+}
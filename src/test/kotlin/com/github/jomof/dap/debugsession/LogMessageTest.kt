@@ -0,0 +1,46 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.debugsession.LogSegment.Expr
+import com.github.jomof.dap.debugsession.LogSegment.Text
+import com.github.jomof.dap.messages.SourceBreakpoint
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [parseLogMessage]. Verifies that logpoint templates split
+ * into text and `{expr}` placeholders, and that logpoints never stop.
+ */
+class LogMessageTest {
+
+    @Test
+    fun `plain text is a single segment`() {
+        assertEquals(listOf(Text("hello")), parseLogMessage("hello"))
+    }
+
+    @Test
+    fun `placeholders are split out and trimmed`() {
+        assertEquals(
+            listOf(Text("i = "), Expr("i"), Text(", sum = "), Expr("a + b")),
+            parseLogMessage("i = {i}, sum = { a + b }"),
+        )
+    }
+
+    @Test
+    fun `doubled braces are literal`() {
+        assertEquals(listOf(Text("{i} = "), Expr("i")), parseLogMessage("{{i}} = {i}"))
+    }
+
+    @Test
+    fun `unterminated brace is kept as text`() {
+        assertEquals(listOf(Text("x = "), Expr("x"), Text(" {oops")), parseLogMessage("x = {x} {oops"))
+    }
+
+    @Test
+    fun `logpoint callback prints and continues`() {
+        val plan = planSourceBreakpoint(SourceBreakpoint(line = 3, logMessage = "n={n}"))
+        assertTrue(plan.isScripted)
+        val body = breakpointCallbackBody("/src/main.rs:3", plan)
+        assertTrue("_kdap_fmt('n')" in body)
+        assertTrue(body.endsWith("return False"))
+    }
+}
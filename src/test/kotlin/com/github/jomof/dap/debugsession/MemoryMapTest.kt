@@ -0,0 +1,49 @@
+package com.github.jomof.dap.debugsession
+
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [parseProcMaps], [MemoryMapping.toMap], and
+ * [formatMemoryMap]. Verifies that `/proc/<pid>/maps` lines become
+ * regions with their permissions, offsets, and paths, and how the
+ * regions are reported and printed.
+ */
+class MemoryMapTest {
+
+    private val maps = """
+        555555554000-555555559000 r--p 00000000 fd:01 1234                       /work/target/debug/app
+        555555559000-55555557a000 rw-p 00000000 00:00 0                          [heap]
+        7ffff7d80000-7ffff7d82000 rw-p 00000000 00:00 0
+        ffffffffff600000-ffffffffff601000 --xp 00000000 00:00 0                  [vsyscall]
+        not a mapping
+    """.trimIndent()
+
+    @Test
+    fun `proc maps lines are parsed`() {
+        val mappings = parseProcMaps(maps)
+        assertEquals(4, mappings.size)
+        assertEquals(MemoryMapping(0x555555554000, 0x555555559000, "r--p", 0, "/work/target/debug/app"), mappings[0])
+        assertEquals("[heap]", mappings[1].path)
+        assertNull(mappings[2].path)
+        assertEquals(0x1000L, mappings[3].end - mappings[3].start)
+    }
+
+    @Test
+    fun `regions are reported with hex addresses and a size`() {
+        assertEquals(
+            mapOf("start" to "0x1000", "end" to "0x3000", "size" to 0x2000L, "permissions" to "r-x",
+                "path" to "[stack]"),
+            MemoryMapping(0x1000, 0x3000, "r-x", path = "[stack]").toMap(),
+        )
+    }
+
+    @Test
+    fun `memory map prints one region per line`() {
+        val lines = formatMemoryMap(parseProcMaps(maps)).lines()
+        assertTrue(lines[0].startsWith("Start"))
+        assertTrue(lines[1].startsWith("0x0000555555554000  0x0000555555559000  r--p  0x00000000"))
+        assertTrue(lines[1].endsWith("/work/target/debug/app"))
+        assertTrue(lines[4].startsWith("0xffffffffff600000"))
+    }
+}
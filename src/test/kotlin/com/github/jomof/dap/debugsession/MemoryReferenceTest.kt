@@ -0,0 +1,65 @@
+package com.github.jomof.dap.debugsession
+
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for the pointer classification in `Variables.kt`. Verifies
+ * that addresses are read from pointer value text and that Rust owning
+ * and fat pointers and C++ containers, strings, and smart pointers are
+ * recognised by type name.
+ */
+class MemoryReferenceTest {
+
+    @Test
+    fun `address is parsed from pointer value text`() {
+        assertEquals(0x7ffeefbff5c8, pointerValueAddress("0x00007ffeefbff5c8"))
+        assertEquals(0x55555555a2a0, pointerValueAddress("0x000055555555a2a0 \"hello\""))
+    }
+
+    @Test
+    fun `null and non-address values have no address`() {
+        assertNull(pointerValueAddress("0x0000000000000000"))
+        assertNull(pointerValueAddress("42"))
+        assertNull(pointerValueAddress("{...}"))
+    }
+
+    @Test
+    fun `C and Rust raw pointers and references are pointer types`() {
+        assertTrue(isPointerType("int *"))
+        assertTrue(isPointerType("*const u8"))
+        assertTrue(isPointerType("*mut i32"))
+        assertTrue(isPointerType("&i32"))
+        assertFalse(isPointerType("i32"))
+        assertFalse(isPointerType("&[u8]"))
+        assertFalse(isPointerType("&str"))
+    }
+
+    @Test
+    fun `Rust owning and fat pointers are recognised`() {
+        assertTrue(isRustDataPointerType("alloc::boxed::Box<i32, alloc::alloc::Global>"))
+        assertTrue(isRustDataPointerType("alloc::vec::Vec<u8, alloc::alloc::Global>"))
+        assertTrue(isRustDataPointerType("alloc::string::String"))
+        assertTrue(isRustDataPointerType("alloc::sync::Arc<u64, alloc::alloc::Global>"))
+        assertTrue(isRustDataPointerType("&[i32]"))
+        assertTrue(isRustDataPointerType("&mut [i32]"))
+        assertTrue(isRustDataPointerType("&str"))
+        assertFalse(isRustDataPointerType("core::option::Option<i32>"))
+    }
+
+    @Test
+    fun `C++ vectors, strings, and smart pointers are recognised`() {
+        assertTrue(isCppDataPointerType("std::vector<int, std::allocator<int> >"))
+        assertTrue(isCppDataPointerType("std::__1::vector<int, std::__1::allocator<int> >"))
+        assertTrue(isCppDataPointerType("std::string"))
+        assertTrue(isCppDataPointerType("std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> >"))
+        assertTrue(isCppDataPointerType("std::wstring"))
+        assertTrue(isCppDataPointerType("std::unique_ptr<Struct, std::default_delete<Struct> >"))
+        assertTrue(isCppDataPointerType("std::shared_ptr<std::map<int, float> >"))
+        assertFalse(isCppDataPointerType("std::map<int, float>"))
+        assertFalse(isCppDataPointerType("std::string *"))
+        assertFalse(isCppDataPointerType("my::std::vector<int>"))
+        assertTrue(isPointerType("std::string *"))
+        assertFalse(isPointerType("std::vector<int>"))
+    }
+}
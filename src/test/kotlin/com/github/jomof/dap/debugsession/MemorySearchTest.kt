@@ -0,0 +1,53 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.SearchMemoryRequest
+import com.github.jomof.dap.sb.SBError
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [searchPattern] and [searchChunks]. Verifies that hex
+ * byte patterns and strings become the bytes searched for, that
+ * malformed or ambiguous patterns are rejected, and that a region is read
+ * in chunks that overlap by the pattern's length.
+ */
+class MemorySearchTest {
+
+    @Test
+    fun `hex bytes are parsed with spaces or a 0x prefix`() {
+        val expected = byteArrayOf(0xde.toByte(), 0xad.toByte(), 0xbe.toByte(), 0xef.toByte())
+        assertArrayEquals(expected, searchPattern(SearchMemoryRequest(seq = 1, bytes = "de ad be ef")))
+        assertArrayEquals(expected, searchPattern(SearchMemoryRequest(seq = 1, bytes = "0xDEADBEEF")))
+    }
+
+    @Test
+    fun `strings are searched for as UTF-8`() {
+        assertArrayEquals("héllo".toByteArray(Charsets.UTF_8),
+            searchPattern(SearchMemoryRequest(seq = 1, string = "héllo")))
+    }
+
+    @Test
+    fun `malformed patterns are rejected`() {
+        assertThrows(SBError::class.java) { searchPattern(SearchMemoryRequest(seq = 1)) }
+        assertThrows(SBError::class.java) { searchPattern(SearchMemoryRequest(seq = 1, bytes = "ab", string = "x")) }
+        assertThrows(SBError::class.java) { searchPattern(SearchMemoryRequest(seq = 1, bytes = "abc")) }
+        assertThrows(SBError::class.java) { searchPattern(SearchMemoryRequest(seq = 1, bytes = "zz")) }
+        assertThrows(SBError::class.java) { searchPattern(SearchMemoryRequest(seq = 1, string = "")) }
+    }
+
+    @Test
+    fun `regions are read in chunks overlapping by the pattern length`() {
+        val start = 0x7fff_0000_0000uL
+        val chunks = searchChunks(start, start + 2uL * SEARCH_CHUNK_SIZE + 16uL, patternLength = 4).toList()
+        assertEquals(
+            listOf(
+                start to SEARCH_CHUNK_SIZE + 3uL,
+                start + SEARCH_CHUNK_SIZE to SEARCH_CHUNK_SIZE + 3uL,
+                start + 2uL * SEARCH_CHUNK_SIZE to 16uL,
+            ),
+            chunks,
+        )
+        assertEquals(listOf(0xffff_ffff_ff60_0000uL to 0x1000uL),
+            searchChunks(0xffff_ffff_ff60_0000uL, 0xffff_ffff_ff60_1000uL, patternLength = 1).toList())
+    }
+}
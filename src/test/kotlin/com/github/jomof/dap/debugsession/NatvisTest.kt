@@ -0,0 +1,106 @@
+package com.github.jomof.dap.debugsession
+
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+import org.junit.jupiter.api.io.TempDir
+import java.io.File
+
+/**
+ * Unit tests for [parseNatvis], [natvisTypePattern], and [natvisFiles].
+ * Verifies that Natvis types, display strings, and expand items are read
+ * with their conditions, that type patterns match LLDB's spelling of
+ * template names, and that malformed files are rejected.
+ */
+class NatvisTest {
+
+    private val vector = """
+        <?xml version="1.0" encoding="utf-8"?>
+        <AutoVisualizer xmlns="http://schemas.microsoft.com/vstudio/debugger/natvis/2010">
+          <Type Name="std::vector&lt;*&gt;">
+            <AlternativeType Name="my::Vec&lt;*&gt;" />
+            <DisplayString Condition="_Mylast == _Myfirst">empty</DisplayString>
+            <DisplayString>{{ size={_Mylast - _Myfirst} }}</DisplayString>
+            <Expand>
+              <Item Name="[capacity]" ExcludeView="simple">_Myend - _Myfirst</Item>
+              <ArrayItems>
+                <Size>_Mylast - _Myfirst</Size>
+                <ValuePointer>_Myfirst</ValuePointer>
+              </ArrayItems>
+            </Expand>
+          </Type>
+          <Type Name="List">
+            <Expand>
+              <LinkedListItems Condition="head != 0">
+                <HeadPointer>head</HeadPointer>
+                <NextPointer>next</NextPointer>
+                <ValueNode>value</ValueNode>
+              </LinkedListItems>
+              <IndexListItems>
+                <Size>count</Size>
+                <ValueNode>slots[${'$'}i]</ValueNode>
+              </IndexListItems>
+              <ExpandedItem>extra</ExpandedItem>
+              <TreeItems><HeadPointer>root</HeadPointer></TreeItems>
+            </Expand>
+          </Type>
+          <Type Name="Handle"><DisplayString>{id,x}</DisplayString></Type>
+        </AutoVisualizer>
+    """.trimIndent()
+
+    @Test
+    fun `types are read with their display strings and items`() {
+        val types = parseNatvis(vector)
+        assertEquals(3, types.size)
+        val vec = types[0]
+        assertEquals(listOf("std::vector<*>", "my::Vec<*>"), vec.names)
+        assertEquals(listOf(
+            NatvisDisplayString("empty", "_Mylast == _Myfirst"),
+            NatvisDisplayString("{{ size={_Mylast - _Myfirst} }}"),
+        ), vec.displayStrings)
+        assertEquals(listOf(
+            NatvisItem.Item("[capacity]", "_Myend - _Myfirst"),
+            NatvisItem.ArrayItems("_Mylast - _Myfirst", "_Myfirst"),
+        ), vec.expand)
+        assertEquals(listOf(
+            NatvisItem.LinkedListItems(null, "head", "next", "value", "head != 0"),
+            NatvisItem.IndexListItems("count", "slots[${'$'}i]"),
+            NatvisItem.ExpandedItem("extra"),
+        ), types[1].expand)
+        assertNull(types[2].expand)
+    }
+
+    @Test
+    fun `malformed files are rejected`() {
+        assertThrows(IllegalArgumentException::class.java) { parseNatvis("<AutoVisualizer><Type") }
+        assertThrows(IllegalArgumentException::class.java) { parseNatvis("<Visualizers/>") }
+        assertThrows(IllegalArgumentException::class.java) { parseNatvis("<AutoVisualizer><Type/></AutoVisualizer>") }
+        assertThrows(IllegalArgumentException::class.java) {
+            parseNatvis("<AutoVisualizer><Type Name=\"T\"><Expand><ArrayItems><Size>n</Size></ArrayItems></Expand></Type></AutoVisualizer>")
+        }
+        assertThrows(IllegalArgumentException::class.java) {
+            parseNatvis("<!DOCTYPE x [<!ENTITY e SYSTEM \"file:///etc/passwd\">]><AutoVisualizer>&e;</AutoVisualizer>")
+        }
+    }
+
+    @Test
+    fun `type patterns match template names as LLDB spells them`() {
+        val vec = Regex(natvisTypePattern("std::vector<*>"))
+        assertEquals("int, std::allocator<int>", vec.matchEntire("std::vector<int, std::allocator<int> >")?.groupValues?.get(1))
+        assertFalse(vec.matches("std::vector<int>::iterator"))
+        assertFalse(vec.matches("my::std::vector<int>"))
+        val map = Regex(natvisTypePattern("Map<*, *>"))
+        assertEquals(listOf("int", "float"), map.matchEntire("Map<int,float>")?.groupValues?.drop(1))
+        assertTrue(Regex(natvisTypePattern("Handle")).matches("Handle"))
+        assertFalse(Regex(natvisTypePattern("Handle")).matches("Handles"))
+    }
+
+    @Test
+    fun `directories contribute their natvis files`(@TempDir dir: File) {
+        File(dir, "b.natvis").writeText("")
+        File(dir, "a.natvis").writeText("")
+        File(dir, "notes.txt").writeText("")
+        val single = File(dir, "other.xml").also { it.writeText("") }
+        assertEquals(listOf("a.natvis", "b.natvis", "other.xml"),
+            natvisFiles(listOf(dir.path, single.path)).map { it.name })
+    }
+}
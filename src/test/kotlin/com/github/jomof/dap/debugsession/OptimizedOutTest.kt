@@ -0,0 +1,47 @@
+package com.github.jomof.dap.debugsession
+
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [withAvailability]. Verifies that a variable without a
+ * value shows `<optimized out: reason>` without children, that one with
+ * missing pieces keeps its value with the missing bytes named, and that
+ * recoverable values are left alone.
+ */
+class OptimizedOutTest {
+
+    private val point = mapOf("name" to "p", "value" to "{x:0, y:5}", "type" to "Point", "variablesReference" to 7,
+        "namedVariables" to 2, "memoryReference" to "0x7ffe0000")
+
+    @Test
+    fun `a variable without a value is shown as optimized out`() {
+        val local = LocalVariable(LocalKind.Local, unavailable = true, optimizedOut = "no location at this PC")
+        assertEquals(mapOf("name" to "p", "value" to "<optimized out: no location at this PC>", "type" to "Point",
+            "variablesReference" to 0, "presentationHint" to mapOf("attributes" to listOf("readOnly"))),
+            withAvailability(point, local))
+    }
+
+    @Test
+    fun `missing pieces are named after the value`() {
+        val local = LocalVariable(LocalKind.Local, optimizedOut = "bytes 0..4")
+        assertEquals(point + ("value" to "{x:0, y:5} <partly optimized out: bytes 0..4>"), withAvailability(point, local))
+    }
+
+    @Test
+    fun `recoverable values are left alone`() {
+        assertEquals(point, withAvailability(point, LocalVariable(LocalKind.Local)))
+    }
+
+    @Test
+    fun `the locals split marks optimized-out values`() {
+        val variables = listOf(point, mapOf("name" to "n", "value" to "0", "variablesReference" to 0))
+        val locals = listOf(
+            LocalVariable(LocalKind.Argument, "p", optimizedOut = "bytes 0..4"),
+            LocalVariable(LocalKind.Argument, "n", unavailable = true, optimizedOut = "its register was not saved by the callee"),
+        )
+        assertEquals(listOf("{x:0, y:5} <partly optimized out: bytes 0..4>",
+            "<optimized out: its register was not saved by the callee>"),
+            partitionLocals(variables, locals, LocalScopeKind.Arguments).map { (it as Map<*, *>)["value"] })
+    }
+}
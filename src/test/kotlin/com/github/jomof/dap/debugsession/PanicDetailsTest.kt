@@ -0,0 +1,63 @@
+package com.github.jomof.dap.debugsession
+
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [parsePanicDetails], [formatBacktrace], and
+ * [panicExceptionInfo]. Verifies that a panic read from the thread is
+ * described as the default panic hook prints it, with its backtrace in
+ * `details`, and that the stderr message is used when it could not be
+ * read.
+ */
+class PanicDetailsTest {
+
+    private val json = """
+        {"thread": "main", "message": "boom", "file": "src/main.rs", "line": 3, "column": 5,
+         "backtrace": [
+           {"function": "std::panicking::rust_panic_with_hook"},
+           {"function": "app::main", "file": "/src/app/src/main.rs", "line": 3, "column": 5}]}
+    """.trimIndent()
+
+    @Test
+    fun `panic details are parsed`() {
+        val details = parsePanicDetails(json)
+        assertEquals("boom", details.message)
+        assertEquals("src/main.rs", details.file)
+        assertEquals(3, details.line)
+        assertEquals(5, details.column)
+        assertEquals("main", details.thread)
+        assertEquals(BacktraceFrame("app::main", "/src/app/src/main.rs", 3, 5), details.backtrace[1])
+        assertNull(details.backtrace[0].file)
+    }
+
+    @Test
+    fun `backtrace is formatted as RUST_BACKTRACE prints it`() {
+        assertEquals(
+            "   0: std::panicking::rust_panic_with_hook\n" +
+                "   1: app::main\n" +
+                "             at /src/app/src/main.rs:3:5",
+            formatBacktrace(parsePanicDetails(json).backtrace),
+        )
+    }
+
+    @Test
+    fun `exception info describes the panic with its backtrace`() {
+        val info = panicExceptionInfo(parsePanicDetails(json), "fallback")
+        assertEquals("rust_panic", info["exceptionId"])
+        assertEquals("always", info["breakMode"])
+        assertEquals("thread 'main' panicked at src/main.rs:3:5:\nboom", info["description"])
+        val details = info["details"] as Map<*, *>
+        assertEquals("boom", details["message"])
+        assertTrue((details["stackTrace"] as String).contains("1: app::main"))
+    }
+
+    @Test
+    fun `exception info falls back to the stderr message`() {
+        val fallback = "thread 'main' panicked at src/main.rs:3:5:\nboom"
+        val info = panicExceptionInfo(null, fallback)
+        assertEquals(fallback, info["description"])
+        assertEquals(fallback, (info["details"] as Map<*, *>)["message"])
+        assertNull((info["details"] as Map<*, *>)["stackTrace"])
+    }
+}
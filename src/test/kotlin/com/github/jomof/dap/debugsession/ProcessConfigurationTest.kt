@@ -0,0 +1,91 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.LaunchRequestArguments
+import com.github.jomof.dap.sb.SBError
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+import org.junit.jupiter.api.io.TempDir
+import java.io.File
+
+/**
+ * Unit tests for [checkProcessConfiguration], [launchEnvironment], and
+ * [parseEnvFile]. Verifies that malformed process attributes are reported
+ * by name and that `envFile`, `env`, and `${env:NAME}` combine as
+ * documented.
+ */
+class ProcessConfigurationTest {
+
+    @TempDir
+    lateinit var dir: File
+
+    @Test
+    fun `well-formed process attributes pass`() {
+        checkProcessConfiguration(JSONObject(
+            """{"args":["panic"],"cwd":"/tmp","env":{"RUST_BACKTRACE":"1"},"envFile":"/tmp/.env","stopOnEntry":"main"}"""))
+        checkProcessConfiguration(JSONObject())
+    }
+
+    @Test
+    fun `malformed process attributes are reported by name`() {
+        fun error(json: String) = assertThrows(SBError::class.java) {
+            checkProcessConfiguration(JSONObject(json))
+        }.message
+
+        assertEquals("\"args\" must be an array of strings.", error("""{"args":"stdio"}"""))
+        assertEquals("\"args\" must be an array of strings; element 1 is not a string.",
+            error("""{"args":["a",2]}"""))
+        assertEquals("\"cwd\" must be a path string.", error("""{"cwd":["/tmp"]}"""))
+        assertEquals("\"env\" value of \"N\" must be a string.", error("""{"env":{"N":1}}"""))
+        assertEquals("\"env\" has an invalid variable name \"A=B\".", error("""{"env":{"A=B":"1"}}"""))
+        assertEquals("\"stopOnEntry\" must be true, false, \"entry\", or \"main\".",
+            error("""{"stopOnEntry":"start"}"""))
+    }
+
+    @Test
+    fun `working directory must be an existing directory`() {
+        checkWorkingDirectory(dir.path)
+        val file = File(dir, "file").apply { writeText("") }
+        val notDirectory = assertThrows(SBError::class.java) { checkWorkingDirectory(file.path) }
+        assertTrue(notDirectory.message!!.endsWith("is not a directory."), notDirectory.message)
+        val missing = assertThrows(SBError::class.java) { checkWorkingDirectory("$dir/missing") }
+        assertTrue(missing.message!!.endsWith("does not exist."), missing.message)
+    }
+
+    @Test
+    fun `env file lines are parsed like dotenv`() {
+        val env = parseEnvFile(
+            """
+            # comment
+            export A=1
+            B = two words # note
+            C='${'$'}{env:HOME} \n'
+            D="line\nnext \"q\""
+            """.trimIndent(),
+            ".env",
+        )
+        val expected = mapOf("A" to "1", "B" to "two words", "C" to "${'$'}{env:HOME} \\n", "D" to "line\nnext \"q\"")
+        assertEquals(expected, env)
+        assertEquals(".env:2: expected NAME=VALUE, found \"oops\".",
+            assertThrows(SBError::class.java) { parseEnvFile("A=1\noops", ".env") }.message)
+        assertThrows(SBError::class.java) { parseEnvFile("A=\"open", ".env") }
+    }
+
+    @Test
+    fun `env overrides envFile and expands env references`() {
+        val envFile = File(dir, ".env").apply { writeText("MODE=file\nBASE=${'$'}{env:HOME}/data\n") }
+        val args = LaunchRequestArguments(
+            envFile = envFile.path,
+            env = mapOf("MODE" to "env", "PATHS" to "${'$'}{env:BASE}:${'$'}{env:MISSING}:${'$'}{env:MODE}"),
+        )
+        val env = launchEnvironment(args, hostEnvironment = mapOf("HOME" to "/home/me"))
+        assertEquals(mapOf("MODE" to "env", "BASE" to "/home/me/data", "PATHS" to "/home/me/data::file"), env)
+    }
+
+    @Test
+    fun `an unreadable env file is an error`() {
+        val args = LaunchRequestArguments(envFile = "$dir/missing.env")
+        val message = assertThrows(SBError::class.java) { launchEnvironment(args, emptyMap()) }.message!!
+        assertTrue(message.startsWith("Cannot read envFile"), message)
+    }
+}
@@ -0,0 +1,42 @@
+package com.github.jomof.dap.debugsession
+
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [processNamePatternToRegex]. Verifies that attach-by-name
+ * patterns treat `*` and `?` as wildcards and everything else literally.
+ */
+class ProcessNamePatternTest {
+
+    @Test
+    fun `plain name matches only itself`() {
+        val regex = processNamePatternToRegex("debuggee")
+        assertTrue(regex.matches("debuggee"))
+        assertFalse(regex.matches("debuggee2"))
+        assertFalse(regex.matches("my-debuggee"))
+    }
+
+    @Test
+    fun `star matches any run of characters`() {
+        val regex = processNamePatternToRegex("rust-*")
+        assertTrue(regex.matches("rust-"))
+        assertTrue(regex.matches("rust-debuggee"))
+        assertFalse(regex.matches("debuggee"))
+    }
+
+    @Test
+    fun `question mark matches exactly one character`() {
+        val regex = processNamePatternToRegex("worker?")
+        assertTrue(regex.matches("worker1"))
+        assertFalse(regex.matches("worker"))
+        assertFalse(regex.matches("worker12"))
+    }
+
+    @Test
+    fun `regex metacharacters are literal`() {
+        val regex = processNamePatternToRegex("a.out")
+        assertTrue(regex.matches("a.out"))
+        assertFalse(regex.matches("a_out"))
+    }
+}
@@ -0,0 +1,109 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.LaunchRequestArguments
+import kotlinx.coroutines.runBlocking
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+import org.junit.jupiter.api.io.TempDir
+import java.io.File
+
+/**
+ * Unit tests for the launch program checks. Verifies that missing,
+ * non-executable, wrong-architecture, and stripped programs are reported
+ * as structured errors with what to do, that runnable ones pass, and that
+ * `"requireDebugInfo": false` lets a program without debug info launch.
+ */
+class ProgramChecksTest {
+
+    @TempDir
+    lateinit var dir: File
+
+    private val facts = ProgramFacts(
+        triple = "x86_64-unknown-linux-gnu",
+        platformName = "host",
+        platformTriple = "x86_64-pc-linux-gnu",
+        compileUnits = 12,
+        isElf = true,
+        hasSymbolTable = true,
+        hasDebugLink = false,
+        buildId = "",
+        hasDebugSections = true,
+    )
+
+    @Test
+    fun `program files must exist and be executable`() {
+        val program = File(dir, "app").apply { writeText("") }
+        assertEquals(ProgramProblem.NotExecutable,
+            assertThrows(ProgramError::class.java) { checkProgramFile(program.path, windows = false) }.problem)
+        program.setExecutable(true)
+        checkProgramFile(program.path, windows = false)
+
+        assertEquals(ProgramProblem.NotFound,
+            assertThrows(ProgramError::class.java) { checkProgramFile("$dir/missing", windows = false) }.problem)
+        assertEquals(ProgramProblem.NotAFile,
+            assertThrows(ProgramError::class.java) { checkProgramFile(dir.path, windows = false) }.problem)
+    }
+
+    @Test
+    fun `windows programs may omit the exe suffix`() {
+        File(dir, "app.exe").writeText("")
+        checkProgramFile("$dir/app", windows = true)
+    }
+
+    @Test
+    fun `architectures run on matching and compatible platforms`() {
+        assertTrue(architectureRuns("x86_64-unknown-linux-gnu", "x86_64-pc-linux-gnu"))
+        assertTrue(architectureRuns("i686-pc-linux-gnu", "x86_64-pc-linux-gnu"))
+        assertTrue(architectureRuns("arm64-apple-macosx", "aarch64-apple-darwin"))
+        assertTrue(architectureRuns("x86_64-apple-macosx", "arm64-apple-macosx"))
+        assertTrue(architectureRuns("x86_64-unknown-linux-gnu", ""))
+        assertFalse(architectureRuns("aarch64-unknown-linux-gnu", "x86_64-pc-linux-gnu"))
+        assertFalse(architectureRuns("x86_64-unknown-linux-gnu", "aarch64-unknown-linux-gnu"))
+    }
+
+    @Test
+    fun `a program for another architecture names both`() {
+        assertNull(architectureProblem("/app", facts))
+        val error = architectureProblem("/app", facts.copy(triple = "aarch64-unknown-linux-gnu"))!!
+        assertEquals(ProgramProblem.WrongArchitecture, error.problem)
+        assertTrue(error.message!!.startsWith("Program \"/app\" is built for aarch64, which this x86_64 host cannot run"),
+            error.message)
+    }
+
+    @Test
+    fun `stripped programs and programs without debug info are told apart`() {
+        assertNull(debugInfoProblem("/app", facts, separateDebugInfo = false))
+        val noDebugInfo = facts.copy(compileUnits = 0)
+        assertNull(debugInfoProblem("/app", noDebugInfo, separateDebugInfo = true))
+
+        val withoutDwarf = debugInfoProblem("/app", noDebugInfo, separateDebugInfo = false)!!
+        assertTrue(withoutDwarf.message!!.startsWith("Program \"/app\" has no debug info — rebuild it"))
+        val stripped = debugInfoProblem("/app", noDebugInfo.copy(hasSymbolTable = false), separateDebugInfo = false)!!
+        assertTrue(stripped.message!!.startsWith("Program \"/app\" is stripped — rebuild it with debug info"))
+        assertTrue(stripped.message!!.contains("debug = true"))
+    }
+
+    @Test
+    fun `requireDebugInfo false skips the debug info check`() = runBlocking {
+        val output = """{"triple":"","platformName":"host","platformTriple":"","compileUnits":0,""" +
+            """"elf":true,"symtab":true,"debuglink":false,"buildId":""}"""
+        val debugger = RecordingDebugger(mapOf("script print(_kdap_program_facts())" to output))
+        val args = LaunchRequestArguments(program = "/app")
+        assertEquals(ProgramProblem.NoDebugInfo,
+            assertThrows(ProgramError::class.java) { runBlocking { checkProgramTarget(args, debugger) } }.problem)
+        checkProgramTarget(args.copy(requireDebugInfo = false), debugger)
+    }
+
+    @Test
+    fun `program errors are structured errors shown to the user`() {
+        val response = JSONObject(ProgramError(ProgramProblem.NotFound, "Program \"/app\" does not exist.")
+            .toResponse(requestSeq = 2).toJson())
+        assertFalse(response.getBoolean("success"))
+        assertEquals("Program \"/app\" does not exist.", response.getString("message"))
+        val error = response.getJSONObject("body").getJSONObject("error")
+        assertEquals(ProgramProblem.NotFound.id, error.getInt("id"))
+        assertEquals("Program \"/app\" does not exist.", error.getString("format"))
+        assertTrue(error.getBoolean("showUser"))
+    }
+}
@@ -0,0 +1,51 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.sb.SBCommandInterpreter
+import com.github.jomof.dap.sb.SBCommandReturnObject
+import com.github.jomof.dap.sb.SBDebugger
+import com.github.jomof.dap.sb.SBPlatform
+import com.github.jomof.dap.sb.SBTarget
+
+/**
+ * An [SBDebugger] for unit tests that records the settings and commands
+ * it is given instead of running them. A command's output is its entry
+ * in [outputs], or empty.
+ */
+internal class RecordingDebugger(
+    private val outputs: Map<String, String> = emptyMap(),
+) : SBDebugger, SBCommandInterpreter {
+    val settings = mutableMapOf<String, String>()
+    val commands = mutableListOf<String>()
+    var sysroot: String? = null
+
+    override suspend fun setVariable(name: String, value: String) {
+        settings[name] = value
+    }
+
+    override suspend fun getVariable(name: String): String? = settings[name]
+
+    override suspend fun setCurrentPlatformSdkRoot(sysroot: String) {
+        this.sysroot = sysroot
+    }
+
+    override suspend fun createTarget(
+        program: String?,
+        triple: String?,
+        platformName: String?,
+        addDependent: Boolean,
+    ): SBTarget = throw UnsupportedOperationException("createTarget")
+
+    override suspend fun selectedTarget(): SBTarget = throw UnsupportedOperationException("selectedTarget")
+
+    override suspend fun selectedPlatform(): SBPlatform = throw UnsupportedOperationException("selectedPlatform")
+
+    override suspend fun commandInterpreter(): SBCommandInterpreter = this
+
+    override suspend fun handleCommand(command: String): String {
+        commands += command
+        return outputs[command].orEmpty()
+    }
+
+    override suspend fun handleCommandWithResult(command: String): SBCommandReturnObject =
+        SBCommandReturnObject(succeeded = true, output = handleCommand(command), error = "")
+}
@@ -0,0 +1,74 @@
+package com.github.jomof.dap.debugsession
+
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [valueIdentity] and [DebugSession.guardExpansion].
+ * Verifies that a value met again below itself is shown as already
+ * shown, that expansion stops at the configured depth, and that the
+ * ancestries are forgotten with the stop.
+ */
+class RecursiveValuesTest {
+
+    private fun node(name: String, address: String?, reference: Int, type: String = "alloc::rc::Rc<Node>") =
+        buildMap<String, Any?> {
+            put("name", name)
+            put("value", "(strong=2, weak=0) {...}")
+            put("type", type)
+            put("variablesReference", reference)
+            put("namedVariables", 2)
+            if (address != null) put("memoryReference", address)
+        }
+
+    private fun reference(variable: Any?): Int = ((variable as Map<*, *>)["variablesReference"] as Number).toInt()
+
+    @Test
+    fun `identity is the type at the address`() {
+        assertEquals("alloc::rc::Rc<Node>@0x1000", valueIdentity(node("a", "0x1000", 1)))
+        assertNull(valueIdentity(node("a", null, 1)))
+    }
+
+    @Test
+    fun `an Rc cycle is shown once`() {
+        val session = DebugSession()
+        session.guardExpansion(listOf(node("a", "0x1000", 10)), parentReference = 1)
+        session.guardExpansion(listOf(node("next", "0x2000", 11)), parentReference = 10)
+        val again = session.guardExpansion(listOf(node("next", "0x1000", 12)), parentReference = 11).single() as Map<*, *>
+        assertEquals(ALREADY_SHOWN, again["value"])
+        assertEquals(0, reference(again))
+        assertFalse(again.containsKey("namedVariables"))
+        assertNull(session.valueAncestries[12])
+    }
+
+    @Test
+    fun `the same address under another type is not a cycle`() {
+        val session = DebugSession()
+        session.guardExpansion(listOf(node("outer", "0x1000", 10, type = "Outer")), parentReference = 1)
+        val field = session.guardExpansion(listOf(node("inner", "0x1000", 11, type = "Inner")), parentReference = 10).single()
+        assertEquals(11, reference(field))
+        assertEquals(ValueAncestry(2, setOf("Outer@0x1000", "Inner@0x1000")), session.valueAncestries[11])
+    }
+
+    @Test
+    fun `expansion stops at the configured depth`() {
+        val session = DebugSession().apply { maxExpansionDepth = 2 }
+        session.guardExpansion(listOf(node("head", null, 10)), parentReference = 1)
+        val limited = session.guardExpansion(listOf(node("next", null, 11)), parentReference = 10).single() as Map<*, *>
+        assertEquals(0, reference(limited))
+        assertEquals("(strong=2, weak=0) {...}", limited["value"])
+
+        val unlimited = DebugSession().apply { maxExpansionDepth = 0 }
+        unlimited.valueAncestries[10] = ValueAncestry(depth = 1_000)
+        assertEquals(11, reference(unlimited.guardExpansion(listOf(node("next", null, 11)), parentReference = 10).single()))
+    }
+
+    @Test
+    fun `ancestries are forgotten with the stop`() {
+        val session = DebugSession()
+        session.guardExpansion(listOf(node("a", "0x1000", 10), mapOf("name" to "n", "value" to "1", "variablesReference" to 0)), 1)
+        assertEquals(setOf(10), session.valueAncestries.keys)
+        session.forgetVariableChunks()
+        assertTrue(session.valueAncestries.isEmpty())
+    }
+}
@@ -0,0 +1,60 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.messages.ScopesRequest
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for the Registers scope helpers. Verifies that lldb-dap's
+ * Registers scope is replaced by KDAP's for the requested frame, with
+ * one reference per frame until the next stop.
+ */
+class RegistersTest {
+
+    private val session = DebugSession()
+
+    private fun scopes(seq: Int, frameId: Int): List<*> {
+        session.onScopesRequest(ScopesRequest(seq = seq, frameId = frameId))
+        val response = DapResponse(
+            seq = 1, requestSeq = seq, command = "scopes", success = true,
+            body = mapOf("scopes" to listOf(
+                mapOf("name" to "Locals", "variablesReference" to 1),
+                mapOf("name" to "Registers", "variablesReference" to 3, "namedVariables" to 4, "expensive" to true),
+            )),
+        )
+        return session.withRegistersScope(response).body["scopes"] as List<*>
+    }
+
+    @Test
+    fun `registers scope is replaced for the frame`() {
+        val scopes = scopes(seq = 5, frameId = 7)
+        assertEquals(mapOf("name" to "Locals", "variablesReference" to 1), scopes[0])
+        assertEquals(
+            mapOf("name" to "Registers", "variablesReference" to FIRST_REGISTERS_REFERENCE, "expensive" to true),
+            scopes[1])
+        assertEquals(RegisterNode(7), session.registerNodes[FIRST_REGISTERS_REFERENCE])
+    }
+
+    @Test
+    fun `a frame keeps its reference until the next stop`() {
+        val first = scopes(seq = 5, frameId = 7)
+        assertEquals(first, scopes(seq = 6, frameId = 7))
+        assertNotEquals(first, scopes(seq = 7, frameId = 8))
+        session.forgetRegisterNodes()
+        assertTrue(session.registerNodes.isEmpty())
+    }
+
+    @Test
+    fun `lane sets are nodes of their own`() {
+        val register = RegisterNode(7, "FP/SIMD", "xmm0")
+        assertNotEquals(register, register.copy(lanes = "f32"))
+        assertEquals(RegisterNode(7, "FP/SIMD", "xmm0", "u8"), register.copy(lanes = "u8"))
+    }
+
+    @Test
+    fun `register references do not collide with statics values`() {
+        assertTrue(FIRST_REGISTERS_REFERENCE > FIRST_STATICS_REFERENCE)
+        assertTrue(FIRST_REGISTERS_REFERENCE < FIRST_STATIC_VALUE_REFERENCE)
+    }
+}
@@ -0,0 +1,50 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.messages.ScopesRequest
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for the return value helpers. Verifies that the Locals
+ * scope of a thread's top frame is recognized from `scopes` traffic and
+ * that the return value replaces lldb-dap's own.
+ */
+class ReturnValuesTest {
+
+    private val session = DebugSession()
+
+    private fun scopes(seq: Int, frameId: Int, localsReference: Int) {
+        session.onScopesRequest(ScopesRequest(seq = seq, frameId = frameId))
+        session.onScopesResponse(DapResponse(
+            seq = 1, requestSeq = seq, command = "scopes", success = true,
+            body = mapOf("scopes" to listOf(
+                mapOf("name" to "Locals", "presentationHint" to "locals", "variablesReference" to localsReference),
+                mapOf("name" to "Globals", "variablesReference" to localsReference + 1),
+            )),
+        ))
+    }
+
+    @Test
+    fun `top frame locals are mapped to their thread`() {
+        scopes(seq = 5, frameId = 3 shl FRAME_INDEX_BITS, localsReference = 1)
+        assertEquals(mapOf(1 to 3), session.returnValueScopes)
+        assertTrue(session.pendingScopes.isEmpty())
+    }
+
+    @Test
+    fun `locals of other frames are not`() {
+        scopes(seq = 5, frameId = 3 shl FRAME_INDEX_BITS, localsReference = 1)
+        scopes(seq = 6, frameId = (3 shl FRAME_INDEX_BITS) + 2, localsReference = 1)
+        assertTrue(session.returnValueScopes.isEmpty())
+        scopes(seq = 7, frameId = FIRST_TASK_FRAME_ID, localsReference = 9)
+        assertTrue(session.returnValueScopes.isEmpty())
+    }
+
+    @Test
+    fun `return value comes first and replaces lldb-dap's`() {
+        val entry = mapOf("name" to "(return) app::area", "value" to "12")
+        val variables = listOf(mapOf("name" to LLDB_DAP_RETURN_VALUE, "value" to "12"), mapOf("name" to "w", "value" to "3"))
+        assertEquals(listOf(entry, variables[1]), withReturnValue(variables, entry))
+    }
+}
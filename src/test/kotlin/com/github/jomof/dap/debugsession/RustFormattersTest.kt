@@ -0,0 +1,62 @@
+package com.github.jomof.dap.debugsession
+
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [RUST_FORMATTERS] and [rustFormatterCommands]. Verifies
+ * that DWARF type names select the intended provider, that other enums
+ * are matched by recognizer, and that the category is enabled once
+ * everything is registered.
+ */
+class RustFormattersTest {
+
+    private fun summaryFor(typeName: String): String? =
+        RUST_FORMATTERS.filter { !it.recognizer }
+            .singleOrNull { Regex(it.pattern).containsMatchIn(typeName) }?.summary
+
+    @Test
+    fun `std collection type names select their formatter`() {
+        assertEquals("_kdap_string_summary", summaryFor("alloc::string::String"))
+        assertEquals("_kdap_str_summary", summaryFor("&str"))
+        assertEquals("_kdap_vec_summary", summaryFor("alloc::vec::Vec<i32, alloc::alloc::Global>"))
+        assertEquals("_kdap_slice_summary", summaryFor("&[u8]"))
+        assertEquals("_kdap_dyn_summary", summaryFor("&dyn core::fmt::Debug"))
+        assertEquals("_kdap_dyn_summary", summaryFor("&mut dyn core::ops::function::FnMut<(), Output=()>"))
+        assertEquals("_kdap_hashmap_summary",
+            summaryFor("std::collections::hash::map::HashMap<&str, i32, std::hash::random::RandomState>"))
+        assertEquals("_kdap_btreemap_summary",
+            summaryFor("alloc::collections::btree::map::BTreeMap<i32, alloc::string::String, alloc::alloc::Global>"))
+        assertEquals("_kdap_enum_summary", summaryFor("core::option::Option<i32>"))
+        assertEquals("_kdap_enum_summary", summaryFor("core::result::Result<i32, alloc::string::String>"))
+        assertEquals("_kdap_box_summary", summaryFor("alloc::boxed::Box<i32, alloc::alloc::Global>"))
+        assertEquals("_kdap_box_summary", summaryFor("alloc::boxed::Box<dyn core::fmt::Debug, alloc::alloc::Global>"))
+        assertEquals("_kdap_rc_summary", summaryFor("alloc::rc::Rc<i32, alloc::alloc::Global>"))
+        assertEquals("_kdap_rc_summary", summaryFor("alloc::sync::Arc<i32, alloc::alloc::Global>"))
+        assertEquals("_kdap_refcell_summary", summaryFor("core::cell::RefCell<i32>"))
+        assertEquals("_kdap_mutex_summary", summaryFor("std::sync::poison::mutex::Mutex<i32>"))
+    }
+
+    @Test
+    fun `related types are not matched`() {
+        assertNull(summaryFor("alloc::vec::into_iter::IntoIter<i32, alloc::alloc::Global>"))
+        assertNull(summaryFor("std::sync::poison::mutex::MutexGuard<i32>"))
+        assertNull(summaryFor("core::cell::Cell<i32>"))
+        assertNull(summaryFor("&i32"))
+        assertNull(summaryFor("&dynamic::Config"))
+    }
+
+    @Test
+    fun `commands register summaries and providers then enable the category`() {
+        val commands = rustFormatterCommands()
+        assertTrue(commands.contains(
+            "type summary add -w kdap-rust -F _kdap_vec_summary -x '^alloc::vec::Vec<.+>$'"))
+        assertTrue(commands.contains(
+            "type synthetic add -w kdap-rust -l _KdapVecProvider -x '^alloc::vec::Vec<.+>$'"))
+        assertTrue(commands.contains(
+            "type synthetic add -w kdap-rust -l _KdapStringProvider -x '^alloc::string::String$'"))
+        assertTrue(commands.contains(
+            "type summary add -w kdap-rust -F _kdap_enum_summary --recognizer-function _kdap_is_rust_enum"))
+        assertEquals("type category enable kdap-rust", commands.last())
+    }
+}
@@ -0,0 +1,157 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.sb.SBCommandReturnObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [KdapScript]. Verifies that `kdap.init` scripts register
+ * summaries, stop hooks, and commands, that summaries are matched by
+ * type, that the API reaches the host and reports its failures as Lua
+ * errors, and that scripts get no library that reaches files or
+ * processes.
+ */
+class ScriptingTest {
+
+    private class FakeHost : ScriptHost {
+        val printed = StringBuilder()
+        val commands = mutableListOf<String>()
+        val children = mapOf(
+            7 to listOf(
+                mapOf("name" to "x", "value" to "1", "type" to "i32", "variablesReference" to 0),
+                mapOf("name" to "y", "value" to "2", "type" to "i32", "variablesReference" to 0),
+            ),
+        )
+
+        override fun evaluate(expression: String, frameId: Int?): Map<String, Any?> =
+            if (expression == "bad") throw ScriptError("no such variable") else mapOf("result" to "$expression@$frameId")
+        override fun variables(reference: Int) = children[reference] ?: emptyList()
+        override fun readMemory(address: Long, count: Int) = ByteArray(count) { (address + it).toByte() }
+        override fun lldb(command: String): SBCommandReturnObject {
+            commands += command
+            return SBCommandReturnObject(succeeded = command != "oops", output = "ran $command\n", error = "bad command\n")
+        }
+        override fun breakAt(file: String, line: Int) = line
+        override fun breakOn(function: String) = function.length
+        override fun deleteBreakpoint(id: Int) = id == 3
+        override fun print(text: String) {
+            printed.append(text)
+        }
+    }
+
+    private val point = mapOf("name" to "p", "value" to "{...}", "type" to "geo::Point", "variablesReference" to 7)
+
+    @Test
+    fun `summaries are matched by type and may read children`() {
+        val script = KdapScript(FakeHost())
+        script.load("""
+            kdap.summary("^geo::Point$", function(v)
+              return "(" .. kdap.child(v, "x").value .. ", " .. kdap.child(v, "y").value .. ")"
+            end)
+            kdap.summary("Point", function(v) return "second" end)
+        """.trimIndent(), "kdap.init")
+        assertTrue(script.hasSummaries)
+        assertEquals("(1, 2)", script.summarize(point))
+        assertEquals("second", script.summarize(point + ("type" to "geo::Point3")))
+        assertNull(script.summarize(point + ("type" to "i32")))
+        assertNull(script.summarize(mapOf("name" to "untyped")))
+    }
+
+    @Test
+    fun `a summary that returns nothing leaves the value alone`() {
+        val script = KdapScript(FakeHost())
+        script.load("kdap.summary('Point', function(v) end)", "kdap.init")
+        assertNull(script.summarize(point))
+    }
+
+    @Test
+    fun `a failing summary raises a script error`() {
+        val script = KdapScript(FakeHost())
+        script.load("kdap.summary('Point', function(v) return kdap.child(v, 'z').value end)", "kdap.init")
+        assertThrows(ScriptError::class.java) { script.summarize(point) }
+    }
+
+    @Test
+    fun `stop hooks get the event`() {
+        val host = FakeHost()
+        val script = KdapScript(host)
+        assertFalse(script.hasStopHooks)
+        script.load("kdap.on_stop(function(e) kdap.print(e.reason .. ' ' .. e.threadId) end)", "kdap.init")
+        assertTrue(script.hasStopHooks)
+        script.onStop(mapOf("reason" to "breakpoint", "threadId" to 1))
+        assertEquals("breakpoint 1", host.printed.toString())
+    }
+
+    @Test
+    fun `commands get their arguments and return their output`() {
+        val script = KdapScript(FakeHost())
+        script.load("""
+            kdap.command("echo", function(args) return "echo: " .. args end)
+            kdap.command("quiet", function(args) end)
+        """.trimIndent(), "kdap.init")
+        assertTrue(script.hasCommand("echo"))
+        assertFalse(script.hasCommand("frame"))
+        assertEquals("echo: a b", script.runCommand("echo", "a b"))
+        assertNull(script.runCommand("quiet", ""))
+        assertThrows(ScriptError::class.java) { script.runCommand("frame", "") }
+    }
+
+    @Test
+    fun `the API reaches the host`() {
+        val host = FakeHost()
+        val script = KdapScript(host)
+        script.load("""
+            kdap.command("check", function()
+              local bytes = kdap.read_memory(16, 3)
+              return table.concat({
+                kdap.evaluate("n").result, kdap.evaluate("n", 5).result,
+                #kdap.variables(7), bytes:byte(1), bytes:byte(3),
+                kdap.lldb("frame info"), kdap.break_at("main.rs", 12), kdap.break_on("main"),
+                tostring(kdap.delete_breakpoint(3)), tostring(kdap.delete_breakpoint(4)),
+              }, ",")
+            end)
+        """.trimIndent(), "kdap.init")
+        assertEquals("n@null,n@5,2,16,18,ran frame info\n,12,4,true,false", script.runCommand("check", ""))
+        assertEquals(listOf("frame info"), host.commands)
+    }
+
+    @Test
+    fun `host failures are Lua errors a script can catch`() {
+        val script = KdapScript(FakeHost())
+        script.load("""
+            kdap.command("try", function()
+              local ok1, err1 = pcall(kdap.evaluate, "bad")
+              local ok2, err2 = pcall(kdap.lldb, "oops")
+              return tostring(ok1) .. " " .. err1 .. "; " .. tostring(ok2) .. " " .. err2
+            end)
+        """.trimIndent(), "kdap.init")
+        val result = script.runCommand("try", "")!!
+        assertTrue(result.startsWith("false "), result)
+        assertTrue("no such variable" in result && "; false " in result && "bad command" in result, result)
+    }
+
+    @Test
+    fun `print goes to the host`() {
+        val host = FakeHost()
+        KdapScript(host).load("print('a', 1)", "kdap.init")
+        assertEquals("a\t1\n", host.printed.toString())
+    }
+
+    @Test
+    fun `scripts have the pure libraries but not io, os, or package`() {
+        val host = FakeHost()
+        KdapScript(host).load("""
+            print(string.upper("a"), table.concat({1, 2}, ","), math.max(1, 2), bit32.band(6, 3))
+            print(io, os, package, require, dofile, loadfile, luajava)
+        """.trimIndent(), "kdap.init")
+        assertEquals("A\t1,2\t2\t2\nnil\tnil\tnil\tnil\tnil\tnil\tnil\n", host.printed.toString())
+    }
+
+    @Test
+    fun `a script that fails to load raises a script error`() {
+        val script = KdapScript(FakeHost())
+        assertThrows(ScriptError::class.java) { script.load("kdap.summary(", "kdap.init") }
+        assertThrows(ScriptError::class.java) { script.load("error('no')", "kdap.init") }
+        assertThrows(ScriptError::class.java) { script.load("kdap.summary('[', function() end)", "kdap.init") }
+    }
+}
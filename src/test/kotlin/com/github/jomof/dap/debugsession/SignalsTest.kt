@@ -0,0 +1,45 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.SignalDisposition
+import com.github.jomof.dap.sb.SBError
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for the signal disposition table. Verifies that `signals`
+ * values parse to dispositions, that invalid ones are reported by signal
+ * name, and that LLDB's settings read back as the disposition they came
+ * from.
+ */
+class SignalsTest {
+
+    @Test
+    fun `signal dispositions parse by name`() {
+        val expected = mapOf(
+            "SIGUSR1" to SignalDisposition.Pass,
+            "SIGINT" to SignalDisposition.Stop,
+            "SIGPIPE" to SignalDisposition.Ignore,
+        )
+        assertEquals(expected, parseSignalDispositions(mapOf("SIGUSR1" to "pass", "SIGINT" to "stop", "SIGPIPE" to "ignore")))
+        assertEquals(emptyMap<String, SignalDisposition>(), parseSignalDispositions(emptyMap()))
+    }
+
+    @Test
+    fun `invalid dispositions are reported by signal name`() {
+        val error = assertThrows(SBError::class.java) { parseSignalDispositions(mapOf("SIGUSR2" to "nostop")) }
+        assertEquals("\"signals\" value of \"SIGUSR2\" must be \"pass\", \"stop\", or \"ignore\".", error.message)
+        val launch = assertThrows(SBError::class.java) {
+            checkProcessConfiguration(JSONObject("""{"signals":["SIGUSR1"]}"""))
+        }
+        assertEquals("\"signals\" must be an object mapping signal names to dispositions.", launch.message)
+    }
+
+    @Test
+    fun `lldb settings read back as dispositions`() {
+        assertEquals(SignalDisposition.Stop, signalDisposition(stop = true, suppress = false))
+        assertEquals(SignalDisposition.Stop, signalDisposition(stop = true, suppress = true))
+        assertEquals(SignalDisposition.Pass, signalDisposition(stop = false, suppress = false))
+        assertEquals(SignalDisposition.Ignore, signalDisposition(stop = false, suppress = true))
+    }
+}
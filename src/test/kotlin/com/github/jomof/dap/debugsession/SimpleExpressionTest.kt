@@ -0,0 +1,166 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.SourceBreakpoint
+import com.github.jomof.dap.debugsession.SimpleExpr.*
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [parseSimpleExpression], [prepareExpression],
+ * [splitFormatSuffix], [splitArraySuffix], [classifyReplInput],
+ * [prepareAssignment], and [toPython]. Verifies Rust-like precedence and
+ * postfix syntax, prefix, format, and array suffix handling, debug
+ * console input routing, assignment preparation, and the Python each
+ * node compiles to.
+ */
+class SimpleExpressionTest {
+
+    @Test
+    fun `arithmetic binds tighter than comparison and logic`() {
+        assertEquals(
+            Binary("&&",
+                Binary(">", Binary("+", Variable("a"), Binary("*", IntLiteral(2), Variable("b"))), IntLiteral(10)),
+                Unary("!", Variable("done"))),
+            parseSimpleExpression("a + 2 * b > 10 && !done"))
+    }
+
+    @Test
+    fun `postfix field, tuple, index, and deref parse`() {
+        assertEquals(
+            Deref(Index(Field(Field(Variable("self"), "items"), "0"), Binary("-", Variable("i"), IntLiteral(1)))),
+            parseSimpleExpression("*self.items.0[i - 1]"))
+        assertEquals(Field(Field(Variable("t"), "0"), "1"), parseSimpleExpression("t.0.1"))
+        assertEquals(Variable("crate::COUNTER"), parseSimpleExpression("crate::COUNTER"))
+    }
+
+    @Test
+    fun `literals parse with separators, hex, and suffixes`() {
+        assertEquals(IntLiteral(1_000_000), parseSimpleExpression("1_000_000"))
+        assertEquals(IntLiteral(255), parseSimpleExpression("0xffu8"))
+        assertEquals(FloatLiteral(2.5), parseSimpleExpression("2.5f32"))
+        assertEquals(BoolLiteral(false), parseSimpleExpression("false"))
+    }
+
+    @Test
+    fun `invalid syntax is rejected`() {
+        assertThrows(ExpressionError::class.java) { parseSimpleExpression("a <") }
+        assertThrows(ExpressionError::class.java) { parseSimpleExpression("a < b < c") }
+        assertThrows(ExpressionError::class.java) { parseSimpleExpression("foo(1") }
+        assertThrows(ExpressionError::class.java) { parseSimpleExpression("(f)(1)") }
+        assertThrows(ExpressionError::class.java) { parseSimpleExpression("x as u8") }
+    }
+
+    @Test
+    fun `prefixes select the evaluator`() {
+        assertEquals(PreparedExpression.Native("sizeof(int)"), prepareExpression("/nat sizeof(int)"))
+        assertEquals(PreparedExpression.Native("(int)x"), prepareExpression("(int)x"))
+        assertEquals(PreparedExpression.Simple(Variable("x")), prepareExpression("/se x"))
+        assertThrows(ExpressionError::class.java) { prepareExpression("/se (int)x") }
+    }
+
+    @Test
+    fun `debug console input is classified`() {
+        assertEquals(ReplInput.MetaCommand("memory read &x"), classifyReplInput("`memory read &x"))
+        assertEquals(ReplInput.Simple(Binary("+", Variable("x"), IntLiteral(1))), classifyReplInput("?x + 1"))
+        assertEquals(ReplInput.NativeExpression("(int)x"), classifyReplInput("?(int)x"))
+        assertEquals(ReplInput.Simple(Assign(Field(Variable("p"), "x"), IntLiteral(5))), classifyReplInput("p.x = 5"))
+        assertInstanceOf(ReplInput.Invalid::class.java, classifyReplInput("?/se ("))
+        for (command in listOf("version", "p -x", "x == 5", "settings set a b")) {
+            assertEquals(ReplInput.Passthrough, classifyReplInput(command), command)
+        }
+    }
+
+    @Test
+    fun `format suffixes are split off`() {
+        assertEquals("ptr" to ValueFormat.Hex, splitFormatSuffix("ptr,x"))
+        assertEquals("self.flags" to ValueFormat.Binary, splitFormatSuffix("self.flags ,b"))
+        assertEquals("/nat (int)c" to ValueFormat.Char, splitFormatSuffix("/nat (int)c,c"))
+        assertEquals("packet.payload" to ValueFormat.Text, splitFormatSuffix("packet.payload,s"))
+        for (expression in listOf("x", "max(a,b)", "a, bb")) {
+            assertEquals(expression to null, splitFormatSuffix(expression), expression)
+        }
+        assertEquals(ReplInput.Simple(Variable("n"), ValueFormat.Decimal), classifyReplInput("?n,d"))
+        assertEquals(ReplInput.NativeExpression("(int)x", ValueFormat.Hex), classifyReplInput("?(int)x,x"))
+    }
+
+    @Test
+    fun `array suffixes are split off`() {
+        assertEquals("buf" to IntLiteral(16), splitArraySuffix("buf,[16]"))
+        assertEquals("self.data" to Field(Variable("self"), "len"), splitArraySuffix("self.data @ self.len"))
+        assertEquals("/nat (char*)p" to IntLiteral(4), splitArraySuffix("/nat (char*)p, [4]"))
+        assertEquals("data,[8]" to ValueFormat.Hex, splitFormatSuffix("data,[8],x"))
+        for (expression in listOf("x", "a@", "@x", "[NSString string:@\"%d\"]", "a,[1 +]")) {
+            assertEquals(expression to null, splitArraySuffix(expression), expression)
+        }
+    }
+
+    @Test
+    fun `assignment is only accepted as console input`() {
+        assertThrows(ExpressionError::class.java) { parseSimpleExpression("x = 1") }
+        assertEquals(
+            "_kdap_se_assign(_kdap_se_var(frame, 'x'), lambda: 1)",
+            (classifyReplInput("x = 1") as ReplInput.Simple).expr.toPython("frame"))
+    }
+
+    @Test
+    fun `assigning a bare name passes it as a possible variant`() {
+        assertEquals(
+            "_kdap_se_assign(_kdap_se_var(frame, 'o'), lambda: _kdap_se_var(frame, 'Option::None'), 'None')",
+            (prepareAssignment("o", "Option::None") as PreparedExpression.Simple).expr.toPython("frame"))
+    }
+
+    @Test
+    fun `assignments are simple only when both sides are`() {
+        assertEquals(
+            PreparedExpression.Simple(Assign(Deref(Variable("p")), IntLiteral(0x10))),
+            prepareAssignment("*p", "0x10"))
+        assertEquals(PreparedExpression.Native("x = (int)y"), prepareAssignment("x", "(int)y"))
+        assertEquals(PreparedExpression.Native("v->len = 2"), prepareAssignment("/nat v->len", "2"))
+        assertThrows(ExpressionError::class.java) { prepareAssignment("a + b", "1") }
+    }
+
+    @Test
+    fun `function and method calls parse`() {
+        assertEquals(Call("crate::area", listOf(IntLiteral(2), Field(Variable("p"), "w"))), parseSimpleExpression("crate::area(2, p.w)"))
+        assertEquals(
+            Binary("+", MethodCall(Field(Variable("self"), "items"), "len", emptyList()), IntLiteral(1)),
+            parseSimpleExpression("self.items.len() + 1"))
+        assertEquals(Index(MethodCall(Variable("v"), "as_ptr", emptyList()), IntLiteral(0)), parseSimpleExpression("v.as_ptr()[0]"))
+        assertEquals(PreparedExpression.Simple(Call("count", emptyList())), prepareExpression("count()"))
+    }
+
+    @Test
+    fun `calls compile to the call helper`() {
+        assertEquals(
+            "_kdap_se_call(frame, _kdap_se_var(frame, 'v'), 'get', [1, _kdap_se_var(frame, 'i')])",
+            parseSimpleExpression("v.get(1, i)").toPython("frame"))
+        assertEquals("_kdap_se_call(frame, None, 'f', [])", parseSimpleExpression("f()").toPython("frame"))
+    }
+
+    @Test
+    fun `expressions compile to SB helper calls`() {
+        assertEquals(
+            "_kdap_se_binary('==', _kdap_se_field(_kdap_se_var(frame, 'p'), 'x'), 3)",
+            parseSimpleExpression("p.x == 3").toPython("frame"))
+        assertEquals(
+            "(_kdap_se_truthy(_kdap_se_var(frame, 'a')) or _kdap_se_truthy(True))",
+            parseSimpleExpression("a || true").toPython("frame"))
+    }
+
+    @Test
+    fun `se breakpoint conditions run in the KDAP callback`() {
+        val plan = planSourceBreakpoint(SourceBreakpoint(line = 7, condition = "/se v[0] > 1"))
+        assertEquals("v[0] > 1", plan.simpleCondition)
+        assertNull(plan.nativeCondition)
+        val body = breakpointCallbackBody("/src/main.rs:7", plan)
+        assertTrue(body.contains("_kdap_se_truthy(_kdap_se_binary('>', _kdap_se_index(_kdap_se_var(frame, 'v'), 0), 1))"))
+    }
+
+    @Test
+    fun `invalid se conditions are reported and ignored`() {
+        val plan = planSourceBreakpoint(SourceBreakpoint(line = 7, condition = "/se v >"))
+        assertNull(plan.simpleCondition)
+        assertNotNull(plan.invalidCondition)
+        assertFalse(plan.isScripted)
+    }
+}
@@ -0,0 +1,56 @@
+package com.github.jomof.dap.debugsession
+
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+import org.junit.jupiter.api.io.TempDir
+import java.io.File
+import java.nio.file.Files
+
+/**
+ * Unit tests for [sourceServerUrls], [rustcCommit], [rustSourceFile],
+ * and [missingSourceMessage]. Verifies that source servers default to the
+ * debuginfod source endpoints, and that standard library paths are found
+ * only in the rust-src of the toolchain of their commit.
+ */
+class SourcesTest {
+
+    @TempDir
+    lateinit var dir: File
+
+    private val commit = "90b35a6239c3d8bdabc530a6a0816f7ff89a0aaf"
+
+    @Test
+    fun `source servers come from the configuration, else debuginfod`() {
+        assertEquals(listOf("https://src.example/{path}"),
+            sourceServerUrls(listOf("https://src.example/{path}"), listOf("https://d.example")))
+        assertEquals(listOf("https://d.example/buildid/{buildId}/source{path}"),
+            sourceServerUrls(null, listOf("https://d.example/")))
+    }
+
+    @Test
+    fun `commit is read from rustc -vV`() {
+        val output = "rustc 1.88.0 (6b00bc388 2025-06-23)\nbinary: rustc\ncommit-hash: $commit\nhost: x86_64-unknown-linux-gnu\n"
+        assertEquals(commit, rustcCommit(output))
+        assertNull(rustcCommit("rustc 1.88.0\ncommit-hash: unknown\n"))
+    }
+
+    @Test
+    fun `standard library paths are found in the rust-src of their commit`() {
+        val library = dir.toPath().resolve("library")
+        Files.createDirectories(library.resolve("core/src"))
+        Files.writeString(library.resolve("core/src/panicking.rs"), "// panicking")
+        val directories = mapOf(commit to library)
+
+        assertEquals(library.resolve("core/src/panicking.rs"),
+            rustSourceFile("/rustc/$commit/library/core/src/panicking.rs", directories))
+        assertNull(rustSourceFile("/rustc/${"0".repeat(40)}/library/core/src/panicking.rs", directories))
+        assertNull(rustSourceFile("/rustc/$commit/library/core/src/missing.rs", directories))
+        assertNull(rustSourceFile("/work/src/main.rs", directories))
+    }
+
+    @Test
+    fun `missing standard library sources say which toolchain has them`() {
+        assertTrue(missingSourceMessage("/rustc/$commit/library/std/src/rt.rs").contains("commit 90b35a623"))
+        assertTrue(missingSourceMessage("/build/gen/out.rs").startsWith("Could not find /build/gen/out.rs"))
+    }
+}
@@ -0,0 +1,104 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.InternalFrames
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [markInlinedFrames], [isInternalFrame], and
+ * [presentInternalFrames]. Verifies that inlined frames get a subtle
+ * presentation hint and a name suffix, that runtime internals are told
+ * from user code, and that they are de-emphasized or collapsed as set,
+ * except for the top frame at a pause.
+ */
+class StackFramesTest {
+
+    private val inlined = mapOf("id" to 524288, "name" to "core::iter::Iterator::fold", "line" to 2585)
+    private val concrete = mapOf("id" to 524289, "name" to "app::sum", "line" to 12, "presentationHint" to "normal")
+
+    @Test
+    fun `inlined frames are marked`() {
+        val marked = markInlinedFrames(listOf(inlined, concrete), setOf(524288))
+        assertEquals(inlined + mapOf("name" to "core::iter::Iterator::fold [inlined]", "presentationHint" to "subtle"),
+            marked[0])
+        assertEquals(concrete, marked[1])
+    }
+
+    private fun frame(id: Int, name: String, path: String? = null): Map<String, Any?> =
+        mapOf("id" to id, "name" to name) + (path?.let { mapOf("source" to mapOf("path" to it)) } ?: emptyMap())
+
+    @Test
+    fun `runtime internals are recognized by name and source`() {
+        val std = "/rustc/90b35a6239c3d8bdabc530a6a0816f7ff89a0aaf/library/std/src/panicking.rs"
+        assertTrue(isInternalFrame(frame(1, "std::panicking::begin_panic_handler::{closure#0}", std)))
+        assertTrue(isInternalFrame(frame(1, "core::panicking::panic_fmt")))
+        assertTrue(isInternalFrame(frame(1, "<alloc::boxed::Box<F> as core::ops::function::Fn<A>>::call")))
+        assertTrue(isInternalFrame(frame(1, "__rust_start_panic")))
+        assertTrue(isInternalFrame(frame(1, "__ubsan_handle_add_overflow")))
+        assertTrue(isInternalFrame(frame(1, "__libc_start_call_main")))
+        assertTrue(isInternalFrame(frame(1, "_start")))
+        assertTrue(isInternalFrame(frame(1, "__cxa_throw")))
+        assertTrue(isInternalFrame(frame(1, "__cxxabiv1::__terminate(void (*)())")))
+        assertTrue(isInternalFrame(frame(1, "std::vector<int, std::allocator<int> >::push_back(int const&)")))
+        assertTrue(isInternalFrame(frame(1, "__gnu_cxx::new_allocator<int>::allocate(unsigned long, void const*)")))
+        assertTrue(isInternalFrame(frame(1, "operator new", "/usr/include/c++/13/bits/new_allocator.h")))
+        assertFalse(isInternalFrame(frame(1, "Diamond::Diamond()", "/work/debuggee/cpp/types.cpp")))
+        assertTrue(isInternalFrame(frame(1, "kotlin.collections.filter@kotlin.collections.Iterable<0:0>(Function1<0:0,Boolean>)")))
+        assertTrue(isInternalFrame(frame(1, "Konan_run_start")))
+        assertTrue(isInternalFrame(frame(1, "kotlin::mm::ThreadData::ThreadData()")))
+        assertTrue(isInternalFrame(frame(1, "kdap.debuggee.Circle.${'$'}<bridge-DNN>area()")))
+        assertFalse(isInternalFrame(frame(1, "kdap.debuggee.Circle.area()")))
+        assertFalse(isInternalFrame(frame(1, "kotlinx.coroutines.launch()")))
+        assertTrue(isInternalFrame(frame(1, "wasmtime::runtime::func::Func::call_impl")))
+        assertTrue(isInternalFrame(frame(1, "wasmtime_runtime::traphandlers::catch_traps")))
+        assertTrue(isInternalFrame(frame(1, "wasmtime_setjmp_23_0_0")))
+        assertTrue(isInternalFrame(frame(1, "{closure#0}", std)))
+        assertFalse(isInternalFrame(frame(1, "rust_debuggee::panic", "/work/debuggee/rust/src/main.rs")))
+        assertFalse(isInternalFrame(frame(1, "<rust_debuggee::Point as core::fmt::Debug>::fmt")))
+        assertFalse(isInternalFrame(frame(1, "core_loop")))
+        assertFalse(isInternalFrame(frame(1, "_started")))
+    }
+
+    @Test
+    fun `internal frames are subtle or collapsed`() {
+        val frames = listOf(
+            frame(1, "rust_panic"),
+            frame(2, "std::panicking::rust_panic_with_hook", "/rustc/90b35a6239c3d8bdabc530a6a0816f7ff89a0aaf/library/std/src/panicking.rs"),
+            frame(3, "rust_debuggee::panic", "/work/main.rs"),
+            frame(4, "core::ops::function::FnOnce::call_once"),
+            frame(5, "rust_debuggee::main", "/work/main.rs"),
+        )
+        assertEquals(frames, presentInternalFrames(frames, InternalFrames.Show))
+
+        val subtle = presentInternalFrames(frames, InternalFrames.Subtle).map { it as Map<*, *> }
+        assertEquals(listOf("subtle", "subtle", null, "subtle", null), subtle.map { it["presentationHint"] })
+        assertEquals("deemphasize", (subtle[1]["source"] as Map<*, *>)["presentationHint"])
+
+        val collapsed = presentInternalFrames(frames, InternalFrames.Collapse).map { it as Map<*, *> }
+        assertEquals(listOf(1, 3, 4, 5), collapsed.map { it["id"] })
+        assertEquals(INTERNAL_FRAMES_LABEL, collapsed[0]["name"])
+        assertEquals("label", collapsed[0]["presentationHint"])
+        assertEquals("subtle", collapsed[2]["presentationHint"])
+    }
+
+    @Test
+    fun `the top frame of a paused thread is never collapsed`() {
+        val frames = listOf(
+            frame(1, "__GI___clock_nanosleep"),
+            frame(2, "__GI___nanosleep"),
+            frame(3, "std::thread::sleep", "/rustc/90b35a6239c3d8bdabc530a6a0816f7ff89a0aaf/library/std/src/thread/mod.rs"),
+            frame(4, "rust_debuggee::main", "/work/main.rs"),
+        )
+        val collapsed = presentInternalFrames(frames, InternalFrames.Collapse, keepTop = true).map { it as Map<*, *> }
+        assertEquals(listOf(1, 2, 4), collapsed.map { it["id"] })
+        assertEquals("__GI___clock_nanosleep", collapsed[0]["name"])
+        assertEquals("subtle", collapsed[0]["presentationHint"])
+        assertEquals(INTERNAL_FRAMES_LABEL, collapsed[1]["name"])
+    }
+
+    @Test
+    fun `frames without an id are left alone`() {
+        val label = mapOf("name" to "[async]", "presentationHint" to "label")
+        assertEquals(listOf(label), markInlinedFrames(listOf(label), setOf(524288)))
+    }
+}
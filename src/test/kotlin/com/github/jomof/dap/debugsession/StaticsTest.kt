@@ -0,0 +1,52 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.messages.ScopesRequest
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for the Statics scope helpers. Verifies that lldb-dap's
+ * Globals scope is replaced by a Statics scope for the requested frame
+ * and that the scope and its values get references of their own.
+ */
+class StaticsTest {
+
+    private val session = DebugSession()
+
+    private fun scopesResponse(seq: Int) = DapResponse(
+        seq = 1, requestSeq = seq, command = "scopes", success = true,
+        body = mapOf("scopes" to listOf(
+            mapOf("name" to "Locals", "presentationHint" to "locals", "variablesReference" to 1),
+            mapOf("name" to "Globals", "variablesReference" to 2, "namedVariables" to 3, "expensive" to false),
+        )),
+    )
+
+    @Test
+    fun `globals scope is replaced by a statics scope for the frame`() {
+        session.onScopesRequest(ScopesRequest(seq = 5, frameId = 7))
+        val scopes = session.withStaticsScope(scopesResponse(5)).body["scopes"] as List<*>
+        assertEquals(mapOf("name" to "Locals", "presentationHint" to "locals", "variablesReference" to 1), scopes[0])
+        assertEquals(
+            mapOf("name" to "Statics", "variablesReference" to FIRST_STATICS_REFERENCE, "expensive" to false),
+            scopes[1])
+        assertEquals(mapOf(FIRST_STATICS_REFERENCE to 7), session.staticsScopes)
+        session.forgetStaticsScopes()
+        assertTrue(session.staticsScopes.isEmpty())
+    }
+
+    @Test
+    fun `scopes of requests not seen are left alone`() {
+        val response = scopesResponse(6)
+        assertEquals(response, session.withStaticsScope(response))
+        assertTrue(session.staticsScopes.isEmpty())
+    }
+
+    @Test
+    fun `statics references are above the chunk references`() {
+        assertFalse(isStaticsReference(FIRST_CHUNK_REFERENCE))
+        assertFalse(isStaticsReference(FIRST_ARRAY_REFERENCE))
+        assertTrue(isStaticsReference(FIRST_STATICS_REFERENCE))
+        assertTrue(isStaticsReference(FIRST_STATIC_VALUE_REFERENCE))
+    }
+}
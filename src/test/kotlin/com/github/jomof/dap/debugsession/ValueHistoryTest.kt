@@ -0,0 +1,88 @@
+package com.github.jomof.dap.debugsession
+
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [ValueHistory], [markChanged], and
+ * [DebugSession.trackValues]. Verifies that a value is marked only when
+ * it differs from the last stop it was shown at, and that the children's
+ * references inherit their parent's frame.
+ */
+class ValueHistoryTest {
+
+    private val frame = ValueFrame(frameId = 1, key = "1:7ffe0000:main")
+
+    private fun variable(name: String, value: String, reference: Int = 0) =
+        mapOf("name" to name, "value" to value, "evaluateName" to name, "variablesReference" to reference)
+
+    private fun attributes(variable: Any?): List<*>? =
+        ((variable as Map<*, *>)["presentationHint"] as? Map<*, *>)?.get("attributes") as? List<*>
+
+    @Test
+    fun `a value is changed only against an earlier stop`() {
+        val history = ValueHistory()
+        assertFalse(history.record("f/n", "1"))
+        assertFalse(history.record("f/n", "2"))
+        history.rotate()
+        assertTrue(history.record("f/n", "3"))
+        assertTrue(history.record("f/n", "3"))
+        history.rotate()
+        assertFalse(history.record("f/n", "3"))
+    }
+
+    @Test
+    fun `a value not shown at a stop keeps its older record`() {
+        val history = ValueHistory()
+        history.record("f/n", "1")
+        history.rotate()
+        history.rotate()
+        assertTrue(history.record("f/n", "2"))
+    }
+
+    @Test
+    fun `the least recently shown values are forgotten past the limit`() {
+        val history = ValueHistory(limit = 2)
+        history.record("f/a", "1")
+        history.record("f/b", "1")
+        history.rotate()
+        history.record("f/c", "1")
+        history.rotate()
+        assertEquals(1, listOf("f/a", "f/b").count { history.record(it, "2") })
+        assertTrue(history.record("f/c", "2"))
+    }
+
+    @Test
+    fun `markChanged keeps other presentation hints`() {
+        val marked = markChanged(mapOf("name" to "x", "presentationHint" to mapOf("kind" to "data", "attributes" to listOf("readOnly"))))
+        assertEquals(mapOf("kind" to "data", "attributes" to listOf("readOnly", CHANGED_ATTRIBUTE)), marked["presentationHint"])
+        assertSame(marked, markChanged(marked))
+        assertEquals(listOf(CHANGED_ATTRIBUTE), attributes(markChanged(mapOf("name" to "y"))))
+    }
+
+    @Test
+    fun `trackValues marks changed variables and records child frames`() {
+        val session = DebugSession()
+        session.trackValues(listOf(variable("n", "1"), variable("v", "size=2", reference = 5)), frame, mapOf("v" to "aa"))
+        assertEquals(frame, session.valueFrames[5])
+        session.rotateValueHistory()
+        assertTrue(session.valueFrames.isEmpty())
+
+        val tracked = session.trackValues(
+            listOf(variable("n", "2"), variable("v", "size=2", reference = 6), variable("m", "0")),
+            frame, mapOf("v" to "bb"),
+        )
+        assertEquals(listOf(CHANGED_ATTRIBUTE), attributes(tracked[0]))
+        assertEquals(listOf(CHANGED_ATTRIBUTE), attributes(tracked[1]))
+        assertNull(attributes(tracked[2]))
+    }
+
+    @Test
+    fun `the same name in another frame is tracked apart`() {
+        val session = DebugSession()
+        session.trackValues(listOf(variable("n", "1")), frame, emptyMap())
+        session.rotateValueHistory()
+        val other = session.trackValues(listOf(variable("n", "2")), frame.copy(key = "1:7ffd0000:step"), emptyMap())
+        assertNull(attributes(other[0]))
+    }
+}
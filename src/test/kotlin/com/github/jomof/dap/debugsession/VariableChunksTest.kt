@@ -0,0 +1,73 @@
+package com.github.jomof.dap.debugsession
+
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for large-container paging in `Variables.kt`. Verifies that
+ * Rust sequence and C++ container lengths are read from their summaries,
+ * that a request's `start` is taken off the count to page, and that
+ * large ranges are split into chunks the way VS Code groups them.
+ */
+class VariableChunksTest {
+
+    private fun vec(value: String, reference: Int = 5) = mapOf(
+        "name" to "v", "type" to "alloc::vec::Vec<i32, alloc::alloc::Global>",
+        "value" to value, "variablesReference" to reference,
+    )
+
+    @Test
+    fun `vec and slice lengths come from the summary`() {
+        assertEquals(1_000_000, indexedChildCount(vec("(1000000) vec![0, 1, 2, ...]")))
+        assertEquals(3, indexedChildCount(mapOf(
+            "type" to "&[u8]", "value" to "(3) &[1, 2, 3]", "variablesReference" to 6)))
+    }
+
+    @Test
+    fun `C++ container sizes come from the summary`() {
+        fun cpp(type: String, value: String) = mapOf("type" to type, "value" to value, "variablesReference" to 8)
+        assertEquals(10, indexedChildCount(cpp("std::vector<std::vector<int> >", "size=10")))
+        assertEquals(2, indexedChildCount(cpp("std::__1::map<int, float, std::__1::less<int> >", "size=2")))
+        assertEquals(2, indexedChildCount(cpp("std::unordered_map<int, float>", "size=2")))
+        assertEquals(0, indexedChildCount(cpp("std::__cxx11::list<int>", "size=0")))
+        assertNull(indexedChildCount(cpp("std::string", "\"size=3\"")))
+        assertNull(indexedChildCount(cpp("std::vector<int>", "{...}")))
+    }
+
+    @Test
+    fun `lldb-dap indexedVariables is kept`() {
+        assertEquals(8, indexedChildCount(mapOf("type" to "int[8]", "indexedVariables" to 8, "variablesReference" to 7)))
+    }
+
+    @Test
+    fun `other values have no indexed count`() {
+        assertNull(indexedChildCount(vec("(3) vec![1, 2, 3]", reference = 0)))
+        assertNull(indexedChildCount(mapOf("type" to "alloc::string::String", "value" to "\"(3) \"", "variablesReference" to 0)))
+        assertNull(indexedChildCount(mapOf("type" to "main::Point", "value" to "{...}", "variablesReference" to 9)))
+    }
+
+    @Test
+    fun `indexed count excludes the elements before start`() {
+        val session = DebugSession()
+        session.indexedVariableCounts[5] = 1_000_000
+        assertEquals(1_000_000, session.indexedVariablesFrom(5, 0))
+        assertEquals(750_000, session.indexedVariablesFrom(5, 250_000))
+
+        val chunk = session.variableChunks.handleFor(VariableChunk(reference = 5, start = 10_000, count = 10_000))
+        assertEquals(9_000, session.indexedVariablesFrom(chunk, 1_000))
+        assertNull(session.indexedVariablesFrom(6, 100))
+    }
+
+    @Test
+    fun `a million elements split into 100 chunks of 10000`() {
+        val chunks = chunkRanges(0, 1_000_000)
+        assertEquals(100, chunks.size)
+        assertEquals(0 to 10_000, chunks.first())
+        assertEquals(990_000 to 10_000, chunks.last())
+    }
+
+    @Test
+    fun `last chunk holds the remainder`() {
+        assertEquals(listOf(200 to 100, 300 to 50), chunkRanges(200, 150))
+    }
+}
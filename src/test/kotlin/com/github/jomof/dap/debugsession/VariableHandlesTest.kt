@@ -0,0 +1,118 @@
+package com.github.jomof.dap.debugsession
+
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [HandleTable]. Verifies that a node keeps its reference
+ * until the table is reset, that references of earlier stops name
+ * nothing, that a table neither grows past its capacity nor leaves
+ * its range, that nodes it drops free their references for new ones,
+ * and that watches keep their references across stops while they are
+ * asked for.
+ */
+class VariableHandlesTest {
+
+    @Test
+    fun `a node keeps its reference until reset`() {
+        val table = HandleTable<String>(first = 1000, end = 2000, capacity = 10)
+        assertEquals(1000, table.handleFor("a"))
+        assertEquals(1001, table.handleFor("b"))
+        assertEquals(1000, table.handleFor("a"))
+        assertEquals("b", table[1001])
+        assertEquals(1001, table.handleOf("b"))
+        assertNull(table.handleOf("c"))
+        assertEquals(mapOf(1000 to "a", 1001 to "b"), table)
+    }
+
+    @Test
+    fun `references of earlier stops name nothing`() {
+        val table = HandleTable<String>(first = 1000, end = 2000, capacity = 10)
+        val stale = table.handleFor("a")
+        table.reset()
+        assertTrue(table.isEmpty())
+        assertNull(table[stale])
+        assertEquals(1010, table.handleFor("a"))
+        assertNull(table[stale])
+    }
+
+    @Test
+    fun `resets without new nodes keep the generation`() {
+        val table = HandleTable<String>(first = 1000, end = 2000, capacity = 10)
+        table.handleFor("a")
+        table.reset()
+        table.reset()
+        assertEquals(1010, table.handleFor("a"))
+    }
+
+    @Test
+    fun `generations wrap around within the range`() {
+        val table = HandleTable<String>(first = 1000, end = 1030, capacity = 10)
+        val seen = (1..4).map { table.handleFor("a").also { table.reset() } }
+        assertEquals(listOf(1000, 1010, 1020, 1000), seen)
+    }
+
+    @Test
+    fun `a full table hands out no more references`() {
+        val table = HandleTable<Int>(first = 1000, end = 2000, capacity = 2)
+        assertEquals(1000, table.handleFor(1))
+        assertEquals(1001, table.handleFor(2))
+        assertEquals(0, table.handleFor(3))
+        assertEquals(2, table.size)
+        assertNull(table[1002])
+    }
+
+    @Test
+    fun `retained nodes keep their references and dropped ones free theirs`() {
+        val table = HandleTable<String>(first = 1000, end = 2000, capacity = 3)
+        listOf("a", "b", "c").forEach { table.handleFor(it) }
+        table.retain { it != "a" }
+        assertEquals(mapOf(1001 to "b", 1002 to "c"), table)
+        assertNull(table.handleOf("a"))
+        assertEquals(1000, table.handleFor("d"))
+        assertEquals(0, table.handleFor("e"))
+    }
+
+    @Test
+    fun `session tables stay in their ranges`() {
+        val session = DebugSession()
+        for (stop in 0 until 5000) {
+            val reference = session.staticsScopes.handleFor(stop)
+            assertTrue(reference in FIRST_STATICS_REFERENCE until FIRST_REGISTERS_REFERENCE, "stop $stop: $reference")
+            session.forgetStaticsScopes()
+        }
+    }
+
+    @Test
+    fun `watch references stay in their range across stops`() {
+        val session = DebugSession()
+        val key = WatchKey("v", frameId = 1, format = null)
+        val reference = session.watchReferences.handleFor(key)
+        assertEquals(FIRST_WATCH_REFERENCE, reference)
+        session.onWatchStop()
+        assertEquals(reference, session.watchReferences.handleFor(key))
+        val last = (1 until MAX_HANDLES_PER_STOP).map { session.watchReferences.handleFor(WatchKey("w$it", 1, null)) }.last()
+        assertTrue(last < FIRST_ARRAY_REFERENCE, "last: $last")
+        assertEquals(0, session.watchReferences.handleFor(WatchKey("more", 1, null)))
+    }
+
+    @Test
+    fun `watches not asked for at the last stop are forgotten`() {
+        val session = DebugSession()
+        val kept = WatchKey("v", frameId = 1, format = null)
+        val gone = WatchKey("v", frameId = 2, format = null)
+        val keptReference = session.watchReferences.handleFor(kept)
+        val goneReference = session.watchReferences.handleFor(gone)
+        session.watchTargets[keptReference] = WatchTarget("{}", 5, 0)
+        session.watchTargets[goneReference] = WatchTarget("{}", 6, 0)
+        session.watchRequests[kept] = 0
+        session.watchRequests[gone] = 0
+        session.onWatchStop()
+        session.watchRequests[kept] = 1
+        session.onWatchStop()
+        assertEquals(keptReference, session.watchReferences.handleOf(kept))
+        assertNull(session.watchReferences.handleOf(gone))
+        assertEquals(setOf(keptReference), session.watchTargets.keys)
+        assertEquals(setOf(kept), session.watchRequests.keys)
+    }
+}
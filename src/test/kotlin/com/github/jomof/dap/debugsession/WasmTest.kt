@@ -0,0 +1,91 @@
+package com.github.jomof.dap.debugsession
+
+import com.github.jomof.dap.messages.LaunchRequestArguments
+import com.github.jomof.dap.sb.SBError
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+import org.junit.jupiter.api.io.TempDir
+import java.io.ByteArrayOutputStream
+import java.io.File
+
+/**
+ * Unit tests for the WebAssembly launch of Wasm.kt. Verifies that
+ * modules are told from native programs by their magic, that custom
+ * sections are read, and that a module's launch becomes a `wasmtime run`
+ * of it, refused without debug info.
+ */
+class WasmTest {
+
+    /** A module with the given custom sections between a type section and a code section. */
+    private fun module(vararg customSections: String): ByteArray = ByteArrayOutputStream().apply {
+        write(byteArrayOf(0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00))
+        write(byteArrayOf(0x01, 0x04, 0x01, 0x60, 0x00, 0x00))
+        for (name in customSections) {
+            val bytes = name.toByteArray()
+            write(0x00)
+            write(bytes.size + 3)
+            write(bytes.size)
+            write(bytes)
+            write(byteArrayOf(0x01, 0x02))
+        }
+        write(byteArrayOf(0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b))
+    }.toByteArray()
+
+    @Test
+    fun `modules are recognized by their magic`(@TempDir dir: File) {
+        val wasm = File(dir, "app.wasm").apply { writeBytes(module()) }
+        val elf = File(dir, "app").apply { writeBytes(byteArrayOf(0x7f, 0x45, 0x4c, 0x46)) }
+        assertTrue(isWasmModule(wasm.path))
+        assertFalse(isWasmModule(elf.path))
+        assertFalse(isWasmModule(File(dir, "missing.wasm").path))
+    }
+
+    @Test
+    fun `custom section names are read in order`() {
+        assertEquals(emptyList<String>(), wasmCustomSections(module()))
+        assertEquals(listOf(".debug_info", ".debug_line", "name"),
+            wasmCustomSections(module(".debug_info", ".debug_line", "name")))
+        assertThrows(SBError::class.java) { wasmCustomSections(module(".debug_info").copyOf(20)) }
+    }
+
+    @Test
+    fun `a module launch runs wasmtime with debug info`(@TempDir dir: File) {
+        val wasm = File(dir, "app.wasm").apply { writeBytes(module(".debug_info")) }
+        val args = LaunchRequestArguments(program = wasm.path, args = listOf("a", "b"),
+            env = mapOf("GREETING" to "hi"), wasmRuntime = "/opt/wasmtime")
+        val launched = wasmLaunchArguments(args, emptyMap())
+        assertEquals("/opt/wasmtime", launched.program)
+        assertEquals(listOf("run", "-D", "debug-info=y", "-O", "opt-level=0", "--dir=.", "--env", "GREETING",
+            wasm.absolutePath, "a", "b"), launched.args)
+        assertEquals(false, launched.requireDebugInfo)
+        assertEquals(args.env, launched.env)
+    }
+
+    @Test
+    fun `other programs are unchanged`(@TempDir dir: File) {
+        val elf = File(dir, "app").apply { writeBytes(byteArrayOf(0x7f, 0x45, 0x4c, 0x46)) }
+        val args = LaunchRequestArguments(program = elf.path)
+        assertSame(args, wasmLaunchArguments(args, emptyMap()))
+    }
+
+    @Test
+    fun `modules without debug info are refused unless allowed`(@TempDir dir: File) {
+        val wasm = File(dir, "app.wasm").apply { writeBytes(module("name")) }
+        val error = assertThrows(ProgramError::class.java) {
+            wasmLaunchArguments(LaunchRequestArguments(program = wasm.path, wasmRuntime = "wasmtime"), emptyMap())
+        }
+        assertEquals(ProgramProblem.NoDebugInfo, error.problem)
+        val allowed = LaunchRequestArguments(program = wasm.path, requireDebugInfo = false, wasmRuntime = "wasmtime")
+        assertEquals("wasmtime", wasmLaunchArguments(allowed, emptyMap()).program)
+    }
+
+    @Test
+    fun `wasmtime is found on PATH`(@TempDir dir: File) {
+        assertEquals("/opt/wasmtime", wasmtimeExecutable("/opt/wasmtime", emptyMap()))
+        val wasmtime = File(dir, if (isWindowsHost()) "wasmtime.exe" else "wasmtime").apply {
+            writeText("")
+            setExecutable(true)
+        }
+        assertEquals(wasmtime.path, wasmtimeExecutable(null, mapOf("PATH" to dir.path)))
+    }
+}
@@ -0,0 +1,75 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.AsyncTask
+import com.github.jomof.dap.debugsession.AwaitLevel
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.isTaskFrame
+import com.github.jomof.dap.debugsession.taskThreadId
+import com.github.jomof.dap.messages.*
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [AsyncTasksHandler]. Verifies that nothing is
+ * intercepted unless tokio tasks are enabled, and that task
+ * pseudo-threads get their await stack and empty scopes from KDAP while
+ * OS threads go to lldb-dap.
+ */
+class AsyncTasksHandlerTest {
+
+    private val session = DebugSession()
+    private val handler = AsyncTasksHandler(session)
+    private val taskThread = taskThreadId(7)!!
+
+    private fun enable() {
+        session.tokioTasks = true
+        session.asyncTasks = mapOf(taskThread to AsyncTask(7, "Running", listOf(
+            AwaitLevel("app::serve::{async_fn_env#0}", "Suspend1"),
+            AwaitLevel("tokio::time::sleep::Sleep", null),
+        )))
+    }
+
+    @Test
+    fun `requests are forwarded when tokio tasks are off`() {
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(ThreadsRequest(seq = 1)))
+        assertInstanceOf(RequestAction.Forward::class.java,
+            handler.onRequest(StackTraceRequest(seq = 2, threadId = taskThread)))
+    }
+
+    @Test
+    fun `threads is left to ThreadsHandler`() {
+        enable()
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(ThreadsRequest(seq = 1)))
+        assertInstanceOf(RequestAction.HandleAsync::class.java, ThreadsHandler(session).onRequest(ThreadsRequest(seq = 1)))
+    }
+
+    @Test
+    fun `stackTrace for a task is its await stack`() {
+        enable()
+        val action = assertInstanceOf(RequestAction.Respond::class.java,
+            handler.onRequest(StackTraceRequest(seq = 3, threadId = taskThread)))
+        val response = action.response as DapResponse
+        assertEquals(3, response.requestSeq)
+        val frames = response.body["stackFrames"] as List<*>
+        assertEquals(listOf("app::serve::{async_fn#0} [Suspend1]", "tokio::time::sleep::Sleep"),
+            frames.map { (it as Map<*, *>)["name"] })
+        assertTrue(frames.all { isTaskFrame((it as Map<*, *>)["id"] as Int) })
+        assertEquals(2, response.body["totalFrames"])
+    }
+
+    @Test
+    fun `os threads and their frames go to lldb-dap`() {
+        enable()
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(StackTraceRequest(seq = 4, threadId = 12345)))
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(ScopesRequest(seq = 5, frameId = 524288)))
+    }
+
+    @Test
+    fun `scopes for a task frame are empty`() {
+        enable()
+        val action = assertInstanceOf(RequestAction.Respond::class.java,
+            handler.onRequest(ScopesRequest(seq = 6, frameId = session.nextTaskFrameId.get())))
+        assertEquals(emptyList<Any>(), (action.response as DapResponse).body["scopes"])
+    }
+}
@@ -0,0 +1,57 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.messages.BreakpointsRequest
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.FunctionBreakpoint
+import com.github.jomof.dap.messages.MemoryMapRequest
+import com.github.jomof.dap.messages.SetBreakpointsRequest
+import com.github.jomof.dap.messages.SetFunctionBreakpointsRequest
+import com.github.jomof.dap.messages.Source
+import com.github.jomof.dap.messages.SourceBreakpoint
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [BreakpointFileHandler]. Verifies that
+ * `kdap/breakpoints` requests parse and return
+ * [RequestAction.HandleAsync], that the client's breakpoints are
+ * remembered while their requests are forwarded, and that other
+ * requests pass through.
+ */
+class BreakpointFileHandlerTest {
+
+    private val session = DebugSession()
+    private val handler = BreakpointFileHandler(session)
+
+    @Test
+    fun `kdap breakpoints request parses`() {
+        val json = """{"type":"request","seq":4,"command":"kdap/breakpoints",
+            "arguments":{"action":"export","path":"/tmp/bps.json"}}"""
+        val request = assertInstanceOf(BreakpointsRequest::class.java, DapMessage.parse(json))
+        assertEquals(BreakpointsRequest(seq = 4, action = "export", path = "/tmp/bps.json"), request)
+        assertEquals(request, DapMessage.parse(request.toJson()))
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `set breakpoints requests are remembered and forwarded`() {
+        val source = Source(path = "/src/main.rs")
+        val set = SetBreakpointsRequest(seq = 2, source = source,
+            breakpoints = listOf(SourceBreakpoint(line = 12, logMessage = "n = {n}")))
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(set))
+        assertEquals(set.copy(seq = 0), session.clientSourceBreakpoints["/src/main.rs"])
+
+        handler.onRequest(SetFunctionBreakpointsRequest(seq = 3, breakpoints = listOf(FunctionBreakpoint("main"))))
+        assertEquals(listOf(FunctionBreakpoint("main")), session.clientFunctionBreakpoints)
+
+        handler.onRequest(SetBreakpointsRequest(seq = 4, source = source))
+        assertFalse("/src/main.rs" in session.clientSourceBreakpoints)
+    }
+
+    @Test
+    fun `other requests are forwarded unchanged`() {
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(MemoryMapRequest(seq = 2)))
+    }
+}
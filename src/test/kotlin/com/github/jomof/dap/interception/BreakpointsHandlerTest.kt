@@ -0,0 +1,196 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.BREAKPOINT_EVENT_MARKER
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.messages.BreakpointEvent
+import com.github.jomof.dap.messages.BreakpointLocationsRequest
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.messages.FunctionBreakpoint
+import com.github.jomof.dap.messages.InitializeRequest
+import com.github.jomof.dap.messages.InstructionBreakpoint
+import com.github.jomof.dap.messages.OutputEvent
+import com.github.jomof.dap.messages.SetBreakpointsRequest
+import com.github.jomof.dap.messages.SetFunctionBreakpointsRequest
+import com.github.jomof.dap.messages.SetInstructionBreakpointsRequest
+import com.github.jomof.dap.messages.Source
+import com.github.jomof.dap.messages.SourceBreakpoint
+import com.github.jomof.dap.messages.StoppedEvent
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [BreakpointsHandler]. Verifies that `setBreakpoints`,
+ * `setFunctionBreakpoints`, and `setInstructionBreakpoints` requests
+ * needing KDAP behavior return [RequestAction.HandleAsync], that
+ * `breakpointLocations` is answered for source files, everything else
+ * passes through, instruction breakpoints are advertised, hardware
+ * breakpoint stops are reported under the breakpoint they stand in for,
+ * and the breakpoint listener's output becomes `breakpoint` events.
+ */
+class BreakpointsHandlerTest {
+
+    private val session = DebugSession()
+    private val handler = BreakpointsHandler(session)
+    private val source = Source(path = "/src/main.rs")
+
+    private fun setBreakpoints(vararg bps: SourceBreakpoint) =
+        SetBreakpointsRequest(seq = 1, source = source, breakpoints = bps.toList())
+
+    @Test
+    fun `plain breakpoints are forwarded unchanged`() {
+        val request = setBreakpoints(SourceBreakpoint(line = 10), SourceBreakpoint(line = 20))
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `native condition is forwarded unchanged`() {
+        val request = setBreakpoints(SourceBreakpoint(line = 10, condition = "i == 5"))
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `python condition returns HandleAsync`() {
+        val request = setBreakpoints(SourceBreakpoint(line = 10, condition = "/py i % 2 == 0"))
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `explicit native prefix returns HandleAsync so it can be stripped`() {
+        val request = setBreakpoints(SourceBreakpoint(line = 10, condition = "/nat i == 5"))
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `hit condition returns HandleAsync`() {
+        val request = setBreakpoints(SourceBreakpoint(line = 10, hitCondition = "%3"))
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `logpoint returns HandleAsync`() {
+        val request = setBreakpoints(SourceBreakpoint(line = 10, logMessage = "i = {i}"))
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `source with existing callbacks returns HandleAsync`() {
+        session.scriptedBreakpoints[source.key] = setOf(1)
+        val request = setBreakpoints(SourceBreakpoint(line = 10))
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `hardware hint returns HandleAsync`() {
+        val bp = SourceBreakpoint.fromJson(JSONObject("""{"line":10,"hardware":true}"""))
+        assertEquals(true, bp.hardware)
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(setBreakpoints(bp)))
+    }
+
+    @Test
+    fun `breakpoints set while a process runs return HandleAsync for the hardware fallback`() {
+        session.processRunning = true
+        val request = setBreakpoints(SourceBreakpoint(line = 10))
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `hardware breakpoint stops report the breakpoint they stand in for`() {
+        session.hardwareBreakpoints[9] = 3
+        val stop = StoppedEvent(seq = 5, reason = "breakpoint", description = "breakpoint 9.1", threadId = 1,
+            hitBreakpointIds = listOf(9, 3))
+        val result = assertInstanceOf(StoppedEvent::class.java, handler.onBackendMessage(stop).single())
+        assertEquals("breakpoint 3.1", result.description)
+        assertEquals(listOf(3), result.hitBreakpointIds)
+
+        val other = stop.copy(description = "breakpoint 4.1", hitBreakpointIds = listOf(4))
+        assertEquals(other, handler.onBackendMessage(other).single())
+    }
+
+    @Test
+    fun `listener output becomes breakpoint changed events`() {
+        val output = OutputEvent(seq = 5, category = "console",
+            output = "before\n$BREAKPOINT_EVENT_MARKER{\"id\":4,\"verified\":true,\"line\":12}\nafter\n")
+        val messages = handler.onBackendMessage(output)
+        assertEquals(3, messages.size)
+        assertEquals(output.copy(output = "before\n"), messages[0])
+        val event = assertInstanceOf(BreakpointEvent::class.java, messages[1])
+        assertEquals("changed", event.reason)
+        val body = JSONObject(event.toJson()).getJSONObject("body").getJSONObject("breakpoint")
+        assertEquals(4, body.getInt("id"))
+        assertTrue(body.getBoolean("verified"))
+        assertEquals(12, body.getInt("line"))
+        assertEquals(output.copy(output = "after\n"), messages[2])
+    }
+
+    @Test
+    fun `lldb-dap breakpoint events parse and pass through`() {
+        val json = """{"type":"event","seq":6,"event":"breakpoint","body":{"reason":"changed","breakpoint":{"id":2,"verified":false}}}"""
+        val event = assertInstanceOf(BreakpointEvent::class.java, DapMessage.parse(json))
+        assertEquals("changed", event.reason)
+        assertEquals(2, (event.breakpoint["id"] as Number).toInt())
+        assertSame(event, handler.onBackendMessage(event).single())
+    }
+
+    @Test
+    fun `plain function breakpoints are forwarded unchanged`() {
+        val request = SetFunctionBreakpointsRequest(
+            seq = 1, breakpoints = listOf(FunctionBreakpoint("rust_debuggee::step_in")))
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `wildcard function breakpoint returns HandleAsync`() {
+        val request = SetFunctionBreakpointsRequest(
+            seq = 1, breakpoints = listOf(FunctionBreakpoint("rust_debuggee::step_in*")))
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `existing pattern breakpoints return HandleAsync so they can be removed`() {
+        session.patternFunctionBreakpoints = listOf(3)
+        val request = SetFunctionBreakpointsRequest(seq = 1)
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `plain instruction breakpoints are forwarded unchanged`() {
+        val request = SetInstructionBreakpointsRequest(
+            seq = 1, breakpoints = listOf(InstructionBreakpoint("0x1000", offset = 4, condition = "x > 1")))
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `instruction breakpoint hit condition returns HandleAsync`() {
+        val request = SetInstructionBreakpointsRequest(
+            seq = 1, breakpoints = listOf(InstructionBreakpoint("0x1000", hitCondition = "%2")))
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `initialize response advertises instruction breakpoints`() {
+        val response = DapResponse(seq = 1, requestSeq = 1, command = "initialize", success = true)
+        val result = assertInstanceOf(DapResponse::class.java, handler.onBackendMessage(response).single())
+        assertEquals(true, result.body["supportsInstructionBreakpoints"])
+    }
+
+    @Test
+    fun `breakpoint locations in a source file return HandleAsync`() {
+        val request = BreakpointLocationsRequest(seq = 1, source = source, line = 10)
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `breakpoint locations without a path are forwarded unchanged`() {
+        val request = BreakpointLocationsRequest(seq = 1, source = Source(sourceReference = 7), line = 10)
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `non-setBreakpoints request is forwarded unchanged`() {
+        val request = InitializeRequest(seq = 2)
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(request))
+    }
+}
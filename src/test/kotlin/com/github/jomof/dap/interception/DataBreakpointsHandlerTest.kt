@@ -0,0 +1,64 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.SoftwareWatchpoint
+import com.github.jomof.dap.messages.ContinueRequest
+import com.github.jomof.dap.messages.DataBreakpoint
+import com.github.jomof.dap.messages.SetDataBreakpointsRequest
+import com.github.jomof.dap.messages.StoppedEvent
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [DataBreakpointsHandler]. Verifies that data breakpoint
+ * requests are handled by KDAP, that `continue` is only taken over while
+ * software watchpoints exist, and that the stop ending a watched
+ * `continue` is reported as a data breakpoint.
+ */
+class DataBreakpointsHandlerTest {
+
+    private val session = DebugSession()
+    private val handler = DataBreakpointsHandler(session)
+
+    @Test
+    fun `setDataBreakpoints returns HandleAsync`() {
+        val request = SetDataBreakpointsRequest(seq = 1, breakpoints = listOf(DataBreakpoint(dataId = "7ffe0010/4")))
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `continue is forwarded without software watchpoints`() {
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(ContinueRequest(seq = 2)))
+    }
+
+    @Test
+    fun `continue returns HandleAsync with software watchpoints`() {
+        session.softwareWatchpoints["7ffe0010/4"] = SoftwareWatchpoint(0x7ffe0010, 4)
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(ContinueRequest(seq = 2)))
+    }
+
+    @Test
+    fun `step stop after watched continue becomes data breakpoint`() {
+        session.softwareWatchStepping = true
+        val result = handler.onBackendMessage(StoppedEvent(seq = 5, reason = "step", threadId = 1))
+        val stopped = assertInstanceOf(StoppedEvent::class.java, result.single())
+        assertEquals("data breakpoint", stopped.reason)
+        assertEquals(1, stopped.threadId)
+        assertFalse(session.softwareWatchStepping)
+    }
+
+    @Test
+    fun `other stops after watched continue are unchanged`() {
+        session.softwareWatchStepping = true
+        val event = StoppedEvent(seq = 5, reason = "breakpoint", threadId = 1)
+        assertSame(event, handler.onBackendMessage(event).single())
+        assertFalse(session.softwareWatchStepping)
+    }
+
+    @Test
+    fun `step stops are unchanged when not watching`() {
+        val event = StoppedEvent(seq = 5, reason = "step", threadId = 1)
+        assertSame(event, handler.onBackendMessage(event).single())
+    }
+}
@@ -0,0 +1,180 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.CompletionContext
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.SimpleExpr
+import com.github.jomof.dap.debugsession.WatchKey
+import com.github.jomof.dap.debugsession.WatchResult
+import com.github.jomof.dap.debugsession.completionContext
+import com.github.jomof.dap.messages.CompletionsRequest
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.messages.EvaluateRequest
+import com.github.jomof.dap.messages.SetVariableRequest
+import com.github.jomof.dap.messages.StoppedEvent
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [EvaluateHandler]. Verifies that simple expressions in
+ * watch and hover contexts, and KDAP-owned debug console input, are
+ * evaluated by KDAP, while native expressions, bare variables, and LLDB
+ * commands go to lldb-dap, that every watch is handled by KDAP's cache,
+ * which stops and writes invalidate, that watches, which may call
+ * functions, can't be cancelled, that "Copy Value" is rendered by
+ * KDAP, and that console completions for backtick commands and `?`
+ * expressions are answered by KDAP.
+ */
+class EvaluateHandlerTest {
+
+    private val session = DebugSession()
+    private val handler = EvaluateHandler(session)
+
+    @Test
+    fun `simple expression in watch context is handled async`() {
+        val request = EvaluateRequest(seq = 4, expression = "p.0 + 1", context = "watch")
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `function calls in watches are handled async`() {
+        for (expression in listOf("v.len()", "crate::area(2, 3) * 2")) {
+            val request = EvaluateRequest(seq = 4, expression = expression, context = "watch")
+            assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request), expression)
+        }
+    }
+
+    @Test
+    fun `watches may call functions and are not cancellable, hovers are`() {
+        val watch = handler.onRequest(EvaluateRequest(seq = 4, expression = "v.len()", context = "watch"))
+        assertFalse(assertInstanceOf(RequestAction.HandleAsync::class.java, watch).cancellable)
+        val hover = handler.onRequest(EvaluateRequest(seq = 5, expression = "self.len", context = "hover"))
+        assertTrue(assertInstanceOf(RequestAction.HandleAsync::class.java, hover).cancellable)
+    }
+
+    @Test
+    fun `field access on hover is handled async`() {
+        val request = EvaluateRequest(seq = 4, expression = "self.len", context = "hover")
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `bare variable is forwarded unchanged`() {
+        val request = EvaluateRequest(seq = 4, expression = "x", context = "hover")
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `native prefix is stripped`() {
+        val request = EvaluateRequest(seq = 4, expression = "/nat sizeof(x)", context = "hover")
+        val action = assertInstanceOf(RequestAction.ForwardModified::class.java, handler.onRequest(request))
+        assertEquals("sizeof(x)", assertInstanceOf(EvaluateRequest::class.java, action.modifiedRequest).expression)
+    }
+
+    @Test
+    fun `native prefix is stripped with the hex format kept`() {
+        val request = EvaluateRequest(seq = 4, expression = "/nat ptr", context = "hover", hex = true)
+        val action = assertInstanceOf(RequestAction.ForwardModified::class.java, handler.onRequest(request))
+        assertTrue(action.modifiedRequest.toJson().contains("\"hex\":true"))
+    }
+
+    @Test
+    fun `expressions with a format suffix are handled async`() {
+        for (expression in listOf("x,x", "(int)x,b", "/nat c,c")) {
+            val request = EvaluateRequest(seq = 4, expression = expression, context = "hover")
+            assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request), expression)
+        }
+    }
+
+    @Test
+    fun `expressions with an array suffix are handled async`() {
+        for (expression in listOf("buf@16", "self.data,[self.len]", "/nat (char*)p@4,x")) {
+            val request = EvaluateRequest(seq = 4, expression = expression, context = "hover")
+            assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request), expression)
+        }
+    }
+
+    @Test
+    fun `expression that is not simple is forwarded unchanged`() {
+        val request = EvaluateRequest(seq = 4, expression = "(int)x", context = "hover")
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `native and bare variable watches are handled async for the cache`() {
+        for (expression in listOf("x", "/nat sizeof(x)", "(int)x")) {
+            val request = EvaluateRequest(seq = 4, expression = expression, context = "watch")
+            assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request), expression)
+        }
+    }
+
+    @Test
+    fun `copy value is rendered by KDAP and the clipboard context advertised`() {
+        val request = EvaluateRequest(seq = 4, expression = "/nat buffer", context = "clipboard")
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+
+        val initialize = DapResponse(seq = 1, requestSeq = 1, command = "initialize", success = true)
+        val response = assertInstanceOf(DapResponse::class.java, handler.onBackendMessage(initialize).single())
+        assertEquals(true, response.body["supportsClipboardContext"])
+    }
+
+    @Test
+    fun `stops and writes date the watch cache`() {
+        val key = WatchKey("x", frameId = 1, format = null)
+        val response = DapResponse(seq = 0, requestSeq = 0, command = "evaluate", success = true)
+        session.watchResults[key] = WatchResult(response, stop = 0, frameKey = null)
+
+        handler.onBackendMessage(StoppedEvent(seq = 9, reason = "breakpoint", threadId = 1))
+        assertEquals(1, session.watchStop.get())
+        assertTrue(key in session.watchResults, "results of earlier stops are kept for watchRefresh")
+
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(SetVariableRequest(seq = 10)))
+        assertTrue(session.watchResults.isEmpty())
+    }
+
+    @Test
+    fun `repl commands are forwarded unchanged`() {
+        for (expression in listOf("version", "p -x", "frame variable", "p.0 + 1")) {
+            val request = EvaluateRequest(seq = 4, expression = expression, context = "repl")
+            assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(request), expression)
+        }
+    }
+
+    @Test
+    fun `repl meta-commands, expressions, and assignments are handled async`() {
+        for (expression in listOf("`memory read &x", "?v[0] + 1", "?/nat foo(1)", "p.x = 3")) {
+            val request = EvaluateRequest(seq = 4, expression = expression, context = "repl")
+            assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request), expression)
+        }
+    }
+
+    @Test
+    fun `_command context is left to the rewriter`() {
+        val request = EvaluateRequest(seq = 4, expression = "`version", context = "_command")
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `completions for meta-commands and expressions are handled async`() {
+        val json = """{"type":"request","seq":5,"command":"completions","arguments":{"text":"?pt.x","column":6,"frameId":3}}"""
+        val request = assertInstanceOf(CompletionsRequest::class.java, DapMessage.parse(json))
+        assertEquals(CompletionsRequest(seq = 5, text = "?pt.x", column = 6, frameId = 3), request)
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+        assertInstanceOf(RequestAction.HandleAsync::class.java,
+            handler.onRequest(CompletionsRequest(seq = 6, text = "`break", column = 7)))
+        assertInstanceOf(RequestAction.Forward::class.java,
+            handler.onRequest(CompletionsRequest(seq = 7, text = "break", column = 6)))
+    }
+
+    @Test
+    fun `completion context finds the word and its base`() {
+        assertEquals(CompletionContext.Command("breakpoint se", 12, "se"), completionContext("`breakpoint se", 15))
+        assertEquals(CompletionContext.Name(null, 1, "cou"), completionContext("?cou", 5))
+        assertEquals(CompletionContext.Name(SimpleExpr.Field(SimpleExpr.Variable("self"), "origin"), 17, "x"),
+            completionContext("?1 + self.origin.x", 19))
+        assertEquals(CompletionContext.Name(null, 5, ""), completionContext("?a + ", 6))
+        assertNull(completionContext("?std::", 7))
+        assertNull(completionContext("frame variable", 15))
+    }
+}
@@ -0,0 +1,106 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.messages.*
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [ExceptionBreakpointsHandler]. Verifies that KDAP's
+ * exception filters are advertised, that only requests involving them
+ * are taken over, and that panic and crash breakpoint hits are reported
+ * as `exception` stops carrying the panic message from stderr or the
+ * crash filter's description.
+ */
+class ExceptionBreakpointsHandlerTest {
+
+    private val session = DebugSession()
+    private val handler = ExceptionBreakpointsHandler(session)
+
+    @Test
+    fun `initialize response gains KDAP filters after lldb-dap's`() {
+        val response = DapResponse(
+            seq = 1, requestSeq = 1, command = "initialize", success = true,
+            body = mapOf("exceptionBreakpointFilters" to listOf(mapOf("filter" to "cpp_throw"))),
+        )
+        val result = assertInstanceOf(DapResponse::class.java, handler.onBackendMessage(response).single())
+        val filters = (result.body["exceptionBreakpointFilters"] as List<*>).map { (it as Map<*, *>)["filter"] }
+        assertEquals(listOf("cpp_throw", "rust_panic", "crash_abort", "crash_stack_protector", "crash_ubsan",
+            "crash_alloc", "signal_SIGSEGV", "signal_SIGABRT", "signal_all"), filters)
+    }
+
+    @Test
+    fun `lldb-dap filters alone are forwarded`() {
+        val request = SetExceptionBreakpointsRequest(seq = 2, filters = listOf("cpp_throw"))
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `rust_panic filter returns HandleAsync`() {
+        val request = SetExceptionBreakpointsRequest(seq = 2, filters = listOf("cpp_throw", "rust_panic"))
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `signal filter in filterOptions returns HandleAsync`() {
+        val request = SetExceptionBreakpointsRequest(
+            seq = 2, filterOptions = listOf(ExceptionFilterOptions(filterId = "signal_SIGSEGV")),
+        )
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `clearing active KDAP filters returns HandleAsync`() {
+        session.panicBreakpointId = 7
+        val request = SetExceptionBreakpointsRequest(seq = 3)
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `panic breakpoint hit becomes exception stop with panic message`() {
+        session.panicBreakpointId = 7
+        handler.onBackendMessage(OutputEvent(seq = 4, category = "stderr",
+            output = "\nthread 'main' panicked at src/main.rs:3:5:\n"))
+        handler.onBackendMessage(OutputEvent(seq = 5, category = "stderr",
+            output = "boom\nnote: run with `RUST_BACKTRACE=1` environment variable to display a backtrace\n"))
+
+        val result = handler.onBackendMessage(StoppedEvent(seq = 6, reason = "breakpoint",
+            description = "breakpoint 7.1", threadId = 1, hitBreakpointIds = listOf(7)))
+        val stopped = assertInstanceOf(StoppedEvent::class.java, result.single())
+        assertEquals("exception", stopped.reason)
+        assertEquals("thread 'main' panicked at src/main.rs:3:5:\nboom", stopped.description)
+        assertInstanceOf(RequestAction.HandleAsync::class.java,
+            handler.onRequest(ExceptionInfoRequest(seq = 7, threadId = 1)))
+    }
+
+    @Test
+    fun `crash filter returns HandleAsync`() {
+        val request = SetExceptionBreakpointsRequest(seq = 2, filters = listOf("crash_ubsan"))
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `crash breakpoint hit becomes exception stop`() {
+        session.crashBreakpointIds["crash_stack_protector"] = 9
+        val result = handler.onBackendMessage(StoppedEvent(seq = 6, reason = "breakpoint",
+            description = "breakpoint 9.1", threadId = 2, hitBreakpointIds = listOf(9)))
+        val stopped = assertInstanceOf(StoppedEvent::class.java, result.single())
+        assertEquals("exception", stopped.reason)
+        assertEquals("Stack smashing detected", stopped.description)
+        assertInstanceOf(RequestAction.HandleAsync::class.java,
+            handler.onRequest(ExceptionInfoRequest(seq = 7, threadId = 2)))
+        assertInstanceOf(RequestAction.Forward::class.java,
+            handler.onRequest(ExceptionInfoRequest(seq = 8, threadId = 1)))
+    }
+
+    @Test
+    fun `other breakpoint stops are unchanged`() {
+        session.panicBreakpointId = 7
+        val event = StoppedEvent(seq = 6, reason = "breakpoint", description = "breakpoint 1.1",
+            threadId = 1, hitBreakpointIds = listOf(1))
+        assertSame(event, handler.onBackendMessage(event).single())
+        assertInstanceOf(RequestAction.Forward::class.java,
+            handler.onRequest(ExceptionInfoRequest(seq = 7, threadId = 1)))
+    }
+}
@@ -0,0 +1,41 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.EvaluateRequest
+import com.github.jomof.dap.messages.HeapRequest
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [HeapHandler]. Verifies that `kdap/heap` requests parse
+ * with their address, given as a string or a number, and return
+ * [RequestAction.HandleAsync], and that other requests pass through.
+ */
+class HeapHandlerTest {
+
+    private val session = DebugSession()
+    private val handler = HeapHandler(session)
+
+    @Test
+    fun `kdap heap request parses its address`() {
+        val json = """{"type":"request","seq":4,"command":"kdap/heap","arguments":{"address":"0x5555555592a0"}}"""
+        val request = assertInstanceOf(HeapRequest::class.java, DapMessage.parse(json))
+        assertEquals(HeapRequest(seq = 4, address = "0x5555555592a0"), request)
+        assertEquals(request, DapMessage.parse(request.toJson()))
+        val numeric = """{"type":"request","seq":5,"command":"kdap/heap","arguments":{"address":4096,"limit":3}}"""
+        assertEquals(HeapRequest(seq = 5, address = "4096", limit = 3), DapMessage.parse(numeric))
+    }
+
+    @Test
+    fun `kdap heap request returns HandleAsync`() {
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(HeapRequest(seq = 1)))
+    }
+
+    @Test
+    fun `other requests are forwarded unchanged`() {
+        val request = EvaluateRequest(seq = 2, expression = "x", context = "repl")
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(request))
+    }
+}
@@ -0,0 +1,35 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.HotReplaceRequest
+import com.github.jomof.dap.messages.MemoryMapRequest
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [HotReplaceHandler]. Verifies that `kdap/hotReplace`
+ * requests parse and return [RequestAction.HandleAsync], and that other
+ * requests pass through.
+ */
+class HotReplaceHandlerTest {
+
+    private val session = DebugSession()
+    private val handler = HotReplaceHandler(session)
+
+    @Test
+    fun `kdap hotReplace request parses`() {
+        val json = """{"type":"request","seq":4,"command":"kdap/hotReplace",
+            "arguments":{"objectFile":"/work/build/shade.o","functions":["shade"]}}"""
+        val request = assertInstanceOf(HotReplaceRequest::class.java, DapMessage.parse(json))
+        assertEquals(HotReplaceRequest(seq = 4, objectFile = "/work/build/shade.o", functions = listOf("shade")), request)
+        assertEquals(request, DapMessage.parse(request.toJson()))
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `other requests are forwarded unchanged`() {
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(MemoryMapRequest(seq = 2)))
+    }
+}
@@ -2,16 +2,19 @@ package com.github.jomof.dap.interception
 
 import com.github.jomof.dap.DapSession.RequestAction
 import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.messages.DapResponse
 import com.github.jomof.dap.messages.InitializeRequest
 import com.github.jomof.dap.messages.LaunchRequest
 import com.github.jomof.dap.messages.LaunchRequestArguments
+import com.github.jomof.dap.messages.RestartRequest
 import com.github.jomof.dap.messages.SetBreakpointsRequest
 import org.junit.jupiter.api.Assertions.*
 import org.junit.jupiter.api.Test
 
 /**
- * Unit tests for [LaunchHandler]. Verifies that `launch` requests
- * return [RequestAction.HandleAsync] and other requests pass through.
+ * Unit tests for [LaunchHandler]. Verifies that `launch` and `restart`
+ * requests return [RequestAction.HandleAsync], that `supportsRestartRequest`
+ * is advertised, and that other requests pass through.
  */
 class LaunchHandlerTest {
 
@@ -24,6 +27,32 @@ class LaunchHandlerTest {
         assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
     }
 
+    @Test
+    fun `core dump launch returns HandleAsync`() {
+        val request = LaunchRequest(seq = 4, arguments = LaunchRequestArguments(coreDump = "/tmp/core.123"))
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `recorded launch returns HandleAsync`() {
+        val request = LaunchRequest(seq = 6, arguments = LaunchRequestArguments(program = "/bin/ls", record = true))
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `restart request returns HandleAsync`() {
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(RestartRequest(seq = 5)))
+    }
+
+    @Test
+    fun `initialize response advertises restart`() {
+        val response = DapResponse(seq = 1, requestSeq = 1, command = "initialize", success = true,
+            body = mapOf("supportsConfigurationDoneRequest" to true))
+        val result = assertInstanceOf(DapResponse::class.java, handler.onBackendMessage(response).single())
+        assertEquals(true, result.body["supportsRestartRequest"])
+        assertEquals(true, result.body["supportsConfigurationDoneRequest"])
+    }
+
     @Test
     fun `initialize request is forwarded unchanged`() {
         val request = InitializeRequest(seq = 2)
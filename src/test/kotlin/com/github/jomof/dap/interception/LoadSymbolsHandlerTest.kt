@@ -0,0 +1,40 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.LoadSymbolsRequest
+import com.github.jomof.dap.messages.ModulesRequest
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [LoadSymbolsHandler]. Verifies that `kdap/loadSymbols`
+ * requests parse with their module and symbol file and return
+ * [RequestAction.HandleAsync], and that other requests pass through.
+ */
+class LoadSymbolsHandlerTest {
+
+    private val session = DebugSession()
+    private val handler = LoadSymbolsHandler(session)
+
+    @Test
+    fun `kdap loadSymbols request parses its module and path`() {
+        val json = """{"type":"request","seq":3,"command":"kdap/loadSymbols",""" +
+            """"arguments":{"module":"libvendor.so","path":"/opt/vendor/libvendor.so.debug"}}"""
+        val request = assertInstanceOf(LoadSymbolsRequest::class.java, DapMessage.parse(json))
+        assertEquals(LoadSymbolsRequest(seq = 3, module = "libvendor.so", path = "/opt/vendor/libvendor.so.debug"), request)
+        assertEquals(request, DapMessage.parse(request.toJson()))
+    }
+
+    @Test
+    fun `kdap loadSymbols request returns HandleAsync`() {
+        val request = LoadSymbolsRequest(seq = 1, module = "libvendor.so", path = "/tmp/libvendor.debug")
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `other requests are forwarded unchanged`() {
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(ModulesRequest(seq = 2)))
+    }
+}
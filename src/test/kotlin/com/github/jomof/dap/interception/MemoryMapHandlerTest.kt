@@ -0,0 +1,34 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.EvaluateRequest
+import com.github.jomof.dap.messages.MemoryMapRequest
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [MemoryMapHandler]. Verifies that `kdap/memoryMap`
+ * requests parse and return [RequestAction.HandleAsync], and that other
+ * requests pass through.
+ */
+class MemoryMapHandlerTest {
+
+    private val session = DebugSession()
+    private val handler = MemoryMapHandler(session)
+
+    @Test
+    fun `kdap memoryMap request parses`() {
+        val json = """{"type":"request","seq":4,"command":"kdap/memoryMap"}"""
+        val request = assertInstanceOf(MemoryMapRequest::class.java, DapMessage.parse(json))
+        assertEquals(request, DapMessage.parse(request.toJson()))
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `other requests are forwarded unchanged`() {
+        val request = EvaluateRequest(seq = 2, expression = "`mappings", context = "repl")
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(request))
+    }
+}
@@ -0,0 +1,41 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.EvaluateRequest
+import com.github.jomof.dap.messages.SearchMemoryRequest
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [MemorySearchHandler]. Verifies that
+ * `kdap/searchMemory` requests parse with their pattern and return a
+ * cancellable [RequestAction.HandleAsync], and that other requests pass
+ * through.
+ */
+class MemorySearchHandlerTest {
+
+    private val session = DebugSession()
+    private val handler = MemorySearchHandler(session)
+
+    @Test
+    fun `kdap searchMemory request parses its pattern`() {
+        val json = """{"type":"request","seq":4,"command":"kdap/searchMemory","arguments":{"string":"needle","maxResults":5}}"""
+        val request = assertInstanceOf(SearchMemoryRequest::class.java, DapMessage.parse(json))
+        assertEquals(SearchMemoryRequest(seq = 4, string = "needle", maxResults = 5), request)
+        assertEquals(request, DapMessage.parse(request.toJson()))
+    }
+
+    @Test
+    fun `kdap searchMemory request returns a cancellable HandleAsync`() {
+        val request = SearchMemoryRequest(seq = 1, bytes = "de ad be ef")
+        assertTrue(assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request)).cancellable)
+    }
+
+    @Test
+    fun `other requests are forwarded unchanged`() {
+        val request = EvaluateRequest(seq = 2, expression = "x", context = "repl")
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(request))
+    }
+}
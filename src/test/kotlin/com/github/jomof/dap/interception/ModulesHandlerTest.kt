@@ -0,0 +1,82 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.MODULE_EVENT_MARKER
+import com.github.jomof.dap.debugsession.SYMBOL_STATUS_MARKER
+import com.github.jomof.dap.messages.*
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [ModulesHandler]. Verifies that `modules` and
+ * `loadedSources` return [RequestAction.HandleAsync], that the module
+ * listener's output becomes `module` events while lldb-dap's are
+ * dropped, that the index warm-up's output becomes `kdap/symbolStatus`
+ * events, and that `supportsLoadedSourcesRequest` is advertised.
+ */
+class ModulesHandlerTest {
+
+    private val session = DebugSession()
+    private val handler = ModulesHandler(session)
+
+    private val module = """{"id":"1a2b","name":"libc.so.6","path":"/lib/libc.so.6","addressRange":"0x7f0000000000"}"""
+
+    @Test
+    fun `modules and loadedSources return HandleAsync`() {
+        val json = """{"type":"request","seq":3,"command":"modules","arguments":{"startModule":2,"moduleCount":5}}"""
+        val request = assertInstanceOf(ModulesRequest::class.java, DapMessage.parse(json))
+        assertEquals(ModulesRequest(seq = 3, startModule = 2, moduleCount = 5), request)
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(LoadedSourcesRequest(seq = 4)))
+    }
+
+    @Test
+    fun `listener output becomes module events`() {
+        val output = OutputEvent(seq = 5, category = "console",
+            output = "before\n$MODULE_EVENT_MARKER{\"reason\":\"new\",\"module\":$module}\nafter\n")
+        val messages = handler.onBackendMessage(output)
+        assertEquals(3, messages.size)
+        assertEquals(output.copy(output = "before\n"), messages[0])
+        val event = assertInstanceOf(ModuleEvent::class.java, messages[1])
+        assertEquals("new", event.reason)
+        assertEquals("/lib/libc.so.6", event.module["path"])
+        assertEquals("0x7f0000000000", JSONObject(event.toJson()).getJSONObject("body")
+            .getJSONObject("module").getString("addressRange"))
+        assertEquals(output.copy(output = "after\n"), messages[2])
+    }
+
+    @Test
+    fun `index warm-up output becomes symbolStatus events`() {
+        val status = """{"state":"indexed","module":"libc.so.6","modulesIndexed":2,"modulesTotal":5}"""
+        val output = OutputEvent(seq = 5, category = "console",
+            output = "$MODULE_EVENT_MARKER{\"reason\":\"new\",\"module\":$module}\n$SYMBOL_STATUS_MARKER$status\nafter\n")
+        val messages = handler.onBackendMessage(output)
+        assertEquals(3, messages.size)
+        assertInstanceOf(ModuleEvent::class.java, messages[0])
+        val event = assertInstanceOf(SymbolStatusEvent::class.java, messages[1])
+        assertEquals(SymbolStatusEvent(0, "indexed", "libc.so.6", 2, 5), event)
+        val body = JSONObject(event.toJson()).getJSONObject("body")
+        assertEquals("kdap/symbolStatus", JSONObject(event.toJson()).getString("event"))
+        assertEquals(5, body.getInt("modulesTotal"))
+        assertEquals(output.copy(output = "after\n"), messages[2])
+    }
+
+    @Test
+    fun `lldb-dap module events are dropped`() {
+        val json = """{"type":"event","seq":6,"event":"module","body":{"reason":"new","module":$module}}"""
+        val event = assertInstanceOf(ModuleEvent::class.java, DapMessage.parse(json))
+        assertEquals("libc.so.6", event.module["name"])
+        assertTrue(handler.onBackendMessage(event).isEmpty())
+    }
+
+    @Test
+    fun `initialize response advertises loadedSources`() {
+        val response = DapResponse(seq = 1, requestSeq = 1, command = "initialize", success = true,
+            body = mapOf("supportsModulesRequest" to true))
+        val result = assertInstanceOf(DapResponse::class.java, handler.onBackendMessage(response).single())
+        assertEquals(true, result.body["supportsLoadedSourcesRequest"])
+        assertEquals(true, result.body["supportsModulesRequest"])
+    }
+}
@@ -0,0 +1,59 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.LOGPOINT_MARKER
+import com.github.jomof.dap.debugsession.PTY_OUTPUT_MARKER
+import com.github.jomof.dap.messages.*
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [OutputCategoryNormalizer]. Verifies that debuggee
+ * output is reported as `stdout` and adapter output as `console`.
+ */
+class OutputCategoryNormalizerTest {
+
+    private val session = DebugSession()
+    private val handler = OutputCategoryNormalizer(session)
+
+    private fun normalize(category: String, output: String): OutputEvent =
+        assertInstanceOf(
+            OutputEvent::class.java,
+            handler.onBackendMessage(OutputEvent(seq = 1, category = category, output = output)).single(),
+        )
+
+    @Test
+    fun `console output before launch stays console`() {
+        val event = normalize("console", "Launching: a.out\n")
+        assertEquals("console", event.category)
+    }
+
+    @Test
+    fun `console output while running becomes stdout`() {
+        session.processRunning = true
+        val event = normalize("console", "hello\n")
+        assertEquals("stdout", event.category)
+    }
+
+    @Test
+    fun `exit status stays console`() {
+        session.processRunning = true
+        val event = normalize("console", "Process 42 exited with status = 0 (0x00000000)\n")
+        assertEquals("console", event.category)
+    }
+
+    @Test
+    fun `logpoint output is console without the marker`() {
+        session.processRunning = true
+        val event = normalize("console", "${LOGPOINT_MARKER}x = 1\n")
+        assertEquals("console", event.category)
+        assertEquals("x = 1\n", event.output)
+    }
+
+    @Test
+    fun `pty output is stdout without markers and keeps carriage returns`() {
+        val event = normalize("console", "${PTY_OUTPUT_MARKER}10%\r${PTY_OUTPUT_MARKER}20%\r")
+        assertEquals("stdout", event.category)
+        assertEquals("10%\r20%\r", event.output)
+    }
+}
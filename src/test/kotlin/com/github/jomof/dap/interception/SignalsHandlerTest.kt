@@ -0,0 +1,40 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.EvaluateRequest
+import com.github.jomof.dap.messages.SignalsRequest
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [SignalsHandler]. Verifies that `kdap/signals` requests
+ * parse with their dispositions and return [RequestAction.HandleAsync],
+ * and that other requests pass through.
+ */
+class SignalsHandlerTest {
+
+    private val session = DebugSession()
+    private val handler = SignalsHandler(session)
+
+    @Test
+    fun `kdap signals request parses its dispositions`() {
+        val json = """{"type":"request","seq":4,"command":"kdap/signals","arguments":{"signals":{"SIGUSR1":"pass"}}}"""
+        val request = assertInstanceOf(SignalsRequest::class.java, DapMessage.parse(json))
+        assertEquals(mapOf("SIGUSR1" to "pass"), request.signals)
+        assertEquals(request, DapMessage.parse(request.toJson()))
+    }
+
+    @Test
+    fun `kdap signals request returns HandleAsync`() {
+        val request = SignalsRequest(seq = 1, signals = mapOf("SIGALRM" to "ignore"))
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `other requests are forwarded unchanged`() {
+        val request = EvaluateRequest(seq = 2, expression = "x", context = "repl")
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(request))
+    }
+}
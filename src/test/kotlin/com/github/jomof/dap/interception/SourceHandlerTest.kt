@@ -0,0 +1,47 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DISASSEMBLY_SOURCE_REFERENCE_BASE
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.DisassembledRange
+import com.github.jomof.dap.debugsession.FETCHED_SOURCE_REFERENCE_BASE
+import com.github.jomof.dap.debugsession.FetchedSource
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.SourceRequest
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [SourceHandler]. Verifies that `source` for a fetched
+ * or disassembly source returns [RequestAction.HandleAsync], and that
+ * other references are forwarded to lldb-dap.
+ */
+class SourceHandlerTest {
+
+    private val session = DebugSession()
+    private val handler = SourceHandler(session)
+
+    @Test
+    fun `source reference is parsed from the source or the arguments`() {
+        val json = """{"type":"request","seq":1,"command":"source","arguments":{"source":{"sourceReference":7},"sourceReference":7}}"""
+        assertEquals(SourceRequest(seq = 1, sourceReference = 7), DapMessage.parse(json))
+        val legacy = """{"type":"request","seq":2,"command":"source","arguments":{"sourceReference":8}}"""
+        assertEquals(SourceRequest(seq = 2, sourceReference = 8), DapMessage.parse(legacy))
+    }
+
+    @Test
+    fun `fetched sources are handled and others forwarded`() {
+        session.fetchedSources[FETCHED_SOURCE_REFERENCE_BASE] = FetchedSource("/rustc/x/library/core/src/lib.rs", null)
+        assertInstanceOf(RequestAction.HandleAsync::class.java,
+            handler.onRequest(SourceRequest(seq = 1, sourceReference = FETCHED_SOURCE_REFERENCE_BASE)))
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(SourceRequest(seq = 2, sourceReference = 3)))
+    }
+
+    @Test
+    fun `disassembly sources are handled`() {
+        session.disassemblySources[DISASSEMBLY_SOURCE_REFERENCE_BASE] =
+            DisassembledRange("memcpy", "libc.so.6", start = 0x1000, end = 0x1010, instructions = emptyList())
+        assertInstanceOf(RequestAction.HandleAsync::class.java,
+            handler.onRequest(SourceRequest(seq = 1, sourceReference = DISASSEMBLY_SOURCE_REFERENCE_BASE)))
+    }
+}
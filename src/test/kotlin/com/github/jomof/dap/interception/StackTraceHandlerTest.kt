@@ -0,0 +1,34 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.taskThreadId
+import com.github.jomof.dap.messages.ScopesRequest
+import com.github.jomof.dap.messages.StackTraceRequest
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [StackTraceHandler]. Verifies that `stackTrace` for OS
+ * threads returns [RequestAction.HandleAsync], and that tokio task
+ * pseudo-threads and other requests are left alone.
+ */
+class StackTraceHandlerTest {
+
+    private val session = DebugSession()
+    private val handler = StackTraceHandler(session)
+
+    @Test
+    fun `stackTrace for a thread returns HandleAsync`() {
+        assertInstanceOf(RequestAction.HandleAsync::class.java,
+            handler.onRequest(StackTraceRequest(seq = 1, threadId = 1, startFrame = 0)))
+    }
+
+    @Test
+    fun `task threads and other requests are forwarded`() {
+        session.tokioTasks = true
+        assertInstanceOf(RequestAction.Forward::class.java,
+            handler.onRequest(StackTraceRequest(seq = 2, threadId = taskThreadId(7)!!)))
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(ScopesRequest(seq = 3, frameId = 1)))
+    }
+}
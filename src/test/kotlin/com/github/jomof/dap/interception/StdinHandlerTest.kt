@@ -0,0 +1,40 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.messages.DapMessage
+import com.github.jomof.dap.messages.EvaluateRequest
+import com.github.jomof.dap.messages.StdinRequest
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [StdinHandler]. Verifies that `kdap/stdin` requests
+ * parse with their text and return [RequestAction.HandleAsync], and that
+ * other requests pass through.
+ */
+class StdinHandlerTest {
+
+    private val session = DebugSession()
+    private val handler = StdinHandler(session)
+
+    @Test
+    fun `kdap stdin request parses its text`() {
+        val json = """{"type":"request","seq":4,"command":"kdap/stdin","arguments":{"text":"42\n"}}"""
+        val request = assertInstanceOf(StdinRequest::class.java, DapMessage.parse(json))
+        assertEquals("42\n", request.text)
+        assertEquals(request, DapMessage.parse(request.toJson()))
+    }
+
+    @Test
+    fun `kdap stdin request returns HandleAsync`() {
+        val request = StdinRequest(seq = 1, text = "yes\n")
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `other requests are forwarded unchanged`() {
+        val request = EvaluateRequest(seq = 2, expression = "x", context = "repl")
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(request))
+    }
+}
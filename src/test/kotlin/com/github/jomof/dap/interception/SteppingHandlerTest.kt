@@ -0,0 +1,363 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.AsyncStep
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.GotoTarget
+import com.github.jomof.dap.debugsession.PendingPause
+import com.github.jomof.dap.debugsession.RecordingDebugger
+import com.github.jomof.dap.debugsession.SkippedStepIn
+import com.github.jomof.dap.debugsession.StepBack
+import com.github.jomof.dap.debugsession.StepInTarget
+import com.github.jomof.dap.debugsession.addStepBackCapability
+import com.github.jomof.dap.debugsession.externalCodePaths
+import com.github.jomof.dap.debugsession.gotoTargetLabel
+import com.github.jomof.dap.debugsession.initJustMyCode
+import com.github.jomof.dap.debugsession.isAsyncBody
+import com.github.jomof.dap.debugsession.parseGotoTargets
+import com.github.jomof.dap.debugsession.parseStepInTargets
+import com.github.jomof.dap.debugsession.pauseThread
+import com.github.jomof.dap.messages.*
+import kotlinx.coroutines.runBlocking
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [SteppingHandler]. Verifies that line steps become
+ * instruction steps only for threads whose top frame has no source, that
+ * explicit granularity passes through, that single-thread `continue`
+ * is run by KDAP and reported as such, that `next` in Rust async
+ * bodies is run by KDAP and ends with a `step` stop, that step-in
+ * targets, `goto`, and `restartFrame` are handled by KDAP, and that
+ * `reverseContinue` and `stepBack` are run by KDAP, a step back ending
+ * with a `step` stop, that just-my-code `stepIn` and
+ * `kdap/stepIntoSkipped` are run by KDAP once `justMyCode` is set up,
+ * and that the stop a `pause` ends with is reported as a `pause` on a
+ * known thread.
+ */
+class SteppingHandlerTest {
+
+    private val session = DebugSession()
+    private val handler = SteppingHandler(session)
+
+    private fun stackTrace(requestSeq: Int, threadId: Int, topFrame: Map<String, Any?>) {
+        handler.onRequest(StackTraceRequest(seq = requestSeq, threadId = threadId, startFrame = 0))
+        handler.onBackendMessage(DapResponse(
+            seq = 1, requestSeq = requestSeq, command = "stackTrace", success = true,
+            body = mapOf("stackFrames" to listOf(topFrame)),
+        ))
+    }
+
+    private val sourceFrame = mapOf("id" to 1, "line" to 12, "source" to mapOf("path" to "/src/main.rs"))
+    private val asyncFrame = mapOf(
+        "id" to 1, "name" to "rust_debuggee::fetch::{async_fn#0}", "line" to 30,
+        "source" to mapOf("path" to "/src/main.rs"),
+    )
+    private val disassemblyFrame = mapOf("id" to 1, "line" to 0, "source" to mapOf("name" to "memcpy", "sourceReference" to 5))
+
+    @Test
+    fun `next in a frame with source is forwarded unchanged`() {
+        stackTrace(requestSeq = 10, threadId = 1, topFrame = sourceFrame)
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(NextRequest(seq = 11, threadId = 1)))
+    }
+
+    @Test
+    fun `next in a frame without source steps by instruction`() {
+        stackTrace(requestSeq = 10, threadId = 1, topFrame = disassemblyFrame)
+        val action = assertInstanceOf(RequestAction.ForwardModified::class.java,
+            handler.onRequest(NextRequest(seq = 11, threadId = 1)))
+        val next = assertInstanceOf(NextRequest::class.java, action.modifiedRequest)
+        assertEquals("instruction", next.granularity)
+        assertEquals(11, next.seq)
+    }
+
+    @Test
+    fun `stepIn in a frame without source steps by instruction`() {
+        stackTrace(requestSeq = 10, threadId = 1, topFrame = disassemblyFrame)
+        val action = assertInstanceOf(RequestAction.ForwardModified::class.java,
+            handler.onRequest(StepInRequest(seq = 11, threadId = 1, granularity = "line")))
+        assertEquals("instruction", (action.modifiedRequest as StepInRequest).granularity)
+    }
+
+    @Test
+    fun `paged stackTrace is forwarded and later pages leave the top frame alone`() {
+        stackTrace(requestSeq = 10, threadId = 1, topFrame = sourceFrame)
+        val page = StackTraceRequest(seq = 12, threadId = 1, startFrame = 20, levels = 20)
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(page))
+        handler.onBackendMessage(DapResponse(
+            seq = 2, requestSeq = 12, command = "stackTrace", success = true,
+            body = mapOf("stackFrames" to listOf(disassemblyFrame), "totalFrames" to 60),
+        ))
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(NextRequest(seq = 13, threadId = 1)))
+    }
+
+    @Test
+    fun `explicit instruction granularity is forwarded unchanged`() {
+        stackTrace(requestSeq = 10, threadId = 1, topFrame = disassemblyFrame)
+        assertInstanceOf(RequestAction.Forward::class.java,
+            handler.onRequest(NextRequest(seq = 11, threadId = 1, granularity = "instruction")))
+    }
+
+    @Test
+    fun `other threads are unaffected`() {
+        stackTrace(requestSeq = 10, threadId = 1, topFrame = disassemblyFrame)
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(NextRequest(seq = 11, threadId = 2)))
+    }
+
+    @Test
+    fun `stop forgets frame state`() {
+        stackTrace(requestSeq = 10, threadId = 1, topFrame = disassemblyFrame)
+        handler.onBackendMessage(StoppedEvent(seq = 12, reason = "step", threadId = 1))
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(NextRequest(seq = 13, threadId = 1)))
+    }
+
+    @Test
+    fun `single-thread continue is handled async`() {
+        assertInstanceOf(RequestAction.HandleAsync::class.java,
+            handler.onRequest(ContinueRequest(seq = 14, threadId = 2, singleThread = true)))
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(ContinueRequest(seq = 15, threadId = 2)))
+    }
+
+    @Test
+    fun `resume requests release suspended threads first`() {
+        session.suspendedThreads.add(3)
+        for (request in listOf(ContinueRequest(seq = 16), NextRequest(seq = 17, threadId = 1), StepOutRequest(seq = 18, threadId = 1))) {
+            assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+        }
+    }
+
+    @Test
+    fun `continued event covers only the single running thread`() {
+        session.singleThreadContinue = 2
+        val result = handler.onBackendMessage(ContinuedEvent(seq = 19, threadId = 1, allThreadsContinued = true))
+        assertEquals(ContinuedEvent(seq = 19, threadId = 2, allThreadsContinued = false), result.single())
+
+        handler.onBackendMessage(StoppedEvent(seq = 20, reason = "breakpoint", threadId = 2))
+        val later = ContinuedEvent(seq = 21, threadId = 1, allThreadsContinued = true)
+        assertEquals(later, handler.onBackendMessage(later).single())
+    }
+
+    @Test
+    fun `async body names are recognized`() {
+        assertTrue(isAsyncBody("rust_debuggee::fetch::{async_fn#0}"))
+        assertTrue(isAsyncBody("rust_debuggee::main::{async_block#1}"))
+        assertTrue(isAsyncBody("rust_debuggee::fetch::{async_fn#0}<i32>"))
+        assertFalse(isAsyncBody("rust_debuggee::fetch::{async_fn#0}::{closure#0}"))
+        assertFalse(isAsyncBody("rust_debuggee::fetch"))
+    }
+
+    @Test
+    fun `next in an async body is handled async`() {
+        stackTrace(requestSeq = 10, threadId = 1, topFrame = asyncFrame)
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(NextRequest(seq = 11, threadId = 1)))
+        assertInstanceOf(RequestAction.Forward::class.java,
+            handler.onRequest(NextRequest(seq = 12, threadId = 1, granularity = "instruction")))
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(StepInRequest(seq = 13, threadId = 1)))
+    }
+
+    @Test
+    fun `stop at an async step breakpoint is reported as a step`() {
+        session.asyncStep = AsyncStep(threadId = 1, breakpointIds = setOf(41, 42))
+        val stop = StoppedEvent(seq = 22, reason = "breakpoint", description = "breakpoint 41.1", threadId = 1, hitBreakpointIds = listOf(41))
+        assertEquals(StoppedEvent(seq = 22, reason = "step", threadId = 1), handler.onBackendMessage(stop).single())
+
+        val userBreakpoint = stop.copy(hitBreakpointIds = listOf(3))
+        assertEquals(userBreakpoint, handler.onBackendMessage(userBreakpoint).single())
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(ContinueRequest(seq = 23)))
+    }
+
+    @Test
+    fun `async step resumed on another thread is reported as a step on that thread`() {
+        session.asyncStep = AsyncStep(threadId = 1, breakpointIds = setOf(41, 42))
+        val stop = StoppedEvent(seq = 22, reason = "breakpoint", description = "breakpoint 42.1", threadId = 3, hitBreakpointIds = listOf(42))
+        assertEquals(StoppedEvent(seq = 22, reason = "step", threadId = 3), handler.onBackendMessage(stop).single())
+
+        val pause = StoppedEvent(seq = 23, reason = "exception", description = "signal SIGSTOP", threadId = 1)
+        assertEquals("exception", assertInstanceOf(StoppedEvent::class.java, handler.onBackendMessage(pause).single()).reason)
+    }
+
+    @Test
+    fun `stepInTargets is handled async`() {
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(StepInTargetsRequest(seq = 24, frameId = 524288)))
+    }
+
+    @Test
+    fun `stepIn to a listed target is handled async until the next stop`() {
+        session.stepInTargets[7] = StepInTarget(address = 0x401136, size = 5, label = "app::Point::scale", line = 12, column = 21)
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(StepInRequest(seq = 25, threadId = 1, targetId = 7)))
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(StepInRequest(seq = 26, threadId = 1, targetId = 8)))
+
+        handler.onBackendMessage(StoppedEvent(seq = 27, reason = "step", threadId = 1))
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(StepInRequest(seq = 28, threadId = 1, targetId = 7)))
+    }
+
+    @Test
+    fun `step-in target list is parsed`() {
+        val json = """[{"address": 4198710, "size": 5, "label": "app::Point::scale", "line": 12, "column": 21}]"""
+        assertEquals(listOf(StepInTarget(4198710, 5, "app::Point::scale", 12, 21)), parseStepInTargets(json))
+    }
+
+    @Test
+    fun `initialize response advertises stepInTargets, gotoTargets, and restartFrame`() {
+        val response = DapResponse(seq = 1, requestSeq = 1, command = "initialize", success = true,
+            body = mapOf("supportsSteppingGranularity" to true))
+        val result = assertInstanceOf(DapResponse::class.java, handler.onBackendMessage(response).single())
+        assertEquals(true, result.body["supportsStepInTargetsRequest"])
+        assertEquals(true, result.body["supportsGotoTargetsRequest"])
+        assertEquals(true, result.body["supportsRestartFrame"])
+        assertEquals(true, result.body["supportsSteppingGranularity"])
+    }
+
+    @Test
+    fun `gotoTargets and goto are handled async`() {
+        assertInstanceOf(RequestAction.HandleAsync::class.java,
+            handler.onRequest(GotoTargetsRequest(seq = 29, source = Source(path = "/src/main.rs"), line = 14)))
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(GotoRequest(seq = 30, threadId = 1, targetId = 1)))
+    }
+
+    @Test
+    fun `goto targets are parsed, labeled, and forgotten on stop`() {
+        val json = """[{"address": 4198720, "line": 14, "column": 0, "function": 4198656},
+            {"address": 4198736, "line": 14, "column": 9, "function": 4198656}]"""
+        val (line, column) = parseGotoTargets(json)
+        assertEquals(GotoTarget(4198736, 14, 9, 4198656), column)
+        assertEquals("Line 14", gotoTargetLabel(line))
+        assertEquals("Line 14, column 9", gotoTargetLabel(column))
+
+        session.gotoTargets[1] = line
+        handler.onBackendMessage(StoppedEvent(seq = 31, reason = "breakpoint", threadId = 1))
+        assertTrue(session.gotoTargets.isEmpty())
+    }
+
+    @Test
+    fun `restartFrame is parsed and handled async`() {
+        val json = """{"type":"request","seq":32,"command":"restartFrame","arguments":{"frameId":524290}}"""
+        val request = assertInstanceOf(RestartFrameRequest::class.java, DapMessage.parse(json))
+        assertEquals(524290, request.frameId)
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `reverseContinue and stepBack are parsed and handled async`() {
+        val json = """{"type":"request","seq":33,"command":"stepBack","arguments":{"threadId":7,"granularity":"line"}}"""
+        val request = assertInstanceOf(StepBackRequest::class.java, DapMessage.parse(json))
+        assertEquals(StepBackRequest(seq = 33, threadId = 7, granularity = "line"), request)
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(ReverseContinueRequest(seq = 34, threadId = 7)))
+    }
+
+    @Test
+    fun `stop at a step back breakpoint is reported as a step`() {
+        session.stepBack = StepBack(threadId = 7, breakpointIds = setOf(4, 5))
+        val stepped = handler.onBackendMessage(
+            StoppedEvent(seq = 35, reason = "breakpoint", threadId = 7, hitBreakpointIds = listOf(5))).single()
+        assertEquals(StoppedEvent(seq = 35, reason = "step", threadId = 7), stepped)
+
+        val user = StoppedEvent(seq = 36, reason = "breakpoint", threadId = 7, hitBreakpointIds = listOf(1))
+        assertEquals(user, handler.onBackendMessage(user).single())
+    }
+
+    @Test
+    fun `stepIn is run by KDAP only with just my code on`() {
+        stackTrace(requestSeq = 10, threadId = 1, topFrame = sourceFrame)
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(StepInRequest(seq = 11, threadId = 1)))
+        session.justMyCode = true
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(StepInRequest(seq = 12, threadId = 1)))
+        assertInstanceOf(RequestAction.Forward::class.java,
+            handler.onRequest(StepInRequest(seq = 13, threadId = 1, granularity = "instruction")))
+
+        stackTrace(requestSeq = 14, threadId = 2, topFrame = disassemblyFrame)
+        assertInstanceOf(RequestAction.ForwardModified::class.java, handler.onRequest(StepInRequest(seq = 15, threadId = 2)))
+    }
+
+    @Test
+    fun `stepIntoSkipped is parsed, handled async, and its stop reported as a step`() {
+        val json = """{"type":"request","seq":40,"command":"kdap/stepIntoSkipped","arguments":{"threadId":7}}"""
+        val request = assertInstanceOf(StepIntoSkippedRequest::class.java, DapMessage.parse(json))
+        assertEquals(StepIntoSkippedRequest(seq = 40, threadId = 7), request)
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+
+        session.skippedStepIn = SkippedStepIn(threadId = 7, breakpointId = 9)
+        val stepped = handler.onBackendMessage(
+            StoppedEvent(seq = 41, reason = "breakpoint", threadId = 7, hitBreakpointIds = listOf(9))).single()
+        assertEquals(StoppedEvent(seq = 41, reason = "step", threadId = 7), stepped)
+        val user = StoppedEvent(seq = 42, reason = "breakpoint", threadId = 7, hitBreakpointIds = listOf(9, 1))
+        assertEquals(user, handler.onBackendMessage(user).single())
+    }
+
+    @Test
+    fun `external code is Cargo's registry and git checkouts and the configured directories`() {
+        assertEquals(
+            listOf("/home/u/.cargo/registry/", "/home/u/.cargo/git/", "/work/vendor/"),
+            externalCodePaths(listOf("/work/vendor"), emptyMap(), "/home/u"),
+        )
+        assertEquals(
+            listOf("C:/cargo/registry/", "C:/cargo/git/"),
+            externalCodePaths(null, mapOf("CARGO_HOME" to "C:\\cargo\\"), "C:\\Users\\u"),
+        )
+    }
+
+    private fun threads(vararg ids: Int) {
+        handler.onBackendMessage(DapResponse(
+            seq = 1, requestSeq = 1, command = "threads", success = true,
+            body = mapOf("threads" to ids.map { mapOf("id" to it, "name" to "thread $it") }),
+        ))
+    }
+
+    @Test
+    fun `pause is run by KDAP and its stop is reported as a pause`() {
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(PauseRequest(seq = 50)))
+        threads(1, 2)
+        handler.onBackendMessage(StoppedEvent(seq = 51, reason = "breakpoint", threadId = 2, hitBreakpointIds = listOf(1)))
+        assertEquals(2, session.lastStoppedThreadId)
+
+        val pause = PendingPause(session.lastStoppedThreadId)
+        session.pendingPause = pause
+        val stopped = handler.onBackendMessage(StoppedEvent(
+            seq = 52, reason = "exception", description = "signal SIGSTOP", threadId = 1, allThreadsStopped = true,
+        )).single()
+        assertEquals(StoppedEvent(seq = 52, reason = "pause", threadId = 2, allThreadsStopped = true), stopped)
+        assertTrue(pause.stopped.isCompleted)
+        assertNull(session.pendingPause)
+        assertTrue(session.pausedStop)
+    }
+
+    @Test
+    fun `stops other than the interrupt end a pause unchanged`() {
+        session.pendingPause = PendingPause(focusThreadId = null)
+        val breakpoint = StoppedEvent(seq = 53, reason = "breakpoint", description = "breakpoint 1.1", threadId = 1)
+        assertEquals(breakpoint, handler.onBackendMessage(breakpoint).single())
+        assertNull(session.pendingPause)
+        assertFalse(session.pausedStop)
+
+        val signal = StoppedEvent(seq = 54, reason = "exception", description = "signal SIGSTOP", threadId = 1)
+        assertEquals(signal, handler.onBackendMessage(signal).single())
+
+        session.pendingPause = PendingPause(focusThreadId = null)
+        handler.onBackendMessage(ContinuedEvent(seq = 55, threadId = 1, allThreadsContinued = true))
+        assertNull(session.pendingPause)
+    }
+
+    @Test
+    fun `a pause is reported on the focused thread if it still exists`() {
+        assertEquals(2, pauseThread(stopped = 1, focus = 2, known = setOf(1, 2)))
+        assertEquals(1, pauseThread(stopped = 1, focus = 3, known = setOf(1, 2)))
+        assertEquals(4, pauseThread(stopped = 9, focus = null, known = setOf(5, 4)))
+        assertEquals(9, pauseThread(stopped = 9, focus = 2, known = emptySet()))
+    }
+
+    @Test
+    fun `just my code is set up with the configured external code`() = runBlocking {
+        val debugger = RecordingDebugger()
+        session.initJustMyCode(listOf("/work/vendor"), debugger)
+        assertTrue(session.justMyCode)
+        assertTrue("/work/vendor/" in debugger.commands.last(), debugger.commands.last())
+    }
+
+    @Test
+    fun `stepBack is advertised only with rr`() {
+        val response = DapResponse(seq = 1, requestSeq = 1, command = "initialize", success = true)
+        assertEquals(true, addStepBackCapability(response, rrAvailable = true).body["supportsStepBack"])
+        assertNull(addStepBackCapability(response, rrAvailable = false).body["supportsStepBack"])
+    }
+}
@@ -0,0 +1,69 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.THREAD_EVENT_MARKER
+import com.github.jomof.dap.debugsession.nameThreads
+import com.github.jomof.dap.debugsession.threadLabel
+import com.github.jomof.dap.messages.*
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [ThreadsHandler]. Verifies that `threads` returns
+ * [RequestAction.HandleAsync], that threads are named like CodeLLDB's,
+ * and that the thread listener's output becomes `thread` events while
+ * lldb-dap's are dropped.
+ */
+class ThreadsHandlerTest {
+
+    private val session = DebugSession()
+    private val handler = ThreadsHandler(session)
+
+    @Test
+    fun `threads returns HandleAsync`() {
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(ThreadsRequest(seq = 1)))
+        session.tokioTasks = true
+        assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(ThreadsRequest(seq = 2)))
+    }
+
+    @Test
+    fun `threads are labelled with index, tid, and name`() {
+        assertEquals("1: tid=4242", threadLabel(1, 4242, null))
+        assertEquals("2: tid=4243 \"worker-1\"", threadLabel(2, 4243, "worker-1"))
+        val threads = listOf(
+            mapOf("id" to 4242, "name" to "Thread #1"),
+            mapOf("id" to 4243, "name" to "Thread #2"),
+            mapOf("id" to 4244, "name" to "Thread #3"),
+        )
+        val names = JSONObject("""{"4242":{"index":1,"name":"spawn"},"4243":{"index":2,"name":null}}""")
+        assertEquals(listOf("1: tid=4242 \"spawn\"", "2: tid=4243", "Thread #3"),
+            nameThreads(threads, names).map { (it as Map<*, *>)["name"] })
+    }
+
+    @Test
+    fun `listener output becomes thread events`() {
+        val output = OutputEvent(seq = 5, category = "console",
+            output = "before\n$THREAD_EVENT_MARKER{\"reason\":\"started\",\"threadId\":4243}\n" +
+                "$THREAD_EVENT_MARKER{\"reason\":\"exited\",\"threadId\":4244}\nafter\n")
+        val messages = handler.onBackendMessage(output)
+        assertEquals(listOf(
+            output.copy(output = "before\n"),
+            ThreadEvent(seq = 0, reason = "started", threadId = 4243),
+            ThreadEvent(seq = 0, reason = "exited", threadId = 4244),
+            output.copy(output = "after\n"),
+        ), messages)
+        val body = JSONObject(messages[1].toJson()).getJSONObject("body")
+        assertEquals("started", body.getString("reason"))
+        assertEquals(4243, body.getInt("threadId"))
+    }
+
+    @Test
+    fun `lldb-dap thread events are dropped`() {
+        val json = """{"type":"event","seq":6,"event":"thread","body":{"reason":"exited","threadId":4244}}"""
+        val event = assertInstanceOf(ThreadEvent::class.java, DapMessage.parse(json))
+        assertEquals(ThreadEvent(seq = 6, reason = "exited", threadId = 4244), event)
+        assertTrue(handler.onBackendMessage(event).isEmpty())
+    }
+}
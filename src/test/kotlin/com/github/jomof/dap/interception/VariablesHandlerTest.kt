@@ -0,0 +1,66 @@
+package com.github.jomof.dap.interception
+
+import com.github.jomof.dap.DapSession.RequestAction
+import com.github.jomof.dap.debugsession.DebugSession
+import com.github.jomof.dap.debugsession.RegisterNode
+import com.github.jomof.dap.debugsession.VariableChunk
+import com.github.jomof.dap.messages.ContinuedEvent
+import com.github.jomof.dap.messages.DapResponse
+import com.github.jomof.dap.messages.EvaluateRequest
+import com.github.jomof.dap.messages.SetExpressionRequest
+import com.github.jomof.dap.messages.SetVariableRequest
+import com.github.jomof.dap.messages.VariablesRequest
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [VariablesHandler]. Verifies that `variables`,
+ * `setVariable`, and `setExpression` are handled by KDAP, that
+ * `supportsSetExpression` is advertised, and that KDAP's references are
+ * forgotten when the process resumes.
+ */
+class VariablesHandlerTest {
+
+    private val session = DebugSession()
+    private val handler = VariablesHandler(session)
+
+    @Test
+    fun `variables requests and writes are handled async`() {
+        for (request in listOf(VariablesRequest(seq = 1), SetVariableRequest(seq = 2), SetExpressionRequest(seq = 3))) {
+            assertInstanceOf(RequestAction.HandleAsync::class.java, handler.onRequest(request))
+        }
+    }
+
+    @Test
+    fun `other requests are forwarded`() {
+        val request = EvaluateRequest(seq = 4, expression = "x", context = "watch")
+        assertInstanceOf(RequestAction.Forward::class.java, handler.onRequest(request))
+    }
+
+    @Test
+    fun `initialize response advertises setExpression`() {
+        val response = DapResponse(seq = 1, requestSeq = 1, command = "initialize", success = true,
+            body = mapOf("supportsSetVariable" to true))
+        val result = assertInstanceOf(DapResponse::class.java, handler.onBackendMessage(response).single())
+        assertEquals(true, result.body["supportsSetExpression"])
+        assertEquals(true, result.body["supportsSetVariable"])
+    }
+
+    @Test
+    fun `references are forgotten on resume`() {
+        val resumes = listOf(
+            ContinuedEvent(seq = 1, threadId = 1),
+            DapResponse(seq = 2, requestSeq = 2, command = "next", success = true),
+        )
+        for (resume in resumes) {
+            session.variableChunks.handleFor(VariableChunk(reference = 5, start = 0, count = 100))
+            session.registerNodes.handleFor(RegisterNode(7))
+            assertEquals(listOf(resume), handler.onBackendMessage(resume))
+            assertTrue(session.variableChunks.isEmpty())
+            assertTrue(session.registerNodes.isEmpty())
+        }
+        session.registerNodes.handleFor(RegisterNode(7))
+        handler.onBackendMessage(DapResponse(seq = 3, requestSeq = 3, command = "next", success = false))
+        assertEquals(1, session.registerNodes.size)
+    }
+}
@@ -0,0 +1,78 @@
+package com.github.jomof.dap.messages
+
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [ConfigurationSchema]. Verifies that the generated
+ * `configurationAttributes` describe the launch and attach properties,
+ * that its enums are the values the argument parsers accept, and that
+ * [ConfigurationSchema.validate] names each ill-typed property.
+ */
+class ConfigurationSchemaTest {
+
+    private fun enumValues(properties: List<ConfigurationProperty>, name: String): List<*> =
+        (properties.single { it.name == name }.type as SchemaType.OneOf).values
+
+    @Test
+    fun `configuration attributes hold a JSON Schema per request`() {
+        val attributes = ConfigurationSchema.configurationAttributes()
+        val launch = attributes.getJSONObject("launch")
+        assertEquals("object", launch.getString("type"))
+        val program = launch.getJSONObject("properties").getJSONObject("program")
+        assertEquals("string", program.getString("type"))
+        assertTrue(program.getString("description").isNotEmpty())
+        val settings = launch.getJSONObject("properties").getJSONObject("_adapterSettings").getJSONObject("properties")
+        assertEquals(listOf("stop", "frame"), settings.getJSONObject("watchRefresh").getJSONArray("enum").toList())
+
+        val attach = attributes.getJSONObject("attach").getJSONObject("properties")
+        assertEquals(listOf("package"), attach.getJSONObject("android").getJSONArray("required").toList())
+        assertFalse(attach.has("coreDump"))
+    }
+
+    @Test
+    fun `enum values are the ones the parsers accept`() {
+        val common = ConfigurationSchema.common
+        val settings = ConfigurationSchema.adapterSettings
+        assertTrue(enumValues(common, "breakpointMode").all { BreakpointMode.fromJson(it as String) != null })
+        assertTrue(enumValues(common, "expressions").all { Expressions.fromJson(it as String) != null })
+        assertTrue(enumValues(settings, "displayFormat").all { DisplayFormat.fromJson(it as String) != null })
+        assertTrue(enumValues(settings, "consoleMode").all { ConsoleMode.fromJson(it as String) != null })
+        assertTrue(enumValues(settings, "showDisassembly").all { ShowDisassembly.fromJson(it as String) != null })
+        assertTrue(enumValues(settings, "staticsScope").all { StaticsScope.fromJson(it as String) != null })
+        assertTrue(enumValues(settings, "internalFrames").all { InternalFrames.fromJson(it as String) != null })
+        assertTrue(enumValues(ConfigurationSchema.launchOnly, "console").all { ConsoleKind.fromJson(it as String) != null })
+    }
+
+    @Test
+    fun `a well-formed configuration has no problems`() {
+        val arguments = JSONObject(
+            """{"program":"/app","args":["-v"],"env":{"RUST_LOG":"debug"},"stopOnEntry":"main",
+               |"stdio":[null,"/tmp/out"],"terminal":"/dev/pts/3","signals":{"SIGPIPE":"ignore"},
+               |"_adapterSettings":{"evaluationTimeout":2.5,"watchRefresh":"frame"},"customKey":1}""".trimMargin())
+        assertEquals(emptyList<String>(), ConfigurationSchema.validate("launch", arguments))
+        assertEquals(emptyList<String>(), ConfigurationSchema.validate("attach", JSONObject("""{"pid":"app*","android":{"package":"com.app"}}""")))
+    }
+
+    @Test
+    fun `ill-typed properties are named by path`() {
+        val launch = JSONObject(
+            """{"program":7,"args":["-v",3],"stopOnEntry":"never",
+               |"_adapterSettings":{"watchRefresh":"always","clipboardMaxElements":1.5}}""".trimMargin())
+        assertEquals(
+            listOf(
+                "`program` must be a string",
+                "`args[1]` must be a string",
+                "`stopOnEntry` must be a boolean or one of \"entry\", \"main\"",
+                "`_adapterSettings.watchRefresh` must be one of \"stop\", \"frame\"",
+                "`_adapterSettings.clipboardMaxElements` must be an integer",
+            ),
+            ConfigurationSchema.validate("launch", launch),
+        )
+        assertEquals(
+            listOf("`android.package` is required", "`android.port` must be an integer"),
+            ConfigurationSchema.validate("attach", JSONObject("""{"android":{"port":"5039"}}""")),
+        )
+    }
+}
@@ -0,0 +1,120 @@
+package com.github.jomof.dap.messages
+
+import org.json.JSONObject
+import org.junit.jupiter.api.Assertions.*
+import org.junit.jupiter.api.Test
+
+/**
+ * Unit tests for [LaunchRequestArguments]. Verifies that KDAP's launch
+ * options parse and round-trip through [LaunchRequestArguments.toJsonObject],
+ * and that its `_adapterSettings` parse.
+ */
+class LaunchRequestArgumentsTest {
+
+    @Test
+    fun `coreDump parses and round-trips`() {
+        val args = LaunchRequestArguments.fromJson(JSONObject("""{"coreDump":"/tmp/core.123"}"""))
+        assertEquals("/tmp/core.123", args.coreDump)
+        assertEquals("/tmp/core.123", args.toJsonObject().getString("coreDump"))
+    }
+
+    @Test
+    fun `symbol search options parse under either name`() {
+        val camel = LaunchRequestArguments.fromJson(JSONObject(
+            """{"sysroot":"/opt/sysroot","solibSearchPath":["/opt/lib"]}"""))
+        assertEquals("/opt/sysroot", camel.common.sysroot)
+        assertEquals(listOf("/opt/lib"), camel.common.solibSearchPath)
+
+        val gdb = LaunchRequestArguments.fromJson(JSONObject("""{"solib-search-path":["/a","/b"]}"""))
+        assertEquals(listOf("/a", "/b"), gdb.common.solibSearchPath)
+        assertEquals(listOf("/a", "/b"), gdb.toJsonObject().getJSONArray("solibSearchPath").toList())
+    }
+
+    @Test
+    fun `record parses and round-trips`() {
+        val args = LaunchRequestArguments.fromJson(JSONObject("""{"program":"/bin/ls","record":true}"""))
+        assertEquals(true, args.record)
+        assertTrue(args.toJsonObject().getBoolean("record"))
+    }
+
+    @Test
+    fun `separate debug info options parse`() {
+        val args = LaunchRequestArguments.fromJson(JSONObject(
+            """{"debugFileDirectories":["/opt/debug"],"debuginfodUrls":["https://debuginfod.example"],"symbolIndexCache":false}"""))
+        assertEquals(listOf("/opt/debug"), args.common.debugFileDirectories)
+        assertEquals(listOf("https://debuginfod.example"), args.common.debuginfodUrls)
+        assertEquals(listOf("https://debuginfod.example"), args.toJsonObject().getJSONArray("debuginfodUrls").toList())
+        assertEquals(false, args.common.symbolIndexCache)
+        assertFalse(args.toJsonObject().getBoolean("symbolIndexCache"))
+    }
+
+    @Test
+    fun `watch and clipboard adapter settings parse`() {
+        val args = LaunchRequestArguments.fromJson(JSONObject(
+            """{"_adapterSettings":{"watchRefresh":"frame","clipboardMaxElements":500,"internalFrames":"collapse"}}"""))
+        assertEquals(WatchRefresh.Frame, args.common.adapterSettings?.watchRefresh)
+        assertEquals(500, args.common.adapterSettings?.clipboardMaxElements)
+        assertEquals(InternalFrames.Collapse, args.common.adapterSettings?.internalFrames)
+    }
+
+    @Test
+    fun `statics scope adapter setting parses`() {
+        val args = LaunchRequestArguments.fromJson(JSONObject("""{"_adapterSettings":{"staticsScope":"all"}}"""))
+        assertEquals(StaticsScope.All, args.common.adapterSettings?.staticsScope)
+        assertNull(LaunchRequestArguments.fromJson(JSONObject("{}")).common.adapterSettings?.staticsScope)
+    }
+
+    @Test
+    fun `requireDebugInfo parses and round-trips`() {
+        val args = LaunchRequestArguments.fromJson(JSONObject("""{"program":"/app","requireDebugInfo":false}"""))
+        assertEquals(false, args.requireDebugInfo)
+        assertEquals(false, args.toJsonObject().getBoolean("requireDebugInfo"))
+        assertNull(LaunchRequestArguments.fromJson(JSONObject("{}")).requireDebugInfo)
+    }
+
+    @Test
+    fun `signals parse and round-trip`() {
+        val args = LaunchRequestArguments.fromJson(
+            JSONObject("""{"program":"/app","signals":{"SIGUSR1":"pass","SIGPIPE":"ignore"}}"""))
+        assertEquals(mapOf("SIGUSR1" to SignalDisposition.Pass, "SIGPIPE" to SignalDisposition.Ignore), args.common.signals)
+        assertEquals("ignore", args.toJsonObject().getJSONObject("signals").getString("SIGPIPE"))
+    }
+
+    @Test
+    fun `just my code options parse`() {
+        val args = LaunchRequestArguments.fromJson(JSONObject(
+            """{"justMyCode":true,"externalCodePaths":["/work/vendor"]}"""))
+        assertEquals(true, args.common.justMyCode)
+        assertEquals(listOf("/work/vendor"), args.common.externalCodePaths)
+        assertEquals(listOf("/work/vendor"), args.toJsonObject().getJSONArray("externalCodePaths").toList())
+    }
+
+    @Test
+    fun `trackAllocations parses`() {
+        val args = LaunchRequestArguments.fromJson(JSONObject("""{"trackAllocations":true}"""))
+        assertEquals(true, args.common.trackAllocations)
+        assertTrue(args.toJsonObject().getBoolean("trackAllocations"))
+    }
+
+    @Test
+    fun `initScript parses`() {
+        val args = LaunchRequestArguments.fromJson(JSONObject("""{"initScript":"/work/debug.lua"}"""))
+        assertEquals("/work/debug.lua", args.common.initScript)
+        assertEquals("/work/debug.lua", args.toJsonObject().getString("initScript"))
+    }
+
+    @Test
+    fun `visualizers parse`() {
+        val args = LaunchRequestArguments.fromJson(JSONObject("""{"visualizers":["/work/app.natvis","/work/natvis"]}"""))
+        assertEquals(listOf("/work/app.natvis", "/work/natvis"), args.common.visualizers)
+        assertEquals(listOf("/work/app.natvis", "/work/natvis"), args.toJsonObject().getJSONArray("visualizers").toList())
+    }
+
+    @Test
+    fun `changed values depth adapter setting parses`() {
+        val args = LaunchRequestArguments.fromJson(JSONObject("""{"_adapterSettings":{"changedValuesDepth":2}}"""))
+        assertEquals(2, args.common.adapterSettings?.changedValuesDepth)
+        val unset = LaunchRequestArguments.fromJson(JSONObject("""{"_adapterSettings":{}}"""))
+        assertEquals(null, unset.common.adapterSettings?.changedValuesDepth)
+    }
+}